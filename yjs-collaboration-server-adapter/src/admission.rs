@@ -0,0 +1,360 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+use yjs_collaboration_server_domain::services::compute_pool::ComputeLoad;
+
+/// Load thresholds above which new connections are rejected.
+///
+/// A value of `0` disables the corresponding check, so the default thresholds
+/// admit every connection.
+#[derive(Clone, Debug, Default)]
+pub struct AdmissionThresholds {
+    /// Maximum number of concurrently admitted connections across all transports
+    pub max_connections: usize,
+    /// Maximum number of documents loaded in the repository
+    pub max_loaded_documents: usize,
+    /// Maximum number of messages waiting in outbound session queues
+    pub max_queue_depth: usize,
+    /// Maximum 1-minute load average per CPU core (e.g. `0.9`)
+    pub max_cpu_load: f64,
+    /// Retry hint returned to rejected clients, in seconds
+    pub retry_after_secs: u64,
+}
+
+/// Load signals sampled by a transport adapter at connection time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadSignals {
+    /// Number of documents currently loaded in the repository
+    pub loaded_documents: usize,
+    /// Number of messages currently queued for delivery to clients
+    pub queue_depth: usize,
+}
+
+/// The resource that was over its threshold when a connection was rejected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverloadReason {
+    /// Too many connections are already admitted
+    Connections(usize),
+    /// Too many documents are loaded
+    LoadedDocuments(usize),
+    /// Outbound queues are too deep
+    QueueDepth(usize),
+    /// Per-core CPU load is too high
+    CpuLoad(f64),
+}
+
+impl fmt::Display for OverloadReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connections(n) => write!(f, "too many active connections ({})", n),
+            Self::LoadedDocuments(n) => write!(f, "too many loaded documents ({})", n),
+            Self::QueueDepth(n) => write!(f, "outbound queues too deep ({} messages)", n),
+            Self::CpuLoad(load) => write!(f, "CPU load too high ({:.2} per core)", load),
+        }
+    }
+}
+
+/// A rejected admission attempt, carrying the reason and a retry hint.
+#[derive(Clone, Debug)]
+pub struct AdmissionRejection {
+    /// Why the connection was rejected
+    pub reason: OverloadReason,
+    /// How long the client should wait before reconnecting
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for AdmissionRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Server overloaded: {}, retry after {}s",
+            self.reason,
+            self.retry_after.as_secs()
+        )
+    }
+}
+
+/// Usage of a resource relative to its limit.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ResourceUsage {
+    /// Current usage
+    pub current: f64,
+    /// Usage at which the resource is saturated, `0` when unlimited
+    pub limit: f64,
+    /// Current usage divided by the limit, absent when unlimited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utilization: Option<f64>,
+}
+
+impl ResourceUsage {
+    /// Measures the usage of a resource.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - Current usage
+    /// * `limit` - Usage at which the resource is saturated, `0` when unlimited
+    ///
+    /// # Returns
+    ///
+    /// The `ResourceUsage`, with a utilization if the resource is limited
+    pub fn new(current: f64, limit: f64) -> Self {
+        Self {
+            current,
+            limit,
+            utilization: (limit > 0.0).then(|| current / limit),
+        }
+    }
+}
+
+/// Collaboration load of the server, normalized for autoscalers.
+///
+/// Each load signal is measured against the threshold the admission controller
+/// rejects connections at, and the compute pool against its concurrency, so the
+/// overall utilization reaches `1.0` when the server starts shedding load or
+/// queuing CRDT operations, whichever comes first. Signals without a threshold
+/// are reported but do not count towards the utilization.
+#[derive(Clone, Debug, Serialize)]
+pub struct CapacityReport {
+    /// Highest utilization among the signals, `0.0` when none is limited
+    pub utilization: f64,
+    /// Name of the signal with the highest utilization, if any is limited
+    pub bottleneck: Option<&'static str>,
+    /// Admitted connections against the maximum number of connections
+    pub connections: ResourceUsage,
+    /// Loaded documents against the maximum number of loaded documents
+    pub loaded_documents: ResourceUsage,
+    /// Messages waiting in connection queues against the maximum queue depth
+    pub queue_depth: ResourceUsage,
+    /// Running CRDT operations against the compute pool's concurrency
+    pub compute: ResourceUsage,
+    /// Per-core CPU load against its threshold, or against one runnable task per
+    /// core when unlimited; absent on platforms without `/proc/loadavg`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_load: Option<ResourceUsage>,
+    /// Share of the CRDT operations that exceeded their time budget since the server started
+    pub compute_over_budget_ratio: f64,
+}
+
+/// Connection admission controller shared by the WebSocket and gRPC adapters.
+///
+/// New connections are checked against the configured thresholds before any
+/// per-connection state is created. Rejected clients receive a retry-after hint
+/// while already admitted sessions keep running unaffected, so overload is shed
+/// at the edge instead of degrading every session equally.
+///
+/// The thresholds may be changed at runtime; connections admitted under the
+/// previous thresholds keep their permits.
+#[derive(Debug)]
+pub struct AdmissionController {
+    thresholds: RwLock<AdmissionThresholds>,
+    active_connections: AtomicUsize,
+}
+
+impl AdmissionController {
+    /// Creates a new admission controller with the provided thresholds.
+    ///
+    /// # Arguments
+    ///
+    /// * `thresholds` - Load thresholds above which connections are rejected
+    ///
+    /// # Returns
+    ///
+    /// A new `AdmissionController` instance.
+    pub fn new(thresholds: AdmissionThresholds) -> Self {
+        Self {
+            thresholds: RwLock::new(thresholds),
+            active_connections: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the enforced thresholds.
+    pub fn thresholds(&self) -> AdmissionThresholds {
+        self.thresholds
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Changes the enforced thresholds, e.g. after the configuration was reloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `thresholds` - Load thresholds above which connections are rejected from now on
+    pub fn set_thresholds(&self, thresholds: AdmissionThresholds) {
+        *self
+            .thresholds
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = thresholds;
+    }
+
+    /// Attempts to admit a new connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `signals` - Current load signals sampled by the calling adapter
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ConnectionPermit)` - The connection is admitted until the permit is dropped
+    /// * `Err(AdmissionRejection)` - A threshold is exceeded and the connection must be refused
+    pub fn try_admit(
+        self: &Arc<Self>,
+        signals: LoadSignals,
+    ) -> Result<ConnectionPermit, AdmissionRejection> {
+        let t = &self.thresholds();
+
+        if t.max_loaded_documents > 0 && signals.loaded_documents >= t.max_loaded_documents {
+            return Err(Self::reject(
+                t,
+                OverloadReason::LoadedDocuments(signals.loaded_documents),
+            ));
+        }
+
+        if t.max_queue_depth > 0 && signals.queue_depth >= t.max_queue_depth {
+            return Err(Self::reject(
+                t,
+                OverloadReason::QueueDepth(signals.queue_depth),
+            ));
+        }
+
+        if t.max_cpu_load > 0.0 {
+            if let Some(load) = cpu_load_per_core() {
+                if load >= t.max_cpu_load {
+                    return Err(Self::reject(t, OverloadReason::CpuLoad(load)));
+                }
+            }
+        }
+
+        // Reserve the slot atomically so concurrent upgrades cannot overshoot the limit
+        let reserved =
+            self.active_connections
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                    if t.max_connections > 0 && current >= t.max_connections {
+                        None
+                    } else {
+                        Some(current + 1)
+                    }
+                });
+
+        match reserved {
+            Ok(_) => Ok(ConnectionPermit {
+                controller: Arc::clone(self),
+            }),
+            Err(current) => Err(Self::reject(t, OverloadReason::Connections(current))),
+        }
+    }
+
+    /// Returns the number of currently admitted connections.
+    pub fn active_connections(&self) -> usize {
+        self.active_connections.load(Ordering::Acquire)
+    }
+
+    /// Measures the server's load against the admission thresholds.
+    ///
+    /// # Arguments
+    ///
+    /// * `signals` - Current load signals sampled by the caller
+    /// * `compute` - Current occupancy of the compute pool
+    ///
+    /// # Returns
+    ///
+    /// The `CapacityReport` of the server
+    pub fn capacity(&self, signals: LoadSignals, compute: ComputeLoad) -> CapacityReport {
+        let t = &self.thresholds();
+        let cpu_limit = if t.max_cpu_load > 0.0 {
+            t.max_cpu_load
+        } else {
+            1.0
+        };
+
+        let connections =
+            ResourceUsage::new(self.active_connections() as f64, t.max_connections as f64);
+        let loaded_documents = ResourceUsage::new(
+            signals.loaded_documents as f64,
+            t.max_loaded_documents as f64,
+        );
+        let queue_depth = ResourceUsage::new(signals.queue_depth as f64, t.max_queue_depth as f64);
+        let compute_usage = ResourceUsage::new(compute.running as f64, compute.concurrency as f64);
+        let cpu_load = cpu_load_per_core().map(|load| ResourceUsage::new(load, cpu_limit));
+
+        let (utilization, bottleneck) = [
+            ("connections", Some(connections)),
+            ("loaded_documents", Some(loaded_documents)),
+            ("queue_depth", Some(queue_depth)),
+            ("compute", Some(compute_usage)),
+            ("cpu_load", cpu_load),
+        ]
+        .into_iter()
+        .filter_map(|(name, usage)| Some((usage?.utilization?, name)))
+        .fold((0.0, None), |(max, bottleneck), (utilization, name)| {
+            if bottleneck.is_none() || utilization > max {
+                (utilization, Some(name))
+            } else {
+                (max, bottleneck)
+            }
+        });
+
+        CapacityReport {
+            utilization,
+            bottleneck,
+            connections,
+            loaded_documents,
+            queue_depth,
+            compute: compute_usage,
+            cpu_load,
+            compute_over_budget_ratio: if compute.calls > 0 {
+                compute.over_budget as f64 / compute.calls as f64
+            } else {
+                0.0
+            },
+        }
+    }
+
+    fn reject(thresholds: &AdmissionThresholds, reason: OverloadReason) -> AdmissionRejection {
+        AdmissionRejection {
+            reason,
+            retry_after: Duration::from_secs(thresholds.retry_after_secs),
+        }
+    }
+}
+
+impl Default for AdmissionController {
+    fn default() -> Self {
+        Self::new(AdmissionThresholds::default())
+    }
+}
+
+/// Proof of admission held for the lifetime of a connection.
+///
+/// Dropping the permit releases the connection slot.
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    controller: Arc<AdmissionController>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.controller
+            .active_connections
+            .fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Reads the 1-minute load average normalized by the number of CPU cores.
+///
+/// Returns `None` on platforms without `/proc/loadavg`.
+fn cpu_load_per_core() -> Option<f64> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let one_minute: f64 = loadavg.split_whitespace().next()?.parse().ok()?;
+    let cores = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    Some(one_minute / cores as f64)
+}
@@ -0,0 +1,450 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tokio::{
+    sync::{
+        broadcast,
+        broadcast::error::RecvError,
+        mpsc::{self, error::TrySendError},
+        OwnedSemaphorePermit,
+    },
+    task::JoinHandle,
+};
+use yjs_collaboration_server_domain::{
+    services::document_service::UpdateNotification,
+    value_objects::{update_encoding::UpdateEncoding, update_origin::OriginKind},
+};
+
+use crate::{
+    delivery_stats::{DeliveryStats, DropReason},
+    outbox::SessionOutbox,
+    payload_compression::PayloadCompression,
+    transport_compression::TransportEncoding,
+};
+
+/// Capacity of the channel between the forwarding tasks and the connection.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Whether a connection receives the updates it sent itself.
+///
+/// Negotiated per connection: most clients already applied their own updates
+/// locally and skip the echo, while some integrations want it back as a
+/// confirmation that the server applied them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EchoPolicy {
+    /// Updates are never delivered back to the connection that sent them
+    #[default]
+    Exclude,
+    /// Updates are also delivered back to the connection that sent them
+    Include,
+}
+
+impl EchoPolicy {
+    /// Returns the policy matching a negotiated echo flag.
+    pub fn from_flag(echo: bool) -> Self {
+        if echo {
+            Self::Include
+        } else {
+            Self::Exclude
+        }
+    }
+
+    /// Returns whether an update must be delivered to a connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Identifier of the client that sent the update
+    /// * `client_id` - Identifier of the receiving connection
+    ///
+    /// # Returns
+    ///
+    /// `false` if the update is the connection's own and echoes are excluded
+    pub fn delivers(self, source: &str, client_id: &str) -> bool {
+        self == Self::Include || source != client_id
+    }
+}
+
+/// An event relayed from a document's broadcast channel to a connection.
+#[derive(Debug)]
+pub enum HubEvent {
+    /// An update applied by another client, or by the connection itself when echoes are included
+    Update {
+        doc_id: String,
+        update: Vec<u8>,
+        /// Identifier of the client that sent the update
+        source: String,
+        /// Identity of the user behind the client, if known
+        user_id: Option<String>,
+        /// Where the update comes from
+        origin: OriginKind,
+        /// Position of the update among the document's broadcasts, `0` for the
+        /// chunks of an oversized diff, which are not part of the sequence
+        sequence_number: u64,
+    },
+    /// The connection fell behind and missed updates, either because its subscription lagged
+    /// or because its queue was full; the document must be resent in full
+    Lagged { doc_id: String, skipped: u64 },
+}
+
+/// Per-connection hub relaying document broadcasts to a WebSocket or gRPC connection.
+///
+/// A connection may collaborate on several documents at once. For every
+/// document it subscribes to, the hub spawns a forwarding task that drains the
+/// document's broadcast receiver into a single channel, so the connection can
+/// `select!` between incoming socket frames and remote updates and deliver them
+/// in real time. Updates originating from the connection itself are skipped
+/// unless its echo policy includes them.
+///
+/// Updates are dropped rather than queued once the connection's queue is full,
+/// so a slow connection cannot hold back its document's broadcast channel; it is
+/// told to resynchronize instead, once it has made room for a `Lagged` event.
+///
+/// Dropping the hub stops every forwarding task.
+pub struct BroadcastHub {
+    client_id: String,
+    echo: EchoPolicy,
+    sender: mpsc::Sender<HubEvent>,
+    receiver: mpsc::Receiver<HubEvent>,
+    subscriptions: HashMap<String, JoinHandle<()>>,
+    outbox: Option<Arc<SessionOutbox>>,
+    stats: Option<Arc<DeliveryStats>>,
+    compression: PayloadCompression,
+    encoding: TransportEncoding,
+    update_encoding: UpdateEncoding,
+}
+
+impl BroadcastHub {
+    /// Creates an empty hub for a connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the connection, used to recognize its own updates
+    ///
+    /// # Returns
+    ///
+    /// A new `BroadcastHub` instance excluding the connection's own updates.
+    pub fn new(client_id: &str) -> Self {
+        let (sender, receiver) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+
+        Self {
+            client_id: client_id.to_string(),
+            echo: EchoPolicy::default(),
+            sender,
+            receiver,
+            subscriptions: HashMap::new(),
+            outbox: None,
+            stats: None,
+            compression: PayloadCompression::default(),
+            encoding: TransportEncoding::default(),
+            update_encoding: UpdateEncoding::default(),
+        }
+    }
+
+    /// Sets whether the connection receives its own updates.
+    ///
+    /// # Arguments
+    ///
+    /// * `echo` - The echo policy negotiated by the connection
+    ///
+    /// # Returns
+    ///
+    /// The `BroadcastHub` applying the given policy
+    pub fn with_echo_policy(mut self, echo: EchoPolicy) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    /// Records the updates relayed to the connection in a session outbox.
+    ///
+    /// Every update of a subscribed document is recorded as soon as it is
+    /// broadcast, including the connection's own, so the updates the client
+    /// misses when its connection ends can be replayed once it reconnects.
+    ///
+    /// # Arguments
+    ///
+    /// * `outbox` - The outbox shared by the connections of the transport
+    ///
+    /// # Returns
+    ///
+    /// The `BroadcastHub` recording into the given outbox
+    pub fn with_outbox(mut self, outbox: Arc<SessionOutbox>) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    /// Counts the updates that could not be relayed to the connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - The counters shared by the connections of both transports
+    ///
+    /// # Returns
+    ///
+    /// The `BroadcastHub` recording its drops into the given counters
+    pub fn with_delivery_stats(mut self, stats: Arc<DeliveryStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Changes whether the connection receives its own updates.
+    ///
+    /// Takes effect for every update not yet received, including those of
+    /// documents the connection already subscribed to.
+    pub fn set_echo_policy(&mut self, echo: EchoPolicy) {
+        self.echo = echo;
+    }
+
+    /// Changes whether the updates relayed to the connection are compressed
+    /// with their document's dictionary.
+    pub fn set_compression(&mut self, enabled: bool) {
+        self.compression.set_enabled(enabled);
+    }
+
+    /// Returns the dictionary compression of the updates relayed to the connection.
+    pub fn compression_mut(&mut self) -> &mut PayloadCompression {
+        &mut self.compression
+    }
+
+    /// Changes the generic compression the connection decodes, applied to large payloads.
+    pub fn set_transport_encoding(&mut self, encoding: TransportEncoding) {
+        self.encoding = encoding;
+    }
+
+    /// Returns the generic compression the connection decodes.
+    pub fn transport_encoding(&self) -> TransportEncoding {
+        self.encoding
+    }
+
+    /// Changes the version of the Yjs encoding of the updates exchanged with the connection.
+    pub fn set_update_encoding(&mut self, encoding: UpdateEncoding) {
+        self.update_encoding = encoding;
+    }
+
+    /// Returns the version of the Yjs encoding of the updates exchanged with the connection.
+    pub fn update_encoding(&self) -> UpdateEncoding {
+        self.update_encoding
+    }
+
+    /// Subscribes the connection to a document's updates.
+    ///
+    /// Subscribing to a document twice keeps the existing subscription, so
+    /// repeated sync requests do not duplicate deliveries.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `updates` - The document's broadcast receiver
+    pub fn subscribe(&mut self, doc_id: &str, updates: broadcast::Receiver<UpdateNotification>) {
+        if self
+            .subscriptions
+            .get(doc_id)
+            .is_some_and(|task| !task.is_finished())
+        {
+            return;
+        }
+
+        let task = tokio::spawn(Self::forward(
+            doc_id.to_string(),
+            self.client_id.clone(),
+            updates,
+            self.sender.clone(),
+            self.outbox.clone(),
+            self.stats.clone(),
+        ));
+        self.subscriptions.insert(doc_id.to_string(), task);
+    }
+
+    /// Stops relaying a document's updates to the connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    pub fn unsubscribe(&mut self, doc_id: &str) {
+        if let Some(task) = self.subscriptions.remove(doc_id) {
+            task.abort();
+        }
+    }
+
+    /// Delivers the remaining chunks of an oversized diff to the connection.
+    ///
+    /// The chunks are queued behind the events already pending and interleave
+    /// with live updates, so a huge diff does not stall real-time delivery. A
+    /// chunk is only queued once the connection has consumed enough events to
+    /// make room for it, which paces the delivery to the connection's speed.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `chunks` - The chunks to deliver, in order
+    /// * `permit` - The session's diff permit, released once every chunk is queued
+    pub fn deliver(&self, doc_id: &str, chunks: Vec<Vec<u8>>, permit: OwnedSemaphorePermit) {
+        let doc_id = doc_id.to_string();
+        let sender = self.sender.clone();
+        let stats = self.stats.clone();
+
+        tokio::spawn(async move {
+            let _permit = permit;
+            for update in chunks {
+                let event = HubEvent::Update {
+                    doc_id: doc_id.clone(),
+                    update,
+                    source: String::new(),
+                    user_id: None,
+                    origin: OriginKind::Server,
+                    sequence_number: 0,
+                };
+                // The connection is gone
+                if sender.send(event).await.is_err() {
+                    break;
+                }
+                if let Some(stats) = &stats {
+                    stats.record_queued(1);
+                }
+            }
+        });
+    }
+
+    /// Waits for the next event from any subscribed document.
+    ///
+    /// The connection's own updates are filtered here, by its echo policy.
+    ///
+    /// # Returns
+    ///
+    /// The next `HubEvent`; pending forever while there are no subscriptions
+    pub async fn recv(&mut self) -> HubEvent {
+        loop {
+            let event = self.receiver.recv().await;
+            if let (Some(_), Some(stats)) = (&event, &self.stats) {
+                stats.record_dequeued(1);
+            }
+            match event {
+                Some(HubEvent::Update { source, .. })
+                    if !self.echo.delivers(&source, &self.client_id) => {}
+                Some(event) => return event,
+                // The hub keeps a sender alive, so the channel never closes
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    /// Returns the identifier of the connection.
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Returns whether an update sent by a client is delivered to the connection,
+    /// following its echo policy.
+    pub fn delivers(&self, source: &str) -> bool {
+        self.echo.delivers(source, &self.client_id)
+    }
+
+    /// Returns whether an update was sent by the connection itself.
+    pub fn is_own(&self, source: &str) -> bool {
+        source == self.client_id
+    }
+
+    /// Returns whether the connection is subscribed to a document.
+    pub fn is_subscribed(&self, doc_id: &str) -> bool {
+        self.subscriptions.contains_key(doc_id)
+    }
+
+    /// Returns the documents the connection is subscribed to.
+    pub fn subscribed_documents(&self) -> Vec<String> {
+        self.subscriptions.keys().cloned().collect()
+    }
+
+    /// Returns the number of documents the connection is subscribed to.
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    /// Drains a document's broadcast receiver into the connection's channel.
+    async fn forward(
+        doc_id: String,
+        client_id: String,
+        mut updates: broadcast::Receiver<UpdateNotification>,
+        sender: mpsc::Sender<HubEvent>,
+        outbox: Option<Arc<SessionOutbox>>,
+        stats: Option<Arc<DeliveryStats>>,
+    ) {
+        let dropped = |reason: DropReason, count: u64| {
+            if let Some(stats) = &stats {
+                stats.record(&doc_id, &client_id, reason, count);
+            }
+        };
+        let queued = || {
+            if let Some(stats) = &stats {
+                stats.record_queued(1);
+            }
+        };
+
+        loop {
+            let event = match updates.recv().await {
+                Ok(notification) => {
+                    if let Some(outbox) = &outbox {
+                        outbox.record(&doc_id, &client_id, &notification);
+                    }
+                    HubEvent::Update {
+                        doc_id: doc_id.clone(),
+                        update: notification.update,
+                        source: notification.source,
+                        user_id: notification.user_id,
+                        origin: notification.origin,
+                        sequence_number: notification.sequence_number,
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    dropped(DropReason::LaggedReceiver, skipped);
+                    HubEvent::Lagged {
+                        doc_id: doc_id.clone(),
+                        skipped,
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            let event = match sender.try_send(event) {
+                Ok(()) => {
+                    queued();
+                    continue;
+                }
+                // The update is dropped; the resync waits for room in the queue
+                Err(TrySendError::Full(HubEvent::Update { .. })) => {
+                    dropped(DropReason::FullQueue, 1);
+                    HubEvent::Lagged {
+                        doc_id: doc_id.clone(),
+                        skipped: 1,
+                    }
+                }
+                Err(TrySendError::Full(event)) => event,
+                Err(TrySendError::Closed(_)) => {
+                    dropped(DropReason::ClosedChannel, 1);
+                    break;
+                }
+            };
+
+            if sender.send(event).await.is_err() {
+                dropped(DropReason::ClosedChannel, 1);
+                break;
+            }
+            queued();
+        }
+    }
+}
+
+impl Drop for BroadcastHub {
+    fn drop(&mut self) {
+        for task in self.subscriptions.values() {
+            task.abort();
+        }
+
+        // Events left in the queue are never delivered
+        self.receiver.close();
+        let mut discarded = 0;
+        while self.receiver.try_recv().is_ok() {
+            discarded += 1;
+        }
+        if let Some(stats) = &self.stats {
+            stats.record_dequeued(discarded);
+        }
+    }
+}
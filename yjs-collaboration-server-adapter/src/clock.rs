@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use chrono::Utc;
+
+/// Client timestamps above this value are interpreted as milliseconds.
+///
+/// Unix seconds stay below it until the year 5138, while millisecond timestamps
+/// have exceeded it since 1973.
+const MILLIS_THRESHOLD: i64 = 100_000_000_000;
+
+/// Returns the current server time in Unix seconds.
+///
+/// Client clocks are never trusted for relayed messages or presence tracking;
+/// every timestamp the server emits or compares comes from this function.
+pub fn server_time() -> i64 {
+    Utc::now().timestamp()
+}
+
+/// Tracks the clock skew of the client on a single connection.
+///
+/// The offset is the server time minus the client's most recently reported
+/// timestamp, so `client time + offset ≈ server time`. It is sent to the client
+/// as a hint with every server message.
+#[derive(Debug, Default)]
+pub struct ClockOffset {
+    offset: AtomicI64,
+}
+
+impl ClockOffset {
+    /// Records a timestamp reported by the client.
+    ///
+    /// Timestamps of `0` (not set) are ignored. Millisecond timestamps, as
+    /// produced by `Date.now()` in browsers, are converted to seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_timestamp` - Timestamp reported by the client
+    /// * `server_time` - Server time at which the timestamp was received
+    pub fn observe(&self, client_timestamp: i64, server_time: i64) {
+        if client_timestamp <= 0 {
+            return;
+        }
+
+        let client_seconds = if client_timestamp > MILLIS_THRESHOLD {
+            client_timestamp / 1000
+        } else {
+            client_timestamp
+        };
+
+        self.offset
+            .store(server_time - client_seconds, Ordering::Relaxed);
+    }
+
+    /// Returns the latest clock offset in seconds, or `0` if the client never sent a timestamp.
+    pub fn get(&self) -> i64 {
+        self.offset.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicIsize, Ordering},
+};
+
+use dashmap::DashMap;
+
+/// Reason a message bound for a client was not delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    /// The connection's channel was closed, as the client disconnected
+    ClosedChannel,
+    /// The connection's queue was full, as the client consumes messages too slowly
+    FullQueue,
+    /// The connection's subscription fell behind the document's broadcast channel
+    LaggedReceiver,
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ClosedChannel => "closed_channel",
+            Self::FullQueue => "full_queue",
+            Self::LaggedReceiver => "lagged_receiver",
+        })
+    }
+}
+
+/// Number of messages dropped for a reason, on a document or for one of its sessions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DropCount {
+    /// Document the messages related to
+    pub document_id: String,
+    /// Client the messages were bound for, or `None` for the document's total
+    pub client_id: Option<String>,
+    /// Why the messages were dropped
+    pub reason: DropReason,
+    /// Number of messages dropped
+    pub count: u64,
+}
+
+/// Counters of the broadcast messages that could not be delivered to clients.
+///
+/// Drops are totalled per document for as long as the server runs, and per
+/// session while the session lasts, so operators can tell a single slow client
+/// from a document whose traffic overwhelms every connection. Both transports
+/// record into the same counters, which the metrics service exports.
+///
+/// The messages waiting in the connections' queues are counted too, as a
+/// measure of how far behind the server is in delivering broadcasts.
+#[derive(Default)]
+pub struct DeliveryStats {
+    documents: DashMap<(String, DropReason), u64>,
+    sessions: DashMap<(String, String, DropReason), u64>,
+    /// Messages queued for connections; briefly negative when a message is
+    /// taken out of a queue before its producer counted it
+    queued: AtomicIsize,
+}
+
+impl DeliveryStats {
+    /// Records messages dropped for a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the messages related to
+    /// * `client_id` - Identifier of the session's client
+    /// * `reason` - Why the messages were dropped
+    /// * `count` - Number of messages dropped
+    pub fn record(&self, document_id: &str, client_id: &str, reason: DropReason, count: u64) {
+        *self
+            .documents
+            .entry((document_id.to_string(), reason))
+            .or_default() += count;
+        *self
+            .sessions
+            .entry((document_id.to_string(), client_id.to_string(), reason))
+            .or_default() += count;
+    }
+
+    /// Discards the counters of a session that left a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the session left
+    /// * `client_id` - Identifier of the session's client
+    pub fn forget(&self, document_id: &str, client_id: &str) {
+        self.sessions
+            .retain(|(document, client, _), _| document != document_id || client != client_id);
+    }
+
+    /// Discards the counters of a disconnected client on every document.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the connection
+    pub fn forget_client(&self, client_id: &str) {
+        self.sessions
+            .retain(|(_, client, _), _| client != client_id);
+    }
+
+    /// Counts messages added to a connection's queue.
+    pub fn record_queued(&self, count: usize) {
+        self.queued.fetch_add(count as isize, Ordering::Relaxed);
+    }
+
+    /// Counts messages taken out of a connection's queue, delivered or discarded.
+    pub fn record_dequeued(&self, count: usize) {
+        self.queued.fetch_sub(count as isize, Ordering::Relaxed);
+    }
+
+    /// Returns the number of messages waiting in the connections' queues.
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed).max(0) as usize
+    }
+
+    /// Returns the drop counters, the documents' totals first.
+    pub fn snapshot(&self) -> Vec<DropCount> {
+        let documents = self.documents.iter().map(|entry| {
+            let (document_id, reason) = entry.key();
+            DropCount {
+                document_id: document_id.clone(),
+                client_id: None,
+                reason: *reason,
+                count: *entry.value(),
+            }
+        });
+        let sessions = self.sessions.iter().map(|entry| {
+            let (document_id, client_id, reason) = entry.key();
+            DropCount {
+                document_id: document_id.clone(),
+                client_id: Some(client_id.clone()),
+                reason: *reason,
+                count: *entry.value(),
+            }
+        });
+        documents.chain(sessions).collect()
+    }
+}
@@ -0,0 +1,1135 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use sonic_rs::{from_str, json, Value};
+use volo_http::{
+    context::ServerContext,
+    http::{header, request::Parts, StatusCode},
+    response::Response,
+    server::{
+        extract::{FromContext, Query},
+        route::{get, post},
+        IntoResponse,
+    },
+    Router,
+};
+use yjs_collaboration_server_domain::{
+    errors::DomainError,
+    repositories::document_repository::DocumentRepository,
+    services::document_service::DocumentService,
+    value_objects::{
+        access_role::AccessGrant, document_metadata::DocumentMetadata, export_mode::ExportMode,
+        gc_options::GcOptions, message::Notice,
+    },
+};
+
+use super::api::error_status;
+use crate::{
+    admission::{AdmissionController, CapacityReport},
+    session_registry::SessionRegistry,
+};
+
+/// Bearer token sent by the client in the `Authorization` header, if any.
+pub struct BearerToken(Option<String>);
+
+impl FromContext for BearerToken {
+    type Rejection = StatusCode;
+
+    async fn from_context(
+        _cx: &mut ServerContext,
+        parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string());
+
+        Ok(Self(token))
+    }
+}
+
+impl BearerToken {
+    /// Falls back to a token passed another way when no `Authorization` header was sent.
+    fn or(self, fallback: Option<String>) -> Self {
+        match self.0 {
+            Some(_) => self,
+            None => Self(fallback.map(|token| token.trim().to_string())),
+        }
+    }
+}
+
+/// Page of the admin dashboard, embedded in the binary.
+const DASHBOARD_PAGE: &[u8] = include_bytes!("dashboard/index.html");
+
+/// Script of the admin dashboard, polling the admin routes.
+const DASHBOARD_SCRIPT: &[u8] = include_bytes!("dashboard/dashboard.js");
+
+/// Stylesheet of the admin dashboard.
+const DASHBOARD_STYLESHEET: &[u8] = include_bytes!("dashboard/dashboard.css");
+
+/// Authentication policy for the admin routes.
+///
+/// The admin token is independent of any credentials used on the public
+/// collaboration endpoints. When no token is configured, access control is left
+/// to the network (e.g. a listener bound to localhost or a unix socket).
+#[derive(Clone, Debug, Default)]
+pub struct AdminAuth {
+    token: Option<String>,
+}
+
+impl AdminAuth {
+    /// Creates an admin authentication policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Bearer token required on every admin request, or `None` to allow all
+    ///
+    /// # Returns
+    ///
+    /// A new `AdminAuth` instance.
+    pub fn new(token: Option<String>) -> Self {
+        Self {
+            token: token.filter(|token| !token.is_empty()),
+        }
+    }
+
+    /// Returns whether a token is required on admin requests.
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Checks the bearer token presented by a client.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the request is authorized, otherwise a `401 Unauthorized` response
+    fn reject(&self, presented: &BearerToken) -> Option<Response> {
+        let expected = self.token.as_ref()?;
+
+        match &presented.0 {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => None,
+            _ => Some(
+                (
+                    StatusCode::UNAUTHORIZED,
+                    ((header::WWW_AUTHENTICATE, "Bearer"), "Unauthorized\n"),
+                )
+                    .into_response(),
+            ),
+        }
+    }
+}
+
+/// Query selecting the documents carrying a tag, or the resident documents when omitted.
+#[derive(Deserialize)]
+struct TagQuery {
+    #[serde(default)]
+    tag: Option<String>,
+}
+
+/// Query selecting the sessions of a document, or of every document when omitted.
+#[derive(Deserialize)]
+struct SessionQuery {
+    #[serde(default)]
+    doc: Option<String>,
+}
+
+/// Query selecting a client to disconnect from a document.
+#[derive(Deserialize)]
+struct KickQuery {
+    doc: String,
+    client_id: String,
+}
+
+/// Query selecting a document; document IDs may contain slashes, so they are
+/// passed as a query parameter rather than a path segment.
+#[derive(Deserialize)]
+struct DocumentQuery {
+    doc: String,
+}
+
+/// Query selecting a document to close, and the grace period its clients are given.
+#[derive(Deserialize)]
+struct CloseQuery {
+    doc: String,
+    /// Seconds the clients are warned before being disconnected, `0` to close at once
+    #[serde(default)]
+    grace_secs: u64,
+    /// Message shown to the clients during the grace period
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Query selecting a document whose garbage collection is changed; options left
+/// out keep their current value.
+#[derive(Deserialize)]
+struct GcQuery {
+    doc: String,
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    on_snapshot: Option<bool>,
+    /// Collect the document's deleted content at once
+    #[serde(default)]
+    collect: bool,
+}
+
+/// Query selecting a document to export or import, and the form of the update.
+#[derive(Deserialize)]
+struct ExportQuery {
+    doc: String,
+    #[serde(default)]
+    mode: ExportMode,
+}
+
+/// Query carrying the admin token to the dashboard page, which browsers open
+/// without an `Authorization` header.
+#[derive(Deserialize)]
+struct DashboardQuery {
+    #[serde(default)]
+    token: Option<String>,
+}
+
+/// Body of the requests adding or removing document tags.
+#[derive(Deserialize)]
+struct TagsRequest {
+    tags: Vec<String>,
+}
+
+/// Body of the requests changing a user's permission.
+#[derive(Deserialize)]
+struct AccessRequest {
+    user_id: String,
+    /// Document the change applies to, or every document when omitted
+    #[serde(default)]
+    doc_id: Option<String>,
+    role: AccessGrant,
+}
+
+/// Change requested on a document's tags.
+enum TagChange {
+    Add,
+    Remove,
+}
+
+/// Source of the metrics served on the admin `/metrics` and `/admin/capacity` routes.
+pub trait MetricsExporter: Send + Sync {
+    /// Renders the current metrics in the Prometheus text exposition format.
+    ///
+    /// # Returns
+    ///
+    /// The exposition text, or `None` when metrics are pushed to another system
+    fn render(&self) -> Option<String>;
+
+    /// Measures the server's collaboration load for autoscalers.
+    ///
+    /// # Returns
+    ///
+    /// The current `CapacityReport`
+    fn capacity(&self) -> CapacityReport;
+}
+
+/// Role switch of a server following a primary as a warm standby.
+pub trait StandbyControl: Send + Sync {
+    /// Returns whether the server still follows its primary.
+    fn is_standby(&self) -> bool;
+
+    /// Promotes the server to primary, accepting edits from its clients.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the server was a standby, `false` if it had already been promoted
+    fn promote(&self) -> bool;
+}
+
+/// Scheduled closing of documents, e.g. for migrations and maintenance windows.
+pub trait MaintenanceControl: Send + Sync {
+    /// Warns the clients of a document that it is closing, then disconnects them
+    /// and unloads the document once the grace period elapses.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `grace` - Time the clients are given before being disconnected
+    /// * `message` - Message shown to the clients, or `None` for a default one
+    ///
+    /// # Returns
+    ///
+    /// The time the document closes, as Unix seconds; a document already closing
+    /// keeps its earlier time
+    fn schedule_close(&self, doc_id: &str, grace: Duration, message: Option<&str>) -> i64;
+}
+
+/// A setting changed at runtime by a configuration reload.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigChange {
+    /// Name of the setting, e.g. `rate_limit`
+    pub setting: String,
+    /// Value before the reload
+    pub from: Value,
+    /// Value applied by the reload
+    pub to: Value,
+}
+
+/// Outcome of a configuration reload.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReloadReport {
+    /// Time of the reload, as Unix seconds
+    pub reloaded_at: i64,
+    /// What triggered the reload: `signal`, `watch` or `admin`
+    pub trigger: &'static str,
+    /// Settings changed at runtime
+    pub applied: Vec<ConfigChange>,
+    /// Sections of the configuration whose changes only take effect after a restart
+    pub restart_required: Vec<String>,
+}
+
+/// Reloading of the configuration file the server was started from.
+pub trait ConfigControl: Send + Sync {
+    /// Reloads the configuration file, applying the settings that may change at runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `trigger` - What triggered the reload, reported back in the `ReloadReport`
+    ///
+    /// # Returns
+    ///
+    /// The `ReloadReport` of the reload, or an error message if the file cannot be loaded
+    fn reload(&self, trigger: &'static str) -> Result<ReloadReport, String>;
+
+    /// Returns the outcome of the last successful reload, or `None` before the first one.
+    fn last_reload(&self) -> Option<ReloadReport>;
+}
+
+/// HTTP router for the management endpoints.
+///
+/// Admin routes are served only by the dedicated admin listener, never by the
+/// public collaboration listeners, so operators can firewall management traffic
+/// by port or socket path.
+///
+/// It defines:
+/// - A status endpoint (`/admin/status`) reporting server load as JSON
+/// - A capacity endpoint (`/admin/capacity`) reporting the load signals and their normalized
+///   utilization as JSON, for autoscalers
+/// - A metrics endpoint (`/metrics`) in the Prometheus text exposition format, when the configured
+///   metrics backend is scraped rather than pushed
+/// - A documents endpoint (`/admin/documents`) listing the resident documents with their size and
+///   clients, or the documents carrying a tag
+/// - A sessions endpoint (`/admin/sessions`) listing the connected clients of every document
+/// - Kick and close endpoints (`POST /admin/documents/kick`, `POST /admin/documents/close`)
+///   disconnecting a client from a document, or every client of a document before unloading it;
+///   closes may give the clients a grace period, warning them with a `document_closing` notice
+/// - A notices endpoint (`POST /admin/notices`) publishing a notice to the connected clients
+/// - An access endpoint (`POST /admin/access`) downgrading or revoking a user's permission, applied
+///   to its connected sessions at once
+/// - Export and import endpoints (`/admin/documents/export`, `/admin/documents/import`)
+///   transferring a document as a single binary update
+/// - A garbage collection endpoint (`/admin/documents/gc`) reporting a document's garbage
+///   collection options and structure, changing the options or collecting its deleted content
+/// - A promotion endpoint (`POST /admin/standby/promote`) turning a warm standby into the primary
+/// - Configuration endpoints (`/admin/config`, `POST /admin/config/reload`) reporting the last
+///   configuration reload, or reloading the configuration file
+/// - A dashboard (`/dashboard`) embedded in the binary, showing the resident documents, the
+///   sessions and the update throughput, with buttons for the kick, close and promotion endpoints
+pub struct AdminRouter<R: DocumentRepository> {
+    state: Arc<AdminState<R>>,
+}
+
+/// Services reported on by the admin routes.
+struct AdminState<R: DocumentRepository> {
+    document_service: Arc<DocumentService<R>>,
+    admission: Arc<AdmissionController>,
+    sessions: Arc<SessionRegistry>,
+    metrics: Arc<dyn MetricsExporter>,
+    standby: Option<Arc<dyn StandbyControl>>,
+    maintenance: Option<Arc<dyn MaintenanceControl>>,
+    config: Option<Arc<dyn ConfigControl>>,
+    auth: AdminAuth,
+}
+
+impl<R: DocumentRepository + Send + Sync + 'static> AdminRouter<R> {
+    /// Creates a new admin router.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_service` - The domain document service to report on
+    /// * `admission` - Admission controller tracking active connections
+    /// * `sessions` - Registry of the connected clients, told of permission changes
+    /// * `metrics` - Exporter rendering the `/metrics` route
+    /// * `standby` - Role switch of the server, if it was started as a warm standby
+    /// * `maintenance` - Scheduler of graceful document closes, if any
+    /// * `auth` - Authentication policy applied to every admin route
+    ///
+    /// # Returns
+    ///
+    /// A new `AdminRouter` instance.
+    pub fn new(
+        document_service: Arc<DocumentService<R>>,
+        admission: Arc<AdmissionController>,
+        sessions: Arc<SessionRegistry>,
+        metrics: Arc<dyn MetricsExporter>,
+        standby: Option<Arc<dyn StandbyControl>>,
+        maintenance: Option<Arc<dyn MaintenanceControl>>,
+        auth: AdminAuth,
+    ) -> Self {
+        Self {
+            state: Arc::new(AdminState {
+                document_service,
+                admission,
+                sessions,
+                metrics,
+                standby,
+                maintenance,
+                config: None,
+                auth,
+            }),
+        }
+    }
+
+    /// Reloads the server's configuration file through the given control.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Reloader of the configuration file the server was started from
+    ///
+    /// # Returns
+    ///
+    /// The `AdminRouter` serving the configuration endpoints
+    pub fn with_config_control(mut self, config: Arc<dyn ConfigControl>) -> Self {
+        Arc::get_mut(&mut self.state)
+            .expect("the admin state is only shared once the router is built")
+            .config = Some(config);
+        self
+    }
+
+    /// Builds the admin router.
+    ///
+    /// # Returns
+    ///
+    /// A configured `Router` instance ready to be used by the admin server.
+    pub fn build_router(&self) -> Router {
+        let state = self.state.clone();
+        let status = get(move |token: BearerToken| {
+            let state = state.clone();
+            async move { state.status(&token) }
+        });
+
+        let state = self.state.clone();
+        let capacity = get(move |token: BearerToken| {
+            let state = state.clone();
+            async move { state.capacity(&token) }
+        });
+
+        let state = self.state.clone();
+        let metrics = get(move |token: BearerToken| {
+            let state = state.clone();
+            async move { state.metrics(&token) }
+        });
+
+        let state = self.state.clone();
+        let notices = post(move |token: BearerToken, body: String| {
+            let state = state.clone();
+            async move { state.publish_notice(&token, &body) }
+        });
+
+        let state = self.state.clone();
+        let access = post(move |token: BearerToken, body: String| {
+            let state = state.clone();
+            async move { state.change_access(&token, &body) }
+        });
+
+        let state = self.state.clone();
+        let tags = get(move |token: BearerToken| {
+            let state = state.clone();
+            async move { state.tag_counts(&token) }
+        });
+
+        let state = self.state.clone();
+        let documents = get(move |token: BearerToken, Query(query): Query<TagQuery>| {
+            let state = state.clone();
+            async move {
+                match query.tag {
+                    Some(tag) => state.tagged_documents(&token, &tag),
+                    None => state.resident_documents(&token).await,
+                }
+            }
+        });
+
+        let state = self.state.clone();
+        let sessions = get(
+            move |token: BearerToken, Query(query): Query<SessionQuery>| {
+                let state = state.clone();
+                async move { state.list_sessions(&token, query.doc.as_deref()) }
+            },
+        );
+
+        let state = self.state.clone();
+        let kick = post(move |token: BearerToken, Query(query): Query<KickQuery>| {
+            let state = state.clone();
+            async move { state.kick(&token, &query.doc, &query.client_id) }
+        });
+
+        let state = self.state.clone();
+        let close = post(move |token: BearerToken, Query(query): Query<CloseQuery>| {
+            let state = state.clone();
+            async move {
+                match query.grace_secs {
+                    0 => state.close_document(&token, &query.doc).await,
+                    grace_secs => state.schedule_close(&token, &query, grace_secs),
+                }
+            }
+        });
+
+        let state = self.state.clone();
+        let get_state = state.clone();
+        let post_state = state.clone();
+        let document_tags = get(
+            move |token: BearerToken, Query(query): Query<DocumentQuery>| {
+                let state = get_state.clone();
+                async move { state.document_tags(&token, &query.doc) }
+            },
+        )
+        .post(
+            move |token: BearerToken, Query(query): Query<DocumentQuery>, body: String| {
+                let state = post_state.clone();
+                async move { state.change_tags(&token, &query.doc, &body, TagChange::Add) }
+            },
+        )
+        .delete(
+            move |token: BearerToken, Query(query): Query<DocumentQuery>, body: String| {
+                let state = state.clone();
+                async move { state.change_tags(&token, &query.doc, &body, TagChange::Remove) }
+            },
+        );
+
+        let state = self.state.clone();
+        let export = get(
+            move |token: BearerToken, Query(query): Query<ExportQuery>| {
+                let state = state.clone();
+                async move { state.export_document(&token, &query.doc, query.mode).await }
+            },
+        );
+
+        let state = self.state.clone();
+        let import = post(
+            move |token: BearerToken, Query(query): Query<ExportQuery>, body: Vec<u8>| {
+                let state = state.clone();
+                async move {
+                    state
+                        .import_document(&token, &query.doc, &body, query.mode)
+                        .await
+                }
+            },
+        );
+
+        let state = self.state.clone();
+        let get_state = state.clone();
+        let gc = get(
+            move |token: BearerToken, Query(query): Query<DocumentQuery>| {
+                let state = get_state.clone();
+                async move { state.document_gc(&token, &query.doc).await }
+            },
+        )
+        .post(move |token: BearerToken, Query(query): Query<GcQuery>| {
+            let state = state.clone();
+            async move { state.change_gc(&token, &query).await }
+        });
+
+        let state = self.state.clone();
+        let promote = post(move |token: BearerToken| {
+            let state = state.clone();
+            async move { state.promote(&token) }
+        });
+
+        let state = self.state.clone();
+        let config = get(move |token: BearerToken| {
+            let state = state.clone();
+            async move { state.last_reload(&token) }
+        });
+
+        let state = self.state.clone();
+        let reload = post(move |token: BearerToken| {
+            let state = state.clone();
+            async move { state.reload_config(&token) }
+        });
+
+        let state = self.state.clone();
+        let dashboard = get(
+            move |token: BearerToken, Query(query): Query<DashboardQuery>| {
+                let state = state.clone();
+                async move { state.dashboard(token.or(query.token)) }
+            },
+        );
+
+        Router::new()
+            .route("/dashboard", dashboard)
+            .route(
+                "/dashboard/dashboard.js",
+                get(|| async { dashboard_asset("text/javascript", DASHBOARD_SCRIPT) }),
+            )
+            .route(
+                "/dashboard/dashboard.css",
+                get(|| async { dashboard_asset("text/css", DASHBOARD_STYLESHEET) }),
+            )
+            .route("/admin/status", status)
+            .route("/admin/capacity", capacity)
+            .route("/admin/notices", notices)
+            .route("/admin/access", access)
+            .route("/admin/tags", tags)
+            .route("/admin/documents", documents)
+            .route("/admin/documents/kick", kick)
+            .route("/admin/documents/close", close)
+            .route("/admin/sessions", sessions)
+            .route("/admin/documents/tags", document_tags)
+            .route("/admin/documents/export", export)
+            .route("/admin/documents/import", import)
+            .route("/admin/documents/gc", gc)
+            .route("/admin/standby/promote", promote)
+            .route("/admin/config", config)
+            .route("/admin/config/reload", reload)
+            .route("/metrics", metrics)
+    }
+}
+
+impl<R: DocumentRepository> AdminState<R> {
+    /// Reports the current server load as JSON.
+    fn status(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let body = json!({
+            "role": self.role(),
+            "loaded_documents": self.document_service.loaded_document_count(),
+            "active_connections": self.admission.active_connections(),
+        });
+
+        ((header::CONTENT_TYPE, "application/json"), body.to_string()).into_response()
+    }
+
+    /// Reports the load signals of the server and their normalized utilization as JSON.
+    fn capacity(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match sonic_rs::to_string(&self.metrics.capacity()) {
+            Ok(body) => ((header::CONTENT_TYPE, "application/json"), body).into_response(),
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to encode the capacity report: {}\n", e),
+            )
+                .into_response(),
+        }
+    }
+
+    /// Publishes a notice, given as JSON, to the connected clients.
+    fn publish_notice(&self, token: &BearerToken, body: &str) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let notice = match from_str::<Notice>(body) {
+            Ok(notice) => notice,
+            Err(e) => {
+                return (StatusCode::BAD_REQUEST, format!("Invalid notice: {}\n", e))
+                    .into_response()
+            }
+        };
+
+        let body = json!({
+            "delivered_to": self.document_service.publish_notice(notice),
+        });
+
+        let mut response =
+            ((header::CONTENT_TYPE, "application/json"), body.to_string()).into_response();
+        *response.status_mut() = StatusCode::ACCEPTED;
+        response
+    }
+
+    /// Changes the permission of a user, given as JSON, and applies it to the user's
+    /// connected sessions, then reports the number of affected sessions as JSON.
+    fn change_access(&self, token: &BearerToken, body: &str) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let request = match from_str::<AccessRequest>(body) {
+            Ok(request) if !request.user_id.is_empty() => request,
+            Ok(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    "Invalid access change: empty user_id\n",
+                )
+                    .into_response()
+            }
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid access change: {}\n", e),
+                )
+                    .into_response()
+            }
+        };
+
+        let user_id = request.user_id.as_str();
+        let doc_id = request.doc_id.as_deref();
+        self.document_service
+            .grant_access(user_id, doc_id, request.role);
+        let sessions = self
+            .sessions
+            .change_permission(user_id, doc_id, request.role, |doc_id| {
+                self.document_service
+                    .access_role(doc_id, Some(user_id))
+                    .ok()
+            });
+
+        json_response(json!({
+            "user_id": user_id,
+            "doc_id": doc_id,
+            "role": request.role,
+            "sessions": sessions,
+        }))
+    }
+
+    /// Reports the number of documents carrying each tag as JSON.
+    fn tag_counts(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match self.document_service.tag_counts() {
+            Ok(counts) => json_response(json!({ "tags": counts })),
+            Err(e) => domain_error(e),
+        }
+    }
+
+    /// Lists the documents carrying a tag as JSON.
+    fn tagged_documents(&self, token: &BearerToken, tag: &str) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match self.document_service.documents_with_tag(tag) {
+            Ok(documents) => json_response(json!({
+                "tag": tag.trim(),
+                "documents": documents,
+            })),
+            Err(e) => domain_error(e),
+        }
+    }
+
+    /// Lists the documents resident in the repository, with their size and clients, as JSON.
+    async fn resident_documents(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let resident = self.document_service.resident_documents().await;
+        let total_bytes: usize = resident.iter().map(|document| document.size_bytes).sum();
+        let documents: Vec<sonic_rs::Value> = resident
+            .iter()
+            .map(|document| {
+                json!({
+                    "doc_id": document.doc_id,
+                    "size_bytes": document.size_bytes,
+                    "subscribers": document.subscribers,
+                    "sequence_number": document.sequence_number,
+                    "clients": self.sessions.client_count(&document.doc_id),
+                })
+            })
+            .collect();
+
+        json_response(json!({
+            "loaded_documents": documents.len(),
+            "total_bytes": total_bytes,
+            "sessions": self.sessions.session_count(),
+            "documents": documents,
+        }))
+    }
+
+    /// Lists the connected clients of a document, or of every document, as JSON.
+    fn list_sessions(&self, token: &BearerToken, doc_id: Option<&str>) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let mut sessions = match doc_id {
+            Some(doc_id) => self.sessions.active_users(doc_id),
+            None => self.sessions.sessions(),
+        };
+        sessions
+            .sort_by(|a, b| (&a.document_id, &a.client_id).cmp(&(&b.document_id, &b.client_id)));
+
+        let sessions: Vec<sonic_rs::Value> = sessions
+            .iter()
+            .map(|session| {
+                json!({
+                    "doc_id": session.document_id,
+                    "client_id": session.client_id,
+                    "user_id": session.user_id,
+                    "user_name": session.user_name,
+                    "role": session.role,
+                    "transport": session.transport.to_string(),
+                    "remote_ip": session.remote_ip.map(|ip| ip.to_string()),
+                    "last_seen": session.last_seen,
+                    "metadata": session.user_metadata,
+                })
+            })
+            .collect();
+
+        json_response(json!({ "sessions": sessions }))
+    }
+
+    /// Disconnects a client from a document, then reports it as JSON.
+    fn kick(&self, token: &BearerToken, doc_id: &str, client_id: &str) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match self.sessions.kick(doc_id, client_id) {
+            Some(session) => json_response(json!({
+                "doc_id": session.document_id,
+                "client_id": session.client_id,
+            })),
+            None => (
+                StatusCode::NOT_FOUND,
+                format!(
+                    "Client {} is not connected to document '{}'\n",
+                    client_id, doc_id
+                ),
+            )
+                .into_response(),
+        }
+    }
+
+    /// Disconnects every client of a document and unloads it from the repository,
+    /// then reports both as JSON.
+    async fn close_document(&self, token: &BearerToken, doc_id: &str) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let disconnected = self.sessions.kick_all(doc_id);
+        match self.document_service.unload_document(doc_id).await {
+            Ok(unloaded) => json_response(json!({
+                "doc_id": doc_id,
+                "disconnected": disconnected,
+                "unloaded": unloaded,
+            })),
+            Err(e) => domain_error(e),
+        }
+    }
+
+    /// Warns the clients of a document that it is closing, then reports when it
+    /// closes as JSON.
+    fn schedule_close(&self, token: &BearerToken, query: &CloseQuery, grace_secs: u64) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let Some(maintenance) = &self.maintenance else {
+            return (
+                StatusCode::NOT_IMPLEMENTED,
+                "Graceful closes are not available on this server\n",
+            )
+                .into_response();
+        };
+        let closes_at = maintenance.schedule_close(
+            &query.doc,
+            Duration::from_secs(grace_secs),
+            query.message.as_deref(),
+        );
+
+        let mut response = json_response(json!({
+            "doc_id": query.doc,
+            "closes_at": closes_at,
+        }));
+        *response.status_mut() = StatusCode::ACCEPTED;
+        response
+    }
+
+    /// Reports the garbage collection options of a document and the size of its
+    /// structure as JSON.
+    async fn document_gc(&self, token: &BearerToken, doc_id: &str) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match self.document_service.get_document_stats(doc_id).await {
+            Some(stats) => json_response(json!({
+                "doc_id": doc_id,
+                "gc": stats.gc,
+                "structure": stats.structure,
+            })),
+            None => (StatusCode::NOT_FOUND, "Document not found\n").into_response(),
+        }
+    }
+
+    /// Changes the garbage collection options of a document, collects its
+    /// deleted content if requested, then reports both as JSON.
+    async fn change_gc(&self, token: &BearerToken, query: &GcQuery) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        if query.enabled.is_some() || query.on_snapshot.is_some() {
+            let current = self.document_service.document_gc_options(&query.doc);
+            let gc = GcOptions {
+                enabled: query.enabled.unwrap_or(current.enabled),
+                on_snapshot: query.on_snapshot.unwrap_or(current.on_snapshot),
+            };
+            if let Err(e) = self
+                .document_service
+                .set_document_gc_options(&query.doc, gc)
+                .await
+            {
+                return domain_error(e);
+            }
+        }
+
+        if query.collect {
+            match self
+                .document_service
+                .collect_document_garbage(&query.doc)
+                .await
+            {
+                Ok(Some(_)) => {}
+                Ok(None) => return (StatusCode::NOT_FOUND, "Document not found\n").into_response(),
+                Err(e) => return domain_error(e),
+            }
+        }
+
+        self.document_gc(token, &query.doc).await
+    }
+
+    /// Reports the tags of a document as JSON.
+    fn document_tags(&self, token: &BearerToken, doc_id: &str) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match self.document_service.document_tags(doc_id) {
+            Ok(tags) => json_response(json!({ "doc_id": doc_id, "tags": tags })),
+            Err(e) => domain_error(e),
+        }
+    }
+
+    /// Adds or removes the tags listed in a JSON body, then reports the
+    /// document's tags.
+    fn change_tags(
+        &self,
+        token: &BearerToken,
+        doc_id: &str,
+        body: &str,
+        change: TagChange,
+    ) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        let request = match from_str::<TagsRequest>(body) {
+            Ok(request) => request,
+            Err(e) => {
+                return (StatusCode::BAD_REQUEST, format!("Invalid tags: {}\n", e)).into_response()
+            }
+        };
+        if let TagChange::Add = change {
+            if let Some(Err(e)) = request
+                .tags
+                .iter()
+                .map(|tag| DocumentMetadata::normalize_tag(tag))
+                .find(Result::is_err)
+            {
+                return domain_error(e);
+            }
+        }
+
+        let result = match change {
+            TagChange::Add => self.document_service.tag_document(doc_id, &request.tags),
+            TagChange::Remove => self.document_service.untag_document(doc_id, &request.tags),
+        };
+        match result {
+            Ok(tags) => json_response(json!({ "doc_id": doc_id, "tags": tags })),
+            Err(e) => domain_error(e),
+        }
+    }
+
+    /// Exports a document as a binary update.
+    async fn export_document(
+        &self,
+        token: &BearerToken,
+        doc_id: &str,
+        mode: ExportMode,
+    ) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        if !self.document_service.document_exists(doc_id).await {
+            return domain_error(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        match self.document_service.export_document(doc_id, mode).await {
+            Ok(update) => {
+                ((header::CONTENT_TYPE, "application/octet-stream"), update).into_response()
+            }
+            Err(e) => domain_error(e),
+        }
+    }
+
+    /// Creates a document from a binary update given as the request body.
+    async fn import_document(
+        &self,
+        token: &BearerToken,
+        doc_id: &str,
+        update: &[u8],
+        mode: ExportMode,
+    ) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        if self.document_service.document_exists(doc_id).await {
+            return domain_error(DomainError::Conflict(doc_id.to_string()));
+        }
+
+        match self
+            .document_service
+            .import_document(doc_id, update, mode)
+            .await
+        {
+            Ok(()) => {
+                let mut response = json_response(json!({ "doc_id": doc_id }));
+                *response.status_mut() = StatusCode::CREATED;
+                response
+            }
+            Err(e) => domain_error(e),
+        }
+    }
+
+    /// Returns whether the server serves as primary or follows one as a standby.
+    fn role(&self) -> &'static str {
+        match &self.standby {
+            Some(standby) if standby.is_standby() => "standby",
+            _ => "primary",
+        }
+    }
+
+    /// Promotes a warm standby to primary, then reports its role as JSON.
+    fn promote(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match &self.standby {
+            Some(standby) => json_response(json!({
+                "promoted": standby.promote(),
+                "role": self.role(),
+            })),
+            None => (
+                StatusCode::NOT_FOUND,
+                "This server was not started as a standby\n",
+            )
+                .into_response(),
+        }
+    }
+
+    /// Reports the outcome of the last configuration reload as JSON.
+    fn last_reload(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match &self.config {
+            Some(config) => json_response(json!({ "last_reload": config.last_reload() })),
+            None => not_reloadable(),
+        }
+    }
+
+    /// Reloads the configuration file, then reports the applied changes as JSON.
+    fn reload_config(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match &self.config {
+            Some(config) => match config.reload("admin") {
+                Ok(report) => match sonic_rs::to_string(&report) {
+                    Ok(body) => ((header::CONTENT_TYPE, "application/json"), body).into_response(),
+                    Err(e) => (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to encode the reload report: {}\n", e),
+                    )
+                        .into_response(),
+                },
+                Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, format!("{}\n", e)).into_response(),
+            },
+            None => not_reloadable(),
+        }
+    }
+
+    /// Serves the dashboard page, whose script then calls the admin routes with the token.
+    fn dashboard(&self, token: BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(&token) {
+            return response;
+        }
+
+        dashboard_asset("text/html; charset=utf-8", DASHBOARD_PAGE)
+    }
+
+    /// Reports server metrics in the Prometheus text exposition format.
+    fn metrics(&self, token: &BearerToken) -> Response {
+        if let Some(response) = self.auth.reject(token) {
+            return response;
+        }
+
+        match self.metrics.render() {
+            Some(body) => {
+                ((header::CONTENT_TYPE, "text/plain; version=0.0.4"), body).into_response()
+            }
+            None => (
+                StatusCode::NOT_FOUND,
+                "Metrics are pushed to the configured metrics backend\n",
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Builds a `200 OK` response carrying a JSON body.
+fn json_response(body: sonic_rs::Value) -> Response {
+    ((header::CONTENT_TYPE, "application/json"), body.to_string()).into_response()
+}
+
+/// Builds the response of the configuration endpoints on a server started without a file.
+fn not_reloadable() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        "This server was not started from a configuration file\n",
+    )
+        .into_response()
+}
+
+/// Builds a `200 OK` response carrying an embedded dashboard asset.
+///
+/// The script and stylesheet hold no server data, so they are served without the
+/// admin token, which a browser cannot attach to the `<script>` and `<link>` requests.
+fn dashboard_asset(content_type: &'static str, asset: &'static [u8]) -> Response {
+    ((header::CONTENT_TYPE, content_type), asset.to_vec()).into_response()
+}
+
+/// Builds a response reporting a domain error with its matching status.
+fn domain_error(error: DomainError) -> Response {
+    (error_status(&error), format!("{}\n", error)).into_response()
+}
+
+/// Compares two byte strings without short-circuiting on the first mismatch.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
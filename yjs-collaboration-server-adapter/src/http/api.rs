@@ -0,0 +1,1640 @@
+use std::{collections::BTreeMap, convert::Infallible, sync::Arc};
+
+use base64::Engine;
+use serde::{Deserialize, Deserializer};
+use sonic_rs::{from_str, json};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+use uuid::Uuid;
+use volo_http::{
+    context::ServerContext,
+    http::{header, request::Parts, HeaderName, HeaderValue, StatusCode},
+    response::Response,
+    server::{
+        extract::FromContext,
+        response::sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+};
+use yjs_collaboration_server_domain::{
+    errors::DomainError,
+    repositories::document_repository::DocumentRepository,
+    services::{document_importer::DEFAULT_TEXT_ROOT, document_service::DocumentService},
+    value_objects::{
+        audit_entry::DEFAULT_AUDIT_PAGE_SIZE,
+        document_activity::ActivityGranularity,
+        document_metadata::{DocumentMetadata, MetadataPatch},
+        export_format::ExportFormat,
+        import_format::ImportFormat,
+        message::{Notice, NoticeKind, NoticeSeverity},
+        search_hit::DEFAULT_SEARCH_LIMIT,
+        shared_edit::{SharedEdit, DEFAULT_MAP_ROOT},
+        tenant::unscoped_document_id,
+    },
+};
+
+use crate::{
+    broadcast_hub::{BroadcastHub, HubEvent},
+    session_registry::SessionRegistry,
+};
+
+/// Response header carrying the document's Base64-encoded state vector.
+pub const STATE_VECTOR_HEADER: &str = "x-yjs-state-vector";
+/// Response header carrying the number of the version a snapshot belongs to.
+pub const VERSION_HEADER: &str = "x-yjs-version";
+
+/// Event carrying the full document state when a subscription starts.
+const SYNC_EVENT: &str = "sync";
+/// Event carrying an update applied by a client.
+const UPDATE_EVENT: &str = "update";
+/// Event carrying the full document state after the subscriber missed updates.
+const RESYNC_EVENT: &str = "resync";
+/// Event carrying a server notice as JSON.
+const NOTICE_EVENT: &str = "notice";
+
+/// Document identifier taken from the `{doc_id}` path segment.
+///
+/// Document IDs may contain slashes, which clients send percent-encoded
+/// (`team-a%2Froadmap`) so the ID stays a single path segment.
+pub struct DocumentPath(pub String);
+
+impl FromContext for DocumentPath {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        cx: &mut ServerContext,
+        _parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        cx.params()
+            .iter()
+            .find(|(key, _)| key == "doc_id")
+            .map(|(_, value)| percent_decode(value))
+            .filter(|doc_id| !doc_id.is_empty())
+            .map(Self)
+            .ok_or((StatusCode::BAD_REQUEST, "Missing document id\n"))
+    }
+}
+
+/// Version number taken from the `{version}` path segment.
+pub struct VersionPath(pub u64);
+
+impl FromContext for VersionPath {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        cx: &mut ServerContext,
+        _parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        cx.params()
+            .iter()
+            .find(|(key, _)| key == "version")
+            .and_then(|(_, value)| value.parse().ok())
+            .map(Self)
+            .ok_or((StatusCode::BAD_REQUEST, "Invalid version number\n"))
+    }
+}
+
+/// Map key taken from the `{key}` path segment, percent-decoded.
+pub struct KeyPath(pub String);
+
+impl FromContext for KeyPath {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        cx: &mut ServerContext,
+        _parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        cx.params()
+            .iter()
+            .find(|(key, _)| key == "key")
+            .map(|(_, value)| percent_decode(value))
+            .filter(|key| !key.is_empty())
+            .map(Self)
+            .ok_or((StatusCode::BAD_REQUEST, "Missing map key\n"))
+    }
+}
+
+/// User identity taken from the `{user_id}` path segment, percent-decoded.
+pub struct UserPath(pub String);
+
+impl FromContext for UserPath {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        cx: &mut ServerContext,
+        _parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        cx.params()
+            .iter()
+            .find(|(key, _)| key == "user_id")
+            .map(|(_, value)| percent_decode(value))
+            .filter(|user_id| !user_id.is_empty())
+            .map(Self)
+            .ok_or((StatusCode::BAD_REQUEST, "Missing user id\n"))
+    }
+}
+
+/// Root name taken from the `{name}` path segment, percent-decoded.
+pub struct RootPath(pub String);
+
+impl FromContext for RootPath {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        cx: &mut ServerContext,
+        _parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        cx.params()
+            .iter()
+            .find(|(key, _)| key == "name")
+            .map(|(_, value)| percent_decode(value))
+            .filter(|name| !name.is_empty())
+            .map(Self)
+            .ok_or((StatusCode::BAD_REQUEST, "Missing root name\n"))
+    }
+}
+
+/// Media type of the request body, taken from the `Content-Type` header.
+pub struct ContentType(pub Option<String>);
+
+impl FromContext for ContentType {
+    type Rejection = Infallible;
+
+    async fn from_context(
+        _cx: &mut ServerContext,
+        parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        let content_type = parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        Ok(Self(content_type))
+    }
+}
+
+/// Body of the request creating a document.
+#[derive(Deserialize)]
+struct CreateDocumentRequest {
+    id: String,
+}
+
+/// Body of the request changing a document's metadata; absent fields are kept.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateMetadataRequest {
+    #[serde(default, deserialize_with = "present")]
+    title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    owner: Option<Option<String>>,
+    tags: Option<Vec<String>>,
+}
+
+/// Deserializes a field that is present, telling a `null` value apart from an
+/// absent field.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Body of the request recording a version, which may be empty.
+#[derive(Default, Deserialize)]
+struct CreateVersionRequest {
+    label: Option<String>,
+    author: Option<String>,
+}
+
+/// Body of the request reverting a document to one of its versions.
+#[derive(Deserialize)]
+struct RevertDocumentRequest {
+    version: u64,
+}
+
+/// Query of the document state route.
+#[derive(Deserialize)]
+pub struct StateQuery {
+    /// Base64-encoded state vector of the caller, to fetch only the missing changes
+    pub state_vector: Option<String>,
+}
+
+/// Query of the document export route.
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// Format of the export, `json` (default), `markdown` or `text`
+    pub format: Option<String>,
+}
+
+/// Query of the document version route.
+#[derive(Deserialize)]
+pub struct VersionQuery {
+    /// Format to export the version to, `json`, `markdown` or `text`; the binary
+    /// snapshot is returned by default
+    pub format: Option<String>,
+}
+
+/// Query of the document import route.
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    /// Format of the body, `text` or `json`; inferred from the `Content-Type` header by default
+    pub format: Option<String>,
+    /// Root seeded from plain text, `content` by default
+    pub root: Option<String>,
+}
+
+/// Query of the map entry routes.
+#[derive(Deserialize)]
+pub struct MapQuery {
+    /// Name of the map root, `map` by default
+    pub root: Option<String>,
+}
+
+/// Query of the array insertion route.
+#[derive(Deserialize)]
+pub struct ArrayInsertQuery {
+    /// Position the items are inserted at; they are appended by default
+    pub index: Option<u32>,
+}
+
+/// Query of the document activity route.
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    /// Width of the returned buckets, `minute` or `hour` (default)
+    pub granularity: Option<String>,
+}
+
+/// Query of the document audit trail route.
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    /// Cursor returned as `next` with the previous page; the trail is read from its oldest
+    /// entry by default
+    pub after: Option<u64>,
+    /// Maximum number of entries returned, 100 by default and at most 1000
+    pub limit: Option<usize>,
+}
+
+/// Query of the user documents route.
+#[derive(Deserialize)]
+pub struct UserDocumentsQuery {
+    /// Tenant to list the documents of, all of them when omitted
+    pub tenant: Option<String>,
+}
+
+/// Query of the search route.
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    /// The full-text query
+    pub q: Option<String>,
+    /// Maximum number of hits returned, 20 by default and at most 100
+    pub limit: Option<usize>,
+}
+
+/// Reports that the server process is alive, without checking its dependencies.
+///
+/// Liveness probes should only restart a server that stopped answering, so
+/// this endpoint succeeds for as long as the HTTP server runs.
+///
+/// # Returns
+///
+/// A `200 OK` response carrying `{"status": "ok"}`
+pub async fn liveness() -> Response {
+    json_response(StatusCode::OK, json!({ "status": "ok" }))
+}
+
+/// Reports whether the server can serve documents, checking each of its dependencies.
+///
+/// The storage backend is always checked, along with the document store, the
+/// archive and the Redis broker when they are configured. Each dependency is
+/// reported as `up` or `down`, with the error of a failed check.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the dependencies
+///
+/// # Returns
+///
+/// A `200 OK` response if every dependency is up, or `503 Service Unavailable`
+/// if any is down, both listing the status of each dependency
+pub async fn readiness<R>(document_service: Arc<DocumentService<R>>) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let dependencies = document_service.check_dependencies().await;
+    let ready = dependencies.iter().all(|dependency| dependency.healthy);
+    let statuses: BTreeMap<&str, sonic_rs::Value> = dependencies
+        .iter()
+        .map(|dependency| {
+            let status = if dependency.healthy { "up" } else { "down" };
+            let report = match &dependency.error {
+                Some(error) => json!({ "status": status, "error": error }),
+                None => json!({ "status": status }),
+            };
+            (dependency.name, report)
+        })
+        .collect();
+
+    if ready {
+        json_response(
+            StatusCode::OK,
+            json!({ "status": "ready", "dependencies": statuses }),
+        )
+    } else {
+        warn!("Readiness check failed: {:?}", dependencies);
+        json_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            json!({ "status": "not_ready", "dependencies": statuses }),
+        )
+    }
+}
+
+/// Lists the documents the caller may read as JSON.
+///
+/// REST requests carry no user identity, so they are authorized as guests.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+///
+/// # Returns
+///
+/// A `200 OK` response listing the document IDs
+pub async fn list_documents<R>(document_service: Arc<DocumentService<R>>) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let documents: Vec<String> = document_service
+        .list_documents()
+        .await
+        .into_iter()
+        .filter(|doc_id| document_service.authorize(doc_id, None).is_ok())
+        .collect();
+
+    json_response(
+        StatusCode::OK,
+        json!({ "count": documents.len(), "documents": documents }),
+    )
+}
+
+/// Searches the text content of the documents.
+///
+/// Hits are the documents matching the query, best match first, each with a
+/// snippet of its content where the matched terms are wrapped in `<b>` tags.
+/// Documents guests may not read are left out. The index is updated shortly
+/// after documents change, so recent edits may not be found yet.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `query` - The full-text query
+/// * `limit` - Optional maximum number of hits returned
+///
+/// # Returns
+///
+/// A `200 OK` response listing the hits, `400 Bad Request` if the query is
+/// missing or malformed, or `503 Service Unavailable` if search is not enabled
+pub async fn search_documents<R>(
+    document_service: Arc<DocumentService<R>>,
+    query: Option<String>,
+    limit: Option<usize>,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let query = query.unwrap_or_default();
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    match document_service.search_documents(&query, limit) {
+        Ok(hits) => {
+            let hits: Vec<_> = hits
+                .into_iter()
+                .filter(|hit| document_service.authorize(&hit.doc_id, None).is_ok())
+                .collect();
+            json_response(
+                StatusCode::OK,
+                json!({ "query": query, "count": hits.len(), "hits": hits }),
+            )
+        }
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Creates an empty document named by a JSON body (`{"id": "..."}`).
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `body` - The JSON request body
+///
+/// # Returns
+///
+/// A `201 Created` response, `409 Conflict` if the document already exists, or
+/// `403 Forbidden` if guests may not write to it
+pub async fn create_document<R>(document_service: Arc<DocumentService<R>>, body: String) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let doc_id = match from_str::<CreateDocumentRequest>(&body) {
+        Ok(request) if !request.id.trim().is_empty() => request.id.trim().to_string(),
+        Ok(_) => return error_response(StatusCode::BAD_REQUEST, "Document id is empty"),
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid request: {}", e))
+        }
+    };
+
+    if let Some(response) = reject_writes(&document_service, &doc_id) {
+        return response;
+    }
+    if document_service.document_exists(&doc_id).await {
+        return domain_error_response(&DomainError::Conflict(doc_id));
+    }
+
+    match document_service.create_document(&doc_id).await {
+        Ok(()) => json_response(StatusCode::CREATED, json!({ "doc_id": doc_id })),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Deletes a document.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document to delete
+///
+/// # Returns
+///
+/// A `204 No Content` response, `404 Not Found` if the document does not
+/// exist, or `403 Forbidden` if guests may not write to it
+pub async fn delete_document<R>(document_service: Arc<DocumentService<R>>, doc_id: &str) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    match document_service.delete_document(doc_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Reports the metadata of a document as JSON.
+///
+/// The title, owner and tags are set by operators; `created_at` and
+/// `updated_at` (Unix seconds) are kept by the server as the document is
+/// created and its content modified. Unknown fields are `null`.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+///
+/// # Returns
+///
+/// A `200 OK` response carrying the metadata, `404 Not Found` if the document
+/// does not exist, or `403 Forbidden` if guests may not read it
+pub async fn get_document_metadata<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    match document_service.document_metadata(doc_id) {
+        Ok(metadata) => metadata_response(doc_id, &metadata),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Changes the title, owner or tags of a document from a JSON body.
+///
+/// Fields absent from the body are kept, and a `null` title or owner is
+/// cleared; `tags` replaces every tag of the document.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `body` - The JSON request body
+///
+/// # Returns
+///
+/// A `200 OK` response carrying the metadata after the change, `400 Bad
+/// Request` if the body or a field is invalid, `404 Not Found` if the document
+/// does not exist, or `403 Forbidden` if guests may not write to it
+pub async fn update_document_metadata<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    body: String,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let patch = match from_str::<UpdateMetadataRequest>(&body) {
+        Ok(request) => MetadataPatch {
+            title: request.title,
+            owner: request.owner,
+            tags: request.tags,
+        },
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid request: {}", e))
+        }
+    };
+
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    match document_service.update_document_metadata(doc_id, patch) {
+        Ok(metadata) => metadata_response(doc_id, &metadata),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Freezes a document read-only, or lets it be edited again.
+///
+/// A frozen document rejects every update with `423 Locked` over REST and a
+/// `DOCUMENT_FROZEN` error over WebSocket and gRPC, e.g. once it is published.
+/// The connected clients are told with a notice when the document is frozen
+/// (`document_locked`) and when it may be edited again.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `frozen` - Whether the document is frozen
+///
+/// # Returns
+///
+/// A `200 OK` response carrying `frozen` and the time the document was frozen,
+/// `404 Not Found` if the document does not exist, `403 Forbidden` if guests may
+/// not write to it, or `503 Service Unavailable` without metadata storage
+pub async fn freeze_document<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    frozen: bool,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    let was_frozen = document_service.is_frozen(doc_id);
+    let metadata = match document_service.set_frozen(doc_id, frozen) {
+        Ok(metadata) => metadata,
+        Err(e) => return domain_error_response(&e),
+    };
+    if was_frozen != frozen {
+        let notice = if frozen {
+            Notice::new(
+                NoticeKind::DocumentLocked,
+                NoticeSeverity::Warning,
+                "This document has been frozen and can no longer be edited",
+            )
+        } else {
+            Notice::new(
+                NoticeKind::General,
+                NoticeSeverity::Info,
+                "This document can be edited again",
+            )
+        };
+        document_service.publish_notice(notice.with_document(doc_id));
+    }
+
+    json_response(
+        StatusCode::OK,
+        json!({ "doc_id": doc_id, "frozen": frozen, "frozen_at": metadata.frozen_at }),
+    )
+}
+
+/// Builds the `200 OK` response carrying the metadata of a document.
+fn metadata_response(doc_id: &str, metadata: &DocumentMetadata) -> Response {
+    json_response(
+        StatusCode::OK,
+        json!({
+            "doc_id": doc_id,
+            "title": metadata.title,
+            "owner": metadata.owner,
+            "tags": metadata.tags,
+            "created_at": metadata.created_at,
+            "updated_at": metadata.updated_at,
+            "archived_at": metadata.archived_at,
+            "frozen_at": metadata.frozen_at
+        }),
+    )
+}
+
+/// Reports the text content of a document as JSON.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document to read
+///
+/// # Returns
+///
+/// A `200 OK` response carrying the content, `404 Not Found` if the document
+/// does not exist, or `403 Forbidden` if guests may not read it
+pub async fn get_document_content<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+
+    match document_service.get_document_content(doc_id).await {
+        Ok(content) => json_response(
+            StatusCode::OK,
+            json!({ "doc_id": doc_id, "content": content }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Returns the state of a document as a binary Yjs v1 update.
+///
+/// The document's state vector is returned Base64-encoded in the
+/// `X-Yjs-State-Vector` header, so the caller can later fetch only the changes
+/// made since.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document to read
+/// * `state_vector` - Optional Base64-encoded state vector; only the changes missing from it are
+///   returned
+///
+/// # Returns
+///
+/// A `200 OK` response carrying the update, `400 Bad Request` if the state
+/// vector is invalid, `404 Not Found` if the document does not exist, or
+/// `403 Forbidden` if guests may not read it
+pub async fn get_document_state<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    state_vector: Option<String>,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    // Query decoding turns an unescaped `+` into a space, which Base64 never contains
+    let state_vector = state_vector.map(|encoded| encoded.replace(' ', "+"));
+    let state = match document_service
+        .get_document_state(doc_id, state_vector.as_deref())
+        .await
+    {
+        Ok(state) => state,
+        Err(e) => return domain_error_response(&e),
+    };
+
+    let mut response = (
+        (header::CONTENT_TYPE, "application/octet-stream"),
+        state.update.unwrap_or_default(),
+    )
+        .into_response();
+    let encoded =
+        base64::engine::general_purpose::STANDARD.encode(state.state_vector.unwrap_or_default());
+    if let Ok(value) = HeaderValue::from_str(&encoded) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(STATE_VECTOR_HEADER), value);
+    }
+    response
+}
+
+/// Exports the content of a document as JSON, Markdown or plain text.
+///
+/// The document's shared types are serialized by the domain `DocumentExporter`;
+/// the response carries the format's media type.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document to export
+/// * `format` - Optional format, `json` (default), `markdown` or `text`
+///
+/// # Returns
+///
+/// A `200 OK` response carrying the exported content, `400 Bad Request` if the
+/// format is unknown, `404 Not Found` if the document does not exist, or
+/// `403 Forbidden` if guests may not read it
+pub async fn export_document_content<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    format: Option<String>,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+
+    let format: ExportFormat = match format.as_deref().map(str::parse).transpose() {
+        Ok(format) => format.unwrap_or_default(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+    };
+    match document_service.export_content(doc_id, format).await {
+        Ok(content) => ((header::CONTENT_TYPE, format.content_type()), content).into_response(),
+        Err(DomainError::NotFound(_)) => not_found(doc_id),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Lists the versions recorded in a document's timeline as JSON.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+///
+/// # Returns
+///
+/// A `200 OK` response listing the versions oldest first, `404 Not Found` if
+/// the document does not exist, or `403 Forbidden` if guests may not read it
+pub async fn list_document_versions<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    match document_service.list_versions(doc_id) {
+        Ok(versions) => json_response(
+            StatusCode::OK,
+            json!({ "doc_id": doc_id, "versions": versions }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Records a version of a document, named by an optional JSON body
+/// (`{"label": "...", "author": "..."}`).
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `body` - The JSON request body, or an empty body for an unnamed snapshot
+///
+/// # Returns
+///
+/// A `201 Created` response carrying the version, `400 Bad Request` if the
+/// body or label is invalid, `404 Not Found` if the document does not exist,
+/// or `403 Forbidden` if guests may not write to it
+pub async fn create_document_version<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    body: String,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let request = if body.trim().is_empty() {
+        CreateVersionRequest::default()
+    } else {
+        match from_str::<CreateVersionRequest>(&body) {
+            Ok(request) => request,
+            Err(e) => {
+                return error_response(StatusCode::BAD_REQUEST, &format!("Invalid request: {}", e))
+            }
+        }
+    };
+
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+
+    match document_service
+        .create_version(doc_id, request.label.as_deref(), request.author.as_deref())
+        .await
+    {
+        Ok(version) => json_response(
+            StatusCode::CREATED,
+            json!({ "doc_id": doc_id, "version": version }),
+        ),
+        Err(DomainError::NotFound(_)) => not_found(doc_id),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Returns a version of a document, as its binary snapshot or exported.
+///
+/// Without a format, the snapshot is returned as a single binary Yjs v1 update
+/// that any client can load, with the version number in the `X-Yjs-Version`
+/// header. With a format, the content of the version is exported like the
+/// export route does for the current state.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `version` - Number of the version
+/// * `format` - Optional export format, `json`, `markdown` or `text`
+///
+/// # Returns
+///
+/// A `200 OK` response carrying the version, `400 Bad Request` if the format is
+/// unknown, `404 Not Found` if the document has no such version, or
+/// `403 Forbidden` if guests may not read it
+pub async fn get_document_version<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    version: u64,
+    format: Option<String>,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+
+    if let Some(format) = format {
+        let format: ExportFormat = match format.parse() {
+            Ok(format) => format,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+        };
+        return match document_service
+            .export_version(doc_id, version, format)
+            .await
+        {
+            Ok(content) => ((header::CONTENT_TYPE, format.content_type()), content).into_response(),
+            Err(e) => domain_error_response(&e),
+        };
+    }
+
+    let (version, snapshot) = match document_service.get_version(doc_id, version) {
+        Ok(found) => found,
+        Err(e) => return domain_error_response(&e),
+    };
+    let mut response =
+        ((header::CONTENT_TYPE, "application/octet-stream"), snapshot).into_response();
+    response.headers_mut().insert(
+        HeaderName::from_static(VERSION_HEADER),
+        HeaderValue::from(version.version),
+    );
+    response
+}
+
+/// Reverts a document to one of its versions, named by a JSON body (`{"version": 3}`).
+///
+/// The version's content is restored by a regular update, broadcast to the
+/// connected clients, which converge to it without reconnecting.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `body` - The JSON request body
+///
+/// # Returns
+///
+/// A `200 OK` response telling whether the document changed, `400 Bad Request`
+/// if the body is invalid, `404 Not Found` if the document has no such version,
+/// `413 Payload Too Large` if the restored content exceeds the document's
+/// limits, or `403 Forbidden` if guests may not write to it
+pub async fn revert_document<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    body: String,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let version = match from_str::<RevertDocumentRequest>(&body) {
+        Ok(request) => request.version,
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("Invalid request: {}", e))
+        }
+    };
+
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+
+    match document_service.revert_document(doc_id, version).await {
+        Ok(reverted) => json_response(
+            StatusCode::OK,
+            json!({ "doc_id": doc_id, "version": version, "reverted": reverted }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Creates a document seeded from plain text or JSON content.
+///
+/// The content is turned into the matching shared types by the domain
+/// `DocumentImporter`, the counterpart of the export route: plain text fills a
+/// single text root, and a JSON object maps root names to their content.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document to create
+/// * `format` - Optional format, `text` or `json`
+/// * `root` - Optional name of the root seeded from plain text
+/// * `content_type` - Media type of the body, used when no format is given
+/// * `body` - The content to import
+///
+/// # Returns
+///
+/// A `201 Created` response carrying the Base64-encoded state vector of the
+/// document, `400 Bad Request` if the format is unknown or the content
+/// malformed, `409 Conflict` if the document already exists, `413 Payload Too
+/// Large` if the content exceeds the document's limits, or `403 Forbidden` if
+/// guests may not write to it
+pub async fn import_document_content<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    format: Option<String>,
+    root: Option<String>,
+    content_type: Option<String>,
+    body: String,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+
+    let format = match format
+        .as_deref()
+        .map(str::parse::<ImportFormat>)
+        .transpose()
+    {
+        Ok(Some(format)) => format,
+        Ok(None) => content_type
+            .as_deref()
+            .map(ImportFormat::from_content_type)
+            .unwrap_or_default(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+    };
+    if document_service.document_exists(doc_id).await {
+        return domain_error_response(&DomainError::Conflict(doc_id.to_string()));
+    }
+
+    let root = root.unwrap_or_else(|| DEFAULT_TEXT_ROOT.to_string());
+    match document_service
+        .import_content(doc_id, body, format, root)
+        .await
+    {
+        Ok(state_vector) => json_response(
+            StatusCode::CREATED,
+            json!({
+                "doc_id": doc_id,
+                "state_vector": base64::engine::general_purpose::STANDARD.encode(state_vector),
+            }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Writes a JSON value under a key of a map root of a document.
+///
+/// The server generates the CRDT update making the change and applies it like
+/// any client update, so the connected clients receive it; backend services
+/// can write into collaborative state without running a Yjs client.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `root` - Optional name of the map root, created if missing
+/// * `key` - Key the value is written under
+/// * `body` - The value, as JSON
+///
+/// # Returns
+///
+/// A `200 OK` response telling whether the document changed, `400 Bad Request`
+/// if the value is not valid JSON, `404 Not Found` if the document does not
+/// exist, `409 Conflict` if the root is not a map, `413 Payload Too Large` if
+/// the value exceeds the document's limits, or `403 Forbidden` if guests may
+/// not write to it
+pub async fn set_map_entry<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    root: Option<String>,
+    key: &str,
+    body: String,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let root = root.unwrap_or_else(|| DEFAULT_MAP_ROOT.to_string());
+    let edit = match SharedEdit::set_map_entry(&root, key, &body) {
+        Ok(edit) => edit,
+        Err(e) => return domain_error_response(&e),
+    };
+
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+
+    match document_service.edit_document(doc_id, edit).await {
+        Ok(changed) => json_response(
+            StatusCode::OK,
+            json!({ "doc_id": doc_id, "root": root, "key": key, "changed": changed }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Removes a key from a map root of a document.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `root` - Optional name of the map root
+/// * `key` - Key to remove
+///
+/// # Returns
+///
+/// A `200 OK` response telling whether the key was removed, `404 Not Found` if
+/// the document does not exist, `409 Conflict` if the root is not a map, or
+/// `403 Forbidden` if guests may not write to it
+pub async fn remove_map_entry<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    root: Option<String>,
+    key: &str,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+
+    let root = root.unwrap_or_else(|| DEFAULT_MAP_ROOT.to_string());
+    let edit = SharedEdit::RemoveMapEntry {
+        root: root.clone(),
+        key: key.to_string(),
+    };
+    match document_service.edit_document(doc_id, edit).await {
+        Ok(removed) => json_response(
+            StatusCode::OK,
+            json!({ "doc_id": doc_id, "root": root, "key": key, "removed": removed }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Inserts the items of a JSON array into an array root of a document.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `root` - Name of the array root, created if missing
+/// * `index` - Optional position the items are inserted at; they are appended by default
+/// * `body` - The items, as a JSON array
+///
+/// # Returns
+///
+/// A `200 OK` response telling whether the document changed, `400 Bad
+/// Request` if the body is not a JSON array or the index is past the end of
+/// the array, `404 Not Found` if the document does not exist, `409 Conflict`
+/// if the root is not an array, `413 Payload Too Large` if the items exceed
+/// the document's limits, or `403 Forbidden` if guests may not write to it
+pub async fn insert_array_items<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    root: &str,
+    index: Option<u32>,
+    body: String,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let edit = match SharedEdit::insert_array_items(root, index, &body) {
+        Ok(edit) => edit,
+        Err(e) => return domain_error_response(&e),
+    };
+    if let Some(response) = reject_writes(&document_service, doc_id) {
+        return response;
+    }
+
+    match document_service.edit_document(doc_id, edit).await {
+        Ok(changed) => json_response(
+            StatusCode::OK,
+            json!({ "doc_id": doc_id, "root": root, "changed": changed }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Reports the statistics of a document as JSON.
+///
+/// The statistics carry the number of characters and words across the
+/// document's text roots, its approximate encoded size in bytes and, when its
+/// policy limits the content, its maximum number of characters. They also
+/// measure the document's CRDT structure, tombstones of deleted content
+/// included, along with how that content is garbage collected.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document to read
+///
+/// # Returns
+///
+/// A `200 OK` response carrying the statistics, `404 Not Found` if the document
+/// does not exist, or `403 Forbidden` if guests may not read it
+pub async fn get_document_stats<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+
+    match document_service.get_document_stats(doc_id).await {
+        Some(stats) => json_response(StatusCode::OK, json!({ "doc_id": doc_id, "stats": stats })),
+        None => not_found(doc_id),
+    }
+}
+
+/// Returns the activity of a document, aggregated into time buckets, as JSON.
+///
+/// Each bucket reports its start as Unix seconds, the number of updates
+/// applied by clients and the number of distinct clients that applied them.
+/// Buckets without activity are omitted, and only the buckets within the
+/// retention window are kept.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `granularity` - Optional bucket width, `minute` or `hour` (default)
+///
+/// # Returns
+///
+/// A `200 OK` response listing the buckets, `400 Bad Request` if the
+/// granularity is unknown, `404 Not Found` if the document does not exist, or
+/// `403 Forbidden` if guests may not read it
+pub async fn get_document_activity<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    granularity: Option<String>,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    let granularity = match granularity.as_deref().map(str::parse).transpose() {
+        Ok(granularity) => granularity.unwrap_or_default(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+    };
+    let buckets = document_service.document_activity(doc_id, granularity);
+
+    json_response(
+        StatusCode::OK,
+        json!({
+            "doc_id": doc_id,
+            "granularity": granularity,
+            "bucket_seconds": granularity.seconds(),
+            "buckets": buckets,
+        }),
+    )
+}
+
+/// Returns the presence history of a document, and who was last active on it, as JSON.
+///
+/// The history lists the joins and departures of clients over either
+/// transport, oldest first, within the rolling window kept by the session
+/// registry. `last_active` reports the present client seen most recently, or
+/// the client of the latest event once everyone left, so clients can show
+/// "last active: X, N minutes ago". Times are server Unix seconds, and guests
+/// are reported with a `null` user ID.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `sessions` - Registry of the clients present on each document
+/// * `doc_id` - Identifier of the document
+///
+/// # Returns
+///
+/// A `200 OK` response listing the present clients and the events, `404 Not
+/// Found` if the document does not exist, or `403 Forbidden` if guests may not
+/// read it
+pub async fn get_presence_history<R>(
+    document_service: Arc<DocumentService<R>>,
+    sessions: Arc<SessionRegistry>,
+    doc_id: &str,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    let mut present = sessions.active_users(doc_id);
+    present.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    let history = sessions.presence_history(doc_id);
+
+    let last_active = match (present.first(), history.last()) {
+        (Some(session), _) => Some(json!({
+            "user_id": non_empty(&session.user_id),
+            "user_name": session.user_name,
+            "at": session.last_seen,
+            "present": true,
+        })),
+        (None, Some(event)) => Some(json!({
+            "user_id": non_empty(&event.user_id),
+            "user_name": event.user_name,
+            "at": event.at,
+            "present": false,
+        })),
+        (None, None) => None,
+    };
+    let present: Vec<sonic_rs::Value> = present
+        .iter()
+        .map(|session| {
+            json!({
+                "client_id": session.client_id,
+                "user_id": non_empty(&session.user_id),
+                "user_name": session.user_name,
+                "transport": session.transport.to_string(),
+                "last_seen": session.last_seen,
+            })
+        })
+        .collect();
+    let events: Vec<sonic_rs::Value> = history
+        .iter()
+        .map(|event| {
+            json!({
+                "change": event.change.to_string(),
+                "client_id": event.client_id,
+                "user_id": non_empty(&event.user_id),
+                "user_name": event.user_name,
+                "transport": event.transport.to_string(),
+                "at": event.at,
+            })
+        })
+        .collect();
+
+    json_response(
+        StatusCode::OK,
+        json!({
+            "doc_id": doc_id,
+            "last_active": last_active,
+            "present": present,
+            "events": events,
+        }),
+    )
+}
+
+/// Returns the documents a user currently has open as JSON.
+///
+/// Each document lists the user's connections on it, over either transport,
+/// and the last time any of them was active, as server Unix seconds. Only the
+/// documents guests may read are listed, as this API is not authenticated.
+/// Given a tenant, only its documents are listed, by their ID within it.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `sessions` - Registry of the clients present on each document
+/// * `user_id` - Identity the user joined the documents with
+/// * `tenant` - Tenant to list the documents of, or an empty string for every document
+///
+/// # Returns
+///
+/// A `200 OK` response listing the documents, empty if the user has none open
+pub fn get_user_documents<R>(
+    document_service: Arc<DocumentService<R>>,
+    sessions: Arc<SessionRegistry>,
+    user_id: &str,
+    tenant: &str,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    let documents: Vec<sonic_rs::Value> = sessions
+        .user_documents(user_id)
+        .iter()
+        .filter(|document| {
+            document_service
+                .authorize(&document.document_id, None)
+                .is_ok()
+        })
+        .filter_map(|document| {
+            let doc_id = unscoped_document_id(tenant, &document.document_id)?;
+            Some(json!({
+                "doc_id": doc_id,
+                "connections": document.connections,
+                "last_seen": document.last_seen,
+            }))
+        })
+        .collect();
+
+    json_response(
+        StatusCode::OK,
+        json!({
+            "user_id": user_id,
+            "documents": documents,
+        }),
+    )
+}
+
+/// Returns a user identity, or `None` for a guest.
+fn non_empty(user_id: &str) -> Option<&str> {
+    (!user_id.is_empty()).then_some(user_id)
+}
+
+/// Returns a page of the audit trail of a document as JSON.
+///
+/// Each entry records who applied an update (client and user), when (as Unix
+/// milliseconds), its size, the sequence number of its broadcast and the
+/// transport it came through. Entries are listed oldest first; the `next`
+/// cursor of a full page is passed as `after` to read the following one.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document
+/// * `after` - Optional cursor returned with the previous page
+/// * `limit` - Optional maximum number of entries returned
+///
+/// # Returns
+///
+/// A `200 OK` response listing the entries, `404 Not Found` if the document
+/// does not exist, `403 Forbidden` if guests may not read it, or
+/// `503 Service Unavailable` if the audit trail is not enabled
+pub async fn get_document_audit<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: &str,
+    after: Option<u64>,
+    limit: Option<usize>,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(doc_id, None) {
+        return domain_error_response(&e);
+    }
+    if !document_service.document_exists(doc_id).await {
+        return not_found(doc_id);
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_AUDIT_PAGE_SIZE);
+    match document_service.list_audit_entries(doc_id, after.unwrap_or(0), limit) {
+        Ok(page) => json_response(
+            StatusCode::OK,
+            json!({ "doc_id": doc_id, "entries": page.entries, "next": page.next }),
+        ),
+        Err(e) => domain_error_response(&e),
+    }
+}
+
+/// Streams the changes of a document as Server-Sent Events.
+///
+/// The stream opens with a `sync` event carrying the full document state, then
+/// relays every update applied to the document as an `update` event, through
+/// the same broadcast channel the WebSocket connections are fed from. Updates
+/// are Base64-encoded Yjs v1 updates. A subscriber that falls behind and misses
+/// updates receives the full state again as a `resync` event. Server notices
+/// concerning the document are delivered as JSON `notice` events.
+///
+/// The stream is read-only and ends when the client disconnects.
+///
+/// # Arguments
+///
+/// * `document_service` - Domain document service owning the documents
+/// * `doc_id` - Identifier of the document to follow
+///
+/// # Returns
+///
+/// A `200 OK` event stream, `404 Not Found` if the document does not exist, or
+/// `403 Forbidden` if guests may not read it
+pub async fn document_events<R>(
+    document_service: Arc<DocumentService<R>>,
+    doc_id: String,
+) -> Response
+where
+    R: DocumentRepository + Send + Sync + 'static,
+{
+    if let Err(e) = document_service.authorize(&doc_id, None) {
+        return domain_error_response(&e);
+    }
+    if !document_service.document_exists(&doc_id).await {
+        return not_found(&doc_id);
+    }
+
+    let subscriber_id = Uuid::new_v4().to_string();
+    let mut hub = BroadcastHub::new(&subscriber_id);
+    let mut notices = document_service.subscribe_notices();
+    let (state, updates) = match document_service.sync_document(&doc_id, None).await {
+        Ok(sync) => sync,
+        Err(e) => return domain_error_response(&e),
+    };
+    hub.subscribe(&doc_id, updates);
+
+    let events = async_stream::stream! {
+        yield Ok::<_, Infallible>(binary_event(SYNC_EVENT, &state));
+
+        loop {
+            let event = tokio::select! {
+                event = hub.recv() => match event {
+                    HubEvent::Update { update, .. } => binary_event(UPDATE_EVENT, &update),
+                    HubEvent::Lagged { skipped, .. } => {
+                        warn!(
+                            "Event subscriber {} lagged by {} updates on document '{}', resyncing",
+                            subscriber_id, skipped, doc_id
+                        );
+                        match document_service.sync_document(&doc_id, None).await {
+                            Ok((state, _)) => binary_event(RESYNC_EVENT, &state),
+                            // The subscriber reconnects and syncs again from scratch
+                            Err(e) => {
+                                warn!("Failed to resync event subscriber {}: {}", subscriber_id, e);
+                                break;
+                            }
+                        }
+                    }
+                },
+                notice = notices.recv() => match notice {
+                    Ok(notice) if notice.is_addressed_to(|id| id == doc_id) => {
+                        match sonic_rs::to_string(&notice) {
+                            Ok(json) => Event::new().event(NOTICE_EVENT).data(json),
+                            Err(e) => {
+                                warn!("Failed to serialize notice: {}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Event subscriber {} missed {} notices", subscriber_id, skipped);
+                        continue;
+                    }
+                    // The document service is shutting down
+                    Err(RecvError::Closed) => break,
+                },
+            };
+
+            yield Ok(event);
+        }
+    };
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::new())
+        .into_response()
+}
+
+/// Builds an event carrying Base64-encoded binary data.
+fn binary_event(name: &'static str, data: &[u8]) -> Event {
+    Event::new()
+        .event(name)
+        .data(base64::engine::general_purpose::STANDARD.encode(data))
+}
+
+/// Returns a `403 Forbidden` response unless guests may write to the document.
+fn reject_writes<R: DocumentRepository>(
+    document_service: &DocumentService<R>,
+    doc_id: &str,
+) -> Option<Response> {
+    match document_service.access_role(doc_id, None) {
+        Ok(role) if role.can_write() => None,
+        Ok(_) => Some(error_response(
+            StatusCode::FORBIDDEN,
+            "Read-only clients may not modify this document",
+        )),
+        Err(e) => Some(domain_error_response(&e)),
+    }
+}
+
+/// Builds a response carrying a JSON body.
+fn json_response(status: StatusCode, body: sonic_rs::Value) -> Response {
+    let mut response =
+        ((header::CONTENT_TYPE, "application/json"), body.to_string()).into_response();
+    *response.status_mut() = status;
+    response
+}
+
+/// Builds an error response carrying a JSON `{"error": ...}` body.
+fn error_response(status: StatusCode, error: &str) -> Response {
+    json_response(status, json!({ "error": error }))
+}
+
+/// Returns the HTTP status reporting a domain error.
+///
+/// # Arguments
+///
+/// * `error` - The error returned by the domain
+///
+/// # Returns
+///
+/// The status code matching the error's kind
+pub fn error_status(error: &DomainError) -> StatusCode {
+    match error {
+        DomainError::NotFound(_) => StatusCode::NOT_FOUND,
+        DomainError::Conflict(_) => StatusCode::CONFLICT,
+        DomainError::InvalidUpdate(_) | DomainError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+        DomainError::Unauthorized(_) => StatusCode::FORBIDDEN,
+        DomainError::DocumentFrozen(_) => StatusCode::LOCKED,
+        DomainError::Moved { .. } => StatusCode::MISDIRECTED_REQUEST,
+        DomainError::LimitExceeded(_) | DomainError::PayloadTooLarge(_) => {
+            StatusCode::PAYLOAD_TOO_LARGE
+        }
+        DomainError::Unavailable(_) | DomainError::RoomFull(_) => StatusCode::SERVICE_UNAVAILABLE,
+        DomainError::StorageFailure(_) | DomainError::Internal(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Builds an error response reporting a domain error with its matching status.
+fn domain_error_response(error: &DomainError) -> Response {
+    error_response(error_status(error), &error.to_string())
+}
+
+/// Builds a `404 Not Found` response for a missing document.
+fn not_found(doc_id: &str) -> Response {
+    domain_error_response(&DomainError::NotFound(doc_id.to_string()))
+}
+
+/// Decodes the percent-encoded bytes of a path segment, keeping malformed
+/// escapes as they are.
+pub(crate) fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use tracing::warn;
+use volo::{Layer, Service};
+use volo_http::{
+    context::ServerContext,
+    http::{header, request::Parts, HeaderValue, Method, Request, StatusCode},
+    response::Response,
+    server::{extract::FromContext, IntoResponse},
+};
+
+/// Path prefix of the REST routes answering CORS requests.
+const API_PREFIX: &str = "/api/";
+
+/// Methods allowed on the REST routes, announced in preflight responses.
+const ALLOWED_METHODS: &str = "GET, POST, DELETE";
+
+/// Custom response headers of the REST routes browsers may read
+/// (`api::STATE_VECTOR_HEADER` and `api::VERSION_HEADER`).
+const EXPOSED_HEADERS: &str = "x-yjs-state-vector, x-yjs-version";
+
+/// Time in seconds browsers may cache a preflight response.
+const PREFLIGHT_MAX_AGE_SECS: &str = "600";
+
+/// Origins allowed to reach the server from a browser.
+///
+/// Browsers send the origin of the page in the `Origin` header of cross-origin
+/// requests and of every WebSocket upgrade. Requests without the header, like
+/// those of native apps and other servers, are never rejected. An empty
+/// allowlist leaves origins unchecked, and `*` allows any origin.
+#[derive(Clone, Debug, Default)]
+pub struct OriginPolicy {
+    /// Allowed origins, lowercased without a trailing slash
+    origins: Arc<Vec<String>>,
+}
+
+impl OriginPolicy {
+    /// Creates an origin policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `origins` - Allowed origins, e.g. `https://app.example.com`, or `*` for any origin
+    ///
+    /// # Returns
+    ///
+    /// A new `OriginPolicy` instance, unrestricted if `origins` is empty
+    pub fn new(origins: &[String]) -> Self {
+        Self {
+            origins: Arc::new(
+                origins
+                    .iter()
+                    .map(|origin| normalize(origin))
+                    .filter(|origin| !origin.is_empty())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns whether origins are checked at all.
+    pub fn is_restricted(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// Returns whether a browser page of the given origin may reach the server.
+    pub fn allows(&self, origin: &str) -> bool {
+        let origin = normalize(origin);
+        !self.is_restricted()
+            || self
+                .origins
+                .iter()
+                .any(|allowed| allowed == "*" || *allowed == origin)
+    }
+
+    /// Checks the origin of a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The origin the request was sent from
+    ///
+    /// # Returns
+    ///
+    /// `None` if the request may proceed, otherwise a `403 Forbidden` response
+    pub fn reject(&self, origin: &RequestOrigin) -> Option<Response> {
+        let origin = origin.0.as_deref()?;
+        if self.allows(origin) {
+            return None;
+        }
+
+        warn!("Rejecting request from origin '{}'", origin);
+        Some((StatusCode::FORBIDDEN, "Origin not allowed\n").into_response())
+    }
+}
+
+/// Lowercases an origin and strips its trailing slash, as origins are compared by value.
+fn normalize(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+/// Origin of a request, taken from its `Origin` header.
+pub struct RequestOrigin(pub Option<String>);
+
+impl FromContext for RequestOrigin {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        _cx: &mut ServerContext,
+        parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self(
+            parts
+                .headers
+                .get(header::ORIGIN)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        ))
+    }
+}
+
+/// Layer answering the CORS requests of the REST routes (`/api/...`).
+///
+/// While the origin policy is restricted, preflight requests from allowed
+/// origins are answered directly, responses to allowed origins carry the CORS
+/// headers, and requests from other origins are rejected with `403 Forbidden`.
+/// Other routes, and every route of an unrestricted policy, are left untouched.
+#[derive(Clone)]
+pub struct CorsLayer {
+    origins: OriginPolicy,
+}
+
+impl CorsLayer {
+    /// Creates a CORS layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `origins` - Origins allowed to call the REST routes from a browser
+    ///
+    /// # Returns
+    ///
+    /// A new `CorsLayer` instance
+    pub fn new(origins: OriginPolicy) -> Self {
+        Self { origins }
+    }
+}
+
+impl<S> Layer<S> for CorsLayer {
+    type Service = Cors<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        Cors {
+            inner,
+            origins: self.origins,
+        }
+    }
+}
+
+/// Service applying the CORS policy of a [`CorsLayer`] to an inner service.
+#[derive(Clone)]
+pub struct Cors<S> {
+    inner: S,
+    origins: OriginPolicy,
+}
+
+impl<S, B> Service<ServerContext, Request<B>> for Cors<S>
+where
+    S: Service<ServerContext, Request<B>> + Send + Sync,
+    S::Response: IntoResponse,
+    B: Send,
+{
+    type Response = Response;
+    type Error = S::Error;
+
+    async fn call(&self, cx: &mut ServerContext, req: Request<B>) -> Result<Response, S::Error> {
+        let origin = req.headers().get(header::ORIGIN).cloned();
+        let origin = match origin {
+            Some(origin)
+                if self.origins.is_restricted() && req.uri().path().starts_with(API_PREFIX) =>
+            {
+                origin
+            }
+            _ => {
+                return self
+                    .inner
+                    .call(cx, req)
+                    .await
+                    .map(IntoResponse::into_response)
+            }
+        };
+
+        let request_origin = RequestOrigin(origin.to_str().ok().map(str::to_string));
+        if let Some(rejection) = self.origins.reject(&request_origin) {
+            return Ok(rejection);
+        }
+
+        let preflight = req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+        if preflight {
+            let requested_headers = req
+                .headers()
+                .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+                .cloned();
+            let mut response = StatusCode::NO_CONTENT.into_response();
+            let headers = response.headers_mut();
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static(ALLOWED_METHODS),
+            );
+            if let Some(requested_headers) = requested_headers {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, requested_headers);
+            }
+            headers.insert(
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from_static(PREFLIGHT_MAX_AGE_SECS),
+            );
+            return Ok(with_allowed_origin(response, origin));
+        }
+
+        let mut response = self.inner.call(cx, req).await?.into_response();
+        response.headers_mut().insert(
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            HeaderValue::from_static(EXPOSED_HEADERS),
+        );
+        Ok(with_allowed_origin(response, origin))
+    }
+}
+
+/// Allows a response to be read by pages of the request's origin.
+fn with_allowed_origin(mut response: Response, origin: HeaderValue) -> Response {
+    let headers = response.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.append(header::VARY, HeaderValue::from_static("origin"));
+    response
+}
@@ -0,0 +1,223 @@
+"use strict";
+
+// Admin dashboard: polls the admin JSON routes and the Prometheus exposition of
+// `/metrics`, and drives the kick, close and promotion routes.
+
+const REFRESH_INTERVAL_MS = 2000;
+const THROUGHPUT_SAMPLES = 150;
+const TOKEN_KEY = "yjs-admin-token";
+
+const throughput = [];
+let lastUpdates = null;
+
+// The page is opened with `?token=<token>` when the admin listener requires one;
+// the token is kept for the session and removed from the address bar.
+function adminToken() {
+  const params = new URLSearchParams(window.location.search);
+  const token = params.get("token");
+  if (token !== null) {
+    sessionStorage.setItem(TOKEN_KEY, token);
+    window.history.replaceState(null, "", window.location.pathname);
+  }
+  return sessionStorage.getItem(TOKEN_KEY);
+}
+
+const token = adminToken();
+
+async function request(method, path) {
+  const headers = token ? { Authorization: `Bearer ${token}` } : {};
+  const response = await fetch(path, { method, headers });
+  if (!response.ok && response.status !== 404) {
+    throw new Error(`${method} ${path}: ${response.status} ${(await response.text()).trim()}`);
+  }
+  return response;
+}
+
+function showError(error) {
+  const element = document.getElementById("error");
+  element.hidden = error === null;
+  element.textContent = error === null ? "" : String(error.message || error);
+}
+
+function formatBytes(bytes) {
+  const units = ["B", "KiB", "MiB", "GiB"];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+function cell(row, text) {
+  const td = document.createElement("td");
+  td.textContent = text === null || text === undefined ? "" : String(text);
+  row.appendChild(td);
+  return td;
+}
+
+function actionCell(row, label, confirmation, method, path) {
+  const button = document.createElement("button");
+  button.className = "action";
+  button.textContent = label;
+  button.addEventListener("click", async () => {
+    if (!window.confirm(confirmation)) {
+      return;
+    }
+    try {
+      await request(method, path);
+      await refresh();
+    } catch (error) {
+      showError(error);
+    }
+  });
+  cell(row, "").appendChild(button);
+}
+
+function renderStatus(status) {
+  const role = document.getElementById("role");
+  role.textContent = status.role;
+  role.className = `badge ${status.role}`;
+  document.getElementById("promote").hidden = status.role !== "standby";
+  document.getElementById("loaded-documents").textContent = status.loaded_documents;
+  document.getElementById("active-connections").textContent = status.active_connections;
+}
+
+function renderDocuments(report) {
+  document.getElementById("sessions").textContent = report.sessions;
+  document.getElementById("total-bytes").textContent = formatBytes(report.total_bytes);
+
+  const body = document.getElementById("documents");
+  body.replaceChildren();
+  for (const doc of report.documents) {
+    const row = document.createElement("tr");
+    cell(row, doc.doc_id);
+    cell(row, formatBytes(doc.size_bytes));
+    cell(row, doc.subscribers);
+    cell(row, doc.clients);
+    cell(row, doc.sequence_number);
+    actionCell(
+      row,
+      "Close",
+      `Disconnect every client of '${doc.doc_id}' and unload it?`,
+      "POST",
+      `/admin/documents/close?doc=${encodeURIComponent(doc.doc_id)}`,
+    );
+    body.appendChild(row);
+  }
+}
+
+function renderSessions(report) {
+  const body = document.getElementById("session-list");
+  body.replaceChildren();
+  for (const session of report.sessions) {
+    const row = document.createElement("tr");
+    cell(row, session.doc_id);
+    cell(row, session.client_id);
+    cell(row, session.user_name || session.user_id || "guest");
+    cell(row, session.role);
+    cell(row, session.transport);
+    cell(row, session.remote_ip);
+    actionCell(
+      row,
+      "Kick",
+      `Disconnect client ${session.client_id} from '${session.doc_id}'?`,
+      "POST",
+      `/admin/documents/kick?doc=${encodeURIComponent(session.doc_id)}` +
+        `&client_id=${encodeURIComponent(session.client_id)}`,
+    );
+    body.appendChild(row);
+  }
+}
+
+// Sums the `apply_update` samples of the `<prefix>_crdt_operations_total` counter.
+function appliedUpdates(exposition) {
+  let total = null;
+  for (const line of exposition.split("\n")) {
+    if (line.startsWith("#") || !line.includes('operation="apply_update"')) {
+      continue;
+    }
+    const name = line.split("{", 1)[0];
+    if (name.endsWith("crdt_operations_total")) {
+      total = (total || 0) + Number(line.slice(line.lastIndexOf(" ") + 1));
+    }
+  }
+  return total;
+}
+
+function renderThroughput(updates) {
+  const now = Date.now();
+  if (lastUpdates !== null && updates !== null) {
+    const seconds = (now - lastUpdates.at) / 1000;
+    const rate = Math.max(0, (updates - lastUpdates.count) / seconds);
+    throughput.push(rate);
+    if (throughput.length > THROUGHPUT_SAMPLES) {
+      throughput.shift();
+    }
+    document.getElementById("throughput").textContent = rate.toFixed(1);
+  }
+  lastUpdates = updates === null ? null : { at: now, count: updates };
+
+  const canvas = document.getElementById("throughput-graph");
+  const context = canvas.getContext("2d");
+  context.clearRect(0, 0, canvas.width, canvas.height);
+  if (throughput.length < 2) {
+    return;
+  }
+
+  const peak = Math.max(1, ...throughput);
+  const step = canvas.width / (THROUGHPUT_SAMPLES - 1);
+  const offset = canvas.width - step * (throughput.length - 1);
+  context.beginPath();
+  throughput.forEach((rate, i) => {
+    const x = offset + i * step;
+    const y = canvas.height - 4 - (rate / peak) * (canvas.height - 24);
+    if (i === 0) {
+      context.moveTo(x, y);
+    } else {
+      context.lineTo(x, y);
+    }
+  });
+  context.strokeStyle = "#0969da";
+  context.lineWidth = 2;
+  context.stroke();
+  context.fillStyle = "#59636e";
+  context.fillText(`peak ${peak.toFixed(1)} updates/s`, 8, 14);
+}
+
+async function refresh() {
+  try {
+    const [status, documents, sessions, metrics] = await Promise.all([
+      request("GET", "/admin/status"),
+      request("GET", "/admin/documents"),
+      request("GET", "/admin/sessions"),
+      request("GET", "/metrics"),
+    ]);
+    renderStatus(await status.json());
+    renderDocuments(await documents.json());
+    renderSessions(await sessions.json());
+
+    const scraped = metrics.status !== 404;
+    document.getElementById("metrics-note").hidden = scraped;
+    renderThroughput(scraped ? appliedUpdates(await metrics.text()) : null);
+    showError(null);
+  } catch (error) {
+    showError(error);
+  }
+}
+
+document.getElementById("promote").addEventListener("click", async () => {
+  if (!window.confirm("Promote this standby to primary? It will stop following its primary.")) {
+    return;
+  }
+  try {
+    await request("POST", "/admin/standby/promote");
+    await refresh();
+  } catch (error) {
+    showError(error);
+  }
+});
+
+refresh();
+setInterval(refresh, REFRESH_INTERVAL_MS);
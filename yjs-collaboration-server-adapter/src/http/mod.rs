@@ -1,3 +1,5 @@
+pub mod admin;
+pub mod api;
+pub mod cors;
 pub mod router;
 pub mod websocket;
-
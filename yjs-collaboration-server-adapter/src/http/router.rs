@@ -1,10 +1,80 @@
-use std::sync::Arc;
+use std::{fmt, str::FromStr, sync::Arc};
 
-use volo_http::{server::route::get, Router};
-use yjs_collaboration_server_domain::repositories::document_repository::DocumentRepository;
-use yjs_collaboration_server_domain::services::document_service::DocumentService;
+use volo_http::{
+    server::{
+        extract::Query,
+        route::{delete, get, patch, post, put},
+        utils::ws::WebSocketUpgrade,
+    },
+    Router,
+};
+use yjs_collaboration_server_domain::{
+    repositories::document_repository::DocumentRepository,
+    services::document_service::DocumentService,
+};
 
-use crate::http::websocket::ws_handler::handle_websocket_upgrade;
+use crate::{
+    admission::AdmissionController,
+    broadcast_hub::EchoPolicy,
+    http::{
+        api::{
+            self, ActivityQuery, ArrayInsertQuery, AuditQuery, ContentType, DocumentPath,
+            ExportQuery, ImportQuery, KeyPath, MapQuery, RootPath, SearchQuery, StateQuery,
+            UserDocumentsQuery, UserPath, VersionPath, VersionQuery,
+        },
+        cors::{OriginPolicy, RequestOrigin},
+        websocket::ws_handler::{handle_websocket_upgrade, ConnectMetadata, WsProtocol},
+    },
+    session_registry::SessionRegistry,
+};
+
+/// A group of HTTP routes that can be enabled per listener.
+///
+/// Listeners only serve the route groups they are configured with, which allows
+/// for example an internal listener bound to localhost to expose routes that the
+/// public listener does not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RouteGroup {
+    /// Liveness and readiness endpoints (`/healthz`, `/readyz`)
+    Health,
+    /// Real-time collaboration WebSocket endpoints (`/ws`, `/ws/{doc_id}`,
+    /// `/ws/{tenant}/{doc_id}`)
+    Collaboration,
+    /// REST document endpoints (`/api/v1/documents`)
+    Api,
+}
+
+impl RouteGroup {
+    /// All route groups, served by listeners without an explicit route filter.
+    pub const ALL: &'static [RouteGroup] = &[
+        RouteGroup::Health,
+        RouteGroup::Collaboration,
+        RouteGroup::Api,
+    ];
+}
+
+impl fmt::Display for RouteGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Health => write!(f, "health"),
+            Self::Collaboration => write!(f, "collaboration"),
+            Self::Api => write!(f, "api"),
+        }
+    }
+}
+
+impl FromStr for RouteGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "health" => Ok(Self::Health),
+            "collaboration" => Ok(Self::Collaboration),
+            "api" => Ok(Self::Api),
+            _ => Err(format!("Unknown route group: {}", s)),
+        }
+    }
+}
 
 /// HTTP router configuration for the collaboration server.
 ///
@@ -12,11 +82,16 @@ use crate::http::websocket::ws_handler::handle_websocket_upgrade;
 /// integrating the domain services with the HTTP interface.
 ///
 /// It defines:
-/// - A health check endpoint to verify server status
+/// - Liveness and readiness endpoints, the latter checking the server's dependencies
 /// - A WebSocket endpoint for real-time collaboration
+/// - REST endpoints listing, creating, deleting and reading documents
+/// - A Server-Sent Events endpoint streaming a document's updates to read-only viewers
 pub struct HttpRouter<R: DocumentRepository> {
     // 直接使用domain层的DocumentService
     document_service: Arc<DocumentService<R>>,
+    admission: Arc<AdmissionController>,
+    sessions: Arc<SessionRegistry>,
+    origins: OriginPolicy,
 }
 
 impl<R: DocumentRepository + Send + Sync + 'static> HttpRouter<R> {
@@ -25,40 +100,352 @@ impl<R: DocumentRepository + Send + Sync + 'static> HttpRouter<R> {
     /// # Arguments
     ///
     /// * `document_service` - The domain document service to handle collaboration logic
+    /// * `admission` - Admission controller used to shed WebSocket connections during overload
+    /// * `sessions` - Registry WebSocket connections record their presence on documents in
     ///
     /// # Returns
     ///
     /// A new `HttpRouter` instance.
-    pub fn new(document_service: Arc<DocumentService<R>>) -> Self {
-        Self { document_service }
+    pub fn new(
+        document_service: Arc<DocumentService<R>>,
+        admission: Arc<AdmissionController>,
+        sessions: Arc<SessionRegistry>,
+    ) -> Self {
+        Self {
+            document_service,
+            admission,
+            sessions,
+            origins: OriginPolicy::default(),
+        }
     }
 
-    /// Health check handler that returns a simple status message.
+    /// Restricts the browser origins that may open WebSocket connections.
     ///
-    /// This endpoint can be used to verify that the server is running.
+    /// Upgrades carrying an `Origin` header the policy does not allow are
+    /// rejected with `403 Forbidden`; by default, every origin is allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `origins` - Origins allowed to connect from a browser
     ///
     /// # Returns
     ///
-    /// A static string confirming the server is operational.
-    async fn health_handler() -> &'static str {
-        "Yjs Collaboration Server Is Health\n"
+    /// The router, checking the origin of WebSocket upgrades
+    pub fn with_origins(mut self, origins: OriginPolicy) -> Self {
+        self.origins = origins;
+        self
     }
 
     /// Builds and configures the HTTP router with all necessary routes.
     ///
     /// This method sets up:
-    /// - A root route (`/`) for health checks
+    /// - Liveness (`/healthz`) and readiness (`/readyz`) routes for health checks
     /// - A WebSocket route (`/ws`) for real-time document collaboration
+    /// - REST routes (`/api/v1/documents`) for document management, statistics, export, import,
+    ///   state retrieval and activity
     ///
     /// # Returns
     ///
     /// A configured `Router` instance ready to be used by the HTTP server.
     pub fn build_router(&self) -> Router {
-        let document_service = self.document_service.clone();
+        self.build_router_for(RouteGroup::ALL)
+    }
+
+    /// Builds an HTTP router that only serves the given route groups.
+    ///
+    /// # Arguments
+    ///
+    /// * `groups` - The route groups to mount on the router
+    ///
+    /// # Returns
+    ///
+    /// A configured `Router` instance containing only the selected routes.
+    pub fn build_router_for(&self, groups: &[RouteGroup]) -> Router {
+        let mut router = Router::new();
+
+        if groups.contains(&RouteGroup::Health) {
+            let document_service = self.document_service.clone();
+            router = router.route("/healthz", get(api::liveness)).route(
+                "/readyz",
+                get(move || api::readiness(document_service.clone())),
+            );
+        }
+
+        if groups.contains(&RouteGroup::Collaboration) {
+            let document_service = self.document_service.clone();
+            let admission = self.admission.clone();
+            let sessions = self.sessions.clone();
+            let origins = self.origins.clone();
+
+            let ws = move |origin: RequestOrigin,
+                           protocol: WsProtocol,
+                           echo: EchoPolicy,
+                           metadata: ConnectMetadata,
+                           upgrade: WebSocketUpgrade| {
+                let rejection = origins.reject(&origin);
+                let document_service = document_service.clone();
+                let admission = admission.clone();
+                let sessions = sessions.clone();
+                async move {
+                    // Browsers let any page open WebSockets, so upgrades are checked here
+                    if let Some(rejection) = rejection {
+                        return rejection;
+                    }
+                    handle_websocket_upgrade(
+                        upgrade,
+                        protocol,
+                        echo,
+                        metadata,
+                        document_service,
+                        admission,
+                        sessions,
+                    )
+                    .await
+                }
+            };
+
+            // `/ws/{doc_id}` matches the URL layout of stock y-websocket providers, and
+            // `/ws/{tenant}/{doc_id}` that of providers given `/ws/{tenant}` as server URL
+            router = router
+                .route("/ws", get(ws.clone()))
+                .route("/ws/{doc_id}", get(ws.clone()))
+                .route("/ws/{tenant}/{doc_id}", get(ws));
+        }
+
+        if groups.contains(&RouteGroup::Api) {
+            let list_service = self.document_service.clone();
+            let create_service = self.document_service.clone();
+            let documents = get(move || api::list_documents(list_service.clone()))
+                .post(move |body: String| api::create_document(create_service.clone(), body));
+
+            let document_service = self.document_service.clone();
+            let document = delete(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = document_service.clone();
+                async move { api::delete_document(document_service, &doc_id).await }
+            });
+
+            let get_service = self.document_service.clone();
+            let patch_service = self.document_service.clone();
+            let meta = get(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = get_service.clone();
+                async move { api::get_document_metadata(document_service, &doc_id).await }
+            })
+            .patch(move |DocumentPath(doc_id): DocumentPath, body: String| {
+                let document_service = patch_service.clone();
+                async move { api::update_document_metadata(document_service, &doc_id, body).await }
+            });
+
+            let document_service = self.document_service.clone();
+            let content = get(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = document_service.clone();
+                async move { api::get_document_content(document_service, &doc_id).await }
+            });
+
+            let document_service = self.document_service.clone();
+            let stats = get(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = document_service.clone();
+                async move { api::get_document_stats(document_service, &doc_id).await }
+            });
+
+            let document_service = self.document_service.clone();
+            let export = get(
+                move |DocumentPath(doc_id): DocumentPath, Query(query): Query<ExportQuery>| {
+                    let document_service = document_service.clone();
+                    async move {
+                        api::export_document_content(document_service, &doc_id, query.format).await
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let import = post(
+                move |DocumentPath(doc_id): DocumentPath,
+                      Query(query): Query<ImportQuery>,
+                      ContentType(content_type): ContentType,
+                      body: String| {
+                    let document_service = document_service.clone();
+                    async move {
+                        api::import_document_content(
+                            document_service,
+                            &doc_id,
+                            query.format,
+                            query.root,
+                            content_type,
+                            body,
+                        )
+                        .await
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let state = get(
+                move |DocumentPath(doc_id): DocumentPath, Query(query): Query<StateQuery>| {
+                    let document_service = document_service.clone();
+                    async move {
+                        api::get_document_state(document_service, &doc_id, query.state_vector).await
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let activity = get(
+                move |DocumentPath(doc_id): DocumentPath, Query(query): Query<ActivityQuery>| {
+                    let document_service = document_service.clone();
+                    async move {
+                        api::get_document_activity(document_service, &doc_id, query.granularity)
+                            .await
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let sessions = self.sessions.clone();
+            let presence = get(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = document_service.clone();
+                let sessions = sessions.clone();
+                async move { api::get_presence_history(document_service, sessions, &doc_id).await }
+            });
+
+            let document_service = self.document_service.clone();
+            let audit = get(
+                move |DocumentPath(doc_id): DocumentPath, Query(query): Query<AuditQuery>| {
+                    let document_service = document_service.clone();
+                    async move {
+                        api::get_document_audit(document_service, &doc_id, query.after, query.limit)
+                            .await
+                    }
+                },
+            );
+
+            let list_service = self.document_service.clone();
+            let create_service = self.document_service.clone();
+            let versions = get(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = list_service.clone();
+                async move { api::list_document_versions(document_service, &doc_id).await }
+            })
+            .post(move |DocumentPath(doc_id): DocumentPath, body: String| {
+                let document_service = create_service.clone();
+                async move { api::create_document_version(document_service, &doc_id, body).await }
+            });
+
+            let document_service = self.document_service.clone();
+            let version = get(
+                move |DocumentPath(doc_id): DocumentPath,
+                      VersionPath(version): VersionPath,
+                      Query(query): Query<VersionQuery>| {
+                    let document_service = document_service.clone();
+                    async move {
+                        api::get_document_version(document_service, &doc_id, version, query.format)
+                            .await
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let revert = post(move |DocumentPath(doc_id): DocumentPath, body: String| {
+                let document_service = document_service.clone();
+                async move { api::revert_document(document_service, &doc_id, body).await }
+            });
+
+            let freeze_service = self.document_service.clone();
+            let thaw_service = self.document_service.clone();
+            let freeze = post(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = freeze_service.clone();
+                async move { api::freeze_document(document_service, &doc_id, true).await }
+            })
+            .delete(move |DocumentPath(doc_id): DocumentPath| {
+                let document_service = thaw_service.clone();
+                async move { api::freeze_document(document_service, &doc_id, false).await }
+            });
+
+            let put_service = self.document_service.clone();
+            let delete_service = self.document_service.clone();
+            let map_entry = put(
+                move |DocumentPath(doc_id): DocumentPath,
+                      KeyPath(key): KeyPath,
+                      Query(query): Query<MapQuery>,
+                      body: String| {
+                    let document_service = put_service.clone();
+                    async move {
+                        api::set_map_entry(document_service, &doc_id, query.root, &key, body).await
+                    }
+                },
+            )
+            .delete(
+                move |DocumentPath(doc_id): DocumentPath,
+                      KeyPath(key): KeyPath,
+                      Query(query): Query<MapQuery>| {
+                    let document_service = delete_service.clone();
+                    async move {
+                        api::remove_map_entry(document_service, &doc_id, query.root, &key).await
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let array_insert = post(
+                move |DocumentPath(doc_id): DocumentPath,
+                      RootPath(root): RootPath,
+                      Query(query): Query<ArrayInsertQuery>,
+                      body: String| {
+                    let document_service = document_service.clone();
+                    async move {
+                        api::insert_array_items(document_service, &doc_id, &root, query.index, body)
+                            .await
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let sessions = self.sessions.clone();
+            let user_documents = get(
+                move |UserPath(user_id): UserPath, Query(query): Query<UserDocumentsQuery>| {
+                    let document_service = document_service.clone();
+                    let sessions = sessions.clone();
+                    async move {
+                        let tenant = query.tenant.unwrap_or_default();
+                        api::get_user_documents(document_service, sessions, &user_id, &tenant)
+                    }
+                },
+            );
+
+            let document_service = self.document_service.clone();
+            let search = get(move |Query(query): Query<SearchQuery>| {
+                api::search_documents(document_service.clone(), query.q, query.limit)
+            });
+
+            let document_service = self.document_service.clone();
+            let events = get(move |DocumentPath(doc_id): DocumentPath| {
+                api::document_events(document_service.clone(), doc_id)
+            });
+
+            router = router
+                .route("/api/v1/documents", documents)
+                .route("/api/v1/documents/{doc_id}", document)
+                .route("/api/v1/documents/{doc_id}/meta", meta)
+                .route("/api/v1/documents/{doc_id}/content", content)
+                .route("/api/v1/documents/{doc_id}/stats", stats)
+                .route("/api/v1/documents/{doc_id}/export", export)
+                .route("/api/v1/documents/{doc_id}/import", import)
+                .route("/api/v1/documents/{doc_id}/state", state)
+                .route("/api/v1/documents/{doc_id}/activity", activity)
+                .route("/api/v1/documents/{doc_id}/presence/history", presence)
+                .route("/api/v1/documents/{doc_id}/audit", audit)
+                .route("/api/v1/documents/{doc_id}/versions", versions)
+                .route("/api/v1/documents/{doc_id}/versions/{version}", version)
+                .route("/api/v1/documents/{doc_id}/revert", revert)
+                .route("/api/v1/documents/{doc_id}/freeze", freeze)
+                .route("/api/v1/documents/{doc_id}/map/{key}", map_entry)
+                .route(
+                    "/api/v1/documents/{doc_id}/array/{name}/insert",
+                    array_insert,
+                )
+                .route("/api/v1/documents/{doc_id}/events", events)
+                .route("/api/v1/users/{user_id}/documents", user_documents)
+                .route("/api/v1/search", search);
+        }
 
-        Router::new().route("/", get(Self::health_handler)).route(
-            "/ws",
-            get(move |upgrade| handle_websocket_upgrade(upgrade, document_service.clone())),
-        )
+        router
     }
 }
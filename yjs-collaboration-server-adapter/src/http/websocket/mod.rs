@@ -1,2 +1,2 @@
+pub mod protobuf_codec;
 pub mod ws_handler;
-
@@ -0,0 +1,308 @@
+use base64::Engine;
+use sonic_rs::{json, JsonValueTrait, Value};
+use yjs_collaboration_server_common::volo_gen::{
+    collaboration::{
+        client_message, server_message, ClientMessage as ProtoClientMessage, CursorUpdate,
+        ErrorMessage, ErrorType, PayloadEncoding, ServerMessage as ProtoServerMessage,
+        Subdocuments, SyncRequired, SyncResponse as ProtoSyncResponse, UpdateMessage,
+        UpdateOriginKind, UserJoined, UserLeft,
+    },
+    pilota::{pb::Message as _, Bytes, LinkedBytes},
+};
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    services::document_service::SyncResponse,
+    value_objects::{
+        message::{ClientMessage, Notice, ServerMessage},
+        message_codec::{EncodedMessage, MessageCodec},
+    },
+};
+
+use crate::{clock::server_time, rpc::collaboration_service::proto_notice};
+
+/// Protobuf encoding, exchanging the `ClientMessage` and `ServerMessage` of the
+/// gRPC schema as binary frames.
+///
+/// Client messages are translated into the messages of the JSON protocol, so a
+/// protobuf connection behaves exactly like a JSON one:
+///
+/// - `SyncRequest` and `SyncStep1` request a sync response, the diff against their state vector
+/// - `UpdateMessage` and `SyncStep2` carry an update, `UpdateBatch` the updates of an offline
+///   session
+/// - `UndoRequest`, `SubdocumentsRequest`, `CursorUpdate` and `GapReport` mirror the `undo`/`redo`,
+///   `subdocs`, `cursor` and `gap` messages
+/// - `HeartBeat` only keeps the client present on the document
+///
+/// `JoinDocument`, `LeaveDocument` and `AwarenessUpdate` have no JSON
+/// counterpart and are rejected, as are compressed payloads. The sync response
+/// carries no state vector and, like the JSON one, names no document.
+pub struct ProtobufCodec;
+
+impl MessageCodec for ProtobufCodec {
+    fn encode(&self, message: &ServerMessage) -> DomainResult<EncodedMessage> {
+        let data = message.data.as_ref();
+        let doc_id = string_field(data, "doc_id");
+        let message_type = match message.message_type.as_str() {
+            "update" => {
+                let origin = data.and_then(|data| data.get("origin"));
+                server_message::MessageType::Update(UpdateMessage {
+                    update_data: decode_base64(message.update.as_deref().unwrap_or_default())?
+                        .into(),
+                    origin_client_id: string_field(origin, "client_id").into(),
+                    sequence_number: int_field(data, "sequence_number"),
+                    dictionary_id: 0,
+                    encoding: PayloadEncoding::ENCODING_IDENTITY,
+                    origin_user_id: string_field(origin, "user_id").into(),
+                    origin_kind: origin_kind(&string_field(origin, "kind")),
+                })
+            }
+            "sync_required" => server_message::MessageType::SyncRequired(SyncRequired {
+                sequence_number: int_field(data, "sequence_number"),
+            }),
+            "subdocs" => server_message::MessageType::Subdocuments(Subdocuments {
+                guids: data
+                    .and_then(|data| data.get("subdocs"))
+                    .and_then(|guids| guids.as_array())
+                    .map(|guids| {
+                        guids
+                            .iter()
+                            .filter_map(|guid| guid.as_str())
+                            .map(|guid| guid.to_string().into())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }),
+            "user_joined" => server_message::MessageType::UserJoined(UserJoined {
+                user_id: string_field(data, "user_id").into(),
+                user_name: string_field(data, "user_name").into(),
+                user_color: string_field(data, "user_color").into(),
+                client_id: string_field(data, "client_id").into(),
+                user_metadata: data
+                    .and_then(|data| data.get("metadata"))
+                    .and_then(|metadata| metadata.as_object())
+                    .map(|metadata| {
+                        metadata
+                            .iter()
+                            .filter_map(|(key, value)| Some((key, value.as_str()?)))
+                            .map(|(key, value)| (key.to_string().into(), value.to_string().into()))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }),
+            "user_left" => server_message::MessageType::UserLeft(UserLeft {
+                user_id: string_field(data, "user_id").into(),
+                client_id: string_field(data, "client_id").into(),
+            }),
+            "cursor" => server_message::MessageType::Cursor(CursorUpdate {
+                client_id: string_field(data, "client_id").into(),
+                anchor: decode_base64(&string_field(data, "anchor"))?.into(),
+                head: decode_base64(&string_field(data, "head"))?.into(),
+                user_id: string_field(data, "user_id").into(),
+                user_name: string_field(data, "user_name").into(),
+                user_color: string_field(data, "user_color").into(),
+                timestamp: int_field(data, "at"),
+            }),
+            "notice" => {
+                let notice: Notice = data
+                    .ok_or_else(|| "the notice is missing".to_string())
+                    .and_then(|data| sonic_rs::to_string(data).map_err(|e| e.to_string()))
+                    .and_then(|data| sonic_rs::from_str(&data).map_err(|e| e.to_string()))
+                    .map_err(|e| DomainError::Internal(format!("Invalid notice: {}", e)))?;
+                server_message::MessageType::Notice(proto_notice(&notice))
+            }
+            "error" => {
+                let (error_code, error_type) = error_type(&string_field(data, "error_type"));
+                server_message::MessageType::Error(ErrorMessage {
+                    error_code,
+                    error_message: string_field(data, "message").into(),
+                    error_type,
+                    document_id: doc_id.clone().into(),
+                })
+            }
+            other => {
+                return Err(DomainError::Internal(format!(
+                    "Message type '{}' has no protobuf counterpart",
+                    other
+                )))
+            }
+        };
+
+        encode_message(&doc_id, message_type)
+    }
+
+    fn encode_sync_response(&self, response: &SyncResponse) -> DomainResult<EncodedMessage> {
+        encode_message(
+            "",
+            server_message::MessageType::SyncResponse(ProtoSyncResponse {
+                update_data: response.update.clone().unwrap_or_default().into(),
+                sequence_number: response.sequence_number as i64,
+                encoding: PayloadEncoding::ENCODING_IDENTITY,
+            }),
+        )
+    }
+
+    fn decode(&self, frame: &[u8]) -> DomainResult<ClientMessage> {
+        let message = ProtoClientMessage::decode(Bytes::copy_from_slice(frame)).map_err(|e| {
+            DomainError::InvalidArgument(format!("Invalid protobuf message: {}", e))
+        })?;
+        let engine = &base64::engine::general_purpose::STANDARD;
+
+        let (message_type, data, update) = match message.message_type {
+            Some(client_message::MessageType::SyncRequest(request)) => {
+                let state_vector = Some(&request.state_vector).filter(|sv| !sv.is_empty());
+                ("sync", None, state_vector.map(|sv| engine.encode(sv)))
+            }
+            Some(client_message::MessageType::SyncStep1(step)) => {
+                let state_vector = Some(&step.state_vector).filter(|sv| !sv.is_empty());
+                ("sync", None, state_vector.map(|sv| engine.encode(sv)))
+            }
+            Some(client_message::MessageType::Update(update)) => {
+                check_identity(update.encoding)?;
+                ("update", None, Some(engine.encode(&update.update_data)))
+            }
+            Some(client_message::MessageType::SyncStep2(step)) => {
+                check_identity(step.encoding)?;
+                ("update", None, Some(engine.encode(&step.update_data)))
+            }
+            Some(client_message::MessageType::UpdateBatch(batch)) => {
+                check_identity(batch.encoding)?;
+                let updates: Vec<String> = batch
+                    .updates
+                    .iter()
+                    .map(|update| engine.encode(update))
+                    .collect();
+                ("update_batch", Some(json!({ "updates": updates })), None)
+            }
+            Some(client_message::MessageType::Undo(undo)) => {
+                (if undo.redo { "redo" } else { "undo" }, None, None)
+            }
+            Some(client_message::MessageType::SubdocumentsRequest(_)) => ("subdocs", None, None),
+            Some(client_message::MessageType::Cursor(cursor)) => {
+                let position = if cursor.anchor.is_empty() && cursor.head.is_empty() {
+                    None
+                } else {
+                    Some(json!({
+                        "anchor": engine.encode(&cursor.anchor),
+                        "head": engine.encode(&cursor.head),
+                    }))
+                };
+                ("cursor", position, None)
+            }
+            Some(client_message::MessageType::GapReport(gap)) => (
+                "gap",
+                Some(json!({ "last_sequence_number": gap.last_sequence_number })),
+                None,
+            ),
+            Some(client_message::MessageType::Heartbeat(_)) => ("heartbeat", None, None),
+            Some(
+                client_message::MessageType::JoinDocument(_)
+                | client_message::MessageType::LeaveDocument(_)
+                | client_message::MessageType::Awareness(_),
+            ) => {
+                return Err(DomainError::InvalidArgument(
+                    "Join, leave and awareness messages are only supported over gRPC".to_string(),
+                ))
+            }
+            None => {
+                return Err(DomainError::InvalidArgument(
+                    "Protobuf message carries no payload".to_string(),
+                ))
+            }
+        };
+
+        Ok(ClientMessage {
+            doc_id: message.document_id.to_string(),
+            message_type: message_type.to_string(),
+            data,
+            update,
+        })
+    }
+}
+
+/// Encodes a server message of a document, stamped with the server time.
+fn encode_message(
+    doc_id: &str,
+    message_type: server_message::MessageType,
+) -> DomainResult<EncodedMessage> {
+    let message = ProtoServerMessage {
+        document_id: doc_id.to_string().into(),
+        timestamp: server_time(),
+        message_type: Some(message_type),
+        clock_offset: 0,
+    };
+
+    let mut buffer = LinkedBytes::with_capacity(message.encoded_len());
+    message
+        .encode(&mut buffer)
+        .map_err(|e| DomainError::Internal(format!("Failed to encode message: {}", e)))?;
+    Ok(EncodedMessage::Binary(buffer.concat().to_vec()))
+}
+
+/// Rejects the payloads compressed by the client, which only gRPC decompresses.
+fn check_identity(encoding: PayloadEncoding) -> DomainResult<()> {
+    if encoding == PayloadEncoding::ENCODING_IDENTITY {
+        Ok(())
+    } else {
+        Err(DomainError::InvalidArgument(
+            "Compressed payloads are only supported over gRPC".to_string(),
+        ))
+    }
+}
+
+/// Converts a JSON error type, named like the gRPC `ErrorType` values, and the
+/// matching HTTP status used as the error code.
+///
+/// # Arguments
+///
+/// * `name` - The error type of the JSON protocol
+///
+/// # Returns
+///
+/// The error code and type; types without a gRPC counterpart are unknown errors
+fn error_type(name: &str) -> (i32, ErrorType) {
+    match name {
+        "AUTHORIZATION_ERROR" => (403, ErrorType::AUTHORIZATION_ERROR),
+        "PERMISSION_DENIED" => (403, ErrorType::PERMISSION_DENIED),
+        "DOCUMENT_NOT_FOUND" => (404, ErrorType::DOCUMENT_NOT_FOUND),
+        "DOCUMENT_FROZEN" => (423, ErrorType::DOCUMENT_FROZEN),
+        "DOCUMENT_MOVED" => (421, ErrorType::DOCUMENT_MOVED),
+        "INVALID_CURSOR" => (400, ErrorType::INVALID_ARGUMENT),
+        "PAYLOAD_TOO_LARGE" => (413, ErrorType::PAYLOAD_TOO_LARGE),
+        "RATE_LIMIT_EXCEEDED" => (429, ErrorType::RATE_LIMIT_EXCEEDED),
+        "ROOM_FULL" => (503, ErrorType::ROOM_FULL),
+        "UNDO_UNAVAILABLE" => (503, ErrorType::UNKNOWN_ERROR),
+        "INTERNAL_ERROR" => (500, ErrorType::INTERNAL_ERROR),
+        _ => (500, ErrorType::UNKNOWN_ERROR),
+    }
+}
+
+/// Converts the kind of an update's origin, named like `OriginKind` values.
+fn origin_kind(name: &str) -> UpdateOriginKind {
+    match name {
+        "remote" => UpdateOriginKind::ORIGIN_REMOTE,
+        "server" => UpdateOriginKind::ORIGIN_SERVER,
+        _ => UpdateOriginKind::ORIGIN_LOCAL,
+    }
+}
+
+/// Reads a string field of a message's data, empty if it is missing or null.
+fn string_field(data: Option<&Value>, name: &str) -> String {
+    data.and_then(|data| data.get(name))
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Reads an integer field of a message's data, `0` if it is missing.
+fn int_field(data: Option<&Value>, name: &str) -> i64 {
+    data.and_then(|data| data.get(name))
+        .and_then(|value| value.as_i64())
+        .unwrap_or_default()
+}
+
+/// Decodes a Base64 payload of a message sent by the server.
+fn decode_base64(encoded: &str) -> DomainResult<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| DomainError::Internal(format!("Invalid Base64 payload: {}", e)))
+}
@@ -1,47 +1,779 @@
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, future::Future, net::IpAddr, pin::Pin, sync::Arc};
 
 use base64::Engine;
 use futures_util::{sink::SinkExt, stream::StreamExt};
-use sonic_rs::{from_str, to_string};
-use tracing::{info, warn};
+use sonic_rs::{json, JsonValueTrait, Value};
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{Instant, Interval, MissedTickBehavior},
+};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
+use volo::{context::Context, net::Address};
 use volo_http::{
+    context::ServerContext,
+    http::{header, request::Parts, StatusCode},
     response::Response,
-    server::utils::ws::{Message, WebSocket, WebSocketUpgrade},
+    server::{
+        extract::FromContext,
+        utils::ws::{Message, WebSocket, WebSocketUpgrade},
+        IntoResponse,
+    },
 };
 use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
     repositories::document_repository::DocumentRepository,
-    services::document_service::DocumentService,
-    value_objects::message::ClientMessage,
+    services::document_service::{DocumentService, SyncResponse},
+    value_objects::{
+        access_role::AccessRole,
+        cursor::CursorPosition,
+        diff_throttle::DiffLimiter,
+        message::{ClientMessage, Notice, NoticeKind, NoticeSeverity, ServerMessage},
+        message_codec::{EncodedMessage, MessageCodec, MessageEncoding},
+        sync_protocol::SyncProtocolMessage,
+        tenant::{scoped_document_id, unscoped_document_id},
+        undo_action::UndoAction,
+        update_encoding::UpdateEncoding,
+        update_frame::{decode_update_frame, encode_sync_frame, encode_update_frame},
+        update_origin::OriginKind,
+    },
+};
+
+use crate::{
+    admission::{AdmissionController, AdmissionRejection, LoadSignals},
+    broadcast_hub::{BroadcastHub, EchoPolicy, HubEvent},
+    delivery_stats::DropReason,
+    http::{
+        api::{error_status, percent_decode},
+        websocket::protobuf_codec::ProtobufCodec,
+    },
+    payload_compression::PayloadCompression,
+    session_registry::{
+        check_metadata, CursorEvent, KeepAlive, PresenceEvent, Session, SessionRegistry, Transport,
+    },
 };
 
+/// Subprotocol offered by clients speaking the binary Yjs sync protocol.
+pub const Y_WEBSOCKET_PROTOCOL: &str = "y-websocket";
+
+/// Subprotocol offered by clients speaking the JSON protocol.
+pub const Y_JSON_PROTOCOL: &str = "yjs-json";
+
+/// Subprotocol offered by clients speaking the JSON protocol encoded as MessagePack.
+pub const Y_MSGPACK_PROTOCOL: &str = "yjs-msgpack";
+
+/// Subprotocol offered by clients speaking the JSON protocol in its compact binary encoding.
+pub const Y_BINARY_MESSAGES_PROTOCOL: &str = "yjs-binary";
+
+/// Subprotocol offered by clients exchanging the protobuf messages of the gRPC schema.
+pub const Y_PROTOBUF_PROTOCOL: &str = "yjs-protobuf";
+
+/// Error sent to read-only clients whose updates are rejected.
+const READ_ONLY_ERROR: &str = "Read-only clients may not update this document";
+
+/// Wire protocol spoken on a WebSocket connection.
+///
+/// The protocol is negotiated with the `format=json|binary` query flag or, when
+/// the flag is absent, by offering the `y-websocket` or `yjs-json` subprotocol.
+/// Legacy clients negotiating neither are served the JSON protocol, so existing
+/// deployments keep working while their clients are upgraded.
+///
+/// The messages of the JSON protocol are JSON text frames by default; clients
+/// may negotiate another encoding of the same messages with the
+/// `encoding=json|msgpack|binary|protobuf` query flag or by offering the
+/// `yjs-msgpack`, `yjs-binary` or `yjs-protobuf` subprotocol. The protobuf
+/// encoding exchanges the `ClientMessage` and `ServerMessage` of the gRPC
+/// schema, translated to and from the JSON messages.
+///
+/// JSON text clients bound to a document may keep every other message JSON
+/// while exchanging the document's updates as raw binary update frames,
+/// negotiated with the `updates=binary` query flag.
+///
+/// JSON clients may exchange their updates in the Yjs v2 encoding, negotiated
+/// with the `update_encoding=v2` query flag; the server converts them to and
+/// from the v1 updates it stores. The binary protocol always exchanges v1
+/// updates, as `y-protocols` does.
+///
+/// Binary connections are bound to a single document, named by the
+/// `/ws/{doc_id}` path (the URL layout used by stock `y-websocket` providers) or
+/// by the `doc` query parameter. JSON connections may be bound the same way, in
+/// which case messages may omit their `doc_id`. The document of a tenant is
+/// named by the `/ws/{tenant}/{doc_id}` path or the `tenant` query parameter,
+/// and bound to under its scoped identifier `{tenant}/{doc_id}`; the messages
+/// of a JSON connection bound to a tenant's document may only name the
+/// tenant's documents.
+///
+/// Binary clients may negotiate the dictionary compression of the updates
+/// relayed to them with the `compression=dictionary` query flag, and the
+/// delivery of server notices as custom frames with the `notices=frames` one.
+///
+/// Both protocols are translated to and from the same Yjs v1 updates and share
+/// each document's broadcast channel, so JSON and binary clients of a document
+/// stay in sync with each other.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WsProtocol {
+    /// Custom JSON messages with Base64-encoded payloads, naming the document per message
+    /// unless the connection is bound to one, in the negotiated frames
+    Json {
+        binding: JsonBinding,
+        frames: JsonFrames,
+    },
+    /// Official Yjs sync protocol (y-protocols/sync) for the given document, with relayed
+    /// updates compressed with the document's dictionary and notices delivered if negotiated
+    Binary {
+        doc_id: String,
+        compression: bool,
+        notices: bool,
+    },
+}
+
+impl WsProtocol {
+    /// Returns the subprotocol confirmed to clients speaking the protocol.
+    pub fn subprotocol(&self) -> &'static str {
+        match self {
+            Self::Json { frames, .. } => match frames.encoding {
+                MessageEncoding::Json => Y_JSON_PROTOCOL,
+                MessageEncoding::MessagePack => Y_MSGPACK_PROTOCOL,
+                MessageEncoding::Binary => Y_BINARY_MESSAGES_PROTOCOL,
+                MessageEncoding::Protobuf => Y_PROTOBUF_PROTOCOL,
+            },
+            Self::Binary { .. } => Y_WEBSOCKET_PROTOCOL,
+        }
+    }
+}
+
+impl FromContext for WsProtocol {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        cx: &mut ServerContext,
+        parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+
+        let offers = |subprotocol: &str| {
+            parts
+                .headers
+                .get(header::SEC_WEBSOCKET_PROTOCOL)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| {
+                    value
+                        .split(',')
+                        .any(|protocol| protocol.trim() == subprotocol)
+                })
+        };
+
+        // The encoding flag takes precedence over the offered subprotocols
+        let encoding = match query_param(query, "encoding") {
+            Some(encoding) => Some(encoding.parse().map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "The encoding must be json, msgpack, binary or protobuf\n",
+                )
+            })?),
+            None if offers(Y_MSGPACK_PROTOCOL) => Some(MessageEncoding::MessagePack),
+            None if offers(Y_BINARY_MESSAGES_PROTOCOL) => Some(MessageEncoding::Binary),
+            None if offers(Y_PROTOBUF_PROTOCOL) => Some(MessageEncoding::Protobuf),
+            None => None,
+        };
+
+        // The format flag takes precedence over the offered subprotocols
+        let binary = match query_param(query, "format") {
+            Some("binary") => true,
+            Some("json") => false,
+            Some(_) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "The format must be json or binary\n",
+                ))
+            }
+            None if encoding.is_none() && offers(Y_WEBSOCKET_PROTOCOL) => true,
+            None => {
+                if encoding.is_none() && !offers(Y_JSON_PROTOCOL) {
+                    debug!("Client negotiated no protocol, serving the legacy JSON protocol");
+                }
+                false
+            }
+        };
+
+        // Path parameters take precedence over the query parameters
+        let param = |path_name: &str, query_name: &str| {
+            cx.params()
+                .iter()
+                .find(|(key, _)| key == path_name)
+                .map(|(_, value)| value.to_string())
+                .or_else(|| query_param(query, query_name).map(str::to_string))
+                .filter(|value| !value.is_empty())
+        };
+        // A tenant scopes the document, e.g. `/ws/acme/roadmap` to `acme/roadmap`
+        let tenant = param("tenant", "tenant");
+        let doc_id = match (tenant.clone(), param("doc_id", "doc")) {
+            (Some(tenant), Some(doc_id)) => {
+                Some(scoped_document_id(&tenant, &doc_id).map_err(|_| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        "The tenant may not contain '/' or '#'\n",
+                    )
+                })?)
+            }
+            (Some(_), None) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "A tenant requires a document id (/ws/{tenant}/{doc_id} or ?doc=)\n",
+                ))
+            }
+            (None, doc_id) => doc_id,
+        };
+
+        let compression = match query_param(query, "compression") {
+            Some("dictionary") => true,
+            Some("none") | None => false,
+            Some(_) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "The compression must be dictionary or none\n",
+                ))
+            }
+        };
+
+        let notices = match query_param(query, "notices") {
+            Some("frames") => true,
+            Some("none") | None => false,
+            Some(_) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "The notices must be frames or none\n",
+                ))
+            }
+        };
+
+        let binary_updates = match query_param(query, "updates") {
+            Some("binary") => true,
+            Some("base64") | None => false,
+            Some(_) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "The updates must be binary or base64\n",
+                ))
+            }
+        };
+
+        let update_encoding = match query_param(query, "update_encoding") {
+            Some(encoding) => encoding.parse().map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "The update encoding must be v1 or v2\n",
+                )
+            })?,
+            None => UpdateEncoding::default(),
+        };
+
+        if !binary {
+            if compression {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Dictionary compression only applies to the binary protocol\n",
+                ));
+            }
+            // JSON clients always receive notices, as `notice` messages
+            if query_param(query, "notices").is_some() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Notice frames only apply to the binary protocol\n",
+                ));
+            }
+            let encoding = encoding.unwrap_or_default();
+            if binary_updates && encoding != MessageEncoding::Json {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Binary update frames only apply to JSON text messages\n",
+                ));
+            }
+            if binary_updates && doc_id.is_none() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Binary update frames require a document id (/ws/{doc_id} or ?doc=)\n",
+                ));
+            }
+            return Ok(Self::Json {
+                binding: JsonBinding { doc_id, tenant },
+                frames: JsonFrames {
+                    encoding,
+                    binary_updates,
+                    update_encoding,
+                },
+            });
+        }
+        if update_encoding != UpdateEncoding::V1 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "The binary protocol only exchanges v1 updates\n",
+            ));
+        }
+        if binary_updates {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Binary update frames only apply to the JSON protocol\n",
+            ));
+        }
+        if query_param(query, "encoding").is_some() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "The encoding only applies to the JSON protocol\n",
+            ));
+        }
+
+        match doc_id {
+            Some(doc_id) => Ok(Self::Binary {
+                doc_id,
+                compression,
+                notices,
+            }),
+            None => Err((
+                StatusCode::BAD_REQUEST,
+                "Binary protocol requires a document id (/ws/{doc_id} or ?doc=)\n",
+            )),
+        }
+    }
+}
+
+/// Document a JSON protocol connection is bound to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JsonBinding {
+    /// The document messages omitting their `doc_id` relate to, if any
+    pub doc_id: Option<String>,
+    /// The tenant whose documents alone the messages may name, if any
+    pub tenant: Option<String>,
+}
+
+/// Frames exchanged with a JSON protocol client.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JsonFrames {
+    /// Encoding of the messages
+    pub encoding: MessageEncoding,
+    /// Whether the updates of the bound document are exchanged as binary update
+    /// frames (see `UpdateFrameType`) instead of Base64 `update` messages
+    pub binary_updates: bool,
+    /// Encoding of the updates exchanged, converted to and from the stored v1 updates
+    pub update_encoding: UpdateEncoding,
+}
+
+/// Echo policy negotiated with the `echo` query flag.
+///
+/// `echo=true` makes the connection receive its own updates back, as a
+/// confirmation that the server applied them; by default they are skipped.
+impl FromContext for EchoPolicy {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_context(
+        _cx: &mut ServerContext,
+        parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        match query_param(parts.uri.query().unwrap_or_default(), "echo") {
+            Some(echo) => echo.parse().map(Self::from_flag).map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "The echo flag must be true or false\n",
+                )
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// Session metadata attached with `meta.<key>=<value>` query parameters, and
+/// the address the client connects from.
+///
+/// Keys and values are percent-decoded, e.g. `?meta.device=ios&meta.app_version=2.4.1`,
+/// and relayed as is to the other clients of the documents the connection
+/// synchronizes with. Metadata exceeding the session limits is rejected with
+/// `400 Bad Request`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectMetadata {
+    /// Metadata the client attached to its session
+    pub user_metadata: HashMap<String, String>,
+    /// IP address of the peer, which is the proxy's behind a reverse proxy
+    pub remote_ip: Option<IpAddr>,
+    /// Path and query string the client connected to, kept to redirect it to another instance
+    pub path_and_query: String,
+}
+
+impl ConnectMetadata {
+    /// Builds the session of a guest connection with this metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Unique identifier of the connection
+    /// * `doc_id` - The document the connection is bound to, or empty for none
+    ///
+    /// # Returns
+    ///
+    /// A guest `Session` carrying the metadata and remote address
+    fn session(self, client_id: &str, doc_id: &str) -> Session {
+        Session {
+            user_metadata: self.user_metadata,
+            remote_ip: self.remote_ip,
+            ..Session::guest(client_id, doc_id, Transport::WebSocket)
+        }
+    }
+}
+
+impl FromContext for ConnectMetadata {
+    type Rejection = (StatusCode, String);
+
+    async fn from_context(
+        cx: &mut ServerContext,
+        parts: &mut Parts,
+    ) -> Result<Self, Self::Rejection> {
+        let metadata = parts
+            .uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .filter_map(|(key, value)| {
+                let key = percent_decode(key.strip_prefix("meta.")?);
+                (!key.is_empty()).then(|| (key, percent_decode(value)))
+            })
+            .collect();
+
+        check_metadata(&metadata).map_err(|e| (StatusCode::BAD_REQUEST, format!("{}\n", e)))?;
+
+        let remote_ip = match cx.rpc_info().caller().address() {
+            Some(Address::Ip(addr)) => Some(addr.ip()),
+            _ => None,
+        };
+        Ok(Self {
+            user_metadata: metadata,
+            remote_ip,
+            path_and_query: parts
+                .uri
+                .path_and_query()
+                .map(|path_and_query| path_and_query.as_str().to_string())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Returns the value of a query string parameter.
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
 /// Handles WebSocket upgrade requests from the routing system.
 ///
 /// This standalone function serves as an entry point for WebSocket connections
 /// in the HTTP router. It upgrades HTTP connections to WebSocket protocol and
 /// delegates the connection handling to the `WebSocketHandler`.
 ///
+/// Connections are checked by the admission controller before upgrading; when the
+/// server is overloaded the request is answered with `503 Service Unavailable` and a
+/// `Retry-After` header instead. Binary connections to a document owned by another
+/// instance of the cluster are answered with `307 Temporary Redirect` to the owner.
+///
 /// # Arguments
 ///
 /// * `ws` - The WebSocket upgrade request
+/// * `protocol` - The negotiated wire protocol
+/// * `echo` - Whether the connection receives its own updates back
+/// * `metadata` - Metadata the client attached to its session
 /// * `document_service` - Domain document service for collaboration operations
+/// * `admission` - Admission controller used to shed load during overload
+/// * `sessions` - Registry the connection's presence on documents is recorded in
 ///
 /// # Returns
 ///
 /// A response that upgrades the connection to WebSocket protocol
 pub async fn handle_websocket_upgrade<R>(
     ws: WebSocketUpgrade,
+    protocol: WsProtocol,
+    echo: EchoPolicy,
+    metadata: ConnectMetadata,
     document_service: Arc<DocumentService<R>>,
+    admission: Arc<AdmissionController>,
+    sessions: Arc<SessionRegistry>,
 ) -> Response
 where
     R: DocumentRepository + Send + Sync + 'static,
 {
-    ws.on_upgrade(move |socket| {
-        Box::pin(WebSocketHandler::<R>::handle_socket(
-            socket,
-            document_service,
-        )) as Pin<Box<dyn Future<Output = ()> + Send>>
-    })
+    let signals = LoadSignals {
+        loaded_documents: document_service.loaded_document_count(),
+        queue_depth: sessions.delivery_stats().queue_depth(),
+    };
+
+    // WebSocket connections carry no user identity, so they are served as guests.
+    // JSON connections name the document per message and are checked per message.
+    let role = match &protocol {
+        WsProtocol::Binary { doc_id, .. } => {
+            let role = match document_service.access_role(doc_id, None) {
+                Ok(role) => role,
+                Err(e) => {
+                    warn!("Rejecting WebSocket connection to '{}': {}", doc_id, e);
+                    return rejection_response(&e, &metadata);
+                }
+            };
+            // A subdocument is synchronized over a connection of its own, once
+            // its parent references it
+            let checked = match document_service.check_subdocument(doc_id).await {
+                Ok(()) => sessions.check_room(doc_id),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = checked {
+                warn!("Rejecting WebSocket connection to '{}': {}", doc_id, e);
+                return (error_status(&e), e.to_string()).into_response();
+            }
+            role
+        }
+        WsProtocol::Json { .. } => AccessRole::default(),
+    };
+
+    let permit = match admission.try_admit(signals) {
+        Ok(permit) => permit,
+        Err(rejection) => {
+            warn!("Rejecting WebSocket connection: {}", rejection);
+            return overloaded_response(&rejection);
+        }
+    };
+
+    ws.protocols([protocol.subprotocol()])
+        .on_upgrade(move |socket| {
+            Box::pin(async move {
+                // Hold the permit until the connection terminates
+                let _permit = permit;
+                // Unique client ID of the connection
+                let client_id = Uuid::new_v4().to_string();
+                match protocol {
+                    WsProtocol::Json { binding, frames } => {
+                        WebSocketHandler::<R>::handle_socket(
+                            socket,
+                            document_service,
+                            sessions,
+                            binding,
+                            frames,
+                            echo,
+                            metadata.session(&client_id, ""),
+                        )
+                        .await
+                    }
+                    WsProtocol::Binary {
+                        doc_id,
+                        compression,
+                        notices,
+                    } => {
+                        let session = Session {
+                            role,
+                            ..metadata.session(&client_id, &doc_id)
+                        };
+                        WebSocketHandler::<R>::handle_binary_socket(
+                            socket,
+                            document_service,
+                            sessions,
+                            session,
+                            echo,
+                            PayloadCompression::from_flag(compression),
+                            notices,
+                        )
+                        .await
+                    }
+                }
+            }) as Pin<Box<dyn Future<Output = ()> + Send>>
+        })
+}
+
+/// Builds the response rejecting a connection to a document.
+///
+/// # Arguments
+///
+/// * `error` - Why the connection is rejected
+/// * `metadata` - Metadata of the connection, naming the URL it requested
+///
+/// # Returns
+///
+/// A `307 Temporary Redirect` to the same URL on the owner of the document if it is owned
+/// by another instance, or the status matching the error otherwise
+fn rejection_response(error: &DomainError, metadata: &ConnectMetadata) -> Response {
+    let DomainError::Moved { owner, .. } = error else {
+        return (error_status(error), error.to_string()).into_response();
+    };
+
+    let location = format!(
+        "{}{}",
+        owner.http_url.trim_end_matches('/'),
+        metadata.path_and_query
+    );
+    let mut response = ([(header::LOCATION, location)], error.to_string()).into_response();
+    *response.status_mut() = StatusCode::TEMPORARY_REDIRECT;
+    response
+}
+
+/// Builds the `503 Service Unavailable` response returned to rejected clients.
+fn overloaded_response(rejection: &AdmissionRejection) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        (
+            (
+                header::RETRY_AFTER,
+                rejection.retry_after.as_secs().to_string(),
+            ),
+            rejection.to_string(),
+        ),
+    )
+        .into_response()
+}
+
+/// WebSocket connection exchanging messages in the encoding negotiated by its client.
+struct MessageSocket {
+    socket: WebSocket,
+    codec: &'static dyn MessageCodec,
+    /// The document whose updates are exchanged as binary update frames, if negotiated
+    framed_doc: Option<String>,
+    /// Encoding of the updates exchanged with the client
+    update_encoding: UpdateEncoding,
+}
+
+impl MessageSocket {
+    /// Returns whether the updates of a document are exchanged as binary update frames.
+    fn frames_updates_of(&self, doc_id: &str) -> bool {
+        self.framed_doc.as_deref() == Some(doc_id)
+    }
+
+    /// Encodes and sends a message.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the message could not be sent
+    async fn send(&mut self, message: &ServerMessage) -> bool {
+        let encoded = self.codec.encode(message);
+        self.send_encoded(encoded, &message.message_type).await
+    }
+
+    /// Encodes and sends the response to a sync request on a document, as a
+    /// binary update frame if the document's updates are framed.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the response could not be sent
+    async fn send_sync_response(&mut self, doc_id: &str, response: &SyncResponse) -> bool {
+        let encoded = self.convert_sync_response(response).and_then(|response| {
+            if self.frames_updates_of(doc_id) {
+                encode_sync_frame(&response).map(EncodedMessage::Binary)
+            } else {
+                self.codec.encode_sync_response(&response)
+            }
+        });
+        self.send_encoded(encoded, "sync response").await
+    }
+
+    /// Converts the update of a sync response to the update encoding of the client.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cow<SyncResponse>)` - The response, borrowed if it needs no conversion
+    /// * `Err(DomainError)` - `InvalidArgument` if the update could not be converted
+    fn convert_sync_response<'a>(
+        &self,
+        response: &'a SyncResponse,
+    ) -> DomainResult<Cow<'a, SyncResponse>> {
+        let Some(update) = response
+            .update
+            .as_deref()
+            .filter(|_| self.update_encoding != UpdateEncoding::V1)
+        else {
+            return Ok(Cow::Borrowed(response));
+        };
+        Ok(Cow::Owned(SyncResponse {
+            update: Some(self.update_encoding.encode(update)?.into_owned()),
+            state_vector: response.state_vector.clone(),
+            sequence_number: response.sequence_number,
+        }))
+    }
+
+    /// Sends an encoded message as a text or binary frame; a message that could not be
+    /// encoded is skipped.
+    async fn send_encoded(&mut self, encoded: DomainResult<EncodedMessage>, what: &str) -> bool {
+        let frame = match encoded {
+            Ok(EncodedMessage::Text(text)) => Message::Text(text),
+            Ok(EncodedMessage::Binary(data)) => Message::Binary(data),
+            Err(e) => {
+                warn!("Failed to encode {} message: {}", what, e);
+                return true;
+            }
+        };
+        self.socket.send(frame).await.is_ok()
+    }
+}
+
+/// Pings of a WebSocket connection and detection of its client going silent.
+///
+/// Half-open connections, whose client vanished without closing them, receive
+/// no frame at all, so they are told apart by the time since the last one.
+struct KeepAliveTimer {
+    keep_alive: KeepAlive,
+    /// Ticks pinging and checking the connection, if either is enabled
+    checks: Option<Interval>,
+    /// Time the client last sent a frame, of any kind
+    last_frame: Instant,
+}
+
+impl KeepAliveTimer {
+    fn new(keep_alive: KeepAlive) -> Self {
+        let checks = keep_alive.check_interval().map(|interval| {
+            let mut checks = tokio::time::interval_at(Instant::now() + interval, interval);
+            checks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            checks
+        });
+        Self {
+            keep_alive,
+            checks,
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// Records a frame received from the client.
+    fn received(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    /// Waits for the next check of the connection; never completes when disabled.
+    async fn tick(&mut self) {
+        match &mut self.checks {
+            Some(checks) => {
+                checks.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Returns whether the client sent no frame for longer than the idle timeout.
+    fn is_idle(&self) -> bool {
+        self.keep_alive
+            .idle_timeout
+            .is_some_and(|timeout| self.last_frame.elapsed() > timeout)
+    }
+
+    /// Checks the connection, closing it if idle and pinging it otherwise.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the connection was closed or could not be pinged
+    async fn check(&self, socket: &mut WebSocket, client_id: &str) -> bool {
+        if self.is_idle() {
+            info!("Closing idle WebSocket connection: {}", client_id);
+            let _ = socket.send(Message::Close(None)).await;
+            return false;
+        }
+        if self.keep_alive.ping_interval.is_some()
+            && socket.send(Message::Ping(Vec::new())).await.is_err()
+        {
+            warn!("Failed to ping client: {}", client_id);
+            return false;
+        }
+        true
+    }
 }
 
 /// WebSocket connection handler for collaborative document editing.
@@ -56,6 +788,8 @@ where
 #[derive(Clone)]
 pub struct WebSocketHandler<R: DocumentRepository> {
     document_service: Arc<DocumentService<R>>,
+    admission: Arc<AdmissionController>,
+    sessions: Arc<SessionRegistry>,
 }
 
 impl<R: DocumentRepository + Send + Sync + 'static> WebSocketHandler<R> {
@@ -64,12 +798,22 @@ impl<R: DocumentRepository + Send + Sync + 'static> WebSocketHandler<R> {
     /// # Arguments
     ///
     /// * `document_service` - Domain document service for collaboration operations
+    /// * `admission` - Admission controller used to shed load during overload
+    /// * `sessions` - Registry the connections' presence on documents is recorded in
     ///
     /// # Returns
     ///
     /// A new `WebSocketHandler` instance
-    pub fn new(document_service: Arc<DocumentService<R>>) -> Self {
-        Self { document_service }
+    pub fn new(
+        document_service: Arc<DocumentService<R>>,
+        admission: Arc<AdmissionController>,
+        sessions: Arc<SessionRegistry>,
+    ) -> Self {
+        Self {
+            document_service,
+            admission,
+            sessions,
+        }
     }
 
     /// Handles a WebSocket upgrade request and sets up the connection.
@@ -77,16 +821,30 @@ impl<R: DocumentRepository + Send + Sync + 'static> WebSocketHandler<R> {
     /// # Arguments
     ///
     /// * `ws` - The WebSocket upgrade request
+    /// * `protocol` - The negotiated wire protocol
+    /// * `echo` - Whether the connection receives its own updates back
+    /// * `metadata` - Metadata the client attached to its session
     ///
     /// # Returns
     ///
     /// A response that upgrades the connection to WebSocket protocol
-    pub fn handle_upgrade(&self, ws: WebSocketUpgrade) -> Response {
-        let document_service = self.document_service.clone();
-        ws.on_upgrade(move |socket| {
-            Box::pin(Self::handle_socket(socket, document_service))
-                as Pin<Box<dyn Future<Output = ()> + Send>>
-        })
+    pub async fn handle_upgrade(
+        &self,
+        ws: WebSocketUpgrade,
+        protocol: WsProtocol,
+        echo: EchoPolicy,
+        metadata: ConnectMetadata,
+    ) -> Response {
+        handle_websocket_upgrade(
+            ws,
+            protocol,
+            echo,
+            metadata,
+            self.document_service.clone(),
+            self.admission.clone(),
+            self.sessions.clone(),
+        )
+        .await
     }
 
     /// Main WebSocket connection handler that processes messages from clients.
@@ -94,133 +852,1401 @@ impl<R: DocumentRepository + Send + Sync + 'static> WebSocketHandler<R> {
     /// This method:
     /// 1. Establishes a new WebSocket connection with a client
     /// 2. Processes incoming messages based on their type
-    /// 3. Forwards document updates between collaborating clients
-    /// 4. Maintains connection until client disconnects
+    /// 3. Subscribes the connection to every document it synchronizes with
+    /// 4. Relays updates from other clients of those documents as they happen, and the connection's
+    ///    own updates back if its echo policy includes them
+    /// 5. Delivers server notices addressed to the connection as `notice` messages
+    /// 6. Registers the connection as a guest, with the metadata it attached, on every document it
+    ///    synchronizes with, and relays the joins and departures of other clients there as
+    ///    `user_joined` and `user_left` messages, whichever transport they are connected over
+    /// 7. Relays the cursor moves of other clients of those documents as `cursor` messages
+    /// 8. Maintains connection until client disconnects, pinging it at the interval of the
+    ///    registry's keep-alive and closing it once the client sent nothing for its idle timeout
+    ///
+    /// Incoming frames, remote updates, notices, presence events and cursor moves are
+    /// awaited together, so they are delivered in real time even while the client is idle.
+    /// Cursor moves come from a channel of their own; a connection falling behind skips
+    /// the oldest ones, never document updates.
+    ///
+    /// When binary update frames are negotiated, the bound document's updates are
+    /// received and sent as raw binary frames, and its sync responses too.
     ///
     /// # Arguments
     ///
     /// * `socket` - The WebSocket connection
     /// * `document_service` - Domain document service for collaboration operations
-    pub async fn handle_socket(mut socket: WebSocket, document_service: Arc<DocumentService<R>>) {
-        // Generate a unique client ID for this connection
-        let client_id = Uuid::new_v4().to_string();
-        info!("New WebSocket connection established: {}", client_id);
-
-        // Process incoming messages until client disconnects
-        while let Some(msg) = socket.next().await {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    // Try to parse the message as a ClientMessage
-                    match from_str::<ClientMessage>(&text) {
-                        Ok(client_msg) => {
-                            info!(
-                                "Received message type '{}' for document '{}'",
-                                client_msg.message_type, client_msg.doc_id
-                            );
+    /// * `sessions` - Registry the connection's presence on documents is recorded in
+    /// * `binding` - The document messages omitting their `doc_id` relate to and the tenant whose
+    ///   documents alone they may name, if the connection is bound to one
+    /// * `frames` - The encoding of the messages exchanged with the client, and whether the bound
+    ///   document's updates are exchanged as binary update frames
+    /// * `echo` - Whether the connection receives its own updates back
+    /// * `session` - The guest session of the connection, registered on each document it
+    ///   synchronizes with
+    pub async fn handle_socket(
+        socket: WebSocket,
+        document_service: Arc<DocumentService<R>>,
+        sessions: Arc<SessionRegistry>,
+        binding: JsonBinding,
+        frames: JsonFrames,
+        echo: EchoPolicy,
+        session: Session,
+    ) {
+        let client_id = session.client_id.clone();
+        info!(
+            "New WebSocket connection established: {} ({} messages{})",
+            client_id,
+            frames.encoding,
+            if frames.binary_updates {
+                ", binary updates"
+            } else {
+                ""
+            }
+        );
+        let mut socket = MessageSocket {
+            socket,
+            codec: frames.encoding.codec().unwrap_or(&ProtobufCodec),
+            framed_doc: binding.doc_id.clone().filter(|_| frames.binary_updates),
+            update_encoding: frames.update_encoding,
+        };
+
+        let mut hub = BroadcastHub::new(&client_id)
+            .with_echo_policy(echo)
+            .with_delivery_stats(sessions.delivery_stats());
+        let diff_limiter = document_service.diff_throttle().session_limiter();
+        let mut notices = document_service.subscribe_notices();
+        let mut presence = sessions.subscribe();
+        let mut cursors = sessions.subscribe_cursors();
+        let mut keep_alive = KeepAliveTimer::new(sessions.keep_alive());
 
-                            // Process message based on its type
-                            match client_msg.message_type.as_str() {
-                                // Client requests initial synchronization
-                                "sync" => {
-                                    // Extract client state vector if provided
-                                    let client_state_vector = match &client_msg.update {
-                                        Some(sv_base64) => {
-                                            match base64::engine::general_purpose::STANDARD
-                                                .decode(sv_base64)
-                                            {
-                                                Ok(sv) => Some(sv),
-                                                Err(e) => {
-                                                    warn!(
-                                                        "Failed to decode client state vector: {}",
-                                                        e
-                                                    );
-                                                    None
-                                                }
-                                            }
-                                        }
-                                        None => None,
-                                    };
-
-                                    let (response, _receiver) = document_service
-                                        .handle_sync_request(
-                                            &client_msg.doc_id,
-                                            client_state_vector.as_deref(),
-                                        )
-                                        .await;
-
-                                    // Send sync response back to client containing updates they
-                                    // need
-                                    if let Ok(resp_json) = to_string(&response) {
-                                        if socket.send(Message::Text(resp_json)).await.is_err() {
-                                            warn!("Failed to send sync response to client");
-                                            break;
-                                        }
+        loop {
+            tokio::select! {
+                msg = socket.socket.next() => {
+                    if let Some(Ok(_)) = msg {
+                        keep_alive.received();
+                    }
+                    let payload = match msg {
+                        Some(Ok(Message::Text(text))) => text.into_bytes(),
+                        // Binary frames only carry updates once they are negotiated, as
+                        // messages are then JSON text
+                        Some(Ok(Message::Binary(data))) if socket.framed_doc.is_some() => {
+                            if !Self::handle_update_frame(
+                                &mut socket,
+                                &document_service,
+                                &sessions,
+                                &session,
+                                &data,
+                            )
+                            .await
+                            {
+                                break;
+                            }
+                            continue;
+                        }
+                        Some(Ok(Message::Binary(data))) => data,
+                        Some(Ok(Message::Close(_))) | None => {
+                            info!("WebSocket connection closed by client: {}", client_id);
+                            break;
+                        }
+                        Some(Err(e)) => {
+                            warn!("WebSocket error: {}", e);
+                            break;
+                        }
+                        Some(Ok(_)) => continue, // Ignore other message types
+                    };
+
+                    let Some(client_msg) =
+                        Self::parse_message(socket.codec, &payload, &binding)
+                    else {
+                        continue;
+                    };
+                    if !Self::handle_client_message(
+                        &mut socket,
+                        &document_service,
+                        &sessions,
+                        &mut hub,
+                        &diff_limiter,
+                        &session,
+                        client_msg,
+                    )
+                    .await
+                    {
+                        break;
+                    }
+                }
+                event = hub.recv() => {
+                    let (doc_id, update, own, sequence_number, origin) = match event {
+                        HubEvent::Update {
+                            doc_id,
+                            update,
+                            source,
+                            user_id,
+                            origin,
+                            sequence_number,
+                        } => {
+                            let own = hub.is_own(&source);
+                            let origin =
+                                json!({ "kind": origin, "client_id": source, "user_id": user_id });
+                            (doc_id, update, own, sequence_number, origin)
+                        }
+                        HubEvent::Lagged { doc_id, skipped } => {
+                            // Resend the full state; applying it is idempotent for the client
+                            warn!(
+                                "Client {} lagged by {} updates on document '{}', resyncing",
+                                client_id, skipped, doc_id
+                            );
+                            let response =
+                                match document_service.handle_sync_request(&doc_id, None).await {
+                                    Ok((response, _)) => response,
+                                    Err(e) => {
+                                        // The client reconnects and syncs again from scratch
+                                        warn!(
+                                            "Failed to resync client {} on document '{}', \
+                                             disconnecting: {}",
+                                            client_id, doc_id, e
+                                        );
+                                        break;
                                     }
+                                };
+                            let update = response.update.unwrap_or_default();
+                            let origin = json!({ "kind": OriginKind::Server });
+                            (doc_id, update, false, response.sequence_number, origin)
+                        }
+                    };
+
+                    if !Self::send_update(
+                        &mut socket,
+                        &doc_id,
+                        &update,
+                        own,
+                        sequence_number,
+                        origin,
+                    )
+                    .await
+                    {
+                        warn!("Failed to relay update to client: {}", client_id);
+                        sessions.delivery_stats().record(
+                            &doc_id,
+                            &client_id,
+                            DropReason::ClosedChannel,
+                            1,
+                        );
+                        break;
+                    }
+                }
+                notice = notices.recv() => match notice {
+                    Ok(notice) => {
+                        // Document notices only concern the documents this connection edits
+                        if notice.is_addressed_to(|doc_id| hub.is_subscribed(doc_id))
+                            && !Self::send_notice(&mut socket, &notice).await
+                        {
+                            warn!("Failed to send notice to client: {}", client_id);
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Client {} missed {} notices", client_id, skipped);
+                    }
+                    // The document service is shutting down
+                    Err(RecvError::Closed) => break,
+                },
+                event = presence.recv() => match event {
+                    Ok(PresenceEvent::Kicked(session)) if session.client_id == client_id => {
+                        info!("Closing WebSocket connection of kicked client: {}", client_id);
+                        let _ = socket.socket.send(Message::Close(None)).await;
+                        break;
+                    }
+                    Ok(event) => {
+                        // Presence only concerns the documents this connection edits
+                        let session = event.session();
+                        if session.client_id != client_id
+                            && hub.is_subscribed(&session.document_id)
+                            && !Self::send_presence(&mut socket, &event).await
+                        {
+                            warn!("Failed to send presence to client: {}", client_id);
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Client {} missed {} presence events", client_id, skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                event = cursors.recv() => match event {
+                    Ok(event) => {
+                        if event.client_id != client_id
+                            && hub.is_subscribed(&event.document_id)
+                            && !Self::send_cursor(&mut socket, &event).await
+                        {
+                            warn!("Failed to send cursor to client: {}", client_id);
+                            break;
+                        }
+                    }
+                    // Later moves supersede the skipped ones
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!("Client {} skipped {} cursor moves", client_id, skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => {
+                    if !keep_alive.check(&mut socket.socket, &client_id).await {
+                        break;
+                    }
+                }
+            }
+        }
+
+        sessions.disconnect(&client_id);
+
+        info!(
+            "WebSocket connection terminated: {} ({} documents subscribed)",
+            client_id,
+            hub.subscription_count()
+        );
+    }
+
+    /// Decodes a message received from a client.
+    ///
+    /// Messages omitting their `doc_id` relate to the document the connection is
+    /// bound to; they are ignored on connections bound to none. Messages naming a
+    /// document of another tenant than the connection's are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The codec of the connection's encoding
+    /// * `payload` - The payload of the text or binary frame
+    /// * `binding` - The document and the tenant the connection is bound to
+    ///
+    /// # Returns
+    ///
+    /// The message, or `None` if it is malformed or names no document
+    fn parse_message(
+        codec: &dyn MessageCodec,
+        payload: &[u8],
+        binding: &JsonBinding,
+    ) -> Option<ClientMessage> {
+        let mut client_msg = match codec.decode(payload) {
+            Ok(client_msg) => client_msg,
+            Err(e) => {
+                warn!("Failed to parse client message: {}", e);
+                return None;
+            }
+        };
+
+        if client_msg.doc_id.is_empty() {
+            match &binding.doc_id {
+                Some(doc_id) => client_msg.doc_id = doc_id.clone(),
+                None => {
+                    warn!(
+                        "Ignoring '{}' message naming no document",
+                        client_msg.message_type
+                    );
+                    return None;
+                }
+            }
+        }
+
+        if let Some(tenant) = &binding.tenant {
+            if unscoped_document_id(tenant, &client_msg.doc_id).is_none() {
+                warn!(
+                    "Ignoring '{}' message naming document '{}' outside tenant '{}'",
+                    client_msg.message_type, client_msg.doc_id, tenant
+                );
+                return None;
+            }
+        }
+
+        Some(client_msg)
+    }
+
+    /// Processes a message received from a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `document_service` - Domain document service for collaboration operations
+    /// * `sessions` - Registry the connection's presence on documents is recorded in
+    /// * `hub` - The connection's broadcast hub
+    /// * `diff_limiter` - The connection's cap on concurrent diffs
+    /// * `session` - The connection's session, registered on each document it synchronizes with
+    /// * `client_msg` - The parsed message
+    ///
+    /// # Returns
+    ///
+    /// `false` if a reply could not be sent and the connection should be closed
+    async fn handle_client_message(
+        socket: &mut MessageSocket,
+        document_service: &DocumentService<R>,
+        sessions: &SessionRegistry,
+        hub: &mut BroadcastHub,
+        diff_limiter: &DiffLimiter,
+        session: &Session,
+        client_msg: ClientMessage,
+    ) -> bool {
+        let client_id = session.client_id.as_str();
+        let origin = session.update_origin();
+        info!(
+            "Received message type '{}' for document '{}'",
+            client_msg.message_type, client_msg.doc_id
+        );
+
+        // WebSocket connections carry no user identity, so they are served as guests
+        let role = match document_service.access_role(&client_msg.doc_id, None) {
+            Ok(role) => role,
+            Err(e) => {
+                warn!("Denied access to document '{}': {}", client_msg.doc_id, e);
+                return Self::send_access_error(socket, &client_msg.doc_id, &e).await;
+            }
+        };
+
+        // Synchronizing makes the client present on the document; any later message
+        // proves it still is
+        if matches!(client_msg.message_type.as_str(), "sync" | "sv")
+            && !sessions.is_present(&client_msg.doc_id, client_id)
+        {
+            let joined = sessions.join(Session {
+                role,
+                ..session.on_document(&client_msg.doc_id)
+            });
+            if let Err(e) = joined {
+                warn!("Rejected join of client {}: {}", client_id, e);
+                return Self::send_error(socket, &client_msg.doc_id, "ROOM_FULL", &e.to_string())
+                    .await;
+            }
+        } else {
+            sessions.touch(&client_msg.doc_id, client_id);
+        }
+
+        // Read-only clients keep receiving updates but may not change the document, and
+        // throttled changes are not applied; the client resends them later
+        if matches!(
+            client_msg.message_type.as_str(),
+            "update" | "update_batch" | "undo" | "redo"
+        ) {
+            if let Some(sent) =
+                Self::reject_update(socket, sessions, session, &client_msg.doc_id, role).await
+            {
+                return sent;
+            }
+        }
+
+        // Process message based on its type
+        match client_msg.message_type.as_str() {
+            // Client requests initial synchronization
+            "sync" => {
+                // Extract client state vector if provided
+                let client_state_vector = match &client_msg.update {
+                    Some(sv_base64) => {
+                        match base64::engine::general_purpose::STANDARD.decode(sv_base64) {
+                            Ok(sv) => Some(sv),
+                            Err(e) => {
+                                warn!("Failed to decode client state vector: {}", e);
+                                None
+                            }
+                        }
+                    }
+                    None => None,
+                };
+
+                return Self::send_sync_response(
+                    socket,
+                    document_service,
+                    hub,
+                    diff_limiter,
+                    &client_msg.doc_id,
+                    client_state_vector.as_deref(),
+                )
+                .await;
+            }
+            // Client sends a document update
+            "update" => {
+                if let Some(update_base64) = &client_msg.update {
+                    let applied = match socket.update_encoding {
+                        UpdateEncoding::V1 => {
+                            document_service
+                                .handle_update_request(&client_msg.doc_id, origin, update_base64)
+                                .await
+                        }
+                        encoding => match Self::decode_update(encoding, update_base64) {
+                            Ok(update) => {
+                                document_service
+                                    .handle_binary_update(&client_msg.doc_id, origin, &update)
+                                    .await
+                            }
+                            Err(e) => Err(e),
+                        },
+                    };
+                    return Self::report_update(socket, &client_msg.doc_id, applied).await;
+                }
+            }
+            // Client sends the updates of an offline session, applied and broadcast as one
+            "update_batch" => {
+                let updates =
+                    Self::decode_update_batch(socket.update_encoding, client_msg.data.as_ref());
+                let applied = match updates {
+                    Ok(updates) => {
+                        document_service
+                            .apply_updates_batch(&client_msg.doc_id, origin, updates)
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                return Self::report_update(socket, &client_msg.doc_id, applied).await;
+            }
+            // Client requests synchronization using state vector
+            "sv" => {
+                if let Some(sv_base64) = &client_msg.update {
+                    match base64::engine::general_purpose::STANDARD.decode(sv_base64) {
+                        Ok(state_vector) => {
+                            return Self::send_sync_response(
+                                socket,
+                                document_service,
+                                hub,
+                                diff_limiter,
+                                &client_msg.doc_id,
+                                Some(&state_vector),
+                            )
+                            .await;
+                        }
+                        Err(e) => {
+                            warn!("Failed to decode Base64 state vector: {}", e);
+                        }
+                    }
+                }
+            }
+            // Client undoes or redoes its last change; the resulting update is broadcast
+            // to every client of the document, the sender included
+            "undo" | "redo" => {
+                let action = if client_msg.message_type == "undo" {
+                    UndoAction::Undo
+                } else {
+                    UndoAction::Redo
+                };
+                if let Err(e) = document_service
+                    .undo_document(&client_msg.doc_id, origin, action)
+                    .await
+                {
+                    warn!(
+                        "Failed to {} on document '{}': {}",
+                        action, client_msg.doc_id, e
+                    );
+                    let error_type = match e {
+                        DomainError::Unavailable(_) => "UNDO_UNAVAILABLE",
+                        DomainError::DocumentFrozen(_) => "DOCUMENT_FROZEN",
+                        _ => "UNDO_ERROR",
+                    };
+                    return Self::send_error(
+                        socket,
+                        &client_msg.doc_id,
+                        error_type,
+                        &e.to_string(),
+                    )
+                    .await;
+                }
+            }
+            // Client asks for the subdocuments the document references
+            "subdocs" => {
+                return Self::send_subdocuments(socket, document_service, &client_msg.doc_id).await;
+            }
+            // Client moved or cleared its cursor; read-only clients share theirs too
+            "cursor" => {
+                let published =
+                    Self::decode_cursor(client_msg.data.as_ref()).and_then(|position| {
+                        sessions.publish_cursor(&client_msg.doc_id, client_id, position)
+                    });
+                if let Err(e) = published {
+                    warn!(
+                        "Rejected cursor of client {} on document '{}': {}",
+                        client_id, client_msg.doc_id, e
+                    );
+                    return Self::send_error(
+                        socket,
+                        &client_msg.doc_id,
+                        "INVALID_CURSOR",
+                        &e.to_string(),
+                    )
+                    .await;
+                }
+            }
+            // Client proves it is still connected; touching its presence above is all it takes
+            "heartbeat" => {}
+            // Client noticed a gap in the sequence numbers of the updates it received
+            "gap" => {
+                info!(
+                    "Client {} missed updates on document '{}', requesting a resync",
+                    client_id, client_msg.doc_id
+                );
+                return Self::send_sync_required(socket, document_service, &client_msg.doc_id)
+                    .await;
+            }
+            _ => warn!("Unknown message type: {}", client_msg.message_type),
+        }
+
+        true
+    }
+
+    /// Applies the update carried by a binary update frame to the connection's bound document.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `document_service` - Domain document service for collaboration operations
+    /// * `sessions` - Registry the connection's presence on documents is recorded in
+    /// * `session` - The connection's session
+    /// * `frame` - The binary frame
+    ///
+    /// # Returns
+    ///
+    /// `false` if a reply could not be sent and the connection should be closed
+    async fn handle_update_frame(
+        socket: &mut MessageSocket,
+        document_service: &DocumentService<R>,
+        sessions: &SessionRegistry,
+        session: &Session,
+        frame: &[u8],
+    ) -> bool {
+        let Some(doc_id) = socket.framed_doc.clone() else {
+            return true;
+        };
+        let decoded =
+            decode_update_frame(frame).and_then(|update| socket.update_encoding.decode(update));
+        let update = match decoded {
+            Ok(update) => update,
+            Err(e) => {
+                warn!("Failed to parse update frame: {}", e);
+                return true;
+            }
+        };
+
+        let role = match document_service.access_role(&doc_id, None) {
+            Ok(role) => role,
+            Err(e) => {
+                warn!("Denied access to document '{}': {}", doc_id, e);
+                return Self::send_access_error(socket, &doc_id, &e).await;
+            }
+        };
+        sessions.touch(&doc_id, &session.client_id);
+        if let Some(sent) = Self::reject_update(socket, sessions, session, &doc_id, role).await {
+            return sent;
+        }
+
+        let applied = document_service
+            .handle_binary_update(&doc_id, session.update_origin(), &update)
+            .await;
+        Self::report_update(socket, &doc_id, applied).await
+    }
+
+    /// Rejects an update from a read-only or throttled client with an error message.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `sessions` - Registry holding the update rate limiter
+    /// * `session` - The connection's session
+    /// * `doc_id` - The document the update is for
+    /// * `role` - The client's role on the document
+    ///
+    /// # Returns
+    ///
+    /// `None` if the update may be applied, otherwise whether the error could be sent
+    async fn reject_update(
+        socket: &mut MessageSocket,
+        sessions: &SessionRegistry,
+        session: &Session,
+        doc_id: &str,
+        role: AccessRole,
+    ) -> Option<bool> {
+        let client_id = session.client_id.as_str();
+        if !role.can_write() {
+            warn!(
+                "Rejected update from read-only client {} on document '{}'",
+                client_id, doc_id
+            );
+            return Some(
+                Self::send_error(socket, doc_id, "PERMISSION_DENIED", READ_ONLY_ERROR).await,
+            );
+        }
+
+        if let Err(e) = sessions
+            .update_limiter()
+            .check(client_id, session.remote_ip)
+        {
+            warn!("Throttled update from client {}: {}", client_id, e);
+            return Some(
+                Self::send_error(socket, doc_id, "RATE_LIMIT_EXCEEDED", &e.to_string()).await,
+            );
+        }
+        None
+    }
+
+    /// Decodes the updates of an `update_batch` message.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The message's data, carrying the Base64-encoded updates as `updates`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Vec<u8>>)` - The binary updates, in order
+    /// * `Err(DomainError)` - `InvalidArgument` if `updates` is missing or an update is not a
+    ///   Base64 string
+    fn decode_update_batch(
+        encoding: UpdateEncoding,
+        data: Option<&Value>,
+    ) -> DomainResult<Vec<Vec<u8>>> {
+        let updates = data
+            .and_then(|data| data.get("updates"))
+            .and_then(|updates| updates.as_array())
+            .ok_or_else(|| {
+                DomainError::InvalidArgument("An update batch needs an updates array".to_string())
+            })?;
+
+        updates
+            .iter()
+            .map(|update| {
+                let update = update.as_str().ok_or_else(|| {
+                    DomainError::InvalidArgument("Batched updates must be strings".to_string())
+                })?;
+                match encoding {
+                    UpdateEncoding::V1 => decode_base64_update(update),
+                    encoding => Self::decode_update(encoding, update),
+                }
+            })
+            .collect()
+    }
+
+    /// Decodes the cursor of a `cursor` message.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The message's data, carrying the Base64-encoded relative positions as `anchor`
+    ///   and `head`, or `null` to clear the cursor
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Option<CursorPosition>)` - The cursor, or `None` if it is cleared
+    /// * `Err(DomainError)` - `InvalidArgument` if an end is missing or is not a Base64-encoded
+    ///   relative position
+    fn decode_cursor(data: Option<&Value>) -> DomainResult<Option<CursorPosition>> {
+        let Some(data) = data.filter(|data| !data.is_null()) else {
+            return Ok(None);
+        };
+
+        let end = |name: &str| {
+            let encoded = data.get(name).and_then(|end| end.as_str()).ok_or_else(|| {
+                DomainError::InvalidArgument(format!("A cursor needs a Base64 {}", name))
+            })?;
+            base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| {
+                    DomainError::InvalidArgument(format!(
+                        "Failed to decode the cursor {}: {}",
+                        name, e
+                    ))
+                })
+        };
+        CursorPosition::new(end("anchor")?, end("head")?).map(Some)
+    }
+
+    /// Decodes a Base64 update sent in an update encoding other than v1 into a v1 update.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoding` - The update encoding negotiated by the client
+    /// * `update_base64` - The Base64-encoded update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The v1 update
+    /// * `Err(DomainError)` - `InvalidArgument` if the update is not Base64 or not a valid update
+    fn decode_update(encoding: UpdateEncoding, update_base64: &str) -> DomainResult<Vec<u8>> {
+        let update = decode_base64_update(update_base64)?;
+        encoding.decode(&update).map(Cow::into_owned)
+    }
+
+    /// Reports the failure to apply a client's update.
+    ///
+    /// Oversized updates are answered with a `PAYLOAD_TOO_LARGE` error and
+    /// updates to a frozen document with a `DOCUMENT_FROZEN` one; other failures
+    /// are only logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `doc_id` - The document the update is for
+    /// * `applied` - The result of applying the update
+    ///
+    /// # Returns
+    ///
+    /// `false` if the error could not be sent and the connection should be closed
+    async fn report_update(
+        socket: &mut MessageSocket,
+        doc_id: &str,
+        applied: DomainResult<()>,
+    ) -> bool {
+        match applied {
+            Ok(()) => true,
+            Err(e @ DomainError::PayloadTooLarge(_)) => {
+                Self::send_error(socket, doc_id, "PAYLOAD_TOO_LARGE", &e.to_string()).await
+            }
+            Err(e @ DomainError::DocumentFrozen(_)) => {
+                Self::send_error(socket, doc_id, "DOCUMENT_FROZEN", &e.to_string()).await
+            }
+            Err(e) => {
+                warn!("Failed to apply update: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Asks the client to synchronize a document again with its state vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `document_service` - Domain document service for collaboration operations
+    /// * `doc_id` - The document to synchronize again
+    ///
+    /// # Returns
+    ///
+    /// `false` if the message could not be sent
+    async fn send_sync_required(
+        socket: &mut MessageSocket,
+        document_service: &DocumentService<R>,
+        doc_id: &str,
+    ) -> bool {
+        let message = ServerMessage {
+            message_type: "sync_required".to_string(),
+            data: Some(json!({
+                "doc_id": doc_id,
+                "sequence_number": document_service.sequence_number(doc_id).await,
+            })),
+            update: None,
+        };
+
+        socket.send(&message).await
+    }
 
-                                    // Note: For broadcast updates, we would need to implement a
-                                    // different approach
-                                    // such as using channels or a broadcast system outside of this
-                                    // handler
+    /// Answers a sync request and subscribes the connection to the document.
+    ///
+    /// The request is rejected with an error message when the connection already
+    /// has the maximum number of diffs in flight. An oversized diff is answered
+    /// with its first chunk; the remaining chunks follow as `update` messages
+    /// delivered through the hub.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `document_service` - Domain document service for collaboration operations
+    /// * `hub` - The connection's broadcast hub
+    /// * `diff_limiter` - The connection's cap on concurrent diffs
+    /// * `doc_id` - The document to synchronize with
+    /// * `client_state_vector` - The client's state vector, if provided
+    ///
+    /// # Returns
+    ///
+    /// `false` if a reply could not be sent and the connection should be closed
+    async fn send_sync_response(
+        socket: &mut MessageSocket,
+        document_service: &DocumentService<R>,
+        hub: &mut BroadcastHub,
+        diff_limiter: &DiffLimiter,
+        doc_id: &str,
+        client_state_vector: Option<&[u8]>,
+    ) -> bool {
+        // Subdocuments are loaded lazily, once their parent references them
+        if let Err(e) = document_service.check_subdocument(doc_id).await {
+            warn!("Rejected sync request for document '{}': {}", doc_id, e);
+            return Self::send_error(socket, doc_id, "DOCUMENT_NOT_FOUND", &e.to_string()).await;
+        }
+
+        let permit = match diff_limiter.try_acquire() {
+            Ok(permit) => permit,
+            Err(e) => {
+                warn!("Rejected sync request for document '{}': {}", doc_id, e);
+                return Self::send_error(socket, doc_id, "RATE_LIMIT_EXCEEDED", &e.to_string())
+                    .await;
+            }
+        };
+
+        let (response, chunks, receiver) = match document_service
+            .handle_chunked_sync_request(doc_id, client_state_vector)
+            .await
+        {
+            Ok(sync) => sync,
+            Err(e) => {
+                warn!("Failed to sync document '{}': {}", doc_id, e);
+                return Self::send_error(socket, doc_id, "INTERNAL_ERROR", &e.to_string()).await;
+            }
+        };
+        hub.subscribe(doc_id, receiver);
+
+        // Send sync response back to client containing updates they need
+        if !socket.send_sync_response(doc_id, &response).await {
+            warn!("Failed to send sync response to client");
+            return false;
+        }
+
+        if !chunks.is_empty() {
+            hub.deliver(doc_id, chunks, permit);
+        }
+
+        true
+    }
+
+    /// Sends the subdocuments a document references.
+    ///
+    /// The reply is a `subdocs` message whose data carries the document and the
+    /// sorted GUIDs of its subdocuments; each one is synchronized as the
+    /// document `<doc_id>#<guid>`.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `document_service` - Domain document service for collaboration operations
+    /// * `doc_id` - The parent document
+    ///
+    /// # Returns
+    ///
+    /// `false` if a reply could not be sent and the connection should be closed
+    async fn send_subdocuments(
+        socket: &mut MessageSocket,
+        document_service: &DocumentService<R>,
+        doc_id: &str,
+    ) -> bool {
+        let guids = match document_service.list_subdocuments(doc_id).await {
+            Ok(guids) => guids,
+            Err(DomainError::NotFound(_)) => {
+                let error = format!("Document '{}' not found", doc_id);
+                return Self::send_error(socket, doc_id, "DOCUMENT_NOT_FOUND", &error).await;
+            }
+            Err(e) => {
+                return Self::send_error(socket, doc_id, "INTERNAL_ERROR", &e.to_string()).await;
+            }
+        };
+
+        let message = ServerMessage {
+            message_type: "subdocs".to_string(),
+            data: Some(json!({ "doc_id": doc_id, "subdocs": guids })),
+            update: None,
+        };
+        socket.send(&message).await
+    }
+
+    /// Sends a document update relayed from another client.
+    ///
+    /// An update the connection sent itself is flagged with `"echo": true`, or
+    /// sent as an `Echo` frame if the document's updates are framed. JSON
+    /// messages also tell where the update comes from; frames do not.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `doc_id` - The document the update belongs to
+    /// * `update` - The binary update
+    /// * `echo` - Whether the update is the connection's own, echoed back
+    /// * `sequence_number` - Position of the update among the document's broadcasts
+    /// * `origin` - Where the update comes from: its kind, and its client and user if known
+    ///
+    /// # Returns
+    ///
+    /// `false` if the message could not be sent
+    async fn send_update(
+        socket: &mut MessageSocket,
+        doc_id: &str,
+        update: &[u8],
+        echo: bool,
+        sequence_number: u64,
+        origin: Value,
+    ) -> bool {
+        let update = match socket.update_encoding.encode(update) {
+            Ok(update) => update,
+            Err(e) => {
+                warn!(
+                    "Failed to encode update of document '{}' as {}: {}",
+                    doc_id, socket.update_encoding, e
+                );
+                return true;
+            }
+        };
+        if socket.frames_updates_of(doc_id) {
+            let frame = encode_update_frame(&update, sequence_number, echo);
+            return socket
+                .send_encoded(Ok(EncodedMessage::Binary(frame)), "update")
+                .await;
+        }
+
+        let data = if echo {
+            json!({
+                "doc_id": doc_id,
+                "sequence_number": sequence_number,
+                "origin": origin,
+                "echo": true,
+            })
+        } else {
+            json!({ "doc_id": doc_id, "sequence_number": sequence_number, "origin": origin })
+        };
+        let message = ServerMessage {
+            message_type: "update".to_string(),
+            data: Some(data),
+            update: Some(base64::engine::general_purpose::STANDARD.encode(&update)),
+        };
+
+        socket.send(&message).await
+    }
+
+    /// Sends the join or departure of another client of a document to the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `event` - The presence event
+    ///
+    /// # Returns
+    ///
+    /// `false` if the message could not be sent
+    async fn send_presence(socket: &mut MessageSocket, event: &PresenceEvent) -> bool {
+        let (message_type, session) = match event {
+            PresenceEvent::Joined(session) => ("user_joined", session),
+            PresenceEvent::Left(session) => ("user_left", session),
+            // Only the client itself is told, and WebSocket clients carry no user
+            // identity an operator could change the permission of
+            PresenceEvent::PermissionChanged { .. } => return true,
+            // The client's departure was already sent as it left
+            PresenceEvent::Kicked(_) => return true,
+        };
+        let message = ServerMessage {
+            message_type: message_type.to_string(),
+            data: Some(json!({
+                "doc_id": session.document_id,
+                "client_id": session.client_id,
+                "user_id": session.user_id,
+                "user_name": session.user_name,
+                "user_color": session.user_color,
+                "metadata": session.user_metadata,
+                "transport": session.transport.to_string(),
+            })),
+            update: None,
+        };
+
+        socket.send(&message).await
+    }
+
+    /// Sends the cursor move of another client.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `event` - The cursor move
+    ///
+    /// # Returns
+    ///
+    /// `false` if the message could not be sent
+    async fn send_cursor(socket: &mut MessageSocket, event: &CursorEvent) -> bool {
+        let engine = &base64::engine::general_purpose::STANDARD;
+        let message = ServerMessage {
+            message_type: "cursor".to_string(),
+            data: Some(json!({
+                "doc_id": event.document_id,
+                "client_id": event.client_id,
+                "user_id": event.user_id,
+                "user_name": event.user_name,
+                "user_color": event.user_color,
+                "anchor": event.position.as_ref().map(|position| engine.encode(&position.anchor)),
+                "head": event.position.as_ref().map(|position| engine.encode(&position.head)),
+                "at": event.at,
+            })),
+            update: None,
+        };
+
+        socket.send(&message).await
+    }
+
+    /// Sends a server notice to the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `notice` - The notice to deliver
+    ///
+    /// # Returns
+    ///
+    /// `false` if the message could not be sent
+    async fn send_notice(socket: &mut MessageSocket, notice: &Notice) -> bool {
+        socket.send(&ServerMessage::notice(notice)).await
+    }
+
+    /// Sends an error concerning a document to the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `doc_id` - The document the error relates to
+    /// * `error_type` - Machine readable error type, named like the gRPC `ErrorType` values
+    /// * `error` - Human readable error message
+    ///
+    /// # Returns
+    ///
+    /// `false` if the message could not be sent
+    async fn send_error(
+        socket: &mut MessageSocket,
+        doc_id: &str,
+        error_type: &str,
+        error: &str,
+    ) -> bool {
+        let message = ServerMessage {
+            message_type: "error".to_string(),
+            data: Some(json!({ "doc_id": doc_id, "error_type": error_type, "message": error })),
+            update: None,
+        };
+
+        socket.send(&message).await
+    }
+
+    /// Sends the error denying the client access to a document.
+    ///
+    /// A document owned by another instance of the cluster is reported as
+    /// `DOCUMENT_MOVED`, after a redirect notice naming the owner's URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `doc_id` - The document the client may not access
+    /// * `error` - Why the client may not access the document
+    ///
+    /// # Returns
+    ///
+    /// `false` if a message could not be sent
+    async fn send_access_error(
+        socket: &mut MessageSocket,
+        doc_id: &str,
+        error: &DomainError,
+    ) -> bool {
+        let DomainError::Moved { owner, .. } = error else {
+            return Self::send_error(socket, doc_id, "AUTHORIZATION_ERROR", &error.to_string())
+                .await;
+        };
+
+        let notice = Notice::new(
+            NoticeKind::Redirect,
+            NoticeSeverity::Warning,
+            error.to_string(),
+        )
+        .with_document(doc_id)
+        .with_redirect_url(&owner.http_url);
+        Self::send_notice(socket, &notice).await
+            && Self::send_error(socket, doc_id, "DOCUMENT_MOVED", &error.to_string()).await
+    }
+
+    /// WebSocket connection handler for the binary Yjs sync protocol.
+    ///
+    /// This method:
+    /// 1. Sends the server's `SyncStep1` so the client replies with its missing updates
+    /// 2. Answers the client's `SyncStep1` with a `SyncStep2`
+    /// 3. Applies `SyncStep2` and `Update` messages from the client
+    /// 4. Relays updates from other clients of the same document as `Update` messages, and the
+    ///    client's own updates too if its echo policy includes them
+    ///
+    /// When the client negotiated dictionary compression and the document has a
+    /// trained dictionary, relayed updates are sent as compressed update
+    /// messages instead, each new dictionary being shipped first.
+    ///
+    /// When the client opted in to notice frames, server notices addressed to its
+    /// document are sent as `MSG_NOTICE` frames; other binary clients, such as
+    /// stock `y-websocket` providers, receive no notices.
+    ///
+    /// Awareness and auth messages are not part of document synchronization and
+    /// are ignored. A read-only client's `SyncStep2` is ignored and its `Update`
+    /// messages are answered with an auth `permission-denied` message, as are the
+    /// updates exceeding the client's rate limit or the server's size limits. The client is
+    /// registered as a guest on the document for as long as it is connected, and is pinged
+    /// and closed when silent as set by the registry's keep-alive.
+    ///
+    /// Once the document is owned by another instance of the cluster, the client's next
+    /// message is answered with an auth `permission-denied` message naming the owner and
+    /// the connection is closed, so the client reconnects and is redirected.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The WebSocket connection
+    /// * `document_service` - Domain document service for collaboration operations
+    /// * `sessions` - Registry the connection's presence on the document is recorded in
+    /// * `session` - The client's session on the document the connection is bound to, with its role
+    ///   and metadata
+    /// * `echo` - Whether the client receives its own updates back
+    /// * `compression` - Whether relayed updates are compressed with the document's dictionary
+    /// * `deliver_notices` - Whether server notices are sent to the client as notice frames
+    pub async fn handle_binary_socket(
+        mut socket: WebSocket,
+        document_service: Arc<DocumentService<R>>,
+        sessions: Arc<SessionRegistry>,
+        session: Session,
+        echo: EchoPolicy,
+        mut compression: PayloadCompression,
+        deliver_notices: bool,
+    ) {
+        let client_id = session.client_id.clone();
+        let doc_id = session.document_id.clone();
+        let role = session.role;
+        let remote_ip = session.remote_ip;
+        info!(
+            "New binary WebSocket connection established: {} (document '{}')",
+            client_id, doc_id
+        );
+
+        let (state_vector, mut updates) = document_service.establish_sync_session(&doc_id).await;
+        // Only watched for the client being disconnected by an operator
+        let mut presence = sessions.subscribe();
+        let mut notices = document_service.subscribe_notices();
+        let mut keep_alive = KeepAliveTimer::new(sessions.keep_alive());
+
+        let step1 = SyncProtocolMessage::SyncStep1(state_vector);
+        if socket.send(Message::Binary(step1.encode())).await.is_err() {
+            warn!("Failed to send sync step 1 to client: {}", client_id);
+            return;
+        }
+        // The room may have filled up since the connection was admitted
+        if let Err(e) = sessions.join(session.clone()) {
+            warn!("Rejected join of client {}: {}", client_id, e);
+            let denied = SyncProtocolMessage::encode_permission_denied(&e.to_string());
+            let _ = socket.send(Message::Binary(denied)).await;
+            return;
+        }
+
+        'connection: loop {
+            tokio::select! {
+                msg = socket.next() => match msg {
+                    Some(Ok(Message::Binary(data))) => {
+                        keep_alive.received();
+                        sessions.touch(&doc_id, &client_id);
+                        // The document may have moved to another instance since the
+                        // upgrade; the client's reconnection is then redirected
+                        if let Err(e) = document_service.check_owner(&doc_id) {
+                            info!("Closing WebSocket connection of client {}: {}", client_id, e);
+                            let denied = SyncProtocolMessage::encode_permission_denied(
+                                &e.to_string(),
+                            );
+                            let _ = socket.send(Message::Binary(denied)).await;
+                            let _ = socket.send(Message::Close(None)).await;
+                            break;
+                        }
+                        let message = match SyncProtocolMessage::decode(&data) {
+                            Ok(Some(message)) => message,
+                            Ok(None) => {
+                                debug!("Ignoring non-sync message from client: {}", client_id);
+                                continue;
+                            }
+                            Err(e) => {
+                                warn!("{}", e);
+                                continue;
+                            }
+                        };
+
+                        // Read-only clients keep receiving updates but may not send any
+                        if !role.can_write() {
+                            match message {
+                                SyncProtocolMessage::SyncStep1(_) => {}
+                                SyncProtocolMessage::SyncStep2(_) => {
+                                    debug!(
+                                        "Ignoring sync step 2 from read-only client: {}",
+                                        client_id
+                                    );
+                                    continue;
                                 }
-                                // Client sends a document update
-                                "update" => {
-                                    if let Some(update_base64) = &client_msg.update {
-                                        if let Err(e) = document_service
-                                            .handle_update_request(
-                                                &client_msg.doc_id,
-                                                update_base64,
-                                            )
-                                            .await
-                                        {
-                                            warn!("Failed to apply update: {}", e);
-                                        }
+                                SyncProtocolMessage::Update(_) => {
+                                    warn!("Rejected update from read-only client: {}", client_id);
+                                    let denied = SyncProtocolMessage::encode_permission_denied(
+                                        READ_ONLY_ERROR,
+                                    );
+                                    if socket.send(Message::Binary(denied)).await.is_err() {
+                                        break;
                                     }
+                                    continue;
+                                }
+                            }
+                        }
+
+                        // Throttled updates are not applied; the client resends them later
+                        if !matches!(message, SyncProtocolMessage::SyncStep1(_)) {
+                            if let Err(e) = sessions.update_limiter().check(&client_id, remote_ip) {
+                                warn!("Throttled update from client {}: {}", client_id, e);
+                                let denied =
+                                    SyncProtocolMessage::encode_permission_denied(&e.to_string());
+                                if socket.send(Message::Binary(denied)).await.is_err() {
+                                    break;
                                 }
-                                // Client requests synchronization using state vector
-                                "sv" => {
-                                    if let Some(sv_base64) = &client_msg.update {
-                                        match document_service
-                                            .handle_sync_step(&client_msg.doc_id, sv_base64)
-                                            .await
-                                        {
-                                            Ok((response, _)) => {
-                                                if let Ok(resp_json) = to_string(&response) {
-                                                    if socket
-                                                        .send(Message::Text(resp_json))
-                                                        .await
-                                                        .is_err()
-                                                    {
-                                                        warn!("Failed to send sv response");
-                                                        break;
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                warn!("Failed to handle sync step: {}", e);
-                                            }
-                                        }
+                                continue;
+                            }
+                        }
+
+                        match document_service
+                            .handle_sync_protocol_message(&doc_id, session.update_origin(), message)
+                            .await
+                        {
+                            // Chunks of an oversized diff are sent one at a time, each
+                            // awaiting the socket, so the client paces the delivery
+                            Ok(replies) => {
+                                for reply in replies {
+                                    if socket.send(Message::Binary(reply.encode())).await.is_err() {
+                                        warn!("Failed to send sync reply to client: {}", client_id);
+                                        break 'connection;
                                     }
                                 }
-                                _ => warn!("Unknown message type: {}", client_msg.message_type),
                             }
+                            Err(e @ (DomainError::PayloadTooLarge(_)
+                            | DomainError::DocumentFrozen(_))) => {
+                                let denied =
+                                    SyncProtocolMessage::encode_permission_denied(&e.to_string());
+                                if socket.send(Message::Binary(denied)).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(e) => warn!("Failed to apply update: {}", e),
                         }
-                        Err(e) => {
-                            warn!("Failed to parse client message: {}", e);
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        info!("WebSocket connection closed by client: {}", client_id);
+                        break;
+                    }
+                    Some(Err(e)) => {
+                        warn!("WebSocket error: {}", e);
+                        break;
+                    }
+                    // Pongs and other message types only prove the client is alive
+                    Some(Ok(_)) => keep_alive.received(),
+                },
+                notification = updates.recv() => match notification {
+                    Ok(notification) if !echo.delivers(&notification.source, &client_id) => {}
+                    Ok(notification) => {
+                        let frames = update_frames(
+                            &document_service,
+                            &mut compression,
+                            &doc_id,
+                            notification.update,
+                        );
+                        if !send_frames(&mut socket, frames).await {
+                            warn!("Failed to relay update to client: {}", client_id);
+                            sessions.delivery_stats().record(
+                                &doc_id,
+                                &client_id,
+                                DropReason::ClosedChannel,
+                                1,
+                            );
+                            break;
                         }
                     }
+                    Err(RecvError::Lagged(skipped)) => {
+                        // Resend the full state; applying it is idempotent for the client
+                        warn!("Client {} lagged by {} updates, resyncing", client_id, skipped);
+                        sessions.delivery_stats().record(
+                            &doc_id,
+                            &client_id,
+                            DropReason::LaggedReceiver,
+                            skipped,
+                        );
+                        let update = match document_service.sync_document(&doc_id, None).await {
+                            Ok((update, _)) => update,
+                            Err(e) => {
+                                // The client reconnects and syncs again from scratch
+                                warn!(
+                                    "Failed to resync client {}, disconnecting: {}",
+                                    client_id, e
+                                );
+                                break;
+                            }
+                        };
+                        let frames =
+                            update_frames(&document_service, &mut compression, &doc_id, update);
+                        if !send_frames(&mut socket, frames).await {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                notice = notices.recv(), if deliver_notices => match notice {
+                    Ok(notice) => {
+                        if notice.is_addressed_to(|id| id == doc_id) {
+                            let frame = SyncProtocolMessage::encode_notice(&notice);
+                            if socket.send(Message::Binary(frame)).await.is_err() {
+                                warn!("Failed to send notice to client: {}", client_id);
+                                break;
+                            }
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Client {} missed {} notices", client_id, skipped);
+                    }
+                    // The document service is shutting down
+                    Err(RecvError::Closed) => break,
+                },
+                event = presence.recv() => match event {
+                    Ok(PresenceEvent::Kicked(session)) if session.client_id == client_id => {
+                        info!("Closing WebSocket connection of kicked client: {}", client_id);
+                        let _ = socket.send(Message::Close(None)).await;
+                        break;
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => {
+                    if !keep_alive.check(&mut socket, &client_id).await {
+                        break;
+                    }
                 }
-                Ok(Message::Close(_)) => {
-                    info!("WebSocket connection closed by client: {}", client_id);
-                    break;
-                }
-                Err(e) => {
-                    warn!("WebSocket error: {}", e);
-                    break;
-                }
-                _ => {} // Ignore other message types
             }
         }
 
+        sessions.leave(&doc_id, &client_id);
         info!("WebSocket connection terminated: {}", client_id);
     }
 }
+
+/// Decodes a Base64 update sent by a JSON client.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` - The binary update
+/// * `Err(DomainError)` - `InvalidArgument` if the update is not Base64
+fn decode_base64_update(update_base64: &str) -> DomainResult<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(update_base64)
+        .map_err(|e| DomainError::InvalidArgument(format!("Failed to decode Base64 update: {}", e)))
+}
+
+/// Encodes an update relayed to a binary client as sync protocol frames.
+///
+/// # Returns
+///
+/// A sync `Update` frame, or a compressed update frame preceded by the
+/// document's dictionary when the client does not hold it yet
+fn update_frames<R: DocumentRepository>(
+    document_service: &DocumentService<R>,
+    compression: &mut PayloadCompression,
+    doc_id: &str,
+    update: Vec<u8>,
+) -> Vec<Vec<u8>> {
+    match compression.compress(document_service.payload_dictionaries(), doc_id, &update) {
+        Some((dictionary, compressed)) => dictionary
+            .map(|dictionary| SyncProtocolMessage::encode_dictionary(&dictionary))
+            .into_iter()
+            .chain(std::iter::once(
+                SyncProtocolMessage::encode_compressed_update(&compressed),
+            ))
+            .collect(),
+        None => vec![SyncProtocolMessage::Update(update).encode()],
+    }
+}
+
+/// Sends binary frames in order.
+///
+/// # Returns
+///
+/// `false` if a frame could not be sent
+async fn send_frames(socket: &mut WebSocket, frames: Vec<Vec<u8>>) -> bool {
+    for frame in frames {
+        if socket.send(Message::Binary(frame)).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
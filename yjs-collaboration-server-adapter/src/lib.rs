@@ -4,5 +4,14 @@
 // to the application's functionality, translating between external formats and
 // the application's internal models.
 
+pub mod admission;
+pub mod broadcast_hub;
+pub mod clock;
+pub mod delivery_stats;
 pub mod http;
-pub mod rpc;
\ No newline at end of file
+pub mod outbox;
+pub mod payload_compression;
+pub mod rate_limiter;
+pub mod rpc;
+pub mod session_registry;
+pub mod transport_compression;
@@ -0,0 +1,263 @@
+use std::{collections::VecDeque, time::Duration};
+
+use dashmap::DashMap;
+use tokio::{
+    sync::{broadcast, oneshot},
+    task::JoinHandle,
+};
+use tracing::debug;
+use yjs_collaboration_server_domain::services::document_service::UpdateNotification;
+
+/// Latest updates of a document relayed to a session, in sequence.
+#[derive(Default)]
+struct Ring {
+    updates: VecDeque<UpdateNotification>,
+}
+
+impl Ring {
+    /// Appends an update, dropping the oldest one beyond the capacity.
+    ///
+    /// An update already recorded is ignored; a gap in the sequence, after a
+    /// lagging subscription or a document reload, starts the ring over.
+    fn record(&mut self, update: UpdateNotification, capacity: usize) {
+        if let (Some(first), Some(last)) = (self.updates.front(), self.updates.back()) {
+            let sequence_number = update.sequence_number;
+            if (first.sequence_number..=last.sequence_number).contains(&sequence_number) {
+                return;
+            }
+            if sequence_number != last.sequence_number + 1 {
+                self.updates.clear();
+            }
+        }
+
+        self.updates.push_back(update);
+        if self.updates.len() > capacity {
+            self.updates.pop_front();
+        }
+    }
+
+    /// Returns the sequence number of the last recorded update, if any.
+    fn last_sequence_number(&self) -> Option<u64> {
+        self.updates.back().map(|update| update.sequence_number)
+    }
+
+    /// Takes the updates numbered after `after` up to `until`.
+    ///
+    /// # Returns
+    ///
+    /// The updates in order, or `None` if some of them are no longer recorded
+    fn replay(self, after: u64, until: u64) -> Option<Vec<UpdateNotification>> {
+        if after > until {
+            return None;
+        }
+
+        match self.updates.front() {
+            Some(first) if first.sequence_number <= after + 1 => Some(
+                self.updates
+                    .into_iter()
+                    .filter(|update| (after + 1..=until).contains(&update.sequence_number))
+                    .collect(),
+            ),
+            None if after == until => Some(Vec::new()),
+            _ => None,
+        }
+    }
+}
+
+/// Updates buffered for a session whose connection ended.
+struct ParkedSession {
+    /// Asks the buffering task to return once it has buffered up to a sequence number
+    stop: oneshot::Sender<u64>,
+    /// The buffering task, yielding its ring unless the session can no longer resume
+    task: JoinHandle<Option<Ring>>,
+}
+
+/// Bounded outbox of the updates relayed to each session, replayed when it reconnects.
+///
+/// While a client is connected, the latest updates of every document it is
+/// subscribed to are recorded, keyed by document and client. When its
+/// connection ends, the outbox keeps buffering the documents' updates for the
+/// retention period, so a client reconnecting with the same client ID can
+/// resume from the last sequence number it received instead of synchronizing
+/// again; that covers the updates lost in flight with the connection as well as
+/// those broadcast during the gap. Once more updates than the capacity were
+/// missed, or the retention period elapsed, the client must resynchronize.
+pub struct SessionOutbox {
+    capacity: usize,
+    retention: Duration,
+    rings: DashMap<(String, String), Ring>,
+    parked: DashMap<(String, String), ParkedSession>,
+}
+
+impl SessionOutbox {
+    /// Creates an empty outbox.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum number of updates kept per session and document (`0` disables the
+    ///   outbox)
+    /// * `retention` - Time the updates of a disconnected session are buffered for
+    ///
+    /// # Returns
+    ///
+    /// A new `SessionOutbox` instance.
+    pub fn new(capacity: usize, retention: Duration) -> Self {
+        Self {
+            capacity,
+            retention,
+            rings: DashMap::new(),
+            parked: DashMap::new(),
+        }
+    }
+
+    /// Returns whether updates are buffered for disconnected sessions.
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0 && !self.retention.is_zero()
+    }
+
+    /// Records an update relayed to a connected session.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the update belongs to
+    /// * `client_id` - Identifier of the session's client
+    /// * `update` - The update
+    pub fn record(&self, document_id: &str, client_id: &str, update: &UpdateNotification) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        self.rings
+            .entry((document_id.to_string(), client_id.to_string()))
+            .or_default()
+            .record(update.clone(), self.capacity);
+    }
+
+    /// Keeps buffering a document's updates for a session whose connection ended.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the session was subscribed to
+    /// * `client_id` - Identifier of the session's client
+    /// * `sequence_number` - Sequence number of the last update broadcast before `updates`
+    ///   subscribed
+    /// * `updates` - A new subscription to the document's updates, taken once the connection
+    ///   stopped relaying them
+    pub fn park(
+        &self,
+        document_id: &str,
+        client_id: &str,
+        sequence_number: u64,
+        updates: broadcast::Receiver<UpdateNotification>,
+    ) {
+        let key = (document_id.to_string(), client_id.to_string());
+        let ring = self.rings.remove(&key).map(|(_, ring)| ring);
+        if !self.is_enabled() {
+            return;
+        }
+
+        // Drop the buffers of sessions that never came back
+        self.parked.retain(|_, parked| !parked.task.is_finished());
+
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(Self::buffer(
+            ring.unwrap_or_default(),
+            sequence_number,
+            updates,
+            self.capacity,
+            self.retention,
+            stopped,
+        ));
+        if let Some(previous) = self.parked.insert(key, ParkedSession { stop, task }) {
+            previous.task.abort();
+        }
+    }
+
+    /// Takes the updates a reconnecting session missed on a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the session resumes
+    /// * `client_id` - Identifier of the session's client
+    /// * `after` - Sequence number of the last update the client received
+    /// * `until` - Sequence number of the last update broadcast before the session subscribed again
+    ///
+    /// # Returns
+    ///
+    /// The missed updates in order, or `None` if the outbox does not hold all of them and
+    /// the client must resynchronize
+    pub async fn resume(
+        &self,
+        document_id: &str,
+        client_id: &str,
+        after: u64,
+        until: u64,
+    ) -> Option<Vec<UpdateNotification>> {
+        let key = (document_id.to_string(), client_id.to_string());
+        let (_, parked) = self.parked.remove(&key)?;
+
+        // The task may already have given up
+        let _ = parked.stop.send(until);
+        let ring = parked.task.await.ok()??;
+        ring.replay(after, until)
+    }
+
+    /// Discards what is recorded for a session that left a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the session left
+    /// * `client_id` - Identifier of the session's client
+    pub fn forget(&self, document_id: &str, client_id: &str) {
+        let key = (document_id.to_string(), client_id.to_string());
+        self.rings.remove(&key);
+        if let Some((_, parked)) = self.parked.remove(&key) {
+            parked.task.abort();
+        }
+    }
+
+    /// Buffers a document's updates until the session resumes or the retention period elapses.
+    ///
+    /// Once asked to stop at a sequence number, the task keeps buffering until it
+    /// reaches it; those updates were broadcast before the session subscribed
+    /// again, so they are already queued.
+    async fn buffer(
+        mut ring: Ring,
+        sequence_number: u64,
+        mut updates: broadcast::Receiver<UpdateNotification>,
+        capacity: usize,
+        retention: Duration,
+        mut stop: oneshot::Receiver<u64>,
+    ) -> Option<Ring> {
+        // Updates broadcast after the last recorded one but before the subscription are lost
+        if ring
+            .last_sequence_number()
+            .is_some_and(|last| last < sequence_number)
+        {
+            ring.updates.clear();
+        }
+
+        let expiry = tokio::time::sleep(retention);
+        tokio::pin!(expiry);
+
+        let until = loop {
+            tokio::select! {
+                update = updates.recv() => ring.record(update.ok()?, capacity),
+                until = &mut stop => break until.ok()?,
+                _ = &mut expiry => {
+                    debug!("Outbox of a disconnected session expired");
+                    return None;
+                }
+            }
+        };
+
+        while ring
+            .last_sequence_number()
+            .map_or(sequence_number, |last| last.max(sequence_number))
+            < until
+        {
+            ring.record(updates.recv().await.ok()?, capacity);
+        }
+        Some(ring)
+    }
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, sync::Arc};
+
+use yjs_collaboration_server_domain::{
+    services::payload_dictionaries::PayloadDictionaries,
+    value_objects::payload_dictionary::{CompressedPayload, PayloadDictionary},
+};
+
+/// Dictionary compression of the updates relayed to a connection.
+///
+/// Negotiated per connection, like the echo policy: clients opting in must
+/// handle dictionary and compressed update messages, while other clients keep
+/// receiving plain updates. The dictionary shipped for each document is
+/// tracked, so a dictionary is sent once, before the first update compressed
+/// with it, and again only when the document's dictionary is retrained.
+#[derive(Debug, Default)]
+pub struct PayloadCompression {
+    enabled: bool,
+    /// Dictionary last shipped to the connection, by document
+    shipped: HashMap<String, u32>,
+}
+
+impl PayloadCompression {
+    /// Returns the compression matching a negotiated compression flag.
+    pub fn from_flag(enabled: bool) -> Self {
+        Self {
+            enabled,
+            shipped: HashMap::new(),
+        }
+    }
+
+    /// Changes whether the updates relayed to the connection are compressed.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Compresses an update relayed to the connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `dictionaries` - The document service's dictionaries, `None` if compression is disabled on
+    ///   the server
+    /// * `doc_id` - Identifier of the document the update belongs to
+    /// * `update` - The binary update
+    ///
+    /// # Returns
+    ///
+    /// `None` if the update must be sent as is, otherwise the dictionary to
+    /// ship first, if the connection does not hold it yet, and the compressed
+    /// update
+    pub fn compress(
+        &mut self,
+        dictionaries: Option<&PayloadDictionaries>,
+        doc_id: &str,
+        update: &[u8],
+    ) -> Option<(Option<Arc<PayloadDictionary>>, CompressedPayload)> {
+        if !self.enabled {
+            return None;
+        }
+
+        let compressed = dictionaries?.compress(doc_id, update)?;
+        let id = compressed.dictionary.id;
+        let ship = match self.shipped.insert(doc_id.to_string(), id) {
+            Some(shipped) if shipped == id => None,
+            _ => Some(compressed.dictionary.clone()),
+        };
+        Some((ship, compressed))
+    }
+}
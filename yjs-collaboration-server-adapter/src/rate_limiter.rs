@@ -0,0 +1,180 @@
+use std::{
+    net::IpAddr,
+    str::FromStr,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use yjs_collaboration_server_domain::errors::DomainError;
+
+/// What the updates counted by a rate limit are grouped by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// Each connection has its own budget
+    #[default]
+    Client,
+    /// The connections from the same IP address share a budget; connections
+    /// without a known address, such as gRPC streams, fall back to their own
+    Ip,
+}
+
+impl FromStr for RateLimitKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client" | "client_id" => Ok(Self::Client),
+            "ip" => Ok(Self::Ip),
+            _ => Err(format!("Unknown rate limit key: {}", s)),
+        }
+    }
+}
+
+/// Rate and burst of the updates a client may send.
+///
+/// A rate of `0` disables the limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UpdateRateLimit {
+    /// Updates a client may send per second, on average
+    pub per_second: f64,
+    /// Updates a client may send at once after being idle
+    pub burst: u32,
+    /// What the updates are counted by
+    pub key: RateLimitKey,
+}
+
+/// Token bucket of a client or address.
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Token-bucket limiter of the updates clients send, shared by both transports.
+///
+/// Every client starts with a full bucket of `burst` tokens, refilled at the
+/// configured rate; each update takes a token, and updates arriving at an
+/// empty bucket are rejected rather than queued, so a client flooding a
+/// document cannot starve the other clients of the compute pool.
+///
+/// The limit may be changed while clients are connected; their buckets keep
+/// their tokens, capped to the new burst on their next refill.
+pub struct UpdateRateLimiter {
+    limit: RwLock<UpdateRateLimit>,
+    buckets: DashMap<String, Bucket>,
+}
+
+impl UpdateRateLimiter {
+    /// Creates a limiter enforcing a rate limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The rate and burst of the updates each client may send
+    ///
+    /// # Returns
+    ///
+    /// A new `UpdateRateLimiter` with every bucket full
+    pub fn new(limit: UpdateRateLimit) -> Self {
+        Self {
+            limit: RwLock::new(limit),
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Returns the enforced rate limit.
+    pub fn limit(&self) -> UpdateRateLimit {
+        *self
+            .limit
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Changes the enforced rate limit, e.g. after the configuration was reloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The rate and burst of the updates each client may send from now on
+    pub fn set_limit(&self, limit: UpdateRateLimit) {
+        let mut current = self
+            .limit
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Buckets counted by another key would never be refilled nor purged
+        if current.key != limit.key {
+            self.buckets.clear();
+        }
+        *current = limit;
+    }
+
+    /// Returns whether updates are limited at all.
+    pub fn is_enabled(&self) -> bool {
+        self.limit().per_second > 0.0
+    }
+
+    /// Takes a token for an update sent by a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the client sending the update
+    /// * `remote_ip` - Address the client connected from, if known
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the update may be applied, otherwise a `LimitExceeded` error telling when the
+    /// client may send its next update
+    pub fn check(&self, client_id: &str, remote_ip: Option<IpAddr>) -> Result<(), DomainError> {
+        let limit = self.limit();
+        if limit.per_second <= 0.0 {
+            return Ok(());
+        }
+
+        let key = match (limit.key, remote_ip) {
+            (RateLimitKey::Ip, Some(ip)) => ip.to_string(),
+            _ => client_id.to_string(),
+        };
+        let capacity = limit.burst.max(1) as f64;
+        let now = Instant::now();
+
+        let mut bucket = self.buckets.entry(key).or_insert_with(|| Bucket {
+            tokens: capacity,
+            refilled: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(capacity);
+        bucket.refilled = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second);
+        Err(DomainError::LimitExceeded(format!(
+            "Too many updates, retry in {} ms",
+            retry_after.as_millis().max(1)
+        )))
+    }
+
+    /// Drops the buckets that refilled completely, which are the same as new ones.
+    pub fn purge_idle(&self) {
+        let limit = self.limit();
+        if limit.per_second <= 0.0 {
+            return;
+        }
+
+        let capacity = limit.burst.max(1) as f64;
+        let now = Instant::now();
+        self.buckets.retain(|_, bucket| {
+            let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+            bucket.tokens + elapsed * limit.per_second < capacity
+        });
+    }
+}
+
+impl Default for UpdateRateLimiter {
+    fn default() -> Self {
+        Self::new(UpdateRateLimit::default())
+    }
+}
@@ -1,34 +1,105 @@
-use std::sync::Arc;
+use std::{borrow::Cow, collections::HashSet, sync::Arc, time::Duration};
 
-use base64::{engine::general_purpose::STANDARD, Engine};
-use chrono::Utc;
+use async_trait::async_trait;
 use dashmap::DashMap;
 use futures::StreamExt;
-use tokio::sync::mpsc;
-use tracing::{error, info, warn};
-use volo_grpc::{BoxStream, RecvStream, Request, Response, Status};
+use tokio::sync::{
+    broadcast::error::RecvError,
+    mpsc::{self, error::TrySendError},
+};
+use tracing::{debug, error, info, warn};
+use volo_grpc::{metadata::MetadataValue, BoxStream, RecvStream, Request, Response, Status};
 use yjs_collaboration_server_common::volo_gen::collaboration::{
-    client_message, server_message, ActiveUser, AwarenessUpdate, ClientMessage,
-    CollaborationService, DocumentState, ErrorMessage, ErrorType, GetActiveUsersRequest,
-    GetActiveUsersResponse, GetDocumentStateRequest, GetDocumentStateResponse, ServerMessage,
-    SyncResponse as ProtoSyncResponse, UpdateMessage, UserJoined, UserLeft,
+    client_message, server_message, ActiveUser, ApplyTransactionRequest, ApplyTransactionResponse,
+    AwarenessUpdate, ClientMessage, CollaborationService, CursorUpdate, DocumentState,
+    ErrorMessage, ErrorType, GetActiveUsersRequest, GetActiveUsersResponse,
+    GetDocumentStateRequest, GetDocumentStateResponse, GetUserDocumentsRequest,
+    GetUserDocumentsResponse, Notice as ProtoNotice, NoticeKind as ProtoNoticeKind,
+    NoticeSeverity as ProtoNoticeSeverity, PayloadDictionary, PayloadEncoding, ReplicateRequest,
+    ReplicationMessage, ServerMessage, Subdocuments, SyncRequired,
+    SyncResponse as ProtoSyncResponse, SyncStep1, SyncStep2, UpdateEncoding as ProtoUpdateEncoding,
+    UpdateMessage, UpdateOriginKind, UserDocument as ProtoUserDocument, UserJoined, UserLeft,
 };
 use yjs_collaboration_server_domain::{
+    errors::DomainError,
     repositories::document_repository::DocumentRepository,
     services::document_service::DocumentService,
+    value_objects::{
+        access_role::{AccessGrant, AccessRole},
+        cursor::CursorPosition,
+        diff_throttle::DiffLimiter,
+        feature_policy::NAMESPACE_SEPARATOR,
+        message::{Notice, NoticeKind, NoticeSeverity},
+        tenant::{scoped_document_id, unscoped_document_id},
+        undo_action::UndoAction,
+        update_encoding::UpdateEncoding,
+        update_origin::{OriginKind, UpdateOrigin, UpdateTransport},
+    },
+};
+
+use crate::{
+    admission::{AdmissionController, LoadSignals},
+    broadcast_hub::{BroadcastHub, EchoPolicy, HubEvent},
+    clock::{server_time, ClockOffset},
+    delivery_stats::DropReason,
+    http::admin::constant_time_eq,
+    outbox::SessionOutbox,
+    payload_compression::PayloadCompression,
+    session_registry::{
+        check_metadata, permission_notice, CursorEvent, PresenceEvent, Session, SessionRegistry,
+        Transport,
+    },
+    transport_compression::{TransportCompression, TransportEncoding},
 };
 
-/// User session information for tracking active users
-#[derive(Clone, Debug)]
-struct UserSession {
-    user_id: String,
-    user_name: String,
-    user_color: String,
-    client_id: String,
-    document_id: String,
-    last_seen: i64,
-    user_metadata: std::collections::HashMap<String, String>,
-    sender: mpsc::Sender<Result<ServerMessage, Status>>,
+/// Interval at which a replication stream looks for documents created since it started.
+const DOCUMENT_SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Outbound channel of a `Collaborate` stream.
+type StreamSender = mpsc::Sender<Result<ServerMessage, Status>>;
+
+/// An update of one of the documents of a cross-document transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionUpdate {
+    /// Identifier of the document, scoped to its tenant
+    pub doc_id: String,
+    /// The binary Yjs v1 update
+    pub update: Vec<u8>,
+}
+
+/// Why a cross-document transaction was aborted.
+#[derive(Debug)]
+pub struct TransactionFailure {
+    /// Identifier of the document whose update failed, empty if the transaction was refused
+    /// before any update was applied
+    pub doc_id: String,
+    /// Why the update failed
+    pub error: DomainError,
+    /// Whether the updates applied before the failure were all rolled back
+    pub rolled_back: bool,
+}
+
+/// Atomic application of updates to several documents, e.g. an index and its content.
+#[async_trait]
+pub trait TransactionControl: Send + Sync {
+    /// Applies updates to several documents, all or none.
+    ///
+    /// # Parameters
+    ///
+    /// * `updates` - The updates, applied in order, at most one per document
+    /// * `origin` - Who sent the updates, and through which transport
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u64>)` - The sequence number of each document once its update was applied, in the
+    ///   order of the updates
+    /// * `Err(TransactionFailure)` - The update that failed, once the updates applied before it
+    ///   were rolled back
+    async fn apply_transaction(
+        &self,
+        updates: Vec<TransactionUpdate>,
+        origin: UpdateOrigin<'_>,
+    ) -> Result<Vec<u64>, TransactionFailure>;
 }
 
 /// Implementation of the Yjs collaboration gRPC service.
@@ -39,11 +110,21 @@ struct UserSession {
 pub struct CollaborationServiceImpl<R: DocumentRepository> {
     /// Document service handling core business logic for documents
     document_service: Arc<DocumentService<R>>,
-    /// Manages active connection sessions with session ID as key and message sender channel as
-    /// value Using DashMap for improved concurrent performance compared to Mutex<HashMap>
-    active_sessions: Arc<DashMap<String, mpsc::Sender<Result<ServerMessage, Status>>>>,
-    /// Tracks active user sessions with detailed user information
-    user_sessions: Arc<DashMap<String, UserSession>>,
+    /// Outbound channels of the streams collaborating on each document, by document ID and then
+    /// client ID, so a broadcast only visits the streams of its own document
+    active_sessions: Arc<DashMap<String, DashMap<String, StreamSender>>>,
+    /// Clients present on each document, shared with the WebSocket adapter
+    sessions: Arc<SessionRegistry>,
+    /// Admission controller used to shed new streams during overload
+    admission: Arc<AdmissionController>,
+    /// Updates relayed to each client, replayed when it reconnects after a dropped stream
+    outbox: Arc<SessionOutbox>,
+    /// Token warm standbys must present to replicate the documents, or `None` to refuse them
+    replication_token: Option<String>,
+    /// Generic compression of the large update payloads exchanged with clients
+    transport_compression: TransportCompression,
+    /// Use case applying cross-document transactions, or `None` to refuse them
+    transactions: Option<Arc<dyn TransactionControl>>,
 }
 
 impl<R: DocumentRepository + Send + Sync + 'static> CollaborationServiceImpl<R> {
@@ -52,27 +133,135 @@ impl<R: DocumentRepository + Send + Sync + 'static> CollaborationServiceImpl<R>
     /// # Parameters
     ///
     /// * `document_service` - An Arc reference to document service
+    /// * `admission` - Admission controller used to shed new streams during overload
+    /// * `sessions` - Registry of the clients present on each document, over any transport
+    /// * `outbox` - Outbox buffering the updates missed by clients whose stream ended
     ///
     /// # Returns
     ///
     /// A new instance of `CollaborationServiceImpl`
-    pub fn new(document_service: Arc<DocumentService<R>>) -> Self {
+    pub fn new(
+        document_service: Arc<DocumentService<R>>,
+        admission: Arc<AdmissionController>,
+        sessions: Arc<SessionRegistry>,
+        outbox: Arc<SessionOutbox>,
+    ) -> Self {
         Self {
             document_service,
             active_sessions: Arc::new(DashMap::new()),
-            user_sessions: Arc::new(DashMap::new()),
+            sessions,
+            admission,
+            outbox,
+            replication_token: None,
+            transport_compression: TransportCompression::default(),
+            transactions: None,
+        }
+    }
+
+    /// Allows warm standbys presenting a token to replicate the documents.
+    ///
+    /// # Parameters
+    ///
+    /// * `token` - Token expected in `Replicate` requests; `None` or an empty token refuses every
+    ///   standby
+    ///
+    /// # Returns
+    ///
+    /// The `CollaborationServiceImpl` accepting replication streams
+    pub fn with_replication_token(mut self, token: Option<String>) -> Self {
+        self.replication_token = token.filter(|token| !token.is_empty());
+        self
+    }
+
+    /// Sets the generic compression of the large update payloads exchanged with clients.
+    ///
+    /// # Parameters
+    ///
+    /// * `settings` - Thresholds and levels of the compression
+    ///
+    /// # Returns
+    ///
+    /// The `CollaborationServiceImpl` compressing payloads with the given settings
+    pub fn with_transport_compression(mut self, settings: TransportCompression) -> Self {
+        self.transport_compression = settings;
+        self
+    }
+
+    /// Serves cross-document transactions through the given use case.
+    ///
+    /// # Parameters
+    ///
+    /// * `transactions` - The use case applying the updates of a transaction, all or none
+    ///
+    /// # Returns
+    ///
+    /// The `CollaborationServiceImpl` accepting `ApplyTransaction` requests
+    pub fn with_transactions(mut self, transactions: Arc<dyn TransactionControl>) -> Self {
+        self.transactions = Some(transactions);
+        self
+    }
+
+    /// Samples the current load signals used for admission control.
+    ///
+    /// The queue depth is the number of messages waiting in outbound session channels,
+    /// plus those waiting in the connections' broadcast hubs. Streams registered on several
+    /// documents are counted once per document, so the value is an upper bound.
+    fn load_signals(&self) -> LoadSignals {
+        let queue_depth = self
+            .active_sessions
+            .iter()
+            .map(|streams| {
+                streams
+                    .iter()
+                    .map(|sender| sender.max_capacity() - sender.capacity())
+                    .sum::<usize>()
+            })
+            .sum::<usize>()
+            + self.sessions.delivery_stats().queue_depth();
+
+        LoadSignals {
+            loaded_documents: self.document_service.loaded_document_count(),
+            queue_depth,
+        }
+    }
+
+    /// Builds a server message stamped with the server's clock.
+    ///
+    /// The clock offset hint is filled in per recipient when the message is
+    /// delivered, since each connection has its own offset.
+    ///
+    /// # Parameters
+    ///
+    /// * `document_id` - Unique identifier for the document
+    /// * `message_type` - The message payload
+    ///
+    /// # Returns
+    ///
+    /// A `ServerMessage` carrying the current server time
+    fn server_message(
+        document_id: &str,
+        message_type: server_message::MessageType,
+    ) -> ServerMessage {
+        ServerMessage {
+            document_id: document_id.to_string().into(),
+            timestamp: server_time(),
+            message_type: Some(message_type),
+            clock_offset: 0,
         }
     }
 
     /// Handles messages received from clients.
     ///
-    /// Processes different message types such as sync requests, document updates,
+    /// Processes different message types such as sync handshakes, document updates,
     /// or users joining a document.
     ///
     /// # Parameters
     ///
     /// * `client_msg` - The message received from the client
     /// * `tx` - Channel for sending responses back to the client
+    /// * `hub` - The connection's broadcast hub, subscribed to a document once the client has
+    ///   synchronized with it and following the echo policy the client joined with
+    /// * `diff_limiter` - The connection's cap on concurrent diffs
     ///
     /// # Returns
     ///
@@ -85,133 +274,415 @@ impl<R: DocumentRepository + Send + Sync + 'static> CollaborationServiceImpl<R>
         &self,
         client_msg: ClientMessage,
         tx: &mpsc::Sender<Result<ServerMessage, Status>>,
+        hub: &mut BroadcastHub,
+        diff_limiter: &DiffLimiter,
     ) -> Result<(), Status> {
         let client_id = client_msg.client_id.to_string();
-        let document_id = client_msg.document_id.to_string();
+        // A tenant scopes the document; replies carry the scoped identifier
+        let document_id = match scoped_document_id(&client_msg.tenant, &client_msg.document_id) {
+            Ok(document_id) => document_id,
+            Err(e) => {
+                warn!("Rejected message of client {}: {}", client_id, e);
+                // The identifier could not be scoped, but is still named with its tenant prefix
+                let document_id = if client_msg.tenant.is_empty() {
+                    client_msg.document_id.to_string()
+                } else {
+                    format!(
+                        "{}{}{}",
+                        client_msg.tenant, NAMESPACE_SEPARATOR, client_msg.document_id
+                    )
+                };
+                let error_msg = Self::server_message(
+                    &document_id,
+                    server_message::MessageType::Error(error_message(&document_id, &e)),
+                );
+                let _ = tx.send(Ok(error_msg)).await;
+                return Ok(());
+            }
+        };
+
+        // Any message proves the user is still present; activity is tracked in server time
+        self.sessions.touch(&document_id, &client_id);
 
         if let Some(message_type) = client_msg.message_type {
-            match message_type {
-                client_message::MessageType::SyncRequest(sync_req) => {
-                    let (response, _) = self
-                        .document_service
-                        .handle_sync_request(&document_id, Some(&sync_req.state_vector.to_vec()))
-                        .await;
+            // Documents are served only to clients holding a role on them; guests
+            // are denied unless the document's policy allows them
+            if matches!(
+                message_type,
+                client_message::MessageType::SyncRequest(_)
+                    | client_message::MessageType::SyncStep1(_)
+                    | client_message::MessageType::SyncStep2(_)
+                    | client_message::MessageType::Update(_)
+                    | client_message::MessageType::GapReport(_)
+                    | client_message::MessageType::SubdocumentsRequest(_)
+                    | client_message::MessageType::Undo(_)
+                    | client_message::MessageType::UpdateBatch(_)
+            ) {
+                let user_id = self.sessions.user_id(&document_id, &client_id);
+                let role = match self
+                    .document_service
+                    .access_role(&document_id, user_id.as_deref())
+                {
+                    Ok(role) => role,
+                    Err(e) => {
+                        warn!("Denied access to document {}: {}", document_id, e);
+                        // Clients of a document owned by another instance are sent to its owner
+                        if let DomainError::Moved { owner, .. } = &e {
+                            let notice = Notice::new(
+                                NoticeKind::Redirect,
+                                NoticeSeverity::Warning,
+                                e.to_string(),
+                            )
+                            .with_document(&document_id)
+                            .with_redirect_url(&owner.grpc_addr);
+                            let _ = tx.send(Ok(Self::notice_message(&notice))).await;
+                        }
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
+                        let _ = tx.send(Ok(error_msg)).await;
+                        return Ok(());
+                    }
+                };
 
-                    let proto_response = ServerMessage {
-                        document_id: document_id.clone().into(),
-                        timestamp: Utc::now().timestamp(),
-                        message_type: Some(server_message::MessageType::SyncResponse(
-                            ProtoSyncResponse {
-                                update_data: response.update.unwrap_or_default().into(),
-                            },
-                        )),
-                    };
+                // Read-only clients complete the handshake, but their side of it is
+                // ignored, as in y-protocols
+                if matches!(message_type, client_message::MessageType::SyncStep2(_))
+                    && !role.can_write()
+                {
+                    debug!(
+                        "Ignored sync step 2 of read-only client {} on document {}",
+                        client_id, document_id
+                    );
+                    return Ok(());
+                }
 
-                    if tx.send(Ok(proto_response)).await.is_err() {
-                        warn!("Failed to send sync response to client {}", client_id);
+                // Read-only clients keep receiving updates but may not send any
+                if matches!(
+                    message_type,
+                    client_message::MessageType::Update(_)
+                        | client_message::MessageType::Undo(_)
+                        | client_message::MessageType::UpdateBatch(_)
+                ) && !role.can_write()
+                {
+                    warn!(
+                        "Rejected update from read-only client {} on document {}",
+                        client_id, document_id
+                    );
+                    let error_msg = Self::server_message(
+                        &document_id,
+                        server_message::MessageType::Error(ErrorMessage {
+                            error_code: 403,
+                            error_message: "Read-only clients may not update this document".into(),
+                            error_type: ErrorType::PERMISSION_DENIED,
+                            document_id: document_id.clone().into(),
+                        }),
+                    );
+                    let _ = tx.send(Ok(error_msg)).await;
+                    return Ok(());
+                }
+
+                // Throttled updates are not applied; the client resends them later.
+                // The peer address of gRPC streams is not tracked, so they are
+                // always limited per client, and a batch counts as one update
+                if matches!(
+                    message_type,
+                    client_message::MessageType::SyncStep2(_)
+                        | client_message::MessageType::Update(_)
+                        | client_message::MessageType::Undo(_)
+                        | client_message::MessageType::UpdateBatch(_)
+                ) {
+                    if let Err(e) = self.sessions.update_limiter().check(&client_id, None) {
+                        warn!("Throttled update from client {}: {}", client_id, e);
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
+                        let _ = tx.send(Ok(error_msg)).await;
+                        return Ok(());
                     }
                 }
-                client_message::MessageType::Update(update) => {
-                    if let Err(e) = self
-                        .document_service
-                        .handle_binary_update(&document_id, &update.update_data)
-                        .await
-                    {
+            }
+
+            // Updates are attributed to the identity the client joined with
+            let user_id = self.sessions.user_id(&document_id, &client_id);
+            let origin =
+                UpdateOrigin::new(&client_id, UpdateTransport::Grpc).with_user(user_id.as_deref());
+
+            match message_type {
+                client_message::MessageType::SyncRequest(sync_req) => {
+                    self.handle_sync(
+                        &document_id,
+                        &sync_req.state_vector,
+                        false,
+                        tx,
+                        hub,
+                        diff_limiter,
+                    )
+                    .await?;
+                }
+                client_message::MessageType::SyncStep1(step1) => {
+                    self.handle_sync(
+                        &document_id,
+                        &step1.state_vector,
+                        true,
+                        tx,
+                        hub,
+                        diff_limiter,
+                    )
+                    .await?;
+                }
+                // The client's reply to the server's SyncStep1 is applied like any update
+                client_message::MessageType::SyncStep2(SyncStep2 {
+                    update_data,
+                    encoding,
+                    ..
+                })
+                | client_message::MessageType::Update(UpdateMessage {
+                    update_data,
+                    encoding,
+                    ..
+                }) => {
+                    let update = self.decode_update(hub.update_encoding(), encoding, &update_data);
+                    let applied = match update {
+                        Ok(update) => self
+                            .document_service
+                            .handle_binary_update(&document_id, origin, &update)
+                            .await
+                            .map(|_| ()),
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = applied {
                         error!("Failed to handle update: {}", e);
-                        let error_msg = ServerMessage {
-                            document_id: document_id.into(),
-                            timestamp: Utc::now().timestamp(),
-                            message_type: Some(server_message::MessageType::Error(ErrorMessage {
-                                error_code: 400,
-                                error_message: e.into(),
-                                error_type: ErrorType::INVALID_UPDATE,
-                            })),
-                        };
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
+                        let _ = tx.send(Ok(error_msg)).await;
+                    }
+                }
+                client_message::MessageType::UpdateBatch(batch) => {
+                    let updates: Result<Vec<Vec<u8>>, DomainError> = batch
+                        .updates
+                        .iter()
+                        .map(|update| {
+                            self.decode_update(hub.update_encoding(), batch.encoding, update)
+                                .map(Cow::into_owned)
+                        })
+                        .collect();
+                    let applied = match updates {
+                        Ok(updates) => {
+                            self.document_service
+                                .apply_updates_batch(&document_id, origin, updates)
+                                .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = applied {
+                        error!("Failed to handle update batch: {}", e);
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
                         let _ = tx.send(Ok(error_msg)).await;
-                    } else {
-                        // Broadcast update to other clients
-                        self.broadcast_update(&document_id, &client_id, &update.update_data)
-                            .await;
                     }
                 }
                 client_message::MessageType::JoinDocument(join) => {
-                    info!("User {} joined document {}", join.user_id, document_id);
+                    hub.set_echo_policy(EchoPolicy::from_flag(join.echo_own_updates));
+                    hub.set_compression(join.accept_compressed_updates);
+                    hub.set_transport_encoding(
+                        transport_encoding(join.accept_encoding).unwrap_or_default(),
+                    );
+                    hub.set_update_encoding(update_encoding(join.update_encoding));
+
+                    let user_metadata = join
+                        .user_metadata
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect();
+                    if let Err(e) = check_metadata(&user_metadata) {
+                        warn!("Rejected join of client {}: {}", client_id, e);
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
+                        let _ = tx.send(Ok(error_msg)).await;
+                        return Ok(());
+                    }
 
-                    // Create user session
-                    let session_id = format!("{}_{}", document_id, client_id);
-                    let user_session = UserSession {
+                    // The role is resolved again on every sync request and update; the
+                    // registry's copy follows the permission changes made by operators
+                    let user_id = Some(join.user_id.as_str()).filter(|id| !id.is_empty());
+                    let role = self
+                        .document_service
+                        .access_role(&document_id, user_id)
+                        .unwrap_or(AccessRole::ReadOnly);
+
+                    // Other users are notified through the registry's presence events, which
+                    // also log the join with its metadata
+                    let joined = self.sessions.join(Session {
                         user_id: join.user_id.to_string(),
                         user_name: join.user_name.to_string(),
                         user_color: join.user_color.to_string(),
-                        client_id: client_id.to_string(),
-                        document_id: document_id.to_string(),
-                        last_seen: Utc::now().timestamp(),
-                        user_metadata: join
-                            .user_metadata
-                            .iter()
-                            .map(|(k, v)| (k.to_string(), v.to_string()))
-                            .collect(),
-                        sender: tx.clone(),
-                    };
-
-                    self.update_user_session(session_id, user_session);
-
-                    // Notify other users
-                    let user_joined = ServerMessage {
-                        document_id: document_id.clone().into(),
-                        timestamp: Utc::now().timestamp(),
-                        message_type: Some(server_message::MessageType::UserJoined(UserJoined {
-                            user_id: join.user_id.clone(),
-                            user_name: join.user_name.clone(),
-                            user_color: join.user_color.clone(),
-                            client_id: client_id.clone().into(),
-                            user_metadata: join.user_metadata.clone(),
-                        })),
-                    };
-
-                    self.broadcast_to_document(&document_id, user_joined, Some(&client_id))
-                        .await;
+                        user_metadata,
+                        role,
+                        ..Session::guest(&client_id, &document_id, Transport::Grpc)
+                    });
+                    if let Err(e) = joined {
+                        warn!("Rejected join of client {}: {}", client_id, e);
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
+                        let _ = tx.send(Ok(error_msg)).await;
+                    }
                 }
                 client_message::MessageType::LeaveDocument(leave) => {
                     info!("User {} left document {}", leave.user_id, document_id);
 
-                    // Remove user session
-                    let session_id = format!("{}_{}", document_id, client_id);
-                    self.remove_user_session(&session_id);
-
-                    let user_left = ServerMessage {
-                        document_id: document_id.clone().into(),
-                        timestamp: Utc::now().timestamp(),
-                        message_type: Some(server_message::MessageType::UserLeft(UserLeft {
-                            user_id: leave.user_id,
-                            client_id: client_id.clone().into(),
-                        })),
-                    };
-
-                    self.broadcast_to_document(&document_id, user_left, Some(&client_id))
-                        .await;
+                    self.sessions.leave(&document_id, &client_id);
+                    self.outbox.forget(&document_id, &client_id);
                 }
                 client_message::MessageType::Awareness(awareness) => {
                     // Broadcast awareness update
-                    let awareness_msg = ServerMessage {
-                        document_id: document_id.clone().into(),
-                        timestamp: Utc::now().timestamp(),
-                        // Handle heartbeat, update user activity status
-                        message_type: Some(server_message::MessageType::Awareness(
-                            AwarenessUpdate {
-                                client_id: awareness.client_id.clone(),
-                                user_info: awareness.user_info.clone(),
-                                awareness_state: awareness.awareness_state.clone(),
-                                timestamp: awareness.timestamp,
-                            },
-                        )),
-                    };
+                    let awareness_msg = Self::server_message(
+                        &document_id,
+                        server_message::MessageType::Awareness(AwarenessUpdate {
+                            client_id: awareness.client_id.clone(),
+                            user_info: awareness.user_info.clone(),
+                            awareness_state: awareness.awareness_state.clone(),
+                            // Client clocks are not trusted for relayed messages
+                            timestamp: server_time(),
+                        }),
+                    );
 
                     self.broadcast_to_document(&document_id, awareness_msg, Some(&client_id))
                         .await;
                 }
+                client_message::MessageType::Cursor(cursor) => {
+                    let position = if cursor.anchor.is_empty() && cursor.head.is_empty() {
+                        Ok(None)
+                    } else {
+                        CursorPosition::new(cursor.anchor.to_vec(), cursor.head.to_vec()).map(Some)
+                    };
+                    let published = position.and_then(|position| {
+                        self.sessions
+                            .publish_cursor(&document_id, &client_id, position)
+                    });
+                    if let Err(e) = published {
+                        warn!(
+                            "Rejected cursor of client {} on document {}: {}",
+                            client_id, document_id, e
+                        );
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
+                        let _ = tx.send(Ok(error_msg)).await;
+                    }
+                }
+                client_message::MessageType::GapReport(gap) => {
+                    // A client reconnecting after a dropped stream resumes from the outbox;
+                    // the live subscription is taken first so no update falls in between
+                    let after = gap.last_sequence_number.max(0) as u64;
+                    let (until, updates) = self
+                        .document_service
+                        .subscribe_with_sequence(&document_id)
+                        .await;
+                    if !hub.is_subscribed(&document_id) {
+                        if let Some(missed) = self
+                            .outbox
+                            .resume(&document_id, &client_id, after, until)
+                            .await
+                        {
+                            info!(
+                                "Client {} resumed document {} after #{}, replaying {} updates",
+                                client_id,
+                                document_id,
+                                after,
+                                missed.len()
+                            );
+                            for update in missed {
+                                if !hub.delivers(&update.source) {
+                                    continue;
+                                }
+                                let mut message = self
+                                    .hub_message(HubEvent::Update {
+                                        doc_id: document_id.clone(),
+                                        update: update.update,
+                                        source: update.source,
+                                        user_id: update.user_id,
+                                        origin: update.origin,
+                                        sequence_number: update.sequence_number,
+                                    })
+                                    .await;
+                                Self::convert_relayed_update(hub.update_encoding(), &mut message);
+                                if tx.send(Ok(message)).await.is_err() {
+                                    return Ok(());
+                                }
+                            }
+                            hub.subscribe(&document_id, updates);
+                            return Ok(());
+                        }
+                    }
+
+                    // 客户端发现更新序列号不连续，通知其携带状态向量重新同步
+                    info!(
+                        "Client {} missed updates on document {} after #{}, requesting a resync",
+                        client_id, document_id, gap.last_sequence_number
+                    );
+                    let sync_required = Self::server_message(
+                        &document_id,
+                        server_message::MessageType::SyncRequired(SyncRequired {
+                            sequence_number: until as i64,
+                        }),
+                    );
+                    if tx.send(Ok(sync_required)).await.is_err() {
+                        warn!("Failed to send sync required to client {}", client_id);
+                    }
+                }
+                // The resulting update reaches the client through its hub, like any update
+                client_message::MessageType::Undo(undo) => {
+                    let action = if undo.redo {
+                        UndoAction::Redo
+                    } else {
+                        UndoAction::Undo
+                    };
+                    if let Err(e) = self
+                        .document_service
+                        .undo_document(&document_id, origin, action)
+                        .await
+                    {
+                        warn!("Failed to {} on document {}: {}", action, document_id, e);
+                        let error_msg = Self::server_message(
+                            &document_id,
+                            server_message::MessageType::Error(error_message(&document_id, &e)),
+                        );
+                        let _ = tx.send(Ok(error_msg)).await;
+                    }
+                }
+                client_message::MessageType::SubdocumentsRequest(_) => {
+                    let message_type =
+                        match self.document_service.list_subdocuments(&document_id).await {
+                            Ok(guids) => server_message::MessageType::Subdocuments(Subdocuments {
+                                guids: guids.into_iter().map(Into::into).collect(),
+                            }),
+                            Err(e) => {
+                                server_message::MessageType::Error(error_message(&document_id, &e))
+                            }
+                        };
+                    if tx
+                        .send(Ok(Self::server_message(&document_id, message_type)))
+                        .await
+                        .is_err()
+                    {
+                        warn!("Failed to send subdocuments to client {}", client_id);
+                    }
+                }
                 client_message::MessageType::Heartbeat(_) => {
-                    // 处理心跳，更新用户活跃状态
+                    // 一次心跳即刷新该客户端在所有文档上的活跃状态，避免被空闲会话回收
+                    self.sessions.touch_client(&client_id);
                 }
             }
         }
@@ -219,31 +690,420 @@ impl<R: DocumentRepository + Send + Sync + 'static> CollaborationServiceImpl<R>
         Ok(())
     }
 
-    /// Broadcasts document update messages to other clients.
+    /// Answers a client's state vector with the updates it is missing.
+    ///
+    /// A legacy `SyncRequest` is answered with a `SyncResponse`. A `SyncStep1`
+    /// is answered with a `SyncStep2`, then with the server's own `SyncStep1`,
+    /// so the client replies with the updates the server is missing, such as
+    /// edits made offline. In both cases an oversized diff is answered with its
+    /// first chunk, the others following as updates through the hub, and the
+    /// client receives the document's updates from then on.
     ///
     /// # Parameters
     ///
-    /// * `document_id` - Unique identifier for the document
-    /// * `origin_client_id` - ID of the client that sent the update
-    /// * `update_data` - The update data content
-    async fn broadcast_update(
+    /// * `document_id` - Identifier of the document to synchronize
+    /// * `state_vector` - The client's state vector
+    /// * `handshake` - Whether the client started the two-step handshake with a `SyncStep1`
+    /// * `tx` - Channel for sending responses back to the client
+    /// * `hub` - The connection's broadcast hub
+    /// * `diff_limiter` - The connection's cap on concurrent diffs
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure with appropriate status
+    async fn handle_sync(
         &self,
         document_id: &str,
-        origin_client_id: &str,
-        update_data: &[u8],
-    ) {
-        let update_msg = ServerMessage {
-            document_id: document_id.to_string().into(),
-            timestamp: Utc::now().timestamp(),
-            message_type: Some(server_message::MessageType::Update(UpdateMessage {
-                // Sequence numbers can be implemented
-                sequence_number: 0,
-                update_data: update_data.to_vec().into(),
-                origin_client_id: origin_client_id.to_string().into(),
-            })),
+        state_vector: &[u8],
+        handshake: bool,
+        tx: &mpsc::Sender<Result<ServerMessage, Status>>,
+        hub: &mut BroadcastHub,
+        diff_limiter: &DiffLimiter,
+    ) -> Result<(), Status> {
+        // Subdocuments are loaded lazily, once their parent references them
+        if let Err(e) = self.document_service.check_subdocument(document_id).await {
+            warn!("Rejected sync request for document {}: {}", document_id, e);
+            let error_msg = Self::server_message(
+                document_id,
+                server_message::MessageType::Error(error_message(document_id, &e)),
+            );
+            let _ = tx.send(Ok(error_msg)).await;
+            return Ok(());
+        }
+
+        let permit = match diff_limiter.try_acquire() {
+            Ok(permit) => permit,
+            Err(e) => {
+                warn!("Rejected sync request for document {}: {}", document_id, e);
+                let error_msg = Self::server_message(
+                    document_id,
+                    server_message::MessageType::Error(error_message(document_id, &e)),
+                );
+                let _ = tx.send(Ok(error_msg)).await;
+                return Ok(());
+            }
+        };
+
+        let (response, chunks, _) = match self
+            .document_service
+            .handle_chunked_sync_request(document_id, Some(state_vector))
+            .await
+        {
+            Ok(sync) => sync,
+            Err(e) => {
+                warn!("Failed to sync document {}: {}", document_id, e);
+                let error_msg = Self::server_message(
+                    document_id,
+                    server_message::MessageType::Error(error_message(document_id, &e)),
+                );
+                let _ = tx.send(Ok(error_msg)).await;
+                return Ok(());
+            }
+        };
+
+        let update = response.update.unwrap_or_default();
+        let update = match encode_update(hub.update_encoding(), update) {
+            Ok(update) => update,
+            Err(e) => {
+                warn!(
+                    "Failed to encode the diff of document {}: {}",
+                    document_id, e
+                );
+                let error_msg = Self::server_message(
+                    document_id,
+                    server_message::MessageType::Error(error_message(document_id, &e)),
+                );
+                let _ = tx.send(Ok(error_msg)).await;
+                return Ok(());
+            }
+        };
+        let (encoding, update_data) = match self
+            .transport_compression
+            .encode(hub.transport_encoding(), &update)
+        {
+            Some((encoding, compressed)) => (payload_encoding(encoding), compressed.into()),
+            None => (PayloadEncoding::ENCODING_IDENTITY, update.into()),
+        };
+        let sequence_number = response.sequence_number as i64;
+        let mut replies = vec![if handshake {
+            server_message::MessageType::SyncStep2(SyncStep2 {
+                update_data,
+                sequence_number,
+                encoding,
+            })
+        } else {
+            server_message::MessageType::SyncResponse(ProtoSyncResponse {
+                update_data,
+                sequence_number,
+                encoding,
+            })
+        }];
+        if handshake {
+            replies.push(server_message::MessageType::SyncStep1(SyncStep1 {
+                state_vector: response.state_vector.unwrap_or_default().into(),
+            }));
+        }
+
+        for reply in replies {
+            if tx
+                .send(Ok(Self::server_message(document_id, reply)))
+                .await
+                .is_err()
+            {
+                warn!("Failed to send sync response on document {}", document_id);
+                return Ok(());
+            }
+        }
+
+        // Relay the document's updates from now on, whichever transport
+        // or server instance they were applied on
+        hub.subscribe(
+            document_id,
+            self.document_service.subscribe(document_id).await,
+        );
+        if !chunks.is_empty() {
+            hub.deliver(document_id, chunks, permit);
+        }
+        Ok(())
+    }
+
+    /// Converts an event of the connection's broadcast hub into a server message.
+    ///
+    /// Document updates are relayed through the hub rather than sent to other
+    /// sessions directly, so updates applied by WebSocket clients or by other
+    /// server instances reach gRPC clients too.
+    ///
+    /// # Parameters
+    ///
+    /// * `event` - The hub event
+    ///
+    /// # Returns
+    ///
+    /// The update message to deliver to the client
+    async fn hub_message(&self, event: HubEvent) -> ServerMessage {
+        let (document_id, update_data, origin, sequence_number) = match event {
+            HubEvent::Update {
+                doc_id,
+                update,
+                source,
+                user_id,
+                origin,
+                sequence_number,
+            } => (doc_id, update, (source, user_id, origin), sequence_number),
+            HubEvent::Lagged { doc_id, skipped } => {
+                // Resend the full state; applying it is idempotent for the client
+                warn!(
+                    "Stream lagged by {} updates on document '{}', resyncing",
+                    skipped, doc_id
+                );
+                let response = match self
+                    .document_service
+                    .handle_sync_request(&doc_id, None)
+                    .await
+                {
+                    Ok((response, _)) => response,
+                    Err(e) => {
+                        // The client has to sync again, as it missed updates
+                        warn!("Failed to resync document '{}': {}", doc_id, e);
+                        return Self::server_message(
+                            &doc_id,
+                            server_message::MessageType::Error(error_message(&doc_id, &e)),
+                        );
+                    }
+                };
+                let update = response.update.unwrap_or_default();
+                let origin = (String::new(), None, OriginKind::Server);
+                (doc_id, update, origin, response.sequence_number)
+            }
+        };
+        let (origin_client_id, origin_user_id, origin_kind) = origin;
+
+        Self::server_message(
+            &document_id,
+            server_message::MessageType::Update(UpdateMessage {
+                sequence_number: sequence_number as i64,
+                update_data: update_data.into(),
+                origin_client_id: origin_client_id.into(),
+                dictionary_id: 0,
+                encoding: PayloadEncoding::ENCODING_IDENTITY,
+                origin_user_id: origin_user_id.unwrap_or_default().into(),
+                origin_kind: update_origin_kind(origin_kind),
+            }),
+        )
+    }
+
+    /// Compresses the update of a message relayed to the client with its document's dictionary,
+    /// if the client accepts compressed updates.
+    ///
+    /// Updates the dictionary does not compress, like those of documents
+    /// without a dictionary, are compressed with the generic algorithm the
+    /// client decodes instead, if they are large enough.
+    ///
+    /// # Parameters
+    ///
+    /// * `compression` - The compression negotiated by the client
+    /// * `accepted` - The generic compression the client decodes
+    /// * `message` - The message about to be relayed, compressed in place
+    ///
+    /// # Returns
+    ///
+    /// The dictionary message to send before the update, if the client does not hold the
+    /// dictionary yet
+    fn compress_update(
+        &self,
+        compression: &mut PayloadCompression,
+        accepted: TransportEncoding,
+        message: &mut ServerMessage,
+    ) -> Option<ServerMessage> {
+        let Some(server_message::MessageType::Update(update)) = message.message_type.as_mut()
+        else {
+            return None;
+        };
+        let Some((dictionary, compressed)) = compression.compress(
+            self.document_service.payload_dictionaries(),
+            &message.document_id,
+            &update.update_data,
+        ) else {
+            if let Some((encoding, compressed)) = self
+                .transport_compression
+                .encode(accepted, &update.update_data)
+            {
+                update.update_data = compressed.into();
+                update.encoding = payload_encoding(encoding);
+            }
+            return None;
+        };
+
+        update.update_data = compressed.data.into();
+        update.dictionary_id = compressed.dictionary.id;
+        dictionary.map(|dictionary| {
+            Self::server_message(
+                &message.document_id,
+                server_message::MessageType::PayloadDictionary(PayloadDictionary {
+                    dictionary_id: dictionary.id,
+                    dictionary: dictionary.data.to_vec().into(),
+                }),
+            )
+        })
+    }
+
+    /// Decompresses the update payload sent by a client.
+    ///
+    /// # Parameters
+    ///
+    /// * `encoding` - The encoding the client flagged the payload with
+    /// * `payload` - The received payload
+    ///
+    /// # Returns
+    ///
+    /// The update to apply, or an `InvalidUpdate` error if the encoding is unknown or the
+    /// payload cannot be decompressed
+    fn decode_payload<'a>(
+        &self,
+        encoding: PayloadEncoding,
+        payload: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, DomainError> {
+        let encoding = transport_encoding(encoding).ok_or_else(|| {
+            DomainError::InvalidUpdate(format!("Unknown payload encoding {:?}", encoding))
+        })?;
+        self.transport_compression.decode(encoding, payload)
+    }
+
+    /// Decompresses the update payload sent by a client and converts it into a v1 update.
+    ///
+    /// # Parameters
+    ///
+    /// * `updates` - The update encoding negotiated by the client
+    /// * `encoding` - The encoding the client flagged the payload with
+    /// * `payload` - The received payload
+    ///
+    /// # Returns
+    ///
+    /// The v1 update to apply, or an `InvalidUpdate` error if the payload cannot be
+    /// decompressed or is not a valid update in the negotiated encoding
+    fn decode_update<'a>(
+        &self,
+        updates: UpdateEncoding,
+        encoding: PayloadEncoding,
+        payload: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, DomainError> {
+        let payload = self.decode_payload(encoding, payload)?;
+        let converted = match updates.decode(&payload)? {
+            Cow::Owned(converted) => Some(converted),
+            Cow::Borrowed(_) => None,
+        };
+        Ok(converted.map_or(payload, Cow::Owned))
+    }
+
+    /// Converts the update of a message relayed to the client into the update
+    /// encoding it negotiated.
+    ///
+    /// An update that cannot be converted is relayed as is, and logged.
+    ///
+    /// # Parameters
+    ///
+    /// * `updates` - The update encoding negotiated by the client
+    /// * `message` - The message about to be relayed, converted in place
+    fn convert_relayed_update(updates: UpdateEncoding, message: &mut ServerMessage) {
+        let Some(server_message::MessageType::Update(update)) = message.message_type.as_mut()
+        else {
+            return;
+        };
+        let converted = match updates.encode(&update.update_data) {
+            Ok(Cow::Owned(converted)) => converted,
+            Ok(Cow::Borrowed(_)) => return,
+            Err(e) => {
+                warn!(
+                    "Failed to encode an update of document {} as {}: {}",
+                    message.document_id, updates, e
+                );
+                return;
+            }
+        };
+        update.update_data = converted.into();
+    }
+
+    /// Converts a presence event of the session registry into a server message.
+    ///
+    /// # Parameters
+    ///
+    /// * `event` - The presence event
+    ///
+    /// # Returns
+    ///
+    /// The user joined or user left message, or the permission notice told to the client
+    /// itself, addressed to the event's document
+    fn presence_message(event: &PresenceEvent) -> ServerMessage {
+        let message_type = match event {
+            PresenceEvent::Joined(session) => server_message::MessageType::UserJoined(UserJoined {
+                user_id: session.user_id.clone().into(),
+                user_name: session.user_name.clone().into(),
+                user_color: session.user_color.clone().into(),
+                client_id: session.client_id.clone().into(),
+                user_metadata: session
+                    .user_metadata
+                    .iter()
+                    .map(|(k, v)| (k.clone().into(), v.clone().into()))
+                    .collect(),
+            }),
+            PresenceEvent::Left(session) | PresenceEvent::Kicked(session) => {
+                server_message::MessageType::UserLeft(UserLeft {
+                    user_id: session.user_id.clone().into(),
+                    client_id: session.client_id.clone().into(),
+                })
+            }
+            PresenceEvent::PermissionChanged { session, grant } => {
+                return Self::notice_message(&permission_notice(session, *grant));
+            }
+        };
+
+        Self::server_message(&event.session().document_id, message_type)
+    }
+
+    /// Converts a cursor move into a server message.
+    ///
+    /// # Parameters
+    ///
+    /// * `event` - The cursor move of another client
+    ///
+    /// # Returns
+    ///
+    /// The cursor message, addressed to the event's document, with empty ends for a cleared
+    /// cursor
+    fn cursor_message(event: &CursorEvent) -> ServerMessage {
+        let (anchor, head) = match &event.position {
+            Some(position) => (position.anchor.clone(), position.head.clone()),
+            None => (Vec::new(), Vec::new()),
         };
-        self.broadcast_to_document(document_id, update_msg, Some(origin_client_id))
-            .await;
+
+        Self::server_message(
+            &event.document_id,
+            server_message::MessageType::Cursor(CursorUpdate {
+                client_id: event.client_id.clone().into(),
+                anchor: anchor.into(),
+                head: head.into(),
+                user_id: event.user_id.clone().into(),
+                user_name: event.user_name.clone().into(),
+                user_color: event.user_color.clone().into(),
+                timestamp: event.at,
+            }),
+        )
+    }
+
+    /// Converts a server notice into a server message.
+    ///
+    /// # Parameters
+    ///
+    /// * `notice` - The notice to deliver
+    ///
+    /// # Returns
+    ///
+    /// The notice message, addressed to the notice's document if any
+    fn notice_message(notice: &Notice) -> ServerMessage {
+        Self::server_message(
+            notice.doc_id.as_deref().unwrap_or_default(),
+            server_message::MessageType::Notice(proto_notice(notice)),
+        )
     }
 
     /// Broadcasts a message to all active sessions for a document.
@@ -259,76 +1119,119 @@ impl<R: DocumentRepository + Send + Sync + 'static> CollaborationServiceImpl<R>
         message: ServerMessage,
         exclude_client: Option<&str>,
     ) {
-        // With DashMap, we can iterate over entries without locking the entire map
-        for entry in self.active_sessions.iter() {
-            let session_id = entry.key();
-            let sender = entry.value();
-
-            if let Some(exclude) = exclude_client {
-                if session_id.contains(exclude) {
-                    continue;
-                }
-            }
+        // Senders are collected first so that no map shard stays locked across sends
+        let recipients: Vec<(String, StreamSender)> = match self.active_sessions.get(document_id) {
+            Some(streams) => streams
+                .iter()
+                .filter(|entry| exclude_client != Some(entry.key().as_str()))
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect(),
+            None => return,
+        };
 
-            if session_id.contains(document_id) {
-                if let Err(_) = sender.send(Ok(message.clone())).await {
-                    warn!("Failed to send message to session {}", session_id);
-                }
+        for (client_id, sender) in recipients {
+            if sender.send(Ok(message.clone())).await.is_err() {
+                warn!(
+                    "Failed to send message to client {} on document {}",
+                    client_id, document_id
+                );
+                self.sessions.delivery_stats().record(
+                    document_id,
+                    &client_id,
+                    DropReason::ClosedChannel,
+                    1,
+                );
             }
         }
     }
 
-    /// Gets active users for a specific document.
+    /// Unregisters a terminated stream from the documents it collaborated on.
     ///
-    /// # Parameters
-    ///
-    /// * `document_id` - Unique identifier for the document
+    /// A client reconnecting under the same identifier may have registered its new
+    /// stream already, so a registration is only removed while it still holds the
+    /// terminated stream's sender.
     ///
-    /// # Returns
+    /// # Parameters
     ///
-    /// Vector of ActiveUser structs representing users currently active in the document
-    fn get_active_users_for_document(&self, document_id: &str) -> Vec<ActiveUser> {
-        self.user_sessions
-            .iter()
-            .filter_map(|entry| {
-                let user_session = entry.value();
-                if user_session.document_id == document_id {
-                    Some(ActiveUser {
-                        user_id: user_session.user_id.clone().into(),
-                        user_name: user_session.user_name.clone().into(),
-                        user_color: user_session.user_color.clone().into(),
-                        client_id: user_session.client_id.clone().into(),
-                        last_seen: user_session.last_seen,
-                        user_metadata: user_session
-                            .user_metadata
-                            .iter()
-                            .map(|(k, v)| (k.clone().into(), v.clone().into()))
-                            .collect(),
-                    })
-                } else {
-                    None
+    /// * `client_id` - Identifier of the stream's client
+    /// * `documents` - The documents the stream registered on
+    /// * `sender` - The sender of the stream's channel
+    fn unregister_stream(
+        &self,
+        client_id: &str,
+        documents: &HashSet<String>,
+        sender: &StreamSender,
+    ) {
+        for document_id in documents {
+            let emptied = match self.active_sessions.get(document_id) {
+                Some(streams) => {
+                    streams.remove_if(client_id, |_, registered| registered.same_channel(sender));
+                    streams.is_empty()
                 }
-            })
-            .collect()
+                None => false,
+            };
+            if emptied {
+                self.active_sessions
+                    .remove_if(document_id, |_, streams| streams.is_empty());
+            }
+        }
     }
 
-    /// Updates user session information.
+    /// Buffers the updates of the documents a terminated stream was subscribed to,
+    /// so the client can resume them if it reconnects within the retention period.
     ///
     /// # Parameters
     ///
-    /// * `session_id` - Unique session identifier
-    /// * `user_session` - User session data to store
-    fn update_user_session(&self, session_id: String, user_session: UserSession) {
-        self.user_sessions.insert(session_id, user_session);
+    /// * `hub` - The terminated stream's broadcast hub
+    async fn park_session(&self, hub: BroadcastHub) {
+        if !self.outbox.is_enabled() {
+            return;
+        }
+
+        let client_id = hub.client_id().to_string();
+        let documents = hub.subscribed_documents();
+        // Stop recording before the outbox takes over the session's updates
+        drop(hub);
+
+        for document_id in documents {
+            let (sequence_number, updates) = self
+                .document_service
+                .subscribe_with_sequence(&document_id)
+                .await;
+            self.outbox
+                .park(&document_id, &client_id, sequence_number, updates);
+        }
     }
 
-    /// Removes a user session.
+    /// Gets active users for a specific document.
+    ///
+    /// Clients connected over WebSocket are listed too, as guests unless they
+    /// joined with an identity.
     ///
     /// # Parameters
     ///
-    /// * `session_id` - Unique session identifier to remove
-    fn remove_user_session(&self, session_id: &str) {
-        self.user_sessions.remove(session_id);
+    /// * `document_id` - Unique identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// Vector of ActiveUser structs representing users currently active in the document
+    fn get_active_users_for_document(&self, document_id: &str) -> Vec<ActiveUser> {
+        self.sessions
+            .active_users(document_id)
+            .into_iter()
+            .map(|session| ActiveUser {
+                user_id: session.user_id.into(),
+                user_name: session.user_name.into(),
+                user_color: session.user_color.into(),
+                client_id: session.client_id.into(),
+                last_seen: session.last_seen,
+                user_metadata: session
+                    .user_metadata
+                    .into_iter()
+                    .map(|(k, v)| (k.into(), v.into()))
+                    .collect(),
+            })
+            .collect()
     }
 }
 
@@ -349,43 +1252,243 @@ impl<R: DocumentRepository + Send + Sync + 'static> CollaborationService
     ///
     /// # Errors
     ///
-    /// Returns a gRPC Status error if the collaboration session cannot be established
+    /// Returns a gRPC Status error if the collaboration session cannot be established,
+    /// including `UNAVAILABLE` with a `retry-after` metadata hint when the server is
+    /// overloaded
     async fn collaborate(
         &self,
         request: Request<RecvStream<ClientMessage>>,
     ) -> Result<Response<BoxStream<'static, Result<ServerMessage, Status>>>, Status> {
+        let permit = match self.admission.try_admit(self.load_signals()) {
+            Ok(permit) => permit,
+            Err(rejection) => {
+                warn!("Rejecting collaboration stream: {}", rejection);
+                let mut status = Status::unavailable(rejection.to_string());
+                status.metadata_mut().insert(
+                    "retry-after",
+                    MetadataValue::from(rejection.retry_after.as_secs()),
+                );
+                return Err(status);
+            }
+        };
+
         let mut stream = request.into_inner();
         let (tx, mut rx) = mpsc::channel(100);
+        let clock_offset = Arc::new(ClockOffset::default());
 
         let service = self.clone();
+        let observed_offset = clock_offset.clone();
+        let diff_limiter = self.document_service.diff_throttle().session_limiter();
+        let mut notices = self.document_service.subscribe_notices();
+        let mut presence = self.sessions.subscribe();
+        let mut cursors = self.sessions.subscribe_cursors();
         tokio::spawn(async move {
-            while let Some(result) = stream.next().await {
-                match result {
-                    Ok(msg) => {
-                        let session_id = format!("{}_{}", msg.document_id, msg.client_id);
-
-                        // Register session - with DashMap, no explicit locking needed
-                        service
-                            .active_sessions
-                            .insert(session_id.clone(), tx.clone());
-
-                        if let Err(e) = service.handle_client_message(msg, &tx).await {
-                            error!("Error handling client message: {:?}", e);
-                            let _ = tx.send(Err(e)).await;
+            // Hold the permit until the client stream terminates
+            let _permit = permit;
+            // Created with the first message, which names the client
+            let mut hub: Option<BroadcastHub> = None;
+            // Documents the stream is registered on, to unregister it once terminated
+            let mut registered: HashSet<String> = HashSet::new();
+
+            loop {
+                tokio::select! {
+                    result = stream.next() => match result {
+                        Some(Ok(msg)) => {
+                            observed_offset.observe(msg.timestamp, server_time());
+
+                            // Register the stream on the document the message relates to,
+                            // scoped to its tenant; invalid tenants are answered with an error
+                            if let Ok(document_id) =
+                                scoped_document_id(&msg.tenant, &msg.document_id)
+                            {
+                                service
+                                    .active_sessions
+                                    .entry(document_id.clone())
+                                    .or_default()
+                                    .insert(msg.client_id.to_string(), tx.clone());
+                                registered.insert(document_id);
+                            }
+
+                            let hub = hub.get_or_insert_with(|| {
+                                BroadcastHub::new(&msg.client_id)
+                                    .with_outbox(Arc::clone(&service.outbox))
+                                    .with_delivery_stats(service.sessions.delivery_stats())
+                            });
+                            if let Err(e) = service
+                                .handle_client_message(msg, &tx, hub, &diff_limiter)
+                                .await
+                            {
+                                error!("Error handling client message: {:?}", e);
+                                let _ = tx.send(Err(e)).await;
+                            }
+                        }
+                        Some(Err(e)) => {
+                            error!("Error receiving client message: {:?}", e);
+                            let _ = tx.send(Err(Status::internal("Stream error"))).await;
+                            break;
+                        }
+                        None => break,
+                    },
+                    event = async {
+                        match hub.as_mut() {
+                            Some(hub) => hub.recv().await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        let mut message = service.hub_message(event).await;
+                        let document_id = message.document_id.to_string();
+                        let dictionary = hub.as_mut().and_then(|hub| {
+                            Self::convert_relayed_update(hub.update_encoding(), &mut message);
+                            let accepted = hub.transport_encoding();
+                            service.compress_update(hub.compression_mut(), accepted, &mut message)
+                        });
+                        let sent = match dictionary {
+                            Some(dictionary) => tx.send(Ok(dictionary)).await.is_ok(),
+                            None => true,
+                        };
+                        if !sent || tx.send(Ok(message)).await.is_err() {
+                            if let Some(hub) = &hub {
+                                service.sessions.delivery_stats().record(
+                                    &document_id,
+                                    hub.client_id(),
+                                    DropReason::ClosedChannel,
+                                    1,
+                                );
+                            }
+                            break;
                         }
                     }
-                    Err(e) => {
-                        error!("Error receiving client message: {:?}", e);
-                        let _ = tx.send(Err(Status::internal("Stream error"))).await;
-                        break;
-                    }
+                    notice = notices.recv() => match notice {
+                        Ok(notice) => {
+                            // Document notices only concern the documents this stream edits
+                            let addressed = notice.is_addressed_to(|doc_id| {
+                                hub.as_ref().is_some_and(|hub| hub.is_subscribed(doc_id))
+                            });
+                            if addressed
+                                && tx.send(Ok(Self::notice_message(&notice))).await.is_err()
+                            {
+                                break;
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Stream missed {} notices", skipped);
+                        }
+                        // The document service is shutting down
+                        Err(RecvError::Closed) => break,
+                    },
+                    event = presence.recv() => match event {
+                        Ok(event) => {
+                            if let PresenceEvent::Kicked(session) = &event {
+                                // Other clients were told of the departure as it left
+                                if hub.as_ref().is_some_and(|hub| hub.is_own(&session.client_id)) {
+                                    info!(
+                                        "Closing stream of kicked client: {}",
+                                        session.client_id
+                                    );
+                                    let _ = tx
+                                        .send(Err(Status::aborted("Disconnected by an operator")))
+                                        .await;
+                                    break;
+                                }
+                                continue;
+                            }
+                            if let PresenceEvent::PermissionChanged { session, grant } = &event {
+                                // Permission changes are only told to the client itself; a
+                                // revoked client stops receiving the document's messages
+                                let Some(hub) = hub
+                                    .as_mut()
+                                    .filter(|hub| hub.is_own(&session.client_id))
+                                else {
+                                    continue;
+                                };
+                                if *grant == AccessGrant::Revoked {
+                                    hub.unsubscribe(&session.document_id);
+                                    service
+                                        .outbox
+                                        .forget(&session.document_id, &session.client_id);
+                                    if let Some(streams) =
+                                        service.active_sessions.get(&session.document_id)
+                                    {
+                                        streams.remove(&session.client_id);
+                                    }
+                                }
+                                if tx.send(Ok(Self::presence_message(&event))).await.is_err() {
+                                    break;
+                                }
+                                continue;
+                            }
+
+                            // Presence only concerns the documents this stream collaborates
+                            // on, whichever transport the other client is connected over
+                            let session = event.session();
+                            let addressed = hub.as_ref().is_some_and(|hub| {
+                                !hub.is_own(&session.client_id)
+                                    && (hub.is_subscribed(&session.document_id)
+                                        || service
+                                            .sessions
+                                            .is_present(&session.document_id, hub.client_id()))
+                            });
+                            if addressed
+                                && tx.send(Ok(Self::presence_message(&event))).await.is_err()
+                            {
+                                break;
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Stream missed {} presence events", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    event = cursors.recv() => match event {
+                        Ok(event) => {
+                            let Some(hub) = hub.as_ref().filter(|hub| {
+                                !hub.is_own(&event.client_id)
+                                    && (hub.is_subscribed(&event.document_id)
+                                        || service
+                                            .sessions
+                                            .is_present(&event.document_id, hub.client_id()))
+                            }) else {
+                                continue;
+                            };
+                            // Cursors never wait for room in the stream's queue, so they
+                            // cannot hold back the document updates queued there
+                            match tx.try_send(Ok(Self::cursor_message(&event))) {
+                                Ok(()) => {}
+                                Err(TrySendError::Full(_)) => {
+                                    service.sessions.delivery_stats().record(
+                                        &event.document_id,
+                                        hub.client_id(),
+                                        DropReason::FullQueue,
+                                        1,
+                                    );
+                                }
+                                Err(TrySendError::Closed(_)) => break,
+                            }
+                        }
+                        // Later moves supersede the skipped ones
+                        Err(RecvError::Lagged(skipped)) => {
+                            debug!("Stream skipped {} cursor moves", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
                 }
             }
+
+            // The client is no longer present on any document
+            if let Some(hub) = hub {
+                service.unregister_stream(hub.client_id(), &registered, &tx);
+                service.sessions.disconnect(hub.client_id());
+                service.park_session(hub).await;
+            }
         });
 
         let output_stream = async_stream::stream! {
             while let Some(msg) = rx.recv().await {
-                yield msg;
+                // Stamp the recipient's clock offset hint on every delivered message
+                yield msg.map(|mut msg| {
+                    msg.clock_offset = clock_offset.get();
+                    msg
+                });
             }
         };
 
@@ -410,23 +1513,28 @@ impl<R: DocumentRepository + Send + Sync + 'static> CollaborationService
         request: Request<GetDocumentStateRequest>,
     ) -> Result<Response<GetDocumentStateResponse>, Status> {
         let req = request.into_inner();
+        let document_id = scoped_document_id(&req.tenant, &req.document_id).map_err(status_of)?;
+
+        let user_id = self.sessions.user_id(&document_id, &req.client_id);
+        self.document_service
+            .authorize(&document_id, user_id.as_deref())
+            .map_err(status_of)?;
 
         // 获取文档状态
         let (response, _) = self
             .document_service
-            .handle_sync_request(&req.document_id, None)
-            .await;
+            .handle_sync_request(&document_id, None)
+            .await
+            .map_err(status_of)?;
 
         let document_state = DocumentState {
             state_vector: response.state_vector.unwrap_or_default().into(),
-            document_data: response
-                .update
-                .as_ref()
-                .map(|u| STANDARD.decode(&u).unwrap_or_default())
-                .unwrap_or_default()
-                .into(),
-            active_users: self.get_active_users_for_document(&req.document_id),
-            last_modified: chrono::Utc::now().timestamp(),
+            document_data: response.update.unwrap_or_default().into(),
+            active_users: self.get_active_users_for_document(&document_id),
+            last_modified: self
+                .document_service
+                .last_modified(&document_id)
+                .unwrap_or_default(),
         };
 
         Ok(Response::new(GetDocumentStateResponse {
@@ -452,11 +1560,410 @@ impl<R: DocumentRepository + Send + Sync + 'static> CollaborationService
         request: Request<GetActiveUsersRequest>,
     ) -> Result<Response<GetActiveUsersResponse>, Status> {
         let req = request.into_inner();
+        let document_id = scoped_document_id(&req.tenant, &req.document_id).map_err(status_of)?;
 
-        let active_users = self.get_active_users_for_document(&req.document_id);
+        let active_users = self.get_active_users_for_document(&document_id);
 
         Ok(Response::new(GetActiveUsersResponse { active_users }))
     }
+
+    /// Gets the documents a user currently has open.
+    ///
+    /// Like the HTTP API, only the documents guests may read are listed.
+    ///
+    /// # Parameters
+    ///
+    /// * `request` - Request containing the user ID, and the tenant to list the documents of
+    ///
+    /// # Returns
+    ///
+    /// A response containing the documents, with the user's connections on each
+    ///
+    /// # Errors
+    ///
+    /// Returns `INVALID_ARGUMENT` if the user ID is empty
+    async fn get_user_documents(
+        &self,
+        request: Request<GetUserDocumentsRequest>,
+    ) -> Result<Response<GetUserDocumentsResponse>, Status> {
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("A user ID is required"));
+        }
+
+        let documents = self
+            .sessions
+            .user_documents(&req.user_id)
+            .into_iter()
+            .filter(|document| {
+                self.document_service
+                    .authorize(&document.document_id, None)
+                    .is_ok()
+            })
+            .filter_map(|document| {
+                // Documents are named by their ID within the tenant, as clients address them
+                let doc_id = unscoped_document_id(&req.tenant, &document.document_id)?;
+                Some(ProtoUserDocument {
+                    document_id: doc_id.to_string().into(),
+                    connections: document.connections as u32,
+                    last_seen: document.last_seen,
+                })
+            })
+            .collect();
+
+        Ok(Response::new(GetUserDocumentsResponse { documents }))
+    }
+
+    /// Streams the state and updates of every document to a warm standby.
+    ///
+    /// The stream opens with the full state of every document, then relays
+    /// every update applied to them, whichever transport or server instance
+    /// applied it. Documents created later are picked up by a periodic scan and
+    /// sent in full, and so is a document whose updates the standby fell behind
+    /// on. Every message is a Yjs v1 update the standby applies as is.
+    ///
+    /// # Parameters
+    ///
+    /// * `request` - Request identifying the standby and carrying the replication token
+    ///
+    /// # Returns
+    ///
+    /// A response containing the stream of replication messages
+    ///
+    /// # Errors
+    ///
+    /// Returns `PERMISSION_DENIED` unless replication is enabled and the request
+    /// carries the configured token
+    async fn replicate(
+        &self,
+        request: Request<ReplicateRequest>,
+    ) -> Result<Response<BoxStream<'static, Result<ReplicationMessage, Status>>>, Status> {
+        let req = request.into_inner();
+        let authorized = self
+            .replication_token
+            .as_ref()
+            .is_some_and(|token| constant_time_eq(req.token.as_bytes(), token.as_bytes()));
+        if !authorized {
+            warn!("Refusing replication to standby '{}'", req.standby_id);
+            return Err(Status::permission_denied(
+                "Replication is disabled or the token is invalid",
+            ));
+        }
+
+        let standby_id = req.standby_id.to_string();
+        info!("Replicating documents to standby '{}'", standby_id);
+
+        let (tx, mut rx) = mpsc::channel(100);
+        let document_service = self.document_service.clone();
+        tokio::spawn(async move {
+            let mut hub = BroadcastHub::new(&format!("standby:{}", standby_id));
+            let mut scan = tokio::time::interval(DOCUMENT_SCAN_INTERVAL);
+
+            'replication: loop {
+                let messages = tokio::select! {
+                    _ = scan.tick() => {
+                        let mut snapshots = Vec::new();
+                        for doc_id in document_service.list_documents().await {
+                            if hub.is_subscribed(&doc_id) {
+                                continue;
+                            }
+                            // Retried on the next scan
+                            match document_service.sync_document(&doc_id, None).await {
+                                Ok((state, updates)) => {
+                                    hub.subscribe(&doc_id, updates);
+                                    snapshots.push(replication_message(&doc_id, state));
+                                }
+                                Err(e) => warn!(
+                                    "Failed to replicate document {} to standby '{}': {}",
+                                    doc_id, standby_id, e
+                                ),
+                            }
+                        }
+                        snapshots
+                    }
+                    event = hub.recv() => match event {
+                        HubEvent::Update { doc_id, update, .. } => {
+                            vec![replication_message(&doc_id, update)]
+                        }
+                        HubEvent::Lagged { doc_id, skipped } => {
+                            warn!(
+                                "Standby '{}' lagged by {} updates on document {}, resending it",
+                                standby_id, skipped, doc_id
+                            );
+                            match document_service.sync_document(&doc_id, None).await {
+                                Ok((state, _)) => vec![replication_message(&doc_id, state)],
+                                // The standby reconnects and receives every document again
+                                Err(e) => {
+                                    warn!(
+                                        "Failed to resend document {} to standby '{}': {}",
+                                        doc_id, standby_id, e
+                                    );
+                                    break 'replication;
+                                }
+                            }
+                        }
+                    },
+                    _ = tx.closed() => break,
+                };
+
+                for message in messages {
+                    if tx.send(Ok(message)).await.is_err() {
+                        break 'replication;
+                    }
+                }
+            }
+
+            info!("Standby '{}' stopped replicating", standby_id);
+        });
+
+        let output_stream = async_stream::stream! {
+            while let Some(message) = rx.recv().await {
+                yield message;
+            }
+        };
+
+        Ok(Response::new(Box::pin(output_stream)))
+    }
+
+    /// Applies updates to several documents atomically.
+    ///
+    /// The client must be allowed to edit every document, with the identity it
+    /// joined the document with, or else the user of the request. The updates
+    /// are applied in order; if one fails, those applied before it are rolled
+    /// back with inverse updates, which are broadcast like any other update.
+    ///
+    /// # Parameters
+    ///
+    /// * `request` - Request containing the updates and the client applying them
+    ///
+    /// # Returns
+    ///
+    /// A response containing the sequence number of each updated document
+    ///
+    /// # Errors
+    ///
+    /// Returns `UNIMPLEMENTED` if transactions are not served, `PERMISSION_DENIED` if the
+    /// client may not edit a document, or the status of the update that failed, with the
+    /// document and whether the transaction was rolled back in its message
+    async fn apply_transaction(
+        &self,
+        request: Request<ApplyTransactionRequest>,
+    ) -> Result<Response<ApplyTransactionResponse>, Status> {
+        let Some(transactions) = &self.transactions else {
+            return Err(Status::unimplemented(
+                "Cross-document transactions are not served",
+            ));
+        };
+        let req = request.into_inner();
+        let client_id = req.client_id.to_string();
+        let request_user = Some(req.user_id.as_str()).filter(|id| !id.is_empty());
+
+        let mut updates = Vec::with_capacity(req.updates.len());
+        for update in &req.updates {
+            let doc_id = scoped_document_id(&req.tenant, &update.document_id).map_err(status_of)?;
+            let user_id = self
+                .sessions
+                .user_id(&doc_id, &client_id)
+                .or_else(|| request_user.map(str::to_string));
+            let role = self
+                .document_service
+                .access_role(&doc_id, user_id.as_deref())
+                .map_err(status_of)?;
+            if !role.can_write() {
+                return Err(Status::permission_denied(format!(
+                    "Client may not edit document '{}'",
+                    doc_id
+                )));
+            }
+            updates.push(TransactionUpdate {
+                doc_id,
+                update: update.update_data.to_vec(),
+            });
+        }
+
+        // A transaction counts as one update against the client's rate limit
+        self.sessions
+            .update_limiter()
+            .check(&client_id, None)
+            .map_err(status_of)?;
+
+        let origin = UpdateOrigin::new(&client_id, UpdateTransport::Grpc).with_user(request_user);
+        let sequence_numbers = transactions
+            .apply_transaction(updates, origin)
+            .await
+            .map_err(|failure| {
+                let outcome = if failure.rolled_back {
+                    "rolled back"
+                } else {
+                    "not fully rolled back"
+                };
+                let status = status_of(failure.error);
+                Status::new(
+                    status.code(),
+                    format!(
+                        "Transaction {} after document '{}' failed: {}",
+                        outcome,
+                        failure.doc_id,
+                        status.message()
+                    ),
+                )
+            })?;
+
+        Ok(Response::new(ApplyTransactionResponse { sequence_numbers }))
+    }
+}
+
+/// Builds the replication message carrying an update or the full state of a document.
+fn replication_message(document_id: &str, update: Vec<u8>) -> ReplicationMessage {
+    ReplicationMessage {
+        document_id: document_id.to_string().into(),
+        update_data: update.into(),
+        timestamp: server_time(),
+    }
+}
+
+/// Converts a v1 update sent to a client into the update encoding it negotiated.
+fn encode_update(updates: UpdateEncoding, update: Vec<u8>) -> Result<Vec<u8>, DomainError> {
+    let converted = match updates.encode(&update)? {
+        Cow::Owned(converted) => Some(converted),
+        Cow::Borrowed(_) => None,
+    };
+    Ok(converted.unwrap_or(update))
+}
+
+/// Converts the update encoding negotiated by a client; unknown versions fall back to v1.
+fn update_encoding(encoding: ProtoUpdateEncoding) -> UpdateEncoding {
+    if encoding == ProtoUpdateEncoding::UPDATE_ENCODING_V2 {
+        UpdateEncoding::V2
+    } else {
+        UpdateEncoding::V1
+    }
+}
+
+/// Converts the encoding of a protobuf payload, or `None` if it is unknown.
+fn transport_encoding(encoding: PayloadEncoding) -> Option<TransportEncoding> {
+    match encoding {
+        PayloadEncoding::ENCODING_IDENTITY => Some(TransportEncoding::Identity),
+        PayloadEncoding::ENCODING_ZSTD => Some(TransportEncoding::Zstd),
+        PayloadEncoding::ENCODING_GZIP => Some(TransportEncoding::Gzip),
+        _ => None,
+    }
+}
+
+/// Converts the encoding of a payload into its protobuf counterpart.
+fn payload_encoding(encoding: TransportEncoding) -> PayloadEncoding {
+    match encoding {
+        TransportEncoding::Identity => PayloadEncoding::ENCODING_IDENTITY,
+        TransportEncoding::Zstd => PayloadEncoding::ENCODING_ZSTD,
+        TransportEncoding::Gzip => PayloadEncoding::ENCODING_GZIP,
+    }
+}
+
+/// Converts where an update comes from into its protobuf counterpart.
+pub(crate) fn update_origin_kind(kind: OriginKind) -> UpdateOriginKind {
+    match kind {
+        OriginKind::Local => UpdateOriginKind::ORIGIN_LOCAL,
+        OriginKind::Remote => UpdateOriginKind::ORIGIN_REMOTE,
+        OriginKind::Server => UpdateOriginKind::ORIGIN_SERVER,
+    }
+}
+
+/// Converts a server notice into its protobuf counterpart.
+///
+/// # Parameters
+///
+/// * `notice` - The notice to deliver
+///
+/// # Returns
+///
+/// The protobuf `Notice`, with `0` and an empty string standing for the unset fields
+pub(crate) fn proto_notice(notice: &Notice) -> ProtoNotice {
+    let kind = match notice.kind {
+        NoticeKind::General => ProtoNoticeKind::GENERAL,
+        NoticeKind::Maintenance => ProtoNoticeKind::MAINTENANCE,
+        NoticeKind::DocumentLocked => ProtoNoticeKind::DOCUMENT_LOCKED,
+        NoticeKind::QuotaWarning => ProtoNoticeKind::QUOTA_WARNING,
+        NoticeKind::Redirect => ProtoNoticeKind::REDIRECT,
+        NoticeKind::PermissionChanged => ProtoNoticeKind::PERMISSION_CHANGED,
+        NoticeKind::DocumentClosing => ProtoNoticeKind::DOCUMENT_CLOSING,
+    };
+    let severity = match notice.severity {
+        NoticeSeverity::Info => ProtoNoticeSeverity::SEVERITY_INFO,
+        NoticeSeverity::Warning => ProtoNoticeSeverity::SEVERITY_WARNING,
+        NoticeSeverity::Critical => ProtoNoticeSeverity::SEVERITY_CRITICAL,
+    };
+
+    ProtoNotice {
+        kind,
+        severity,
+        message: notice.message.clone().into(),
+        scheduled_at: notice.scheduled_at.unwrap_or_default(),
+        redirect_url: notice.redirect_url.clone().unwrap_or_default().into(),
+    }
+}
+
+/// Builds the error message reporting a domain error to a client.
+///
+/// # Parameters
+///
+/// * `document_id` - The document the error relates to, as the client named it if it could not be
+///   scoped
+/// * `error` - The error returned by the domain
+///
+/// # Returns
+///
+/// An `ErrorMessage` naming the document, whose code follows the matching HTTP status
+fn error_message(document_id: &str, error: &DomainError) -> ErrorMessage {
+    let (error_code, error_type) = match error {
+        DomainError::NotFound(_) => (404, ErrorType::DOCUMENT_NOT_FOUND),
+        DomainError::Conflict(_) => (409, ErrorType::DOCUMENT_EXISTS),
+        DomainError::InvalidUpdate(_) => (400, ErrorType::INVALID_UPDATE),
+        DomainError::InvalidArgument(_) => (400, ErrorType::INVALID_ARGUMENT),
+        DomainError::Unauthorized(_) => (403, ErrorType::AUTHORIZATION_ERROR),
+        DomainError::LimitExceeded(_) => (429, ErrorType::RATE_LIMIT_EXCEEDED),
+        DomainError::Unavailable(_) => (503, ErrorType::CONNECTION_ERROR),
+        DomainError::RoomFull(_) => (503, ErrorType::ROOM_FULL),
+        DomainError::PayloadTooLarge(_) => (413, ErrorType::PAYLOAD_TOO_LARGE),
+        DomainError::DocumentFrozen(_) => (423, ErrorType::DOCUMENT_FROZEN),
+        DomainError::Moved { .. } => (421, ErrorType::DOCUMENT_MOVED),
+        DomainError::StorageFailure(_) | DomainError::Internal(_) => {
+            (500, ErrorType::INTERNAL_ERROR)
+        }
+    };
+
+    ErrorMessage {
+        error_code,
+        error_message: error.to_string().into(),
+        error_type,
+        document_id: document_id.to_string().into(),
+    }
+}
+
+/// Converts a domain error into the gRPC status of the matching code.
+///
+/// # Parameters
+///
+/// * `error` - The error returned by the domain
+///
+/// # Returns
+///
+/// A `Status` carrying the error's message
+fn status_of(error: DomainError) -> Status {
+    let message = error.to_string();
+    match error {
+        DomainError::NotFound(_) => Status::not_found(message),
+        DomainError::Conflict(_) => Status::already_exists(message),
+        DomainError::InvalidUpdate(_) | DomainError::InvalidArgument(_) => {
+            Status::invalid_argument(message)
+        }
+        DomainError::Unauthorized(_) => Status::permission_denied(message),
+        DomainError::LimitExceeded(_)
+        | DomainError::RoomFull(_)
+        | DomainError::PayloadTooLarge(_) => Status::resource_exhausted(message),
+        DomainError::DocumentFrozen(_) => Status::failed_precondition(message),
+        DomainError::Unavailable(_) | DomainError::Moved { .. } => Status::unavailable(message),
+        DomainError::StorageFailure(_) | DomainError::Internal(_) => Status::internal(message),
+    }
 }
 
 /// Implementation of Clone for CollaborationServiceImpl
@@ -470,7 +1977,12 @@ impl<R: DocumentRepository> Clone for CollaborationServiceImpl<R> {
         Self {
             document_service: Arc::clone(&self.document_service),
             active_sessions: Arc::clone(&self.active_sessions),
-            user_sessions: Arc::clone(&self.user_sessions),
+            sessions: Arc::clone(&self.sessions),
+            admission: Arc::clone(&self.admission),
+            outbox: Arc::clone(&self.outbox),
+            replication_token: self.replication_token.clone(),
+            transport_compression: self.transport_compression,
+            transactions: self.transactions.clone(),
         }
     }
 }
@@ -0,0 +1,89 @@
+use futures::{stream, StreamExt};
+use volo_grpc::{BoxStream, Request, Response, Status};
+use yjs_collaboration_server_common::volo_gen::grpc::health::v1::{
+    health_check_response::ServingStatus, Health, HealthCheckRequest, HealthCheckResponse,
+};
+
+/// Full name of the collaboration service, as named in health checks.
+pub const COLLABORATION_SERVICE_NAME: &str = "collaboration.CollaborationService";
+
+/// Implementation of the standard gRPC health checking service (`grpc.health.v1.Health`).
+///
+/// The server as a whole, probed with an empty service name, and the
+/// collaboration service are serving for as long as the gRPC server runs, so
+/// load balancers and tools like `grpc_health_probe` can probe it without the
+/// collaboration protocol.
+#[derive(Clone, Default)]
+pub struct HealthServiceImpl;
+
+impl HealthServiceImpl {
+    /// Creates a new health service.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `HealthServiceImpl`
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Returns the status of a service, or `None` if the server does not provide it.
+    fn status_of(service: &str) -> Option<ServingStatus> {
+        match service {
+            "" | COLLABORATION_SERVICE_NAME => Some(ServingStatus::SERVING),
+            _ => None,
+        }
+    }
+}
+
+impl Health for HealthServiceImpl {
+    /// Reports the current status of a service.
+    ///
+    /// # Parameters
+    ///
+    /// * `request` - Request naming the service, empty for the whole server
+    ///
+    /// # Returns
+    ///
+    /// A response carrying the service's status
+    ///
+    /// # Errors
+    ///
+    /// Returns a `NOT_FOUND` status if the server does not provide the service
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+        match Self::status_of(&service) {
+            Some(status) => Ok(Response::new(HealthCheckResponse { status })),
+            None => Err(Status::not_found(format!("Unknown service: {}", service))),
+        }
+    }
+
+    /// Streams the status of a service, starting with its current status.
+    ///
+    /// A service the server does not provide is reported as `SERVICE_UNKNOWN`
+    /// rather than rejected, as the health checking protocol requires.
+    ///
+    /// # Parameters
+    ///
+    /// * `request` - Request naming the service, empty for the whole server
+    ///
+    /// # Returns
+    ///
+    /// A response containing the stream of the service's statuses
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<BoxStream<'static, Result<HealthCheckResponse, Status>>>, Status> {
+        let service = request.into_inner().service;
+        let status = Self::status_of(&service).unwrap_or(ServingStatus::SERVICE_UNKNOWN);
+
+        // Statuses do not change while the server runs, so the stream stays open
+        // without further messages until the client cancels it
+        let statuses = stream::once(async move { Ok(HealthCheckResponse { status }) })
+            .chain(stream::pending());
+
+        Ok(Response::new(Box::pin(statuses)))
+    }
+}
@@ -1,2 +1,3 @@
 pub mod collaboration_service;
-
+pub mod health_service;
+pub mod reflection_service;
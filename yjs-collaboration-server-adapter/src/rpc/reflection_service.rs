@@ -0,0 +1,324 @@
+use std::{collections::HashSet, sync::Arc};
+
+use futures::StreamExt;
+use tracing::warn;
+use volo_grpc::{BoxStream, RecvStream, Request, Response, Status};
+use yjs_collaboration_server_common::volo_gen::grpc::reflection::v1alpha::{
+    server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+    ErrorResponse, FileDescriptorResponse, ListServiceResponse, ServerReflection,
+    ServerReflectionRequest, ServerReflectionResponse, ServiceResponse,
+};
+
+/// gRPC status code reported for an unknown file or symbol.
+const NOT_FOUND: i32 = 5;
+
+/// gRPC status code reported for a request naming nothing to look up.
+const INVALID_ARGUMENT: i32 = 3;
+
+/// gRPC status code reported for extension lookups, as no served file declares extensions.
+const UNIMPLEMENTED: i32 = 12;
+
+/// A protobuf file of the descriptor set, with the symbols it defines.
+#[derive(Default)]
+struct FileDescriptor {
+    /// Name of the file, e.g. `collaboration.proto`
+    name: String,
+    /// The encoded `FileDescriptorProto`, sent as is to clients
+    encoded: Vec<u8>,
+    /// Names of the files it imports
+    dependencies: Vec<String>,
+    /// Full names of its services
+    services: Vec<String>,
+    /// Full names of its messages, enums, services and methods
+    symbols: Vec<String>,
+}
+
+/// Implementation of the standard gRPC server reflection service
+/// (`grpc.reflection.v1alpha.ServerReflection`).
+///
+/// Tools like `grpcurl` list the served services and fetch the descriptors of
+/// their protobuf files through reflection, so they can call the collaboration
+/// service without a copy of its `.proto` files. Descriptors are read from an
+/// encoded `FileDescriptorSet` compiled at build time; an empty set lists no
+/// service. Extensions are not supported, as no served file declares any.
+#[derive(Clone, Default)]
+pub struct ReflectionServiceImpl {
+    /// Files of the descriptor set, in the order they were compiled
+    files: Arc<Vec<FileDescriptor>>,
+}
+
+impl ReflectionServiceImpl {
+    /// Creates a reflection service serving the files of a descriptor set.
+    ///
+    /// A set that cannot be decoded is logged and served as an empty set.
+    ///
+    /// # Parameters
+    ///
+    /// * `descriptor_set` - The encoded `FileDescriptorSet` of the served protobuf files
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `ReflectionServiceImpl`
+    pub fn new(descriptor_set: &[u8]) -> Self {
+        let files = length_delimited_fields(descriptor_set)
+            .and_then(|fields| {
+                fields
+                    .into_iter()
+                    .filter(|(number, _)| *number == 1)
+                    .map(|(_, file)| parse_file(file))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .unwrap_or_else(|| {
+                warn!("Failed to decode the descriptor set, reflection lists no service");
+                Vec::new()
+            });
+
+        Self {
+            files: Arc::new(files),
+        }
+    }
+
+    /// Returns whether the service has descriptors to serve.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Answers a reflection request.
+    ///
+    /// # Parameters
+    ///
+    /// * `request` - The client's request
+    ///
+    /// # Returns
+    ///
+    /// The response to the request, carrying the request itself as required by the protocol
+    fn respond(&self, request: ServerReflectionRequest) -> ServerReflectionResponse {
+        let message_response = match &request.message_request {
+            Some(MessageRequest::ListServices(_)) => {
+                MessageResponse::ListServicesResponse(ListServiceResponse {
+                    service: self
+                        .files
+                        .iter()
+                        .flat_map(|file| &file.services)
+                        .map(|name| ServiceResponse {
+                            name: name.clone().into(),
+                        })
+                        .collect(),
+                })
+            }
+            Some(MessageRequest::FileByFilename(name)) => {
+                match self.files.iter().find(|file| file.name == name.as_str()) {
+                    Some(file) => self.file_response(file),
+                    None => error_response(NOT_FOUND, format!("Unknown file: {}", name)),
+                }
+            }
+            Some(MessageRequest::FileContainingSymbol(symbol)) => {
+                let defining = self
+                    .files
+                    .iter()
+                    .find(|file| file.symbols.iter().any(|name| name == symbol.as_str()));
+                match defining {
+                    Some(file) => self.file_response(file),
+                    None => error_response(NOT_FOUND, format!("Unknown symbol: {}", symbol)),
+                }
+            }
+            Some(MessageRequest::FileContainingExtension(_))
+            | Some(MessageRequest::AllExtensionNumbersOfType(_)) => {
+                error_response(UNIMPLEMENTED, "Extensions are not supported".to_string())
+            }
+            None => error_response(
+                INVALID_ARGUMENT,
+                "The request names nothing to look up".to_string(),
+            ),
+        };
+
+        ServerReflectionResponse {
+            valid_host: request.host.clone(),
+            original_request: Some(request),
+            message_response: Some(message_response),
+        }
+    }
+
+    /// Builds the response carrying a file and every file it depends on, directly or not.
+    fn file_response(&self, file: &FileDescriptor) -> MessageResponse {
+        let mut pending = vec![file.name.as_str()];
+        let mut visited = HashSet::new();
+        let mut encoded = Vec::new();
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name) {
+                continue;
+            }
+            if let Some(file) = self.files.iter().find(|file| file.name == name) {
+                encoded.push(file.encoded.clone().into());
+                pending.extend(file.dependencies.iter().map(String::as_str));
+            }
+        }
+
+        MessageResponse::FileDescriptorResponse(FileDescriptorResponse {
+            file_descriptor_proto: encoded,
+        })
+    }
+}
+
+impl ServerReflection for ReflectionServiceImpl {
+    /// Answers the reflection requests of a client, one response per request.
+    ///
+    /// # Parameters
+    ///
+    /// * `request` - Request object containing the stream of reflection requests
+    ///
+    /// # Returns
+    ///
+    /// A response containing the stream of reflection responses
+    async fn server_reflection_info(
+        &self,
+        request: Request<RecvStream<ServerReflectionRequest>>,
+    ) -> Result<Response<BoxStream<'static, Result<ServerReflectionResponse, Status>>>, Status>
+    {
+        let service = self.clone();
+        let mut requests = request.into_inner();
+        let responses = async_stream::try_stream! {
+            while let Some(request) = requests.next().await {
+                yield service.respond(request?);
+            }
+        };
+
+        Ok(Response::new(Box::pin(responses)))
+    }
+}
+
+/// Builds the response reporting a failed lookup.
+fn error_response(error_code: i32, error_message: String) -> MessageResponse {
+    MessageResponse::ErrorResponse(ErrorResponse {
+        error_code,
+        error_message: error_message.into(),
+    })
+}
+
+/// Indexes an encoded `FileDescriptorProto`.
+///
+/// # Parameters
+///
+/// * `encoded` - The encoded file descriptor
+///
+/// # Returns
+///
+/// The file with its dependencies and symbols, or `None` if it cannot be decoded
+fn parse_file(encoded: &[u8]) -> Option<FileDescriptor> {
+    let mut file = FileDescriptor {
+        encoded: encoded.to_vec(),
+        ..FileDescriptor::default()
+    };
+    let mut package = String::new();
+    let (mut messages, mut enums, mut services) = (Vec::new(), Vec::new(), Vec::new());
+    for (number, value) in length_delimited_fields(encoded)? {
+        match number {
+            1 => file.name = utf8(value)?,
+            2 => package = utf8(value)?,
+            3 => file.dependencies.push(utf8(value)?),
+            4 => messages.push(value),
+            5 => enums.push(value),
+            6 => services.push(value),
+            _ => {}
+        }
+    }
+
+    let scope = if package.is_empty() {
+        String::new()
+    } else {
+        format!("{}.", package)
+    };
+    for message in messages {
+        collect_message_symbols(&scope, message, &mut file.symbols)?;
+    }
+    for enumeration in enums {
+        file.symbols
+            .push(format!("{}{}", scope, name_of(enumeration)?));
+    }
+    for service in services {
+        let name = format!("{}{}", scope, name_of(service)?);
+        for (number, method) in length_delimited_fields(service)? {
+            if number == 2 {
+                file.symbols.push(format!("{}.{}", name, name_of(method)?));
+            }
+        }
+        file.services.push(name.clone());
+        file.symbols.push(name);
+    }
+    Some(file)
+}
+
+/// Collects the full names of an encoded `DescriptorProto` and of its nested types.
+fn collect_message_symbols(scope: &str, message: &[u8], symbols: &mut Vec<String>) -> Option<()> {
+    let name = format!("{}{}", scope, name_of(message)?);
+    for (number, value) in length_delimited_fields(message)? {
+        match number {
+            3 => collect_message_symbols(&format!("{}.", name), value, symbols)?,
+            4 => symbols.push(format!("{}.{}", name, name_of(value)?)),
+            _ => {}
+        }
+    }
+    symbols.push(name);
+    Some(())
+}
+
+/// Reads the `name` field, numbered 1 in every descriptor type.
+fn name_of(descriptor: &[u8]) -> Option<String> {
+    length_delimited_fields(descriptor)?
+        .into_iter()
+        .find(|(number, _)| *number == 1)
+        .and_then(|(_, name)| utf8(name))
+}
+
+/// Decodes a string field.
+fn utf8(value: &[u8]) -> Option<String> {
+    String::from_utf8(value.to_vec()).ok()
+}
+
+/// Reads the length-delimited fields of an encoded protobuf message, skipping the others.
+///
+/// Descriptors are only looked up by name and nesting, all of which are
+/// length-delimited, so a full protobuf decoder is not needed.
+///
+/// # Parameters
+///
+/// * `message` - The encoded message
+///
+/// # Returns
+///
+/// The number and content of each length-delimited field in order, or `None` if the message is
+/// malformed
+fn length_delimited_fields(mut message: &[u8]) -> Option<Vec<(u64, &[u8])>> {
+    let mut fields = Vec::new();
+    while !message.is_empty() {
+        let key = read_varint(&mut message)?;
+        match key & 0x7 {
+            0 => {
+                read_varint(&mut message)?;
+            }
+            1 => message = message.get(8..)?,
+            2 => {
+                let length = usize::try_from(read_varint(&mut message)?).ok()?;
+                fields.push((key >> 3, message.get(..length)?));
+                message = &message[length..];
+            }
+            5 => message = message.get(4..)?,
+            _ => return None,
+        }
+    }
+    Some(fields)
+}
+
+/// Reads a base 128 varint, advancing past it.
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
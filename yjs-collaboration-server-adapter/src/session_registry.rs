@@ -0,0 +1,1183 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tracing::info;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    value_objects::{
+        access_role::{AccessGrant, AccessRole},
+        cursor::CursorPosition,
+        feature_policy::FeaturePolicies,
+        message::{Notice, NoticeKind, NoticeSeverity},
+        tenant::TenantQuotas,
+        update_origin::{UpdateOrigin, UpdateTransport},
+    },
+};
+
+use crate::{
+    clock::server_time,
+    delivery_stats::DeliveryStats,
+    rate_limiter::{UpdateRateLimit, UpdateRateLimiter},
+};
+
+/// Capacity of the channel delivering presence events to connections.
+const PRESENCE_CHANNEL_CAPACITY: usize = 256;
+
+/// Capacity of the channel delivering cursor moves to connections.
+///
+/// Each move supersedes the previous one of its client, so connections falling
+/// behind skip the oldest moves rather than slowing down.
+const CURSOR_CHANNEL_CAPACITY: usize = 1024;
+
+/// Maximum number of metadata entries a client may attach to its session.
+pub const MAX_METADATA_ENTRIES: usize = 32;
+
+/// Maximum length in bytes of a session metadata key or value.
+pub const MAX_METADATA_LENGTH: usize = 256;
+
+/// Limits on the sessions of a document and of a client, enforced when they join.
+///
+/// A value of `0` disables the corresponding limit, so the default limits admit
+/// every join. The number of connections across the server is limited by the
+/// admission controller instead.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RoomLimits {
+    /// Maximum number of clients present on a document at once
+    pub max_clients_per_document: usize,
+    /// Maximum number of documents a client is present on at once
+    pub max_documents_per_client: usize,
+}
+
+/// Bounds of the rolling window of presence events kept per document.
+///
+/// A size of `0` disables the history.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PresenceHistoryLimits {
+    /// Maximum number of events kept per document, the oldest being dropped first
+    pub max_events_per_document: usize,
+    /// Time after which an event is dropped
+    pub retention: Duration,
+}
+
+/// Keep-alive of the WebSocket connections.
+///
+/// The server pings every connection at a fixed interval, and closes the ones
+/// that sent no frame, not even a pong, for longer than the idle timeout, as if
+/// their clients left. `None` disables the pings or the timeout; the default
+/// disables both.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeepAlive {
+    /// Interval between two pings of a connection
+    pub ping_interval: Option<Duration>,
+    /// Time without any frame from the client after which a connection is closed
+    pub idle_timeout: Option<Duration>,
+}
+
+impl KeepAlive {
+    /// Returns the interval at which connections are pinged and checked for idleness, if any.
+    pub fn check_interval(&self) -> Option<Duration> {
+        self.ping_interval
+            .or(self.idle_timeout)
+            .filter(|interval| !interval.is_zero())
+    }
+}
+
+/// Change recorded in the presence history of a document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresenceChange {
+    /// A client joined the document
+    Joined,
+    /// A client left the document, disconnected, or was evicted or kicked
+    Left,
+}
+
+impl fmt::Display for PresenceChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Joined => "joined",
+            Self::Left => "left",
+        })
+    }
+}
+
+/// A join or departure kept in the presence history of a document.
+#[derive(Clone, Debug)]
+pub struct PresenceRecord {
+    /// Whether the client joined or left
+    pub change: PresenceChange,
+    /// Identifier of the connection
+    pub client_id: String,
+    /// Identity of the user, empty for a guest
+    pub user_id: String,
+    /// Display name of the user
+    pub user_name: String,
+    /// Transport the client was connected over
+    pub transport: Transport,
+    /// Time of the change, as server Unix seconds
+    pub at: i64,
+}
+
+impl PresenceRecord {
+    /// Records a change of a session at the current server time.
+    fn new(change: PresenceChange, session: &Session) -> Self {
+        Self {
+            change,
+            client_id: session.client_id.clone(),
+            user_id: session.user_id.clone(),
+            user_name: session.user_name.clone(),
+            transport: session.transport,
+            at: server_time(),
+        }
+    }
+}
+
+/// A document a user has open, over any number of connections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserDocument {
+    /// Identifier of the document
+    pub document_id: String,
+    /// Connections of the user present on the document, over any transport
+    pub connections: usize,
+    /// Last activity of the user on the document, as server Unix seconds
+    pub last_seen: i64,
+}
+
+/// A cursor move of a client, relayed to the other clients of the document.
+#[derive(Clone, Debug)]
+pub struct CursorEvent {
+    /// Document the cursor is in
+    pub document_id: String,
+    /// Identifier of the connection
+    pub client_id: String,
+    /// Identity of the user, empty for a guest
+    pub user_id: String,
+    /// Display name of the user
+    pub user_name: String,
+    /// Display color of the user
+    pub user_color: String,
+    /// The cursor, or `None` once the client cleared it (e.g. its editor lost focus)
+    pub position: Option<CursorPosition>,
+    /// Time of the move, as server Unix seconds
+    pub at: i64,
+}
+
+/// Transport a session is connected over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    /// A JSON or binary WebSocket connection
+    WebSocket,
+    /// A gRPC `Collaborate` stream
+    Grpc,
+    /// The server itself, e.g. the virtual collaborators of the simulation
+    Server,
+}
+
+impl Transport {
+    /// Returns whether clients of the transport send heartbeats while idle.
+    ///
+    /// Sessions over other transports are never evicted for inactivity; they
+    /// end with their connection.
+    pub fn has_heartbeat(self) -> bool {
+        matches!(self, Self::Grpc)
+    }
+}
+
+impl From<Transport> for UpdateTransport {
+    fn from(transport: Transport) -> Self {
+        match transport {
+            Transport::WebSocket => Self::WebSocket,
+            Transport::Grpc => Self::Grpc,
+            Transport::Server => Self::Server,
+        }
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WebSocket => "websocket",
+            Self::Grpc => "grpc",
+            Self::Server => "server",
+        })
+    }
+}
+
+/// A client present on a document.
+#[derive(Clone, Debug)]
+pub struct Session {
+    /// Identifier of the connection
+    pub client_id: String,
+    /// Document the client is present on
+    pub document_id: String,
+    /// Identity of the user, empty for a guest
+    pub user_id: String,
+    /// Display name of the user
+    pub user_name: String,
+    /// Display color of the user
+    pub user_color: String,
+    /// Free-form attributes attached by the client when connecting (e.g. device type, app
+    /// version), relayed as is in presence messages
+    pub user_metadata: HashMap<String, String>,
+    /// Permission the client holds on the document
+    pub role: AccessRole,
+    /// Last activity of the client, as server Unix seconds
+    pub last_seen: i64,
+    /// Transport the client is connected over
+    pub transport: Transport,
+    /// Address the client connected from, if known
+    pub remote_ip: Option<IpAddr>,
+}
+
+impl Session {
+    /// Creates the session of a guest without a display identity.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the connection
+    /// * `document_id` - Document the client is present on
+    /// * `transport` - Transport the client is connected over
+    ///
+    /// # Returns
+    ///
+    /// A new `Session` seen at the current server time
+    pub fn guest(client_id: &str, document_id: &str, transport: Transport) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            document_id: document_id.to_string(),
+            user_id: String::new(),
+            user_name: String::new(),
+            user_color: String::new(),
+            user_metadata: HashMap::new(),
+            role: AccessRole::ReadWrite,
+            last_seen: server_time(),
+            transport,
+            remote_ip: None,
+        }
+    }
+
+    /// Returns a copy of the session for another document, seen at the current server time.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - Document the client is present on
+    ///
+    /// # Returns
+    ///
+    /// A new `Session` with the same client, identity and metadata
+    pub fn on_document(&self, document_id: &str) -> Self {
+        Self {
+            document_id: document_id.to_string(),
+            last_seen: server_time(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the origin of the updates the client sends, recorded in audit trails.
+    ///
+    /// # Returns
+    ///
+    /// The client, its user identity unless a guest, and its transport
+    pub fn update_origin(&self) -> UpdateOrigin<'_> {
+        UpdateOrigin::new(&self.client_id, self.transport.into())
+            .with_user(Some(self.user_id.as_str()))
+    }
+}
+
+/// Checks the metadata a client attaches to its session against the size limits.
+///
+/// # Arguments
+///
+/// * `metadata` - The attached metadata
+///
+/// # Returns
+///
+/// `Ok(())`, or `DomainError::InvalidArgument` naming the exceeded limit
+pub fn check_metadata(metadata: &HashMap<String, String>) -> DomainResult<()> {
+    if metadata.len() > MAX_METADATA_ENTRIES {
+        return Err(DomainError::InvalidArgument(format!(
+            "At most {} metadata entries may be attached to a session",
+            MAX_METADATA_ENTRIES
+        )));
+    }
+
+    if let Some((key, _)) = metadata
+        .iter()
+        .find(|(key, value)| key.len() > MAX_METADATA_LENGTH || value.len() > MAX_METADATA_LENGTH)
+    {
+        return Err(DomainError::InvalidArgument(format!(
+            "Metadata entry '{}' exceeds {} bytes",
+            key.chars().take(32).collect::<String>(),
+            MAX_METADATA_LENGTH
+        )));
+    }
+
+    Ok(())
+}
+
+/// A change of the clients present on a document.
+#[derive(Clone, Debug)]
+pub enum PresenceEvent {
+    /// A client joined a document, or updated its identity
+    Joined(Session),
+    /// A client left a document or disconnected
+    Left(Session),
+    /// An operator changed the permission of a client's user on a document; only
+    /// the client itself is told, and a revoked client also leaves the document
+    PermissionChanged {
+        /// The client's session, holding its new role
+        session: Session,
+        /// The permission granted
+        grant: AccessGrant,
+    },
+    /// An operator disconnected a client from a document; the client has left
+    /// it, and its connection is closed
+    Kicked(Session),
+}
+
+impl PresenceEvent {
+    /// Returns the session the event concerns.
+    pub fn session(&self) -> &Session {
+        match self {
+            Self::Joined(session)
+            | Self::Left(session)
+            | Self::Kicked(session)
+            | Self::PermissionChanged { session, .. } => session,
+        }
+    }
+}
+
+/// Builds the notice telling a client its permission changed.
+///
+/// # Arguments
+///
+/// * `session` - The client's session
+/// * `grant` - The permission granted
+///
+/// # Returns
+///
+/// The `PermissionChanged` notice, addressed to the session's document
+pub fn permission_notice(session: &Session, grant: AccessGrant) -> Notice {
+    let (severity, message) = match grant {
+        AccessGrant::ReadOnly => (
+            NoticeSeverity::Warning,
+            "You can no longer edit this document",
+        ),
+        AccessGrant::ReadWrite => (NoticeSeverity::Info, "Your editing permission was restored"),
+        AccessGrant::Revoked => (
+            NoticeSeverity::Critical,
+            "Your access to this document was revoked",
+        ),
+    };
+    Notice::new(NoticeKind::PermissionChanged, severity, message)
+        .with_document(&session.document_id)
+}
+
+/// Registry of the clients present on each document, shared by every transport.
+///
+/// WebSocket and gRPC adapters register their sessions here, so active-user
+/// queries list every client whatever it is connected over, and each
+/// connection can relay the joins and departures of clients on other
+/// transports from the registry's presence events. The messages connections
+/// fail to deliver are counted here too, per document and session, and the
+/// updates clients send are rate limited here, whatever their transport.
+///
+/// Joins are checked against the room limits, so a crowded document or a
+/// client spread over too many documents is refused with a typed error rather
+/// than slowing down every session of the server. Joins are checked against
+/// the connection limit of the document's tenant too.
+///
+/// The joins and departures of each document are kept in a rolling window, so
+/// documents can report who was last active on them after everyone left. The
+/// history lives in memory and is lost when the server restarts.
+///
+/// Cursor moves travel on a channel of their own, apart from document updates
+/// and presence events, and each client's moves are throttled, so a burst of
+/// cursor moves never delays the updates of a document.
+pub struct SessionRegistry {
+    sessions: DashMap<(String, String), Session>,
+    /// Clients present on each document
+    document_clients: DashMap<String, HashSet<String>>,
+    /// Documents each client is present on
+    client_documents: DashMap<String, HashSet<String>>,
+    /// Number of sessions of each client on the documents of each tenant
+    tenant_clients: DashMap<String, HashMap<String, usize>>,
+    history: DashMap<String, VecDeque<PresenceRecord>>,
+    history_limits: PresenceHistoryLimits,
+    events: broadcast::Sender<PresenceEvent>,
+    cursors: broadcast::Sender<CursorEvent>,
+    /// Minimum delay between two relayed cursor moves of a client on a document
+    cursor_interval: Duration,
+    /// Time of the last relayed cursor move of each session
+    cursor_moves: DashMap<(String, String), Instant>,
+    delivery_stats: Arc<DeliveryStats>,
+    limits: RoomLimits,
+    tenant_quotas: TenantQuotas,
+    update_limiter: UpdateRateLimiter,
+    /// Pings and idle timeout of the WebSocket connections
+    keep_alive: KeepAlive,
+    /// Serializes joins, so concurrent joins cannot overshoot the limits
+    joins: Mutex<()>,
+}
+
+impl SessionRegistry {
+    /// Creates an empty registry.
+    ///
+    /// # Returns
+    ///
+    /// A new `SessionRegistry` instance.
+    pub fn new() -> Self {
+        Self {
+            sessions: DashMap::new(),
+            document_clients: DashMap::new(),
+            client_documents: DashMap::new(),
+            tenant_clients: DashMap::new(),
+            history: DashMap::new(),
+            history_limits: PresenceHistoryLimits::default(),
+            events: broadcast::channel(PRESENCE_CHANNEL_CAPACITY).0,
+            cursors: broadcast::channel(CURSOR_CHANNEL_CAPACITY).0,
+            cursor_interval: Duration::ZERO,
+            cursor_moves: DashMap::new(),
+            delivery_stats: Arc::new(DeliveryStats::default()),
+            limits: RoomLimits::default(),
+            tenant_quotas: TenantQuotas::default(),
+            update_limiter: UpdateRateLimiter::default(),
+            keep_alive: KeepAlive::default(),
+            joins: Mutex::new(()),
+        }
+    }
+
+    /// Limits the clients of each document and the documents of each client.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The limits enforced when a client joins a document
+    ///
+    /// # Returns
+    ///
+    /// The `SessionRegistry` enforcing the given limits
+    pub fn with_limits(mut self, limits: RoomLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Limits the clients connected to the documents of each tenant.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant_quotas` - The limits of every tenant
+    ///
+    /// # Returns
+    ///
+    /// The `SessionRegistry` enforcing the tenants' connection limits
+    pub fn with_tenant_quotas(mut self, tenant_quotas: TenantQuotas) -> Self {
+        self.tenant_quotas = tenant_quotas;
+        self
+    }
+
+    /// Keeps a rolling window of the joins and departures of each document.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The bounds of the window kept per document
+    ///
+    /// # Returns
+    ///
+    /// The `SessionRegistry` recording presence history
+    pub fn with_presence_history(mut self, limits: PresenceHistoryLimits) -> Self {
+        self.history_limits = limits;
+        self
+    }
+
+    /// Throttles the cursor moves each client may send on a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Minimum delay between two relayed moves, `Duration::ZERO` to relay them all
+    ///
+    /// # Returns
+    ///
+    /// The `SessionRegistry` throttling cursor moves
+    pub fn with_cursor_interval(mut self, interval: Duration) -> Self {
+        self.cursor_interval = interval;
+        self
+    }
+
+    /// Limits the rate of the updates each client may send.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The rate and burst of the updates each client may send
+    ///
+    /// # Returns
+    ///
+    /// The `SessionRegistry` rate limiting updates
+    pub fn with_update_rate_limit(mut self, limit: UpdateRateLimit) -> Self {
+        self.update_limiter = UpdateRateLimiter::new(limit);
+        self
+    }
+
+    /// Pings the WebSocket connections and closes the ones whose clients went silent.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_alive` - The ping interval and idle timeout of the connections
+    ///
+    /// # Returns
+    ///
+    /// The `SessionRegistry` keeping WebSocket connections alive
+    pub fn with_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Returns the keep-alive of the WebSocket connections.
+    pub fn keep_alive(&self) -> KeepAlive {
+        self.keep_alive
+    }
+
+    /// Returns the limiter of the updates clients send.
+    pub fn update_limiter(&self) -> &UpdateRateLimiter {
+        &self.update_limiter
+    }
+
+    /// Returns the counters of the messages connections failed to deliver.
+    pub fn delivery_stats(&self) -> Arc<DeliveryStats> {
+        self.delivery_stats.clone()
+    }
+
+    /// Checks whether a new client may join a document, before its connection is set up.
+    ///
+    /// The check is repeated when the client joins, as other clients may join in the meantime.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the client is about to join
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or `DomainError::RoomFull` if the document, or its tenant, already has as many
+    /// clients as allowed
+    pub fn check_room(&self, document_id: &str) -> DomainResult<()> {
+        let max = self.limits.max_clients_per_document;
+        if max > 0 && self.client_count(document_id) >= max {
+            return Err(room_full(document_id, max));
+        }
+        self.check_tenant(document_id, None)
+    }
+
+    /// Checks whether a client may connect to the documents of a document's tenant.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the client is about to join
+    /// * `client_id` - Identifier of the connection, or `None` for a connection not set up yet
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the document belongs to no tenant or the client is already present on the
+    /// tenant's documents, or `DomainError::RoomFull` if the tenant already has as many clients
+    /// as allowed
+    fn check_tenant(&self, document_id: &str, client_id: Option<&str>) -> DomainResult<()> {
+        let Some((tenant, limits)) = self.tenant_quotas.limits_of(document_id) else {
+            return Ok(());
+        };
+        let max = limits.max_connections;
+        if max == 0 {
+            return Ok(());
+        }
+
+        let Some(clients) = self.tenant_clients.get(tenant) else {
+            return Ok(());
+        };
+        if client_id.is_some_and(|client_id| clients.contains_key(client_id)) {
+            return Ok(());
+        }
+
+        if clients.len() >= max {
+            return Err(DomainError::RoomFull(format!(
+                "Tenant '{}' already has {} connected clients, the maximum allowed",
+                tenant, max
+            )));
+        }
+        Ok(())
+    }
+
+    /// Registers a client on a document, replacing its previous session there.
+    ///
+    /// A client already present on the document is always let in again, e.g. to
+    /// update its identity; new sessions are checked against the room limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The client's session
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or `DomainError::RoomFull` if the document or its tenant already has as many
+    /// clients as allowed, or the client is already present on as many documents as allowed
+    pub fn join(&self, session: Session) -> DomainResult<()> {
+        let key = (session.document_id.clone(), session.client_id.clone());
+        let _joins = self
+            .joins
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let rejoined = self.sessions.contains_key(&key);
+        if !rejoined {
+            let max = self.limits.max_clients_per_document;
+            if max > 0 && self.client_count(&session.document_id) >= max {
+                return Err(room_full(&session.document_id, max));
+            }
+            self.check_tenant(&session.document_id, Some(&session.client_id))?;
+
+            let max = self.limits.max_documents_per_client;
+            if max > 0 && self.document_count(&session.client_id) >= max {
+                return Err(DomainError::RoomFull(format!(
+                    "Client {} is already present on {} documents, the maximum allowed",
+                    session.client_id, max
+                )));
+            }
+        }
+
+        info!(
+            "Client {} joined document '{}' over {} (user: '{}', metadata: {:?})",
+            session.client_id,
+            session.document_id,
+            session.transport,
+            session.user_id,
+            session.user_metadata
+        );
+        if !rejoined {
+            self.record_presence(PresenceChange::Joined, &session);
+        }
+        if self.sessions.insert(key, session.clone()).is_none() {
+            self.index(&session.document_id, &session.client_id);
+        }
+        let _ = self.events.send(PresenceEvent::Joined(session));
+        Ok(())
+    }
+
+    /// Adds a new session to the indexes by document, client and tenant.
+    fn index(&self, document_id: &str, client_id: &str) {
+        self.document_clients
+            .entry(document_id.to_string())
+            .or_default()
+            .insert(client_id.to_string());
+        self.client_documents
+            .entry(client_id.to_string())
+            .or_default()
+            .insert(document_id.to_string());
+        if let Some(tenant) = FeaturePolicies::namespace_of(document_id) {
+            *self
+                .tenant_clients
+                .entry(tenant.to_string())
+                .or_default()
+                .entry(client_id.to_string())
+                .or_default() += 1;
+        }
+    }
+
+    /// Removes a session from the indexes by document, client and tenant.
+    fn unindex(&self, document_id: &str, client_id: &str) {
+        if let Some(mut clients) = self.document_clients.get_mut(document_id) {
+            clients.remove(client_id);
+        }
+        self.document_clients
+            .remove_if(document_id, |_, clients| clients.is_empty());
+
+        if let Some(mut documents) = self.client_documents.get_mut(client_id) {
+            documents.remove(document_id);
+        }
+        self.client_documents
+            .remove_if(client_id, |_, documents| documents.is_empty());
+
+        let Some(tenant) = FeaturePolicies::namespace_of(document_id) else {
+            return;
+        };
+        if let Some(mut clients) = self.tenant_clients.get_mut(tenant) {
+            if let Some(sessions) = clients.get_mut(client_id) {
+                *sessions -= 1;
+                if *sessions == 0 {
+                    clients.remove(client_id);
+                }
+            }
+        }
+        self.tenant_clients
+            .remove_if(tenant, |_, clients| clients.is_empty());
+    }
+
+    /// Returns the documents a client is present on.
+    fn documents_of(&self, client_id: &str) -> Vec<String> {
+        self.client_documents
+            .get(client_id)
+            .map(|documents| documents.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes a client from a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the client leaves
+    /// * `client_id` - Identifier of the connection
+    ///
+    /// # Returns
+    ///
+    /// The removed session, or `None` if the client was not present
+    pub fn leave(&self, document_id: &str, client_id: &str) -> Option<Session> {
+        let (_, session) = self
+            .sessions
+            .remove(&(document_id.to_string(), client_id.to_string()))?;
+        self.unindex(document_id, client_id);
+        self.delivery_stats.forget(document_id, client_id);
+        self.cursor_moves
+            .remove(&(document_id.to_string(), client_id.to_string()));
+        self.record_presence(PresenceChange::Left, &session);
+        let _ = self.events.send(PresenceEvent::Left(session.clone()));
+        Some(session)
+    }
+
+    /// Applies a permission change to the sessions of a user, while they stay connected.
+    ///
+    /// The role of every matching session is flipped and its client is sent a
+    /// `PermissionChanged` event; revoked sessions are removed, so the other
+    /// clients of the document see the user leave.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Identity of the user
+    /// * `document_id` - The document the change applies to, or `None` for every document
+    /// * `grant` - The permission granted
+    /// * `role` - Resolves the role of a session once the grant applies
+    ///
+    /// # Returns
+    ///
+    /// The number of affected sessions
+    pub fn change_permission(
+        &self,
+        user_id: &str,
+        document_id: Option<&str>,
+        grant: AccessGrant,
+        role: impl Fn(&str) -> Option<AccessRole>,
+    ) -> usize {
+        let keys: Vec<(String, String)> = self
+            .sessions
+            .iter()
+            .filter(|entry| {
+                !user_id.is_empty()
+                    && entry.user_id == user_id
+                    && (document_id.is_none() || document_id == Some(entry.document_id.as_str()))
+            })
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        let mut changed = 0;
+        for (doc_id, client_id) in keys {
+            let session = match role(&doc_id) {
+                Some(role) => {
+                    let Some(mut session) =
+                        self.sessions.get_mut(&(doc_id.clone(), client_id.clone()))
+                    else {
+                        continue;
+                    };
+                    session.role = role;
+                    session.clone()
+                }
+                None => match self.leave(&doc_id, &client_id) {
+                    Some(session) => session,
+                    None => continue,
+                },
+            };
+
+            info!(
+                "Permission of client {} on document '{}' changed to {:?} (user: '{}')",
+                client_id, doc_id, grant, user_id
+            );
+            let _ = self
+                .events
+                .send(PresenceEvent::PermissionChanged { session, grant });
+            changed += 1;
+        }
+        changed
+    }
+
+    /// Disconnects a client from a document at an operator's request.
+    ///
+    /// The client leaves the document, so its other clients see it leave, and
+    /// is sent a `Kicked` event closing its connection, which also removes it
+    /// from the other documents it was present on.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the client is disconnected from
+    /// * `client_id` - Identifier of the connection
+    ///
+    /// # Returns
+    ///
+    /// The removed session, or `None` if the client was not present
+    pub fn kick(&self, document_id: &str, client_id: &str) -> Option<Session> {
+        let session = self.leave(document_id, client_id)?;
+        info!(
+            "Client {} disconnected from document '{}' by an operator",
+            client_id, document_id
+        );
+        let _ = self.events.send(PresenceEvent::Kicked(session.clone()));
+        Some(session)
+    }
+
+    /// Disconnects every client of a document at an operator's request.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document whose clients are disconnected
+    ///
+    /// # Returns
+    ///
+    /// The number of disconnected clients
+    pub fn kick_all(&self, document_id: &str) -> usize {
+        self.active_users(document_id)
+            .iter()
+            .filter_map(|session| self.kick(document_id, &session.client_id))
+            .count()
+    }
+
+    /// Removes a disconnected client from every document it was present on.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the connection
+    pub fn disconnect(&self, client_id: &str) {
+        for document_id in self.documents_of(client_id) {
+            self.leave(&document_id, client_id);
+        }
+        // Also covers the documents the client synchronized with without joining
+        self.delivery_stats.forget_client(client_id);
+        self.update_limiter.purge_idle();
+    }
+
+    /// Records activity of a client on a document, in server time.
+    pub fn touch(&self, document_id: &str, client_id: &str) {
+        if let Some(mut session) = self
+            .sessions
+            .get_mut(&(document_id.to_string(), client_id.to_string()))
+        {
+            session.last_seen = server_time();
+        }
+    }
+
+    /// Records activity of a client on every document it is present on.
+    pub fn touch_client(&self, client_id: &str) {
+        let now = server_time();
+        for document_id in self.documents_of(client_id) {
+            if let Some(mut session) = self.sessions.get_mut(&(document_id, client_id.to_string()))
+            {
+                session.last_seen = now;
+            }
+        }
+    }
+
+    /// Removes the sessions idle for longer than a timeout, as if their clients left.
+    ///
+    /// Only sessions over a transport with heartbeats are evicted, so the
+    /// clients of a half-open connection stop being listed as present.
+    ///
+    /// # Arguments
+    ///
+    /// * `idle_timeout` - Time without activity after which a session is evicted
+    ///
+    /// # Returns
+    ///
+    /// The number of evicted sessions
+    pub fn evict_idle(&self, idle_timeout: Duration) -> usize {
+        let deadline = server_time() - idle_timeout.as_secs() as i64;
+        let idle: Vec<(String, String)> = self
+            .sessions
+            .iter()
+            .filter(|entry| entry.transport.has_heartbeat() && entry.last_seen < deadline)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        let mut evicted = 0;
+        for key in idle {
+            // The client may have been active since the scan
+            if let Some((_, session)) = self
+                .sessions
+                .remove_if(&key, |_, session| session.last_seen < deadline)
+            {
+                self.unindex(&key.0, &key.1);
+                self.cursor_moves.remove(&key);
+                self.record_presence(PresenceChange::Left, &session);
+                let _ = self.events.send(PresenceEvent::Left(session));
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    /// Spawns a task evicting idle sessions at a fixed interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Delay between two scans
+    /// * `idle_timeout` - Time without activity after which a session is evicted
+    ///
+    /// # Returns
+    ///
+    /// The handle of the reaper task
+    pub fn spawn_reaper(
+        self: &Arc<Self>,
+        interval: Duration,
+        idle_timeout: Duration,
+    ) -> JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                registry.prune_presence_history();
+                let evicted = registry.evict_idle(idle_timeout);
+                if evicted > 0 {
+                    info!(
+                        "Evicted {} sessions idle for over {:?}",
+                        evicted, idle_timeout
+                    );
+                }
+            }
+        })
+    }
+
+    /// Returns whether a client is present on a document.
+    pub fn is_present(&self, document_id: &str, client_id: &str) -> bool {
+        self.sessions
+            .contains_key(&(document_id.to_string(), client_id.to_string()))
+    }
+
+    /// Returns the user identity a client joined a document with.
+    ///
+    /// # Returns
+    ///
+    /// The user ID, or `None` if the client has not joined with an identity
+    pub fn user_id(&self, document_id: &str, client_id: &str) -> Option<String> {
+        self.sessions
+            .get(&(document_id.to_string(), client_id.to_string()))
+            .map(|session| session.user_id.clone())
+            .filter(|user_id| !user_id.is_empty())
+    }
+
+    /// Lists the clients present on a document, over any transport.
+    pub fn active_users(&self, document_id: &str) -> Vec<Session> {
+        let clients: Vec<String> = self
+            .document_clients
+            .get(document_id)
+            .map(|clients| clients.iter().cloned().collect())
+            .unwrap_or_default();
+        clients
+            .into_iter()
+            .filter_map(|client_id| {
+                self.sessions
+                    .get(&(document_id.to_string(), client_id))
+                    .map(|session| session.value().clone())
+            })
+            .collect()
+    }
+
+    /// Returns the number of clients present on a document.
+    pub fn client_count(&self, document_id: &str) -> usize {
+        self.document_clients
+            .get(document_id)
+            .map_or(0, |clients| clients.len())
+    }
+
+    /// Returns the number of documents a client is present on.
+    pub fn document_count(&self, client_id: &str) -> usize {
+        self.client_documents
+            .get(client_id)
+            .map_or(0, |documents| documents.len())
+    }
+
+    /// Lists the documents a user currently has open, e.g. for a "currently editing" view.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Identity the user joined with; guests cannot be listed
+    ///
+    /// # Returns
+    ///
+    /// The documents, sorted by identifier, with the user's connections on each
+    pub fn user_documents(&self, user_id: &str) -> Vec<UserDocument> {
+        if user_id.is_empty() {
+            return Vec::new();
+        }
+
+        let mut documents: BTreeMap<String, UserDocument> = BTreeMap::new();
+        for entry in self
+            .sessions
+            .iter()
+            .filter(|entry| entry.user_id == user_id)
+        {
+            let document = documents
+                .entry(entry.document_id.clone())
+                .or_insert_with(|| UserDocument {
+                    document_id: entry.document_id.clone(),
+                    connections: 0,
+                    last_seen: entry.last_seen,
+                });
+            document.connections += 1;
+            document.last_seen = document.last_seen.max(entry.last_seen);
+        }
+        documents.into_values().collect()
+    }
+
+    /// Lists the sessions of every document, over any transport.
+    pub fn sessions(&self) -> Vec<Session> {
+        self.sessions
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Returns the number of sessions across every document.
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Lists the joins and departures kept in the history of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document whose history is listed
+    ///
+    /// # Returns
+    ///
+    /// The events within the retention period, oldest first, empty if there is none or the
+    /// history is disabled
+    pub fn presence_history(&self, document_id: &str) -> Vec<PresenceRecord> {
+        let cutoff = self.history_cutoff();
+        let Some(mut events) = self.history.get_mut(document_id) else {
+            return Vec::new();
+        };
+        while events.front().is_some_and(|event| event.at < cutoff) {
+            events.pop_front();
+        }
+        events.iter().cloned().collect()
+    }
+
+    /// Drops the presence events past the retention period, and the histories left empty.
+    pub fn prune_presence_history(&self) {
+        let cutoff = self.history_cutoff();
+        self.history.retain(|_, events| {
+            while events.front().is_some_and(|event| event.at < cutoff) {
+                events.pop_front();
+            }
+            !events.is_empty()
+        });
+    }
+
+    /// Appends a change of a session to the history of its document.
+    fn record_presence(&self, change: PresenceChange, session: &Session) {
+        let max = self.history_limits.max_events_per_document;
+        if max == 0 {
+            return;
+        }
+
+        let mut events = self.history.entry(session.document_id.clone()).or_default();
+        events.push_back(PresenceRecord::new(change, session));
+        while events.len() > max {
+            events.pop_front();
+        }
+    }
+
+    /// Returns the time before which presence events are dropped, as server Unix seconds.
+    fn history_cutoff(&self) -> i64 {
+        server_time() - self.history_limits.retention.as_secs() as i64
+    }
+
+    /// Relays a client's cursor move to the other clients of the document.
+    ///
+    /// Moves arriving less than the cursor interval after the last relayed one
+    /// are dropped, as the client's next move supersedes them; clearing a
+    /// cursor is always relayed, so it never lingers on the other clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_id` - The document the cursor is in
+    /// * `client_id` - Identifier of the connection
+    /// * `position` - The cursor, or `None` to clear it
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the move was relayed, `false` if it was throttled
+    /// * `Err(DomainError)` - `InvalidArgument` if the client is not present on the document
+    pub fn publish_cursor(
+        &self,
+        document_id: &str,
+        client_id: &str,
+        position: Option<CursorPosition>,
+    ) -> DomainResult<bool> {
+        let key = (document_id.to_string(), client_id.to_string());
+        let Some(session) = self.sessions.get(&key) else {
+            return Err(DomainError::InvalidArgument(format!(
+                "Client {} must join document '{}' before sharing its cursor",
+                client_id, document_id
+            )));
+        };
+        let event = CursorEvent {
+            document_id: session.document_id.clone(),
+            client_id: session.client_id.clone(),
+            user_id: session.user_id.clone(),
+            user_name: session.user_name.clone(),
+            user_color: session.user_color.clone(),
+            position,
+            at: server_time(),
+        };
+        drop(session);
+
+        if event.position.is_some() && !self.cursor_interval.is_zero() {
+            let now = Instant::now();
+            match self.cursor_moves.entry(key) {
+                Entry::Occupied(last) if now < *last.get() + self.cursor_interval => {
+                    return Ok(false)
+                }
+                Entry::Occupied(mut last) => {
+                    last.insert(now);
+                }
+                Entry::Vacant(last) => {
+                    last.insert(now);
+                }
+            }
+        }
+
+        let _ = self.cursors.send(event);
+        Ok(true)
+    }
+
+    /// Subscribes to the cursor moves of clients on every document.
+    pub fn subscribe_cursors(&self) -> broadcast::Receiver<CursorEvent> {
+        self.cursors.subscribe()
+    }
+
+    /// Subscribes to the joins and departures of clients on every document.
+    pub fn subscribe(&self) -> broadcast::Receiver<PresenceEvent> {
+        self.events.subscribe()
+    }
+}
+
+/// Builds the error refusing a client on a full document.
+fn room_full(document_id: &str, max: usize) -> DomainError {
+    DomainError::RoomFull(format!(
+        "Document '{}' already has {} clients, the maximum allowed",
+        document_id, max
+    ))
+}
+
+impl Default for SessionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
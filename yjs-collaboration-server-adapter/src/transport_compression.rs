@@ -0,0 +1,169 @@
+use std::{
+    borrow::Cow,
+    io::{Read, Write},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use tracing::warn;
+use yjs_collaboration_server_domain::errors::DomainError;
+
+/// Default size below which payloads are sent uncompressed.
+const DEFAULT_MIN_PAYLOAD_SIZE: usize = 4096;
+
+/// Default Zstandard compression level.
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Default gzip compression level.
+const DEFAULT_GZIP_LEVEL: u32 = 6;
+
+/// Default maximum size of a decompressed payload, when the size of updates is not limited.
+const DEFAULT_MAX_DECODED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Generic compression algorithm of a transported update payload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransportEncoding {
+    /// The payload is sent as is
+    #[default]
+    Identity,
+    /// The payload is a single Zstandard frame
+    Zstd,
+    /// The payload is in the gzip format
+    Gzip,
+}
+
+/// Transparent compression of the update payloads of gRPC messages.
+///
+/// Unlike dictionary compression, which suits the small updates of chatty
+/// documents, this compresses the large payloads of the initial sync and of
+/// the chunks of oversized diffs with a generic algorithm any client has at
+/// hand. Clients opt in by naming the algorithm they decode when joining a
+/// document; payloads above the size threshold are then compressed with it,
+/// unless compression does not make them smaller. Clients may send compressed
+/// payloads too, which are decompressed before being applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransportCompression {
+    /// Whether payloads sent to clients are compressed at all
+    pub enabled: bool,
+    /// Size in bytes below which payloads are sent uncompressed
+    pub min_payload_size: usize,
+    /// Zstandard compression level (1-22)
+    pub zstd_level: i32,
+    /// Gzip compression level (0-9)
+    pub gzip_level: u32,
+    /// Maximum size in bytes of a payload received from a client, once decompressed
+    pub max_decoded_size: usize,
+}
+
+impl Default for TransportCompression {
+    /// Creates settings compressing the payloads of at least 4 KiB with default levels.
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_payload_size: DEFAULT_MIN_PAYLOAD_SIZE,
+            zstd_level: DEFAULT_ZSTD_LEVEL,
+            gzip_level: DEFAULT_GZIP_LEVEL,
+            max_decoded_size: DEFAULT_MAX_DECODED_SIZE,
+        }
+    }
+}
+
+impl TransportCompression {
+    /// Compresses a payload sent to a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `accepted` - The encoding the client decodes
+    /// * `payload` - The payload to send
+    ///
+    /// # Returns
+    ///
+    /// `None` if the payload must be sent as is, because the client decodes no
+    /// encoding, compression is disabled, the payload is below the threshold or
+    /// compressing it fails or does not make it smaller; otherwise the encoding
+    /// and the compressed payload
+    pub fn encode(
+        &self,
+        accepted: TransportEncoding,
+        payload: &[u8],
+    ) -> Option<(TransportEncoding, Vec<u8>)> {
+        if !self.enabled || payload.len() < self.min_payload_size {
+            return None;
+        }
+
+        let compressed = match accepted {
+            TransportEncoding::Identity => return None,
+            TransportEncoding::Zstd => zstd::bulk::compress(payload, self.zstd_level),
+            TransportEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::new(self.gzip_level));
+                encoder.write_all(payload).and_then(|_| encoder.finish())
+            }
+        };
+
+        match compressed {
+            Ok(compressed) if compressed.len() < payload.len() => Some((accepted, compressed)),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Failed to compress a payload with {:?}: {}", accepted, e);
+                None
+            }
+        }
+    }
+
+    /// Decompresses a payload received from a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoding` - The encoding the client flagged the payload with
+    /// * `payload` - The received payload
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cow<[u8]>)` - The decompressed payload, borrowed if it was not compressed
+    /// * `Err(DomainError)` - `InvalidUpdate` if the payload cannot be decompressed, or
+    ///   `PayloadTooLarge` if it decompresses to more than the maximum size
+    pub fn decode<'a>(
+        &self,
+        encoding: TransportEncoding,
+        payload: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, DomainError> {
+        match encoding {
+            TransportEncoding::Identity => Ok(Cow::Borrowed(payload)),
+            TransportEncoding::Zstd => zstd::stream::read::Decoder::with_buffer(payload)
+                .map_err(|e| invalid_payload(encoding, e))
+                .and_then(|decoder| self.read_capped(encoding, decoder))
+                .map(Cow::Owned),
+            TransportEncoding::Gzip => self
+                .read_capped(encoding, GzDecoder::new(payload))
+                .map(Cow::Owned),
+        }
+    }
+
+    /// Reads a decoder to the end, stopping past the maximum decoded size.
+    fn read_capped(
+        &self,
+        encoding: TransportEncoding,
+        decoder: impl Read,
+    ) -> Result<Vec<u8>, DomainError> {
+        let mut decoded = Vec::new();
+        decoder
+            .take(self.max_decoded_size as u64 + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|e| invalid_payload(encoding, e))?;
+
+        if decoded.len() > self.max_decoded_size {
+            return Err(DomainError::PayloadTooLarge(format!(
+                "Compressed update exceeds {} bytes once decompressed",
+                self.max_decoded_size
+            )));
+        }
+        Ok(decoded)
+    }
+}
+
+/// Builds the error reporting a payload that cannot be decompressed.
+fn invalid_payload(encoding: TransportEncoding, error: std::io::Error) -> DomainError {
+    DomainError::InvalidUpdate(format!(
+        "Failed to decompress a {:?} update: {}",
+        encoding, error
+    ))
+}
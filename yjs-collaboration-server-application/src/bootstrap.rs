@@ -1,14 +1,29 @@
-use std::path::Path;
+use std::{future::Future, path::Path, pin::Pin, sync::Arc};
 
-use tokio::try_join;
-use tracing::{info, warn};
+use futures::future::try_join_all;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+use yjs_collaboration_server_adapter::{
+    http::admin::{ConfigControl, StandbyControl},
+    session_registry::PresenceEvent,
+};
 
 use crate::{
-    config::AppConfig,
+    check::{self, CheckReport},
+    config::{
+        AppConfig, ConfigOverrides, LogLevelHandle, MetricsBackend, SearchBackend, StorageBackend,
+    },
+    config_reload::ConfigReloader,
     container::Container,
-    servers::{HttpServer, RpcServer},
+    replay::{self, ReplayReport},
+    servers::{AdminServer, HttpServer, RpcServer},
+    simulation::{Simulation, SimulationConfig},
 };
 
+/// A running server, boxed so servers of different types can be joined
+type ServerFuture =
+    Pin<Box<dyn Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send>>;
+
 /// Default configuration file path for the application
 const DEFAULT_CONFIG_PATH: &str = "./config/bootstrap.yaml";
 
@@ -24,6 +39,14 @@ pub struct ApplicationBootstrap {
     config: AppConfig,
     /// Dependency injection container for services and repositories
     container: Container,
+    /// Virtual collaborators started alongside the servers, for protocol debugging
+    simulation: Option<SimulationConfig>,
+    /// Configuration file the configuration was loaded from, reloaded while the servers run
+    config_path: Option<String>,
+    /// Command line flags applied over the configuration file and on every reload
+    overrides: ConfigOverrides,
+    /// Handle changing the log level of the logging system initialized on startup
+    log_level: Option<LogLevelHandle>,
 }
 
 impl ApplicationBootstrap {
@@ -37,59 +60,195 @@ impl ApplicationBootstrap {
     /// # Returns
     ///
     /// A new `ApplicationBootstrap` instance ready for running the application
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dependencies cannot be initialized; use `try_new` to handle the error
     pub fn new() -> Self {
-        // Try loading configuration from a yaml file
-        let config = Self::load_config();
-        config.init_logging();
+        Self::try_new().unwrap_or_else(|e| panic!("Failed to initialize application: {}", e))
+    }
+
+    /// Creates a new application bootstrap instance, reporting initialization failures.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ApplicationBootstrap)` - An instance ready for running the application
+    /// * `Err(String)` - Error message if the configuration is invalid or a dependency (e.g. the
+    ///   storage backend) fails to initialize
+    pub fn try_new() -> Result<Self, String> {
+        Self::try_with_overrides(ConfigOverrides::default())
+    }
+
+    /// Creates a new application bootstrap instance, with settings given on the command line.
+    ///
+    /// A configuration loaded from a file is reloaded on `SIGHUP` while the
+    /// application runs, with the same command line settings applied over it.
+    ///
+    /// # Parameters
+    ///
+    /// * `overrides` - Settings given as command line flags, overriding every other source
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ApplicationBootstrap)` - An instance ready for running the application
+    /// * `Err(String)` - Error message if the configuration is invalid or a dependency (e.g. the
+    ///   storage backend) fails to initialize
+    pub fn try_with_overrides(overrides: ConfigOverrides) -> Result<Self, String> {
+        let (config, config_path) = Self::load_config(&overrides)?;
+        config.validate()?;
+        let log_level = config.init_logging();
+        match &config_path {
+            Some(path) => info!("The configuration is loaded from {}", path),
+            None => {
+                info!("No configuration file, the configuration is loaded from the environment")
+            }
+        }
 
-        let container = Container::new();
+        let mut bootstrap = Self::from_config(config)?;
+        bootstrap.config_path = config_path;
+        bootstrap.overrides = overrides;
+        bootstrap.log_level = Some(log_level);
+        Ok(bootstrap)
+    }
+
+    /// Creates an application bootstrap instance from a given configuration.
+    ///
+    /// The configuration is neither loaded from a file nor from environment
+    /// variables, and logging is left uninitialized, so the server can be
+    /// embedded in another process, such as an integration test.
+    ///
+    /// # Parameters
+    ///
+    /// * `config` - The application configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ApplicationBootstrap)` - An instance ready for running the application
+    /// * `Err(String)` - Error message if a dependency (e.g. the storage backend) fails to
+    ///   initialize
+    pub fn from_config(config: AppConfig) -> Result<Self, String> {
+        let container = Container::new(&config)?;
 
-        Self { config, container }
+        Ok(Self {
+            config,
+            container,
+            simulation: None,
+            config_path: None,
+            overrides: ConfigOverrides::default(),
+            log_level: None,
+        })
+    }
+
+    /// Enables simulation mode.
+    ///
+    /// Scripted virtual collaborators edit the configured document while the
+    /// servers run, so editor integrations can be tested locally against
+    /// realistic multi-user traffic.
+    ///
+    /// # Parameters
+    ///
+    /// * `simulation` - Simulation settings
+    ///
+    /// # Returns
+    ///
+    /// The `ApplicationBootstrap` with simulation mode enabled
+    pub fn with_simulation(mut self, simulation: SimulationConfig) -> Self {
+        self.simulation = Some(simulation);
+        self
     }
 
     /// Loads application configuration from available sources.
     ///
-    /// The configuration loading follows this priority:
-    /// 1. YAML file specified by CONFIG_PATH environment variable
-    /// 2. Default YAML file at ./config/bootstrap.yaml
-    /// 3. Fall back to environment variables if file loading fails
+    /// Each source overrides the settings of the previous ones:
+    /// 1. The default configuration
+    /// 2. The YAML file given by `--config`, by the CONFIG_PATH environment variable, or the
+    ///    default file at ./config/bootstrap.yaml; only the latter two may be missing
+    /// 3. Environment variables
+    /// 4. Command line flags
+    ///
+    /// # Parameters
+    ///
+    /// * `overrides` - Settings given as command line flags
     ///
     /// # Returns
     ///
-    /// An `AppConfig` instance containing the application configuration
-    fn load_config() -> AppConfig {
-        // First check whether the configuration file path is specified through the environment
-        // variable
-        let config_path =
-            std::env::var("CONFIG_PATH").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
-
-        // Try to load the configuration from the specified YAML file
-        if AppConfig::config_exists(&config_path) {
-            match AppConfig::from_yaml(&config_path) {
-                Ok(config) => {
-                    info!(
-                        "The configuration is loaded successfully from the configuration file {}",
-                        config_path
-                    );
-                    return config;
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to load from configuration file: {}, will be configured using \
-                         environment variables",
-                        e
-                    );
-                }
+    /// * `Ok((AppConfig, Option<String>))` - The configuration, not validated yet, along with the
+    ///   path of the file it was loaded from, if any
+    /// * `Err(String)` - Error message if a source holds an invalid setting; a file failing to
+    ///   parse is not skipped
+    fn load_config(overrides: &ConfigOverrides) -> Result<(AppConfig, Option<String>), String> {
+        let config_path = match &overrides.config_path {
+            Some(path) if !AppConfig::config_exists(path) => {
+                return Err(format!("Configuration file {} does not exist", path));
             }
-        } else {
-            info!(
-                "Configuration file {} does not exist, will use environment variable configuration",
-                config_path
-            );
-        }
+            Some(path) => Some(path.clone()),
+            None => Some(
+                std::env::var("CONFIG_PATH").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string()),
+            )
+            .filter(AppConfig::config_exists),
+        };
+
+        let config = AppConfig::load(config_path.as_deref(), overrides)?;
+        Ok((config, config_path))
+    }
 
-        // Fall back to environment variable configuration
-        AppConfig::from_env()
+    /// Verifies the deployment's configuration without starting any server.
+    ///
+    /// The configuration is loaded from the same sources as on startup, and
+    /// every invalid setting is reported. The configured storage and metrics
+    /// backends are then opened to prove they are reachable.
+    ///
+    /// # Parameters
+    ///
+    /// * `overrides` - Settings given as command line flags
+    ///
+    /// # Returns
+    ///
+    /// A `CheckReport` listing the outcome of every check
+    pub fn check(overrides: &ConfigOverrides) -> CheckReport {
+        let mut report = CheckReport::default();
+
+        let config = match Self::load_config(overrides) {
+            Ok((config, Some(path))) => {
+                report.ok("configuration", format!("loaded from {}", path));
+                config
+            }
+            Ok((config, None)) => {
+                report.ok(
+                    "configuration",
+                    "no configuration file, using environment variables",
+                );
+                config
+            }
+            Err(e) => {
+                report.fail("configuration", e);
+                return report;
+            }
+        };
+
+        check::check_config(&config, &mut report);
+        report
+    }
+
+    /// Replays the persisted update log of a document, to investigate a document
+    /// that fails to load.
+    ///
+    /// The configuration is loaded as on startup to select the storage backend,
+    /// whose log is read without being compacted.
+    ///
+    /// # Parameters
+    ///
+    /// * `doc_id` - Identifier of the document to replay
+    /// * `overrides` - Settings given as command line flags
+    ///
+    /// # Returns
+    ///
+    /// A `ReplayReport` listing the outcome of every replayed entry
+    pub fn replay(doc_id: &str, overrides: &ConfigOverrides) -> ReplayReport {
+        match Self::load_config(overrides) {
+            Ok((config, _)) => replay::replay_document(&config, doc_id),
+            Err(e) => ReplayReport::failed(doc_id, e),
+        }
     }
 
     /// Generates a default configuration file at the specified path.
@@ -112,10 +271,21 @@ impl ApplicationBootstrap {
 
     /// Runs the application by starting the configured servers.
     ///
-    /// Based on the configuration, this method will start:
+    /// Based on the configuration, this method will start, in parallel:
     /// - HTTP server (if enabled)
     /// - gRPC server (if enabled)
-    /// - Both servers in parallel (if both enabled)
+    /// - Admin server on its dedicated address (if enabled)
+    ///
+    /// With a push-based metrics backend, the metrics publisher is started too.
+    ///
+    /// As a warm standby, the replication from the primary is started too.
+    ///
+    /// With webhook targets, the webhook dispatcher is started too.
+    ///
+    /// With a configuration loaded from a file, the file is reloaded on `SIGHUP`,
+    /// and once modified if it is watched.
+    ///
+    /// In simulation mode, the virtual collaborators are started as well.
     ///
     /// # Returns
     ///
@@ -125,51 +295,288 @@ impl ApplicationBootstrap {
     /// # Errors
     ///
     /// Returns an error if:
-    /// - No servers are enabled in the configuration
+    /// - Neither the HTTP nor the gRPC server is enabled in the configuration
+    /// - The admin address is invalid or overlaps a public listener
     /// - Any server fails to initialize or run
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         info!("Starting Yjs Collaboration Server");
         info!("Configuration: {:?}", self.config);
 
-        // Start servers based on configuration
-        match (self.config.enable_http, self.config.enable_grpc) {
-            (true, true) => {
-                // Start both HTTP and gRPC servers
-                let http_server = HttpServer::new(
-                    self.config.http_socket_addr(),
-                    self.container.get_document_service(),
-                );
-                let rpc_server = RpcServer::new(
-                    self.config.grpc_socket_addr(),
-                    self.container.get_document_service(),
-                );
+        if !self.config.enable_http && !self.config.enable_grpc {
+            return Err("No servers enabled in configuration".into());
+        }
 
-                info!("Starting both HTTP and gRPC servers");
-                try_join!(http_server.start(), rpc_server.start())?;
+        // Replayed before clients connect, so they sync against the recovered state
+        if self.config.storage.wal.is_enabled() {
+            let recovered = self
+                .container
+                .get_document_service()
+                .recover_from_write_ahead_log()
+                .await
+                .map_err(|e| format!("Failed to recover from the write-ahead log: {}", e))?;
+            if recovered > 0 {
+                info!("Recovered {} updates from the write-ahead log", recovered);
             }
-            (true, false) => {
-                // Start only HTTP server
-                info!("Starting HTTP server only");
-                let http_server = HttpServer::new(
-                    self.config.http_socket_addr(),
-                    self.container.get_document_service(),
-                );
-                http_server.start().await?;
+        }
+
+        let reloader = self.config_path.as_ref().map(|path| {
+            let mut reloader = ConfigReloader::new(
+                path.clone(),
+                self.overrides.clone(),
+                self.config.clone(),
+                &self.container,
+            );
+            if let Some(log_level) = self.log_level.clone() {
+                reloader = reloader.with_log_level(log_level);
             }
-            (false, true) => {
-                // Start only gRPC server
-                info!("Starting gRPC server only");
-                let rpc_server = RpcServer::new(
-                    self.config.grpc_socket_addr(),
-                    self.container.get_document_service(),
-                );
-                rpc_server.start().await?;
+            Arc::new(reloader)
+        });
+
+        let mut servers: Vec<ServerFuture> = Vec::new();
+
+        if self.config.enable_http {
+            info!("Starting HTTP server");
+            let http_server = HttpServer::new(
+                self.config.http_listeners(),
+                self.config.security.origin_policy(),
+                self.container.get_document_service(),
+                self.container.get_admission_controller(),
+                self.container.get_session_registry(),
+            );
+            servers.push(Box::pin(http_server.start()));
+        }
+
+        if self.config.enable_grpc {
+            info!("Starting gRPC server");
+            let rpc_server = RpcServer::new(
+                self.config.grpc_socket_addrs(),
+                self.container.get_document_service(),
+                self.container.get_admission_controller(),
+                self.container.get_session_registry(),
+                self.container.get_session_outbox(),
+                self.config.replication.token.clone(),
+                self.config
+                    .transport_compression
+                    .settings(self.config.limits.max_update_bytes),
+            )
+            .with_transactions(self.container.get_document_use_cases());
+            servers.push(Box::pin(rpc_server.start()));
+        }
+
+        if self.config.admin.enabled {
+            let admin_server = AdminServer::new(
+                self.config.admin_address()?,
+                self.config.admin.auth(),
+                self.container.get_document_service(),
+                self.container.get_admission_controller(),
+                self.container.get_session_registry(),
+                self.container.get_metrics_service(),
+                self.container
+                    .get_standby()
+                    .map(|standby| -> Arc<dyn StandbyControl> { standby }),
+            )
+            .with_maintenance(self.container.get_document_use_cases());
+            let admin_server = match &reloader {
+                Some(reloader) => {
+                    admin_server.with_config_control(Arc::clone(reloader) as Arc<dyn ConfigControl>)
+                }
+                None => admin_server,
+            };
+            servers.push(Box::pin(admin_server.start()));
+        }
+
+        if self.config.metrics.backend == MetricsBackend::Statsd {
+            info!(
+                "Pushing metrics to statsd at {}",
+                self.config.metrics.statsd_addr
+            );
+            self.container
+                .get_metrics_service()
+                .spawn_publisher(self.config.metrics.flush_interval());
+        }
+
+        if let Some(idle_timeout) = self.config.sessions.idle_timeout() {
+            self.container
+                .get_session_registry()
+                .spawn_reaper(self.config.sessions.reap_interval(), idle_timeout);
+        }
+
+        if self.config.storage.backend == StorageBackend::Memory
+            && self.config.storage.eviction.policy().is_enabled()
+        {
+            info!("Evicting idle documents from memory");
+            let document_service = self.container.get_document_service();
+            let mut scans = tokio::time::interval(self.config.storage.eviction.scan_interval());
+            tokio::spawn(async move {
+                loop {
+                    scans.tick().await;
+                    let evicted = document_service.evict_documents().await;
+                    if evicted > 0 {
+                        info!("Evicted {} documents from memory", evicted);
+                    }
+                }
+            });
+        }
+
+        if self.config.storage.backend == StorageBackend::S3 {
+            info!(
+                "Flushing buffered updates to bucket '{}' every {} seconds",
+                self.config.storage.s3.bucket,
+                self.config.storage.s3.flush_interval().as_secs()
+            );
+            let document_service = self.container.get_document_service();
+            let mut flushes = tokio::time::interval(self.config.storage.s3.flush_interval());
+            tokio::spawn(async move {
+                loop {
+                    flushes.tick().await;
+                    let flushed = document_service.flush_documents();
+                    if flushed > 0 {
+                        debug!("Flushed the updates of {} documents", flushed);
+                    }
+                }
+            });
+        }
+
+        if self.config.storage.wal.is_enabled() {
+            info!(
+                "Logging applied updates to {}, checkpointed every {} seconds",
+                self.config.storage.wal.path,
+                self.config.storage.wal.checkpoint_interval().as_secs()
+            );
+            let document_service = self.container.get_document_service();
+            let mut checkpoints =
+                tokio::time::interval(self.config.storage.wal.checkpoint_interval());
+            tokio::spawn(async move {
+                loop {
+                    checkpoints.tick().await;
+                    if let Err(e) = document_service.checkpoint_write_ahead_log().await {
+                        warn!("Failed to checkpoint the write-ahead log: {}", e);
+                    }
+                }
+            });
+        }
+
+        if self.config.storage.archive.is_enabled() {
+            info!(
+                "Archiving documents untouched for {} days to {}",
+                self.config.storage.archive.archive_after_days, self.config.storage.archive.path
+            );
+            let document_service = self.container.get_document_service();
+            let mut scans = tokio::time::interval(self.config.storage.archive.scan_interval());
+            tokio::spawn(async move {
+                loop {
+                    scans.tick().await;
+                    let archived = document_service.archive_idle_documents().await;
+                    if archived > 0 {
+                        info!("Archived {} idle documents", archived);
+                    }
+                }
+            });
+        }
+
+        if let Some(interval) = self.config.versions.interval() {
+            info!(
+                "Recording versions of updated documents every {} seconds",
+                interval.as_secs()
+            );
+            let document_service = self.container.get_document_service();
+            let mut snapshots = tokio::time::interval(interval);
+            tokio::spawn(async move {
+                loop {
+                    snapshots.tick().await;
+                    let recorded = document_service.record_periodic_versions().await;
+                    if recorded > 0 {
+                        info!("Recorded versions of {} documents", recorded);
+                    }
+                }
+            });
+        }
+
+        if self.config.search.backend != SearchBackend::None {
+            info!(
+                "Indexing changed documents for search every {} seconds",
+                self.config.search.debounce().as_secs()
+            );
+            let document_service = self.container.get_document_service();
+            let rebuild = self.config.search.backend == SearchBackend::Memory;
+            let mut passes = tokio::time::interval(self.config.search.debounce());
+            tokio::spawn(async move {
+                // An index kept in memory starts empty
+                if rebuild {
+                    document_service.reindex_all_documents().await;
+                }
+                loop {
+                    passes.tick().await;
+                    let indexed = document_service.index_documents().await;
+                    if indexed > 0 {
+                        debug!("Indexed {} documents for search", indexed);
+                    }
+                }
+            });
+        }
+
+        if let Some(standby) = self.container.get_standby() {
+            tokio::spawn(standby.run(self.container.get_document_service()));
+        }
+
+        if let Some(webhooks) = self.container.get_webhooks() {
+            info!("Delivering document events to webhook targets");
+            tokio::spawn(webhooks.run(
+                self.container.get_document_service(),
+                self.container.get_session_registry(),
+            ));
+        }
+
+        if self.config.policies.has_undo_enabled() {
+            info!("Keeping server-side undo stacks of connected clients");
+            let document_service = self.container.get_document_service();
+            let mut presence = self.container.get_session_registry().subscribe();
+            tokio::spawn(async move {
+                loop {
+                    match presence.recv().await {
+                        // A client's undo stack is dropped once it leaves the document
+                        Ok(PresenceEvent::Left(session)) => {
+                            document_service
+                                .release_undo(&session.document_id, &session.client_id)
+                                .await;
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Undo stacks missed {} presence events", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+        }
+
+        if let Some(reloader) = &reloader {
+            #[cfg(unix)]
+            {
+                if let Err(e) = reloader.spawn_on_hangup() {
+                    warn!("Failed to reload the configuration on SIGHUP: {}", e);
+                }
             }
-            (false, false) => {
-                return Err("No servers enabled in configuration".into());
+            if let Some(interval) = self.config.reload.watch_interval() {
+                info!(
+                    "Reloading the configuration once {} is modified",
+                    reloader.path()
+                );
+                reloader.spawn_watcher(interval);
             }
         }
 
+        if let Some(simulation) = self.simulation {
+            Simulation::new(
+                simulation,
+                self.container.get_document_service(),
+                self.container.get_session_registry(),
+            )
+            .spawn();
+        }
+
+        try_join_all(servers).await?;
+
         Ok(())
     }
 }
@@ -0,0 +1,434 @@
+use std::{fmt, net::SocketAddr, sync::Arc, time::Duration};
+
+use yjs_collaboration_server_adapter::http::router::RouteGroup;
+use yjs_collaboration_server_domain::services::compute_pool::ComputePool;
+use yjs_collaboration_server_infrastructure::adapters::{
+    nats_update_broker::NatsUpdateBroker, redis_update_broker::RedisUpdateBroker,
+};
+
+use crate::{
+    config::{AppConfig, BrokerBackend, ClusterBackend, StorageBackend, UpdateExportBackend},
+    container::Container,
+};
+
+/// Outcome of a single self-check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// The check passed
+    Ok,
+    /// The deployment works but the setting is likely a mistake
+    Warning,
+    /// The server would fail to start or serve with this setting
+    Failed,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Ok => "ok",
+            Self::Warning => "warn",
+            Self::Failed => "FAIL",
+        })
+    }
+}
+
+/// Result of a single self-check.
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// Name of the checked component, e.g. "storage"
+    pub name: &'static str,
+    /// Outcome of the check
+    pub status: CheckStatus,
+    /// Human readable explanation
+    pub detail: String,
+}
+
+/// Report of a deployment self-check.
+///
+/// Produced by the `check` command, which loads the configuration the server
+/// would start with and verifies it against the environment without serving
+/// any traffic, so CI/CD pipelines can validate a deployment before rollout.
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    results: Vec<CheckResult>,
+}
+
+impl CheckReport {
+    /// Returns the result of every check, in the order they ran.
+    pub fn results(&self) -> &[CheckResult] {
+        &self.results
+    }
+
+    /// Returns whether no check failed; warnings do not fail the report.
+    pub fn passed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| result.status != CheckStatus::Failed)
+    }
+
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        self.results.push(CheckResult {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    pub(crate) fn ok(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Ok, detail);
+    }
+
+    fn warn(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Warning, detail);
+    }
+
+    pub(crate) fn fail(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Failed, detail);
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            writeln!(
+                f,
+                "[{:>4}] {}: {}",
+                result.status, result.name, result.detail
+            )?;
+        }
+
+        let failed = self
+            .results
+            .iter()
+            .filter(|result| result.status == CheckStatus::Failed)
+            .count();
+        if failed == 0 {
+            write!(f, "All checks passed")
+        } else {
+            write!(f, "{} check(s) failed", failed)
+        }
+    }
+}
+
+/// Verifies the settings of a configuration that need no backend to be checked.
+///
+/// Server startup refuses a configuration failing any of these checks.
+///
+/// # Parameters
+///
+/// * `config` - The configuration the server would start with
+/// * `report` - Report receiving the results
+pub(crate) fn check_settings(config: &AppConfig, report: &mut CheckReport) {
+    check_listeners(config, report);
+    check_admin(config, report);
+    check_origins(config, report);
+    check_replication(config, report);
+    check_cluster(config, report);
+
+    if config.broker.backend == BrokerBackend::Nats {
+        if let Err(e) = config.broker.nats_settings(&config.cluster.node_id) {
+            report.fail("broker", e);
+        }
+    }
+
+    let sessions = &config.sessions;
+    if sessions.ws_ping_interval_secs > 0
+        && sessions.ws_idle_timeout_secs > 0
+        && sessions.ws_idle_timeout_secs <= sessions.ws_ping_interval_secs
+    {
+        report.fail(
+            "websocket keep-alive",
+            format!(
+                "the idle timeout ({}s) must exceed the ping interval ({}s)",
+                sessions.ws_idle_timeout_secs, sessions.ws_ping_interval_secs
+            ),
+        );
+    }
+
+    if config.update_export.backend == UpdateExportBackend::Kafka {
+        let export = &config.update_export;
+        if export.kafka_brokers.is_empty() {
+            report.fail("update export", "no Kafka broker configured");
+        } else if export.kafka_topic.is_empty() {
+            report.fail("update export", "no Kafka topic configured");
+        } else {
+            report.ok(
+                "update export",
+                format!(
+                    "producing to topic '{}' through {}",
+                    export.kafka_topic,
+                    export.kafka_brokers.join(", ")
+                ),
+            );
+        }
+    }
+
+    if config.moderation.is_enabled() {
+        match config.moderation.processors() {
+            Ok(processors) => report.ok(
+                "moderation",
+                format!("{} processor(s) on the inserted text", processors.len()),
+            ),
+            Err(e) => report.fail("moderation", e),
+        }
+    }
+
+    if matches!(
+        config.log_level.as_str(),
+        "trace" | "debug" | "info" | "warn" | "error"
+    ) {
+        report.ok("logging", format!("level {}", config.log_level));
+    } else {
+        report.fail(
+            "logging",
+            format!(
+                "unknown level '{}', expected trace, debug, info, warn or error",
+                config.log_level
+            ),
+        );
+    }
+}
+
+/// Maximum time to wait for the cross-instance broker.
+const BROKER_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Verifies a loaded configuration against the environment.
+///
+/// Every invalid setting is reported, as on startup. Storage and metrics
+/// backends are then opened (and closed again), and the broker and the
+/// cluster's Redis server are pinged, to prove they are reachable.
+///
+/// # Parameters
+///
+/// * `config` - The configuration the server would start with
+/// * `report` - Report receiving the results
+pub(crate) fn check_config(config: &AppConfig, report: &mut CheckReport) {
+    check_settings(config, report);
+
+    match Container::open_repository(config, Arc::new(ComputePool::default())) {
+        Ok(_) => report.ok(
+            "storage",
+            match config.storage.backend {
+                StorageBackend::Memory => {
+                    "in-memory storage, documents are lost on restart".to_string()
+                }
+                StorageBackend::Sled => format!("opened sled database at {}", config.storage.path),
+                StorageBackend::Postgres => "connected to PostgreSQL".to_string(),
+                StorageBackend::S3 => format!("reached bucket '{}'", config.storage.s3.bucket),
+            },
+        ),
+        Err(e) => report.fail("storage", e),
+    }
+
+    match Container::open_metrics_sink(config) {
+        Ok(_) => report.ok("metrics", format!("{:?} backend", config.metrics.backend)),
+        Err(e) => report.fail("metrics", e),
+    }
+
+    match config.broker.backend {
+        BrokerBackend::None => report.ok("broker", "single instance, no broker"),
+        BrokerBackend::Redis => match RedisUpdateBroker::check_connection(
+            &config.broker.redis_url,
+            BROKER_CHECK_TIMEOUT,
+        ) {
+            Ok(()) => report.ok("broker", "connected to Redis"),
+            Err(e) => report.fail("broker", e),
+        },
+        BrokerBackend::Nats => {
+            match NatsUpdateBroker::check_connection(&config.broker.nats.url, BROKER_CHECK_TIMEOUT)
+            {
+                Ok(()) => report.ok("broker", "connected to NATS JetStream"),
+                Err(e) => report.fail("broker", e),
+            }
+        }
+    }
+
+    if config.cluster.backend == ClusterBackend::Redis {
+        match RedisUpdateBroker::check_connection(&config.cluster.redis_url, BROKER_CHECK_TIMEOUT) {
+            Ok(()) => report.ok("cluster membership", "connected to Redis"),
+            Err(e) => report.fail("cluster membership", e),
+        }
+    }
+}
+
+fn check_listeners(config: &AppConfig, report: &mut CheckReport) {
+    if !config.enable_http && !config.enable_grpc {
+        report.fail("servers", "neither the HTTP nor the gRPC server is enabled");
+        return;
+    }
+
+    if config.enable_http {
+        let mut addrs = vec![config.http_addr.as_str()];
+        addrs.extend(config.http_listeners.iter().map(|l| l.addr.as_str()));
+
+        let errors = invalid_addresses(&addrs)
+            .into_iter()
+            .chain(config.http_listeners.iter().flat_map(|listener| {
+                listener.routes.iter().filter_map(move |route| {
+                    route
+                        .parse::<RouteGroup>()
+                        .err()
+                        .map(|e| format!("{} on listener {}", e, listener.addr))
+                })
+            }))
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() {
+            report.ok("http", format!("listening on {}", addrs.join(", ")));
+        } else {
+            report.fail("http", errors.join("; "));
+        }
+    }
+
+    if config.enable_grpc {
+        let mut addrs = vec![config.grpc_addr.as_str()];
+        addrs.extend(config.grpc_listeners.iter().map(String::as_str));
+
+        let errors = invalid_addresses(&addrs);
+        if errors.is_empty() {
+            report.ok("grpc", format!("listening on {}", addrs.join(", ")));
+        } else {
+            report.fail("grpc", errors.join("; "));
+        }
+    }
+}
+
+/// Returns an error message for every unparsable or duplicate address.
+fn invalid_addresses(addrs: &[&str]) -> Vec<String> {
+    let mut seen = Vec::new();
+    let mut errors = Vec::new();
+
+    for addr in addrs {
+        match addr.parse::<SocketAddr>() {
+            Ok(parsed) if seen.contains(&parsed) => {
+                errors.push(format!("duplicate address {}", parsed))
+            }
+            Ok(parsed) => seen.push(parsed),
+            Err(e) => errors.push(format!("invalid address '{}': {}", addr, e)),
+        }
+    }
+
+    errors
+}
+
+fn check_admin(config: &AppConfig, report: &mut CheckReport) {
+    if !config.admin.enabled {
+        report.ok("admin", "disabled");
+        return;
+    }
+
+    match config.admin_address() {
+        Ok(addr) => report.ok("admin", format!("listening on {}", addr)),
+        Err(e) => report.fail("admin", e),
+    }
+
+    match config.admin.auth_token.as_deref() {
+        Some(token) if token.trim().is_empty() => {
+            report.fail("admin auth", "auth token is set but empty")
+        }
+        Some(_) => report.ok("admin auth", "bearer token configured"),
+        None => report.warn(
+            "admin auth",
+            "no auth token; access is restricted only at the network level",
+        ),
+    }
+}
+
+fn check_origins(config: &AppConfig, report: &mut CheckReport) {
+    if !config.enable_http {
+        return;
+    }
+
+    let origins = &config.security.allowed_origins;
+    if origins.is_empty() {
+        report.warn(
+            "origins",
+            "no allowed origins; pages of any origin may open WebSocket connections",
+        );
+        return;
+    }
+
+    let invalid: Vec<&str> = origins
+        .iter()
+        .map(|origin| origin.trim().trim_end_matches('/'))
+        .filter(|origin| *origin != "*" && !is_origin(origin))
+        .collect();
+    if invalid.is_empty() {
+        report.ok("origins", format!("{} allowed origin(s)", origins.len()));
+    } else {
+        report.warn(
+            "origins",
+            format!("never matching origin(s): {}", invalid.join(", ")),
+        );
+    }
+}
+
+/// Returns whether a value has the form of an origin: a scheme and a host, without a path.
+fn is_origin(value: &str) -> bool {
+    match value.split_once("://") {
+        Some((scheme, host)) => !scheme.is_empty() && !host.is_empty() && !host.contains('/'),
+        None => false,
+    }
+}
+
+fn check_replication(config: &AppConfig, report: &mut CheckReport) {
+    match config.replication.standby() {
+        Ok(Some(standby)) => {
+            if !config.admin.enabled && standby.failover_timeout.is_none() {
+                report.warn(
+                    "replication",
+                    format!(
+                        "standby of {} without failover timeout or admin server, it can never be \
+                         promoted",
+                        standby.primary_addr
+                    ),
+                );
+            } else {
+                report.ok(
+                    "replication",
+                    format!("standby of {}", standby.primary_addr),
+                );
+            }
+        }
+        Ok(None) if config.replication.token.is_some() => {
+            report.ok("replication", "primary accepting standbys")
+        }
+        Ok(None) => report.ok("replication", "disabled"),
+        Err(e) => report.fail("replication", e),
+    }
+}
+
+fn check_cluster(config: &AppConfig, report: &mut CheckReport) {
+    match config.cluster.local_member() {
+        Ok(Some(member)) => {
+            if config.cluster.backend == ClusterBackend::Static && config.cluster.members.is_empty()
+            {
+                report.warn(
+                    "cluster",
+                    format!(
+                        "node '{}' is the only static member, it owns every document",
+                        member.node_id
+                    ),
+                );
+            } else if config.broker.backend == BrokerBackend::None && !config.cluster.forwarding {
+                report.warn(
+                    "cluster",
+                    format!(
+                        "node '{}' without a broker, documents read through the REST API of a \
+                         node not owning them may be stale",
+                        member.node_id
+                    ),
+                );
+            } else {
+                let mode = if config.cluster.forwarding {
+                    "forwarding"
+                } else {
+                    "redirecting"
+                };
+                report.ok("cluster", format!("node '{}', {}", member.node_id, mode));
+            }
+        }
+        Ok(None) => report.ok("cluster", "disabled"),
+        Err(e) => report.fail("cluster", e),
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+use tracing::{info, warn};
+use yjs_collaboration_server_adapter::{
+    admission::AdmissionController,
+    clock::server_time,
+    http::admin::{ConfigChange, ConfigControl, ReloadReport},
+    session_registry::SessionRegistry,
+};
+
+use crate::{
+    config::{AppConfig, ConfigOverrides, LogLevelHandle, PolicyConfig},
+    container::Container,
+    webhooks::WebhookDispatcher,
+};
+
+/// Webhook targets of the default policy and of the namespaces overriding them.
+#[derive(Serialize)]
+struct WebhookTargets<'a> {
+    default: &'a [String],
+    namespaces: BTreeMap<&'a str, &'a [String]>,
+}
+
+impl<'a> WebhookTargets<'a> {
+    /// Collects the webhook targets configured in a policy table.
+    fn of(policies: &'a PolicyConfig) -> Self {
+        Self {
+            default: &policies.default.webhook_targets,
+            namespaces: policies
+                .namespaces
+                .iter()
+                .filter_map(|(namespace, overrides)| {
+                    Some((namespace.as_str(), overrides.webhook_targets.as_deref()?))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Reloads the configuration file the server was started from.
+///
+/// The log level, the update rate limit, the admission thresholds and the
+/// webhook targets are applied to the running services; every other setting,
+/// such as the listen addresses or the storage backend, is only read on
+/// startup, so its changes are reported as requiring a restart and are not
+/// applied. Every reload logs the applied changes and is kept for the admin
+/// `/admin/config` route.
+pub struct ConfigReloader {
+    path: String,
+    /// Command line flags applied over the reloaded file, as on startup
+    overrides: ConfigOverrides,
+    /// Configuration in effect: the startup configuration with the reloaded settings applied
+    config: Mutex<AppConfig>,
+    log_level: Option<LogLevelHandle>,
+    admission: Arc<AdmissionController>,
+    sessions: Arc<SessionRegistry>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    last_reload: Mutex<Option<ReloadReport>>,
+}
+
+impl ConfigReloader {
+    /// Creates a reloader of a configuration file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - Path of the YAML configuration file
+    /// * `overrides` - Settings given as command line flags
+    /// * `config` - Configuration the server was started with
+    /// * `container` - Container of the services the reloaded settings are applied to
+    ///
+    /// # Returns
+    ///
+    /// A new `ConfigReloader` leaving the log level unchanged on reloads
+    pub fn new(
+        path: String,
+        overrides: ConfigOverrides,
+        config: AppConfig,
+        container: &Container,
+    ) -> Self {
+        Self {
+            path,
+            overrides,
+            config: Mutex::new(config),
+            log_level: None,
+            admission: container.get_admission_controller(),
+            sessions: container.get_session_registry(),
+            webhooks: container.get_webhooks(),
+            last_reload: Mutex::new(None),
+        }
+    }
+
+    /// Changes the log level of the process on reloads.
+    ///
+    /// # Parameters
+    ///
+    /// * `log_level` - Handle returned when the logging system was initialized
+    ///
+    /// # Returns
+    ///
+    /// The `ConfigReloader` applying log level changes
+    pub fn with_log_level(mut self, log_level: LogLevelHandle) -> Self {
+        self.log_level = Some(log_level);
+        self
+    }
+
+    /// Returns the path of the reloaded configuration file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Reloads the configuration whenever the process receives `SIGHUP`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The signal handler is installed
+    /// * `Err(std::io::Error)` - If the signal handler cannot be installed
+    #[cfg(unix)]
+    pub fn spawn_on_hangup(self: &Arc<Self>) -> std::io::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = signal(SignalKind::hangup())?;
+        let reloader = Arc::clone(self);
+        tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                reloader.reload_logged("signal");
+            }
+        });
+        Ok(())
+    }
+
+    /// Reloads the configuration whenever the modification time of its file changes.
+    ///
+    /// # Parameters
+    ///
+    /// * `interval` - Interval between two checks of the file
+    pub fn spawn_watcher(self: &Arc<Self>, interval: Duration) {
+        let reloader = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut modified = reloader.modified();
+            let mut checks = tokio::time::interval(interval);
+            loop {
+                checks.tick().await;
+                let latest = reloader.modified();
+                if latest != modified {
+                    modified = latest;
+                    reloader.reload_logged("watch");
+                }
+            }
+        });
+    }
+
+    /// Returns the modification time of the configuration file, if it can be read.
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Reloads the configuration, logging a failure instead of reporting it.
+    fn reload_logged(&self, trigger: &'static str) {
+        if let Err(e) = self.reload(trigger) {
+            warn!(
+                "Failed to reload the configuration from {}: {}",
+                self.path, e
+            );
+        }
+    }
+}
+
+impl ConfigControl for ConfigReloader {
+    fn reload(&self, trigger: &'static str) -> Result<ReloadReport, String> {
+        // Environment variables and flags still override the file, as on startup
+        let next = AppConfig::load(Some(&self.path), &self.overrides)?;
+        next.validate()?;
+        let mut config = self
+            .config
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut effective = config.clone();
+        let mut applied = Vec::new();
+
+        // Without a handle, logging was set up by the process embedding the server
+        if let Some(log_level) = &self.log_level {
+            if let Some(change) = change("log_level", &config.log_level, &next.log_level) {
+                log_level
+                    .reload(next.level_filter())
+                    .map_err(|e| format!("Failed to change the log level: {}", e))?;
+                effective.log_level = next.log_level.clone();
+                applied.push(change);
+            }
+        }
+
+        if let Some(change) = change("rate_limit", &config.rate_limit, &next.rate_limit) {
+            self.sessions
+                .update_limiter()
+                .set_limit(next.rate_limit.update_rate_limit());
+            effective.rate_limit = next.rate_limit.clone();
+            applied.push(change);
+        }
+
+        if let Some(change) = change("admission", &config.admission, &next.admission) {
+            self.admission.set_thresholds(next.admission.thresholds());
+            effective.admission = next.admission.clone();
+            applied.push(change);
+        }
+
+        // Webhook events are only listened to when some document had targets on startup
+        if let Some(webhooks) = &self.webhooks {
+            effective.policies.default.webhook_targets =
+                next.policies.default.webhook_targets.clone();
+            for (namespace, overrides) in effective.policies.namespaces.iter_mut() {
+                overrides.webhook_targets = next
+                    .policies
+                    .namespaces
+                    .get(namespace)
+                    .and_then(|next_overrides| next_overrides.webhook_targets.clone());
+            }
+            if let Some(change) = change(
+                "webhook_targets",
+                &WebhookTargets::of(&config.policies),
+                &WebhookTargets::of(&effective.policies),
+            ) {
+                webhooks.set_targets(effective.policies.policies());
+                applied.push(change);
+            }
+        }
+
+        let restart_required = restart_required(&effective, &next);
+
+        for change in &applied {
+            info!(
+                "Reloaded {} from {}: {} -> {}",
+                change.setting, self.path, change.from, change.to
+            );
+        }
+        if !restart_required.is_empty() {
+            warn!(
+                "Changes to {} in {} take effect after a restart and were not applied",
+                restart_required.join(", "),
+                self.path
+            );
+        }
+        *config = effective;
+
+        let report = ReloadReport {
+            reloaded_at: server_time(),
+            trigger,
+            applied,
+            restart_required,
+        };
+        *self
+            .last_reload
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(report.clone());
+        Ok(report)
+    }
+
+    fn last_reload(&self) -> Option<ReloadReport> {
+        self.last_reload
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+/// Compares a setting before and after a reload.
+///
+/// # Parameters
+///
+/// * `setting` - Name of the setting
+/// * `from` - Value in effect
+/// * `to` - Reloaded value
+///
+/// # Returns
+///
+/// The `ConfigChange` of the setting, or `None` if its value is unchanged
+fn change<T: Serialize>(setting: &str, from: &T, to: &T) -> Option<ConfigChange> {
+    let from = sonic_rs::to_value(from).ok()?;
+    let to = sonic_rs::to_value(to).ok()?;
+    (from != to).then(|| ConfigChange {
+        setting: setting.to_string(),
+        from,
+        to,
+    })
+}
+
+/// Lists the top-level sections of the configuration that differ from the configuration in
+/// effect once the reloadable settings were applied.
+fn restart_required(effective: &AppConfig, next: &AppConfig) -> Vec<String> {
+    let (Ok(serde_yaml::Value::Mapping(effective)), Ok(serde_yaml::Value::Mapping(next))) =
+        (serde_yaml::to_value(effective), serde_yaml::to_value(next))
+    else {
+        return Vec::new();
+    };
+
+    effective
+        .keys()
+        .chain(next.keys())
+        .filter(|key| effective.get(*key) != next.get(*key))
+        .filter_map(|key| key.as_str().map(str::to_string))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
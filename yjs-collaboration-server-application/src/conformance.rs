@@ -0,0 +1,888 @@
+use std::{
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::Engine;
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use sonic_rs::{from_str, json, JsonValueTrait, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use volo_grpc::RecvStream;
+use yjs_collaboration_server_common::volo_gen::collaboration::{
+    client_message, server_message, AwarenessUpdate, ClientMessage, CollaborationServiceClient,
+    CollaborationServiceClientBuilder, JoinDocument, ServerMessage, SyncRequest, UpdateMessage,
+};
+use yjs_collaboration_server_domain::{
+    services::document_service::SyncResponse,
+    value_objects::{
+        message::ServerMessage as JsonServerMessage, sync_protocol::SyncProtocolMessage,
+    },
+};
+use yrs::{
+    encoding::write::Write,
+    sync::protocol::MSG_AWARENESS,
+    updates::{
+        decoder::Decode,
+        encoder::{Encode, Encoder, EncoderV1},
+    },
+    Doc, GetString, ReadTxn, Text, TextRef, Transact, Update,
+};
+
+/// Root name of the shared text edited by the conformance clients.
+const TEXT_ROOT: &str = "content";
+
+/// Settings of a conformance run.
+#[derive(Clone, Debug)]
+pub struct ConformanceConfig {
+    /// WebSocket endpoint to test, e.g. `ws://127.0.0.1:8080/ws` (`None` skips WebSocket
+    /// scenarios)
+    pub ws_url: Option<String>,
+    /// gRPC address to test, e.g. `127.0.0.1:8081` (`None` skips gRPC scenarios)
+    pub grpc_addr: Option<String>,
+    /// Maximum duration of a single scenario
+    pub timeout: Duration,
+}
+
+impl Default for ConformanceConfig {
+    /// Targets a server running locally with the default listeners.
+    fn default() -> Self {
+        Self {
+            ws_url: Some("ws://127.0.0.1:8080/ws".to_string()),
+            grpc_addr: Some("127.0.0.1:8081".to_string()),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Result of a single conformance scenario.
+#[derive(Debug, Clone)]
+pub struct ScenarioResult {
+    /// Protocol the scenario ran over, e.g. "ws-binary"
+    pub protocol: &'static str,
+    /// Behavior verified by the scenario, e.g. "sync handshake"
+    pub scenario: &'static str,
+    /// `Err` explains why the server did not behave as expected
+    pub outcome: Result<(), String>,
+}
+
+/// Report of a conformance run.
+///
+/// Lists the outcome of every protocol scenario, so alternative client
+/// implementations can compare the server's behavior with their own.
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    results: Vec<ScenarioResult>,
+}
+
+impl ConformanceReport {
+    /// Returns the result of every scenario, in the order they ran.
+    pub fn results(&self) -> &[ScenarioResult] {
+        &self.results
+    }
+
+    /// Returns whether every scenario passed.
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.outcome.is_ok())
+    }
+
+    fn record(
+        &mut self,
+        protocol: &'static str,
+        scenario: &'static str,
+        outcome: Result<(), String>,
+    ) {
+        self.results.push(ScenarioResult {
+            protocol,
+            scenario,
+            outcome,
+        });
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            match &result.outcome {
+                Ok(()) => writeln!(f, "[pass] {}: {}", result.protocol, result.scenario)?,
+                Err(e) => writeln!(f, "[FAIL] {}: {}: {}", result.protocol, result.scenario, e)?,
+            }
+        }
+
+        let passed = self
+            .results
+            .iter()
+            .filter(|result| result.outcome.is_ok())
+            .count();
+        write!(f, "{}/{} scenarios passed", passed, self.results.len())
+    }
+}
+
+/// Protocol conformance suite run against a live server.
+///
+/// Each scenario plays one or more clients against the configured endpoints
+/// and verifies a behavior that client implementations rely on: the sync
+/// handshake, convergence of concurrent updates, awareness handling and
+/// catching up after a reconnection. Every scenario edits its own document,
+/// named after the run, so runs never interfere with each other or with real
+/// documents.
+pub struct ConformanceSuite {
+    config: ConformanceConfig,
+    run_id: String,
+}
+
+impl ConformanceSuite {
+    /// Creates a conformance suite.
+    ///
+    /// # Parameters
+    ///
+    /// * `config` - Endpoints to test and scenario timeout
+    ///
+    /// # Returns
+    ///
+    /// A new `ConformanceSuite` instance
+    pub fn new(config: ConformanceConfig) -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        Self {
+            config,
+            run_id: format!("{:x}", started),
+        }
+    }
+
+    /// Runs every scenario of the configured protocols.
+    ///
+    /// # Returns
+    ///
+    /// A `ConformanceReport` listing the outcome of every scenario
+    pub async fn run(&self) -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+
+        if let Some(url) = &self.config.ws_url {
+            let url = url.trim_end_matches('/');
+
+            report.record(
+                "ws-binary",
+                "sync handshake",
+                self.scenario(ws_binary_handshake(
+                    url,
+                    &self.doc_id("ws-binary-handshake"),
+                ))
+                .await,
+            );
+            report.record(
+                "ws-binary",
+                "concurrent updates",
+                self.scenario(ws_binary_concurrent(
+                    url,
+                    &self.doc_id("ws-binary-concurrent"),
+                ))
+                .await,
+            );
+            report.record(
+                "ws-binary",
+                "awareness",
+                self.scenario(ws_binary_awareness(
+                    url,
+                    &self.doc_id("ws-binary-awareness"),
+                ))
+                .await,
+            );
+            report.record(
+                "ws-binary",
+                "reconnection",
+                self.scenario(ws_binary_reconnection(
+                    url,
+                    &self.doc_id("ws-binary-reconnect"),
+                ))
+                .await,
+            );
+            report.record(
+                "ws-json",
+                "sync handshake",
+                self.scenario(ws_json_handshake(url, &self.doc_id("ws-json-handshake")))
+                    .await,
+            );
+            report.record(
+                "ws-json",
+                "concurrent updates",
+                self.scenario(ws_json_concurrent(url, &self.doc_id("ws-json-concurrent")))
+                    .await,
+            );
+        }
+
+        if let Some(addr) = &self.config.grpc_addr {
+            let client = match addr.parse::<SocketAddr>() {
+                Ok(addr) => CollaborationServiceClientBuilder::new("yjs-collaboration-server")
+                    .address(addr)
+                    .build(),
+                Err(e) => {
+                    report.record("grpc", "connection", Err(format!("{}: {}", addr, e)));
+                    return report;
+                }
+            };
+
+            report.record(
+                "grpc",
+                "sync handshake",
+                self.scenario(grpc_handshake(&client, &self.doc_id("grpc-handshake")))
+                    .await,
+            );
+            report.record(
+                "grpc",
+                "concurrent updates",
+                self.scenario(grpc_concurrent(&client, &self.doc_id("grpc-concurrent")))
+                    .await,
+            );
+            report.record(
+                "grpc",
+                "awareness",
+                self.scenario(grpc_awareness(&client, &self.doc_id("grpc-awareness")))
+                    .await,
+            );
+            report.record(
+                "grpc",
+                "reconnection",
+                self.scenario(grpc_reconnection(&client, &self.doc_id("grpc-reconnect")))
+                    .await,
+            );
+        }
+
+        report
+    }
+
+    /// Names the document of a scenario; document IDs must fit in a URL path segment.
+    fn doc_id(&self, scenario: &str) -> String {
+        format!("conformance-{}-{}", self.run_id, scenario)
+    }
+
+    /// Runs a scenario, failing it when it exceeds the configured timeout.
+    async fn scenario(
+        &self,
+        scenario: impl Future<Output = Result<(), String>>,
+    ) -> Result<(), String> {
+        tokio::time::timeout(self.config.timeout, scenario)
+            .await
+            .unwrap_or_else(|_| Err(format!("timed out after {:?}", self.config.timeout)))
+    }
+}
+
+/// A client's replica of the document, holding a single shared text.
+struct Replica {
+    doc: Doc,
+    text: TextRef,
+}
+
+impl Replica {
+    fn new() -> Self {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text(TEXT_ROOT);
+        Self { doc, text }
+    }
+
+    fn state_vector(&self) -> Vec<u8> {
+        self.doc.transact().state_vector().encode_v1()
+    }
+
+    /// Appends text and returns the resulting update.
+    fn append(&self, chunk: &str) -> Vec<u8> {
+        let before = self.doc.transact().state_vector();
+        {
+            let mut txn = self.doc.transact_mut();
+            let len = self.text.len(&txn);
+            self.text.insert(&mut txn, len, chunk);
+        }
+        self.doc.transact().encode_state_as_update_v1(&before)
+    }
+
+    fn apply(&self, update: &[u8]) -> Result<(), String> {
+        let update = Update::decode_v1(update).map_err(|e| format!("corrupt update: {}", e))?;
+        self.doc
+            .transact_mut()
+            .apply_update(update)
+            .map_err(|e| format!("update could not be applied: {}", e))
+    }
+
+    fn content(&self) -> String {
+        self.text.get_string(&self.doc.transact())
+    }
+
+    /// Fails unless the replica contains every expected chunk.
+    fn expect_chunks(&self, name: &str, chunks: &[&str]) -> Result<(), String> {
+        let content = self.content();
+        match chunks.iter().find(|chunk| !content.contains(*chunk)) {
+            Some(missing) => Err(format!(
+                "{} is missing {:?}, has {:?}",
+                name, missing, content
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fails unless both replicas hold the same content.
+fn expect_converged(a: &Replica, b: &Replica) -> Result<(), String> {
+    let (a, b) = (a.content(), b.content());
+    if a == b {
+        Ok(())
+    } else {
+        Err(format!("replicas diverged: {:?} != {:?}", a, b))
+    }
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A client of the native `y-websocket` binary protocol.
+struct BinaryClient {
+    socket: Socket,
+}
+
+impl BinaryClient {
+    async fn connect(url: &str, doc_id: &str) -> Result<Self, String> {
+        let url = format!("{}/{}?format=binary", url, doc_id);
+        let (socket, _) = connect_async(url.as_str())
+            .await
+            .map_err(|e| format!("failed to connect to {}: {}", url, e))?;
+        Ok(Self { socket })
+    }
+
+    /// Connects and completes the handshake: answers the server's `SyncStep1`
+    /// and applies the `SyncStep2` answering the replica's own.
+    async fn join(url: &str, doc_id: &str, replica: &Replica) -> Result<Self, String> {
+        let mut client = Self::connect(url, doc_id).await?;
+
+        match client.recv().await? {
+            SyncProtocolMessage::SyncStep1(_) => {}
+            other => {
+                return Err(format!(
+                    "expected SyncStep1 from the server, got {:?}",
+                    other
+                ))
+            }
+        }
+        client
+            .send(&SyncProtocolMessage::SyncStep1(replica.state_vector()))
+            .await?;
+        let update = client.recv_step2().await?;
+        replica.apply(&update)?;
+
+        Ok(client)
+    }
+
+    async fn send(&mut self, message: &SyncProtocolMessage) -> Result<(), String> {
+        self.send_frame(message.encode()).await
+    }
+
+    async fn send_frame(&mut self, frame: Vec<u8>) -> Result<(), String> {
+        self.socket
+            .send(Message::Binary(frame))
+            .await
+            .map_err(|e| format!("failed to send frame: {}", e))
+    }
+
+    /// Receives the next sync protocol message, skipping other frames.
+    async fn recv(&mut self) -> Result<SyncProtocolMessage, String> {
+        loop {
+            match self.socket.next().await {
+                Some(Ok(Message::Binary(frame))) => {
+                    if let Some(message) =
+                        SyncProtocolMessage::decode(&frame).map_err(|e| e.to_string())?
+                    {
+                        return Ok(message);
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err("connection closed by the server".to_string())
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(format!("connection failed: {}", e)),
+            }
+        }
+    }
+
+    /// Receives the next `SyncStep2`, skipping updates relayed meanwhile.
+    async fn recv_step2(&mut self) -> Result<Vec<u8>, String> {
+        loop {
+            if let SyncProtocolMessage::SyncStep2(update) = self.recv().await? {
+                return Ok(update);
+            }
+        }
+    }
+
+    /// Receives the next relayed `Update`.
+    async fn recv_update(&mut self) -> Result<Vec<u8>, String> {
+        loop {
+            if let SyncProtocolMessage::Update(update) = self.recv().await? {
+                return Ok(update);
+            }
+        }
+    }
+
+    /// Waits until the server has processed every frame sent so far, by
+    /// issuing a sync request and awaiting its answer.
+    async fn flush(&mut self, replica: &Replica) -> Result<(), String> {
+        self.send(&SyncProtocolMessage::SyncStep1(replica.state_vector()))
+            .await?;
+        let update = self.recv_step2().await?;
+        replica.apply(&update)
+    }
+
+    async fn close(mut self) {
+        let _ = self.socket.close(None).await;
+    }
+}
+
+/// The server opens the handshake with its `SyncStep1` and answers the client's
+/// `SyncStep1` with a `SyncStep2`.
+async fn ws_binary_handshake(url: &str, doc_id: &str) -> Result<(), String> {
+    let replica = Replica::new();
+    let client = BinaryClient::join(url, doc_id, &replica).await?;
+    client.close().await;
+    Ok(())
+}
+
+/// Updates sent concurrently by two clients are relayed to each other, and
+/// both replicas converge.
+async fn ws_binary_concurrent(url: &str, doc_id: &str) -> Result<(), String> {
+    let (a, b) = (Replica::new(), Replica::new());
+    let mut client_a = BinaryClient::join(url, doc_id, &a).await?;
+    let mut client_b = BinaryClient::join(url, doc_id, &b).await?;
+
+    client_a
+        .send(&SyncProtocolMessage::Update(a.append("alpha ")))
+        .await?;
+    client_b
+        .send(&SyncProtocolMessage::Update(b.append("beta ")))
+        .await?;
+
+    a.apply(&client_a.recv_update().await?)?;
+    b.apply(&client_b.recv_update().await?)?;
+    a.expect_chunks("client A", &["alpha ", "beta "])?;
+    expect_converged(&a, &b)?;
+
+    client_a.close().await;
+    client_b.close().await;
+    Ok(())
+}
+
+/// Awareness frames do not break the connection: the sync protocol keeps
+/// working after one was sent.
+async fn ws_binary_awareness(url: &str, doc_id: &str) -> Result<(), String> {
+    let replica = Replica::new();
+    let mut client = BinaryClient::join(url, doc_id, &replica).await?;
+
+    client
+        .send_frame(awareness_frame(
+            replica.doc.client_id(),
+            r#"{"user":{"name":"conformance"}}"#,
+        ))
+        .await?;
+    client.flush(&replica).await?;
+
+    client.close().await;
+    Ok(())
+}
+
+/// A client reconnecting with its state vector receives exactly the changes
+/// made while it was away.
+async fn ws_binary_reconnection(url: &str, doc_id: &str) -> Result<(), String> {
+    let (a, b) = (Replica::new(), Replica::new());
+
+    let mut client_a = BinaryClient::join(url, doc_id, &a).await?;
+    client_a
+        .send(&SyncProtocolMessage::Update(a.append("before ")))
+        .await?;
+    client_a.flush(&a).await?;
+    client_a.close().await;
+
+    let mut client_b = BinaryClient::join(url, doc_id, &b).await?;
+    b.expect_chunks("client B", &["before "])?;
+    client_b
+        .send(&SyncProtocolMessage::Update(b.append("while-away ")))
+        .await?;
+    client_b.flush(&b).await?;
+
+    let client_a = BinaryClient::join(url, doc_id, &a).await?;
+    a.expect_chunks("reconnected client A", &["before ", "while-away "])?;
+    expect_converged(&a, &b)?;
+
+    client_a.close().await;
+    client_b.close().await;
+    Ok(())
+}
+
+/// Encodes a `y-protocols/awareness` frame announcing a single client state.
+fn awareness_frame(client_id: u64, state: &str) -> Vec<u8> {
+    let mut update = EncoderV1::new();
+    update.write_var(1u32);
+    update.write_var(client_id);
+    update.write_var(1u32);
+    update.write_string(state);
+
+    let mut frame = EncoderV1::new();
+    frame.write_var(MSG_AWARENESS);
+    frame.write_buf(update.to_vec());
+    frame.to_vec()
+}
+
+/// Message received on a JSON protocol connection.
+enum JsonFrame {
+    /// Answer to a `sync` or `sv` request
+    Sync(SyncResponse),
+    /// Typed server message (`update`, `notice`, `error`)
+    Message(JsonServerMessage),
+}
+
+/// A client of the JSON protocol.
+struct JsonClient {
+    socket: Socket,
+}
+
+impl JsonClient {
+    async fn connect(url: &str) -> Result<Self, String> {
+        let (socket, _) = connect_async(url)
+            .await
+            .map_err(|e| format!("failed to connect to {}: {}", url, e))?;
+        Ok(Self { socket })
+    }
+
+    /// Connects and synchronizes the replica with the document.
+    async fn join(url: &str, doc_id: &str, replica: &Replica) -> Result<Self, String> {
+        let mut client = Self::connect(url).await?;
+        client
+            .send(json!({
+                "type": "sync",
+                "doc_id": doc_id,
+                "update": base64::engine::general_purpose::STANDARD.encode(replica.state_vector()),
+            }))
+            .await?;
+
+        let response = client.recv_sync().await?;
+        if response.state_vector.is_none() {
+            return Err("sync response carries no state vector".to_string());
+        }
+        if let Some(update) = response.update {
+            replica.apply(&update)?;
+        }
+
+        Ok(client)
+    }
+
+    async fn send(&mut self, message: Value) -> Result<(), String> {
+        self.socket
+            .send(Message::Text(message.to_string()))
+            .await
+            .map_err(|e| format!("failed to send message: {}", e))
+    }
+
+    async fn recv(&mut self) -> Result<JsonFrame, String> {
+        loop {
+            let text = match self.socket.next().await {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err("connection closed by the server".to_string())
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(format!("connection failed: {}", e)),
+            };
+
+            let value: Value =
+                from_str(&text).map_err(|e| format!("invalid JSON {:?}: {}", text, e))?;
+            return if value.get("type").is_some() {
+                let message =
+                    from_str(&text).map_err(|e| format!("invalid message {:?}: {}", text, e))?;
+                Ok(JsonFrame::Message(message))
+            } else {
+                let response = from_str(&text)
+                    .map_err(|e| format!("invalid sync response {:?}: {}", text, e))?;
+                Ok(JsonFrame::Sync(response))
+            };
+        }
+    }
+
+    /// Receives the next sync response, skipping notices and updates.
+    async fn recv_sync(&mut self) -> Result<SyncResponse, String> {
+        loop {
+            match self.recv().await? {
+                JsonFrame::Sync(response) => return Ok(response),
+                JsonFrame::Message(message) => Self::check_error(&message)?,
+            }
+        }
+    }
+
+    /// Receives the next relayed update.
+    async fn recv_update(&mut self) -> Result<Vec<u8>, String> {
+        loop {
+            let JsonFrame::Message(message) = self.recv().await? else {
+                continue;
+            };
+            Self::check_error(&message)?;
+
+            if message.message_type == "update" {
+                let encoded = message.update.ok_or("update message carries no update")?;
+                return base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| format!("update is not valid Base64: {}", e));
+            }
+        }
+    }
+
+    fn check_error(message: &JsonServerMessage) -> Result<(), String> {
+        if message.message_type == "error" {
+            let detail = message
+                .data
+                .as_ref()
+                .map(|data| data.to_string())
+                .unwrap_or_default();
+            return Err(format!("server error: {}", detail));
+        }
+        Ok(())
+    }
+
+    async fn close(mut self) {
+        let _ = self.socket.close(None).await;
+    }
+}
+
+/// A `sync` request is answered with the document's state vector and update.
+async fn ws_json_handshake(url: &str, doc_id: &str) -> Result<(), String> {
+    let replica = Replica::new();
+    let client = JsonClient::join(url, doc_id, &replica).await?;
+    client.close().await;
+    Ok(())
+}
+
+/// Updates sent concurrently by two clients are relayed to each other, and
+/// both replicas converge.
+async fn ws_json_concurrent(url: &str, doc_id: &str) -> Result<(), String> {
+    let (a, b) = (Replica::new(), Replica::new());
+    let mut client_a = JsonClient::join(url, doc_id, &a).await?;
+    let mut client_b = JsonClient::join(url, doc_id, &b).await?;
+
+    for (client, replica, chunk) in [(&mut client_a, &a, "alpha "), (&mut client_b, &b, "beta ")] {
+        let update = base64::engine::general_purpose::STANDARD.encode(replica.append(chunk));
+        client
+            .send(json!({ "type": "update", "doc_id": doc_id, "update": update }))
+            .await?;
+    }
+
+    a.apply(&client_a.recv_update().await?)?;
+    b.apply(&client_b.recv_update().await?)?;
+    a.expect_chunks("client A", &["alpha ", "beta "])?;
+    expect_converged(&a, &b)?;
+
+    client_a.close().await;
+    client_b.close().await;
+    Ok(())
+}
+
+/// A bidirectional `Collaborate` stream bound to one document.
+struct GrpcSession {
+    client_id: String,
+    doc_id: String,
+    sender: mpsc::UnboundedSender<ClientMessage>,
+    stream: RecvStream<ServerMessage>,
+}
+
+impl GrpcSession {
+    async fn open(
+        client: &CollaborationServiceClient,
+        doc_id: &str,
+        client_id: &str,
+    ) -> Result<Self, String> {
+        let (sender, requests) = mpsc::unbounded();
+        let stream = client
+            .collaborate(requests)
+            .await
+            .map_err(|e| format!("failed to open the Collaborate stream: {}", e))?
+            .into_inner();
+
+        Ok(Self {
+            client_id: client_id.to_string(),
+            doc_id: doc_id.to_string(),
+            sender,
+            stream,
+        })
+    }
+
+    /// Opens a stream and synchronizes the replica with the document.
+    async fn join(
+        client: &CollaborationServiceClient,
+        doc_id: &str,
+        client_id: &str,
+        replica: &Replica,
+    ) -> Result<Self, String> {
+        let mut session = Self::open(client, doc_id, client_id).await?;
+        session.sync(replica).await?;
+        Ok(session)
+    }
+
+    fn send(&self, message_type: client_message::MessageType) -> Result<(), String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        self.sender
+            .unbounded_send(ClientMessage {
+                client_id: self.client_id.clone().into(),
+                document_id: self.doc_id.clone().into(),
+                timestamp,
+                message_type: Some(message_type),
+                tenant: Default::default(),
+            })
+            .map_err(|_| "the Collaborate stream is closed".to_string())
+    }
+
+    fn send_update(&self, update: Vec<u8>) -> Result<(), String> {
+        self.send(client_message::MessageType::Update(UpdateMessage {
+            update_data: update.into(),
+            origin_client_id: self.client_id.clone().into(),
+            sequence_number: 0,
+            dictionary_id: 0,
+            encoding: Default::default(),
+            origin_user_id: Default::default(),
+            origin_kind: Default::default(),
+        }))
+    }
+
+    /// Requests the changes missing from the replica and applies them. The
+    /// stream is ordered, so every message sent before has been processed.
+    async fn sync(&mut self, replica: &Replica) -> Result<(), String> {
+        self.send(client_message::MessageType::SyncRequest(SyncRequest {
+            state_vector: replica.state_vector().into(),
+        }))?;
+
+        loop {
+            if let server_message::MessageType::SyncResponse(response) = self.recv().await? {
+                return replica.apply(&response.update_data);
+            }
+        }
+    }
+
+    /// Receives the next server message, failing on error messages.
+    async fn recv(&mut self) -> Result<server_message::MessageType, String> {
+        loop {
+            let message = match self.stream.next().await {
+                Some(Ok(message)) => message,
+                Some(Err(status)) => return Err(format!("stream failed: {}", status)),
+                None => return Err("stream closed by the server".to_string()),
+            };
+
+            match message.message_type {
+                Some(server_message::MessageType::Error(error)) => {
+                    return Err(format!(
+                        "server error {}: {}",
+                        error.error_code, error.error_message
+                    ))
+                }
+                Some(message_type) => return Ok(message_type),
+                None => {}
+            }
+        }
+    }
+
+    /// Receives the next update relayed from another client.
+    async fn recv_update(&mut self) -> Result<Vec<u8>, String> {
+        loop {
+            if let server_message::MessageType::Update(update) = self.recv().await? {
+                if update.origin_client_id.as_str() != self.client_id {
+                    return Ok(update.update_data.to_vec());
+                }
+            }
+        }
+    }
+}
+
+/// A `SyncRequest` is answered with a `SyncResponse`.
+async fn grpc_handshake(client: &CollaborationServiceClient, doc_id: &str) -> Result<(), String> {
+    let replica = Replica::new();
+    GrpcSession::join(client, doc_id, "conformance-a", &replica).await?;
+    Ok(())
+}
+
+/// Updates sent concurrently by two clients are relayed to each other, and
+/// both replicas converge.
+async fn grpc_concurrent(client: &CollaborationServiceClient, doc_id: &str) -> Result<(), String> {
+    let (a, b) = (Replica::new(), Replica::new());
+    let mut session_a = GrpcSession::join(client, doc_id, "conformance-a", &a).await?;
+    let mut session_b = GrpcSession::join(client, doc_id, "conformance-b", &b).await?;
+
+    session_a.send_update(a.append("alpha "))?;
+    session_b.send_update(b.append("beta "))?;
+
+    a.apply(&session_a.recv_update().await?)?;
+    b.apply(&session_b.recv_update().await?)?;
+    a.expect_chunks("client A", &["alpha ", "beta "])?;
+    expect_converged(&a, &b)
+}
+
+/// Awareness updates of a joined client are relayed to the other clients of
+/// the document, stamped with the server's clock.
+async fn grpc_awareness(client: &CollaborationServiceClient, doc_id: &str) -> Result<(), String> {
+    let (a, b) = (Replica::new(), Replica::new());
+    let session_a = GrpcSession::join(client, doc_id, "conformance-a", &a).await?;
+    let mut session_b = GrpcSession::join(client, doc_id, "conformance-b", &b).await?;
+
+    for session in [&session_a, &session_b] {
+        session.send(client_message::MessageType::JoinDocument(JoinDocument {
+            user_id: session.client_id.clone().into(),
+            user_name: session.client_id.clone().into(),
+            user_color: "#3366ff".into(),
+            user_metadata: Default::default(),
+            echo_own_updates: false,
+            accept_compressed_updates: false,
+            accept_encoding: Default::default(),
+            update_encoding: Default::default(),
+        }))?;
+    }
+    // Processing the sync request proves B's join was processed before A's awareness
+    session_b.sync(&b).await?;
+
+    session_a.send(client_message::MessageType::Awareness(AwarenessUpdate {
+        client_id: session_a.client_id.clone().into(),
+        user_info: r#"{"name":"conformance-a"}"#.into(),
+        awareness_state: r#"{"cursor":0}"#.into(),
+        timestamp: 0,
+    }))?;
+
+    loop {
+        if let server_message::MessageType::Awareness(awareness) = session_b.recv().await? {
+            if awareness.client_id.as_str() != session_a.client_id {
+                continue;
+            }
+            if awareness.timestamp <= 0 {
+                return Err("relayed awareness carries no server timestamp".to_string());
+            }
+            return Ok(());
+        }
+    }
+}
+
+/// A client reconnecting with its state vector receives exactly the changes
+/// made while it was away.
+async fn grpc_reconnection(
+    client: &CollaborationServiceClient,
+    doc_id: &str,
+) -> Result<(), String> {
+    let (a, b) = (Replica::new(), Replica::new());
+
+    let mut session_a = GrpcSession::join(client, doc_id, "conformance-a", &a).await?;
+    session_a.send_update(a.append("before "))?;
+    session_a.sync(&a).await?;
+    drop(session_a);
+
+    let mut session_b = GrpcSession::join(client, doc_id, "conformance-b", &b).await?;
+    b.expect_chunks("client B", &["before "])?;
+    session_b.send_update(b.append("while-away "))?;
+    session_b.sync(&b).await?;
+
+    GrpcSession::join(client, doc_id, "conformance-a", &a).await?;
+    a.expect_chunks("reconnected client A", &["before ", "while-away "])?;
+    expect_converged(&a, &b)
+}
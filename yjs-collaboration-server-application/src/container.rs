@@ -1,34 +1,487 @@
 use std::sync::Arc;
-use yjs_collaboration_server_domain::services::document_service::DocumentService;
-use yjs_collaboration_server_infrastructure::adapters::in_memory_document_repository::InMemoryDocumentRepository;
+
+#[cfg(feature = "fault-injection")]
+use tracing::warn;
+use yjs_collaboration_server_adapter::{
+    admission::AdmissionController, outbox::SessionOutbox, session_registry::SessionRegistry,
+};
+use yjs_collaboration_server_domain::{
+    repositories::{
+        access_control::AccessControl, audit_sink::AuditSink,
+        cluster_membership::ClusterMembership,
+        document_metadata_repository::DocumentMetadataRepository,
+        document_repository::DocumentRepository, document_store::DocumentStore,
+        search_index::SearchIndex, update_broker::UpdateBroker, update_exporter::UpdateExporter,
+        version_repository::VersionRepository,
+    },
+    services::{
+        compute_pool::ComputePool, document_ownership::DocumentOwnership,
+        document_service::DocumentService, payload_dictionaries::PayloadDictionaries,
+    },
+};
+#[cfg(feature = "fault-injection")]
+use yjs_collaboration_server_infrastructure::adapters::fault_injection::{
+    FaultInjectingBroker, FaultInjectingRepository,
+};
+use yjs_collaboration_server_infrastructure::adapters::{
+    file_audit_sink::FileAuditSink, file_document_store::FileDocumentStore,
+    file_write_ahead_log::FileWriteAheadLog, grpc_document_forwarder::GrpcDocumentForwarder,
+    in_memory_audit_sink::InMemoryAuditSink,
+    in_memory_document_repository::InMemoryDocumentRepository,
+    in_memory_document_store::InMemoryDocumentStore,
+    in_memory_metadata_repository::InMemoryMetadataRepository,
+    in_memory_version_repository::InMemoryVersionRepository,
+    kafka_update_exporter::KafkaUpdateExporter, nats_update_broker::NatsUpdateBroker,
+    persistent_document_repository::PersistentDocumentRepository,
+    postgres_document_repository::PostgresDocumentRepository,
+    redis_cluster_membership::RedisClusterMembership, redis_update_broker::RedisUpdateBroker,
+    s3_document_repository::S3DocumentRepository, schema_update_validator::SchemaUpdateValidator,
+    static_cluster_membership::StaticClusterMembership, tantivy_search_index::TantivySearchIndex,
+};
+
+use crate::{
+    config::{
+        AppConfig, AuditBackend, BrokerBackend, ClusterBackend, MetricsBackend, SearchBackend,
+        StorageBackend, UpdateExportBackend,
+    },
+    metrics::{MetricsService, MetricsSink, PrometheusSink, StatsdSink},
+    services::document_application_service::DocumentUseCases,
+    standby::{Standby, StandbyAccessControl},
+    webhooks::WebhookDispatcher,
+};
+
+/// Document repository selected by the storage configuration
+pub type AppDocumentRepository = Box<dyn DocumentRepository>;
+
+/// Document, metadata and version repositories of the same storage backend
+pub(crate) type AppRepositories = (
+    AppDocumentRepository,
+    Arc<dyn DocumentMetadataRepository>,
+    Arc<dyn VersionRepository>,
+);
 
 /// Dependency injection container
 /// Follows DDD architecture, manages dependencies across layers
 pub struct Container {
     // Application layer
-    document_service: Arc<DocumentService<InMemoryDocumentRepository>>,
+    document_service: Arc<DocumentService<AppDocumentRepository>>,
+    // Adapter layer - shared across HTTP and gRPC servers
+    admission_controller: Arc<AdmissionController>,
+    // Adapter layer - clients present on each document, over either transport
+    session_registry: Arc<SessionRegistry>,
+    // Adapter layer - updates buffered for gRPC clients whose stream dropped
+    session_outbox: Arc<SessionOutbox>,
+    // Domain layer - shared by every document for CPU-heavy CRDT operations
+    compute_pool: Arc<ComputePool>,
+    // Application layer - workflows spanning documents and sessions, such as scheduled closes
+    document_use_cases: Arc<DocumentUseCases<AppDocumentRepository>>,
+    // Application layer - metrics exported to the configured backend
+    metrics_service: Arc<MetricsService>,
+    // Application layer - set when the server follows a primary as a warm standby
+    standby: Option<Arc<Standby>>,
+    // Application layer - set when a feature policy has webhook targets
+    webhooks: Option<Arc<WebhookDispatcher>>,
 }
 
 impl Container {
     /// Create and configure all dependencies
-    pub fn new() -> Self {
+    ///
+    /// Fails if the configured storage, metrics or broker backend cannot be opened, or if the
+    /// standby or cluster settings are invalid
+    pub fn new(config: &AppConfig) -> Result<Self, String> {
+        // Compute pool running CRDT operations off the async workers
+        let compute_pool = Arc::new(ComputePool::new(config.compute.budget()));
+
         // Create infrastructure dependencies
-        let document_repository = InMemoryDocumentRepository::new();
+        let (document_repository, metadata_repository, version_repository) =
+            Self::open_repository(config, compute_pool.clone())?;
+        let broker = Self::open_broker(config)?;
+
+        // Staging builds wrap the repository and broker in fault-injecting decorators
+        #[cfg(feature = "fault-injection")]
+        let (document_repository, broker) = {
+            let faults = Arc::new(config.faults.injector());
+            if faults.plan().is_active() {
+                warn!("Fault injection enabled: {:?}", faults.plan());
+            }
+            let broker = broker.map(|broker| -> Arc<dyn UpdateBroker> {
+                Arc::new(FaultInjectingBroker::new(broker, faults.clone()))
+            });
+            let repository: AppDocumentRepository =
+                Box::new(FaultInjectingRepository::new(document_repository, faults));
+            (repository, broker)
+        };
+
+        // A standby serves documents read-only until it is promoted
+        let standby = config
+            .replication
+            .standby()?
+            .map(|standby| Arc::new(Standby::new(standby)));
+        let mut access_control: Arc<dyn AccessControl> = Arc::new(config.access.access_control());
+        if let Some(standby) = &standby {
+            access_control = Arc::new(StandbyAccessControl::new(access_control, standby.clone()));
+        }
 
         // Application layer - create use case service
-        let document_service = Arc::new(DocumentService::new(document_repository));
+        let mut document_service = DocumentService::new(document_repository)
+            .with_metadata(metadata_repository)
+            .with_versions(version_repository, config.versions.policy())
+            .with_access_control(access_control)
+            .with_policies(config.policies.policies())
+            .with_diff_throttle(config.sync.throttle())
+            .with_update_limits(config.limits.update_limits())
+            .with_broadcast_coalescing(config.broadcast.coalescing())
+            .with_tenant_quotas(config.tenants.quotas())
+            .with_activity_retention(config.activity.retention());
+        if config.payload_compression.enabled {
+            document_service =
+                document_service.with_payload_dictionaries(PayloadDictionaries::new(
+                    Arc::new(config.payload_compression.compressor()),
+                    config.payload_compression.settings(),
+                ));
+        }
+        if let Some(broker) = broker {
+            document_service = document_service.with_broker(broker);
+        }
+        if let Some(ownership) = Self::join_cluster(config)? {
+            if config.cluster.forwarding {
+                let forwarder = GrpcDocumentForwarder::new(ownership.local().clone());
+                document_service = document_service.with_forwarder(Arc::new(forwarder));
+            }
+            document_service = document_service.with_ownership(ownership);
+        }
+        let collation = config
+            .collation
+            .collation()
+            .map_err(|e| format!("Failed to load the collation: {}", e))?;
+        if let Some(collation) = collation {
+            document_service = document_service.with_collation(Arc::new(collation));
+        }
+        if let Some(store) = Self::open_eviction_store(config)? {
+            document_service = document_service.with_store(store);
+        }
+        // Reads the archived documents from the metadata, so it comes after `with_metadata`
+        if config.storage.archive.is_enabled() {
+            let archive = FileDocumentStore::new(
+                &config.storage.archive.path,
+                config.storage.compression.codec(),
+            )
+            .map_err(|e| format!("Failed to open the document archive: {}", e))?;
+            document_service = document_service
+                .with_archive(Arc::new(archive), config.storage.archive.archive_after());
+        }
+        if config.storage.wal.is_enabled() {
+            let write_ahead_log = FileWriteAheadLog::new(
+                &config.storage.wal.path,
+                config.storage.wal.sync,
+                config.storage.wal.segment_size_bytes,
+            )
+            .map_err(|e| format!("Failed to open the write-ahead log: {}", e))?;
+            document_service = document_service.with_write_ahead_log(Arc::new(write_ahead_log));
+        }
+        if let Some(audit) = Self::open_audit_sink(config)? {
+            document_service = document_service.with_audit_sink(audit);
+        }
+        if config.validation.is_enabled() {
+            document_service = document_service.with_validator(Arc::new(
+                SchemaUpdateValidator::new(config.validation.rules()),
+            ));
+        }
+        // Custom processors, implementing `UpdateProcessor`, are registered the same way,
+        // e.g. `document_service.with_update_processor(Arc::new(MyProcessor::new()))`;
+        // they run in the order they are registered
+        for processor in config.moderation.processors()? {
+            document_service = document_service.with_update_processor(Arc::new(processor));
+        }
+        if let Some(exporter) = Self::open_update_exporter(config)? {
+            document_service = document_service.with_exporter(exporter);
+        }
+        if let Some(search) = Self::open_search_index(config)? {
+            document_service = document_service.with_search_index(search);
+        }
+        let document_service = Arc::new(document_service);
+
+        // Connection admission control shared by both transports
+        let admission_controller =
+            Arc::new(AdmissionController::new(config.admission.thresholds()));
+
+        // Presence of the clients of both transports, along with their delivery failures
+        // and the rate of the updates they send
+        let session_registry = Arc::new(
+            SessionRegistry::new()
+                .with_limits(config.sessions.room_limits())
+                .with_presence_history(config.sessions.presence_history())
+                .with_cursor_interval(config.sessions.cursor_interval())
+                .with_keep_alive(config.sessions.keep_alive())
+                .with_tenant_quotas(config.tenants.quotas())
+                .with_update_rate_limit(config.rate_limit.update_rate_limit()),
+        );
+
+        // Metrics collected across layers and handed to the configured backend
+        let metrics_sink = Self::open_metrics_sink(config)?;
+        let metrics_service = Arc::new(MetricsService::new(
+            document_service.clone(),
+            admission_controller.clone(),
+            compute_pool.clone(),
+            session_registry.delivery_stats(),
+            metrics_sink,
+        ));
+
+        // Webhooks only listen to events when some document may be delivered
+        let webhooks = if config.policies.has_webhook_targets() {
+            Some(Arc::new(WebhookDispatcher::new(
+                config.webhooks.settings(),
+            )?))
+        } else {
+            None
+        };
+
+        let document_use_cases = Arc::new(
+            DocumentUseCases::new(document_service.clone())
+                .with_session_registry(session_registry.clone()),
+        );
+
+        Ok(Self {
+            document_service,
+            admission_controller,
+            session_registry,
+            session_outbox: Arc::new(SessionOutbox::new(
+                config.sessions.outbox_capacity,
+                config.sessions.outbox_retention(),
+            )),
+            compute_pool,
+            document_use_cases,
+            metrics_service,
+            standby,
+            webhooks,
+        })
+    }
+
+    /// Opens the document, metadata and version repositories selected by the storage
+    /// configuration
+    ///
+    /// Fails if the storage backend cannot be opened or reached
+    pub(crate) fn open_repository(
+        config: &AppConfig,
+        compute_pool: Arc<ComputePool>,
+    ) -> Result<AppRepositories, String> {
+        Ok(match config.storage.backend {
+            StorageBackend::Memory => (
+                Box::new(
+                    InMemoryDocumentRepository::with_compute_pool(compute_pool)
+                        .with_eviction(config.storage.eviction.policy()),
+                ),
+                Arc::new(InMemoryMetadataRepository::new()),
+                Arc::new(InMemoryVersionRepository::new()),
+            ),
+            StorageBackend::Sled => {
+                let repository = PersistentDocumentRepository::open(
+                    &config.storage.path,
+                    config.storage.compact_threshold,
+                    config.storage.compression.codec(),
+                    compute_pool,
+                )?;
+                let metadata = repository.metadata_repository();
+                let versions = repository.version_repository();
+                (Box::new(repository), metadata, versions)
+            }
+            StorageBackend::Postgres => {
+                let repository = PostgresDocumentRepository::connect(
+                    &config.storage.postgres.url,
+                    config.storage.postgres.connect_timeout(),
+                    config.storage.compact_threshold,
+                    config.storage.compression.codec(),
+                    compute_pool,
+                )?;
+                let metadata = repository.metadata_repository();
+                let versions = repository.version_repository();
+                (Box::new(repository), metadata, versions)
+            }
+            StorageBackend::S3 => (
+                Box::new(S3DocumentRepository::connect(
+                    &config.storage.s3.settings(),
+                    config.storage.compact_threshold,
+                    config.storage.compression.codec(),
+                    compute_pool,
+                )?),
+                Arc::new(InMemoryMetadataRepository::new()),
+                Arc::new(InMemoryVersionRepository::new()),
+            ),
+        })
+    }
+
+    /// Opens the store documents evicted from memory are saved to, if eviction is enabled
+    ///
+    /// Fails if the eviction directory cannot be created
+    pub(crate) fn open_eviction_store(
+        config: &AppConfig,
+    ) -> Result<Option<Arc<dyn DocumentStore>>, String> {
+        let eviction = &config.storage.eviction;
+        if config.storage.backend != StorageBackend::Memory || !eviction.policy().is_enabled() {
+            return Ok(None);
+        }
+
+        Ok(Some(if eviction.path.is_empty() {
+            Arc::new(InMemoryDocumentStore::new())
+        } else {
+            Arc::new(
+                FileDocumentStore::new(&eviction.path, config.storage.compression.codec())
+                    .map_err(|e| format!("Failed to open the eviction store: {}", e))?,
+            )
+        }))
+    }
+
+    /// Opens the sink of the audit trails selected by the audit configuration, if enabled
+    ///
+    /// Fails if the audit directory cannot be created
+    pub(crate) fn open_audit_sink(
+        config: &AppConfig,
+    ) -> Result<Option<Arc<dyn AuditSink>>, String> {
+        let audit = &config.audit;
+        Ok(match audit.backend {
+            AuditBackend::None => None,
+            AuditBackend::Memory => Some(Arc::new(InMemoryAuditSink::new(
+                audit.max_entries_per_document,
+            ))),
+            AuditBackend::File => {
+                Some(Arc::new(FileAuditSink::new(&audit.path).map_err(|e| {
+                    format!("Failed to open the audit trail: {}", e)
+                })?))
+            }
+        })
+    }
+
+    /// Opens the export of the applied updates selected by the export configuration, if enabled
+    ///
+    /// Fails if no Kafka broker or topic is configured
+    fn open_update_exporter(config: &AppConfig) -> Result<Option<Arc<dyn UpdateExporter>>, String> {
+        Ok(match config.update_export.backend {
+            UpdateExportBackend::None => None,
+            UpdateExportBackend::Kafka => Some(Arc::new(KafkaUpdateExporter::connect(
+                config.update_export.kafka_settings(),
+            )?)),
+        })
+    }
 
-        Self { document_service }
+    /// Opens the full-text index selected by the search configuration, if enabled
+    ///
+    /// Fails if the index directory cannot be created or the index cannot be opened
+    fn open_search_index(config: &AppConfig) -> Result<Option<Arc<dyn SearchIndex>>, String> {
+        let search = match config.search.backend {
+            SearchBackend::None => return Ok(None),
+            SearchBackend::Memory => TantivySearchIndex::in_memory(),
+            SearchBackend::File => TantivySearchIndex::open_in_dir(&config.search.path),
+        }
+        .map_err(|e| format!("Failed to open the search index: {}", e))?;
+        Ok(Some(Arc::new(search)))
+    }
+
+    /// Opens the sink selected by the metrics configuration
+    ///
+    /// Fails if the metrics backend address is invalid
+    pub(crate) fn open_metrics_sink(config: &AppConfig) -> Result<Arc<dyn MetricsSink>, String> {
+        Ok(match config.metrics.backend {
+            MetricsBackend::Prometheus => Arc::new(PrometheusSink::new(&config.metrics.prefix)),
+            MetricsBackend::Statsd => Arc::new(StatsdSink::new(
+                &config.metrics.statsd_addr,
+                &config.metrics.prefix,
+            )?),
+        })
+    }
+
+    /// Opens the broker selected by the broker configuration
+    ///
+    /// Returns `None` for a single instance; fails if the broker URL or NATS names are invalid
+    pub(crate) fn open_broker(config: &AppConfig) -> Result<Option<Arc<dyn UpdateBroker>>, String> {
+        Ok(match config.broker.backend {
+            BrokerBackend::None => None,
+            BrokerBackend::Redis => Some(Arc::new(RedisUpdateBroker::connect(
+                &config.broker.redis_url,
+                &config.broker.channel_prefix,
+            )?)),
+            BrokerBackend::Nats => Some(Arc::new(NatsUpdateBroker::connect(
+                &config.broker.nats.url,
+                config.broker.nats_settings(&config.cluster.node_id)?,
+            )?)),
+        })
+    }
+
+    /// Joins the cluster selected by the cluster configuration
+    ///
+    /// Returns `None` outside a cluster; fails if the cluster settings or the Redis URL are
+    /// invalid
+    fn join_cluster(config: &AppConfig) -> Result<Option<Arc<DocumentOwnership>>, String> {
+        let Some(local) = config.cluster.local_member()? else {
+            return Ok(None);
+        };
+
+        let membership: Arc<dyn ClusterMembership> = match config.cluster.backend {
+            ClusterBackend::None => return Ok(None),
+            ClusterBackend::Static => {
+                Arc::new(StaticClusterMembership::new(config.cluster.members.clone()))
+            }
+            ClusterBackend::Redis => Arc::new(RedisClusterMembership::connect(
+                &config.cluster.redis_url,
+                &config.cluster.key_prefix,
+                config.cluster.heartbeat_interval(),
+                config.cluster.member_ttl(),
+            )?),
+        };
+        let ownership = DocumentOwnership::join(local, membership)
+            .map_err(|e| format!("Failed to join the cluster: {}", e))?;
+        Ok(Some(Arc::new(ownership)))
     }
 
     /// Get document use case service
-    pub fn get_document_service(&self) -> Arc<DocumentService<InMemoryDocumentRepository>> {
+    pub fn get_document_service(&self) -> Arc<DocumentService<AppDocumentRepository>> {
         self.document_service.clone()
     }
+
+    /// Get the application workflows spanning documents and sessions
+    pub fn get_document_use_cases(&self) -> Arc<DocumentUseCases<AppDocumentRepository>> {
+        self.document_use_cases.clone()
+    }
+
+    /// Get the connection admission controller
+    pub fn get_admission_controller(&self) -> Arc<AdmissionController> {
+        self.admission_controller.clone()
+    }
+
+    /// Get the registry of the sessions of both transports
+    pub fn get_session_registry(&self) -> Arc<SessionRegistry> {
+        self.session_registry.clone()
+    }
+
+    /// Get the outbox of the updates missed by dropped gRPC sessions
+    pub fn get_session_outbox(&self) -> Arc<SessionOutbox> {
+        self.session_outbox.clone()
+    }
+
+    /// Get the CRDT compute pool
+    pub fn get_compute_pool(&self) -> Arc<ComputePool> {
+        self.compute_pool.clone()
+    }
+
+    /// Get the metrics service
+    pub fn get_metrics_service(&self) -> Arc<MetricsService> {
+        self.metrics_service.clone()
+    }
+
+    /// Get the warm standby, if the server follows a primary
+    pub fn get_standby(&self) -> Option<Arc<Standby>> {
+        self.standby.clone()
+    }
+
+    /// Get the webhook dispatcher, if any document may have webhook targets
+    pub fn get_webhooks(&self) -> Option<Arc<WebhookDispatcher>> {
+        self.webhooks.clone()
+    }
 }
 
 impl Default for Container {
     fn default() -> Self {
-        Self::new()
+        // The default configuration uses in-memory storage and Prometheus metrics, which cannot
+        // fail to open
+        Self::new(&AppConfig::default()).expect("default backends are always available")
     }
 }
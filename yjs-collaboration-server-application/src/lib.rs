@@ -4,12 +4,24 @@
 // that coordinates the domain objects and infrastructure services.
 
 pub mod bootstrap;
+pub mod check;
 pub mod config;
+pub mod config_reload;
+pub mod conformance;
 pub mod container;
+pub mod metrics;
+pub mod replay;
 pub mod servers;
 pub mod services;
+pub mod simulation;
+pub mod standby;
+pub mod webhooks;
 
 // Re-export commonly used application types
 pub use bootstrap::ApplicationBootstrap;
+pub use check::CheckReport;
 pub use config::AppConfig;
+pub use conformance::{ConformanceConfig, ConformanceReport, ConformanceSuite};
+pub use replay::ReplayReport;
 pub use services::document_application_service::DocumentUseCases;
+pub use simulation::SimulationConfig;
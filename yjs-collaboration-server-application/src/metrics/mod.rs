@@ -0,0 +1,310 @@
+pub mod prometheus;
+pub mod statsd;
+
+use std::{sync::Arc, time::Duration};
+
+pub use prometheus::PrometheusSink;
+pub use statsd::StatsdSink;
+use tokio::task::JoinHandle;
+use tracing::warn;
+use yjs_collaboration_server_adapter::{
+    admission::{AdmissionController, CapacityReport, LoadSignals},
+    delivery_stats::DeliveryStats,
+    http::admin::MetricsExporter,
+};
+use yjs_collaboration_server_domain::services::{
+    compute_pool::ComputePool, document_service::DocumentService,
+};
+
+use crate::container::AppDocumentRepository;
+
+/// Kind of a metric, deciding how sinks aggregate it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricKind {
+    /// A value that can go up and down
+    Gauge,
+    /// A monotonically increasing total
+    Counter,
+}
+
+/// A single metric sample.
+///
+/// Names are given without a backend-specific prefix or separator conventions;
+/// sinks add their own prefix and encode labels as their backend expects.
+#[derive(Clone, Debug)]
+pub struct Metric {
+    /// Metric name, e.g. `crdt_operations_total`
+    pub name: &'static str,
+    /// Human readable description
+    pub help: &'static str,
+    /// Kind of the metric
+    pub kind: MetricKind,
+    /// Label names and values distinguishing series of the same metric
+    pub labels: Vec<(&'static str, String)>,
+    /// Current value (the running total for counters)
+    pub value: f64,
+}
+
+impl Metric {
+    /// Creates a gauge sample.
+    pub fn gauge(name: &'static str, help: &'static str, value: f64) -> Self {
+        Self {
+            name,
+            help,
+            kind: MetricKind::Gauge,
+            labels: Vec::new(),
+            value,
+        }
+    }
+
+    /// Creates a counter sample.
+    pub fn counter(name: &'static str, help: &'static str, value: f64) -> Self {
+        Self {
+            kind: MetricKind::Counter,
+            ..Self::gauge(name, help, value)
+        }
+    }
+
+    /// Adds a label to the sample.
+    pub fn with_label(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.labels.push((name, value.into()));
+        self
+    }
+}
+
+/// Backend receiving the server's metrics.
+///
+/// Scraped backends render a snapshot on request; push-based backends send each
+/// published snapshot to a remote collector. Implementations must be thread-safe
+/// as they are shared by the admin server and the publisher task.
+pub trait MetricsSink: Send + Sync {
+    /// Sends a snapshot of every metric to the backend.
+    ///
+    /// # Parameters
+    ///
+    /// * `metrics` - The current metric samples
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the snapshot was sent (or the sink is scraped instead)
+    /// * `Err(String)` - Error message if sending failed
+    fn publish(&self, metrics: &[Metric]) -> Result<(), String>;
+
+    /// Renders a snapshot in the Prometheus text exposition format for scraping.
+    ///
+    /// # Returns
+    ///
+    /// The exposition text, or `None` if the backend is push-based
+    fn render(&self, _metrics: &[Metric]) -> Option<String> {
+        None
+    }
+}
+
+/// Collects the server's instrumentation and hands it to the configured sink.
+///
+/// Every module's metrics are gathered here in a backend-neutral form, so the
+/// same instrumentation is served on the admin `/metrics` route for Prometheus
+/// or pushed periodically to statsd.
+pub struct MetricsService {
+    document_service: Arc<DocumentService<AppDocumentRepository>>,
+    admission: Arc<AdmissionController>,
+    compute: Arc<ComputePool>,
+    delivery: Arc<DeliveryStats>,
+    sink: Arc<dyn MetricsSink>,
+}
+
+impl MetricsService {
+    /// Creates a new metrics service.
+    ///
+    /// # Parameters
+    ///
+    /// * `document_service` - The domain document service to report on
+    /// * `admission` - Admission controller tracking active connections
+    /// * `compute` - Compute pool recording CRDT operation metrics
+    /// * `delivery` - Counters of the broadcast messages connections failed to deliver
+    /// * `sink` - Backend receiving the metrics
+    ///
+    /// # Returns
+    ///
+    /// A new `MetricsService` instance
+    pub fn new(
+        document_service: Arc<DocumentService<AppDocumentRepository>>,
+        admission: Arc<AdmissionController>,
+        compute: Arc<ComputePool>,
+        delivery: Arc<DeliveryStats>,
+        sink: Arc<dyn MetricsSink>,
+    ) -> Self {
+        Self {
+            document_service,
+            admission,
+            compute,
+            delivery,
+            sink,
+        }
+    }
+
+    /// Samples every metric.
+    ///
+    /// # Returns
+    ///
+    /// The current metric samples, grouped by metric name
+    pub fn collect(&self) -> Vec<Metric> {
+        let mut metrics = vec![
+            Metric::gauge(
+                "loaded_documents",
+                "Number of documents loaded in memory",
+                self.document_service.loaded_document_count() as f64,
+            ),
+            Metric::gauge(
+                "active_connections",
+                "Number of admitted client connections",
+                self.admission.active_connections() as f64,
+            ),
+            Metric::gauge(
+                "connection_queue_depth",
+                "Number of messages waiting in connection queues",
+                self.delivery.queue_depth() as f64,
+            ),
+            Metric::gauge(
+                "capacity_utilization",
+                "Highest utilization among the load signals, 1 when the server sheds load",
+                self.capacity().utilization,
+            ),
+        ];
+
+        let stats = self.compute.stats();
+        for s in &stats {
+            metrics.push(
+                Metric::counter(
+                    "crdt_operations_total",
+                    "Number of CRDT operations executed on the compute pool",
+                    s.calls as f64,
+                )
+                .with_label("operation", s.operation.to_string()),
+            );
+        }
+        for s in &stats {
+            metrics.push(
+                Metric::counter(
+                    "crdt_operation_seconds_sum",
+                    "Total time spent executing CRDT operations",
+                    s.total_time.as_secs_f64(),
+                )
+                .with_label("operation", s.operation.to_string()),
+            );
+        }
+        for s in &stats {
+            metrics.push(
+                Metric::gauge(
+                    "crdt_operation_seconds_max",
+                    "Longest single CRDT operation",
+                    s.max_time.as_secs_f64(),
+                )
+                .with_label("operation", s.operation.to_string()),
+            );
+        }
+        for s in &stats {
+            metrics.push(
+                Metric::counter(
+                    "crdt_operations_over_budget_total",
+                    "Number of CRDT operations that exceeded their time budget",
+                    s.over_budget as f64,
+                )
+                .with_label("operation", s.operation.to_string()),
+            );
+        }
+
+        for (limit, count) in self.document_service.oversized_update_counts() {
+            metrics.push(
+                Metric::counter(
+                    "oversized_updates_total",
+                    "Number of updates rejected for exceeding the maximum update or document size",
+                    count as f64,
+                )
+                .with_label("limit", limit.to_string()),
+            );
+        }
+
+        match self.document_service.tag_counts() {
+            Ok(counts) => {
+                for (tag, count) in counts {
+                    metrics.push(
+                        Metric::gauge(
+                            "tagged_documents",
+                            "Number of documents carrying a tag",
+                            count as f64,
+                        )
+                        .with_label("tag", tag),
+                    );
+                }
+            }
+            Err(e) => warn!("Failed to count document tags: {}", e),
+        }
+
+        let drops = self.delivery.snapshot();
+        for dropped in drops.iter().filter(|dropped| dropped.client_id.is_none()) {
+            metrics.push(
+                Metric::counter(
+                    "broadcast_dropped_total",
+                    "Number of broadcast messages that could not be delivered, per document",
+                    dropped.count as f64,
+                )
+                .with_label("document", dropped.document_id.clone())
+                .with_label("reason", dropped.reason.to_string()),
+            );
+        }
+        for dropped in &drops {
+            if let Some(client_id) = &dropped.client_id {
+                metrics.push(
+                    Metric::counter(
+                        "session_broadcast_dropped_total",
+                        "Number of broadcast messages that could not be delivered to a connected \
+                         session",
+                        dropped.count as f64,
+                    )
+                    .with_label("document", dropped.document_id.clone())
+                    .with_label("client", client_id.clone())
+                    .with_label("reason", dropped.reason.to_string()),
+                );
+            }
+        }
+
+        metrics
+    }
+
+    /// Spawns a task publishing a snapshot to the sink at a fixed interval.
+    ///
+    /// # Parameters
+    ///
+    /// * `interval` - Delay between two snapshots
+    ///
+    /// # Returns
+    ///
+    /// The handle of the publisher task
+    pub fn spawn_publisher(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = service.sink.publish(&service.collect()) {
+                    warn!("Failed to publish metrics: {}", e);
+                }
+            }
+        })
+    }
+}
+
+impl MetricsExporter for MetricsService {
+    fn render(&self) -> Option<String> {
+        self.sink.render(&self.collect())
+    }
+
+    fn capacity(&self) -> CapacityReport {
+        let signals = LoadSignals {
+            loaded_documents: self.document_service.loaded_document_count(),
+            queue_depth: self.delivery.queue_depth(),
+        };
+        self.admission.capacity(signals, self.compute.load())
+    }
+}
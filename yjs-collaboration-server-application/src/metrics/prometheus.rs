@@ -0,0 +1,79 @@
+use crate::metrics::{Metric, MetricKind, MetricsSink};
+
+/// Metrics sink scraped by Prometheus on the admin `/metrics` route.
+pub struct PrometheusSink {
+    prefix: String,
+}
+
+impl PrometheusSink {
+    /// Creates a Prometheus sink.
+    ///
+    /// # Parameters
+    ///
+    /// * `prefix` - Prefix prepended to every metric name (e.g. `yjs`)
+    ///
+    /// # Returns
+    ///
+    /// A new `PrometheusSink` instance
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+        }
+    }
+
+    fn metric_name(&self, metric: &Metric) -> String {
+        if self.prefix.is_empty() {
+            metric.name.to_string()
+        } else {
+            format!("{}_{}", self.prefix, metric.name)
+        }
+    }
+}
+
+impl MetricsSink for PrometheusSink {
+    /// Prometheus pulls metrics, so publishing is a no-op.
+    fn publish(&self, _metrics: &[Metric]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn render(&self, metrics: &[Metric]) -> Option<String> {
+        let mut body = String::new();
+        let mut previous = None;
+
+        for metric in metrics {
+            let name = self.metric_name(metric);
+
+            // Samples are grouped by name, so the header is written once per metric
+            if previous != Some(metric.name) {
+                let kind = match metric.kind {
+                    MetricKind::Gauge => "gauge",
+                    MetricKind::Counter => "counter",
+                };
+                body.push_str(&format!(
+                    "# HELP {} {}\n# TYPE {} {}\n",
+                    name, metric.help, name, kind
+                ));
+                previous = Some(metric.name);
+            }
+
+            let labels = metric
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, value.replace('"', "\\\"")))
+                .collect::<Vec<_>>();
+
+            if labels.is_empty() {
+                body.push_str(&format!("{} {}\n", name, metric.value));
+            } else {
+                body.push_str(&format!(
+                    "{}{{{}}} {}\n",
+                    name,
+                    labels.join(","),
+                    metric.value
+                ));
+            }
+        }
+
+        Some(body)
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, net::UdpSocket, sync::Mutex};
+
+use crate::metrics::{Metric, MetricKind, MetricsSink};
+
+/// Maximum payload of a single statsd datagram, safely below common MTUs.
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+/// Metrics sink pushing to a statsd daemon over UDP.
+///
+/// Gauges are sent as `|g`. Counters are running totals on the server, so the
+/// increment since the total last sent is sent as `|c`. Labels are appended to
+/// the metric name as dotted segments, e.g. `yjs.crdt_operations_total.apply_update`.
+pub struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+    /// Counter totals last sent, keyed by statsd metric name
+    last_counters: Mutex<HashMap<String, f64>>,
+}
+
+impl StatsdSink {
+    /// Creates a statsd sink.
+    ///
+    /// # Parameters
+    ///
+    /// * `addr` - Address of the statsd daemon in format "host:port"
+    /// * `prefix` - Prefix prepended to every metric name (e.g. `yjs`)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(StatsdSink)` - A sink sending to the daemon
+    /// * `Err(String)` - Error message if the address cannot be resolved
+    pub fn new(addr: &str, prefix: &str) -> Result<Self, String> {
+        let socket = UdpSocket::bind("[::]:0")
+            .or_else(|_| UdpSocket::bind("0.0.0.0:0"))
+            .map_err(|e| format!("Failed to bind statsd socket: {}", e))?;
+        socket
+            .connect(addr)
+            .map_err(|e| format!("Invalid statsd address '{}': {}", addr, e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to configure statsd socket: {}", e))?;
+
+        Ok(Self {
+            socket,
+            prefix: prefix.to_string(),
+            last_counters: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn metric_name(&self, metric: &Metric) -> String {
+        let mut name = if self.prefix.is_empty() {
+            metric.name.to_string()
+        } else {
+            format!("{}.{}", self.prefix, metric.name)
+        };
+
+        for (_, value) in &metric.labels {
+            name.push('.');
+            name.push_str(&value.replace(['.', ':', '|'], "_"));
+        }
+
+        name
+    }
+
+    fn send(&self, payload: &str) -> Result<(), String> {
+        self.socket
+            .send(payload.as_bytes())
+            .map(|_| ())
+            .map_err(|e| format!("Failed to send metrics to statsd: {}", e))
+    }
+}
+
+impl MetricsSink for StatsdSink {
+    fn publish(&self, metrics: &[Metric]) -> Result<(), String> {
+        let mut last_counters = self.last_counters.lock().map_err(|e| e.to_string())?;
+        let mut payload = String::new();
+        // Counters of the datagram being built, recorded once it is sent so the
+        // deltas of a failed send are sent again with the next publication
+        let mut payload_counters = Vec::new();
+
+        for metric in metrics {
+            let name = self.metric_name(metric);
+            let (line, counter) = match metric.kind {
+                MetricKind::Gauge => (format!("{}:{}|g", name, metric.value), None),
+                MetricKind::Counter => {
+                    let previous = last_counters.get(&name).copied().unwrap_or(0.0);
+                    let delta = metric.value - previous;
+                    if delta <= 0.0 {
+                        // A counter reset, e.g. by a restart, counts from its new value
+                        last_counters.insert(name, metric.value);
+                        continue;
+                    }
+                    (format!("{}:{}|c", name, delta), Some((name, metric.value)))
+                }
+            };
+
+            if !payload.is_empty() && payload.len() + line.len() + 1 > MAX_DATAGRAM_SIZE {
+                self.send(&payload)?;
+                last_counters.extend(payload_counters.drain(..));
+                payload.clear();
+            }
+            if !payload.is_empty() {
+                payload.push('\n');
+            }
+            payload.push_str(&line);
+            payload_counters.extend(counter);
+        }
+
+        if !payload.is_empty() {
+            self.send(&payload)?;
+            last_counters.extend(payload_counters);
+        }
+
+        Ok(())
+    }
+}
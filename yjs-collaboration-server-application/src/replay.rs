@@ -0,0 +1,240 @@
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use yjs_collaboration_server_domain::{
+    entities::document::CollaborativeDocument,
+    services::compute_pool::ComputePool,
+    value_objects::logged_update::{LogPosition, LoggedUpdate},
+};
+use yrs::{updates::decoder::Decode, StateVector, Update};
+
+use crate::{check::CheckStatus, config::AppConfig, container::Container};
+
+/// Result of replaying a single entry of a document's update log.
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    /// Position of the entry in the log
+    pub position: LogPosition,
+    /// Outcome of the step; a warning flags changes waiting for missing dependencies
+    pub status: CheckStatus,
+    /// Human readable explanation
+    pub detail: String,
+}
+
+/// Report of the replay of a document's update log.
+///
+/// Produced by the `replay` command, which reads a document's persisted
+/// snapshot and updates without compacting them, applies them one at a time to
+/// a fresh document and validates the document after every step. The replay
+/// stops at the first entry that fails to decode or apply, or after which the
+/// document no longer survives an encoding round trip.
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    /// Identifier of the replayed document
+    doc_id: String,
+    /// Result of every replayed entry, in log order
+    steps: Vec<ReplayStep>,
+    /// Failure not tied to a single entry, e.g. an unreadable log
+    error: Option<String>,
+}
+
+impl ReplayReport {
+    fn new(doc_id: &str) -> Self {
+        Self {
+            doc_id: doc_id.to_string(),
+            steps: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Creates the report of a replay that could not start, e.g. on an invalid configuration.
+    pub(crate) fn failed(doc_id: &str, error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::new(doc_id)
+        }
+    }
+
+    /// Returns the result of every replayed entry, in log order.
+    pub fn steps(&self) -> &[ReplayStep] {
+        &self.steps
+    }
+
+    /// Returns the first entry that failed, if any.
+    pub fn first_failure(&self) -> Option<&ReplayStep> {
+        self.steps
+            .iter()
+            .find(|step| step.status == CheckStatus::Failed)
+    }
+
+    /// Returns whether the whole log replayed cleanly; warnings do not fail the report.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.first_failure().is_none()
+    }
+
+    fn push(&mut self, position: LogPosition, status: CheckStatus, detail: impl Into<String>) {
+        self.steps.push(ReplayStep {
+            position,
+            status,
+            detail: detail.into(),
+        });
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Replaying the update log of '{}'", self.doc_id)?;
+        for step in &self.steps {
+            writeln!(f, "[{:>4}] {}: {}", step.status, step.position, step.detail)?;
+        }
+
+        if let Some(step) = self.first_failure() {
+            write!(f, "First failing entry: {}", step.position)
+        } else if let Some(error) = &self.error {
+            write!(f, "Replay failed: {}", error)
+        } else {
+            write!(f, "Replayed {} entries without errors", self.steps.len())
+        }
+    }
+}
+
+/// Replays the persisted update log of a document.
+///
+/// The storage backend is opened as on startup, so the server should be
+/// stopped first when its backend (such as sled) cannot be opened twice.
+///
+/// # Parameters
+///
+/// * `config` - The configuration selecting the storage backend
+/// * `doc_id` - Identifier of the document to replay
+///
+/// # Returns
+///
+/// A `ReplayReport` listing the outcome of every replayed entry
+pub(crate) fn replay_document(config: &AppConfig, doc_id: &str) -> ReplayReport {
+    let mut report = ReplayReport::new(doc_id);
+
+    let entries = Container::open_repository(config, Arc::new(ComputePool::default())).and_then(
+        |(repository, _, _)| repository.logged_updates(doc_id).map_err(|e| e.to_string()),
+    );
+    match entries {
+        Ok(entries) if entries.is_empty() => {
+            report.error = Some("nothing is persisted for the document".to_string());
+        }
+        Ok(entries) => replay_entries(&entries, &mut report),
+        Err(e) => report.error = Some(format!("failed to read the update log: {}", e)),
+    }
+    report
+}
+
+/// Applies log entries one at a time, stopping at the first failing one.
+///
+/// Once every entry is applied, the entries are also merged into a single
+/// update, as the repositories do when loading a document, and the result is
+/// compared with the step by step replay.
+fn replay_entries(entries: &[LoggedUpdate], report: &mut ReplayReport) {
+    let mut document = CollaborativeDocument::new();
+    let mut applied = Vec::with_capacity(entries.len());
+    let mut first_pending = None;
+
+    for entry in entries {
+        let data = match &entry.data {
+            Ok(data) => data,
+            Err(e) => {
+                let detail = format!("stored value could not be decompressed: {}", e);
+                report.push(entry.position, CheckStatus::Failed, detail);
+                return;
+            }
+        };
+        if let Err(e) = apply(&mut document, data) {
+            report.push(entry.position, CheckStatus::Failed, e);
+            return;
+        }
+        applied.push(data.as_slice());
+
+        if let Some(divergence) = round_trip_divergence(&document) {
+            let detail = format!("document diverges after applying it: {}", divergence);
+            report.push(entry.position, CheckStatus::Failed, detail);
+            return;
+        }
+        if document.has_pending_changes() {
+            first_pending.get_or_insert(entry.position);
+            let detail = format!(
+                "{} bytes applied, some changes wait for missing dependencies",
+                data.len()
+            );
+            report.push(entry.position, CheckStatus::Warning, detail);
+        } else {
+            report.push(
+                entry.position,
+                CheckStatus::Ok,
+                format!("{} bytes applied", data.len()),
+            );
+        }
+    }
+
+    if document.has_pending_changes() {
+        report.error = Some(format!(
+            "changes still wait for dependencies missing from the log, first since {}",
+            first_pending.unwrap_or(LogPosition::Snapshot)
+        ));
+        return;
+    }
+
+    let mut loaded = CollaborativeDocument::new();
+    let merged = yrs::merge_updates_v1(&applied)
+        .map_err(|e| format!("the entries cannot be merged: {}", e))
+        .and_then(|merged| apply(&mut loaded, &merged));
+    if let Err(e) = merged {
+        report.error = Some(format!("loading the document would fail, {}", e));
+    } else if let Some(difference) = difference(&document, &loaded) {
+        report.error = Some(format!(
+            "loading the merged log diverges from the step by step replay: {}",
+            difference
+        ));
+    }
+}
+
+/// Decodes and applies an update, turning a panic of the CRDT into an error.
+fn apply(document: &mut CollaborativeDocument, update: &[u8]) -> Result<(), String> {
+    Update::decode_v1(update).map_err(|e| format!("update fails to decode: {}", e))?;
+
+    panic::catch_unwind(AssertUnwindSafe(|| document.apply_update(update)))
+        .map_err(|_| "applying the update panicked".to_string())?
+        .map(|_| ())
+        .map_err(|e| format!("update fails to apply: {}", e))
+}
+
+/// Checks that a document encodes into a state that restores the same document.
+///
+/// # Returns
+///
+/// `None` if the restored document matches, or how it differs
+fn round_trip_divergence(document: &CollaborativeDocument) -> Option<String> {
+    let mut restored = CollaborativeDocument::new();
+    match apply(&mut restored, &document.encode_full_state()) {
+        Ok(()) => difference(document, &restored),
+        Err(e) => Some(format!("its encoded state is invalid, {}", e)),
+    }
+}
+
+/// Compares the state vectors and text content of two documents.
+///
+/// # Returns
+///
+/// `None` if the documents match, or how they differ
+fn difference(expected: &CollaborativeDocument, actual: &CollaborativeDocument) -> Option<String> {
+    let state_vector = |document: &CollaborativeDocument| {
+        StateVector::decode_v1(&document.get_state_vector()).unwrap_or_default()
+    };
+    if state_vector(expected) != state_vector(actual) {
+        return Some("state vectors differ".to_string());
+    }
+    if expected.get_text_content() != actual.get_text_content() {
+        return Some("contents differ".to_string());
+    }
+    None
+}
@@ -0,0 +1,108 @@
+use std::{sync::Arc, time::Duration};
+
+use tracing::{info, warn};
+use volo_http::{
+    context::ServerContext,
+    http::StatusCode,
+    server::{layer::TimeoutLayer, Server},
+    Address,
+};
+use yjs_collaboration_server_adapter::{
+    admission::AdmissionController,
+    http::admin::{
+        AdminAuth, AdminRouter, ConfigControl, MaintenanceControl, MetricsExporter, StandbyControl,
+    },
+    session_registry::SessionRegistry,
+};
+use yjs_collaboration_server_domain::services::document_service::DocumentService;
+
+use crate::container::AppDocumentRepository;
+
+/// Admin server application service
+/// Serves the management routes on a dedicated address (TCP or unix socket),
+/// separate from the public collaboration listeners
+pub struct AdminServer {
+    addr: Address,
+    auth: AdminAuth,
+    document_service: Arc<DocumentService<AppDocumentRepository>>,
+    admission_controller: Arc<AdmissionController>,
+    session_registry: Arc<SessionRegistry>,
+    metrics: Arc<dyn MetricsExporter>,
+    standby: Option<Arc<dyn StandbyControl>>,
+    maintenance: Option<Arc<dyn MaintenanceControl>>,
+    config: Option<Arc<dyn ConfigControl>>,
+}
+
+impl AdminServer {
+    pub fn new(
+        addr: Address,
+        auth: AdminAuth,
+        document_service: Arc<DocumentService<AppDocumentRepository>>,
+        admission_controller: Arc<AdmissionController>,
+        session_registry: Arc<SessionRegistry>,
+        metrics: Arc<dyn MetricsExporter>,
+        standby: Option<Arc<dyn StandbyControl>>,
+    ) -> Self {
+        Self {
+            addr,
+            auth,
+            document_service,
+            admission_controller,
+            session_registry,
+            metrics,
+            standby,
+            maintenance: None,
+            config: None,
+        }
+    }
+
+    /// Schedules graceful document closes through the given control
+    pub fn with_maintenance(mut self, maintenance: Arc<dyn MaintenanceControl>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// Reloads the configuration file through the given control
+    pub fn with_config_control(mut self, config: Arc<dyn ConfigControl>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Timeout handler
+    fn timeout_handler(_: &ServerContext) -> (StatusCode, &'static str) {
+        (StatusCode::INTERNAL_SERVER_ERROR, "Timeout!\n")
+    }
+
+    /// Start the admin server
+    pub async fn start(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.auth.is_enabled() {
+            warn!(
+                "Admin server on {} has no auth token configured; restrict access to it at the \
+                 network level",
+                self.addr
+            );
+        }
+
+        let mut admin_router = AdminRouter::new(
+            self.document_service,
+            self.admission_controller,
+            self.session_registry,
+            self.metrics,
+            self.standby,
+            self.maintenance,
+            self.auth,
+        );
+        if let Some(config) = self.config {
+            admin_router = admin_router.with_config_control(config);
+        }
+
+        let app = admin_router.build_router().layer(TimeoutLayer::new(
+            Duration::from_secs(30),
+            Self::timeout_handler,
+        ));
+
+        info!("Starting admin server on {}", self.addr);
+
+        Server::new(app).run(self.addr).await
+    }
+}
@@ -1,5 +1,6 @@
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 
+use futures::future::try_join_all;
 use tracing::info;
 use volo_http::{
     context::ServerContext,
@@ -7,26 +8,49 @@ use volo_http::{
     server::{layer::TimeoutLayer, Server},
     Address,
 };
-
-use yjs_collaboration_server_adapter::http::router;
+use yjs_collaboration_server_adapter::{
+    admission::AdmissionController,
+    http::{
+        cors::{CorsLayer, OriginPolicy},
+        router::{self, RouteGroup},
+    },
+    session_registry::SessionRegistry,
+};
 use yjs_collaboration_server_domain::services::document_service::DocumentService;
-use yjs_collaboration_server_infrastructure::adapters::in_memory_document_repository::InMemoryDocumentRepository;
+
+use crate::container::AppDocumentRepository;
+
+/// A single HTTP listen address and the route groups served on it
+#[derive(Clone, Debug)]
+pub struct HttpListener {
+    pub addr: SocketAddr,
+    pub routes: Vec<RouteGroup>,
+}
 
 /// HTTP server application service
 /// Responsible for starting and managing the lifecycle of the HTTP server
 pub struct HttpServer {
-    addr: SocketAddr,
-    document_service: Arc<DocumentService<InMemoryDocumentRepository>>,
+    listeners: Vec<HttpListener>,
+    origins: OriginPolicy,
+    document_service: Arc<DocumentService<AppDocumentRepository>>,
+    admission_controller: Arc<AdmissionController>,
+    session_registry: Arc<SessionRegistry>,
 }
 
 impl HttpServer {
     pub fn new(
-        addr: SocketAddr,
-        document_service: Arc<DocumentService<InMemoryDocumentRepository>>,
+        listeners: Vec<HttpListener>,
+        origins: OriginPolicy,
+        document_service: Arc<DocumentService<AppDocumentRepository>>,
+        admission_controller: Arc<AdmissionController>,
+        session_registry: Arc<SessionRegistry>,
     ) -> Self {
         Self {
-            addr,
+            listeners,
+            origins,
             document_service,
+            admission_controller,
+            session_registry,
         }
     }
 
@@ -35,23 +59,34 @@ impl HttpServer {
         (StatusCode::INTERNAL_SERVER_ERROR, "Timeout!\n")
     }
 
-    /// Start the HTTP server
+    /// Start the HTTP server on every configured listener
     pub async fn start(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        info!("Starting HTTP server on {}", self.addr);
-
         // Create router with dependency injection
-        let http_router = router::HttpRouter::new(self.document_service.clone());
-        let app = http_router.build_router().layer(TimeoutLayer::new(
-            Duration::from_secs(30),
-            Self::timeout_handler,
-        ));
-
-        let addr = Address::from(self.addr);
-
-        Server::new(app)
-            .run(addr)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        let http_router = router::HttpRouter::new(
+            self.document_service.clone(),
+            self.admission_controller.clone(),
+            self.session_registry.clone(),
+        )
+        .with_origins(self.origins.clone());
+
+        let servers = self.listeners.iter().map(|listener| {
+            info!(
+                "Starting HTTP server on {} (routes: {:?})",
+                listener.addr, listener.routes
+            );
+
+            let app = http_router
+                .build_router_for(&listener.routes)
+                .layer(CorsLayer::new(self.origins.clone()))
+                .layer(TimeoutLayer::new(
+                    Duration::from_secs(30),
+                    Self::timeout_handler,
+                ));
+
+            Server::new(app).run(Address::from(listener.addr))
+        });
+
+        try_join_all(servers).await?;
 
         Ok(())
     }
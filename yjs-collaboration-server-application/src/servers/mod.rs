@@ -1,5 +1,7 @@
+pub mod admin_server;
 pub mod http_server;
 pub mod rpc_server;
 
+pub use admin_server::AdminServer;
 pub use http_server::HttpServer;
 pub use rpc_server::RpcServer;
@@ -1,49 +1,119 @@
 use std::{net::SocketAddr, sync::Arc};
 
-use tracing::info;
+use futures::future::try_join_all;
+use tracing::{info, warn};
 use volo_grpc::server::{Server, ServiceBuilder};
-use yjs_collaboration_server_adapter::rpc::collaboration_service::CollaborationServiceImpl;
+use yjs_collaboration_server_adapter::{
+    admission::AdmissionController,
+    outbox::SessionOutbox,
+    rpc::{
+        collaboration_service::{CollaborationServiceImpl, TransactionControl},
+        health_service::HealthServiceImpl,
+        reflection_service::ReflectionServiceImpl,
+    },
+    session_registry::SessionRegistry,
+    transport_compression::TransportCompression,
+};
 use yjs_collaboration_server_common::volo_gen;
 use yjs_collaboration_server_domain::services::document_service::DocumentService;
-use yjs_collaboration_server_infrastructure::adapters::in_memory_document_repository::InMemoryDocumentRepository;
+
+use crate::container::AppDocumentRepository;
 
 /// RPC server application service
 /// Responsible for starting and managing the lifecycle of the gRPC server
 pub struct RpcServer {
-    addr: SocketAddr,
-    document_service: Arc<DocumentService<InMemoryDocumentRepository>>,
+    addrs: Vec<SocketAddr>,
+    document_service: Arc<DocumentService<AppDocumentRepository>>,
+    admission_controller: Arc<AdmissionController>,
+    session_registry: Arc<SessionRegistry>,
+    session_outbox: Arc<SessionOutbox>,
+    replication_token: Option<String>,
+    transport_compression: TransportCompression,
+    /// Use case applying cross-document transactions, or `None` to refuse them
+    transactions: Option<Arc<dyn TransactionControl>>,
 }
 
 impl RpcServer {
     pub fn new(
-        addr: SocketAddr,
-        document_service: Arc<DocumentService<InMemoryDocumentRepository>>,
+        addrs: Vec<SocketAddr>,
+        document_service: Arc<DocumentService<AppDocumentRepository>>,
+        admission_controller: Arc<AdmissionController>,
+        session_registry: Arc<SessionRegistry>,
+        session_outbox: Arc<SessionOutbox>,
+        replication_token: Option<String>,
+        transport_compression: TransportCompression,
     ) -> Self {
         Self {
-            addr,
+            addrs,
             document_service,
+            admission_controller,
+            session_registry,
+            session_outbox,
+            replication_token,
+            transport_compression,
+            transactions: None,
         }
     }
 
-    /// Start the gRPC server
+    /// Serves cross-document transactions through the given use case
+    pub fn with_transactions(mut self, transactions: Arc<dyn TransactionControl>) -> Self {
+        self.transactions = Some(transactions);
+        self
+    }
+
+    /// Start the gRPC server on every configured address
     pub async fn start(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        info!("Starting gRPC server on {}", self.addr);
-
-        // Create collaboration service
-        let collaboration_service = CollaborationServiceImpl::new(self.document_service.clone());
-
-        let addr = volo::net::Address::from(self.addr);
-
-        Server::new()
-            .add_service(
-                ServiceBuilder::new(volo_gen::collaboration::CollaborationServiceServer::new(
-                    collaboration_service,
-                ))
-                .build(),
-            )
-            .run(addr)
-            .await
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        // Create collaboration service, shared by all listeners so sessions and
+        // broadcasts span every address; the session registry is shared with the
+        // HTTP server so WebSocket clients are listed as present too
+        let mut collaboration_service = CollaborationServiceImpl::new(
+            self.document_service.clone(),
+            self.admission_controller.clone(),
+            self.session_registry.clone(),
+            self.session_outbox.clone(),
+        )
+        .with_replication_token(self.replication_token.clone())
+        .with_transport_compression(self.transport_compression);
+        if let Some(transactions) = &self.transactions {
+            collaboration_service = collaboration_service.with_transactions(transactions.clone());
+        }
+
+        // Standard health checking and reflection let load balancers and tools
+        // probe and discover the collaboration service
+        let health_service = HealthServiceImpl::new();
+        let reflection_service = ReflectionServiceImpl::new(volo_gen::FILE_DESCRIPTOR_SET);
+        if reflection_service.is_empty() {
+            warn!("gRPC reflection lists no service, as the server was built without protoc");
+        }
+
+        let servers = self.addrs.iter().map(|addr| {
+            info!("Starting gRPC server on {}", addr);
+
+            Server::new()
+                .add_service(
+                    ServiceBuilder::new(volo_gen::collaboration::CollaborationServiceServer::new(
+                        collaboration_service.clone(),
+                    ))
+                    .build(),
+                )
+                .add_service(
+                    ServiceBuilder::new(volo_gen::grpc::health::v1::HealthServer::new(
+                        health_service.clone(),
+                    ))
+                    .build(),
+                )
+                .add_service(
+                    ServiceBuilder::new(
+                        volo_gen::grpc::reflection::v1alpha::ServerReflectionServer::new(
+                            reflection_service.clone(),
+                        ),
+                    )
+                    .build(),
+                )
+                .run(volo::net::Address::from(*addr))
+        });
+
+        try_join_all(servers).await?;
 
         Ok(())
     }
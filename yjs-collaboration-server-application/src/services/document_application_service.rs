@@ -1,11 +1,33 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
+use async_trait::async_trait;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use yjs_collaboration_server_adapter::{
+    http::admin::MaintenanceControl,
+    rpc::collaboration_service::{TransactionControl, TransactionFailure, TransactionUpdate},
+    session_registry::SessionRegistry,
+};
 use yjs_collaboration_server_domain::{
+    errors::DomainError,
     repositories::document_repository::DocumentRepository,
-    services::document_service::DocumentService
-    ,
+    services::document_service::DocumentService,
+    value_objects::{
+        message::{Notice, NoticeKind, NoticeSeverity},
+        update_origin::UpdateOrigin,
+    },
 };
 
+/// Message shown to the clients of a closing document when the operator gave none.
+const DEFAULT_CLOSING_MESSAGE: &str = "This document is closing for maintenance";
+
+/// Maximum documents a single cross-document transaction may update.
+const MAX_TRANSACTION_DOCUMENTS: usize = 64;
+
 /// Application service implementing complex document use cases and workflows.
 ///
 /// This service acts as an orchestration layer that coordinates multiple domain services
@@ -20,7 +42,12 @@ use yjs_collaboration_server_domain::{
 /// when appropriate.
 pub struct DocumentUseCases<R: DocumentRepository> {
     document_service: Arc<DocumentService<R>>,
-    // Other domain services could be added here for orchestration
+    /// Registry of the connected clients, disconnected from the documents closed
+    sessions: Option<Arc<SessionRegistry>>,
+    /// Documents closing, with the time they close as Unix seconds
+    closing: Arc<Mutex<HashMap<String, i64>>>,
+    /// Serializes cross-document transactions, so they never interleave
+    transactions: tokio::sync::Mutex<()>,
 }
 
 impl<R: DocumentRepository + Send + Sync + 'static> DocumentUseCases<R> {
@@ -36,6 +63,9 @@ impl<R: DocumentRepository + Send + Sync + 'static> DocumentUseCases<R> {
     pub fn new(document_service: Arc<DocumentService<R>>) -> Self {
         Self {
             document_service,
+            sessions: None,
+            closing: Arc::default(),
+            transactions: tokio::sync::Mutex::new(()),
         }
     }
 
@@ -50,9 +80,21 @@ impl<R: DocumentRepository + Send + Sync + 'static> DocumentUseCases<R> {
     /// A new `DocumentUseCases` instance with freshly created domain service
     pub fn with_repository(document_repository: R) -> Self {
         let document_service = Arc::new(DocumentService::new(document_repository));
-        Self {
-            document_service,
-        }
+        Self::new(document_service)
+    }
+
+    /// Disconnects the clients of the documents closed through the given registry.
+    ///
+    /// # Parameters
+    ///
+    /// * `sessions` - Registry of the clients connected over every transport
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentUseCases` disconnecting clients when their document closes
+    pub fn with_session_registry(mut self, sessions: Arc<SessionRegistry>) -> Self {
+        self.sessions = Some(sessions);
+        self
     }
 
     /// Get direct access to the domain service.
@@ -67,4 +109,215 @@ impl<R: DocumentRepository + Send + Sync + 'static> DocumentUseCases<R> {
         self.document_service.clone()
     }
 
+    /// Closes a document after warning its clients, e.g. before a migration.
+    ///
+    /// A `DocumentClosing` notice carrying the closing time is published to the
+    /// clients of the document, which may save their work during the grace
+    /// period. Once it elapses, the clients still connected are disconnected and
+    /// the document is saved to the store and unloaded; it is restored from the
+    /// store when opened again.
+    ///
+    /// # Parameters
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `grace` - Time the clients are given before being disconnected
+    /// * `message` - Message shown to the clients, or `None` for a default one
+    ///
+    /// # Returns
+    ///
+    /// The time the document closes, as Unix seconds; a document already closing
+    /// keeps its earlier time
+    pub fn close_document(&self, doc_id: &str, grace: Duration, message: Option<&str>) -> i64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let closes_at = (now + grace).as_secs() as i64;
+        {
+            let mut closing = self.closing.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(&scheduled) = closing.get(doc_id) {
+                return scheduled;
+            }
+            closing.insert(doc_id.to_string(), closes_at);
+        }
+
+        let notice = Notice::new(
+            NoticeKind::DocumentClosing,
+            NoticeSeverity::Critical,
+            message.unwrap_or(DEFAULT_CLOSING_MESSAGE),
+        )
+        .with_document(doc_id)
+        .with_scheduled_at(closes_at);
+        let warned = self.document_service.publish_notice(notice);
+        info!(
+            "Document '{}' closes in {:?}, notice offered to {} connections",
+            doc_id, grace, warned
+        );
+
+        let document_service = self.document_service.clone();
+        let sessions = self.sessions.clone();
+        let closing = self.closing.clone();
+        let doc_id = doc_id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(grace).await;
+            let disconnected = sessions
+                .as_ref()
+                .map_or(0, |sessions| sessions.kick_all(&doc_id));
+            match document_service.unload_document(&doc_id).await {
+                Ok(unloaded) => info!(
+                    "Document '{}' closed: {} clients disconnected, unloaded: {}",
+                    doc_id, disconnected, unloaded
+                ),
+                Err(e) => warn!(
+                    "Document '{}' closed, {} clients disconnected, but not unloaded: {}",
+                    doc_id, disconnected, e
+                ),
+            }
+            closing
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&doc_id);
+        });
+
+        closes_at
+    }
+
+    /// Applies updates to several documents, all or none, e.g. to keep an index
+    /// document in sync with the content documents it lists.
+    ///
+    /// The updates are applied in order, each like a client update, recorded
+    /// under a key unique to the transaction. If one fails, the updates applied
+    /// before it are reverted, in reverse order, with updates broadcast like any
+    /// other: the content they inserted is deleted and the content they deleted
+    /// is restored. Transactions are serialized with each other, but not with
+    /// the clients editing the same documents: their changes are kept through a
+    /// rollback, though they may observe the updates applied before it.
+    /// Documents the transaction created are left empty rather than deleted, as
+    /// other clients may have joined them since.
+    ///
+    /// # Parameters
+    ///
+    /// * `updates` - The updates, applied in order, at most one per document
+    /// * `origin` - Who sent the updates, and through which transport
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u64>)` - The sequence number of each document once its update was applied, in the
+    ///   order of the updates
+    /// * `Err(TransactionFailure)` - `InvalidArgument` if the transaction is empty or updates a
+    ///   document twice, `LimitExceeded` if it updates too many documents, or the error of the
+    ///   update that failed
+    pub async fn apply_transaction(
+        &self,
+        updates: Vec<TransactionUpdate>,
+        origin: UpdateOrigin<'_>,
+    ) -> Result<Vec<u64>, TransactionFailure> {
+        let refuse = |error: DomainError| TransactionFailure {
+            doc_id: String::new(),
+            error,
+            rolled_back: true,
+        };
+        if updates.is_empty() {
+            return Err(refuse(DomainError::InvalidArgument(
+                "A transaction needs at least one update".to_string(),
+            )));
+        }
+        if updates.len() > MAX_TRANSACTION_DOCUMENTS {
+            return Err(refuse(DomainError::LimitExceeded(format!(
+                "A transaction may update at most {} documents",
+                MAX_TRANSACTION_DOCUMENTS
+            ))));
+        }
+        let mut documents = HashSet::new();
+        if let Some(update) = updates
+            .iter()
+            .find(|update| !documents.insert(update.doc_id.as_str()))
+        {
+            return Err(refuse(DomainError::InvalidArgument(format!(
+                "Document '{}' is updated twice; merge its updates",
+                update.doc_id
+            ))));
+        }
+
+        let _transaction = self.transactions.lock().await;
+        let key = format!("transaction-{}", Uuid::new_v4());
+        let mut applied: Vec<&str> = Vec::with_capacity(updates.len());
+        let mut sequence_numbers = Vec::with_capacity(updates.len());
+        for update in &updates {
+            let outcome = self
+                .document_service
+                .apply_revertible_update(&update.doc_id, &update.update, origin, &key)
+                .await;
+            match outcome {
+                Ok(()) => {
+                    applied.push(&update.doc_id);
+                    sequence_numbers
+                        .push(self.document_service.sequence_number(&update.doc_id).await);
+                }
+                Err(error) => {
+                    warn!(
+                        "Transaction of client '{}' failed on document '{}', rolling back {} \
+                         documents: {}",
+                        origin.client_id,
+                        update.doc_id,
+                        applied.len(),
+                        error
+                    );
+                    // The failed update may have been applied before failing, e.g. to broadcast
+                    applied.push(&update.doc_id);
+                    let rolled_back = self.roll_back(applied, origin, &key).await;
+                    return Err(TransactionFailure {
+                        doc_id: update.doc_id.clone(),
+                        error,
+                        rolled_back,
+                    });
+                }
+            }
+        }
+
+        for doc_id in applied {
+            self.document_service.release_revertible(doc_id, &key).await;
+        }
+        info!(
+            "Transaction of client '{}' updated {} documents",
+            origin.client_id,
+            updates.len()
+        );
+        Ok(sequence_numbers)
+    }
+
+    /// Reverts the updates a transaction applied, last first; returns whether all were.
+    async fn roll_back(&self, applied: Vec<&str>, origin: UpdateOrigin<'_>, key: &str) -> bool {
+        let mut rolled_back = true;
+        for doc_id in applied.into_iter().rev() {
+            let reverted = self
+                .document_service
+                .revert_update(doc_id, origin, key)
+                .await;
+            if let Err(e) = reverted {
+                error!(
+                    "Failed to roll back document '{}' after a failed transaction: {}",
+                    doc_id, e
+                );
+                rolled_back = false;
+            }
+        }
+        rolled_back
+    }
+}
+
+impl<R: DocumentRepository + Send + Sync + 'static> MaintenanceControl for DocumentUseCases<R> {
+    fn schedule_close(&self, doc_id: &str, grace: Duration, message: Option<&str>) -> i64 {
+        self.close_document(doc_id, grace, message)
+    }
+}
+
+#[async_trait]
+impl<R: DocumentRepository + Send + Sync + 'static> TransactionControl for DocumentUseCases<R> {
+    async fn apply_transaction(
+        &self,
+        updates: Vec<TransactionUpdate>,
+        origin: UpdateOrigin<'_>,
+    ) -> Result<Vec<u64>, TransactionFailure> {
+        DocumentUseCases::apply_transaction(self, updates, origin).await
+    }
 }
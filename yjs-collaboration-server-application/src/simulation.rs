@@ -0,0 +1,267 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use yjs_collaboration_server_adapter::session_registry::{Session, SessionRegistry, Transport};
+use yjs_collaboration_server_domain::{
+    repositories::document_repository::DocumentRepository,
+    services::document_service::DocumentService,
+    value_objects::{sync_protocol::SyncProtocolMessage, update_origin::UpdateOrigin},
+};
+use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    Doc, GetString, OffsetKind, Options, ReadTxn, Text, TextRef, Transact, Update,
+};
+
+/// Root name of the shared text edited by the virtual collaborators.
+const TEXT_ROOT: &str = "content";
+
+/// Phrases typed by the virtual collaborators, one per collaborator (cycled).
+const SCRIPTS: [&str; 4] = [
+    "The quick brown fox jumps over the lazy dog. ",
+    "Collaborative editing keeps every replica convergent. ",
+    "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ",
+    "Hello from a simulated collaborator! ",
+];
+
+/// Settings for the protocol debugging simulation.
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    /// Document edited by the virtual collaborators
+    pub doc_id: String,
+    /// Number of virtual collaborators
+    pub collaborators: usize,
+    /// Average delay between two keystrokes of a collaborator
+    pub typing_interval: Duration,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            doc_id: "simulation".to_string(),
+            collaborators: 3,
+            typing_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Scripted multi-user traffic for local protocol debugging.
+///
+/// Each virtual collaborator keeps its own Yrs replica of the document and
+/// edits it in a loop: it joins, pulls the updates it is missing, types its
+/// script at a moving cursor with occasional deletions, then leaves for a few
+/// seconds and rejoins. Updates go through the same domain service as real
+/// clients and are tagged with the collaborator's identifier, so WebSocket and
+/// gRPC clients connected to the document see them as remote edits. Joins and
+/// leaves go through the session registry, so those clients see the
+/// collaborators come and go too.
+pub struct Simulation<R: DocumentRepository> {
+    config: SimulationConfig,
+    document_service: Arc<DocumentService<R>>,
+    sessions: Arc<SessionRegistry>,
+}
+
+impl<R: DocumentRepository + Send + Sync + 'static> Simulation<R> {
+    /// Creates a new simulation.
+    ///
+    /// # Parameters
+    ///
+    /// * `config` - Simulation settings
+    /// * `document_service` - The domain document service the collaborators edit through
+    /// * `sessions` - Registry the collaborators join and leave the document through
+    ///
+    /// # Returns
+    ///
+    /// A new `Simulation` instance
+    pub fn new(
+        config: SimulationConfig,
+        document_service: Arc<DocumentService<R>>,
+        sessions: Arc<SessionRegistry>,
+    ) -> Self {
+        Self {
+            config,
+            document_service,
+            sessions,
+        }
+    }
+
+    /// Spawns one task per virtual collaborator.
+    ///
+    /// # Returns
+    ///
+    /// The handles of the spawned collaborator tasks
+    pub fn spawn(self) -> Vec<JoinHandle<()>> {
+        info!(
+            "Starting simulation with {} virtual collaborators on document '{}'",
+            self.config.collaborators, self.config.doc_id
+        );
+
+        (0..self.config.collaborators)
+            .map(|index| {
+                let collaborator = VirtualCollaborator::new(index);
+                let config = self.config.clone();
+                let document_service = self.document_service.clone();
+                let sessions = self.sessions.clone();
+                tokio::spawn(collaborator.run(config, document_service, sessions))
+            })
+            .collect()
+    }
+}
+
+/// A scripted collaborator with its own replica of the document.
+struct VirtualCollaborator {
+    name: String,
+    client_id: String,
+    script: &'static str,
+    doc: Doc,
+    text: TextRef,
+    cursor: u32,
+    rng: u64,
+}
+
+impl VirtualCollaborator {
+    fn new(index: usize) -> Self {
+        // Index text like JavaScript clients do, so cursors stay valid around remote edits
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        });
+        let text = doc.get_or_insert_text(TEXT_ROOT);
+
+        Self {
+            name: format!("sim-user-{}", index + 1),
+            client_id: format!("simulation-{}", index + 1),
+            script: SCRIPTS[index % SCRIPTS.len()],
+            doc,
+            text,
+            cursor: 0,
+            rng: 0x9E37_79B9_7F4A_7C15 ^ (index as u64 + 1),
+        }
+    }
+
+    /// Runs the join / type / leave loop forever.
+    async fn run<R: DocumentRepository>(
+        mut self,
+        config: SimulationConfig,
+        document_service: Arc<DocumentService<R>>,
+        sessions: Arc<SessionRegistry>,
+    ) {
+        let mut typed = 0usize;
+
+        loop {
+            let session = Session {
+                user_name: self.name.clone(),
+                ..Session::guest(&self.client_id, &config.doc_id, Transport::Server)
+            };
+            if let Err(e) = sessions.join(session) {
+                warn!("[simulation] {} could not join: {}", self.name, e);
+                tokio::time::sleep(Duration::from_secs(1 + self.next_random(5))).await;
+                continue;
+            }
+            info!("[simulation] {} joined '{}'", self.name, config.doc_id);
+
+            let keystrokes = 20 + self.next_random(60);
+            for _ in 0..keystrokes {
+                let jitter = self.next_random(config.typing_interval.as_millis() as u64 + 1);
+                let delay = config.typing_interval / 2 + Duration::from_millis(jitter);
+                tokio::time::sleep(delay).await;
+
+                self.pull(&document_service, &config.doc_id).await;
+                let update = self.edit(typed);
+                typed += 1;
+                sessions.touch(&config.doc_id, &self.client_id);
+
+                if let Err(e) = document_service
+                    .handle_sync_protocol_message(
+                        &config.doc_id,
+                        UpdateOrigin::server(&self.client_id),
+                        SyncProtocolMessage::Update(update),
+                    )
+                    .await
+                {
+                    warn!("[simulation] {} failed to push update: {}", self.name, e);
+                }
+            }
+
+            sessions.leave(&config.doc_id, &self.client_id);
+            let pause = 1 + self.next_random(5);
+            info!(
+                "[simulation] {} left '{}' for {}s",
+                self.name, config.doc_id, pause
+            );
+            tokio::time::sleep(Duration::from_secs(pause)).await;
+        }
+    }
+
+    /// Applies the updates the replica is missing from the server.
+    async fn pull<R: DocumentRepository>(
+        &mut self,
+        document_service: &DocumentService<R>,
+        doc_id: &str,
+    ) {
+        let state_vector = self.doc.transact().state_vector().encode_v1();
+        let update = match document_service
+            .sync_document(doc_id, Some(&state_vector))
+            .await
+        {
+            Ok((update, _)) => update,
+            Err(e) => {
+                warn!("[simulation] {} failed to sync: {}", self.name, e);
+                return;
+            }
+        };
+
+        match Update::decode_v1(&update) {
+            Ok(update) => {
+                if let Err(e) = self.doc.transact_mut().apply_update(update) {
+                    warn!("[simulation] {} failed to apply update: {}", self.name, e);
+                }
+            }
+            Err(e) => warn!("[simulation] {} received a corrupt update: {}", self.name, e),
+        }
+    }
+
+    /// Performs one keystroke and returns the resulting update.
+    fn edit(&mut self, typed: usize) -> Vec<u8> {
+        let before = self.doc.transact().state_vector();
+        let jump_to = (self.next_random(20) == 0).then(|| self.next_random(u64::MAX));
+        let delete = self.next_random(8) == 0;
+
+        {
+            let mut txn = self.doc.transact_mut();
+            let len = self.text.len(&txn);
+
+            // Occasionally move the cursor, as if the user clicked elsewhere
+            if let Some(position) = jump_to {
+                self.cursor = (position % (len as u64 + 1)) as u32;
+            }
+            self.cursor = self.cursor.min(len);
+
+            if self.cursor > 0 && delete {
+                self.cursor -= 1;
+                self.text.remove_range(&mut txn, self.cursor, 1);
+            } else {
+                let index = typed % self.script.len();
+                let chunk = &self.script[index..index + 1];
+                self.text.insert(&mut txn, self.cursor, chunk);
+                self.cursor += 1;
+            }
+        }
+
+        let txn = self.doc.transact();
+        debug!(
+            "[simulation] {} sees {} characters",
+            self.name,
+            self.text.get_string(&txn).chars().count()
+        );
+        txn.encode_state_as_update_v1(&before)
+    }
+
+    /// Returns a pseudo-random number in `0..bound` (xorshift64).
+    fn next_random(&mut self, bound: u64) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng % bound.max(1)
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+use yjs_collaboration_server_adapter::http::admin::StandbyControl;
+use yjs_collaboration_server_common::volo_gen::collaboration::{
+    CollaborationServiceClientBuilder, ReplicateRequest,
+};
+use yjs_collaboration_server_domain::{
+    errors::DomainResult,
+    repositories::{access_control::AccessControl, document_repository::DocumentRepository},
+    services::document_service::DocumentService,
+    value_objects::{
+        access_role::AccessRole,
+        message::{Notice, NoticeKind, NoticeSeverity},
+        update_origin::UpdateOrigin,
+    },
+};
+
+/// Source tag of the updates replicated from the primary.
+const REPLICATION_SOURCE: &str = "replication";
+
+/// Delay before reconnecting to the primary after the stream failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Settings of a warm standby.
+#[derive(Clone, Debug)]
+pub struct StandbyConfig {
+    /// gRPC address of the primary
+    pub primary_addr: SocketAddr,
+    /// Replication token shared with the primary
+    pub token: String,
+    /// Name identifying the standby in the primary's logs
+    pub standby_id: String,
+    /// Time without contact with the primary after which the standby promotes itself
+    /// (`None` waits for a manual promotion)
+    pub failover_timeout: Option<Duration>,
+    /// URL clients of the primary should reconnect to once the standby is promoted
+    pub advertised_url: Option<String>,
+}
+
+/// Warm standby following a primary server.
+///
+/// The standby streams every document update from the primary through the
+/// `Replicate` RPC and applies it locally, so its documents stay current while
+/// its own clients may only read them. Once promoted, by an operator through
+/// the admin API or automatically when the primary stays unreachable for the
+/// failover timeout, it stops following the primary, accepts edits and
+/// announces itself to connected clients with a redirect notice.
+pub struct Standby {
+    config: StandbyConfig,
+    promoted: watch::Sender<bool>,
+}
+
+impl Standby {
+    /// Creates a standby that has not been promoted yet.
+    ///
+    /// # Parameters
+    ///
+    /// * `config` - Standby settings
+    ///
+    /// # Returns
+    ///
+    /// A new `Standby` instance
+    pub fn new(config: StandbyConfig) -> Self {
+        Self {
+            config,
+            promoted: watch::Sender::new(false),
+        }
+    }
+
+    /// Follows the primary until the standby is promoted, then announces the promotion.
+    ///
+    /// # Parameters
+    ///
+    /// * `document_service` - The domain document service replicated updates are applied to
+    pub async fn run<R: DocumentRepository + Send + Sync + 'static>(
+        self: Arc<Self>,
+        document_service: Arc<DocumentService<R>>,
+    ) {
+        let mut promoted = self.promoted.subscribe();
+        if !*promoted.borrow() {
+            tokio::select! {
+                _ = self.follow(&document_service) => {}
+                _ = promoted.wait_for(|promoted| *promoted) => {}
+            }
+        }
+
+        info!(
+            "Standby '{}' promoted to primary, accepting edits",
+            self.config.standby_id
+        );
+
+        let mut notice = Notice::new(
+            NoticeKind::Redirect,
+            NoticeSeverity::Warning,
+            "This server has been promoted to primary",
+        );
+        if let Some(url) = &self.config.advertised_url {
+            notice = notice.with_redirect_url(url);
+        }
+        document_service.publish_notice(notice);
+    }
+
+    /// Replicates the primary's updates, reconnecting after failures, until the failover
+    /// timeout elapses without contact with the primary.
+    async fn follow<R: DocumentRepository + Send + Sync + 'static>(
+        &self,
+        document_service: &DocumentService<R>,
+    ) {
+        let client = CollaborationServiceClientBuilder::new("yjs-collaboration-server")
+            .address(self.config.primary_addr)
+            .build();
+        let mut last_contact = Instant::now();
+
+        loop {
+            info!(
+                "Standby '{}' following primary at {}",
+                self.config.standby_id, self.config.primary_addr
+            );
+
+            let request = ReplicateRequest {
+                standby_id: self.config.standby_id.clone().into(),
+                token: self.config.token.clone().into(),
+            };
+
+            match client.replicate(request).await {
+                Ok(response) => {
+                    let mut stream = response.into_inner();
+                    while let Some(message) = stream.next().await {
+                        let message = match message {
+                            Ok(message) => message,
+                            Err(status) => {
+                                warn!("Replication stream failed: {}", status);
+                                break;
+                            }
+                        };
+                        last_contact = Instant::now();
+
+                        if let Err(e) = document_service
+                            .handle_binary_update(
+                                &message.document_id,
+                                UpdateOrigin::server(REPLICATION_SOURCE),
+                                &message.update_data,
+                            )
+                            .await
+                        {
+                            warn!(
+                                "Failed to apply replicated update to document '{}': {}",
+                                message.document_id, e
+                            );
+                        }
+                    }
+                    debug!("Replication stream closed by the primary");
+                }
+                Err(status) => warn!(
+                    "Failed to reach primary at {}: {}",
+                    self.config.primary_addr, status
+                ),
+            }
+
+            if let Some(timeout) = self.config.failover_timeout {
+                if last_contact.elapsed() >= timeout {
+                    warn!(
+                        "Primary unreachable for {:?}, promoting standby '{}'",
+                        timeout, self.config.standby_id
+                    );
+                    self.promote();
+                    return;
+                }
+            }
+
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+impl StandbyControl for Standby {
+    fn is_standby(&self) -> bool {
+        !*self.promoted.borrow()
+    }
+
+    fn promote(&self) -> bool {
+        self.promoted
+            .send_if_modified(|promoted| !std::mem::replace(promoted, true))
+    }
+}
+
+/// Access control decorator making every document read-only on a standby.
+///
+/// Clients keep the role resolved by the decorated access control once the
+/// standby is promoted; until then, they may join documents but not edit them.
+pub struct StandbyAccessControl {
+    inner: Arc<dyn AccessControl>,
+    standby: Arc<Standby>,
+}
+
+impl StandbyAccessControl {
+    /// Wraps an access control.
+    ///
+    /// # Parameters
+    ///
+    /// * `inner` - The decorated access control
+    /// * `standby` - The standby whose role restricts edits
+    ///
+    /// # Returns
+    ///
+    /// A new `StandbyAccessControl` instance
+    pub fn new(inner: Arc<dyn AccessControl>, standby: Arc<Standby>) -> Self {
+        Self { inner, standby }
+    }
+}
+
+impl AccessControl for StandbyAccessControl {
+    fn role(&self, doc_id: &str, user_id: Option<&str>) -> DomainResult<AccessRole> {
+        let role = self.inner.role(doc_id, user_id)?;
+        if self.standby.is_standby() {
+            return Ok(AccessRole::ReadOnly);
+        }
+        Ok(role)
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sonic_rs::{json, Value};
+use tokio::sync::{broadcast::error::RecvError, Semaphore};
+use tracing::{debug, warn};
+use uuid::Uuid;
+use yjs_collaboration_server_adapter::{
+    clock::server_time,
+    session_registry::{PresenceEvent, Session, SessionRegistry},
+};
+use yjs_collaboration_server_domain::{
+    repositories::document_repository::DocumentRepository,
+    services::document_service::DocumentService,
+    value_objects::{document_event::DocumentEvent, feature_policy::FeaturePolicies},
+};
+
+/// Header carrying the HMAC-SHA256 signature of a delivery's body.
+pub const SIGNATURE_HEADER: &str = "x-yjs-signature";
+
+/// Header naming the delivered event.
+pub const EVENT_HEADER: &str = "x-yjs-event";
+
+/// Header carrying the identifier of a delivery, unchanged across its retries.
+pub const DELIVERY_HEADER: &str = "x-yjs-delivery";
+
+/// Maximum number of requests in flight to webhook targets.
+const MAX_CONCURRENT_DELIVERIES: usize = 32;
+
+/// Interval at which throttled `document.updated` events are delivered.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings of webhook deliveries.
+#[derive(Clone, Debug)]
+pub struct WebhookSettings {
+    /// Secret signing the body of every delivery, or `None` to send them unsigned
+    pub secret: Option<String>,
+    /// Attempts made to deliver an event before giving up
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after every failed attempt
+    pub retry_backoff: Duration,
+    /// Timeout of a single delivery attempt
+    pub timeout: Duration,
+    /// Minimum time between two `document.updated` events of a document (zero = every update)
+    pub update_throttle: Duration,
+}
+
+/// Updates of a document applied since its last `document.updated` event.
+struct PendingUpdates {
+    /// Earliest time the next event may be delivered
+    next_delivery: Instant,
+    /// Number of updates applied
+    updates: u64,
+    /// Clients that applied them
+    sources: BTreeSet<String>,
+}
+
+/// Delivers document lifecycle and presence events to webhook targets.
+///
+/// Every event is POSTed as JSON to the webhook targets of its document's
+/// feature policy, so a namespace can notify its own search indexer or audit
+/// trail. `document.updated` events are throttled per document: the first
+/// update is delivered right away, and those applied during the throttle
+/// period are summarized in a single event once it elapses. Failed deliveries
+/// are retried with an exponential backoff; events are not persisted and are
+/// lost when the server stops.
+pub struct WebhookDispatcher {
+    settings: WebhookSettings,
+    client: reqwest::Client,
+    deliveries: Semaphore,
+    /// Policies of a reloaded configuration, whose targets replace those of the document service
+    targets: RwLock<Option<FeaturePolicies>>,
+}
+
+impl WebhookDispatcher {
+    /// Creates a dispatcher.
+    ///
+    /// # Parameters
+    ///
+    /// * `settings` - Webhook delivery settings
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(WebhookDispatcher)` - The dispatcher
+    /// * `Err(String)` - Error message if the HTTP client cannot be created
+    pub fn new(settings: WebhookSettings) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(settings.timeout)
+            .build()
+            .map_err(|e| format!("Failed to create the webhook client: {}", e))?;
+
+        Ok(Self {
+            settings,
+            client,
+            deliveries: Semaphore::new(MAX_CONCURRENT_DELIVERIES),
+            targets: RwLock::new(None),
+        })
+    }
+
+    /// Replaces the webhook targets of the documents, e.g. after the configuration was reloaded.
+    ///
+    /// Only the targets of the given policies are used; their other settings are still
+    /// enforced by the document service as configured on startup.
+    ///
+    /// # Parameters
+    ///
+    /// * `policies` - The feature policies holding the targets of every namespace
+    pub fn set_targets(&self, policies: FeaturePolicies) {
+        *self
+            .targets
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(policies);
+    }
+
+    /// Delivers the events of the documents and of the clients present on them until the
+    /// document service shuts down.
+    ///
+    /// # Parameters
+    ///
+    /// * `document_service` - The domain document service publishing lifecycle events
+    /// * `sessions` - Registry of the clients present on each document, over any transport
+    pub async fn run<R: DocumentRepository + Send + Sync + 'static>(
+        self: Arc<Self>,
+        document_service: Arc<DocumentService<R>>,
+        sessions: Arc<SessionRegistry>,
+    ) {
+        let mut events = document_service.subscribe_events();
+        let mut presence = sessions.subscribe();
+        let mut pending: HashMap<String, PendingUpdates> = HashMap::new();
+        let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(DocumentEvent::Updated { doc_id, source }) => {
+                        self.throttle_update(&document_service, &mut pending, doc_id, source);
+                    }
+                    Ok(DocumentEvent::Annotated { doc_id, source, labels }) => {
+                        let data = json!({ "client_id": source, "labels": labels });
+                        self.dispatch(&document_service, &doc_id, "document.annotated", data);
+                    }
+                    Ok(event) => {
+                        if let DocumentEvent::Deleted { doc_id } = &event {
+                            pending.remove(doc_id);
+                        }
+                        self.dispatch(&document_service, event.doc_id(), event.name(), json!({}));
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Webhooks missed {} document events", skipped);
+                    }
+                    // The document service is shutting down
+                    Err(RecvError::Closed) => break,
+                },
+                event = presence.recv() => match event {
+                    Ok(PresenceEvent::Joined(session)) => self.dispatch(
+                        &document_service,
+                        &session.document_id,
+                        "user.joined",
+                        session_data(&session),
+                    ),
+                    Ok(PresenceEvent::Left(session)) => self.dispatch(
+                        &document_service,
+                        &session.document_id,
+                        "user.left",
+                        session_data(&session),
+                    ),
+                    // A kicked client's departure was dispatched as it left
+                    Ok(PresenceEvent::PermissionChanged { .. } | PresenceEvent::Kicked(_)) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Webhooks missed {} presence events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = flush.tick() => self.flush_updates(&document_service, &mut pending),
+            }
+        }
+    }
+
+    /// Delivers an update right away unless the document's previous `document.updated` event
+    /// is more recent than the throttle period, in which case it is summarized in the next one.
+    fn throttle_update<R: DocumentRepository + Send + Sync + 'static>(
+        self: &Arc<Self>,
+        document_service: &DocumentService<R>,
+        pending: &mut HashMap<String, PendingUpdates>,
+        doc_id: String,
+        source: String,
+    ) {
+        if let Some(updates) = pending.get_mut(&doc_id) {
+            updates.updates += 1;
+            updates.sources.insert(source);
+            return;
+        }
+
+        let data = json!({ "updates": 1, "clients": [source] });
+        self.dispatch(document_service, &doc_id, "document.updated", data);
+        if !self.settings.update_throttle.is_zero() {
+            pending.insert(
+                doc_id,
+                PendingUpdates {
+                    next_delivery: Instant::now() + self.settings.update_throttle,
+                    updates: 0,
+                    sources: BTreeSet::new(),
+                },
+            );
+        }
+    }
+
+    /// Delivers the updates summarized for the documents whose throttle period elapsed.
+    fn flush_updates<R: DocumentRepository + Send + Sync + 'static>(
+        self: &Arc<Self>,
+        document_service: &DocumentService<R>,
+        pending: &mut HashMap<String, PendingUpdates>,
+    ) {
+        let now = Instant::now();
+        pending.retain(|doc_id, updates| {
+            if updates.next_delivery > now {
+                return true;
+            }
+            // Documents left untouched for a whole period are delivered right away again
+            if updates.updates == 0 {
+                return false;
+            }
+
+            let sources = std::mem::take(&mut updates.sources);
+            let data = json!({ "updates": updates.updates, "clients": sources });
+            self.dispatch(document_service, doc_id, "document.updated", data);
+            updates.updates = 0;
+            updates.next_delivery = now + self.settings.update_throttle;
+            true
+        });
+    }
+
+    /// Sends an event to the webhook targets of its document in the background.
+    fn dispatch<R: DocumentRepository + Send + Sync + 'static>(
+        self: &Arc<Self>,
+        document_service: &DocumentService<R>,
+        doc_id: &str,
+        event: &'static str,
+        data: Value,
+    ) {
+        let policy = match self
+            .targets
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+        {
+            Some(policies) => policies.resolve(doc_id),
+            None => document_service.document_policy(doc_id),
+        };
+        let targets = policy.webhook_targets.clone();
+        if targets.is_empty() {
+            return;
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let payload = json!({
+            "id": id,
+            "event": event,
+            "doc_id": doc_id,
+            "timestamp": server_time(),
+            "data": data,
+        });
+        let body = match sonic_rs::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to serialize webhook event {}: {}", event, e);
+                return;
+            }
+        };
+
+        for url in targets {
+            tokio::spawn(Arc::clone(self).deliver(url, event, id.clone(), body.clone()));
+        }
+    }
+
+    /// Delivers an event to a target, retrying failed attempts with an exponential backoff.
+    ///
+    /// Client errors other than `429 Too Many Requests` are not retried, as the
+    /// target would reject the same request again.
+    async fn deliver(self: Arc<Self>, url: String, event: &'static str, id: String, body: String) {
+        let signature = self.signature(&body);
+        let max_attempts = self.settings.max_attempts.max(1);
+        let mut backoff = self.settings.retry_backoff;
+
+        for attempt in 1..=max_attempts {
+            let mut request = self
+                .client
+                .post(&url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event)
+                .header(DELIVERY_HEADER, &id)
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            let result = {
+                // The semaphore is never closed
+                let _permit = self.deliveries.acquire().await;
+                request.send().await
+            };
+            match result {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Delivered webhook event {} {} to {}", event, id, url);
+                    return;
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    warn!(
+                        "Webhook target {} answered {} to event {} (attempt {}/{})",
+                        url, status, event, attempt, max_attempts
+                    );
+                    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                    {
+                        break;
+                    }
+                }
+                Err(e) => warn!(
+                    "Failed to deliver event {} to webhook target {} (attempt {}/{}): {}",
+                    event, url, attempt, max_attempts, e
+                ),
+            }
+
+            if attempt < max_attempts {
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+            }
+        }
+
+        warn!("Gave up delivering event {} {} to {}", event, id, url);
+    }
+
+    /// Signs a delivery's body with the shared secret.
+    ///
+    /// # Parameters
+    ///
+    /// * `body` - The JSON body of the delivery
+    ///
+    /// # Returns
+    ///
+    /// The `sha256=<hex>` HMAC of the body, or `None` without a secret
+    fn signature(&self, body: &str) -> Option<String> {
+        let secret = self.settings.secret.as_deref().filter(|s| !s.is_empty())?;
+        // HMAC accepts keys of any length
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).ok()?;
+        mac.update(body.as_bytes());
+        Some(format!(
+            "sha256={}",
+            hex::encode(mac.finalize().into_bytes())
+        ))
+    }
+}
+
+/// Describes the client of a presence event.
+fn session_data(session: &Session) -> Value {
+    json!({
+        "client_id": session.client_id,
+        "user_id": session.user_id,
+        "user_name": session.user_name,
+        "transport": session.transport.to_string(),
+    })
+}
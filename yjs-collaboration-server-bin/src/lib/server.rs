@@ -2,12 +2,219 @@
 //
 // This is the main entry point for the Yjs Collaboration Server executable.
 // It initializes the application bootstrap and starts the server.
+//
+// Usage (`server --help` and `server <COMMAND> --help` list every flag):
+//   server [CONFIG FLAGS] [--simulate [DOC_ID]] [--collaborators N]
+//   server check [CONFIG FLAGS]
+//   server conformance [--ws URL | --no-ws] [--grpc ADDR | --no-grpc] [--timeout SECS]
+//   server replay DOC_ID [CONFIG FLAGS]
+//
+// Config flags override the configuration file and the environment variables:
+//   --config PATH, --http-addr ADDR, --grpc-addr ADDR, --admin-addr ADDR,
+//   --log-level LEVEL, --http | --no-http, --grpc | --no-grpc,
+//   --set SECTION.SETTING=VALUE (repeatable, VALUE in YAML)
+//
+// `--simulate` starts scripted virtual collaborators editing DOC_ID (default
+// `simulation`) next to the servers, for testing editor integrations locally.
+//
+// `check` verifies the configuration and the reachability of the configured
+// backends, prints a report and exits non-zero if any check failed.
+//
+// `conformance` runs the protocol conformance scenarios against a running
+// server (by default `ws://127.0.0.1:8080/ws` and `127.0.0.1:8081`), prints
+// the outcome of every scenario and exits non-zero if any failed.
+//
+// `replay` reads the persisted update log of DOC_ID from the configured storage
+// backend, applies it one entry at a time with validation after each step,
+// prints the outcome of every entry and exits non-zero at the first entry that
+// fails to decode or makes the document diverge.
+
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+use yjs_collaboration_server_application::{
+    config::ConfigOverrides, ApplicationBootstrap, ConformanceConfig, ConformanceSuite,
+    SimulationConfig,
+};
+
+/// Yjs Collaboration Server
+#[derive(Parser)]
+#[command(name = "server", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    config: ConfigArgs,
+    /// Starts virtual collaborators editing DOC_ID (default `simulation`) next to the servers
+    #[arg(long, value_name = "DOC_ID", num_args = 0..=1)]
+    simulate: Option<Option<String>>,
+    /// Number of virtual collaborators
+    #[arg(long, value_name = "N", requires = "simulate")]
+    collaborators: Option<usize>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Verifies the configuration and the reachability of the configured backends
+    Check {
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+    /// Runs the protocol conformance scenarios against a running server
+    Conformance(ConformanceArgs),
+    /// Replays the persisted update log of a document, validating each entry
+    Replay {
+        /// Document whose update log is replayed
+        doc_id: String,
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+}
 
-use yjs_collaboration_server_application::ApplicationBootstrap;
+/// Flags overriding the configuration file and the environment variables.
+#[derive(Args)]
+struct ConfigArgs {
+    /// YAML configuration file read instead of `CONFIG_PATH`
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+    /// HTTP server address
+    #[arg(long, value_name = "ADDR")]
+    http_addr: Option<String>,
+    /// gRPC server address
+    #[arg(long, value_name = "ADDR")]
+    grpc_addr: Option<String>,
+    /// Admin server address, enabling the admin server
+    #[arg(long, value_name = "ADDR")]
+    admin_addr: Option<String>,
+    /// Log level
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<String>,
+    /// Enables the HTTP server
+    #[arg(long, overrides_with = "no_http")]
+    http: bool,
+    /// Disables the HTTP server
+    #[arg(long, overrides_with = "http")]
+    no_http: bool,
+    /// Enables the gRPC server
+    #[arg(long, overrides_with = "no_grpc")]
+    grpc: bool,
+    /// Disables the gRPC server
+    #[arg(long, overrides_with = "grpc")]
+    no_grpc: bool,
+    /// Overrides any setting, with a YAML value (repeatable)
+    #[arg(long, value_name = "SECTION.SETTING=VALUE")]
+    set: Vec<String>,
+}
+
+impl ConfigArgs {
+    /// Converts the flags into configuration overrides.
+    fn into_overrides(self) -> ConfigOverrides {
+        ConfigOverrides {
+            config_path: self.config,
+            http_addr: self.http_addr,
+            grpc_addr: self.grpc_addr,
+            admin_addr: self.admin_addr,
+            log_level: self.log_level,
+            enable_http: enablement(self.http, self.no_http),
+            enable_grpc: enablement(self.grpc, self.no_grpc),
+            settings: self.set,
+        }
+    }
+}
+
+/// Flags of the `conformance` command.
+#[derive(Args)]
+struct ConformanceArgs {
+    /// WebSocket endpoint to test (default `ws://127.0.0.1:8080/ws`)
+    #[arg(long, value_name = "URL", conflicts_with = "no_ws")]
+    ws: Option<String>,
+    /// Skips the WebSocket scenarios
+    #[arg(long)]
+    no_ws: bool,
+    /// gRPC address to test (default `127.0.0.1:8081`)
+    #[arg(long, value_name = "ADDR", conflicts_with = "no_grpc")]
+    grpc: Option<String>,
+    /// Skips the gRPC scenarios
+    #[arg(long)]
+    no_grpc: bool,
+    /// Maximum duration of a single scenario, in seconds
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+}
+
+impl ConformanceArgs {
+    /// Converts the flags into the endpoints to test.
+    fn into_config(self) -> ConformanceConfig {
+        let mut config = ConformanceConfig::default();
+        if self.no_ws {
+            config.ws_url = None;
+        } else if self.ws.is_some() {
+            config.ws_url = self.ws;
+        }
+        if self.no_grpc {
+            config.grpc_addr = None;
+        } else if self.grpc.is_some() {
+            config.grpc_addr = self.grpc;
+        }
+        if let Some(secs) = self.timeout {
+            config.timeout = Duration::from_secs(secs);
+        }
+        config
+    }
+}
+
+/// Resolves a pair of enabling and disabling flags.
+///
+/// # Returns
+///
+/// * `Some(bool)` - Whether the feature is enabled, if either flag was given
+/// * `None` - If neither flag was given
+fn enablement(enable: bool, disable: bool) -> Option<bool> {
+    match (enable, disable) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
 
 #[volo::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Check { config }) => {
+            let report = ApplicationBootstrap::check(&config.into_overrides());
+            println!("{}", report);
+            std::process::exit(if report.passed() { 0 } else { 1 });
+        }
+        Some(Command::Conformance(args)) => {
+            let report = ConformanceSuite::new(args.into_config()).run().await;
+            println!("{}", report);
+            std::process::exit(if report.passed() { 0 } else { 1 });
+        }
+        Some(Command::Replay { doc_id, config }) => {
+            let report = ApplicationBootstrap::replay(&doc_id, &config.into_overrides());
+            println!("{}", report);
+            std::process::exit(if report.passed() { 0 } else { 1 });
+        }
+        None => {}
+    }
+
+    let simulation = cli.simulate.map(|doc_id| {
+        let mut simulation = SimulationConfig::default();
+        if let Some(doc_id) = doc_id {
+            simulation.doc_id = doc_id;
+        }
+        if let Some(collaborators) = cli.collaborators {
+            simulation.collaborators = collaborators;
+        }
+        simulation
+    });
+
     // Create and run the application bootstrap
-    let bootstrap = ApplicationBootstrap::new();
+    let mut bootstrap = ApplicationBootstrap::try_with_overrides(cli.config.into_overrides())?;
+    if let Some(simulation) = simulation {
+        bootstrap = bootstrap.with_simulation(simulation);
+    }
     bootstrap.run().await
 }
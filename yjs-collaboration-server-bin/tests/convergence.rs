@@ -0,0 +1,63 @@
+// Convergence of concurrent edits
+//
+// Simulated clients edit a shared text at random through the server's
+// protocols; every replica, and the server's document, must end up identical.
+
+mod support;
+
+use std::time::Duration;
+
+use support::{
+    assert_converged, edit_in_turns, spawn_clients, unique_doc_id, SimulatedClient, TestServer,
+    TransportKind,
+};
+
+/// Maximum time the replicas take to converge once the edits stop
+const CONVERGENCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+async fn converge(kinds: &[TransportKind], clients: usize, rounds: usize) {
+    let server = TestServer::start().await;
+    let doc_id = unique_doc_id("convergence");
+
+    let mut clients = spawn_clients(&server, &doc_id, kinds, clients).await;
+    edit_in_turns(&mut clients, rounds).await;
+    assert_converged(&server, &doc_id, &mut clients, CONVERGENCE_TIMEOUT).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn grpc_clients_converge() {
+    converge(&[TransportKind::Grpc], 5, 20).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_websocket_clients_converge() {
+    converge(&[TransportKind::BinaryWebSocket], 5, 20).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn json_websocket_clients_converge() {
+    converge(&[TransportKind::JsonWebSocket], 5, 20).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_of_every_protocol_converge() {
+    converge(&TransportKind::ALL, 9, 20).await;
+}
+
+/// Clients joining while others edit receive the changes made before them.
+#[tokio::test(flavor = "multi_thread")]
+async fn late_joiners_converge() {
+    let server = TestServer::start().await;
+    let doc_id = unique_doc_id("late-joiners");
+
+    let mut clients = spawn_clients(&server, &doc_id, &TransportKind::ALL, 3).await;
+    edit_in_turns(&mut clients, 10).await;
+
+    for (seed, kind) in TransportKind::ALL.into_iter().enumerate() {
+        let seed = (clients.len() + seed) as u64;
+        clients.push(SimulatedClient::join(&server, kind, &doc_id, seed).await);
+    }
+    edit_in_turns(&mut clients, 10).await;
+
+    assert_converged(&server, &doc_id, &mut clients, CONVERGENCE_TIMEOUT).await;
+}
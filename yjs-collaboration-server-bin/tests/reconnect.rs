@@ -0,0 +1,87 @@
+// Reconnection of clients
+//
+// Clients disconnect, keep editing their replica offline while the others
+// edit too, then join again with their replica; both sides receive the
+// changes they missed and every replica converges.
+
+mod support;
+
+use std::time::Duration;
+
+use support::{
+    assert_converged, edit_in_turns, spawn_clients, unique_doc_id, TestServer, TransportKind,
+};
+
+/// Maximum time the replicas take to converge once the edits stop
+const CONVERGENCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Disconnects the first client, edits on both sides, and reconnects it over
+/// the given protocol.
+async fn reconnect(kind: TransportKind, offline_edits: usize) {
+    let server = TestServer::start().await;
+    let doc_id = unique_doc_id("reconnect");
+
+    let mut clients = spawn_clients(&server, &doc_id, &[kind, TransportKind::Grpc], 3).await;
+    edit_in_turns(&mut clients, 5).await;
+    assert_converged(&server, &doc_id, &mut clients, CONVERGENCE_TIMEOUT).await;
+
+    let mut offline = clients.remove(0).disconnect().await;
+    for _ in 0..offline_edits {
+        offline.random_edit();
+    }
+    edit_in_turns(&mut clients, 5).await;
+
+    clients.push(offline.reconnect(&server, kind, &doc_id).await);
+    edit_in_turns(&mut clients, 5).await;
+    assert_converged(&server, &doc_id, &mut clients, CONVERGENCE_TIMEOUT).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn grpc_client_catches_up_on_reconnect() {
+    reconnect(TransportKind::Grpc, 0).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn grpc_client_uploads_offline_edits_on_reconnect() {
+    reconnect(TransportKind::Grpc, 10).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_websocket_client_catches_up_on_reconnect() {
+    reconnect(TransportKind::BinaryWebSocket, 0).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_websocket_client_uploads_offline_edits_on_reconnect() {
+    reconnect(TransportKind::BinaryWebSocket, 10).await;
+}
+
+/// The JSON protocol does not ask for the client's missing changes, so only
+/// catching up is covered.
+#[tokio::test(flavor = "multi_thread")]
+async fn json_websocket_client_catches_up_on_reconnect() {
+    reconnect(TransportKind::JsonWebSocket, 0).await;
+}
+
+/// Clients repeatedly dropping and rejoining while the others edit still
+/// converge with them.
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_rejoining_repeatedly_converge() {
+    let server = TestServer::start().await;
+    let doc_id = unique_doc_id("rejoin");
+
+    let mut clients = spawn_clients(&server, &doc_id, &TransportKind::ALL, 6).await;
+    for round in 0..5 {
+        let index = round % clients.len();
+        let client = clients.remove(index);
+        let kind = client.kind;
+        let mut offline = client.disconnect().await;
+        if kind != TransportKind::JsonWebSocket {
+            offline.random_edit();
+        }
+        edit_in_turns(&mut clients, 3).await;
+        clients.insert(index, offline.reconnect(&server, kind, &doc_id).await);
+    }
+
+    assert_converged(&server, &doc_id, &mut clients, CONVERGENCE_TIMEOUT).await;
+}
@@ -0,0 +1,344 @@
+// Test support for the integration tests
+//
+// Boots the HTTP and gRPC servers in-process on ephemeral ports, with the
+// in-memory repository, and connects simulated clients applying random edits
+// to a shared text through any of the server's protocols.
+//
+// The in-memory repository is shared by every server of a test binary, so
+// each test works on documents of its own, named with `unique_doc_id`.
+
+#![allow(dead_code)]
+
+use std::{
+    net::{SocketAddr, TcpListener},
+    time::{Duration, Instant},
+};
+
+use rand::{distributions::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
+use tokio::{net::TcpStream, task::JoinHandle};
+use yjs_collaboration_server_application::{AppConfig, ApplicationBootstrap};
+use yjs_collaboration_server_client::{
+    BinaryWebSocketTransport, ClientOptions, DocumentSession, GrpcTransport,
+    JsonWebSocketTransport, Transport,
+};
+use yrs::{Doc, GetString, Text, TextRef, Transact, TransactionMut};
+
+/// Name of the shared text edited by the simulated clients
+pub const TEXT_ROOT: &str = "content";
+
+/// Maximum time the servers take to accept connections
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time a client waits for an event before considering itself idle
+const IDLE_WAIT: Duration = Duration::from_millis(50);
+
+/// A session over a transport chosen at runtime
+pub type Session = DocumentSession<Box<dyn Transport>>;
+
+/// Protocol a simulated client speaks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportKind {
+    /// The gRPC `Collaborate` stream
+    Grpc,
+    /// The native `y-websocket` binary protocol
+    BinaryWebSocket,
+    /// The JSON protocol over WebSocket
+    JsonWebSocket,
+}
+
+impl TransportKind {
+    /// Every protocol, in the order clients cycle through them
+    pub const ALL: [TransportKind; 3] = [Self::Grpc, Self::BinaryWebSocket, Self::JsonWebSocket];
+}
+
+/// Returns a document identifier no other test uses.
+pub fn unique_doc_id(prefix: &str) -> String {
+    format!("{}-{}", prefix, uuid::Uuid::new_v4())
+}
+
+/// Returns a local address with a port free at the time of the call.
+fn free_addr() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("no free local port")
+}
+
+/// Waits until a server accepts connections on an address.
+async fn wait_for(addr: SocketAddr) {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    while TcpStream::connect(addr).await.is_err() {
+        assert!(
+            Instant::now() < deadline,
+            "server did not listen on {} within {:?}",
+            addr,
+            STARTUP_TIMEOUT
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// The HTTP and gRPC servers running in the test process, stopped on drop.
+pub struct TestServer {
+    /// Address of the HTTP listener, serving the WebSocket endpoint
+    pub http_addr: SocketAddr,
+    /// Address of the gRPC listener
+    pub grpc_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    /// Starts the servers with the default configuration.
+    pub async fn start() -> Self {
+        Self::start_with(AppConfig::default()).await
+    }
+
+    /// Starts the servers with a configuration, whose listen addresses are
+    /// replaced by ephemeral ports and whose admin server is disabled.
+    pub async fn start_with(mut config: AppConfig) -> Self {
+        let (http_addr, grpc_addr) = (free_addr(), free_addr());
+        config.http_addr = http_addr.to_string();
+        config.grpc_addr = grpc_addr.to_string();
+        config.http_listeners.clear();
+        config.grpc_listeners.clear();
+        config.admin.enabled = false;
+
+        let bootstrap = ApplicationBootstrap::from_config(config).expect("invalid configuration");
+        let task = tokio::spawn(async move {
+            if let Err(e) = bootstrap.run().await {
+                panic!("server failed: {}", e);
+            }
+        });
+        wait_for(http_addr).await;
+        wait_for(grpc_addr).await;
+
+        Self {
+            http_addr,
+            grpc_addr,
+            task,
+        }
+    }
+
+    /// Returns the URL of the WebSocket endpoint.
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}/ws", self.http_addr)
+    }
+
+    /// Opens a connection to a document.
+    pub async fn connect(
+        &self,
+        kind: TransportKind,
+        doc_id: &str,
+        options: &ClientOptions,
+    ) -> Box<dyn Transport> {
+        match kind {
+            TransportKind::Grpc => Box::new(
+                GrpcTransport::connect(self.grpc_addr, doc_id, options)
+                    .await
+                    .expect("gRPC connection failed"),
+            ),
+            TransportKind::BinaryWebSocket => Box::new(
+                BinaryWebSocketTransport::connect(&self.ws_url(), doc_id, options)
+                    .await
+                    .expect("binary WebSocket connection failed"),
+            ),
+            TransportKind::JsonWebSocket => Box::new(
+                JsonWebSocketTransport::connect(&self.ws_url(), doc_id, options)
+                    .await
+                    .expect("JSON WebSocket connection failed"),
+            ),
+        }
+    }
+
+    /// Joins a document and returns its current content.
+    pub async fn content(&self, doc_id: &str) -> String {
+        let transport = self
+            .connect(TransportKind::Grpc, doc_id, &ClientOptions::default())
+            .await;
+        let session = Session::join(transport)
+            .await
+            .expect("observer could not join");
+        let doc = session.close().await;
+        let text = doc.get_or_insert_text(TEXT_ROOT);
+        let content = text.get_string(&doc.transact());
+        content
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Makes a random change to a text: inserts a short word at a random position,
+/// or, one time in four, deletes a few characters.
+fn random_change(rng: &mut StdRng, text: &TextRef, txn: &mut TransactionMut) {
+    let len = text.len(txn);
+    if len > 0 && rng.gen_bool(0.25) {
+        let index = rng.gen_range(0..len);
+        let count = rng.gen_range(1..=3).min(len - index);
+        text.remove_range(txn, index, count);
+    } else {
+        let word: String = (0..rng.gen_range(1..=8))
+            .map(|_| rng.sample(Alphanumeric) as char)
+            .collect();
+        let index = rng.gen_range(0..=len);
+        text.insert(txn, index, &word);
+    }
+}
+
+/// A client connected to a document, editing its text at random.
+pub struct SimulatedClient {
+    /// Name of the client, used in assertion messages
+    pub name: String,
+    /// Protocol the client speaks
+    pub kind: TransportKind,
+    session: Session,
+    text: TextRef,
+    rng: StdRng,
+}
+
+impl SimulatedClient {
+    /// Connects a client with an empty replica and joins a document.
+    pub async fn join(server: &TestServer, kind: TransportKind, doc_id: &str, seed: u64) -> Self {
+        OfflineClient {
+            name: format!("client-{}", seed),
+            doc: Doc::new(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+        .reconnect(server, kind, doc_id)
+        .await
+    }
+
+    /// Makes a random change and sends it to the server.
+    pub async fn random_edit(&mut self) {
+        let (rng, text) = (&mut self.rng, &self.text);
+        self.session
+            .edit(|txn| random_change(rng, text, txn))
+            .await
+            .unwrap_or_else(|e| panic!("{} could not send its edit: {}", self.name, e));
+    }
+
+    /// Returns the content of the client's replica.
+    pub fn content(&self) -> String {
+        self.text.get_string(&self.session.doc().transact())
+    }
+
+    /// Applies the events received until none arrives for a short while.
+    pub async fn drain(&mut self) {
+        while let Ok(event) = tokio::time::timeout(IDLE_WAIT, self.session.next_event()).await {
+            if let Err(e) = event {
+                panic!("{} lost its connection: {}", self.name, e);
+            }
+        }
+    }
+
+    /// Closes the connection, keeping the replica to edit it offline.
+    pub async fn disconnect(self) -> OfflineClient {
+        OfflineClient {
+            name: self.name,
+            doc: self.session.close().await,
+            rng: self.rng,
+        }
+    }
+}
+
+/// A disconnected client, editing its replica offline.
+pub struct OfflineClient {
+    /// Name of the client, used in assertion messages
+    pub name: String,
+    doc: Doc,
+    rng: StdRng,
+}
+
+impl OfflineClient {
+    /// Makes a random change to the replica.
+    pub fn random_edit(&mut self) {
+        let text = self.doc.get_or_insert_text(TEXT_ROOT);
+        let mut txn = self.doc.transact_mut();
+        random_change(&mut self.rng, &text, &mut txn);
+    }
+
+    /// Connects again and joins a document with the replica, exchanging the
+    /// changes either side is missing.
+    pub async fn reconnect(
+        self,
+        server: &TestServer,
+        kind: TransportKind,
+        doc_id: &str,
+    ) -> SimulatedClient {
+        let options = ClientOptions::default().with_client_id(self.name.clone());
+        let transport = server.connect(kind, doc_id, &options).await;
+        let text = self.doc.get_or_insert_text(TEXT_ROOT);
+        let session = Session::join_with(transport, self.doc)
+            .await
+            .unwrap_or_else(|e| panic!("{} could not join {}: {}", self.name, doc_id, e));
+
+        SimulatedClient {
+            name: self.name,
+            kind,
+            session,
+            text,
+            rng: self.rng,
+        }
+    }
+}
+
+/// Connects clients to a document, cycling through protocols.
+pub async fn spawn_clients(
+    server: &TestServer,
+    doc_id: &str,
+    kinds: &[TransportKind],
+    count: usize,
+) -> Vec<SimulatedClient> {
+    let mut clients = Vec::with_capacity(count);
+    for (seed, kind) in kinds.iter().cycle().take(count).enumerate() {
+        clients.push(SimulatedClient::join(server, *kind, doc_id, seed as u64).await);
+    }
+    clients
+}
+
+/// Has every client make random edits, in turns.
+pub async fn edit_in_turns(clients: &mut [SimulatedClient], rounds: usize) {
+    for _ in 0..rounds {
+        for client in clients.iter_mut() {
+            client.random_edit().await;
+        }
+    }
+}
+
+/// Waits until every client and the server hold the same content.
+///
+/// # Panics
+///
+/// Panics if the replicas still differ after `timeout`.
+pub async fn assert_converged(
+    server: &TestServer,
+    doc_id: &str,
+    clients: &mut [SimulatedClient],
+    timeout: Duration,
+) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        for client in clients.iter_mut() {
+            client.drain().await;
+        }
+
+        let expected = server.content(doc_id).await;
+        let diverged: Vec<_> = clients
+            .iter()
+            .map(|client| (client.name.as_str(), client.kind, client.content()))
+            .filter(|(_, _, content)| *content != expected)
+            .collect();
+        if diverged.is_empty() {
+            return;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "clients did not converge within {:?}: server has {:?}, diverged clients {:?}",
+            timeout,
+            expected,
+            diverged
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use thiserror::Error;
+
+/// Failure of a client operation.
+///
+/// Only failures of the connection itself are reported as errors; updates the
+/// server rejects are delivered as [`RemoteEvent::Error`](crate::event::RemoteEvent::Error)
+/// and leave the session usable.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum ClientError {
+    /// The server could not be reached or refused the connection
+    #[error("Failed to connect: {0}")]
+    Connection(String),
+    /// The connection was closed, by the server or the network
+    #[error("Connection closed")]
+    Closed,
+    /// The server rejected joining the document, e.g. a subdocument its parent
+    /// does not reference yet
+    #[error("Rejected by the server: {0}")]
+    Rejected(String),
+    /// The server sent a message the client could not decode or apply
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+    /// The transport does not support the operation
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+/// Result of a client operation.
+pub type ClientResult<T> = Result<T, ClientError>;
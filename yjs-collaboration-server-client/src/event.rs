@@ -0,0 +1,50 @@
+/// A client present on the same document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Peer {
+    /// Identifier of the client's connection
+    pub client_id: String,
+    /// Identity of the user, empty for a guest
+    pub user_id: String,
+    /// Display name of the user
+    pub user_name: String,
+}
+
+/// Awareness state of a client, such as its cursor or selection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Awareness {
+    /// Identifier of the client the state belongs to
+    pub client_id: String,
+    /// JSON description of the user (name, color, ...)
+    pub user_info: String,
+    /// JSON awareness state of the client
+    pub state: String,
+}
+
+/// Something that happened on the document, received from the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoteEvent {
+    /// Changes made by other clients, already applied to the session's document
+    Update(Vec<u8>),
+    /// Awareness state of another client
+    Awareness(Awareness),
+    /// A client joined the document
+    UserJoined(Peer),
+    /// A client left the document
+    UserLeft(Peer),
+    /// A notice from the server, such as a maintenance announcement
+    Notice(String),
+    /// A request the server rejected, e.g. an update from a read-only client or one
+    /// exceeding the size limits
+    Error(String),
+}
+
+/// A message received from the server by a transport.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerEvent {
+    /// The server's state vector, asking for the updates it is missing
+    SyncStep1(Vec<u8>),
+    /// The updates missing from the state vector the client sent
+    SyncStep2(Vec<u8>),
+    /// Any other event
+    Remote(RemoteEvent),
+}
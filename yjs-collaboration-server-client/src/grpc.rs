@@ -0,0 +1,204 @@
+use std::{
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use futures::{channel::mpsc, StreamExt};
+use volo_grpc::RecvStream;
+use yjs_collaboration_server_common::volo_gen::collaboration::{
+    client_message, server_message, AwarenessUpdate, ClientMessage,
+    CollaborationServiceClientBuilder, JoinDocument, LeaveDocument, ServerMessage, SyncStep1,
+    SyncStep2, UpdateMessage,
+};
+
+use crate::{
+    error::{ClientError, ClientResult},
+    event::{Awareness, Peer, RemoteEvent, ServerEvent},
+    options::ClientOptions,
+    transport::{ClientRequest, Transport},
+};
+
+/// A gRPC `Collaborate` stream bound to a document.
+///
+/// The client joins the document with its identity when the stream opens, so
+/// its updates are attributed to its user and it is listed among the
+/// document's peers; it does not ask for its own updates to be echoed back.
+pub struct GrpcTransport {
+    client_id: String,
+    user_id: String,
+    doc_id: String,
+    tenant: String,
+    sender: mpsc::UnboundedSender<ClientMessage>,
+    stream: RecvStream<ServerMessage>,
+}
+
+impl GrpcTransport {
+    /// Opens a stream and joins a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the server's gRPC listener, e.g. `127.0.0.1:8081`
+    /// * `doc_id` - Identifier of the document
+    /// * `options` - Identity, metadata and tenant of the client
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(GrpcTransport)` - The stream
+    /// * `Err(ClientError)` - `Connection` if the stream could not be opened
+    pub async fn connect(
+        addr: SocketAddr,
+        doc_id: &str,
+        options: &ClientOptions,
+    ) -> ClientResult<Self> {
+        let client = CollaborationServiceClientBuilder::new("yjs-collaboration-server")
+            .address(addr)
+            .build();
+        let (sender, requests) = mpsc::unbounded();
+        let stream = client
+            .collaborate(requests)
+            .await
+            .map_err(|e| ClientError::Connection(format!("{}: {}", addr, e)))?
+            .into_inner();
+
+        let transport = Self {
+            client_id: options.client_id.clone(),
+            user_id: options.user_id.clone(),
+            doc_id: doc_id.to_string(),
+            tenant: options.tenant.clone(),
+            sender,
+            stream,
+        };
+        transport.send_message(client_message::MessageType::JoinDocument(JoinDocument {
+            user_id: options.user_id.clone().into(),
+            user_name: options.user_name.clone().into(),
+            user_color: options.user_color.clone().into(),
+            user_metadata: options
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.clone().into(), v.clone().into()))
+                .collect(),
+            echo_own_updates: false,
+            accept_compressed_updates: false,
+            accept_encoding: Default::default(),
+            update_encoding: Default::default(),
+        }))?;
+
+        Ok(transport)
+    }
+
+    /// Sends a message on the stream, stamped with the client's clock.
+    fn send_message(&self, message_type: client_message::MessageType) -> ClientResult<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        self.sender
+            .unbounded_send(ClientMessage {
+                client_id: self.client_id.clone().into(),
+                document_id: self.doc_id.clone().into(),
+                timestamp,
+                message_type: Some(message_type),
+                tenant: self.tenant.clone().into(),
+            })
+            .map_err(|_| ClientError::Closed)
+    }
+}
+
+#[async_trait]
+impl Transport for GrpcTransport {
+    async fn send(&mut self, request: ClientRequest) -> ClientResult<()> {
+        let message_type = match request {
+            ClientRequest::SyncStep1(state_vector) => {
+                client_message::MessageType::SyncStep1(SyncStep1 {
+                    state_vector: state_vector.into(),
+                })
+            }
+            ClientRequest::SyncStep2(update) => client_message::MessageType::SyncStep2(SyncStep2 {
+                update_data: update.into(),
+                sequence_number: 0,
+                encoding: Default::default(),
+            }),
+            ClientRequest::Update(update) => client_message::MessageType::Update(UpdateMessage {
+                update_data: update.into(),
+                origin_client_id: self.client_id.clone().into(),
+                sequence_number: 0,
+                dictionary_id: 0,
+                encoding: Default::default(),
+                origin_user_id: Default::default(),
+                origin_kind: Default::default(),
+            }),
+            ClientRequest::Awareness(awareness) => {
+                client_message::MessageType::Awareness(AwarenessUpdate {
+                    client_id: self.client_id.clone().into(),
+                    user_info: awareness.user_info.into(),
+                    awareness_state: awareness.state.into(),
+                    timestamp: 0,
+                })
+            }
+        };
+        self.send_message(message_type)
+    }
+
+    async fn recv(&mut self) -> ClientResult<ServerEvent> {
+        loop {
+            let message = match self.stream.next().await {
+                Some(Ok(message)) => message,
+                Some(Err(status)) => return Err(ClientError::Connection(status.to_string())),
+                None => return Err(ClientError::Closed),
+            };
+
+            let event = match message.message_type {
+                Some(server_message::MessageType::SyncStep1(step1)) => {
+                    ServerEvent::SyncStep1(step1.state_vector.to_vec())
+                }
+                Some(server_message::MessageType::SyncStep2(step2)) => {
+                    ServerEvent::SyncStep2(step2.update_data.to_vec())
+                }
+                Some(server_message::MessageType::SyncResponse(response)) => {
+                    ServerEvent::SyncStep2(response.update_data.to_vec())
+                }
+                Some(server_message::MessageType::Update(update)) => {
+                    ServerEvent::Remote(RemoteEvent::Update(update.update_data.to_vec()))
+                }
+                Some(server_message::MessageType::Awareness(awareness)) => {
+                    ServerEvent::Remote(RemoteEvent::Awareness(Awareness {
+                        client_id: awareness.client_id.to_string(),
+                        user_info: awareness.user_info.to_string(),
+                        state: awareness.awareness_state.to_string(),
+                    }))
+                }
+                Some(server_message::MessageType::UserJoined(joined)) => {
+                    ServerEvent::Remote(RemoteEvent::UserJoined(Peer {
+                        client_id: joined.client_id.to_string(),
+                        user_id: joined.user_id.to_string(),
+                        user_name: joined.user_name.to_string(),
+                    }))
+                }
+                Some(server_message::MessageType::UserLeft(left)) => {
+                    ServerEvent::Remote(RemoteEvent::UserLeft(Peer {
+                        client_id: left.client_id.to_string(),
+                        user_id: left.user_id.to_string(),
+                        user_name: String::new(),
+                    }))
+                }
+                Some(server_message::MessageType::Notice(notice)) => {
+                    ServerEvent::Remote(RemoteEvent::Notice(notice.message.to_string()))
+                }
+                Some(server_message::MessageType::Error(error)) => {
+                    ServerEvent::Remote(RemoteEvent::Error(error.error_message.to_string()))
+                }
+                _ => continue,
+            };
+            return Ok(event);
+        }
+    }
+
+    async fn close(&mut self) {
+        let _ = self.send_message(client_message::MessageType::LeaveDocument(LeaveDocument {
+            user_id: self.user_id.clone().into(),
+        }));
+        self.sender.close_channel();
+    }
+}
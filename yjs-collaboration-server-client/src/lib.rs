@@ -0,0 +1,22 @@
+// Client for the Yjs Collaboration Server
+//
+// This crate provides a typed async client speaking the server's gRPC
+// `Collaborate` stream and its WebSocket JSON and binary protocols, for bots,
+// server-side agents and integration tests.
+
+pub mod error;
+pub mod event;
+pub mod grpc;
+pub mod options;
+pub mod session;
+pub mod transport;
+pub mod websocket;
+
+// Re-export commonly used client types
+pub use error::{ClientError, ClientResult};
+pub use event::{Awareness, Peer, RemoteEvent};
+pub use grpc::GrpcTransport;
+pub use options::ClientOptions;
+pub use session::DocumentSession;
+pub use transport::Transport;
+pub use websocket::{BinaryWebSocketTransport, JsonWebSocketTransport};
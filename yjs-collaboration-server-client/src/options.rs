@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+/// Identity a client presents when joining a document.
+///
+/// The user identity is only sent over gRPC; WebSocket connections are served
+/// as guests, and only carry the metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientOptions {
+    /// Identifier of the client, unique per connection
+    pub client_id: String,
+    /// Identity of the user, empty for a guest
+    pub user_id: String,
+    /// Display name of the user
+    pub user_name: String,
+    /// Display color of the user
+    pub user_color: String,
+    /// Free-form attributes relayed to the other clients, e.g. the device type
+    pub metadata: HashMap<String, String>,
+    /// Tenant the document belongs to, empty for none
+    pub tenant: String,
+}
+
+impl Default for ClientOptions {
+    /// Creates options for a guest with a random client identifier.
+    fn default() -> Self {
+        Self {
+            client_id: uuid::Uuid::new_v4().to_string(),
+            user_id: String::new(),
+            user_name: String::new(),
+            user_color: String::new(),
+            metadata: HashMap::new(),
+            tenant: String::new(),
+        }
+    }
+}
+
+impl ClientOptions {
+    /// Sets the identifier of the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the client, unique per connection
+    ///
+    /// # Returns
+    ///
+    /// The `ClientOptions` with the client identifier set
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = client_id.into();
+        self
+    }
+
+    /// Sets the user the client acts for.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Identity of the user
+    /// * `user_name` - Display name of the user
+    /// * `user_color` - Display color of the user
+    ///
+    /// # Returns
+    ///
+    /// The `ClientOptions` with the user identity set
+    pub fn with_user(
+        mut self,
+        user_id: impl Into<String>,
+        user_name: impl Into<String>,
+        user_color: impl Into<String>,
+    ) -> Self {
+        self.user_id = user_id.into();
+        self.user_name = user_name.into();
+        self.user_color = user_color.into();
+        self
+    }
+
+    /// Attaches an attribute relayed to the other clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Name of the attribute
+    /// * `value` - Value of the attribute
+    ///
+    /// # Returns
+    ///
+    /// The `ClientOptions` with the attribute added
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the tenant the document belongs to.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant` - The tenant
+    ///
+    /// # Returns
+    ///
+    /// The `ClientOptions` with the tenant set
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = tenant.into();
+        self
+    }
+}
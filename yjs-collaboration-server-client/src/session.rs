@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    Doc, ReadTxn, StateVector, Transact, TransactionMut, Update,
+};
+
+use crate::{
+    error::{ClientError, ClientResult},
+    event::{Awareness, RemoteEvent, ServerEvent},
+    transport::{ClientRequest, Transport},
+};
+
+/// A document kept in sync with the server over a transport.
+///
+/// The session holds a local replica of the document: local edits are applied
+/// to it and sent to the server, and remote updates are applied to it as they
+/// are received through [`next_event`](Self::next_event). The replica is only
+/// updated while the caller polls for events, so a session should be polled
+/// continuously, e.g. from its own task.
+pub struct DocumentSession<T: Transport> {
+    doc: Doc,
+    transport: T,
+    pending: VecDeque<RemoteEvent>,
+}
+
+impl<T: Transport> DocumentSession<T> {
+    /// Joins a document with an empty replica.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - A connection bound to the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentSession)` - The session, its replica holding the document's current state
+    /// * `Err(ClientError)` - `Rejected` if the server refused to synchronize the document, or any
+    ///   failure of the transport
+    pub async fn join(transport: T) -> ClientResult<Self> {
+        Self::join_with(transport, Doc::new()).await
+    }
+
+    /// Joins a document with an existing replica, e.g. one edited offline.
+    ///
+    /// The replica receives the changes it is missing; over gRPC and the binary
+    /// WebSocket protocol, the server receives the changes it is missing in
+    /// return.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` - A connection bound to the document
+    /// * `doc` - The replica of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentSession)` - The session, its replica holding the document's current state
+    /// * `Err(ClientError)` - `Rejected` if the server refused to synchronize the document, or any
+    ///   failure of the transport
+    pub async fn join_with(transport: T, doc: Doc) -> ClientResult<Self> {
+        let mut session = Self {
+            doc,
+            transport,
+            pending: VecDeque::new(),
+        };
+
+        let state_vector = session.doc.transact().state_vector().encode_v1();
+        session
+            .transport
+            .send(ClientRequest::SyncStep1(state_vector))
+            .await?;
+
+        loop {
+            match session.transport.recv().await? {
+                ServerEvent::SyncStep2(update) => {
+                    session.apply(&update)?;
+                    return Ok(session);
+                }
+                ServerEvent::SyncStep1(state_vector) => session.answer(&state_vector).await?,
+                ServerEvent::Remote(RemoteEvent::Error(reason)) => {
+                    return Err(ClientError::Rejected(reason))
+                }
+                ServerEvent::Remote(event) => session.pending.push_back(event),
+            }
+        }
+    }
+
+    /// Returns the replica of the document.
+    ///
+    /// Changes made to it directly are not sent to the server; make them
+    /// through [`edit`](Self::edit) instead.
+    ///
+    /// # Returns
+    ///
+    /// A reference to the replica
+    pub fn doc(&self) -> &Doc {
+        &self.doc
+    }
+
+    /// Edits the document and sends the change to the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The edit, made within a transaction of the replica
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the change was sent, or the edit changed nothing
+    /// * `Err(ClientError)` - `Closed` if the connection is closed
+    pub async fn edit<F>(&mut self, f: F) -> ClientResult<()>
+    where
+        F: FnOnce(&mut TransactionMut),
+    {
+        let before = self.doc.transact().state_vector();
+        {
+            let mut txn = self.doc.transact_mut();
+            f(&mut txn);
+        }
+
+        let update = {
+            let txn = self.doc.transact();
+            if txn.state_vector() == before {
+                return Ok(());
+            }
+            txn.encode_state_as_update_v1(&before)
+        };
+        self.transport.send(ClientRequest::Update(update)).await
+    }
+
+    /// Applies an update made elsewhere, e.g. by another replica of the
+    /// client, and sends it to the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The binary Yjs update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was applied and sent
+    /// * `Err(ClientError)` - `Protocol` if the update is invalid, or `Closed` if the connection is
+    ///   closed
+    pub async fn send_update(&mut self, update: Vec<u8>) -> ClientResult<()> {
+        self.apply(&update)?;
+        self.transport.send(ClientRequest::Update(update)).await
+    }
+
+    /// Shares the client's awareness state with the other clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_info` - JSON description of the user (name, color, ...)
+    /// * `state` - JSON awareness state, such as the cursor position
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the state was sent
+    /// * `Err(ClientError)` - `Unsupported` over WebSocket, or `Closed` if the connection is closed
+    pub async fn set_awareness(
+        &mut self,
+        user_info: impl Into<String>,
+        state: impl Into<String>,
+    ) -> ClientResult<()> {
+        self.transport
+            .send(ClientRequest::Awareness(Awareness {
+                client_id: String::new(),
+                user_info: user_info.into(),
+                state: state.into(),
+            }))
+            .await
+    }
+
+    /// Waits for the next event on the document.
+    ///
+    /// Remote updates are applied to the replica before being returned, and
+    /// the server's requests for missing changes are answered transparently.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RemoteEvent)` - The event
+    /// * `Err(ClientError)` - `Protocol` if a remote update could not be applied, or `Closed` if
+    ///   the connection is closed
+    pub async fn next_event(&mut self) -> ClientResult<RemoteEvent> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(event);
+        }
+
+        loop {
+            match self.transport.recv().await? {
+                ServerEvent::SyncStep1(state_vector) => self.answer(&state_vector).await?,
+                ServerEvent::SyncStep2(update)
+                | ServerEvent::Remote(RemoteEvent::Update(update))
+                    if !update.is_empty() =>
+                {
+                    self.apply(&update)?;
+                    return Ok(RemoteEvent::Update(update));
+                }
+                ServerEvent::SyncStep2(_) | ServerEvent::Remote(RemoteEvent::Update(_)) => {}
+                ServerEvent::Remote(event) => return Ok(event),
+            }
+        }
+    }
+
+    /// Leaves the document and closes the connection.
+    ///
+    /// # Returns
+    ///
+    /// The replica of the document
+    pub async fn close(mut self) -> Doc {
+        self.transport.close().await;
+        self.doc
+    }
+
+    /// Applies an update received from the server to the replica, where an
+    /// empty update stands for no change.
+    fn apply(&self, update: &[u8]) -> ClientResult<()> {
+        if update.is_empty() {
+            return Ok(());
+        }
+        let update = Update::decode_v1(update)
+            .map_err(|e| ClientError::Protocol(format!("Corrupt update: {}", e)))?;
+        self.doc
+            .transact_mut()
+            .apply_update(update)
+            .map_err(|e| ClientError::Protocol(format!("Update could not be applied: {}", e)))
+    }
+
+    /// Answers the server's state vector with the changes it is missing.
+    async fn answer(&mut self, state_vector: &[u8]) -> ClientResult<()> {
+        let state_vector = StateVector::decode_v1(state_vector)
+            .map_err(|e| ClientError::Protocol(format!("Corrupt state vector: {}", e)))?;
+        let update = self.doc.transact().encode_state_as_update_v1(&state_vector);
+        self.transport.send(ClientRequest::SyncStep2(update)).await
+    }
+}
@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+
+use crate::{
+    error::ClientResult,
+    event::{Awareness, ServerEvent},
+};
+
+/// A message sent to the server by a transport.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientRequest {
+    /// The client's state vector, asking for the updates it is missing
+    SyncStep1(Vec<u8>),
+    /// The updates the server is missing, answering its `SyncStep1`
+    SyncStep2(Vec<u8>),
+    /// A local change
+    Update(Vec<u8>),
+    /// The client's awareness state
+    Awareness(Awareness),
+}
+
+/// A connection to the server bound to a single document.
+///
+/// Transports translate the requests of a [`DocumentSession`] into the
+/// messages of their protocol, and the server's messages into events; the
+/// session drives the synchronization.
+///
+/// [`DocumentSession`]: crate::session::DocumentSession
+#[async_trait]
+pub trait Transport: Send {
+    /// Sends a message to the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The message to send
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the message was sent
+    /// * `Err(ClientError)` - `Closed` if the connection is closed, or `Unsupported` if the
+    ///   protocol cannot carry the message
+    async fn send(&mut self, request: ClientRequest) -> ClientResult<()>;
+
+    /// Receives the next message from the server.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ServerEvent)` - The message
+    /// * `Err(ClientError)` - `Closed` if the connection is closed, or `Protocol` if the message
+    ///   could not be decoded
+    async fn recv(&mut self) -> ClientResult<ServerEvent>;
+
+    /// Closes the connection.
+    async fn close(&mut self);
+}
+
+/// Lets sessions be opened over a transport chosen at runtime.
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    async fn send(&mut self, request: ClientRequest) -> ClientResult<()> {
+        (**self).send(request).await
+    }
+
+    async fn recv(&mut self) -> ClientResult<ServerEvent> {
+        (**self).recv().await
+    }
+
+    async fn close(&mut self) {
+        (**self).close().await
+    }
+}
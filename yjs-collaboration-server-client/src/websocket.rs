@@ -0,0 +1,263 @@
+use async_trait::async_trait;
+use base64::Engine;
+use futures::{SinkExt, StreamExt};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use sonic_rs::{from_str, json, JsonValueTrait, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use yjs_collaboration_server_domain::{
+    services::document_service::SyncResponse,
+    value_objects::{message::ServerMessage, sync_protocol::SyncProtocolMessage},
+};
+use yrs::{
+    sync::{Message as ProtocolMessage, SyncMessage},
+    updates::{decoder::Decode, encoder::Encode},
+};
+
+use crate::{
+    error::{ClientError, ClientResult},
+    event::{RemoteEvent, ServerEvent},
+    options::ClientOptions,
+    transport::{ClientRequest, Transport},
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Builds the URL of a WebSocket connection bound to a document.
+///
+/// # Arguments
+///
+/// * `url` - The server's WebSocket endpoint, e.g. `ws://127.0.0.1:8080/ws`
+/// * `doc_id` - Identifier of the document
+/// * `options` - Tenant and metadata of the client
+/// * `binary` - Whether the connection speaks the binary protocol
+///
+/// # Returns
+///
+/// The URL, with every parameter percent-encoded
+fn document_url(url: &str, doc_id: &str, options: &ClientOptions, binary: bool) -> String {
+    let encode = |value: &str| utf8_percent_encode(value, NON_ALPHANUMERIC).to_string();
+
+    let mut url = format!("{}?doc={}", url.trim_end_matches('/'), encode(doc_id));
+    if !options.tenant.is_empty() {
+        url.push_str(&format!("&tenant={}", encode(&options.tenant)));
+    }
+    if binary {
+        url.push_str("&format=binary");
+    }
+    for (key, value) in &options.metadata {
+        url.push_str(&format!("&meta.{}={}", encode(key), encode(value)));
+    }
+    url
+}
+
+/// Opens a WebSocket connection.
+async fn connect(url: &str) -> ClientResult<Socket> {
+    let (socket, _) = connect_async(url)
+        .await
+        .map_err(|e| ClientError::Connection(format!("{}: {}", url, e)))?;
+    Ok(socket)
+}
+
+/// Receives the next frame, skipping pings and pongs.
+async fn next_frame(socket: &mut Socket) -> ClientResult<Message> {
+    loop {
+        match socket.next().await {
+            Some(Ok(Message::Close(_))) | None => return Err(ClientError::Closed),
+            Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+            Some(Ok(message)) => return Ok(message),
+            Some(Err(e)) => return Err(ClientError::Connection(e.to_string())),
+        }
+    }
+}
+
+/// A connection speaking the binary `y-websocket` protocol.
+///
+/// The server relays no awareness over this protocol, so sending awareness
+/// fails with `Unsupported`; updates it rejects are reported through auth
+/// `permission-denied` messages, delivered as errors.
+pub struct BinaryWebSocketTransport {
+    socket: Socket,
+}
+
+impl BinaryWebSocketTransport {
+    /// Connects to a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The server's WebSocket endpoint, e.g. `ws://127.0.0.1:8080/ws`
+    /// * `doc_id` - Identifier of the document
+    /// * `options` - Tenant and metadata of the client; the server assigns the client identifier
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(BinaryWebSocketTransport)` - The connection
+    /// * `Err(ClientError)` - `Connection` if the server could not be reached or refused it
+    pub async fn connect(url: &str, doc_id: &str, options: &ClientOptions) -> ClientResult<Self> {
+        let socket = connect(&document_url(url, doc_id, options, true)).await?;
+        Ok(Self { socket })
+    }
+}
+
+#[async_trait]
+impl Transport for BinaryWebSocketTransport {
+    async fn send(&mut self, request: ClientRequest) -> ClientResult<()> {
+        let message = match request {
+            ClientRequest::SyncStep1(state_vector) => SyncProtocolMessage::SyncStep1(state_vector),
+            ClientRequest::SyncStep2(update) => SyncProtocolMessage::SyncStep2(update),
+            ClientRequest::Update(update) => SyncProtocolMessage::Update(update),
+            ClientRequest::Awareness(_) => {
+                return Err(ClientError::Unsupported(
+                    "The binary WebSocket protocol does not relay awareness".to_string(),
+                ))
+            }
+        };
+        self.socket
+            .send(Message::Binary(message.encode()))
+            .await
+            .map_err(|_| ClientError::Closed)
+    }
+
+    async fn recv(&mut self) -> ClientResult<ServerEvent> {
+        loop {
+            let Message::Binary(frame) = next_frame(&mut self.socket).await? else {
+                continue;
+            };
+            let message = ProtocolMessage::decode_v1(&frame)
+                .map_err(|e| ClientError::Protocol(format!("Invalid frame: {}", e)))?;
+
+            return Ok(match message {
+                ProtocolMessage::Sync(SyncMessage::SyncStep1(state_vector)) => {
+                    ServerEvent::SyncStep1(state_vector.encode_v1())
+                }
+                ProtocolMessage::Sync(SyncMessage::SyncStep2(update)) => {
+                    ServerEvent::SyncStep2(update)
+                }
+                ProtocolMessage::Sync(SyncMessage::Update(update)) => {
+                    ServerEvent::Remote(RemoteEvent::Update(update))
+                }
+                ProtocolMessage::Auth(Some(reason)) => {
+                    ServerEvent::Remote(RemoteEvent::Error(reason))
+                }
+                _ => continue,
+            });
+        }
+    }
+
+    async fn close(&mut self) {
+        let _ = self.socket.close(None).await;
+    }
+}
+
+/// A connection speaking the JSON protocol.
+///
+/// Updates and state vectors travel Base64-encoded in JSON messages. The
+/// server never asks a JSON client for its missing updates, so local changes
+/// made before joining are only sent once edited again; it relays no
+/// awareness over this protocol either, so sending awareness fails with
+/// `Unsupported`.
+pub struct JsonWebSocketTransport {
+    socket: Socket,
+    doc_id: String,
+}
+
+impl JsonWebSocketTransport {
+    /// Connects to a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The server's WebSocket endpoint, e.g. `ws://127.0.0.1:8080/ws`
+    /// * `doc_id` - Identifier of the document
+    /// * `options` - Tenant and metadata of the client; the server assigns the client identifier
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JsonWebSocketTransport)` - The connection
+    /// * `Err(ClientError)` - `Connection` if the server could not be reached or refused it
+    pub async fn connect(url: &str, doc_id: &str, options: &ClientOptions) -> ClientResult<Self> {
+        let socket = connect(&document_url(url, doc_id, options, false)).await?;
+        Ok(Self {
+            socket,
+            doc_id: doc_id.to_string(),
+        })
+    }
+
+    /// Returns the `message` of an error or notice, or its whole data.
+    fn describe(message: &ServerMessage) -> String {
+        let Some(data) = &message.data else {
+            return String::new();
+        };
+        match data.get("message").as_str() {
+            Some(text) => text.to_string(),
+            None => data.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for JsonWebSocketTransport {
+    async fn send(&mut self, request: ClientRequest) -> ClientResult<()> {
+        let encode = |payload: &[u8]| base64::engine::general_purpose::STANDARD.encode(payload);
+        let message = match request {
+            ClientRequest::SyncStep1(state_vector) => json!({
+                "type": "sync",
+                "doc_id": self.doc_id,
+                "update": encode(&state_vector),
+            }),
+            ClientRequest::SyncStep2(update) | ClientRequest::Update(update) => json!({
+                "type": "update",
+                "doc_id": self.doc_id,
+                "update": encode(&update),
+            }),
+            ClientRequest::Awareness(_) => {
+                return Err(ClientError::Unsupported(
+                    "The JSON WebSocket protocol does not relay awareness".to_string(),
+                ))
+            }
+        };
+        self.socket
+            .send(Message::Text(message.to_string()))
+            .await
+            .map_err(|_| ClientError::Closed)
+    }
+
+    async fn recv(&mut self) -> ClientResult<ServerEvent> {
+        loop {
+            let Message::Text(text) = next_frame(&mut self.socket).await? else {
+                continue;
+            };
+            let value: Value = from_str(&text)
+                .map_err(|e| ClientError::Protocol(format!("Invalid JSON {:?}: {}", text, e)))?;
+
+            // Sync responses are the only messages without a type
+            if value.get("type").is_none() {
+                let response: SyncResponse = from_str(&text).map_err(|e| {
+                    ClientError::Protocol(format!("Invalid sync response {:?}: {}", text, e))
+                })?;
+                return Ok(ServerEvent::SyncStep2(response.update.unwrap_or_default()));
+            }
+
+            let message: ServerMessage = from_str(&text)
+                .map_err(|e| ClientError::Protocol(format!("Invalid message {:?}: {}", text, e)))?;
+            let event = match message.message_type.as_str() {
+                "update" => {
+                    let encoded = message.update.as_deref().unwrap_or_default();
+                    let update = base64::engine::general_purpose::STANDARD
+                        .decode(encoded)
+                        .map_err(|e| {
+                            ClientError::Protocol(format!("Update is not valid Base64: {}", e))
+                        })?;
+                    RemoteEvent::Update(update)
+                }
+                "notice" => RemoteEvent::Notice(Self::describe(&message)),
+                "error" => RemoteEvent::Error(Self::describe(&message)),
+                _ => continue,
+            };
+            return Ok(ServerEvent::Remote(event));
+        }
+    }
+
+    async fn close(&mut self) {
+        let _ = self.socket.close(None).await;
+    }
+}
@@ -1,3 +1,48 @@
+use std::{env, path::PathBuf, process::Command};
+
+/// Protobuf files served through gRPC server reflection.
+const REFLECTED_IDLS: [&str; 3] = ["collaboration.proto", "health.proto", "reflection.proto"];
+
 fn main() {
     volo_build::ConfigBuilder::default().write().unwrap();
+    write_descriptor_set();
+}
+
+/// Compiles the served protobuf files into a descriptor set for server reflection.
+///
+/// The descriptors are produced by `protoc` (or the compiler named by the
+/// `PROTOC` variable); without one, an empty set is written and reflection
+/// lists no service, while every service keeps working.
+fn write_descriptor_set() {
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("file_descriptor_set.bin");
+    for idl in REFLECTED_IDLS {
+        println!("cargo:rerun-if-changed=../idl/{}", idl);
+    }
+    println!("cargo:rerun-if-changed=volo.yml");
+    println!("cargo:rerun-if-env-changed=PROTOC");
+
+    let protoc = env::var("PROTOC").unwrap_or_else(|_| "protoc".to_string());
+    let compiled = Command::new(&protoc)
+        .arg("--include_imports")
+        .arg(format!("--descriptor_set_out={}", out.display()))
+        .arg("--proto_path=../idl")
+        .args(REFLECTED_IDLS)
+        .status();
+    match compiled {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            println!(
+                "cargo:warning=gRPC reflection disabled: {} exited with {}",
+                protoc, status
+            );
+            std::fs::write(&out, []).unwrap();
+        }
+        Err(e) => {
+            println!(
+                "cargo:warning=gRPC reflection disabled: {} not found ({})",
+                protoc, e
+            );
+            std::fs::write(&out, []).unwrap();
+        }
+    }
 }
@@ -3,3 +3,11 @@ mod r#gen {
 }
 
 pub use r#gen::volo_gen::*;
+
+/// Runtime of the generated messages, for encoding them outside of gRPC.
+pub use pilota;
+
+/// Encoded `FileDescriptorSet` of the served protobuf files, empty if `protoc` was
+/// not available at build time.
+pub static FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/file_descriptor_set.bin"));
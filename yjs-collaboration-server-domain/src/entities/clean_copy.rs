@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use yrs::{
+    block::{ClientID, Prelim},
+    types::{
+        text::{Diff, YChange},
+        xml::XmlIn,
+        Attrs, ToJson,
+    },
+    Any, Array, ArrayPrelim, ArrayRef, Doc, GetString, Map, MapPrelim, MapRef, Out, ReadTxn, Text,
+    TextPrelim, TextRef, Transact, TransactionMut, WriteTxn, Xml, XmlElementPrelim, XmlFragment,
+    XmlFragmentPrelim, XmlFragmentRef, XmlOut, XmlTextPrelim,
+};
+
+/// Client ID owning every change of a clean copy.
+pub const CLEAN_COPY_CLIENT_ID: ClientID = 1;
+
+/// Kind of shared type a root of the source document holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RootKind {
+    Map,
+    Array,
+    Text,
+    XmlFragment,
+}
+
+/// A root's value along with the kind of shared type it holds.
+type TypedRoot = (Out, RootKind);
+
+/// Re-materializes the content of a document into a fresh one.
+///
+/// The copy holds the same shared types with the same content and formatting,
+/// but every change is authored by `CLEAN_COPY_CLIENT_ID` and nothing of the
+/// source's edit history (contributor client IDs, deleted content) survives.
+///
+/// Roots received from clients are not typed on the server, so their kind is
+/// inferred from their content: roots with keys are maps, roots containing
+/// XML nodes are XML fragments, roots made of single characters (and embeds)
+/// are texts, and any other root is an array. Empty roots are dropped.
+///
+/// # Arguments
+///
+/// * `source` - The document to copy
+///
+/// # Returns
+///
+/// A new document holding the source's current content
+pub(crate) fn clean_copy(source: &Doc) -> Doc {
+    let copy = Doc::with_client_id(CLEAN_COPY_CLIENT_ID);
+    let source_txn = source.transact();
+    let mut txn = copy.transact_mut();
+
+    for (name, value) in source_txn.root_refs() {
+        if let Some(kind) = root_kind(&source_txn, &value) {
+            copy_root(&source_txn, name, value, &kind, &mut txn);
+        }
+    }
+
+    drop(txn);
+    copy
+}
+
+/// Restores the content of another document's roots in a transaction.
+///
+/// Roots whose content differs from the source's are emptied, then filled
+/// with a copy of the source's content, and roots missing from the source are
+/// emptied, so the transaction's update brings any replica of the target to
+/// the source's content. Roots holding the same content are left untouched.
+///
+/// # Arguments
+///
+/// * `source` - The document holding the content to restore
+/// * `txn` - A transaction on the document to restore the content into
+///
+/// # Returns
+///
+/// `true` if any root was changed
+pub(crate) fn restore_content(source: &Doc, txn: &mut TransactionMut) -> bool {
+    let source_txn = source.transact();
+    let mut roots: BTreeMap<String, (Option<TypedRoot>, Option<TypedRoot>)> = BTreeMap::new();
+    for (name, value) in source_txn.root_refs() {
+        if let Some(kind) = root_kind(&source_txn, &value) {
+            roots.entry(name.to_string()).or_default().0 = Some((value, kind));
+        }
+    }
+    let current_txn: &TransactionMut = txn;
+    for (name, value) in current_txn.root_refs() {
+        if let Some(kind) = root_kind(current_txn, &value) {
+            roots.entry(name.to_string()).or_default().1 = Some((value, kind));
+        }
+    }
+
+    let mut changed = false;
+    for (name, (source, current)) in roots {
+        if let (Some((source, source_kind)), Some((current, current_kind))) = (&source, &current) {
+            let source_content = root_content(&source_txn, source.clone(), source_kind);
+            if source_content.is_some()
+                && source_content == root_content(txn, current.clone(), current_kind)
+            {
+                continue;
+            }
+        }
+
+        if let Some((current, kind)) = current {
+            clear_root(txn, current, &kind);
+        }
+        if let Some((source, kind)) = source {
+            copy_root(&source_txn, &name, source, &kind, txn);
+        }
+        changed = true;
+    }
+    changed
+}
+
+/// Lists the roots whose content differs between two versions of a document.
+///
+/// # Arguments
+///
+/// * `before` - The document before a change
+/// * `after` - The document after the change
+///
+/// # Returns
+///
+/// The names of the roots added, removed or holding a different content
+pub(crate) fn changed_roots(before: &Doc, after: &Doc) -> BTreeSet<String> {
+    let before_txn = before.transact();
+    let after_txn = after.transact();
+    let mut roots: BTreeMap<String, (Option<TypedRoot>, Option<TypedRoot>)> = BTreeMap::new();
+    for (name, value) in before_txn.root_refs() {
+        if let Some(kind) = root_kind(&before_txn, &value) {
+            roots.entry(name.to_string()).or_default().0 = Some((value, kind));
+        }
+    }
+    for (name, value) in after_txn.root_refs() {
+        if let Some(kind) = root_kind(&after_txn, &value) {
+            roots.entry(name.to_string()).or_default().1 = Some((value, kind));
+        }
+    }
+
+    roots
+        .into_iter()
+        .filter(|(_, roots)| match roots {
+            (Some((before, before_kind)), Some((after, after_kind))) => {
+                root_content(&before_txn, before.clone(), before_kind)
+                    != root_content(&after_txn, after.clone(), after_kind)
+            }
+            (None, None) => false,
+            _ => true,
+        })
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Copies a root of the source document into a root of the same name and kind.
+fn copy_root<T: ReadTxn>(
+    source_txn: &T,
+    name: &str,
+    value: Out,
+    kind: &RootKind,
+    txn: &mut TransactionMut,
+) {
+    match kind {
+        RootKind::Map => {
+            let target = txn.get_or_insert_map(name);
+            let source = match value {
+                Out::YMap(map) => map,
+                Out::UndefinedRef(branch) => MapRef::from(branch),
+                _ => return,
+            };
+            copy_map(source_txn, &source, txn, &target);
+        }
+        RootKind::Array => {
+            let target = txn.get_or_insert_array(name);
+            let source = match value {
+                Out::YArray(array) => array,
+                Out::UndefinedRef(branch) => ArrayRef::from(branch),
+                _ => return,
+            };
+            copy_array(source_txn, &source, txn, &target);
+        }
+        RootKind::Text => {
+            let target = txn.get_or_insert_text(name);
+            let source = match value {
+                Out::YText(text) => text,
+                Out::UndefinedRef(branch) => TextRef::from(branch),
+                _ => return,
+            };
+            copy_text(source_txn, &source, txn, &target);
+        }
+        RootKind::XmlFragment => {
+            let target = txn.get_or_insert_xml_fragment(name);
+            let source = match value {
+                Out::YXmlFragment(fragment) => fragment,
+                Out::UndefinedRef(branch) => XmlFragmentRef::from(branch),
+                _ => return,
+            };
+            copy_xml_children(source_txn, &source, txn, &target);
+        }
+    }
+}
+
+/// Content of a root, compared to find the roots that need restoring.
+#[derive(PartialEq)]
+enum RootContent {
+    /// Maps and arrays, as JSON
+    Json(Any),
+    /// Text chunks with their formatting attributes
+    Text(Vec<(Any, Option<Box<Attrs>>)>),
+    /// XML fragments, serialized with their attributes and formatting
+    Xml(String),
+}
+
+/// Reads the content of a root, or `None` if it does not hold the given kind.
+fn root_content<T: ReadTxn>(txn: &T, value: Out, kind: &RootKind) -> Option<RootContent> {
+    Some(match (kind, value) {
+        (RootKind::Map, Out::UndefinedRef(branch)) => {
+            RootContent::Json(MapRef::from(branch).to_json(txn))
+        }
+        (RootKind::Array, Out::UndefinedRef(branch)) => {
+            RootContent::Json(ArrayRef::from(branch).to_json(txn))
+        }
+        (RootKind::Map, map @ Out::YMap(_)) | (RootKind::Array, map @ Out::YArray(_)) => {
+            RootContent::Json(map.to_json(txn))
+        }
+        (RootKind::Text, Out::YText(text)) => text_content(txn, &text),
+        (RootKind::Text, Out::UndefinedRef(branch)) => text_content(txn, &TextRef::from(branch)),
+        (RootKind::XmlFragment, Out::YXmlFragment(fragment)) => {
+            RootContent::Xml(fragment.get_string(txn))
+        }
+        (RootKind::XmlFragment, Out::UndefinedRef(branch)) => {
+            RootContent::Xml(XmlFragmentRef::from(branch).get_string(txn))
+        }
+        _ => return None,
+    })
+}
+
+fn text_content<T: ReadTxn>(txn: &T, text: &TextRef) -> RootContent {
+    let chunks: Vec<Diff<YChange>> = text.diff(txn, YChange::identity);
+    RootContent::Text(
+        chunks
+            .into_iter()
+            .map(|chunk| (chunk.insert.to_json(txn), chunk.attributes))
+            .collect(),
+    )
+}
+
+/// Removes the whole content of a root.
+fn clear_root(txn: &mut TransactionMut, value: Out, kind: &RootKind) {
+    match (kind, value) {
+        (RootKind::Map, value) => {
+            let map = match value {
+                Out::YMap(map) => map,
+                Out::UndefinedRef(branch) => MapRef::from(branch),
+                _ => return,
+            };
+            let keys: Vec<String> = map.keys(txn).map(str::to_string).collect();
+            for key in keys {
+                map.remove(txn, &key);
+            }
+        }
+        (RootKind::Text, value) => {
+            let text = match value {
+                Out::YText(text) => text,
+                Out::UndefinedRef(branch) => TextRef::from(branch),
+                _ => return,
+            };
+            let len = text.len(txn);
+            text.remove_range(txn, 0, len);
+        }
+        (RootKind::XmlFragment, value) => {
+            let fragment = match value {
+                Out::YXmlFragment(fragment) => fragment,
+                Out::UndefinedRef(branch) => XmlFragmentRef::from(branch),
+                _ => return,
+            };
+            let len = fragment.len(txn);
+            fragment.remove_range(txn, 0, len);
+        }
+        (RootKind::Array, value) => {
+            let array = match value {
+                Out::YArray(array) => array,
+                Out::UndefinedRef(branch) => ArrayRef::from(branch),
+                _ => return,
+            };
+            let len = array.len(txn);
+            array.remove_range(txn, 0, len);
+        }
+    }
+}
+
+/// Infers the kind of a root, or `None` if it is empty or cannot be a root.
+pub(crate) fn root_kind<T: ReadTxn>(txn: &T, value: &Out) -> Option<RootKind> {
+    let branch = match value {
+        Out::YMap(_) => return Some(RootKind::Map),
+        Out::YArray(_) => return Some(RootKind::Array),
+        Out::YText(_) => return Some(RootKind::Text),
+        Out::YXmlFragment(_) => return Some(RootKind::XmlFragment),
+        Out::UndefinedRef(branch) => *branch,
+        _ => return None,
+    };
+
+    if MapRef::from(branch).len(txn) > 0 {
+        return Some(RootKind::Map);
+    }
+
+    let items: Vec<Out> = ArrayRef::from(branch).iter(txn).collect();
+    if items.is_empty() {
+        return None;
+    }
+    if items
+        .iter()
+        .any(|item| matches!(item, Out::YXmlElement(_) | Out::YXmlText(_)))
+    {
+        return Some(RootKind::XmlFragment);
+    }
+
+    // Text content is read back one character per item, embeds as plain values
+    let is_text = items.iter().all(|item| match item {
+        Out::Any(Any::String(s)) => s.chars().count() == 1,
+        Out::Any(_) => true,
+        _ => false,
+    }) && items
+        .iter()
+        .any(|item| matches!(item, Out::Any(Any::String(_))));
+
+    Some(if is_text {
+        RootKind::Text
+    } else {
+        RootKind::Array
+    })
+}
+
+/// Destination of a copied value: a map entry or the end of an array.
+trait Slot {
+    fn put<V: Prelim>(&self, txn: &mut TransactionMut, value: V) -> V::Return;
+}
+
+struct MapSlot<'a> {
+    map: &'a MapRef,
+    key: &'a str,
+}
+
+impl Slot for MapSlot<'_> {
+    fn put<V: Prelim>(&self, txn: &mut TransactionMut, value: V) -> V::Return {
+        self.map.insert(txn, self.key, value)
+    }
+}
+
+struct ArraySlot<'a>(&'a ArrayRef);
+
+impl Slot for ArraySlot<'_> {
+    fn put<V: Prelim>(&self, txn: &mut TransactionMut, value: V) -> V::Return {
+        self.0.push_back(txn, value)
+    }
+}
+
+/// Copies a value, recreating nested shared types in the destination.
+fn copy_value<T: ReadTxn>(source_txn: &T, value: Out, txn: &mut TransactionMut, slot: &impl Slot) {
+    match value {
+        Out::Any(any) => {
+            slot.put(txn, any);
+        }
+        Out::YMap(map) => {
+            let target = slot.put(txn, MapPrelim::default());
+            copy_map(source_txn, &map, txn, &target);
+        }
+        Out::YArray(array) => {
+            let target = slot.put(txn, ArrayPrelim::default());
+            copy_array(source_txn, &array, txn, &target);
+        }
+        Out::YText(text) => {
+            let target = slot.put(txn, TextPrelim::new(""));
+            copy_text(source_txn, &text, txn, &target);
+        }
+        Out::YXmlFragment(fragment) => {
+            let target = slot.put(txn, XmlFragmentPrelim::default());
+            copy_xml_children(source_txn, &fragment, txn, &target);
+        }
+        Out::YXmlElement(element) => {
+            let target = slot.put(txn, XmlElementPrelim::empty(element.tag().clone()));
+            copy_attributes(source_txn, &element, txn, &target);
+            copy_xml_children(source_txn, &element, txn, &target);
+        }
+        Out::YXmlText(text) => {
+            let target = slot.put(txn, XmlTextPrelim::new(""));
+            copy_attributes(source_txn, &text, txn, &target);
+            copy_text(source_txn, &text, txn, &target);
+        }
+        // Subdocuments and untyped values keep their content as plain data
+        other => {
+            slot.put(txn, other.to_json(source_txn));
+        }
+    }
+}
+
+fn copy_map<T: ReadTxn>(
+    source_txn: &T,
+    source: &MapRef,
+    txn: &mut TransactionMut,
+    target: &MapRef,
+) {
+    for (key, value) in source.iter(source_txn) {
+        copy_value(source_txn, value, txn, &MapSlot { map: target, key });
+    }
+}
+
+fn copy_array<T: ReadTxn>(
+    source_txn: &T,
+    source: &ArrayRef,
+    txn: &mut TransactionMut,
+    target: &ArrayRef,
+) {
+    for value in source.iter(source_txn) {
+        copy_value(source_txn, value, txn, &ArraySlot(target));
+    }
+}
+
+/// Copies text content chunk by chunk, keeping formatting attributes and embeds.
+fn copy_text<T: ReadTxn>(
+    source_txn: &T,
+    source: &impl Text,
+    txn: &mut TransactionMut,
+    target: &impl Text,
+) {
+    let chunks: Vec<Diff<YChange>> = source.diff(source_txn, YChange::identity);
+    for chunk in chunks {
+        let attributes: Attrs = chunk.attributes.map(|attrs| *attrs).unwrap_or_default();
+        let index = target.len(txn);
+        match chunk.insert {
+            Out::Any(Any::String(s)) => {
+                target.insert_with_attributes(txn, index, &s, attributes);
+            }
+            Out::Any(any) => {
+                target.insert_embed_with_attributes(txn, index, any, attributes);
+            }
+            other => {
+                let embed = other.to_json(source_txn);
+                target.insert_embed_with_attributes(txn, index, embed, attributes);
+            }
+        }
+    }
+}
+
+fn copy_attributes<T: ReadTxn>(
+    source_txn: &T,
+    source: &impl Xml,
+    txn: &mut TransactionMut,
+    target: &impl Xml,
+) {
+    for (name, value) in source.attributes(source_txn) {
+        target.insert_attribute(txn, name, value);
+    }
+}
+
+fn copy_xml_children<T: ReadTxn>(
+    source_txn: &T,
+    source: &impl XmlFragment,
+    txn: &mut TransactionMut,
+    target: &impl XmlFragment,
+) {
+    for child in source.children(source_txn) {
+        match child {
+            XmlOut::Element(element) => {
+                let copy = target.push_back(txn, XmlElementPrelim::empty(element.tag().clone()));
+                copy_attributes(source_txn, &element, txn, &copy);
+                copy_xml_children(source_txn, &element, txn, &copy);
+            }
+            XmlOut::Text(text) => {
+                let copy = target.push_back(txn, XmlTextPrelim::new(""));
+                copy_attributes(source_txn, &text, txn, &copy);
+                copy_text(source_txn, &text, txn, &copy);
+            }
+            XmlOut::Fragment(fragment) => {
+                // Fragments are only nested as XML input, which reads back as any XML node
+                let copy = target.push_back(txn, XmlIn::from(XmlFragmentPrelim::default()));
+                if let Some(copy) = copy.into_xml_fragment() {
+                    copy_xml_children(source_txn, &fragment, txn, &copy);
+                }
+            }
+        }
+    }
+}
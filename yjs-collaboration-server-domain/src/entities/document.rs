@@ -1,8 +1,34 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex},
+};
+
 use yrs::{
+    types::Delta,
+    undo::Options as UndoOptions,
     updates::{decoder::Decode, encoder::Encode},
-    Doc, GetString, ReadTxn, StateVector, Transact, Update,
+    Any, Array, ArrayRef, DeleteSet, Doc, GetString, Map, MapRef, Observable, Options, Out,
+    ReadTxn, StateVector, Subscription, Text, TextRef, Transact, UndoManager, Update, WriteTxn, ID,
+};
+
+use super::{
+    clean_copy::{changed_roots, clean_copy, restore_content, root_kind, RootKind},
+    update_chunks::split_update,
+};
+use crate::{
+    errors::{DomainError, DomainResult},
+    value_objects::{
+        content_stats::{ContentStats, StructureStats},
+        shared_edit::SharedEdit,
+        text_insertion::TextInsertion,
+        undo_action::UndoAction,
+        update_changes::UpdateChanges,
+    },
 };
 
+/// Root names checked first when extracting the document's text content.
+const PREFERRED_TEXT_ROOTS: [&str; 5] = ["", "content", "text", "body", "document"];
+
 /// Represents a collaborative document that multiple clients can edit simultaneously.
 ///
 /// This entity encapsulates a Yjs document (via Yrs' `Doc`) and provides methods for
@@ -11,6 +37,22 @@ use yrs::{
 /// This is the core domain entity of the collaboration system.
 pub struct CollaborativeDocument {
     pub(crate) doc: Doc,
+    /// Options the document was built with, kept to rebuild it with other settings
+    options: Options,
+    /// Undo stacks of the clients whose changes are tracked, by client ID
+    undo_managers: HashMap<String, UndoManager>,
+    /// Updates that can be reverted on their own, by the key they were recorded under
+    revertible: HashMap<String, RevertibleChange>,
+}
+
+/// What an update recorded as revertible changed.
+struct RevertibleChange {
+    /// Undo stack tracking the update alone, restoring the content it deleted
+    undo: UndoManager,
+    /// State vector before the update
+    before: StateVector,
+    /// State vector once the update and the changes made on its behalf were applied
+    after: StateVector,
 }
 
 impl CollaborativeDocument {
@@ -20,7 +62,13 @@ impl CollaborativeDocument {
     ///
     /// A new `CollaborativeDocument` instance with an initialized Yjs document.
     pub fn new() -> Self {
-        Self { doc: Doc::new() }
+        let options = Options::default();
+        Self {
+            doc: Doc::with_options(options.clone()),
+            options,
+            undo_managers: HashMap::new(),
+            revertible: HashMap::new(),
+        }
     }
 
     /// Retrieves the document's current state vector.
@@ -37,6 +85,34 @@ impl CollaborativeDocument {
         sv.encode_v1()
     }
 
+    /// Checks whether the document holds changes whose dependencies are missing.
+    ///
+    /// Changes received before the changes they build upon are kept pending, and
+    /// missing from the content, until those changes arrive.
+    ///
+    /// # Returns
+    ///
+    /// `true` if some applied changes are still pending.
+    pub fn has_pending_changes(&self) -> bool {
+        self.doc.transact().has_missing_updates()
+    }
+
+    /// Lists the subdocuments the document references.
+    ///
+    /// Subdocuments are nested Yjs documents stored in the document's shared
+    /// types. Only their references are part of the document: their content is
+    /// synchronized separately, as documents of their own.
+    ///
+    /// # Returns
+    ///
+    /// The GUIDs of the referenced subdocuments, sorted.
+    pub fn subdocument_guids(&self) -> Vec<String> {
+        let txn = self.doc.transact();
+        let mut guids: Vec<String> = txn.subdoc_guids().map(|guid| guid.to_string()).collect();
+        guids.sort();
+        guids
+    }
+
     /// Applies an update to the document.
     ///
     /// This method integrates changes from a client into the document.
@@ -48,24 +124,272 @@ impl CollaborativeDocument {
     /// # Returns
     ///
     /// * `Ok(Vec<u8>)` - The document's new state vector after applying the update
-    /// * `Err(String)` - An error message if the update couldn't be applied
-    pub fn apply_update(&mut self, update: &[u8]) -> Result<Vec<u8>, String> {
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update couldn't be applied
+    pub fn apply_update(&mut self, update: &[u8]) -> DomainResult<Vec<u8>> {
         if let Ok(update) = Update::decode_v1(update) {
             let mut txn = self.doc.transact_mut();
 
             // Apply update and handle potential errors
             let result = txn.apply_update(update);
             if let Err(e) = result {
-                return Err(e.to_string());
+                return Err(DomainError::InvalidUpdate(e.to_string()));
             }
 
+            // Commit the transaction before opening a new one to read the state vector
+            drop(txn);
+
             // Get the updated state vector
             Ok(self.get_state_vector())
         } else {
-            Err("Failed to decode update".to_string())
+            Err(DomainError::InvalidUpdate(
+                "Failed to decode update".to_string(),
+            ))
         }
     }
 
+    /// Applies an update to the document in a transaction tagged with its origin.
+    ///
+    /// Undo stacks track the transactions of their client's origin only, so
+    /// tagging an update keeps it out of the other clients' stacks.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - A binary-encoded update
+    /// * `origin` - The origin tagging the transaction, e.g. the sending client's identifier
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The document's new state vector after applying the update
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update couldn't be applied
+    pub fn apply_update_from(&mut self, update: &[u8], origin: &str) -> DomainResult<Vec<u8>> {
+        let update = Update::decode_v1(update)
+            .map_err(|_| DomainError::InvalidUpdate("Failed to decode update".to_string()))?;
+
+        let mut txn = self.doc.transact_mut_with(origin);
+        txn.apply_update(update)
+            .map_err(|e| DomainError::InvalidUpdate(e.to_string()))?;
+        drop(txn);
+
+        Ok(self.get_state_vector())
+    }
+
+    /// Merges updates into a single update.
+    ///
+    /// Applying the merged update is equivalent to applying every update in turn,
+    /// in a single transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `updates` - Binary-encoded updates
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The merged update
+    /// * `Err(DomainError)` - `InvalidUpdate` if an update couldn't be decoded
+    pub fn merge_updates(updates: &[Vec<u8>]) -> DomainResult<Vec<u8>> {
+        yrs::merge_updates_v1(updates).map_err(|e| DomainError::InvalidUpdate(e.to_string()))
+    }
+
+    /// Applies an update from a client, tracking it in the client's undo stack.
+    ///
+    /// The update is applied in a transaction originating from the client, so
+    /// the client's undo stack captures it. The stack tracks every root type the
+    /// document held before the update; changes to root types created by the
+    /// update itself are tracked from the client's next update on.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - A binary-encoded update from the client
+    /// * `client_id` - Identifier of the client the update comes from
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The document's new state vector after applying the update
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update couldn't be applied
+    pub fn apply_tracked_update(
+        &mut self,
+        update: &[u8],
+        client_id: &str,
+    ) -> DomainResult<Vec<u8>> {
+        let update = Update::decode_v1(update)
+            .map_err(|_| DomainError::InvalidUpdate("Failed to decode update".to_string()))?;
+
+        let manager = match self.undo_managers.entry(client_id.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let mut manager = UndoManager::with_options(&self.doc, UndoOptions::default());
+                manager.include_origin(client_id);
+                entry.insert(manager)
+            }
+        };
+        expand_undo_scope(&self.doc, manager);
+
+        let mut txn = self.doc.transact_mut_with(client_id);
+        txn.apply_update(update)
+            .map_err(|e| DomainError::InvalidUpdate(e.to_string()))?;
+        drop(txn);
+
+        Ok(self.get_state_vector())
+    }
+
+    /// Undoes or redoes the last change tracked in a client's undo stack.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the client whose change is undone or redone
+    /// * `action` - Whether to undo or redo the change
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Vec<u8>)` - The binary-encoded update the document changed by
+    /// * `None` - If the client's stack has nothing to undo or redo
+    pub fn undo(&mut self, client_id: &str, action: UndoAction) -> Option<Vec<u8>> {
+        let manager = self.undo_managers.get_mut(client_id)?;
+        let before = self.doc.transact().state_vector();
+
+        let changed = match action {
+            UndoAction::Undo => manager.undo_blocking(),
+            UndoAction::Redo => manager.redo_blocking(),
+        };
+        if !changed {
+            return None;
+        }
+
+        // The diff carries the restored content and the whole delete set, which
+        // includes the deletions made by the undo
+        Some(self.doc.transact().encode_state_as_update_v1(&before))
+    }
+
+    /// Drops a client's undo stack, e.g. once it leaves the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the client
+    pub fn release_undo(&mut self, client_id: &str) {
+        self.undo_managers.remove(client_id);
+    }
+
+    /// Applies an update, recording what it changes so it can be reverted on its own.
+    ///
+    /// The update is applied in a transaction originating from the key, tracked
+    /// by an undo stack of its own, so reverting it leaves the changes other
+    /// clients make in the meantime untouched. Changes made on the update's
+    /// behalf afterwards are recorded with [`seal_revertible`](Self::seal_revertible).
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - A binary-encoded update
+    /// * `key` - Key recording the update, unique among the document's revertible updates
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The document's new state vector after applying the update
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update couldn't be applied
+    pub fn apply_revertible_update(&mut self, update: &[u8], key: &str) -> DomainResult<Vec<u8>> {
+        self.begin_revertible(key, key);
+        let applied = self.apply_update_from(update, key);
+        match applied {
+            Ok(_) => self.seal_revertible(key),
+            Err(_) => self.release_revertible(key),
+        }
+        applied
+    }
+
+    /// Starts recording the changes of an origin's transactions, so they can be reverted on
+    /// their own until [`seal_revertible`](Self::seal_revertible) is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key recording the changes, unique among the document's revertible updates
+    /// * `origin` - The origin tagging the transactions of the changes
+    pub fn begin_revertible(&mut self, key: &str, origin: &str) {
+        let mut undo = UndoManager::with_options(&self.doc, UndoOptions::default());
+        undo.include_origin(origin);
+        expand_undo_scope(&self.doc, &mut undo);
+        let before = self.doc.transact().state_vector();
+
+        self.revertible.insert(
+            key.to_string(),
+            RevertibleChange {
+                undo,
+                after: before.clone(),
+                before,
+            },
+        );
+    }
+
+    /// Extends a revertible update to the changes applied since, e.g. the
+    /// corrections of the text it inserted.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key the update was recorded under
+    pub fn seal_revertible(&mut self, key: &str) {
+        if let Some(change) = self.revertible.get_mut(key) {
+            change.after = self.doc.transact().state_vector();
+        }
+    }
+
+    /// Reverts a revertible update, keeping the changes made since by others.
+    ///
+    /// The content the update deleted is restored through its undo stack, then
+    /// everything it inserted is deleted, including its content in roots it
+    /// created, which the undo stack could not track yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key the update was recorded under
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Vec<u8>))` - The binary-encoded update the document changed by
+    /// * `Ok(None)` - If no update is recorded under the key
+    /// * `Err(DomainError)` - `Internal` if the inserted content couldn't be deleted
+    pub fn revert(&mut self, key: &str) -> DomainResult<Option<Vec<u8>>> {
+        let Some(mut change) = self.revertible.remove(key) else {
+            return Ok(None);
+        };
+        let before = self.doc.transact().state_vector();
+
+        change.undo.undo_blocking();
+
+        let mut inserted = DeleteSet::new();
+        for (client, clock) in change.after.iter() {
+            let start = change.before.get(client);
+            if *clock > start {
+                inserted.insert(ID::new(*client, start), *clock - start);
+            }
+        }
+        if !inserted.is_empty() {
+            // An update without any struct, only deleting the inserted ranges
+            let mut deletion = vec![0];
+            deletion.extend(inserted.encode_v1());
+            let deletion = Update::decode_v1(&deletion)
+                .map_err(|e| DomainError::Internal(format!("Invalid revert: {}", e)))?;
+            self.doc
+                .transact_mut()
+                .apply_update(deletion)
+                .map_err(|e| DomainError::Internal(format!("Failed to revert: {}", e)))?;
+        }
+
+        // The diff carries the restored content and the whole delete set, which
+        // includes the deletions made by the revert
+        let diff = self.doc.transact().encode_state_as_update_v1(&before);
+        // The undo stack unobserves the document when dropped, which needs no
+        // transaction open
+        drop(change);
+        Ok(Some(diff))
+    }
+
+    /// Drops what a revertible update changed, once it no longer needs reverting.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key the update was recorded under
+    pub fn release_revertible(&mut self, key: &str) {
+        self.revertible.remove(key);
+    }
+
     /// Retrieves updates that a client is missing based on its state vector.
     ///
     /// This method computes the difference between the document's current state
@@ -78,21 +402,247 @@ impl CollaborativeDocument {
     /// # Returns
     ///
     /// * `Ok(Vec<u8>)` - Binary-encoded updates the client needs to apply
-    /// * `Err(String)` - An error message if the client state couldn't be processed
-    pub fn get_missing_updates(&self, client_state: &[u8]) -> Result<Vec<u8>, String> {
+    /// * `Err(DomainError)` - `InvalidUpdate` if the client state couldn't be decoded
+    pub fn get_missing_updates(&self, client_state: &[u8]) -> DomainResult<Vec<u8>> {
         if let Ok(sv) = StateVector::decode_v1(client_state) {
             let txn = self.doc.transact();
-            let updates = txn.encode_state_as_update_v2(&sv);
+            let updates = txn.encode_state_as_update_v1(&sv);
             Ok(updates)
         } else {
-            Err("Failed to decode state vector".to_string())
+            Err(DomainError::InvalidUpdate(
+                "Failed to decode state vector".to_string(),
+            ))
         }
     }
 
+    /// Retrieves the updates a client is missing, split into several updates if large.
+    ///
+    /// A diff at or below `threshold` bytes is returned as a single update. A
+    /// larger diff is split at struct boundaries into updates of about
+    /// `chunk_size` bytes, so the changes of a single author may span several
+    /// chunks. Every chunk is a valid update on its own, but the deletions are
+    /// only sent with the last one: chunks are meant to be applied in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_state` - A binary-encoded state vector from the client
+    /// * `threshold` - Size in bytes above which the diff is split
+    /// * `chunk_size` - Target size in bytes of each chunk
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Vec<u8>>)` - Binary-encoded updates the client needs to apply, in order
+    /// * `Err(DomainError)` - `InvalidUpdate` if the client state couldn't be decoded
+    pub fn get_missing_update_chunks(
+        &self,
+        client_state: &[u8],
+        threshold: usize,
+        chunk_size: usize,
+    ) -> DomainResult<Vec<Vec<u8>>> {
+        let client_sv = StateVector::decode_v1(client_state)
+            .map_err(|_| DomainError::InvalidUpdate("Failed to decode state vector".to_string()))?;
+        let diff = self.doc.transact().encode_state_as_update_v1(&client_sv);
+        if diff.len() <= threshold {
+            return Ok(vec![diff]);
+        }
+
+        // The diff was just encoded by the CRDT, so it always splits
+        match split_update(&diff, chunk_size) {
+            Ok(chunks) => Ok(chunks),
+            Err(_) => Ok(vec![diff]),
+        }
+    }
+
+    /// Encodes the complete document state as a single update.
+    ///
+    /// This is equivalent to computing the missing updates for a client with an
+    /// empty state vector, and is used for initial loads and full state recovery.
+    ///
+    /// # Returns
+    ///
+    /// A binary-encoded update containing the whole document.
+    pub fn encode_full_state(&self) -> Vec<u8> {
+        let txn = self.doc.transact();
+        txn.encode_state_as_update_v1(&StateVector::default())
+    }
+
+    /// Encodes a "clean room" copy of the document as a single update.
+    ///
+    /// The document's current content is re-materialized into a fresh document
+    /// owned by a single server client ID, so the update carries neither the
+    /// client IDs of the contributors nor deleted content. It is usually much
+    /// smaller than the full state, but it is a new document: it cannot be
+    /// merged with copies of the original.
+    ///
+    /// # Returns
+    ///
+    /// A binary-encoded update containing the document's current content.
+    pub fn encode_clean_state(&self) -> Vec<u8> {
+        let copy = clean_copy(&self.doc);
+        let txn = copy.transact();
+        txn.encode_state_as_update_v1(&StateVector::default())
+    }
+
+    /// Computes the update restoring the content of a previous state of the document.
+    ///
+    /// The content of the roots that changed since the given state is replaced
+    /// by a copy of their former content, in a transaction authored by the
+    /// document's own client ID on top of its current state. The resulting update
+    /// is a regular change: applied to the document and to the clients' replicas,
+    /// it makes them converge to the former content while keeping the history.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The previous state to restore, encoded as a single update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Vec<u8>))` - The binary-encoded update restoring the former content
+    /// * `Ok(None)` - If the document already holds the former content
+    /// * `Err(DomainError)` - `InvalidUpdate` if the state couldn't be decoded
+    pub fn revert_update(&self, state: &[u8]) -> DomainResult<Option<Vec<u8>>> {
+        let previous = Doc::new();
+        let update = Update::decode_v1(state)
+            .map_err(|_| DomainError::InvalidUpdate("Failed to decode state".to_string()))?;
+        previous
+            .transact_mut()
+            .apply_update(update)
+            .map_err(|e| DomainError::InvalidUpdate(e.to_string()))?;
+
+        let copy = self.working_copy()?;
+        let mut txn = copy.transact_mut();
+        if !restore_content(&previous, &mut txn) {
+            return Ok(None);
+        }
+        Ok(Some(txn.encode_update_v1()))
+    }
+
+    /// Computes the update writing a change into a map, array or text root of the document.
+    ///
+    /// Like reverts, the change is made on a copy of the document, in a
+    /// transaction authored by the document's own client ID, so the document
+    /// only changes once the update is applied. Missing roots are created;
+    /// roots of the right kind are written into whether the server ever typed
+    /// them or not.
+    ///
+    /// # Arguments
+    ///
+    /// * `edit` - The change to write
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Vec<u8>))` - The binary-encoded update making the change
+    /// * `Ok(None)` - If the change leaves the document as is, e.g. removing a missing key
+    /// * `Err(DomainError)` - `Conflict` if the root holds another kind of shared type, or
+    ///   `InvalidArgument` if the values are inserted past the end of the array or the text range
+    ///   is out of bounds
+    pub fn edit_update(&self, edit: &SharedEdit) -> DomainResult<Option<Vec<u8>>> {
+        let copy = self.working_copy()?;
+        let mut txn = copy.transact_mut();
+        let existing = txn
+            .root_refs()
+            .find(|(name, _)| *name == edit.root())
+            .map(|(_, value)| value);
+        let kind = existing.as_ref().and_then(|value| root_kind(&txn, value));
+        let expected = match edit {
+            SharedEdit::SetMapEntry { .. } | SharedEdit::RemoveMapEntry { .. } => RootKind::Map,
+            SharedEdit::InsertArrayItems { .. } => RootKind::Array,
+            SharedEdit::ReplaceText { .. } => RootKind::Text,
+        };
+        if kind.is_some_and(|kind| kind != expected) {
+            return Err(DomainError::Conflict(format!(
+                "Root '{}' holds another kind of shared type",
+                edit.root()
+            )));
+        }
+
+        match edit {
+            SharedEdit::SetMapEntry { root, key, value } => {
+                let map = match existing {
+                    Some(Out::YMap(map)) => map,
+                    Some(Out::UndefinedRef(branch)) => MapRef::from(branch),
+                    _ => txn.get_or_insert_map(root.as_str()),
+                };
+                map.insert(&mut txn, key.as_str(), value.clone());
+            }
+            SharedEdit::RemoveMapEntry { key, .. } => {
+                let map = match existing {
+                    Some(Out::YMap(map)) => map,
+                    Some(Out::UndefinedRef(branch)) => MapRef::from(branch),
+                    _ => return Ok(None),
+                };
+                if map.remove(&mut txn, key).is_none() {
+                    return Ok(None);
+                }
+            }
+            SharedEdit::InsertArrayItems {
+                root,
+                index,
+                values,
+            } => {
+                let array = match existing {
+                    Some(Out::YArray(array)) => array,
+                    Some(Out::UndefinedRef(branch)) => ArrayRef::from(branch),
+                    _ => txn.get_or_insert_array(root.as_str()),
+                };
+                let len = array.len(&txn);
+                let index = index.unwrap_or(len);
+                if index > len {
+                    return Err(DomainError::InvalidArgument(format!(
+                        "Index {} is past the end of array '{}' of {} items",
+                        index, root, len
+                    )));
+                }
+                if values.is_empty() {
+                    return Ok(None);
+                }
+                array.insert_range(&mut txn, index, values.iter().cloned());
+            }
+            SharedEdit::ReplaceText {
+                root,
+                index,
+                len,
+                text,
+            } => {
+                let target = match existing {
+                    Some(Out::YText(target)) => target,
+                    Some(Out::UndefinedRef(branch)) => TextRef::from(branch),
+                    _ => txn.get_or_insert_text(root.as_str()),
+                };
+                let end = index.saturating_add(*len);
+                if end > target.len(&txn) {
+                    return Err(DomainError::InvalidArgument(format!(
+                        "Range {}..{} is past the end of text '{}'",
+                        index, end, root
+                    )));
+                }
+                if *len == 0 && text.is_empty() {
+                    return Ok(None);
+                }
+                target.remove_range(&mut txn, *index, *len);
+                target.insert(&mut txn, *index, text);
+            }
+        }
+        Ok(Some(txn.encode_update_v1()))
+    }
+
+    /// Copies the document under its own client ID, so updates can be computed
+    /// without changing it until they are applied.
+    fn working_copy(&self) -> DomainResult<Doc> {
+        let copy = Doc::with_client_id(self.doc.client_id());
+        let current = Update::decode_v1(&self.encode_full_state())
+            .map_err(|e| DomainError::Internal(e.to_string()))?;
+        copy.transact_mut()
+            .apply_update(current)
+            .map_err(|e| DomainError::Internal(e.to_string()))?;
+        Ok(copy)
+    }
+
     /// Retrieves the text content of the document.
     ///
-    /// This method extracts text content from the Yjs document using the correct Yrs API.
-    /// It uses the GetString trait to extract actual text content from TextRef objects.
+    /// Root types received from clients are not defined on the server, so they are
+    /// read through the transaction's root references and interpreted as text.
+    /// Well-known root names are preferred; otherwise the first non-empty root is used.
     ///
     /// # Returns
     ///
@@ -100,26 +650,272 @@ impl CollaborativeDocument {
     pub fn get_text_content(&self) -> String {
         let txn = self.doc.transact();
 
-        // Try to get the default text object (commonly used root text)
-        let text_ref = self.doc.get_or_insert_text("");
-        let content = text_ref.get_string(&txn);
+        let roots: Vec<(String, String)> = txn
+            .root_refs()
+            .map(|(name, value)| {
+                let content = match value {
+                    Out::YText(text) => text.get_string(&txn),
+                    Out::YXmlFragment(xml) => xml.get_string(&txn),
+                    Out::UndefinedRef(branch) => TextRef::from(branch).get_string(&txn),
+                    _ => String::new(),
+                };
+                (name.to_string(), content)
+            })
+            .collect();
 
-        // If the default text is not empty, return it
-        if !content.is_empty() {
-            return content;
+        // Try the commonly used root names first
+        for preferred in PREFERRED_TEXT_ROOTS {
+            if let Some((_, content)) = roots
+                .iter()
+                .find(|(name, content)| name == preferred && !content.is_empty())
+            {
+                return content.clone();
+            }
         }
 
-        // If no default text exists, try common field names
-        for field_name in ["content", "text", "body", "document"] {
-            let text_ref = self.doc.get_or_insert_text(field_name);
-            let content = text_ref.get_string(&txn);
+        // Fall back to any root with text content, or an empty string
+        roots
+            .into_iter()
+            .map(|(_, content)| content)
+            .find(|content| !content.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Counts the characters and words across the document's text roots.
+    ///
+    /// Roots received from clients are read as text, like for the text content,
+    /// and XML roots are counted without their markup.
+    ///
+    /// # Returns
+    ///
+    /// The `ContentStats` of the whole document.
+    pub fn content_stats(&self) -> ContentStats {
+        let txn = self.doc.transact();
+
+        txn.root_refs()
+            .map(|(_, value)| match value {
+                Out::YText(text) => ContentStats::of(&text.get_string(&txn)),
+                Out::YXmlFragment(xml) => markup_stats(&xml.get_string(&txn)),
+                Out::UndefinedRef(branch) => {
+                    ContentStats::of(&TextRef::from(branch).get_string(&txn))
+                }
+                _ => ContentStats::default(),
+            })
+            .fold(ContentStats::default(), ContentStats::combine)
+    }
+
+    /// Counts the content the document would have once an update is applied,
+    /// without applying it.
+    ///
+    /// The update is applied to a copy of the document, so this is as costly as
+    /// restoring the document and should be reserved to documents close to a
+    /// content limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - A binary-encoded update from a client
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ContentStats)` - The counts of the updated copy
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update couldn't be applied
+    pub fn content_stats_with(&self, update: &[u8]) -> DomainResult<ContentStats> {
+        let mut preview = CollaborativeDocument::new();
+        preview.apply_update(&self.encode_full_state())?;
+        preview.apply_update(update)?;
+        Ok(preview.content_stats())
+    }
+
+    /// Measures the changes an update would make to the document, without applying it.
+    ///
+    /// Like `content_stats_with`, the update is applied to a copy of the
+    /// document, and the roots of both are compared, so this is as costly as
+    /// restoring the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - A binary-encoded update from a client
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(UpdateChanges)` - The roots the update changes and the items it inserts and deletes
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update couldn't be applied
+    pub fn changes_with(&self, update: &[u8]) -> DomainResult<UpdateChanges> {
+        let mut preview = CollaborativeDocument::new();
+        preview.apply_update(&self.encode_full_state())?;
+        preview.apply_update(update)?;
+
+        let before = self.structure_stats();
+        let after = preview.structure_stats();
+        Ok(UpdateChanges {
+            root_types: changed_roots(&self.doc, &preview.doc),
+            inserted_items: after.items.saturating_sub(before.items),
+            deleted_items: after.deleted_items.saturating_sub(before.deleted_items),
+        })
+    }
+
+    /// Applies changes to the document, finding the text they insert into its text roots.
+    ///
+    /// The text roots are observed while the changes are applied, so only the
+    /// inserted text is read, and each place of a root it is inserted at is
+    /// reported on its own. Roots holding text that are not defined on the
+    /// server yet are defined as text roots first, so they can be observed; the
+    /// text of roots the changes create is reported whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `apply` - Applies the changes, e.g. a client update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((T, Vec<TextInsertion>))` - The result of `apply`, and the text inserted by root name,
+    ///   later insertions into a root first so replacing one keeps the others' offsets
+    /// * `Err(DomainError)` - The error `apply` failed with
+    pub fn text_insertions_of<T>(
+        &mut self,
+        apply: impl FnOnce(&mut Self) -> DomainResult<T>,
+    ) -> DomainResult<(T, Vec<TextInsertion>)> {
+        let observed = self.define_text_roots();
+        let inserted = Arc::new(Mutex::new(Vec::new()));
+        let subscriptions: Vec<Subscription> = observed
+            .iter()
+            .map(|(root, text)| {
+                let inserted = inserted.clone();
+                let root = root.clone();
+                text.observe(move |txn, event| {
+                    let mut inserted = inserted.lock().unwrap_or_else(|e| e.into_inner());
+                    record_insertions(&mut inserted, &root, event.delta(txn));
+                })
+            })
+            .collect();
+        let applied = apply(self);
+        drop(subscriptions);
+        let applied = applied?;
+
+        let mut insertions =
+            std::mem::take(&mut *inserted.lock().unwrap_or_else(|e| e.into_inner()));
+        let txn = self.doc.transact();
+        for (name, value) in txn.root_refs() {
+            if observed.iter().any(|(root, _)| root == name)
+                || root_kind(&txn, &value) != Some(RootKind::Text)
+            {
+                continue;
+            }
+            let text = match value {
+                Out::YText(text) => text,
+                Out::UndefinedRef(branch) => TextRef::from(branch),
+                _ => continue,
+            };
+            let content = text.get_string(&txn);
             if !content.is_empty() {
-                return content;
+                insertions.push(TextInsertion {
+                    root: name.to_string(),
+                    index: 0,
+                    text: content,
+                });
             }
         }
+        drop(txn);
+
+        insertions.sort_by(|a, b| a.root.cmp(&b.root).then(b.index.cmp(&a.index)));
+        Ok((applied, insertions))
+    }
+
+    /// Defines the roots holding text as text roots, returning them by name.
+    fn define_text_roots(&mut self) -> Vec<(String, TextRef)> {
+        let names: Vec<String> = {
+            let txn = self.doc.transact();
+            txn.root_refs()
+                .filter(|(_, value)| root_kind(&txn, value) == Some(RootKind::Text))
+                .map(|(name, _)| name.to_string())
+                .collect()
+        };
+        names
+            .into_iter()
+            .map(|name| {
+                let text = self.doc.get_or_insert_text(name.as_str());
+                (name, text)
+            })
+            .collect()
+    }
+
+    /// Measures the document's CRDT structure, tombstones included.
+    ///
+    /// # Returns
+    ///
+    /// The `StructureStats` of the document.
+    pub fn structure_stats(&self) -> StructureStats {
+        let snapshot = self.doc.transact().snapshot();
+
+        StructureStats {
+            clients: snapshot.state_map.len(),
+            items: snapshot
+                .state_map
+                .iter()
+                .map(|(_, &clock)| u64::from(clock))
+                .sum(),
+            deleted_items: snapshot
+                .delete_set
+                .iter()
+                .flat_map(|(_, ranges)| ranges.iter())
+                .map(|range| u64::from(range.end - range.start))
+                .sum(),
+        }
+    }
+
+    /// Checks whether deleted content is garbage collected as soon as it is deleted.
+    pub fn is_gc_enabled(&self) -> bool {
+        !self.doc.skip_gc()
+    }
+
+    /// Enables or disables the garbage collection of deleted content.
+    ///
+    /// Yjs reads this option when a document is created, so the document is
+    /// rebuilt from its full state under the same client ID and GUID. Undo
+    /// stacks track the changes of the former document and are dropped.
+    /// Enabling collection collects the tombstones carried over as they are
+    /// applied to the rebuilt document.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether deleted content is collected from now on
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document now uses the requested setting
+    /// * `Err(DomainError)` - `InvalidUpdate` if the state couldn't be applied to the rebuilt
+    ///   document
+    pub fn set_gc_enabled(&mut self, enabled: bool) -> DomainResult<()> {
+        if self.is_gc_enabled() == enabled {
+            return Ok(());
+        }
 
-        // If no text content found, return empty string
-        String::new()
+        let state = self.encode_full_state();
+        let options = Options {
+            skip_gc: !enabled,
+            ..self.options.clone()
+        };
+        let rebuilt = Doc::with_options(options.clone());
+        let update = Update::decode_v1(&state)
+            .map_err(|_| DomainError::InvalidUpdate("Failed to decode state".to_string()))?;
+        rebuilt
+            .transact_mut()
+            .apply_update(update)
+            .map_err(|e| DomainError::InvalidUpdate(e.to_string()))?;
+
+        self.doc = rebuilt;
+        self.options = options;
+        self.undo_managers.clear();
+        self.revertible.clear();
+        Ok(())
+    }
+
+    /// Garbage collects the content of every tombstone of the document, whether
+    /// collection is enabled or not.
+    ///
+    /// Content still tracked by an undo stack is kept.
+    pub fn collect_garbage(&mut self) {
+        self.doc.transact_mut().force_gc();
     }
 
     /// Retrieves a simple text representation of the document.
@@ -146,3 +942,75 @@ impl CollaborativeDocument {
         }
     }
 }
+
+/// Counts the text of an XML root, skipping its tags.
+///
+/// Each run of text between two tags is counted separately, so words are never
+/// joined across elements.
+fn markup_stats(xml: &str) -> ContentStats {
+    let mut stats = ContentStats::default();
+    let mut rest = xml;
+    while !rest.is_empty() {
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        stats = stats.combine(ContentStats::of(&rest[..text_end]));
+        rest = &rest[text_end..];
+
+        let tag_end = rest.find('>').map_or(rest.len(), |end| end + 1);
+        rest = &rest[tag_end..];
+    }
+    stats
+}
+
+/// Records the text a change of a text root inserts, merging adjacent insertions.
+///
+/// Offsets are counted in the root once changed: retained and inserted content
+/// moves them forward, embeds by one, while deleted content is gone already.
+fn record_insertions(insertions: &mut Vec<TextInsertion>, root: &str, delta: &[Delta]) {
+    let mut index = 0;
+    for change in delta {
+        match change {
+            Delta::Retain(len, _) => index += len,
+            Delta::Deleted(_) => {}
+            Delta::Inserted(Out::Any(Any::String(text)), _) => {
+                match insertions.last_mut() {
+                    Some(last)
+                        if last.root == root && last.index + last.text.len() as u32 == index =>
+                    {
+                        last.text.push_str(text)
+                    }
+                    _ => insertions.push(TextInsertion {
+                        root: root.to_string(),
+                        index,
+                        text: text.to_string(),
+                    }),
+                }
+                index += text.len() as u32;
+            }
+            Delta::Inserted(..) => index += 1,
+        }
+    }
+}
+
+/// Adds every root type of a document to the scope of an undo stack.
+///
+/// Root types received from clients are not defined on the server, and yrs
+/// reports no change of an undefined root to undo stacks, so such roots are
+/// first defined as the kind of shared type their content shows. Empty roots
+/// are left out until they hold something.
+fn expand_undo_scope(doc: &Doc, manager: &mut UndoManager) {
+    let roots: Vec<(String, RootKind)> = {
+        let txn = doc.transact();
+        txn.root_refs()
+            .filter_map(|(name, root)| Some((name.to_string(), root_kind(&txn, &root)?)))
+            .collect()
+    };
+    for (name, kind) in roots {
+        let name = name.as_str();
+        match kind {
+            RootKind::Text => manager.expand_scope(&doc.get_or_insert_text(name)),
+            RootKind::Array => manager.expand_scope(&doc.get_or_insert_array(name)),
+            RootKind::Map => manager.expand_scope(&doc.get_or_insert_map(name)),
+            RootKind::XmlFragment => manager.expand_scope(&doc.get_or_insert_xml_fragment(name)),
+        }
+    }
+}
@@ -1,2 +1,3 @@
+pub(crate) mod clean_copy;
 pub mod document;
-
+pub(crate) mod update_chunks;
@@ -0,0 +1,152 @@
+use std::ops::Range;
+
+use yrs::{
+    block::{
+        ClientID, ItemContent, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN,
+        HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
+    },
+    encoding::{
+        read::{Cursor, Error, Read},
+        write::Write,
+    },
+    updates::decoder::{Decoder, DecoderV1},
+    OffsetKind,
+};
+
+/// A delete set removing nothing, as encoded at the end of an update.
+const EMPTY_DELETE_SET: [u8; 1] = [0];
+
+/// Consecutive structs of one client copied into a chunk.
+struct ClientRun {
+    client: ClientID,
+    /// Clock of the first struct
+    clock: u32,
+    /// Number of structs
+    structs: u32,
+    /// Position of the encoded structs in the split update
+    bytes: Range<usize>,
+}
+
+/// Splits a v1-encoded update into updates of about `chunk_size` bytes.
+///
+/// The structs of the update are copied as they are, in order, so the changes
+/// of a single client are split between chunks at struct boundaries. The
+/// delete set is sent once, with the last chunk: a CRDT drops the deletions of
+/// changes it has not seen yet, so the chunks are meant to be applied in order.
+/// A single struct larger than `chunk_size` makes a chunk of its own.
+///
+/// # Arguments
+///
+/// * `update` - A binary-encoded update
+/// * `chunk_size` - Target size in bytes of each chunk
+///
+/// # Returns
+///
+/// * `Ok(Vec<Vec<u8>>)` - Binary-encoded updates equivalent to `update` when applied in order
+/// * `Err(Error)` - If `update` is not a valid v1 update
+pub(crate) fn split_update(update: &[u8], chunk_size: usize) -> Result<Vec<Vec<u8>>, Error> {
+    let mut cursor = Cursor::new(update);
+    let mut chunks = Vec::new();
+    let mut runs = Vec::new();
+    let mut chunk_len = 0;
+
+    let clients: u32 = cursor.read_var()?;
+    for _ in 0..clients {
+        let structs: u32 = cursor.read_var()?;
+        let client: ClientID = cursor.read_var()?;
+        let mut clock: u32 = cursor.read_var()?;
+        let mut run = ClientRun {
+            client,
+            clock,
+            structs: 0,
+            bytes: cursor.next..cursor.next,
+        };
+
+        for _ in 0..structs {
+            let start = cursor.next;
+            let len = read_struct(&mut cursor)?;
+            let size = cursor.next - start;
+            if chunk_len > 0 && chunk_len + size > chunk_size {
+                if run.structs > 0 {
+                    runs.push(run);
+                }
+                chunks.push(encode_chunk(update, &runs, &EMPTY_DELETE_SET));
+                runs.clear();
+                chunk_len = 0;
+                run = ClientRun {
+                    client,
+                    clock,
+                    structs: 0,
+                    bytes: start..start,
+                };
+            }
+
+            run.structs += 1;
+            run.bytes.end = cursor.next;
+            clock += len;
+            chunk_len += size;
+        }
+        if run.structs > 0 {
+            runs.push(run);
+        }
+    }
+
+    let delete_set = &update[cursor.next..];
+    chunks.push(encode_chunk(update, &runs, delete_set));
+    Ok(chunks)
+}
+
+/// Reads the struct at the cursor's position and moves the cursor past it.
+///
+/// # Returns
+///
+/// * `Ok(u32)` - Number of clock ticks the struct spans
+/// * `Err(Error)` - If the struct couldn't be decoded
+fn read_struct(cursor: &mut Cursor) -> Result<u32, Error> {
+    let mut decoder = DecoderV1::new(Cursor {
+        buf: cursor.buf,
+        next: cursor.next,
+    });
+
+    let len = match decoder.read_info()? {
+        BLOCK_SKIP_REF_NUMBER => decoder.read_var()?,
+        BLOCK_GC_REF_NUMBER => decoder.read_len()?,
+        info => {
+            if info & HAS_ORIGIN != 0 {
+                decoder.read_left_id()?;
+            }
+            if info & HAS_RIGHT_ORIGIN != 0 {
+                decoder.read_right_id()?;
+            }
+            // Items without origins name their parent
+            if info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0 {
+                if decoder.read_parent_info()? {
+                    decoder.read_string()?;
+                } else {
+                    decoder.read_left_id()?;
+                }
+                if info & HAS_PARENT_SUB != 0 {
+                    decoder.read_string()?;
+                }
+            }
+            ItemContent::decode(&mut decoder, info)?.len(OffsetKind::Utf16)
+        }
+    };
+
+    cursor.next = cursor.buf.len() - decoder.read_to_end()?.len();
+    Ok(len)
+}
+
+/// Encodes the given runs of structs, followed by a delete set, as an update.
+fn encode_chunk(update: &[u8], runs: &[ClientRun], delete_set: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::new();
+    chunk.write_var(runs.len());
+    for run in runs {
+        chunk.write_var(run.structs);
+        chunk.write_var(run.client);
+        chunk.write_var(run.clock);
+        chunk.extend_from_slice(&update[run.bytes.clone()]);
+    }
+    chunk.extend_from_slice(delete_set);
+    chunk
+}
@@ -0,0 +1,98 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::value_objects::cluster_member::ClusterMember;
+
+/// Failure of a domain operation.
+///
+/// Domain services, repositories and the ports implemented by the
+/// infrastructure layer report failures with this type, so transport adapters
+/// can map them to the matching HTTP status or gRPC code instead of guessing
+/// from a message.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum DomainError {
+    /// The document does not exist
+    #[error("Document '{0}' not found")]
+    NotFound(String),
+    /// The document already exists
+    #[error("Document '{0}' already exists")]
+    Conflict(String),
+    /// A binary update or state vector could not be decoded or applied
+    #[error("{0}")]
+    InvalidUpdate(String),
+    /// A request argument is malformed, such as an invalid tag or Base64 payload
+    #[error("{0}")]
+    InvalidArgument(String),
+    /// The client may not access the document
+    #[error("{0}")]
+    Unauthorized(String),
+    /// The document is frozen read-only and rejects every update
+    #[error("Document '{0}' is frozen")]
+    DocumentFrozen(String),
+    /// A limit would be exceeded, such as the maximum document size
+    #[error("{0}")]
+    LimitExceeded(String),
+    /// An update exceeds the server's maximum update size, or would make the
+    /// document exceed its maximum document size
+    #[error("{0}")]
+    PayloadTooLarge(String),
+    /// The document already has as many clients as allowed, or the client is
+    /// already present on as many documents as allowed
+    #[error("{0}")]
+    RoomFull(String),
+    /// The storage backend failed to read or write
+    #[error("{0}")]
+    StorageFailure(String),
+    /// The document is owned by another instance of the cluster, which its clients
+    /// should connect to
+    #[error(
+        "Document '{}' is owned by cluster node '{}' at {}",
+        .doc_id,
+        .owner.node_id,
+        .owner.http_url
+    )]
+    Moved {
+        /// The document
+        doc_id: String,
+        /// The instance owning the document
+        owner: ClusterMember,
+    },
+    /// A dependency is not configured or no longer reachable, such as the broker
+    #[error("{0}")]
+    Unavailable(String),
+    /// An unexpected failure within the server
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl DomainError {
+    /// Wraps an error raised by a storage backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The backend error
+    ///
+    /// # Returns
+    ///
+    /// A `StorageFailure` carrying the error's message
+    pub fn storage(error: impl Display) -> Self {
+        Self::StorageFailure(error.to_string())
+    }
+
+    /// Wraps an error raised while reaching a dependency.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The dependency error
+    ///
+    /// # Returns
+    ///
+    /// An `Unavailable` error carrying the error's message
+    pub fn unavailable(error: impl Display) -> Self {
+        Self::Unavailable(error.to_string())
+    }
+}
+
+/// Result of a domain operation.
+pub type DomainResult<T> = Result<T, DomainError>;
@@ -4,10 +4,12 @@
 // and repository interfaces of the Yjs Collaboration Server.
 
 pub mod entities;
+pub mod errors;
 pub mod repositories;
 pub mod services;
 pub mod value_objects;
 
 // Re-export commonly used domain types
 pub use entities::document::CollaborativeDocument;
+pub use errors::{DomainError, DomainResult};
 pub use services::document_service::SingleDocumentServiceImpl;
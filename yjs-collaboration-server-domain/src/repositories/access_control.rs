@@ -0,0 +1,23 @@
+use crate::{errors::DomainResult, value_objects::access_role::AccessRole};
+
+/// Decides which clients may read and edit documents.
+///
+/// Transport adapters resolve a client's role when it joins a document, serve
+/// sync responses and updates to any client with a role, and reject updates
+/// from read-only clients.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait AccessControl: Send + Sync {
+    /// Resolves the role of a client on a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `user_id` - Identity of the client, or `None` for a guest
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AccessRole)` - The permission the client holds on the document
+    /// * `Err(DomainError)` - `Unauthorized` if the client may not access the document at all
+    fn role(&self, doc_id: &str, user_id: Option<&str>) -> DomainResult<AccessRole>;
+}
@@ -0,0 +1,51 @@
+use crate::{errors::DomainResult, value_objects::audit_entry::AuditEntry};
+
+/// Sink for the audit trail of the updates applied to documents.
+///
+/// Each document has its own trail, recording who applied which update, when,
+/// its size and the transport it came through. Trails are append-only: an
+/// implementation may drop the oldest entries to bound its storage, but never
+/// rewrites or renumbers the others.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait AuditSink: Send + Sync {
+    /// Appends an entry to a document's trail.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `entry` - The entry; its number is replaced by the next one of the trail
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - The number given to the entry
+    /// * `Err(DomainError)` - `StorageFailure` if the entry could not be written
+    fn record(&self, doc_id: &str, entry: AuditEntry) -> DomainResult<u64>;
+
+    /// Lists entries of a document's trail.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `after` - Number of the last entry already read, `0` to start from the oldest
+    /// * `limit` - Maximum number of entries returned
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<AuditEntry>)` - The entries numbered above `after`, oldest first, empty if there
+    ///   is none
+    /// * `Err(DomainError)` - `StorageFailure` if the entries could not be read
+    fn list(&self, doc_id: &str, after: u64, limit: usize) -> DomainResult<Vec<AuditEntry>>;
+
+    /// Removes the trail of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document has no trail anymore
+    /// * `Err(DomainError)` - `StorageFailure` if the trail could not be removed
+    fn clear(&self, doc_id: &str) -> DomainResult<()>;
+}
@@ -0,0 +1,38 @@
+use tokio::sync::watch;
+
+use crate::{errors::DomainResult, value_objects::cluster_member::ClusterMember};
+
+/// Membership list of the server instances sharing the documents of a cluster.
+///
+/// Every instance announces itself when it joins and follows the list of the
+/// live instances, from which the owner of each document is derived. Every
+/// instance must eventually see the same list, so that they agree on the owners.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait ClusterMembership: Send + Sync {
+    /// Announces this instance and starts following the membership list.
+    ///
+    /// # Arguments
+    ///
+    /// * `member` - The instance joining the cluster
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Receiver)` - A receiver of the live members, sorted by node identifier, which includes
+    ///   `member` until the list is first read
+    /// * `Err(DomainError)` - `Unavailable` if the instance could not be announced
+    fn join(&self, member: ClusterMember) -> DomainResult<watch::Receiver<Vec<ClusterMember>>>;
+
+    /// Checks whether the membership list is currently followed.
+    ///
+    /// Used by readiness probes, so implementations should answer without a
+    /// round trip when they can. The default implementation is always healthy.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the membership list is up to date
+    /// * `Err(DomainError)` - `Unavailable` if the list can no longer be read
+    fn check_health(&self) -> DomainResult<()> {
+        Ok(())
+    }
+}
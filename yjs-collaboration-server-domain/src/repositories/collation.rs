@@ -0,0 +1,37 @@
+use std::cmp::Ordering;
+
+/// Orders and matches the names shown by the admin APIs, such as document IDs
+/// and tags, following the conventions of a language.
+///
+/// Without a collation, names are sorted by their bytes and matched exactly,
+/// which misplaces accented and non-Latin names and tells apart names differing
+/// only by case.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait Collation: Send + Sync {
+    /// Compares two names.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The first name
+    /// * `b` - The second name
+    ///
+    /// # Returns
+    ///
+    /// The order of `a` relative to `b`
+    fn compare(&self, a: &str, b: &str) -> Ordering;
+
+    /// Checks whether two names are equivalent, e.g. when looking up a tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The first name
+    /// * `b` - The second name
+    ///
+    /// # Returns
+    ///
+    /// `true` if the collation does not tell the names apart
+    fn matches(&self, a: &str, b: &str) -> bool {
+        self.compare(a, b) == Ordering::Equal
+    }
+}
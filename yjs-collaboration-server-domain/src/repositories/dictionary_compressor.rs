@@ -0,0 +1,37 @@
+use crate::errors::DomainResult;
+
+/// Trains compression dictionaries and compresses payloads with them.
+///
+/// A dictionary trained on a document's past updates captures the byte
+/// patterns its edits repeat (client IDs, root names, formatting attributes),
+/// so even the small updates of a chatty document compress well, unlike with
+/// generic compression that starts from scratch on every message.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait DictionaryCompressor: Send + Sync {
+    /// Trains a dictionary on sample payloads.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Payloads representative of those to compress
+    /// * `max_size` - Maximum size of the dictionary in bytes
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The trained dictionary
+    /// * `Err(DomainError)` - If the samples are too few or too small to train a dictionary
+    fn train(&self, samples: &[Vec<u8>], max_size: usize) -> DomainResult<Vec<u8>>;
+
+    /// Compresses a payload with a dictionary.
+    ///
+    /// # Arguments
+    ///
+    /// * `dictionary` - A dictionary returned by `train`
+    /// * `payload` - The payload to compress
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The compressed payload, which only decompresses with the same dictionary
+    /// * `Err(DomainError)` - If the payload could not be compressed
+    fn compress(&self, dictionary: &[u8], payload: &[u8]) -> DomainResult<Vec<u8>>;
+}
@@ -0,0 +1,47 @@
+use tokio::sync::mpsc;
+
+use crate::{errors::DomainResult, value_objects::cluster_member::ClusterMember};
+
+/// A message relayed from the owner of a forwarded document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OwnerMessage {
+    /// An update applied on the owner, or part of its state sent when the relay connects
+    Update(Vec<u8>),
+    /// The owner's state vector, sent when the relay connects, to which the changes the
+    /// owner is missing must be sent back as an update
+    StateVector(Vec<u8>),
+}
+
+/// Relay between the replica of a document on this instance and its owner.
+pub struct ForwardedDocument {
+    /// Sender of the updates applied on this instance, to be applied on the owner
+    pub to_owner: mpsc::UnboundedSender<Vec<u8>>,
+    /// Receiver of the messages of the owner, to be applied on this instance
+    pub from_owner: mpsc::UnboundedReceiver<OwnerMessage>,
+}
+
+/// Forwarding of the documents owned by other members of a cluster.
+///
+/// Instead of sending its clients to the owner of their document, an instance
+/// may serve them from a replica of the document kept in sync with the owner:
+/// the updates its clients apply are relayed to the owner, which persists and
+/// shares them, and the updates applied on the owner are relayed back.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait DocumentForwarder: Send + Sync {
+    /// Starts relaying a document between this instance and its owner.
+    ///
+    /// The relay reconnects on its own when the owner is unreachable, keeping the
+    /// updates to send meanwhile, and stops once either side of the channels is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `owner` - The member owning the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ForwardedDocument)` - The channels relaying the document
+    /// * `Err(DomainError)` - `Unavailable` if the relay could not be started
+    fn forward(&self, doc_id: &str, owner: &ClusterMember) -> DomainResult<ForwardedDocument>;
+}
@@ -0,0 +1,45 @@
+use crate::{errors::DomainResult, value_objects::document_metadata::DocumentMetadata};
+
+/// Repository interface for the operator-managed metadata of documents.
+///
+/// Metadata is stored independently of the document content, so it can be
+/// read and queried without loading documents into memory. Documents without
+/// metadata are simply absent from the repository.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait DocumentMetadataRepository: Send + Sync {
+    /// Retrieves the metadata of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentMetadata)` - The stored metadata, empty if there is none
+    /// * `Err(DomainError)` - `StorageFailure` if the metadata could not be read
+    fn get(&self, doc_id: &str) -> DomainResult<DocumentMetadata>;
+
+    /// Stores the metadata of a document, replacing the previous metadata.
+    ///
+    /// Storing empty metadata removes the document from the repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `metadata` - The new metadata
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the metadata was stored
+    /// * `Err(DomainError)` - `StorageFailure` if the metadata could not be written
+    fn put(&self, doc_id: &str, metadata: &DocumentMetadata) -> DomainResult<()>;
+
+    /// Lists the metadata of every document that has any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(String, DocumentMetadata)>)` - Document IDs and their metadata
+    /// * `Err(DomainError)` - `StorageFailure` if the metadata could not be read
+    fn list(&self) -> DomainResult<Vec<(String, DocumentMetadata)>>;
+}
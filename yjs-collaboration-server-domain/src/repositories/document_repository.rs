@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 
-use crate::services::document_service::SingleDocumentServiceImpl;
+use crate::{
+    errors::{DomainError, DomainResult},
+    services::document_service::SingleDocumentServiceImpl,
+    value_objects::logged_update::LoggedUpdate,
+};
 
 /// Repository interface for document storage and retrieval operations.
 ///
@@ -23,12 +27,10 @@ pub trait DocumentRepository: Send + Sync {
     ///
     /// # Returns
     ///
-    /// * `Ok(Arc<Mutex<SingleDocumentServiceImpl>>)` - If the document was created successfully
-    /// * `Err(String)` - If the document already exists or creation failed
-    fn create_document(
-        &self,
-        doc_id: &str,
-    ) -> Result<Arc<Mutex<SingleDocumentServiceImpl>>, String>;
+    /// * `Ok(Arc<RwLock<SingleDocumentServiceImpl>>)` - If the document was created successfully
+    /// * `Err(DomainError)` - `Conflict` if the document already exists, or `StorageFailure`
+    fn create_document(&self, doc_id: &str)
+        -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>>;
 
     /// Retrieves an existing document by ID.
     ///
@@ -38,9 +40,9 @@ pub trait DocumentRepository: Send + Sync {
     ///
     /// # Returns
     ///
-    /// * `Some(Arc<Mutex<SingleDocumentServiceImpl>>)` - If the document exists
+    /// * `Some(Arc<RwLock<SingleDocumentServiceImpl>>)` - If the document exists
     /// * `None` - If the document does not exist
-    fn get_document(&self, doc_id: &str) -> Option<Arc<Mutex<SingleDocumentServiceImpl>>>;
+    fn get_document(&self, doc_id: &str) -> Option<Arc<RwLock<SingleDocumentServiceImpl>>>;
 
     /// Retrieves an existing document by ID or creates a new one if it doesn't exist.
     ///
@@ -54,7 +56,7 @@ pub trait DocumentRepository: Send + Sync {
     /// # Returns
     ///
     /// A thread-safe reference to the document service for the requested document.
-    fn get_or_create(&self, doc_id: &str) -> Arc<Mutex<SingleDocumentServiceImpl>>;
+    fn get_or_create(&self, doc_id: &str) -> Arc<RwLock<SingleDocumentServiceImpl>>;
 
     /// Updates an existing document.
     ///
@@ -66,12 +68,12 @@ pub trait DocumentRepository: Send + Sync {
     /// # Returns
     ///
     /// * `Ok(())` - If the document was updated successfully
-    /// * `Err(String)` - If the document does not exist or update failed
+    /// * `Err(DomainError)` - `NotFound` if the document does not exist, or `StorageFailure`
     fn update_document(
         &self,
         doc_id: &str,
-        document: Arc<Mutex<SingleDocumentServiceImpl>>,
-    ) -> Result<(), String>;
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+    ) -> DomainResult<()>;
 
     /// Deletes a document by ID.
     ///
@@ -82,8 +84,8 @@ pub trait DocumentRepository: Send + Sync {
     /// # Returns
     ///
     /// * `Ok(())` - If the document was deleted successfully
-    /// * `Err(String)` - If the document does not exist or deletion failed
-    fn delete_document(&self, doc_id: &str) -> Result<(), String>;
+    /// * `Err(DomainError)` - `NotFound` if the document does not exist, or `StorageFailure`
+    fn delete_document(&self, doc_id: &str) -> DomainResult<()>;
 
     /// Lists all document IDs in the repository.
     ///
@@ -116,6 +118,163 @@ pub trait DocumentRepository: Send + Sync {
     /// # Returns
     ///
     /// * `Ok(())` - If all documents were cleared successfully
-    /// * `Err(String)` - If the operation failed
-    fn clear(&self) -> Result<(), String>;
+    /// * `Err(DomainError)` - `StorageFailure` if the operation failed
+    fn clear(&self) -> DomainResult<()>;
+
+    /// Writes the updates the repository buffers to durable storage.
+    ///
+    /// Repositories writing each update as it is applied keep the default no-op;
+    /// those batching updates, such as object storage backends, are flushed
+    /// periodically by the application.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(usize)` - The number of documents whose updates were written
+    /// * `Err(DomainError)` - `StorageFailure` if some updates could not be written; they are kept
+    ///   for the next flush
+    fn flush(&self) -> DomainResult<usize> {
+        Ok(0)
+    }
+
+    /// Returns whether the repository keeps documents across restarts.
+    ///
+    /// Updates buffered by the repository count as kept once [`flush`] returns.
+    ///
+    /// # Returns
+    ///
+    /// `true` for persistent backends, `false` for repositories keeping documents in memory only
+    ///
+    /// [`flush`]: DocumentRepository::flush
+    fn is_durable(&self) -> bool {
+        false
+    }
+
+    /// Lists the documents currently loaded in memory.
+    ///
+    /// The default implementation, for repositories keeping every document in
+    /// memory, lists all documents.
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the resident documents, without loading persisted ones
+    fn resident_documents(&self) -> Vec<String> {
+        self.list_documents()
+    }
+
+    /// Lists the resident documents the repository's eviction policy selects.
+    ///
+    /// The document service saves each candidate to its document store before
+    /// deleting it from the repository, and restores it from the store when it
+    /// is opened again. The default implementation never evicts a document.
+    ///
+    /// # Returns
+    ///
+    /// The IDs of the documents to evict, least recently used first
+    fn eviction_candidates(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Reads a document's persisted update log without compacting it.
+    ///
+    /// Used to investigate documents that fail to load. The default
+    /// implementation, for repositories that persist nothing, has no log to read.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<LoggedUpdate>)` - The snapshot, if any, then the updates in the order they were
+    ///   appended; empty if nothing is persisted for the document
+    /// * `Err(DomainError)` - `Unavailable` if the repository persists no log, or `StorageFailure`
+    fn logged_updates(&self, _doc_id: &str) -> DomainResult<Vec<LoggedUpdate>> {
+        Err(DomainError::Unavailable(
+            "The repository persists no update log".to_string(),
+        ))
+    }
+
+    /// Checks whether the storage backend can currently be reached.
+    ///
+    /// Used by readiness probes, so implementations should answer quickly. The
+    /// default implementation, for repositories that keep everything in memory,
+    /// is always healthy.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the storage backend answered
+    /// * `Err(DomainError)` - `Unavailable` if it could not be reached
+    fn check_health(&self) -> DomainResult<()> {
+        Ok(())
+    }
+}
+
+/// Forwards to the boxed repository, so the storage backend can be chosen at runtime.
+impl<T: DocumentRepository + ?Sized> DocumentRepository for Box<T> {
+    fn create_document(
+        &self,
+        doc_id: &str,
+    ) -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        (**self).create_document(doc_id)
+    }
+
+    fn get_document(&self, doc_id: &str) -> Option<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        (**self).get_document(doc_id)
+    }
+
+    fn get_or_create(&self, doc_id: &str) -> Arc<RwLock<SingleDocumentServiceImpl>> {
+        (**self).get_or_create(doc_id)
+    }
+
+    fn update_document(
+        &self,
+        doc_id: &str,
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+    ) -> DomainResult<()> {
+        (**self).update_document(doc_id, document)
+    }
+
+    fn delete_document(&self, doc_id: &str) -> DomainResult<()> {
+        (**self).delete_document(doc_id)
+    }
+
+    fn list_documents(&self) -> Vec<String> {
+        (**self).list_documents()
+    }
+
+    fn exists(&self, doc_id: &str) -> bool {
+        (**self).exists(doc_id)
+    }
+
+    fn count(&self) -> usize {
+        (**self).count()
+    }
+
+    fn clear(&self) -> DomainResult<()> {
+        (**self).clear()
+    }
+
+    fn flush(&self) -> DomainResult<usize> {
+        (**self).flush()
+    }
+
+    fn is_durable(&self) -> bool {
+        (**self).is_durable()
+    }
+
+    fn resident_documents(&self) -> Vec<String> {
+        (**self).resident_documents()
+    }
+
+    fn eviction_candidates(&self) -> Vec<String> {
+        (**self).eviction_candidates()
+    }
+
+    fn logged_updates(&self, doc_id: &str) -> DomainResult<Vec<LoggedUpdate>> {
+        (**self).logged_updates(doc_id)
+    }
+
+    fn check_health(&self) -> DomainResult<()> {
+        (**self).check_health()
+    }
 }
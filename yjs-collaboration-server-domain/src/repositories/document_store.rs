@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+
+use crate::errors::DomainResult;
+
+/// Asynchronous storage of the encoded state of documents.
+///
+/// Unlike the `DocumentRepository`, which holds the live documents clients
+/// collaborate on, a store only keeps each document's state as a single
+/// binary-encoded update. Its methods are asynchronous, so stores backed by a
+/// database or an object storage service can be implemented without blocking
+/// the runtime. The document service restores a document from the store when
+/// it is first opened and saves it back once it has been updated.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+#[async_trait]
+pub trait DocumentStore: Send + Sync {
+    /// Loads the saved state of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Vec<u8>))` - The binary-encoded state of the document
+    /// * `Ok(None)` - If no state is saved for the document
+    /// * `Err(DomainError)` - `StorageFailure` if the state could not be read
+    async fn load(&self, doc_id: &str) -> DomainResult<Option<Vec<u8>>>;
+
+    /// Saves the state of a document, replacing the previously saved state.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `state` - The binary-encoded state of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the state was saved
+    /// * `Err(DomainError)` - `StorageFailure` if the state could not be written
+    async fn save(&self, doc_id: &str, state: &[u8]) -> DomainResult<()>;
+
+    /// Deletes the saved state of a document, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If no state is saved for the document anymore
+    /// * `Err(DomainError)` - `StorageFailure` if the state could not be removed
+    async fn delete(&self, doc_id: &str) -> DomainResult<()>;
+
+    /// Lists the documents whose state is saved.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - The identifiers of the saved documents
+    /// * `Err(DomainError)` - `StorageFailure` if the store could not be read
+    async fn list(&self) -> DomainResult<Vec<String>>;
+
+    /// Checks whether the state of a document is saved.
+    ///
+    /// The default implementation loads the state; stores that can check for a
+    /// document more cheaply should override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether a state is saved for the document
+    /// * `Err(DomainError)` - `StorageFailure` if the store could not be read
+    async fn contains(&self, doc_id: &str) -> DomainResult<bool> {
+        Ok(self.load(doc_id).await?.is_some())
+    }
+
+    /// Checks whether the store can currently be reached.
+    ///
+    /// Used by readiness probes, so implementations should answer quickly. The
+    /// default implementation is always healthy.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the store answered
+    /// * `Err(DomainError)` - `Unavailable` if it could not be reached
+    async fn check_health(&self) -> DomainResult<()> {
+        Ok(())
+    }
+}
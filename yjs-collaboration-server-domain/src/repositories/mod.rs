@@ -1,2 +1,17 @@
+pub mod access_control;
+pub mod audit_sink;
+pub mod cluster_membership;
+pub mod collation;
+pub mod dictionary_compressor;
+pub mod document_forwarder;
+pub mod document_metadata_repository;
 pub mod document_repository;
-
+pub mod document_store;
+pub mod search_index;
+pub mod update_broker;
+pub mod update_exporter;
+pub mod update_log;
+pub mod update_processor;
+pub mod update_validator;
+pub mod version_repository;
+pub mod write_ahead_log;
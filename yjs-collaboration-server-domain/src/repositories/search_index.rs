@@ -0,0 +1,57 @@
+use crate::{errors::DomainResult, value_objects::search_hit::SearchHit};
+
+/// Full-text index of the content of documents.
+///
+/// Documents are indexed by their plain text content, replacing whatever was
+/// indexed for them before. Changes are buffered until `commit`, so a batch of
+/// documents becomes searchable at once; searches only see committed changes.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait SearchIndex: Send + Sync {
+    /// Indexes the content of a document, replacing its previous content.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `content` - The plain text content of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the content was buffered for the next commit
+    /// * `Err(DomainError)` - `StorageFailure` if the content could not be indexed
+    fn index(&self, doc_id: &str, content: &str) -> DomainResult<()>;
+
+    /// Removes a document from the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the removal was buffered for the next commit
+    /// * `Err(DomainError)` - `StorageFailure` if the removal could not be recorded
+    fn remove(&self, doc_id: &str) -> DomainResult<()>;
+
+    /// Makes the changes buffered since the last commit searchable.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the changes were committed
+    /// * `Err(DomainError)` - `StorageFailure` if the changes could not be written
+    fn commit(&self) -> DomainResult<()>;
+
+    /// Searches the indexed documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query, in the syntax of the implementation
+    /// * `limit` - Maximum number of hits returned
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchHit>)` - The matching documents, best match first
+    /// * `Err(DomainError)` - `InvalidArgument` if the query is malformed, or `StorageFailure`
+    ///   if the index could not be read
+    fn search(&self, query: &str, limit: usize) -> DomainResult<Vec<SearchHit>>;
+}
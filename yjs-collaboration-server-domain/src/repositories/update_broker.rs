@@ -0,0 +1,52 @@
+use tokio::sync::mpsc;
+
+use crate::errors::DomainResult;
+
+/// Fan-out of applied updates between server instances.
+///
+/// When several instances serve the same documents, each publishes the updates
+/// its clients apply and subscribes to the updates published by the others, so
+/// clients connected to different instances see each other's edits.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait UpdateBroker: Send + Sync {
+    /// Publishes an update applied on this instance to the other instances.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `update` - The binary-encoded update that was applied
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was handed to the broker
+    /// * `Err(DomainError)` - `Unavailable` if the broker is no longer available
+    fn publish(&self, doc_id: &str, update: &[u8]) -> DomainResult<()>;
+
+    /// Starts receiving the updates other instances publish for a document.
+    ///
+    /// Updates published by this instance are not delivered back to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(UnboundedReceiver)` - A receiver yielding remote updates
+    /// * `Err(DomainError)` - `Unavailable` if the subscription could not be registered
+    fn subscribe(&self, doc_id: &str) -> DomainResult<mpsc::UnboundedReceiver<Vec<u8>>>;
+
+    /// Checks whether the broker is currently connected to the other instances.
+    ///
+    /// Used by readiness probes, so implementations should answer without a
+    /// round trip when they can. The default implementation is always healthy.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If updates are currently shared with the other instances
+    /// * `Err(DomainError)` - `Unavailable` if the broker is disconnected
+    fn check_health(&self) -> DomainResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{errors::DomainResult, value_objects::exported_update::ExportedUpdate};
+
+/// Export of the updates applied to documents to downstream pipelines, such
+/// as analytics or machine learning.
+///
+/// Every update applied on this instance is exported once, by the instance
+/// that applied it: updates relayed from other instances are not exported
+/// again. Exporting must not slow down the documents, so implementations
+/// queue updates and deliver them in the background; a failing export does
+/// not make the server unready.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait UpdateExporter: Send + Sync {
+    /// Queues an update for export.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The applied update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was queued
+    /// * `Err(DomainError)` - `Unavailable` if the queue is full or the exporter has stopped
+    fn export(&self, update: ExportedUpdate) -> DomainResult<()>;
+}
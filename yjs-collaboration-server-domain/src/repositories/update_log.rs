@@ -0,0 +1,41 @@
+use crate::errors::DomainResult;
+
+/// Durable log of the updates applied to documents.
+///
+/// Persistent repositories attach an update log to the documents they load, so
+/// that every update is recorded as soon as it has been integrated and before it
+/// is broadcast to other clients.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait UpdateLog: Send + Sync {
+    /// Appends an applied update to a document's log.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `update` - The binary-encoded update that was applied
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was persisted
+    /// * `Err(DomainError)` - `StorageFailure` if the update could not be written
+    fn append(&self, doc_id: &str, update: &[u8]) -> DomainResult<()>;
+
+    /// Merges a document's logged updates into a single snapshot.
+    ///
+    /// Called on the blocking thread pool every few appends for documents whose
+    /// policy disables history, so the individual updates are not retained. Logs
+    /// without snapshots keep the default no-op.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the log was compacted
+    /// * `Err(DomainError)` - `StorageFailure` if the log could not be rewritten
+    fn compact(&self, _doc_id: &str) -> DomainResult<()> {
+        Ok(())
+    }
+}
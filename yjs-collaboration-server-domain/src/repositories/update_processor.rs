@@ -0,0 +1,44 @@
+use crate::{
+    errors::DomainResult,
+    value_objects::{
+        text_insertion::{ProcessorVerdict, TextInsertion},
+        update_origin::TransactionOrigin,
+    },
+};
+
+/// A stage of the pipeline processing the text clients insert into documents,
+/// e.g. to mask profanity or redact personal data.
+///
+/// Processors run in the order they are registered, on every text an update
+/// inserts, before the update is applied; each one sees the text as replaced
+/// by the previous ones. An update any processor rejects is not applied.
+/// Replacements are applied together with the update, so the other clients
+/// only ever receive the processed text.
+///
+/// Processors run on the compute pool while the document is locked, so
+/// implementations should decide from the text alone, without blocking.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait UpdateProcessor: Send + Sync {
+    /// Returns the name of the processor, reported in logs and labels.
+    fn name(&self) -> &str;
+
+    /// Inspects a text an update inserts into a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `insertion` - The inserted text, as replaced by the previous processors
+    /// * `origin` - The client and user applying the update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ProcessorVerdict)` - Whether the text is kept, replaced or the update rejected
+    /// * `Err(DomainError)` - If the text could not be processed, which rejects the update
+    fn process(
+        &self,
+        doc_id: &str,
+        insertion: &TextInsertion,
+        origin: &TransactionOrigin,
+    ) -> DomainResult<ProcessorVerdict>;
+}
@@ -0,0 +1,38 @@
+use crate::{
+    errors::DomainResult,
+    value_objects::{update_changes::UpdateChanges, update_origin::TransactionOrigin},
+};
+
+/// Validation of the updates clients apply to documents, enforcing a document
+/// schema on the server.
+///
+/// Every update sent by a client is previewed on a copy of its document, and
+/// the changes it would make are validated before it is applied; a rejected
+/// update is not applied, persisted nor broadcast. Updates made by the server
+/// itself, such as imports and reverts, and updates relayed from other
+/// instances, which validated them, are not validated.
+///
+/// Validation runs on the compute pool while the document is locked, so
+/// implementations should decide from the changes alone, without blocking.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait UpdateValidator: Send + Sync {
+    /// Checks whether an update may be applied to a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `changes` - The changes the update would make to the document
+    /// * `origin` - The client and user applying the update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update may be applied
+    /// * `Err(DomainError)` - `InvalidUpdate` naming the violated constraint otherwise
+    fn validate(
+        &self,
+        doc_id: &str,
+        changes: &UpdateChanges,
+        origin: &TransactionOrigin,
+    ) -> DomainResult<()>;
+}
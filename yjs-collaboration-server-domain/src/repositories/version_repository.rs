@@ -0,0 +1,76 @@
+use crate::{errors::DomainResult, value_objects::document_version::DocumentVersion};
+
+/// Repository interface for the versions recorded in documents' timelines.
+///
+/// Each version is stored as its metadata (number, label, author and time)
+/// along with the snapshot of the document's state. Versions are stored
+/// independently of the document content, so a document's timeline can be
+/// listed without loading the document into memory.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait VersionRepository: Send + Sync {
+    /// Lists the versions of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<DocumentVersion>)` - The versions, oldest first, empty if there is none
+    /// * `Err(DomainError)` - `StorageFailure` if the versions could not be read
+    fn list(&self, doc_id: &str) -> DomainResult<Vec<DocumentVersion>>;
+
+    /// Retrieves a version of a document with its snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `version` - The number of the version
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some((DocumentVersion, Vec<u8>)))` - The version and its binary-encoded snapshot
+    /// * `Ok(None)` - If the document has no such version
+    /// * `Err(DomainError)` - `StorageFailure` if the version could not be read
+    fn get(&self, doc_id: &str, version: u64) -> DomainResult<Option<(DocumentVersion, Vec<u8>)>>;
+
+    /// Stores a version of a document with its snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `version` - The metadata of the version
+    /// * `snapshot` - The binary-encoded state of the document at that version
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the version was stored
+    /// * `Err(DomainError)` - `StorageFailure` if the version could not be written
+    fn put(&self, doc_id: &str, version: &DocumentVersion, snapshot: &[u8]) -> DomainResult<()>;
+
+    /// Removes a version of a document, if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `version` - The number of the version
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the version is not stored anymore
+    /// * `Err(DomainError)` - `StorageFailure` if the version could not be removed
+    fn remove(&self, doc_id: &str, version: u64) -> DomainResult<()>;
+
+    /// Removes every version of a document, e.g. once it is deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If no version of the document is stored anymore
+    /// * `Err(DomainError)` - `StorageFailure` if the versions could not be removed
+    fn clear(&self, doc_id: &str) -> DomainResult<()>;
+}
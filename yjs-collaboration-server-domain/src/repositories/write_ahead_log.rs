@@ -0,0 +1,59 @@
+use crate::errors::DomainResult;
+
+/// Local log of the updates applied to documents, replayed after a crash.
+///
+/// The document service appends every update it applies before the client is
+/// acknowledged, so edits buffered by the storage backend, or held in memory
+/// only, survive a crash. Appended updates are grouped in segments; a
+/// checkpoint seals the current segment, and once the storage backend holds
+/// every update of the sealed segments they are truncated.
+///
+/// Appends may block while the update reaches the disk, so the document
+/// service runs them on the blocking thread pool.
+///
+/// Implementations must be thread-safe as they will be accessed concurrently.
+pub trait WriteAheadLog: Send + Sync {
+    /// Appends an applied update to the current segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `update` - The binary-encoded update that was applied
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was written, and synced if the log syncs its appends
+    /// * `Err(DomainError)` - `StorageFailure` if the update could not be written
+    fn append(&self, doc_id: &str, update: &[u8]) -> DomainResult<()>;
+
+    /// Seals the current segment, so updates are appended to a new one.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(u64)` - A checkpoint covering the sealed segments, to pass to
+    ///   [`truncate`](WriteAheadLog::truncate)
+    /// * `Err(DomainError)` - `StorageFailure` if the new segment could not be created
+    fn rotate(&self) -> DomainResult<u64>;
+
+    /// Deletes the segments a checkpoint covers.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - A checkpoint returned by [`rotate`](WriteAheadLog::rotate)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the segments were deleted
+    /// * `Err(DomainError)` - `StorageFailure` if a segment could not be deleted
+    fn truncate(&self, checkpoint: u64) -> DomainResult<()>;
+
+    /// Reads the updates of the segments left by previous runs, oldest first.
+    ///
+    /// A record torn by a crash ends the replay of its segment.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(String, Vec<u8>)>)` - The logged updates with their document identifiers
+    /// * `Err(DomainError)` - `StorageFailure` if a segment could not be read
+    fn replay(&self) -> DomainResult<Vec<(String, Vec<u8>)>>;
+}
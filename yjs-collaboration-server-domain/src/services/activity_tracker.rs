@@ -0,0 +1,188 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::value_objects::document_activity::{
+    ActivityBucket, ActivityGranularity, ActivityRetention,
+};
+
+/// Updates and editors counted during a time bucket.
+struct Bucket {
+    start: i64,
+    updates: u64,
+    editors: HashSet<String>,
+}
+
+/// Buckets of a single granularity, oldest first.
+#[derive(Default)]
+struct Buckets(VecDeque<Bucket>);
+
+impl Buckets {
+    /// Drops the buckets that started before the retention window ending at `now`.
+    fn prune(&mut self, granularity: ActivityGranularity, retained: usize, now: i64) {
+        let width = granularity.seconds();
+        let oldest = bucket_start(now, width) - width * (retained as i64 - 1);
+        while self.0.front().is_some_and(|bucket| bucket.start < oldest) {
+            self.0.pop_front();
+        }
+    }
+
+    fn record(
+        &mut self,
+        granularity: ActivityGranularity,
+        retained: usize,
+        now: i64,
+        editor: &str,
+    ) {
+        if retained == 0 {
+            return;
+        }
+
+        let start = bucket_start(now, granularity.seconds());
+        if self.0.back().map(|bucket| bucket.start) != Some(start) {
+            self.0.push_back(Bucket {
+                start,
+                updates: 0,
+                editors: HashSet::new(),
+            });
+        }
+        if let Some(bucket) = self.0.back_mut() {
+            bucket.updates += 1;
+            if !bucket.editors.contains(editor) {
+                bucket.editors.insert(editor.to_string());
+            }
+        }
+
+        self.prune(granularity, retained, now);
+    }
+}
+
+/// Activity of a single document, by minute and by hour.
+#[derive(Default)]
+struct DocumentActivity {
+    minutes: Buckets,
+    hours: Buckets,
+}
+
+impl DocumentActivity {
+    fn buckets(&mut self, granularity: ActivityGranularity) -> &mut Buckets {
+        match granularity {
+            ActivityGranularity::Minute => &mut self.minutes,
+            ActivityGranularity::Hour => &mut self.hours,
+        }
+    }
+}
+
+/// In-memory aggregation of document activity into time buckets.
+///
+/// Every update applied by a client is counted in the current minute and hour
+/// buckets of its document, along with the client that applied it, so product
+/// dashboards can show when collaboration happens and how many people take
+/// part. Only the buckets within the retention window are kept; activity is
+/// not persisted and starts over when the server restarts.
+pub struct ActivityTracker {
+    retention: ActivityRetention,
+    documents: Mutex<HashMap<String, DocumentActivity>>,
+}
+
+impl ActivityTracker {
+    /// Creates a tracker retaining a bounded number of buckets per document.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention` - Number of minute and hour buckets kept per document
+    ///
+    /// # Returns
+    ///
+    /// A new `ActivityTracker` instance.
+    pub fn new(retention: ActivityRetention) -> Self {
+        Self {
+            retention,
+            documents: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts an update applied to a document at the current time.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the updated document
+    /// * `editor` - Identifier of the client that applied the update
+    pub fn record(&self, doc_id: &str, editor: &str) {
+        if self.retention.minutes == 0 && self.retention.hours == 0 {
+            return;
+        }
+
+        let now = now();
+        let mut documents = self.documents();
+        let activity = documents.entry(doc_id.to_string()).or_default();
+        for granularity in [ActivityGranularity::Minute, ActivityGranularity::Hour] {
+            let retained = self.retention.buckets(granularity);
+            activity
+                .buckets(granularity)
+                .record(granularity, retained, now, editor);
+        }
+    }
+
+    /// Returns the activity of a document within the retention window.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `granularity` - Width of the returned buckets
+    ///
+    /// # Returns
+    ///
+    /// The buckets during which the document was edited, oldest first; buckets
+    /// without activity are omitted
+    pub fn activity(&self, doc_id: &str, granularity: ActivityGranularity) -> Vec<ActivityBucket> {
+        let mut documents = self.documents();
+        let Some(activity) = documents.get_mut(doc_id) else {
+            return Vec::new();
+        };
+
+        let buckets = activity.buckets(granularity);
+        buckets.prune(granularity, self.retention.buckets(granularity), now());
+        buckets
+            .0
+            .iter()
+            .map(|bucket| ActivityBucket {
+                start: bucket.start,
+                updates: bucket.updates,
+                unique_editors: bucket.editors.len(),
+            })
+            .collect()
+    }
+
+    fn documents(&self) -> MutexGuard<'_, HashMap<String, DocumentActivity>> {
+        self.documents
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Discards the activity of a deleted document.
+    pub fn forget(&self, doc_id: &str) {
+        self.documents().remove(doc_id);
+    }
+}
+
+impl Default for ActivityTracker {
+    fn default() -> Self {
+        Self::new(ActivityRetention::default())
+    }
+}
+
+/// Returns the start of the bucket of the given width containing a time.
+fn bucket_start(time: i64, width: i64) -> i64 {
+    time - time.rem_euclid(width)
+}
+
+/// Returns the current time as Unix seconds.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::repositories::document_store::DocumentStore;
+
+/// Cold storage tier documents left untouched are moved to.
+///
+/// Archived documents are removed from the primary repository and only kept in
+/// the archive store, usually cheaper and slower storage such as an object
+/// storage bucket. The tier keeps track of when each document was last opened,
+/// and of which documents are archived, so that opening one rehydrates it.
+pub struct ArchiveTier {
+    /// Store holding the state of archived documents
+    store: Arc<dyn DocumentStore>,
+    /// Time a document must be left untouched before it is archived
+    archive_after: Duration,
+    /// Documents currently archived
+    archived: Mutex<HashSet<String>>,
+    /// Last time each document was opened since the service started
+    touched: Mutex<HashMap<String, Instant>>,
+    /// Stands for the last access of the documents not opened since the service started
+    started: Instant,
+    /// Serializes the moves between the tiers
+    moving: tokio::sync::Mutex<()>,
+}
+
+impl ArchiveTier {
+    /// Creates a tier.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - Store holding the state of archived documents
+    /// * `archive_after` - Time a document must be left untouched before it is archived
+    /// * `archived` - Documents already archived, as recorded in their metadata
+    pub fn new(
+        store: Arc<dyn DocumentStore>,
+        archive_after: Duration,
+        archived: HashSet<String>,
+    ) -> Self {
+        Self {
+            store,
+            archive_after,
+            archived: Mutex::new(archived),
+            touched: Mutex::new(HashMap::new()),
+            started: Instant::now(),
+            moving: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Returns the store holding the state of archived documents.
+    pub fn store(&self) -> &dyn DocumentStore {
+        self.store.as_ref()
+    }
+
+    /// Waits until no document is being moved between the tiers, then holds the others' moves.
+    pub async fn lock_moves(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.moving.lock().await
+    }
+
+    /// Records that a document was opened.
+    pub fn touch(&self, doc_id: &str) {
+        lock(&self.touched).insert(doc_id.to_string(), Instant::now());
+    }
+
+    /// Returns whether a document was left untouched long enough to be archived.
+    pub fn is_idle(&self, doc_id: &str) -> bool {
+        let touched = lock(&self.touched)
+            .get(doc_id)
+            .copied()
+            .unwrap_or(self.started);
+        touched.elapsed() >= self.archive_after
+    }
+
+    /// Returns whether a document is archived.
+    pub fn is_archived(&self, doc_id: &str) -> bool {
+        lock(&self.archived).contains(doc_id)
+    }
+
+    /// Returns the archived documents.
+    pub fn archived(&self) -> Vec<String> {
+        lock(&self.archived).iter().cloned().collect()
+    }
+
+    /// Records whether a document is archived.
+    pub fn set_archived(&self, doc_id: &str, archived: bool) {
+        let mut documents = lock(&self.archived);
+        if archived {
+            documents.insert(doc_id.to_string());
+            lock(&self.touched).remove(doc_id);
+        } else {
+            documents.remove(doc_id);
+        }
+    }
+}
+
+/// Locks a mutex, recovering it if a panicking thread poisoned it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,390 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, Semaphore};
+
+use crate::{
+    entities::document::CollaborativeDocument,
+    errors::{DomainError, DomainResult},
+};
+
+/// A kind of CPU-heavy CRDT operation executed on the compute pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrdtOperation {
+    /// Decoding and integrating a client update
+    ApplyUpdate,
+    /// Computing the updates a client is missing from its state vector
+    ComputeDiff,
+    /// Encoding the complete document state
+    EncodeState,
+    /// Extracting the document's text content
+    ReadContent,
+}
+
+impl CrdtOperation {
+    /// All operation kinds, in the order used for metrics.
+    pub const ALL: [CrdtOperation; 4] = [
+        CrdtOperation::ApplyUpdate,
+        CrdtOperation::ComputeDiff,
+        CrdtOperation::EncodeState,
+        CrdtOperation::ReadContent,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for CrdtOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ApplyUpdate => write!(f, "apply_update"),
+            Self::ComputeDiff => write!(f, "compute_diff"),
+            Self::EncodeState => write!(f, "encode_state"),
+            Self::ReadContent => write!(f, "read_content"),
+        }
+    }
+}
+
+/// Longest time a document waits before its next operation after overrunning a budget.
+const MAX_COMPUTE_DEBT: Duration = Duration::from_secs(1);
+
+/// Time budgets for CRDT operations.
+///
+/// Yrs operations cannot be preempted, so a budget does not abort the operation.
+/// Instead, the pool is time-sliced between documents: a document whose operation
+/// exceeds its budget waits out the overrun, up to a second, before its next
+/// operation may take a slot of the pool, leaving the slots to other documents.
+/// Runs exceeding their budget are also counted so operators can spot documents
+/// that stall the compute pool.
+#[derive(Clone, Debug)]
+pub struct ComputeBudget {
+    /// Maximum number of CRDT operations running concurrently (`0` = number of CPU cores)
+    pub max_concurrency: usize,
+    /// Budget for applying a client update
+    pub apply_update: Duration,
+    /// Budget for computing a diff against a client state vector
+    pub compute_diff: Duration,
+    /// Budget for encoding the complete document state
+    pub encode_state: Duration,
+    /// Budget for extracting the document's text content
+    pub read_content: Duration,
+}
+
+impl ComputeBudget {
+    /// Returns the budget for the given operation kind.
+    pub fn for_operation(&self, operation: CrdtOperation) -> Duration {
+        match operation {
+            CrdtOperation::ApplyUpdate => self.apply_update,
+            CrdtOperation::ComputeDiff => self.compute_diff,
+            CrdtOperation::EncodeState => self.encode_state,
+            CrdtOperation::ReadContent => self.read_content,
+        }
+    }
+}
+
+impl Default for ComputeBudget {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 0,
+            apply_update: Duration::from_millis(50),
+            compute_diff: Duration::from_millis(50),
+            encode_state: Duration::from_millis(100),
+            read_content: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Snapshot of the metrics recorded for one operation kind.
+#[derive(Clone, Debug)]
+pub struct OperationStats {
+    /// The operation kind
+    pub operation: CrdtOperation,
+    /// Number of completed operations
+    pub calls: u64,
+    /// Total time spent executing operations
+    pub total_time: Duration,
+    /// Longest single execution
+    pub max_time: Duration,
+    /// Number of executions that exceeded the operation's budget
+    pub over_budget: u64,
+}
+
+/// Occupancy of the compute pool, sampled for capacity reports.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ComputeLoad {
+    /// Number of operations currently running
+    pub running: usize,
+    /// Maximum number of operations running concurrently
+    pub concurrency: usize,
+    /// Number of operations completed since the server started
+    pub calls: u64,
+    /// Number of completed operations that exceeded their budget
+    pub over_budget: u64,
+}
+
+#[derive(Debug, Default)]
+struct OperationMetrics {
+    calls: AtomicU64,
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+    over_budget: AtomicU64,
+}
+
+/// A document operated on through the compute pool.
+///
+/// Operations reading the document share it, while those changing it hold it
+/// exclusively. The time the document's last operation ran over budget is kept
+/// alongside it, so operations sharing the document record it as well.
+#[derive(Clone)]
+pub struct PooledDocument {
+    document: Arc<RwLock<CollaborativeDocument>>,
+    /// Microseconds the last operation on the document ran over its budget, waited out by the
+    /// next
+    debt: Arc<AtomicU64>,
+}
+
+impl PooledDocument {
+    /// Wraps a document to operate on it through the compute pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document
+    ///
+    /// # Returns
+    ///
+    /// A new `PooledDocument` instance.
+    pub fn new(document: CollaborativeDocument) -> Self {
+        Self {
+            document: Arc::new(RwLock::new(document)),
+            debt: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Locks the document for reading, for operations too quick for the pool.
+    pub async fn read(&self) -> RwLockReadGuard<'_, CollaborativeDocument> {
+        self.document.read().await
+    }
+
+    /// Locks the document for writing, for operations too quick for the pool.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, CollaborativeDocument> {
+        self.document.write().await
+    }
+
+    /// Waits out the time the last operation ran over budget, capped to `MAX_COMPUTE_DEBT`.
+    ///
+    /// The document is not locked meanwhile, so the operations already holding
+    /// it are not delayed.
+    async fn wait_out_debt(&self) {
+        let debt =
+            Duration::from_micros(self.debt.swap(0, Ordering::Relaxed)).min(MAX_COMPUTE_DEBT);
+        if !debt.is_zero() {
+            tokio::time::sleep(debt).await;
+        }
+    }
+}
+
+/// Compute pool for CPU-heavy CRDT operations.
+///
+/// Operations run on Tokio's blocking thread pool so that applying a huge update
+/// or diffing a massive document never stalls the async workers serving the
+/// WebSocket and gRPC transports. A semaphore bounds how many operations run at
+/// once, and every execution is timed against its per-operation budget.
+#[derive(Debug)]
+pub struct ComputePool {
+    budget: ComputeBudget,
+    permits: Arc<Semaphore>,
+    concurrency: usize,
+    metrics: [OperationMetrics; 4],
+}
+
+impl ComputePool {
+    /// Creates a new compute pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Concurrency limit and per-operation time budgets
+    ///
+    /// # Returns
+    ///
+    /// A new `ComputePool` instance.
+    pub fn new(budget: ComputeBudget) -> Self {
+        let concurrency = match budget.max_concurrency {
+            0 => std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            n => n,
+        };
+
+        Self {
+            budget,
+            permits: Arc::new(Semaphore::new(concurrency)),
+            concurrency,
+            metrics: Default::default(),
+        }
+    }
+
+    /// Runs a CRDT operation changing a document on the blocking thread pool.
+    ///
+    /// The operation first waits out the time the document's previous operation
+    /// ran over budget, then locks the document exclusively for its duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The kind of operation, used for budgets and metrics
+    /// * `document` - The document to operate on
+    /// * `f` - The operation itself
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(T)` - The operation's result
+    /// * `Err(DomainError)` - `Internal` if the operation panicked or the pool is closed
+    pub async fn run<T, F>(
+        &self,
+        operation: CrdtOperation,
+        document: PooledDocument,
+        f: F,
+    ) -> DomainResult<T>
+    where
+        F: FnOnce(&mut CollaborativeDocument) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        document.wait_out_debt().await;
+        // Lock the document first so queued operations on one document do not hold
+        // permits that other documents could use
+        let doc = document.document.clone().write_owned().await;
+        self.execute(operation, &document, doc, move |mut doc| f(&mut doc))
+            .await
+    }
+
+    /// Runs a CRDT operation reading a document on the blocking thread pool.
+    ///
+    /// The operation first waits out the time the document's previous operation
+    /// ran over budget. The document is then shared with the other operations
+    /// reading it, and only waits for those changing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - The kind of operation, used for budgets and metrics
+    /// * `document` - The document to read
+    /// * `f` - The operation itself
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(T)` - The operation's result
+    /// * `Err(DomainError)` - `Internal` if the operation panicked or the pool is closed
+    pub async fn read<T, F>(
+        &self,
+        operation: CrdtOperation,
+        document: PooledDocument,
+        f: F,
+    ) -> DomainResult<T>
+    where
+        F: FnOnce(&CollaborativeDocument) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        document.wait_out_debt().await;
+        let doc = document.document.clone().read_owned().await;
+        self.execute(operation, &document, doc, move |doc| f(&doc))
+            .await
+    }
+
+    /// Runs an operation on a locked document once it holds a permit, recording the
+    /// time it runs over budget.
+    async fn execute<G, T, F>(
+        &self,
+        operation: CrdtOperation,
+        document: &PooledDocument,
+        doc: G,
+        f: F,
+    ) -> DomainResult<T>
+    where
+        G: Send + 'static,
+        F: FnOnce(G) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| DomainError::Internal(format!("Compute pool closed: {}", e)))?;
+
+        let budget = self.budget.for_operation(operation);
+        let started = Instant::now();
+        let debt = document.debt.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let result = f(doc);
+            let overrun = started.elapsed().saturating_sub(budget);
+            debt.store(overrun.as_micros() as u64, Ordering::Relaxed);
+            result
+        })
+        .await
+        .map_err(|e| DomainError::Internal(format!("CRDT operation {} failed: {}", operation, e)));
+
+        self.record(operation, started.elapsed());
+        result
+    }
+
+    /// Returns a snapshot of the metrics for every operation kind.
+    pub fn stats(&self) -> Vec<OperationStats> {
+        CrdtOperation::ALL
+            .iter()
+            .map(|&operation| {
+                let metrics = &self.metrics[operation.index()];
+                OperationStats {
+                    operation,
+                    calls: metrics.calls.load(Ordering::Relaxed),
+                    total_time: Duration::from_micros(metrics.total_micros.load(Ordering::Relaxed)),
+                    max_time: Duration::from_micros(metrics.max_micros.load(Ordering::Relaxed)),
+                    over_budget: metrics.over_budget.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns how busy the pool is and how many operations exceeded their budget.
+    pub fn load(&self) -> ComputeLoad {
+        let (calls, over_budget) = self.metrics.iter().fold((0, 0), |(calls, over), metrics| {
+            (
+                calls + metrics.calls.load(Ordering::Relaxed),
+                over + metrics.over_budget.load(Ordering::Relaxed),
+            )
+        });
+
+        ComputeLoad {
+            running: self
+                .concurrency
+                .saturating_sub(self.permits.available_permits()),
+            concurrency: self.concurrency,
+            calls,
+            over_budget,
+        }
+    }
+
+    fn record(&self, operation: CrdtOperation, elapsed: Duration) {
+        let metrics = &self.metrics[operation.index()];
+        let micros = elapsed.as_micros() as u64;
+
+        metrics.calls.fetch_add(1, Ordering::Relaxed);
+        metrics.total_micros.fetch_add(micros, Ordering::Relaxed);
+        metrics.max_micros.fetch_max(micros, Ordering::Relaxed);
+
+        if elapsed > self.budget.for_operation(operation) {
+            metrics.over_budget.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for ComputePool {
+    fn default() -> Self {
+        Self::new(ComputeBudget::default())
+    }
+}
@@ -0,0 +1,446 @@
+use std::sync::{Arc, Weak};
+
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+
+use crate::{
+    entities::document::CollaborativeDocument,
+    errors::{DomainError, DomainResult},
+    services::document_service::{
+        SingleDocumentServiceImpl, SyncResponse, UpdateNotification, EMPTY_STATE_VECTOR,
+    },
+    value_objects::{
+        diff_throttle::DiffThrottle,
+        update_limits::{SizeLimit, UpdateLimits},
+        update_origin::TransactionOrigin,
+    },
+};
+
+/// Capacity of a document's command channel; senders wait once it is full.
+const COMMAND_CHANNEL_CAPACITY: usize = 256;
+
+/// Maximum number of queued commands handled under a single lock of the document.
+const MAX_BATCH_COMMANDS: usize = 64;
+
+/// Sizes of a document before and after a client update was applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AppliedUpdate {
+    /// Approximate encoded size of the document before the update
+    pub previous_size: usize,
+    /// Characters across the document's text roots before the update
+    pub previous_characters: usize,
+    /// Approximate encoded size of the document after the update
+    pub size: usize,
+    /// Characters across the document's text roots after the update
+    pub characters: usize,
+    /// Sequence number of the broadcast carrying the update
+    pub sequence_number: u64,
+}
+
+impl AppliedUpdate {
+    /// Returns the sizes after the update, as if the document had not grown.
+    fn settled(self) -> Self {
+        Self {
+            previous_size: self.size,
+            previous_characters: self.characters,
+            ..self
+        }
+    }
+}
+
+/// A client update the document refused.
+#[derive(Clone, Debug)]
+pub struct RejectedUpdate {
+    /// Server-wide size limit the update exceeds, if that is why it was refused
+    pub limit: Option<SizeLimit>,
+    /// Why the update was refused
+    pub error: DomainError,
+}
+
+impl From<DomainError> for RejectedUpdate {
+    fn from(error: DomainError) -> Self {
+        Self { limit: None, error }
+    }
+}
+
+/// Outcome of a client update handled by a document actor.
+pub type UpdateOutcome = Result<AppliedUpdate, RejectedUpdate>;
+
+/// Updates a client is missing, with what it needs to follow the document afterwards.
+#[derive(Debug)]
+pub struct DocumentDiff {
+    /// The missing updates, a single one unless the diff was chunked
+    pub updates: Vec<Vec<u8>>,
+    /// The current state vector of the document
+    pub state_vector: Vec<u8>,
+    /// Sequence number of the last update included in the diff
+    pub sequence_number: u64,
+    /// Receiver of the updates broadcast after the diff
+    pub receiver: broadcast::Receiver<UpdateNotification>,
+}
+
+/// A subscription to a document's updates.
+#[derive(Debug)]
+pub struct Subscription {
+    /// Sequence number of the last update broadcast before the subscription
+    pub sequence_number: u64,
+    /// The state vector of the document when it was subscribed to
+    pub state_vector: Vec<u8>,
+    /// Receiver of the updates broadcast after the subscription
+    pub receiver: broadcast::Receiver<UpdateNotification>,
+}
+
+/// A command sent to a document actor, answered through its reply channel.
+enum DocumentCommand {
+    /// Apply a client update
+    ApplyUpdate {
+        update: Vec<u8>,
+        origin: TransactionOrigin,
+        reply: oneshot::Sender<UpdateOutcome>,
+    },
+    /// Compute the updates missing from a state vector, the whole document without one
+    Diff {
+        state_vector: Option<Vec<u8>>,
+        throttle: Option<DiffThrottle>,
+        reply: oneshot::Sender<DomainResult<DocumentDiff>>,
+    },
+    /// Subscribe to the document's updates
+    Subscribe {
+        reply: oneshot::Sender<Subscription>,
+    },
+    /// Encode the complete state of the document
+    Snapshot {
+        reply: oneshot::Sender<DomainResult<SyncResponse>>,
+    },
+}
+
+impl DocumentCommand {
+    /// Checks whether the command applies an update.
+    fn is_update(&self) -> bool {
+        matches!(self, Self::ApplyUpdate { .. })
+    }
+
+    /// Checks whether the command applies an update of the given origin.
+    fn is_update_from(&self, origin: &TransactionOrigin) -> bool {
+        matches!(self, Self::ApplyUpdate { origin: other, .. } if other == origin)
+    }
+}
+
+/// Handle of the task serving the hot operations of a document.
+///
+/// Clients no longer contend for the document's lock: their updates, diffs,
+/// subscriptions and snapshots are queued to the document's actor, which
+/// handles them in order, so a diff always matches the sequence number and
+/// subscription returned with it. The actor drains up to 64 queued commands
+/// under a single lock of the document, and consecutive updates from the same
+/// client are merged and applied as one, then logged and broadcast once.
+///
+/// Lifecycle operations, such as eviction or archival, still lock the document
+/// directly. Once a document is unloaded or deleted, its actor stops and drops
+/// the commands it has not handled, whose senders get `None` and open the
+/// document again.
+#[derive(Clone, Debug)]
+pub struct DocumentActor {
+    commands: mpsc::Sender<DocumentCommand>,
+}
+
+impl DocumentActor {
+    /// Spawns the actor of a document.
+    ///
+    /// The actor only keeps a weak reference to the document, and stops once
+    /// every handle is dropped or the document is retired or dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document, as held by the repository
+    /// * `limits` - Server-wide limits on the size of updates and documents
+    ///
+    /// # Returns
+    ///
+    /// A handle sending commands to the actor
+    pub fn spawn(document: &Arc<RwLock<SingleDocumentServiceImpl>>, limits: UpdateLimits) -> Self {
+        let (commands, receiver) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+        tokio::spawn(Self::run(Arc::downgrade(document), receiver, limits));
+        Self { commands }
+    }
+
+    /// Checks whether the actor stopped.
+    pub fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+
+    /// Sends a command and waits for its reply.
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> DocumentCommand,
+    ) -> Option<T> {
+        let (reply, replied) = oneshot::channel();
+        self.commands.send(command(reply)).await.ok()?;
+        replied.await.ok()
+    }
+
+    /// Applies a client update, tracked in the client's undo stack if the document's policy
+    /// enables undo.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The binary update
+    /// * `origin` - Origin of the update, tagging its transaction and its broadcast
+    ///
+    /// # Returns
+    ///
+    /// The outcome of the update, or `None` if the actor stopped before handling it
+    pub async fn apply_update(
+        &self,
+        update: Vec<u8>,
+        origin: TransactionOrigin,
+    ) -> Option<UpdateOutcome> {
+        self.request(|reply| DocumentCommand::ApplyUpdate {
+            update,
+            origin,
+            reply,
+        })
+        .await
+    }
+
+    /// Computes the updates missing from a client's state vector and subscribes to the
+    /// following ones.
+    ///
+    /// Without a state vector, or when it cannot be decoded, the whole document
+    /// is returned. With a throttle, a diff above its chunk threshold is split
+    /// into several updates.
+    ///
+    /// # Arguments
+    ///
+    /// * `state_vector` - The client's binary state vector, if any
+    /// * `throttle` - Chunking threshold and chunk size, or `None` for a single update
+    ///
+    /// # Returns
+    ///
+    /// The diff or the error computing it, or `None` if the actor stopped before computing it
+    pub async fn diff(
+        &self,
+        state_vector: Option<Vec<u8>>,
+        throttle: Option<DiffThrottle>,
+    ) -> Option<DomainResult<DocumentDiff>> {
+        self.request(|reply| DocumentCommand::Diff {
+            state_vector,
+            throttle,
+            reply,
+        })
+        .await
+    }
+
+    /// Subscribes to the document's updates.
+    ///
+    /// # Returns
+    ///
+    /// The subscription, or `None` if the actor stopped before subscribing
+    pub async fn subscribe(&self) -> Option<Subscription> {
+        self.request(|reply| DocumentCommand::Subscribe { reply })
+            .await
+    }
+
+    /// Encodes the complete state of the document as a single update.
+    ///
+    /// # Returns
+    ///
+    /// The state and state vector of the document or the error encoding them, or `None` if the
+    /// actor stopped before encoding them
+    pub async fn snapshot(&self) -> Option<DomainResult<SyncResponse>> {
+        self.request(|reply| DocumentCommand::Snapshot { reply })
+            .await
+    }
+
+    /// Handles the commands sent to the actor until it stops.
+    async fn run(
+        document: Weak<RwLock<SingleDocumentServiceImpl>>,
+        mut commands: mpsc::Receiver<DocumentCommand>,
+        limits: UpdateLimits,
+    ) {
+        let mut batch = Vec::with_capacity(MAX_BATCH_COMMANDS);
+        while commands.recv_many(&mut batch, MAX_BATCH_COMMANDS).await > 0 {
+            let Some(document) = document.upgrade() else {
+                break;
+            };
+
+            // Batches that only read share the document with its other readers
+            let handled = if batch.iter().any(DocumentCommand::is_update) {
+                let state = document.write().await;
+                Self::handle_batch(&state, &mut batch, limits).await
+            } else {
+                let state = document.read().await;
+                Self::handle_batch(&state, &mut batch, limits).await
+            };
+            if !handled {
+                break;
+            }
+        }
+
+        // Unloaded or deleted: the commands left are dropped, and their senders
+        // open the document again
+        commands.close();
+    }
+
+    /// Handles a batch of commands on a locked document.
+    ///
+    /// Returns `false`, leaving the batch untouched, if the document was retired.
+    async fn handle_batch(
+        state: &SingleDocumentServiceImpl,
+        batch: &mut Vec<DocumentCommand>,
+        limits: UpdateLimits,
+    ) -> bool {
+        if state.is_retired() {
+            return false;
+        }
+
+        let mut queued = batch.drain(..).peekable();
+        while let Some(command) = queued.next() {
+            match command {
+                DocumentCommand::ApplyUpdate {
+                    update,
+                    origin,
+                    reply,
+                } => {
+                    let (mut updates, mut replies) = (vec![update], vec![reply]);
+                    while let Some(DocumentCommand::ApplyUpdate { update, reply, .. }) =
+                        queued.next_if(|next| next.is_update_from(&origin))
+                    {
+                        updates.push(update);
+                        replies.push(reply);
+                    }
+                    Self::apply_batch(state, limits, &origin, updates, replies).await;
+                }
+                DocumentCommand::Diff {
+                    state_vector,
+                    throttle,
+                    reply,
+                } => {
+                    let diff = Self::compute_diff(state, state_vector.as_deref(), throttle).await;
+                    let _ = reply.send(diff);
+                }
+                DocumentCommand::Subscribe { reply } => {
+                    state.flush_broadcast();
+                    let _ = reply.send(Subscription {
+                        sequence_number: state.sequence_number(),
+                        state_vector: state.get_state_vector().await,
+                        receiver: state.subscribe(),
+                    });
+                }
+                DocumentCommand::Snapshot { reply } => {
+                    let _ = reply.send(state.get_state().await);
+                }
+            }
+        }
+        true
+    }
+
+    /// Applies consecutive updates of one origin, merged into a single update if possible.
+    ///
+    /// If the updates cannot be merged, or the merged update is refused, they
+    /// are applied one by one, so a refused update does not fail the others.
+    async fn apply_batch(
+        state: &SingleDocumentServiceImpl,
+        limits: UpdateLimits,
+        origin: &TransactionOrigin,
+        updates: Vec<Vec<u8>>,
+        replies: Vec<oneshot::Sender<UpdateOutcome>>,
+    ) {
+        if updates.len() > 1 {
+            if let Ok(merged) = CollaborativeDocument::merge_updates(&updates) {
+                if let Ok(applied) = Self::apply_to(state, limits, &merged, origin, None).await {
+                    // Only the first reply reports the growth, so warnings are published once
+                    for (i, reply) in replies.into_iter().enumerate() {
+                        let _ = reply.send(Ok(if i == 0 { applied } else { applied.settled() }));
+                    }
+                    return;
+                }
+            }
+        }
+
+        for (update, reply) in updates.into_iter().zip(replies) {
+            let _ = reply.send(Self::apply_to(state, limits, &update, origin, None).await);
+        }
+    }
+
+    /// Applies a client update to a locked document.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The locked document
+    /// * `limits` - Server-wide limits on the size of updates and documents
+    /// * `update` - The binary update
+    /// * `origin` - Origin of the update
+    /// * `revertible` - Key to record the update under so it can be reverted, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AppliedUpdate)` - The sizes of the document before and after the update
+    /// * `Err(RejectedUpdate)` - The exceeded limit, if any, and why the update couldn't be applied
+    pub async fn apply_to(
+        state: &SingleDocumentServiceImpl,
+        limits: UpdateLimits,
+        update: &[u8],
+        origin: &TransactionOrigin,
+        revertible: Option<&str>,
+    ) -> UpdateOutcome {
+        limits
+            .check(state.size(), update.len())
+            .map_err(|(limit, error)| RejectedUpdate {
+                limit: Some(limit),
+                error,
+            })?;
+
+        let previous_size = state.size();
+        let previous_characters = state.content_stats().characters;
+        match revertible {
+            Some(key) => state.apply_revertible_update(update, origin, key).await?,
+            None => state.apply_tracked_update(update, origin).await?,
+        }
+        Ok(AppliedUpdate {
+            previous_size,
+            previous_characters,
+            size: state.size(),
+            characters: state.content_stats().characters,
+            sequence_number: state.pending_sequence_number(),
+        })
+    }
+
+    /// Computes the updates missing from a state vector on a locked document.
+    async fn compute_diff(
+        state: &SingleDocumentServiceImpl,
+        state_vector: Option<&[u8]>,
+        throttle: Option<DiffThrottle>,
+    ) -> DomainResult<DocumentDiff> {
+        // Updates held back are broadcast first, as the diff covers them
+        state.flush_broadcast();
+        let updates = match throttle {
+            Some(throttle) => {
+                let chunks = match state_vector {
+                    Some(sv) => state.diff_chunks(sv, &throttle).await.ok(),
+                    None => None,
+                };
+                match chunks {
+                    Some(chunks) => chunks,
+                    None => state.diff_chunks(EMPTY_STATE_VECTOR, &throttle).await?,
+                }
+            }
+            None => {
+                let diff = match state_vector {
+                    Some(sv) => state.diff_update(sv).await.ok(),
+                    None => None,
+                };
+                match diff {
+                    Some(update) => vec![update],
+                    None => vec![state.get_full_update().await?],
+                }
+            }
+        };
+
+        Ok(DocumentDiff {
+            updates,
+            state_vector: state.get_state_vector().await,
+            sequence_number: state.sequence_number(),
+            receiver: state.subscribe(),
+        })
+    }
+}
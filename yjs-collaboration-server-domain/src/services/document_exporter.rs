@@ -0,0 +1,640 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use yrs::{
+    types::{
+        text::{Diff, YChange},
+        Attrs, ToJson,
+    },
+    Any, ArrayRef, GetString, MapRef, Out, ReadTxn, Text, TextRef, Transact, Xml, XmlElementRef,
+    XmlFragment, XmlFragmentRef, XmlOut, XmlTextRef,
+};
+
+use crate::{
+    entities::{
+        clean_copy::{root_kind, RootKind},
+        document::CollaborativeDocument,
+    },
+    errors::{DomainError, DomainResult},
+    value_objects::export_format::ExportFormat,
+};
+
+/// Separator between the blocks of a Markdown export.
+const MARKDOWN_BLOCK_SEPARATOR: &str = "\n\n";
+
+/// Separator between the blocks of a plain text export.
+const TEXT_BLOCK_SEPARATOR: &str = "\n";
+
+/// A root of the exported document, typed after its inferred kind.
+enum Root {
+    Map(MapRef),
+    Array(ArrayRef),
+    Text(TextRef),
+    Xml(XmlFragmentRef),
+}
+
+impl Root {
+    /// Returns whether the root holds structured data rather than text.
+    fn is_data(&self) -> bool {
+        matches!(self, Self::Map(_) | Self::Array(_))
+    }
+}
+
+/// Domain service serializing the content of a document into readable formats.
+///
+/// The exporter walks the document's shared types rather than its CRDT state,
+/// so the result carries the current content only. Roots received from clients
+/// are not typed on the server, so their kind is inferred from their content,
+/// as for clean copies. XML roots are read as ProseMirror / Tiptap documents
+/// and text roots as Quill deltas: their node types, formatting attributes and
+/// marks are mapped to Markdown, e.g. `heading` nodes and `header` attributes
+/// to headings and `bold` or `strong` marks to strong emphasis.
+pub struct DocumentExporter;
+
+impl DocumentExporter {
+    /// Exports the content of a document.
+    ///
+    /// * JSON maps every root to its content: maps and arrays as their JSON value, texts as strings
+    ///   and XML roots as ProseMirror-style node trees (`{"type": ..., "attrs": {...}, "content":
+    ///   [...]}`)
+    /// * Markdown renders the text and XML roots, followed by the map and array roots as JSON code
+    ///   blocks
+    /// * Plain text keeps the text of the text and XML roots, one block per line
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document to export
+    /// * `format` - The format to export the document to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The exported document
+    /// * `Err(DomainError)` - `Internal` if the content could not be serialized
+    pub fn export(document: &CollaborativeDocument, format: ExportFormat) -> DomainResult<String> {
+        let txn = document.doc.transact();
+        let roots = typed_roots(&txn);
+
+        match format {
+            ExportFormat::Json => {
+                let content: BTreeMap<&str, Any> = roots
+                    .iter()
+                    .map(|(name, root)| (name.as_str(), root_json(&txn, root)))
+                    .collect();
+                to_json_string(&content)
+            }
+            ExportFormat::Markdown => export_blocks(&txn, &roots, true),
+            ExportFormat::Text => export_blocks(&txn, &roots, false),
+        }
+    }
+}
+
+/// Types the non-empty roots of a document, text roots first, each group sorted by name.
+fn typed_roots<T: ReadTxn>(txn: &T) -> Vec<(String, Root)> {
+    let mut roots: Vec<(String, Root)> = txn
+        .root_refs()
+        .filter_map(|(name, value)| {
+            let root = match (root_kind(txn, &value)?, value) {
+                (RootKind::Map, Out::YMap(map)) => Root::Map(map),
+                (RootKind::Map, Out::UndefinedRef(branch)) => Root::Map(MapRef::from(branch)),
+                (RootKind::Array, Out::YArray(array)) => Root::Array(array),
+                (RootKind::Array, Out::UndefinedRef(branch)) => Root::Array(ArrayRef::from(branch)),
+                (RootKind::Text, Out::YText(text)) => Root::Text(text),
+                (RootKind::Text, Out::UndefinedRef(branch)) => Root::Text(TextRef::from(branch)),
+                (RootKind::XmlFragment, Out::YXmlFragment(fragment)) => Root::Xml(fragment),
+                (RootKind::XmlFragment, Out::UndefinedRef(branch)) => {
+                    Root::Xml(XmlFragmentRef::from(branch))
+                }
+                _ => return None,
+            };
+            Some((name.to_string(), root))
+        })
+        .collect();
+
+    roots.sort_by(|(a_name, a), (b_name, b)| (a.is_data(), a_name).cmp(&(b.is_data(), b_name)));
+    roots
+}
+
+fn to_json_string<V: serde::Serialize>(value: &V) -> DomainResult<String> {
+    sonic_rs::to_string_pretty(value)
+        .map_err(|e| DomainError::Internal(format!("Failed to serialize the document: {}", e)))
+}
+
+/// Converts a root to its JSON value.
+fn root_json<T: ReadTxn>(txn: &T, root: &Root) -> Any {
+    match root {
+        Root::Map(map) => map.to_json(txn),
+        Root::Array(array) => array.to_json(txn),
+        Root::Text(text) => Any::String(text.get_string(txn).into()),
+        Root::Xml(fragment) => Any::Array(xml_children_json(txn, fragment).into()),
+    }
+}
+
+/// Builds a JSON object from its entries.
+fn object(entries: Vec<(&str, Any)>) -> Any {
+    let entries: HashMap<String, Any> = entries
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+    Any::Map(Arc::new(entries))
+}
+
+/// Converts the children of an XML node to ProseMirror-style JSON nodes.
+fn xml_children_json<T: ReadTxn>(txn: &T, parent: &impl XmlFragment) -> Vec<Any> {
+    let mut nodes = Vec::new();
+    for child in parent.children(txn) {
+        match child {
+            XmlOut::Element(element) => {
+                let mut entries = vec![("type", Any::String(element.tag().clone()))];
+                let attrs: Vec<(&str, Any)> = element
+                    .attributes(txn)
+                    .map(|(name, value)| (name, attribute_value(value)))
+                    .collect();
+                if !attrs.is_empty() {
+                    entries.push(("attrs", object(attrs)));
+                }
+                let content = xml_children_json(txn, &element);
+                if !content.is_empty() {
+                    entries.push(("content", Any::Array(content.into())));
+                }
+                nodes.push(object(entries));
+            }
+            XmlOut::Text(text) => nodes.extend(text_nodes_json(txn, &text)),
+            XmlOut::Fragment(fragment) => nodes.extend(xml_children_json(txn, &fragment)),
+        }
+    }
+    nodes
+}
+
+/// Converts an XML text to ProseMirror-style text nodes, one per formatting run.
+fn text_nodes_json<T: ReadTxn>(txn: &T, text: &XmlTextRef) -> Vec<Any> {
+    let chunks: Vec<Diff<YChange>> = text.diff(txn, YChange::identity);
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let mut entries = match chunk.insert {
+                Out::Any(Any::String(s)) => {
+                    vec![
+                        ("type", Any::String("text".into())),
+                        ("text", Any::String(s)),
+                    ]
+                }
+                other => vec![
+                    ("type", Any::String("embed".into())),
+                    ("value", other.to_json(txn)),
+                ],
+            };
+            let marks: Vec<Any> = chunk
+                .attributes
+                .iter()
+                .flat_map(|attrs| attrs.iter())
+                .map(|(name, value)| match value {
+                    Any::Map(_) => object(vec![
+                        ("type", Any::String(name.clone())),
+                        ("attrs", value.clone()),
+                    ]),
+                    _ => object(vec![("type", Any::String(name.clone()))]),
+                })
+                .collect();
+            if !marks.is_empty() {
+                entries.push(("marks", Any::Array(marks.into())));
+            }
+            object(entries)
+        })
+        .collect()
+}
+
+/// Renders the roots of a document as Markdown or plain text.
+fn export_blocks<T: ReadTxn>(
+    txn: &T,
+    roots: &[(String, Root)],
+    markdown: bool,
+) -> DomainResult<String> {
+    let renderer = Renderer { txn, markdown };
+    let mut blocks = Vec::new();
+    for (_, root) in roots {
+        match root {
+            Root::Text(text) => blocks.extend(renderer.text_blocks(text)),
+            Root::Xml(fragment) => blocks.extend(renderer.xml_blocks(fragment)),
+            Root::Map(_) | Root::Array(_) if markdown => {
+                let json = to_json_string(&root_json(txn, root))?;
+                blocks.push(Block::new(format!("```json\n{}\n```", json)));
+            }
+            Root::Map(_) | Root::Array(_) => {}
+        }
+    }
+
+    let separator = if markdown {
+        MARKDOWN_BLOCK_SEPARATOR
+    } else {
+        TEXT_BLOCK_SEPARATOR
+    };
+    let mut exported = join_lines(&blocks, separator);
+    if !exported.is_empty() {
+        exported.push('\n');
+    }
+    Ok(exported)
+}
+
+/// A block of exported content, e.g. a paragraph or a list item.
+struct Block {
+    /// Rendered content, possibly spanning several lines
+    text: String,
+    /// Whether the block stays on the line after a previous tight block, as
+    /// list items and code lines do
+    tight: bool,
+}
+
+impl Block {
+    fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            tight: false,
+        }
+    }
+
+    fn tight(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            tight: true,
+        }
+    }
+}
+
+/// Renders text and XML content as Markdown, or as plain text without formatting.
+struct Renderer<'a, T: ReadTxn> {
+    txn: &'a T,
+    markdown: bool,
+}
+
+impl<T: ReadTxn> Renderer<'_, T> {
+    /// Renders a Quill-style text, whose block formatting is carried by the
+    /// attributes of the newline ending each line.
+    fn text_blocks(&self, text: &TextRef) -> Vec<Block> {
+        if !self.markdown {
+            let content = text.get_string(self.txn);
+            let content = content.trim_end_matches('\n');
+            return if content.is_empty() {
+                Vec::new()
+            } else {
+                vec![Block::new(content)]
+            };
+        }
+
+        let mut blocks = Vec::new();
+        let mut line = String::new();
+        let chunks: Vec<Diff<YChange>> = text.diff(self.txn, YChange::identity);
+        for chunk in chunks {
+            let attrs = chunk.attributes.as_deref();
+            match chunk.insert {
+                Out::Any(Any::String(s)) => {
+                    for (i, segment) in s.split('\n').enumerate() {
+                        if i > 0 {
+                            blocks.extend(self.line_block(std::mem::take(&mut line), attrs));
+                        }
+                        line.push_str(&self.inline(segment, attrs));
+                    }
+                }
+                Out::Any(embed) => line.push_str(&self.embed(&embed)),
+                _ => {}
+            }
+        }
+        blocks.extend(self.line_block(line, None));
+        blocks
+    }
+
+    /// Renders a line of a Quill-style text with the block attributes of its newline.
+    fn line_block(&self, line: String, attrs: Option<&Attrs>) -> Option<Block> {
+        let attr = |name: &str| attrs.and_then(|attrs| attrs.get(name));
+
+        if let Some(level) = attr("header").and_then(as_number) {
+            return Some(Block::new(format!("{} {}", heading_marker(level), line)));
+        }
+        if attr("code-block").is_some_and(is_set) {
+            return Some(Block::tight(format!("    {}", line)));
+        }
+        if line.is_empty() {
+            return None;
+        }
+        if let Some(Any::String(list)) = attr("list") {
+            let marker = match list.as_ref() {
+                "ordered" => "1.",
+                "checked" => "- [x]",
+                "unchecked" => "- [ ]",
+                _ => "-",
+            };
+            let indent = attr("indent").and_then(as_number).unwrap_or(0);
+            return Some(Block::tight(format!(
+                "{}{} {}",
+                "  ".repeat(indent),
+                marker,
+                line
+            )));
+        }
+        if attr("blockquote").is_some_and(is_set) {
+            return Some(Block::new(format!("> {}", line)));
+        }
+        Some(Block::new(line))
+    }
+
+    /// Renders a run of text with its formatting marks.
+    fn inline(&self, text: &str, marks: Option<&Attrs>) -> String {
+        let Some(marks) = marks.filter(|_| self.markdown && !text.is_empty()) else {
+            return text.to_string();
+        };
+        let has = |names: &[&str]| {
+            names
+                .iter()
+                .any(|name| marks.get(*name).is_some_and(is_set))
+        };
+
+        let mut text = text.to_string();
+        if has(&["code"]) {
+            text = format!("`{}`", text);
+        }
+        if has(&["bold", "strong"]) {
+            text = format!("**{}**", text);
+        }
+        if has(&["italic", "em"]) {
+            text = format!("*{}*", text);
+        }
+        if has(&["strike", "strikethrough"]) {
+            text = format!("~~{}~~", text);
+        }
+        if let Some(href) = marks.get("link").and_then(link_target) {
+            text = format!("[{}]({})", text, href);
+        }
+        text
+    }
+
+    /// Renders an embed of a Quill-style text; only images have a Markdown form.
+    fn embed(&self, embed: &Any) -> String {
+        match embed {
+            Any::Map(map) if self.markdown => match map.get("image") {
+                Some(Any::String(src)) => format!("![]({})", src),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        }
+    }
+
+    /// Renders the block-level children of a ProseMirror-style XML node.
+    ///
+    /// Text found directly among blocks is rendered as a paragraph of its own.
+    fn xml_blocks(&self, parent: &impl XmlFragment) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut loose = String::new();
+        for child in parent.children(self.txn) {
+            match child {
+                XmlOut::Element(element) => {
+                    if !loose.is_empty() {
+                        blocks.push(Block::new(std::mem::take(&mut loose)));
+                    }
+                    blocks.extend(self.element_blocks(&element));
+                }
+                XmlOut::Text(text) => loose.push_str(&self.xml_text(&text)),
+                XmlOut::Fragment(fragment) => blocks.extend(self.xml_blocks(&fragment)),
+            }
+        }
+        if !loose.trim().is_empty() {
+            blocks.push(Block::new(loose));
+        }
+        blocks
+    }
+
+    /// Renders a block-level XML element.
+    fn element_blocks(&self, element: &XmlElementRef) -> Vec<Block> {
+        let tag = normalized_tag(element.tag());
+        let markdown = self.markdown;
+
+        match tag.as_str() {
+            "heading" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = attribute(self.txn, element, "level")
+                    .as_ref()
+                    .and_then(as_number)
+                    .or_else(|| tag.strip_prefix('h')?.parse().ok())
+                    .unwrap_or(1);
+                let text = self.xml_inline(element);
+                if markdown {
+                    vec![Block::new(format!("{} {}", heading_marker(level), text))]
+                } else {
+                    vec![Block::new(text)]
+                }
+            }
+            "blockquote" => {
+                let quoted = self.xml_blocks(element);
+                if !markdown {
+                    return quoted;
+                }
+                let text = join_lines(&quoted, MARKDOWN_BLOCK_SEPARATOR);
+                let lines: Vec<String> = text
+                    .lines()
+                    .map(|line| format!("> {}", line).trim_end().to_string())
+                    .collect();
+                vec![Block::new(lines.join("\n"))]
+            }
+            "bulletlist" | "ul" | "orderedlist" | "ol" | "tasklist" => {
+                let ordered = matches!(tag.as_str(), "orderedlist" | "ol");
+                let start = ["start", "order"]
+                    .iter()
+                    .find_map(|name| {
+                        attribute(self.txn, element, name)
+                            .as_ref()
+                            .and_then(as_number)
+                    })
+                    .unwrap_or(1);
+
+                let mut items = Vec::new();
+                for child in element.children(self.txn) {
+                    let XmlOut::Element(item) = child else {
+                        continue;
+                    };
+                    let content = join_lines(&self.xml_blocks(&item), "\n");
+                    if !markdown {
+                        items.push(Block::new(content));
+                        continue;
+                    }
+
+                    let checked = attribute(self.txn, &item, "checked");
+                    let marker = match (ordered, checked) {
+                        (true, _) => format!("{}.", start + items.len()),
+                        (false, Some(checked)) if is_set(&checked) => "- [x]".to_string(),
+                        (false, Some(_)) => "- [ ]".to_string(),
+                        (false, None) => "-".to_string(),
+                    };
+                    // Continuation lines are indented under the item's first line
+                    let indent = " ".repeat(marker.len() + 1);
+                    let text = content
+                        .lines()
+                        .enumerate()
+                        .map(|(i, line)| match i {
+                            0 => format!("{} {}", marker, line),
+                            _ if line.is_empty() => String::new(),
+                            _ => format!("{}{}", indent, line),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    items.push(Block::tight(text));
+                }
+                items
+            }
+            "codeblock" | "pre" => {
+                let code = Renderer {
+                    txn: self.txn,
+                    markdown: false,
+                }
+                .xml_inline(element);
+                if !markdown {
+                    return vec![Block::new(code)];
+                }
+                let language = match attribute(self.txn, element, "language") {
+                    Some(Any::String(language)) => language.to_string(),
+                    _ => String::new(),
+                };
+                vec![Block::new(format!("```{}\n{}\n```", language, code))]
+            }
+            "horizontalrule" | "hr" if markdown => vec![Block::new("---")],
+            "horizontalrule" | "hr" => Vec::new(),
+            "image" | "img" => {
+                let image = self.image(element);
+                if image.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![Block::new(image)]
+                }
+            }
+            _ => {
+                let has_blocks = element
+                    .children(self.txn)
+                    .any(|child| matches!(child, XmlOut::Element(_)))
+                    && !matches!(tag.as_str(), "paragraph" | "p");
+                if has_blocks {
+                    return self.xml_blocks(element);
+                }
+                let text = self.xml_inline(element);
+                if text.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![Block::new(text)]
+                }
+            }
+        }
+    }
+
+    /// Renders the inline content of an XML element.
+    fn xml_inline(&self, parent: &impl XmlFragment) -> String {
+        let mut text = String::new();
+        for child in parent.children(self.txn) {
+            match child {
+                XmlOut::Text(xml_text) => text.push_str(&self.xml_text(&xml_text)),
+                XmlOut::Element(element) => match normalized_tag(element.tag()).as_str() {
+                    "hardbreak" | "br" if self.markdown => text.push_str("  \n"),
+                    "hardbreak" | "br" => text.push('\n'),
+                    "image" | "img" => text.push_str(&self.image(&element)),
+                    _ => text.push_str(&self.xml_inline(&element)),
+                },
+                XmlOut::Fragment(fragment) => text.push_str(&self.xml_inline(&fragment)),
+            }
+        }
+        text
+    }
+
+    /// Renders an XML text with its marks.
+    fn xml_text(&self, text: &XmlTextRef) -> String {
+        let chunks: Vec<Diff<YChange>> = text.diff(self.txn, YChange::identity);
+        chunks
+            .into_iter()
+            .map(|chunk| match chunk.insert {
+                Out::Any(Any::String(s)) => self.inline(&s, chunk.attributes.as_deref()),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    /// Renders an image element; images have no plain text form.
+    fn image(&self, element: &XmlElementRef) -> String {
+        if !self.markdown {
+            return String::new();
+        }
+        let text_attribute = |name: &str| match attribute(self.txn, element, name) {
+            Some(Any::String(value)) => value.to_string(),
+            _ => String::new(),
+        };
+        let src = text_attribute("src");
+        if src.is_empty() {
+            return String::new();
+        }
+        format!("![{}]({})", text_attribute("alt"), src)
+    }
+}
+
+/// Reads an attribute of an XML node as JSON.
+fn attribute<T: ReadTxn>(txn: &T, node: &impl Xml, name: &str) -> Option<Any> {
+    node.attributes(txn)
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| attribute_value(value))
+}
+
+/// Converts an XML attribute to JSON; attributes read back as strings, so the
+/// `true`, `false` and `null` a client stored read back as those literals.
+fn attribute_value(value: String) -> Any {
+    match value.as_str() {
+        "true" => Any::Bool(true),
+        "false" => Any::Bool(false),
+        "null" => Any::Null,
+        _ => Any::String(value.into()),
+    }
+}
+
+/// Normalizes an XML tag, so ProseMirror's `bullet_list` and Tiptap's `bulletList` match.
+fn normalized_tag(tag: &str) -> String {
+    tag.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Joins the text of blocks, keeping tight blocks on consecutive lines.
+fn join_lines(blocks: &[Block], separator: &str) -> String {
+    let mut text = String::new();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            let tight = block.tight && blocks[i - 1].tight;
+            text.push_str(if tight { "\n" } else { separator });
+        }
+        text.push_str(&block.text);
+    }
+    text
+}
+
+fn heading_marker(level: usize) -> String {
+    "#".repeat(level.clamp(1, 6))
+}
+
+/// Reads a numeric attribute, which clients may store as a number or a string.
+fn as_number(value: &Any) -> Option<usize> {
+    match value {
+        Any::Number(n) if *n >= 0.0 => Some(*n as usize),
+        Any::BigInt(n) => usize::try_from(*n).ok(),
+        Any::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Checks whether a formatting attribute is set; formatting is removed by setting it to null.
+fn is_set(value: &Any) -> bool {
+    !matches!(value, Any::Null | Any::Undefined | Any::Bool(false))
+}
+
+/// Reads the target of a link mark: a URL in Quill, an object with an `href` in ProseMirror.
+fn link_target(value: &Any) -> Option<String> {
+    match value {
+        Any::String(href) => Some(href.to_string()),
+        Any::Map(attrs) => match attrs.get("href") {
+            Some(Any::String(href)) => Some(href.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use yrs::{
+    types::Attrs, Any, Array, Doc, Map, ReadTxn, StateVector, Text, Transact, TransactionMut,
+    WriteTxn, Xml, XmlElementPrelim, XmlFragment, XmlTextPrelim, XmlTextRef,
+};
+
+use crate::{
+    entities::clean_copy::CLEAN_COPY_CLIENT_ID,
+    errors::{DomainError, DomainResult},
+    value_objects::import_format::ImportFormat,
+};
+
+/// Root seeded from plain text when the request does not name one.
+pub const DEFAULT_TEXT_ROOT: &str = "content";
+
+/// Domain service building the CRDT structure of a document from plain text or JSON.
+///
+/// The content is written in a single transaction of a fresh document, authored
+/// by the same server client ID as clean copies, and encoded as one update that
+/// seeds the imported document. It is the counterpart of the `DocumentExporter`:
+/// the JSON it exports can be imported back.
+pub struct DocumentImporter;
+
+impl DocumentImporter {
+    /// Builds the update seeding a document with some content.
+    ///
+    /// * Plain text is inserted into a single text root
+    /// * JSON must be an object mapping root names to their content: strings become text roots,
+    ///   objects map roots, arrays of ProseMirror-style nodes (`{"type": ..., "attrs": {...},
+    ///   "content": [...]}`) XML roots, and other arrays array roots
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The content to import
+    /// * `format` - The format of the content
+    /// * `text_root` - Name of the root seeded from plain text
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The binary-encoded update holding the content
+    /// * `Err(DomainError)` - `InvalidArgument` if the JSON is malformed or not an object of roots
+    pub fn import(content: &str, format: ImportFormat, text_root: &str) -> DomainResult<Vec<u8>> {
+        let doc = Doc::with_client_id(CLEAN_COPY_CLIENT_ID);
+        {
+            let mut txn = doc.transact_mut();
+            match format {
+                ImportFormat::Text => {
+                    txn.get_or_insert_text(text_root).push(&mut txn, content);
+                }
+                ImportFormat::Json => import_json(&mut txn, content)?,
+            }
+        }
+
+        let txn = doc.transact();
+        Ok(txn.encode_state_as_update_v1(&StateVector::default()))
+    }
+}
+
+/// Writes the roots of a JSON object into a document.
+fn import_json(txn: &mut TransactionMut, content: &str) -> DomainResult<()> {
+    let value: Any = sonic_rs::from_str(content)
+        .map_err(|e| DomainError::InvalidArgument(format!("Invalid JSON content: {}", e)))?;
+    let Any::Map(roots) = value else {
+        return Err(DomainError::InvalidArgument(
+            "JSON content must be an object mapping root names to their content".to_string(),
+        ));
+    };
+
+    for (name, value) in roots.iter() {
+        match value {
+            Any::String(text) => {
+                txn.get_or_insert_text(name.as_str()).push(txn, text);
+            }
+            Any::Map(entries) => {
+                let map = txn.get_or_insert_map(name.as_str());
+                for (key, value) in entries.iter() {
+                    map.insert(txn, key.as_str(), value.clone());
+                }
+            }
+            Any::Array(items) if !items.is_empty() && items.iter().all(is_node) => {
+                let fragment = txn.get_or_insert_xml_fragment(name.as_str());
+                push_nodes(txn, &fragment, items);
+            }
+            Any::Array(items) => {
+                let array = txn.get_or_insert_array(name.as_str());
+                for item in items.iter() {
+                    array.push_back(txn, item.clone());
+                }
+            }
+            _ => {
+                return Err(DomainError::InvalidArgument(format!(
+                    "Root '{}' must be a string, an object or an array",
+                    name
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether a JSON value is a ProseMirror-style node, an object with a `type`.
+fn is_node(value: &Any) -> bool {
+    matches!(value, Any::Map(node) if matches!(node.get("type"), Some(Any::String(_))))
+}
+
+/// Appends ProseMirror-style nodes to an XML node.
+///
+/// Consecutive text and embed nodes are written into a single XML text, their
+/// marks becoming formatting attributes, as ProseMirror bindings do.
+fn push_nodes(txn: &mut TransactionMut, parent: &impl XmlFragment, nodes: &[Any]) {
+    let mut text: Option<XmlTextRef> = None;
+    for node in nodes {
+        let Any::Map(node) = node else {
+            continue;
+        };
+        let Some(Any::String(kind)) = node.get("type") else {
+            continue;
+        };
+
+        match kind.as_ref() {
+            "text" | "embed" => {
+                let target =
+                    text.get_or_insert_with(|| parent.push_back(txn, XmlTextPrelim::new("")));
+                let attributes = marks(node);
+                let index = target.len(txn);
+                match (kind.as_ref(), node.get("text"), node.get("value")) {
+                    ("text", Some(Any::String(s)), _) => {
+                        target.insert_with_attributes(txn, index, s, attributes);
+                    }
+                    ("embed", _, Some(value)) => {
+                        target.insert_embed_with_attributes(txn, index, value.clone(), attributes);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {
+                text = None;
+                let element = parent.push_back(txn, XmlElementPrelim::empty(kind.clone()));
+                if let Some(Any::Map(attrs)) = node.get("attrs") {
+                    for (name, value) in attrs.iter() {
+                        element.insert_attribute(txn, name.as_str(), attribute_string(value));
+                    }
+                }
+                if let Some(Any::Array(content)) = node.get("content") {
+                    push_nodes(txn, &element, content);
+                }
+            }
+        }
+    }
+}
+
+/// Converts a JSON attribute to the string XML attributes are stored as:
+/// strings as they are, other values as their JSON, e.g. `false` or `2`.
+fn attribute_string(value: &Any) -> String {
+    match value {
+        Any::String(s) => s.to_string(),
+        other => {
+            let mut json = String::new();
+            other.to_json(&mut json);
+            json
+        }
+    }
+}
+
+/// Converts the marks of a text node to formatting attributes: marks with
+/// attributes keep them as the value, other marks are set to `true`.
+fn marks(node: &HashMap<String, Any>) -> Attrs {
+    let Some(Any::Array(marks)) = node.get("marks") else {
+        return Attrs::default();
+    };
+
+    marks
+        .iter()
+        .filter_map(|mark| {
+            let Any::Map(mark) = mark else {
+                return None;
+            };
+            let Some(Any::String(name)) = mark.get("type") else {
+                return None;
+            };
+            let value = match mark.get("attrs") {
+                Some(attrs @ Any::Map(_)) => attrs.clone(),
+                _ => Any::Bool(true),
+            };
+            Some((name.clone(), value))
+        })
+        .collect()
+}
@@ -0,0 +1,178 @@
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::watch;
+
+use crate::{
+    errors::{DomainError, DomainResult},
+    repositories::cluster_membership::ClusterMembership,
+    value_objects::cluster_member::ClusterMember,
+};
+
+/// Points each member is placed at on the ring, evening out the share of documents it owns.
+const VIRTUAL_NODES: usize = 64;
+
+/// Ownership of the documents of a cluster, sharded by consistent hashing.
+///
+/// Every member of the cluster is placed at several points of a hash ring, and
+/// a document is owned by the member at the first point following the hash of
+/// its identifier. Every instance derives the owners from the same membership
+/// list, so they agree on them without coordination, and a member joining or
+/// leaving only moves the documents next to its points.
+///
+/// Documents are only persisted and shared by their owner, so their state
+/// never diverges between instances; the other instances send their clients to
+/// the owner, or relay their updates to it.
+pub struct DocumentOwnership {
+    /// This instance
+    local: ClusterMember,
+    /// Membership list the owners are derived from
+    membership: Arc<dyn ClusterMembership>,
+    /// Live members of the cluster, as last read from the membership list
+    members: watch::Receiver<Vec<ClusterMember>>,
+    /// Ring built from the live members, rebuilt when they change
+    ring: RwLock<HashRing>,
+}
+
+impl DocumentOwnership {
+    /// Joins a cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - This instance, as advertised to the other members
+    /// * `membership` - Membership list of the cluster
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentOwnership)` - The ownership of the documents, every document being owned by
+    ///   this instance until the membership list is first read
+    /// * `Err(DomainError)` - `Unavailable` if the instance could not be announced
+    pub fn join(
+        local: ClusterMember,
+        membership: Arc<dyn ClusterMembership>,
+    ) -> DomainResult<Self> {
+        let members = membership.join(local.clone())?;
+        Ok(Self {
+            local,
+            membership,
+            members,
+            ring: RwLock::new(HashRing::default()),
+        })
+    }
+
+    /// Returns this instance.
+    pub fn local(&self) -> &ClusterMember {
+        &self.local
+    }
+
+    /// Returns the live members of the cluster, sorted by node identifier.
+    pub fn members(&self) -> Vec<ClusterMember> {
+        self.members.borrow().clone()
+    }
+
+    /// Finds the member owning a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// The owner of the document, or this instance while no member is known
+    pub fn owner(&self, doc_id: &str) -> ClusterMember {
+        let members = self.members.borrow();
+        {
+            let ring = self
+                .ring
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if ring.members == *members {
+                return ring.owner(doc_id).unwrap_or(&self.local).clone();
+            }
+        }
+
+        let mut ring = self
+            .ring
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if ring.members != *members {
+            *ring = HashRing::new(members.clone());
+        }
+        ring.owner(doc_id).unwrap_or(&self.local).clone()
+    }
+
+    /// Checks that this instance owns a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If this instance owns the document
+    /// * `Err(DomainError::Moved)` - Naming the owner of the document otherwise
+    pub fn check(&self, doc_id: &str) -> DomainResult<()> {
+        let owner = self.owner(doc_id);
+        if owner.node_id == self.local.node_id {
+            return Ok(());
+        }
+        Err(DomainError::Moved {
+            doc_id: doc_id.to_string(),
+            owner,
+        })
+    }
+
+    /// Checks whether the membership list is currently followed.
+    pub fn check_health(&self) -> DomainResult<()> {
+        self.membership.check_health()
+    }
+}
+
+/// Consistent hash ring of the members of a cluster.
+#[derive(Default)]
+struct HashRing {
+    /// Members the ring was built from
+    members: Vec<ClusterMember>,
+    /// Points of the ring, sorted, with the index of the member placed there
+    points: Vec<(u64, usize)>,
+}
+
+impl HashRing {
+    /// Places every member at its points of the ring.
+    fn new(members: Vec<ClusterMember>) -> Self {
+        let mut points: Vec<(u64, usize)> = members
+            .iter()
+            .enumerate()
+            .flat_map(|(index, member)| {
+                (0..VIRTUAL_NODES).map(move |point| {
+                    let key = format!("{}#{}", member.node_id, point);
+                    (ring_hash(key.as_bytes()), index)
+                })
+            })
+            .collect();
+        points.sort_unstable();
+        Self { members, points }
+    }
+
+    /// Returns the member at the first point following the hash of a document.
+    fn owner(&self, doc_id: &str) -> Option<&ClusterMember> {
+        let hash = ring_hash(doc_id.as_bytes());
+        let next = self.points.partition_point(|(point, _)| *point < hash);
+        // The ring wraps around past its last point
+        let (_, index) = self.points.get(next).or_else(|| self.points.first())?;
+        self.members.get(*index)
+    }
+}
+
+/// Hashes a key onto the ring.
+///
+/// FNV-1a followed by the SplitMix64 finalizer, which spreads keys differing
+/// by a single character. Unlike the standard library's hasher, it gives the
+/// same result on every instance, whatever its platform or compiler version.
+fn ring_hash(bytes: &[u8]) -> u64 {
+    let mut hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
@@ -1,13 +1,116 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use base64::Engine;
-use tokio::sync::{broadcast, Mutex};
+use tokio::sync::{broadcast, mpsc, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use tracing::{debug, warn};
 
 use crate::{
     entities::document::CollaborativeDocument,
-    repositories::document_repository::DocumentRepository,
+    errors::{DomainError, DomainResult},
+    repositories::{
+        access_control::AccessControl,
+        audit_sink::AuditSink,
+        collation::Collation,
+        document_forwarder::{DocumentForwarder, OwnerMessage},
+        document_metadata_repository::DocumentMetadataRepository,
+        document_repository::DocumentRepository,
+        document_store::DocumentStore,
+        search_index::SearchIndex,
+        update_broker::UpdateBroker,
+        update_exporter::UpdateExporter,
+        update_log::UpdateLog,
+        update_processor::UpdateProcessor,
+        update_validator::UpdateValidator,
+        version_repository::VersionRepository,
+        write_ahead_log::WriteAheadLog,
+    },
+    services::{
+        activity_tracker::ActivityTracker,
+        archive_tier::ArchiveTier,
+        compute_pool::{ComputePool, CrdtOperation, PooledDocument},
+        document_actor::{DocumentActor, Subscription, UpdateOutcome},
+        document_exporter::DocumentExporter,
+        document_importer::DocumentImporter,
+        document_ownership::DocumentOwnership,
+        payload_dictionaries::PayloadDictionaries,
+        update_coalescer::UpdateCoalescer,
+        update_pipeline::{ProcessedUpdate, UpdatePipeline},
+    },
+    value_objects::{
+        access_role::{AccessGrant, AccessRole},
+        audit_entry::{AuditEntry, AuditPage, MAX_AUDIT_PAGE_SIZE},
+        broadcast_coalescing::BroadcastCoalescing,
+        cluster_member::ClusterMember,
+        content_stats::{ContentStats, DocumentStats, ResidentDocumentStats, StructureStats},
+        dependency_health::DependencyHealth,
+        diff_throttle::DiffThrottle,
+        document_activity::{ActivityBucket, ActivityGranularity, ActivityRetention},
+        document_event::DocumentEvent,
+        document_metadata::{DocumentMetadata, MetadataPatch},
+        document_version::{DocumentVersion, VersionPolicy},
+        export_format::ExportFormat,
+        export_mode::ExportMode,
+        exported_update::ExportedUpdate,
+        feature_policy::{FeaturePolicies, FeaturePolicy},
+        gc_options::GcOptions,
+        import_format::ImportFormat,
+        message::{Notice, NoticeKind, NoticeSeverity},
+        search_hit::{SearchHit, MAX_SEARCH_LIMIT},
+        shared_edit::SharedEdit,
+        subdocument::{root_document_id, split_subdocument_id},
+        sync_protocol::SyncProtocolMessage,
+        tenant::TenantQuotas,
+        undo_action::UndoAction,
+        update_limits::{SizeLimit, UpdateLimits},
+        update_origin::{OriginKind, TransactionOrigin, UpdateOrigin},
+    },
 };
 
+/// Source of the updates writing the text replaced by the update processors over the text
+/// a client inserted, which are also broadcast to that client.
+pub const PROCESSING_UPDATE_SOURCE: &str = "processing";
+
+/// Key recording a client update while the update processors inspect it, so it can be
+/// reverted if they reject it.
+const PROCESSING_REVERT_KEY: &str = "processing";
+
+/// Source of updates received from other server instances through the update broker.
+pub const REMOTE_UPDATE_SOURCE: &str = "remote";
+
+/// Source of the updates restoring imported documents.
+pub const IMPORT_UPDATE_SOURCE: &str = "import";
+
+/// Source of the updates moving archived documents back to the repository.
+pub const REHYDRATE_UPDATE_SOURCE: &str = "rehydrate";
+
+/// Source of the updates made by the server itself, such as reverts.
+pub const SERVER_UPDATE_SOURCE: &str = "server";
+
+/// Source of the updates undoing or redoing a client's changes, which are also
+/// broadcast to the client that asked for them.
+pub const UNDO_UPDATE_SOURCE: &str = "undo";
+
+/// Capacity of the channel delivering notices to connections.
+const NOTICE_CHANNEL_CAPACITY: usize = 64;
+
+/// Number of updates appended to the log of a document without history between two
+/// compactions.
+const HISTORYLESS_COMPACTION_INTERVAL: usize = 64;
+
+/// Capacity of the channel delivering document lifecycle events to integrations.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Binary encoding of an empty state vector, which makes a diff cover the whole document.
+pub(crate) const EMPTY_STATE_VECTOR: &[u8] = &[0];
+
 /// A domain service that manages collaborative documents and their operations.
 ///
 /// This service provides comprehensive document collaboration capabilities:
@@ -24,6 +127,85 @@ use crate::{
 /// about the concrete implementation details.
 pub struct DocumentService<R: DocumentRepository> {
     document_repository: R,
+    /// Feature policies resolved for each document when it is first opened
+    policies: FeaturePolicies,
+    /// Limits on the documents of every tenant
+    tenant_quotas: TenantQuotas,
+    /// Broker sharing applied updates with other server instances
+    broker: Option<Arc<dyn UpdateBroker>>,
+    /// Documents this instance owns within its cluster; without one, it owns every document
+    ownership: Option<Arc<DocumentOwnership>>,
+    /// Relay serving the documents owned by other instances from local replicas,
+    /// instead of sending their clients to the owner
+    forwarder: Option<Arc<dyn DocumentForwarder>>,
+    /// Limits applied to the diffs computed for clients
+    diff_throttle: DiffThrottle,
+    /// Server-wide limits on the size of updates and documents
+    update_limits: UpdateLimits,
+    /// How the updates broadcast for each document are coalesced
+    broadcast_coalescing: BroadcastCoalescing,
+    /// Updates rejected for exceeding the maximum update size
+    oversized_updates: AtomicU64,
+    /// Updates rejected for exceeding the maximum document size
+    oversized_documents: AtomicU64,
+    /// Notices delivered to every connection, which filters them by document
+    notices: broadcast::Sender<Notice>,
+    /// Lifecycle events of every document, delivered to integrations such as webhooks
+    events: broadcast::Sender<DocumentEvent>,
+    /// Storage of operator-managed document metadata such as tags
+    metadata: Option<Arc<dyn DocumentMetadataRepository>>,
+    /// Serializes read-modify-write cycles on document metadata
+    metadata_lock: std::sync::Mutex<()>,
+    /// Resolves the role of clients joining documents; without one, every
+    /// client allowed by the feature policy may edit
+    access_control: Option<Arc<dyn AccessControl>>,
+    /// Orders document IDs and matches tags; without one, they are sorted by
+    /// their bytes and matched exactly
+    collation: Option<Arc<dyn Collation>>,
+    /// Restrictions granted by operators at runtime, by user and document (`None` for
+    /// every document)
+    access_grants: std::sync::Mutex<HashMap<(String, Option<String>), AccessGrant>>,
+    /// Updates and editors of each document, aggregated into time buckets
+    activity: ActivityTracker,
+    /// Store documents are restored from when first opened and saved to once updated
+    store: Option<Arc<dyn DocumentStore>>,
+    /// Local log of the updates applied since the last checkpoint
+    write_ahead_log: Option<Arc<dyn WriteAheadLog>>,
+    /// Actors serving the updates, diffs, subscriptions and snapshots of resident documents
+    actors: std::sync::Mutex<HashMap<String, DocumentActor>>,
+    /// Documents updated since they were last saved to the store
+    unsaved: std::sync::Mutex<BTreeSet<String>>,
+    /// Cold storage idle documents are moved to
+    archive: Option<ArchiveTier>,
+    /// Dictionaries compressing the updates relayed to the clients that negotiated it
+    payload_dictionaries: Option<Arc<PayloadDictionaries>>,
+    /// Storage of the versions recorded in documents' timelines
+    versions: Option<Arc<dyn VersionRepository>>,
+    /// When versions are recorded and how many are kept
+    version_policy: VersionPolicy,
+    /// Serializes the numbering and pruning of versions
+    versions_lock: std::sync::Mutex<()>,
+    /// Documents updated since their last periodic snapshot
+    unversioned: std::sync::Mutex<BTreeSet<String>>,
+    /// Sink of the audit trail of the updates applied to each document
+    audit: Option<Arc<dyn AuditSink>>,
+    /// Export of every update applied on this instance to downstream pipelines
+    exporter: Option<Arc<dyn UpdateExporter>>,
+    /// Validation of the updates clients apply, enforcing a document schema
+    validator: Option<Arc<dyn UpdateValidator>>,
+    /// Processors inspecting the text clients insert, e.g. to mask profanity
+    pipeline: Option<Arc<UpdatePipeline>>,
+    /// Time each document was last modified, as Unix seconds, recorded in its
+    /// metadata at most once per second
+    modified: std::sync::Mutex<HashMap<String, i64>>,
+    /// Whether each document checked so far is frozen, cached from its metadata
+    frozen: std::sync::Mutex<HashMap<String, bool>>,
+    /// Garbage collection options set by operators, overriding each document's policy
+    gc_overrides: std::sync::Mutex<HashMap<String, GcOptions>>,
+    /// Full-text index of the content of documents
+    search: Option<Arc<dyn SearchIndex>>,
+    /// Documents created, updated or deleted since they were last indexed
+    unindexed: std::sync::Mutex<BTreeSet<String>>,
 }
 
 impl<R: DocumentRepository> DocumentService<R> {
@@ -39,350 +221,4417 @@ impl<R: DocumentRepository> DocumentService<R> {
     pub fn new(document_repository: R) -> Self {
         Self {
             document_repository,
+            policies: FeaturePolicies::default(),
+            tenant_quotas: TenantQuotas::default(),
+            broker: None,
+            ownership: None,
+            forwarder: None,
+            diff_throttle: DiffThrottle::default(),
+            update_limits: UpdateLimits::default(),
+            broadcast_coalescing: BroadcastCoalescing::default(),
+            oversized_updates: AtomicU64::new(0),
+            oversized_documents: AtomicU64::new(0),
+            notices: broadcast::channel(NOTICE_CHANNEL_CAPACITY).0,
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            metadata: None,
+            metadata_lock: std::sync::Mutex::new(()),
+            access_control: None,
+            collation: None,
+            access_grants: std::sync::Mutex::new(HashMap::new()),
+            activity: ActivityTracker::default(),
+            store: None,
+            write_ahead_log: None,
+            actors: std::sync::Mutex::new(HashMap::new()),
+            unsaved: std::sync::Mutex::new(BTreeSet::new()),
+            archive: None,
+            payload_dictionaries: None,
+            versions: None,
+            version_policy: VersionPolicy::default(),
+            versions_lock: std::sync::Mutex::new(()),
+            unversioned: std::sync::Mutex::new(BTreeSet::new()),
+            audit: None,
+            exporter: None,
+            validator: None,
+            pipeline: None,
+            modified: std::sync::Mutex::new(HashMap::new()),
+            frozen: std::sync::Mutex::new(HashMap::new()),
+            gc_overrides: std::sync::Mutex::new(HashMap::new()),
+            search: None,
+            unindexed: std::sync::Mutex::new(BTreeSet::new()),
         }
     }
 
-    /// Handles a sync request from a client.
-    ///
-    /// This method processes client synchronization requests and returns the missing
-    /// updates based on the client's state vector, along with a receiver for future updates.
+    /// Applies per-namespace feature policies to the documents of this service.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to synchronize with
-    /// * `client_state_vector` - The client's current state vector (optional)
+    /// * `policies` - The feature policies of every namespace
     ///
     /// # Returns
     ///
-    /// A tuple containing:
-    /// * A SyncResponse with the updates the client needs and current state vector
-    /// * A broadcast receiver for future document updates
-    pub async fn handle_sync_request(
-        &self,
-        doc_id: &str,
-        client_state_vector: Option<&[u8]>,
-    ) -> (SyncResponse, broadcast::Receiver<UpdateNotification>) {
-        // Get the missing updates based on client's state vector
-        let (update_data, receiver) = self.sync_document(doc_id, client_state_vector).await;
-
-        let response = SyncResponse {
-            update: if update_data.is_empty() {
-                None
-            } else {
-                Some(update_data)
-            },
-            state_vector: None,
-        };
-
-        (response, receiver)
+    /// The `DocumentService` enforcing the given policies
+    pub fn with_policies(mut self, policies: FeaturePolicies) -> Self {
+        self.policies = policies;
+        self
     }
 
-    /// Handles an update request from a client.
-    ///
-    /// This method processes document updates sent by clients in Base64 format.
+    /// Limits the number of documents of each tenant.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to update
-    /// * `update_base64` - The Base64-encoded update data
+    /// * `tenant_quotas` - The limits of every tenant
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - If the update was successfully applied
-    /// * `Err(String)` - An error message if the update couldn't be applied
-    pub async fn handle_update_request(
-        &self,
-        doc_id: &str,
-        update_base64: &str,
-    ) -> Result<(), String> {
-        // Decode Base64 update data
-        let update_data = base64::engine::general_purpose::STANDARD
-            .decode(update_base64)
-            .map_err(|e| format!("Failed to decode Base64 update: {}", e))?;
-
-        // Apply the update using existing method
-        self.apply_document_update(doc_id, &update_data).await
+    /// The `DocumentService` refusing new documents to tenants at their limit
+    pub fn with_tenant_quotas(mut self, tenant_quotas: TenantQuotas) -> Self {
+        self.tenant_quotas = tenant_quotas;
+        self
     }
 
-    /// Handles a synchronization step with a state vector from a client.
+    /// Shares applied updates with other server instances through a broker.
     ///
-    /// This method processes client state vectors and returns the necessary updates
-    /// to bring the client up to date.
+    /// Every document subscribes to the broker when it is first opened; updates
+    /// applied locally are published, and updates published by other instances
+    /// are applied and broadcast to this instance's clients.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to synchronize
-    /// * `state_vector_base64` - The Base64-encoded client state vector
+    /// * `broker` - The broker connecting the instances
     ///
     /// # Returns
     ///
-    /// A result containing:
-    /// * `Ok((SyncResponse, receiver))` - Response with updates and receiver for future updates
-    /// * `Err(String)` - An error message if synchronization couldn't be processed
-    pub async fn handle_sync_step(
-        &self,
-        doc_id: &str,
-        state_vector_base64: &str,
-    ) -> Result<(SyncResponse, broadcast::Receiver<UpdateNotification>), String> {
-        // Decode Base64 state vector
-        let state_vector = base64::engine::general_purpose::STANDARD
-            .decode(state_vector_base64)
-            .map_err(|e| format!("Failed to decode Base64 state vector: {}", e))?;
-
-        // Sync with the provided state vector
-        let (update, receiver) = self.sync_document(doc_id, Some(&state_vector)).await;
-
-        let response = SyncResponse {
-            update: if update.is_empty() {
-                None
-            } else {
-                Some(update)
-            },
-            state_vector: None,
-        };
-
-        Ok((response, receiver))
+    /// The `DocumentService` fanning out updates through the broker
+    pub fn with_broker(mut self, broker: Arc<dyn UpdateBroker>) -> Self {
+        self.broker = Some(broker);
+        self
     }
 
-    /// Handles a binary update from a client.
+    /// Shards the documents between the instances of a cluster.
     ///
-    /// This method processes binary document updates directly without Base64 encoding.
+    /// Clients may only join the documents this instance owns; joining any
+    /// other document fails with `DomainError::Moved`, naming its owner, which
+    /// transport adapters redirect the clients to, unless documents are forwarded.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to update
-    /// * `update_data` - The binary update data
+    /// * `ownership` - The ownership of the documents within the cluster
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - If the update was successfully applied
-    /// * `Err(String)` - An error message if the update couldn't be applied
-    pub async fn handle_binary_update(
-        &self,
-        doc_id: &str,
-        update_data: &[u8],
-    ) -> Result<(), String> {
-        // Apply the update using existing method
-        self.apply_document_update(doc_id, update_data).await
+    /// The `DocumentService` serving only the documents it owns
+    pub fn with_ownership(mut self, ownership: Arc<DocumentOwnership>) -> Self {
+        self.ownership = Some(ownership);
+        self
     }
 
-    /// Establishes a synchronization session for a document.
+    /// Returns the ownership of the documents within the cluster, if the instance is clustered.
+    pub fn ownership(&self) -> Option<&Arc<DocumentOwnership>> {
+        self.ownership.as_ref()
+    }
+
+    /// Serves the documents owned by other instances of the cluster through their owner.
     ///
-    /// This is the core business logic for initiating collaboration on a document.
-    /// It ensures the document exists and sets up the necessary channels for
-    /// real-time collaboration.
+    /// Clients joining a document this instance does not own are served from a
+    /// local replica instead of being redirected: the updates applied to it are
+    /// relayed to the owner, which persists, versions, indexes and shares them,
+    /// and the owner's updates are relayed back. A replica keeps following the
+    /// owner it was opened with until it is unloaded.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to synchronize with
+    /// * `forwarder` - The relay to the owners of the documents
     ///
     /// # Returns
     ///
-    /// A tuple containing:
-    /// * The document's current state vector as binary data
-    /// * A broadcast receiver for future document updates
-    pub async fn establish_sync_session(
-        &self,
-        doc_id: &str,
-    ) -> (Vec<u8>, broadcast::Receiver<UpdateNotification>) {
-        // Use repository abstraction - domain doesn't know about storage details
-        let doc_service = self.document_repository.get_or_create(doc_id);
-
-        // Get document state and subscribe to updates
-        let state = doc_service.lock().await;
-        let state_vector = state.get_state_vector();
-        let update_receiver = state.subscribe();
+    /// The `DocumentService` serving every document
+    pub fn with_forwarder(mut self, forwarder: Arc<dyn DocumentForwarder>) -> Self {
+        self.forwarder = Some(forwarder);
+        self
+    }
 
-        (state_vector, update_receiver)
+    /// Returns the owner a document is forwarded to, if another instance owns it and
+    /// documents are forwarded.
+    fn forwarding_owner(&self, doc_id: &str) -> Option<ClusterMember> {
+        self.forwarder.as_ref()?;
+        let ownership = self.ownership.as_ref()?;
+        let owner = ownership.owner(root_document_id(doc_id));
+        (owner.node_id != ownership.local().node_id).then_some(owner)
     }
 
-    /// Applies a document update using the collaborative editing protocol.
-    ///
-    /// This method encapsulates the business rules for applying updates to
-    /// collaborative documents, ensuring data consistency and proper
-    /// synchronization across all clients.
+    /// Sets the limits applied to the diffs computed for clients.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to update
-    /// * `update_data` - The binary update data to apply
+    /// * `diff_throttle` - Chunking threshold, chunk size and per-session cap
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - If the update was successfully applied
-    /// * `Err(String)` - An error message if the update couldn't be applied
-    pub async fn apply_document_update(
-        &self,
-        doc_id: &str,
-        update_data: &[u8],
-    ) -> Result<(), String> {
-        // Use repository abstraction for document access
-        let doc_service = self.document_repository.get_or_create(doc_id);
-        let state = doc_service.lock().await;
-        state.apply_update(update_data).await
+    /// The `DocumentService` applying the given limits
+    pub fn with_diff_throttle(mut self, diff_throttle: DiffThrottle) -> Self {
+        self.diff_throttle = diff_throttle;
+        self
     }
 
-    /// Computes missing updates for client synchronization.
-    ///
-    /// This implements the core synchronization algorithm that determines
-    /// what updates a client needs based on their current state vector.
+    /// Sets the server-wide limits on the size of updates and documents.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to synchronize
-    /// * `client_state_vector` - The client's current state vector
+    /// * `update_limits` - Maximum update size and maximum encoded document size
     ///
     /// # Returns
     ///
-    /// A tuple containing:
-    /// * The binary update data the client needs
-    /// * A broadcast receiver for future document updates
-    pub async fn sync_document(
-        &self,
-        doc_id: &str,
-        client_state_vector: Option<&[u8]>,
-    ) -> (Vec<u8>, broadcast::Receiver<UpdateNotification>) {
-        let doc_service = self.document_repository.get_or_create(doc_id);
-
-        // Use read lock for sync operation as it primarily reads the document state
-        let state = doc_service.lock().await;
-
-        // Generate update based on client's state vector
-        let update = match client_state_vector {
-            Some(sv) => state.diff_update(sv),
-            None => state.get_state_vector(),
-        };
-
-        let receiver = state.subscribe();
-        (update, receiver)
+    /// The `DocumentService` rejecting the updates exceeding the limits
+    pub fn with_update_limits(mut self, update_limits: UpdateLimits) -> Self {
+        self.update_limits = update_limits;
+        self
     }
 
-    /// Gets the complete content of a document.
-    ///
-    /// This method provides access to the document's full content,
-    /// typically for initial loading or full state recovery.
+    /// Coalesces the updates broadcast for each document within a window.
     ///
     /// # Arguments
     ///
-    /// * `doc_id` - Identifier for the document to retrieve
+    /// * `broadcast_coalescing` - Window updates are held back for, and the size past which they
+    ///   are broadcast without waiting
     ///
     /// # Returns
     ///
-    /// * `Some(String)` - The document content if the document exists
-    /// * `None` - If the document doesn't exist
-    pub async fn get_document_content(&self, doc_id: &str) -> Option<String> {
-        let doc_service = self.document_repository.get_document(doc_id)?;
-
-        // Use read lock as this operation only reads the document
-        let state = doc_service.lock().await;
-        Some(state.get_content().await)
+    /// The `DocumentService` merging the updates a client sends within the window
+    pub fn with_broadcast_coalescing(mut self, broadcast_coalescing: BroadcastCoalescing) -> Self {
+        self.broadcast_coalescing = broadcast_coalescing;
+        self
     }
-}
-
-/// Response to a sync request
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-pub struct SyncResponse {
-    /// The binary update to apply
-    pub update: Option<Vec<u8>>,
-    /// The current state vector of the document
-    pub state_vector: Option<Vec<u8>>,
-}
 
-/// Notification of a document update
-#[derive(Clone, Debug)]
-pub struct UpdateNotification {
-    /// The binary update data
-    pub update: Vec<u8>,
-    /// Source of the update
-    pub source: String,
-}
+    /// Stores operator-managed document metadata, such as tags, in a repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - The metadata repository
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` supporting document tags
+    pub fn with_metadata(mut self, metadata: Arc<dyn DocumentMetadataRepository>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
 
-/// Concrete implementation of a single document service using Yjs CRDT
-pub struct SingleDocumentServiceImpl {
-    /// The collaborative document instance
-    document: Arc<Mutex<CollaborativeDocument>>,
-    /// Broadcast channel for sending updates to subscribers
-    update_sender: broadcast::Sender<UpdateNotification>,
-}
+    /// Resolves the role of clients joining documents through an access control.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_control` - The access control deciding who may read and edit documents
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` enforcing per-document roles
+    pub fn with_access_control(mut self, access_control: Arc<dyn AccessControl>) -> Self {
+        self.access_control = Some(access_control);
+        self
+    }
 
-impl SingleDocumentServiceImpl {
-    /// Creates a new document service instance
-    pub fn new() -> Self {
-        let (update_sender, _) = broadcast::channel(1024);
+    /// Sorts document IDs and matches tags following the conventions of a language.
+    ///
+    /// # Arguments
+    ///
+    /// * `collation` - The collation ordering and matching names
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` listing documents and tags in the collation's order
+    pub fn with_collation(mut self, collation: Arc<dyn Collation>) -> Self {
+        self.collation = Some(collation);
+        self
+    }
 
-        Self {
-            document: Arc::new(Mutex::new(CollaborativeDocument::new())),
-            update_sender,
-        }
+    /// Sets how much document activity is retained.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention` - Number of minute and hour buckets kept per document
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` aggregating activity within the given window
+    pub fn with_activity_retention(mut self, retention: ActivityRetention) -> Self {
+        self.activity = ActivityTracker::new(retention);
+        self
     }
 
-    /// Get the current state of the document
-    pub async fn get_state(&self) -> SyncResponse {
-        let doc = self.document.lock().await;
-        SyncResponse {
-            update: None,
-            state_vector: Some(doc.get_state_vector()),
-        }
+    /// Trains per-document dictionaries on the updates applied by clients, to
+    /// compress the updates relayed to the clients that negotiated it.
+    ///
+    /// # Arguments
+    ///
+    /// * `dictionaries` - The dictionaries, trained and applied by their compressor
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` sampling client updates into the dictionaries
+    pub fn with_payload_dictionaries(mut self, dictionaries: PayloadDictionaries) -> Self {
+        self.payload_dictionaries = Some(Arc::new(dictionaries));
+        self
     }
 
-    /// Apply an update to the document
-    pub async fn apply_update(&self, update_data: &[u8]) -> Result<(), String> {
-        let mut doc = self.document.lock().await;
-        doc.apply_update(update_data)?;
+    /// Saves the state of documents to a store and restores them from it.
+    ///
+    /// A document missing from the repository is restored from the store when it
+    /// is first opened, and documents updated since are saved back by
+    /// [`save_documents`](Self::save_documents).
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The document store
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` saving documents to the store
+    pub fn with_store(mut self, store: Arc<dyn DocumentStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
 
-        // Broadcast the update to subscribers
-        let notification = UpdateNotification {
-            update: update_data.to_vec(),
-            source: "server".to_string(),
-        };
+    /// Records every applied update in a write-ahead log before it is acknowledged.
+    ///
+    /// The log is replayed at startup by
+    /// [`recover_from_write_ahead_log`](Self::recover_from_write_ahead_log) and
+    /// truncated by [`checkpoint_write_ahead_log`](Self::checkpoint_write_ahead_log).
+    ///
+    /// # Arguments
+    ///
+    /// * `write_ahead_log` - The write-ahead log
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` logging applied updates
+    pub fn with_write_ahead_log(mut self, write_ahead_log: Arc<dyn WriteAheadLog>) -> Self {
+        self.write_ahead_log = Some(write_ahead_log);
+        self
+    }
 
-        let _ = self.update_sender.send(notification);
-        Ok(())
+    /// Records versions of documents in a repository, named on demand and periodically.
+    ///
+    /// # Arguments
+    ///
+    /// * `versions` - The repository storing the versions and their snapshots
+    /// * `policy` - The interval between periodic snapshots and the versions kept per document
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` recording document versions
+    pub fn with_versions(
+        mut self,
+        versions: Arc<dyn VersionRepository>,
+        policy: VersionPolicy,
+    ) -> Self {
+        self.versions = Some(versions);
+        self.version_policy = policy;
+        self
     }
 
-    /// Subscribe to updates to the document
-    pub fn subscribe(&self) -> broadcast::Receiver<UpdateNotification> {
-        self.update_sender.subscribe()
+    /// Records who applied which update to each document, and through which transport.
+    ///
+    /// Client updates, undos, reverts and imports are recorded; updates relayed
+    /// from other server instances are recorded by the instance that applied them.
+    ///
+    /// # Arguments
+    ///
+    /// * `audit` - The sink of the documents' audit trails
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` recording an audit trail
+    pub fn with_audit_sink(mut self, audit: Arc<dyn AuditSink>) -> Self {
+        self.audit = Some(audit);
+        self
     }
 
-    /// Get the current content of the document
-    pub async fn get_content(&self) -> String {
-        let doc = self.document.lock().await;
-        doc.get_content_as_string()
+    /// Exports every update applied to the documents, e.g. to an analytics pipeline.
+    ///
+    /// Unlike the audit trail, the updates themselves are exported, whatever
+    /// applied them: clients, the server or the owner of a forwarded document.
+    /// Updates relayed from other server instances are exported by the
+    /// instance that applied them.
+    ///
+    /// # Arguments
+    ///
+    /// * `exporter` - The export of the applied updates
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` exporting the applied updates
+    pub fn with_exporter(mut self, exporter: Arc<dyn UpdateExporter>) -> Self {
+        self.exporter = Some(exporter);
+        self
     }
 
-    /// Get the current state vector of the document
-    pub fn get_state_vector(&self) -> Vec<u8> {
-        // Note: This is a simplified synchronous version for compatibility
-        // In a real async implementation, we would need to handle the lock properly
-        if let Ok(doc) = self.document.try_lock() {
-            doc.get_state_vector()
-        } else {
-            vec![]
-        }
+    /// Validates the updates clients apply before applying them, e.g. against a document schema.
+    ///
+    /// Each update is previewed on a copy of its document to measure its
+    /// changes, which doubles the cost of applying it; a rejected update fails
+    /// with the validator's `InvalidUpdate` error and leaves the document as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `validator` - The validation of the updates clients apply
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` validating client updates
+    pub fn with_validator(mut self, validator: Arc<dyn UpdateValidator>) -> Self {
+        self.validator = Some(validator);
+        self
     }
 
-    /// Get a diff update based on the provided state vector
+    /// Runs a processor on the text clients insert, after the processors already registered.
     ///
-    /// This method computes the missing updates that a client needs based on
-    /// their current state vector compared to the server's document state.
+    /// Each update inserting text is previewed on a copy of its document to
+    /// extract the inserted text. An update a processor rejects fails with an
+    /// `InvalidUpdate` error and leaves the document as is; the text a processor
+    /// replaces is written over the inserted one in the same broadcast, so the
+    /// other clients never receive the original text, while the sender receives
+    /// the replacement as a separate update. Labels are published as
+    /// `DocumentEvent::Annotated` events once the update is applied.
     ///
     /// # Arguments
     ///
-    /// * `client_state_vector` - The client's current state vector
+    /// * `processor` - The processor to append to the pipeline
     ///
     /// # Returns
     ///
-    /// Binary update data containing all changes the client is missing
-    pub fn diff_update(&self, client_state_vector: &[u8]) -> Vec<u8> {
-        // Note: This is a simplified synchronous version for compatibility
-        // In a real async implementation, we would need to handle the lock properly
-        if let Ok(doc) = self.document.try_lock() {
-            doc.get_missing_updates(client_state_vector)
-                .unwrap_or_else(|_| vec![])
-        } else {
-            vec![]
-        }
+    /// The `DocumentService` running the processor on client updates
+    pub fn with_update_processor(mut self, processor: Arc<dyn UpdateProcessor>) -> Self {
+        let pipeline = self
+            .pipeline
+            .as_deref()
+            .cloned()
+            .unwrap_or_else(|| UpdatePipeline::new(self.events.clone()));
+        self.pipeline = Some(Arc::new(pipeline.with_processor(processor)));
+        self
+    }
+
+    /// Indexes the text content of documents for full-text search.
+    ///
+    /// Documents are not indexed as each update is applied: the ones changed
+    /// since the last pass are reindexed together by `index_documents`, so a
+    /// burst of updates costs a single reindexing.
+    ///
+    /// # Arguments
+    ///
+    /// * `search` - The full-text index of the documents
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` indexing documents for search
+    pub fn with_search_index(mut self, search: Arc<dyn SearchIndex>) -> Self {
+        self.search = Some(search);
+        self
+    }
+
+    /// Moves documents left untouched for a while to an archive tier.
+    ///
+    /// Archived documents are removed from the repository and recorded in their
+    /// metadata, then moved back to the repository when opened again. The
+    /// documents already archived are read from the metadata, so the metadata
+    /// repository must be set first.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The store holding the state of archived documents
+    /// * `archive_after` - Time a document must be left untouched before it is archived
+    ///
+    /// # Returns
+    ///
+    /// The `DocumentService` archiving idle documents
+    pub fn with_archive(mut self, store: Arc<dyn DocumentStore>, archive_after: Duration) -> Self {
+        let archived = match self.metadata.as_ref().map(|metadata| metadata.list()) {
+            Some(Ok(documents)) => documents
+                .into_iter()
+                .filter(|(_, metadata)| metadata.archived_at.is_some())
+                .map(|(doc_id, _)| doc_id)
+                .collect(),
+            Some(Err(e)) => {
+                warn!("Failed to read which documents are archived: {}", e);
+                Default::default()
+            }
+            None => Default::default(),
+        };
+
+        self.archive = Some(ArchiveTier::new(store, archive_after, archived));
+        self
+    }
+
+    /// Returns the limits applied to the diffs computed for clients.
+    pub fn diff_throttle(&self) -> &DiffThrottle {
+        &self.diff_throttle
+    }
+
+    /// Returns the dictionaries compressing relayed updates, if enabled.
+    pub fn payload_dictionaries(&self) -> Option<&PayloadDictionaries> {
+        self.payload_dictionaries.as_deref()
+    }
+
+    /// Returns the metadata repository, or an error if none is configured.
+    fn metadata(&self) -> DomainResult<&Arc<dyn DocumentMetadataRepository>> {
+        self.metadata.as_ref().ok_or_else(|| {
+            DomainError::Unavailable("Document metadata is not available".to_string())
+        })
+    }
+
+    /// Returns the tags of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(BTreeSet<String>)` - The document's tags, empty if it has none
+    /// * `Err(DomainError)` - If the metadata could not be read
+    pub fn document_tags(&self, doc_id: &str) -> DomainResult<BTreeSet<String>> {
+        Ok(self.metadata()?.get(doc_id)?.tags)
+    }
+
+    /// Adds tags to a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `tags` - The tags to add; tags the document already has are ignored
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(BTreeSet<String>)` - The document's tags after the change
+    /// * `Err(DomainError)` - If a tag is invalid or the metadata could not be written
+    pub fn tag_document(&self, doc_id: &str, tags: &[String]) -> DomainResult<BTreeSet<String>> {
+        let tags = tags
+            .iter()
+            .map(|tag| DocumentMetadata::normalize_tag(tag))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.update_metadata(doc_id, |metadata| metadata.tags.extend(tags))
+            .map(|metadata| metadata.tags)
+    }
+
+    /// Removes tags from a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `tags` - The tags to remove; tags the document does not have are ignored
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(BTreeSet<String>)` - The document's tags after the change
+    /// * `Err(DomainError)` - If the metadata could not be written
+    pub fn untag_document(&self, doc_id: &str, tags: &[String]) -> DomainResult<BTreeSet<String>> {
+        self.update_metadata(doc_id, |metadata| {
+            for tag in tags {
+                metadata.tags.remove(tag.trim());
+            }
+        })
+        .map(|metadata| metadata.tags)
+    }
+
+    /// Returns the metadata of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentMetadata)` - The document's metadata, empty if it has none
+    /// * `Err(DomainError)` - If the metadata could not be read
+    pub fn document_metadata(&self, doc_id: &str) -> DomainResult<DocumentMetadata> {
+        self.metadata()?.get(doc_id)
+    }
+
+    /// Changes the title, owner or tags of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `patch` - The fields to change
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentMetadata)` - The document's metadata after the change
+    /// * `Err(DomainError)` - If a field is invalid or the metadata could not be written
+    pub fn update_document_metadata(
+        &self,
+        doc_id: &str,
+        patch: MetadataPatch,
+    ) -> DomainResult<DocumentMetadata> {
+        let mut applied = Ok(());
+        let metadata = self.update_metadata(doc_id, |metadata| applied = metadata.apply(patch))?;
+        applied.map(|()| metadata)
+    }
+
+    /// Freezes a document read-only, or lets it be edited again.
+    ///
+    /// A frozen document rejects every update, from clients and from the REST
+    /// API alike, with `DocumentFrozen`; it can still be read and synchronized.
+    /// Freezing an already frozen document keeps the time it was first frozen.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `frozen` - Whether the document is frozen
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentMetadata)` - The document's metadata after the change
+    /// * `Err(DomainError)` - If the metadata could not be written
+    pub fn set_frozen(&self, doc_id: &str, frozen: bool) -> DomainResult<DocumentMetadata> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        let metadata = self.update_metadata(doc_id, |metadata| {
+            metadata.frozen_at = if frozen {
+                metadata.frozen_at.or(Some(now))
+            } else {
+                None
+            };
+        })?;
+        self.frozen
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(doc_id.to_string(), frozen);
+        Ok(metadata)
+    }
+
+    /// Checks whether a document is frozen read-only.
+    ///
+    /// The flag is read from the document's metadata once, then cached; a
+    /// document whose metadata cannot be read is not considered frozen, so a
+    /// storage failure does not block collaboration.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// `true` if the document rejects updates
+    pub fn is_frozen(&self, doc_id: &str) -> bool {
+        let Some(repository) = &self.metadata else {
+            return false;
+        };
+        if let Some(frozen) = self
+            .frozen
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(doc_id)
+        {
+            return *frozen;
+        }
+
+        let frozen = match repository.get(doc_id) {
+            Ok(metadata) => metadata.frozen_at.is_some(),
+            Err(e) => {
+                warn!("Failed to read the metadata of '{}': {}", doc_id, e);
+                return false;
+            }
+        };
+        self.frozen
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(doc_id.to_string(), frozen);
+        frozen
+    }
+
+    /// Rejects the updates of a frozen document.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document may be updated
+    /// * `Err(DomainError)` - `DocumentFrozen` if it is frozen
+    fn check_writable(&self, doc_id: &str) -> DomainResult<()> {
+        if self.is_frozen(doc_id) {
+            return Err(DomainError::DocumentFrozen(doc_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns how the content deleted from a document is garbage collected.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// The options set by an operator, or those of the document's policy
+    pub fn document_gc_options(&self, doc_id: &str) -> GcOptions {
+        self.gc_overrides
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(doc_id)
+            .copied()
+            .unwrap_or_else(|| self.policies.resolve(doc_id).gc_options())
+    }
+
+    /// Sets how the content deleted from a document is garbage collected,
+    /// overriding its policy.
+    ///
+    /// The options apply to the document at once if it is resident, and
+    /// whenever it is opened again; they are kept in memory only, so the
+    /// document's policy applies again once the server restarts. Enabling or
+    /// disabling collection rebuilds a resident document, dropping the undo
+    /// stacks of its clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `gc` - The garbage collection options
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the options are set
+    /// * `Err(DomainError)` - If the resident document could not be rebuilt
+    pub async fn set_document_gc_options(&self, doc_id: &str, gc: GcOptions) -> DomainResult<()> {
+        self.gc_overrides
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(doc_id.to_string(), gc);
+
+        let Some(document) = self.document_repository.get_document(doc_id) else {
+            return Ok(());
+        };
+        let mut state = document.write().await;
+        // Documents opened later on pick the options up with their policy
+        if state.policy().is_none() || state.is_retired() {
+            return Ok(());
+        }
+        state.set_gc_options(gc).await
+    }
+
+    /// Garbage collects the content deleted from a document at once, whatever
+    /// its options.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(StructureStats))` - The document's structure once collected
+    /// * `Ok(None)` - If the document doesn't exist
+    /// * `Err(DomainError)` - If the document could not be collected
+    pub async fn collect_document_garbage(
+        &self,
+        doc_id: &str,
+    ) -> DomainResult<Option<StructureStats>> {
+        if !self.document_exists(doc_id).await {
+            return Ok(None);
+        }
+
+        let state = self.read_document(doc_id).await;
+        state.collect_garbage().await?;
+        Ok(Some(state.structure_stats().await))
+    }
+
+    /// Returns when the content of a document was last modified.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// The time of the last modification as Unix seconds, or `None` if it is unknown
+    pub fn last_modified(&self, doc_id: &str) -> Option<i64> {
+        let modified = self
+            .modified
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(doc_id)
+            .copied();
+        modified.or_else(|| {
+            let metadata = self.metadata.as_ref()?;
+            match metadata.get(doc_id) {
+                Ok(metadata) => metadata.updated_at,
+                Err(e) => {
+                    warn!("Failed to read the metadata of '{}': {}", doc_id, e);
+                    None
+                }
+            }
+        })
+    }
+
+    /// Records that a document was created or modified, in its metadata if
+    /// enabled.
+    ///
+    /// The content is already modified, so a failure to record it is only
+    /// logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    fn record_modified(&self, doc_id: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        let previous = self
+            .modified
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(doc_id.to_string(), now);
+        if previous == Some(now) || self.metadata.is_none() {
+            return;
+        }
+        if let Err(e) = self.update_metadata(doc_id, |metadata| metadata.touch(now)) {
+            warn!(
+                "Failed to record the modification of '{}' in its metadata: {}",
+                doc_id, e
+            );
+        }
+    }
+
+    /// Applies a change to a document's metadata and stores the result.
+    ///
+    /// A change that does not alter the metadata is not written.
+    fn update_metadata(
+        &self,
+        doc_id: &str,
+        change: impl FnOnce(&mut DocumentMetadata),
+    ) -> DomainResult<DocumentMetadata> {
+        let repository = self.metadata()?;
+        let _guard = self
+            .metadata_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let previous = repository.get(doc_id)?;
+        let mut metadata = previous.clone();
+        change(&mut metadata);
+        if metadata != previous {
+            repository.put(doc_id, &metadata)?;
+        }
+        Ok(metadata)
+    }
+
+    /// Lists the documents carrying a tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag to look for
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - Identifiers of the tagged documents, sorted
+    /// * `Err(DomainError)` - If the metadata could not be read
+    pub fn documents_with_tag(&self, tag: &str) -> DomainResult<Vec<String>> {
+        let tag = tag.trim();
+        let mut doc_ids: Vec<String> = self
+            .metadata()?
+            .list()?
+            .into_iter()
+            .filter(|(_, metadata)| {
+                metadata
+                    .tags
+                    .iter()
+                    .any(|candidate| self.names_match(candidate, tag))
+            })
+            .map(|(doc_id, _)| doc_id)
+            .collect();
+        doc_ids.sort();
+        self.collate(&mut doc_ids);
+        Ok(doc_ids)
+    }
+
+    /// Counts the documents carrying each tag.
+    ///
+    /// Tags the collation does not tell apart are counted together, under the
+    /// spelling met first.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(BTreeMap<String, usize>)` - Number of documents per tag
+    /// * `Err(DomainError)` - If the metadata could not be read
+    pub fn tag_counts(&self) -> DomainResult<BTreeMap<String, usize>> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for (_, metadata) in self.metadata()?.list()? {
+            // A document carrying equivalent spellings of a tag counts once
+            let mut counted = BTreeSet::new();
+            for tag in metadata.tags {
+                let tag = counts
+                    .keys()
+                    .find(|known| self.names_match(known, &tag))
+                    .cloned()
+                    .unwrap_or(tag);
+                if counted.insert(tag.clone()) {
+                    *counts.entry(tag).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Returns the version repository, or an error if none is configured.
+    fn versions(&self) -> DomainResult<&Arc<dyn VersionRepository>> {
+        self.versions.as_ref().ok_or_else(|| {
+            DomainError::Unavailable("Document versions are not available".to_string())
+        })
+    }
+
+    /// Records a named version of a document, a snapshot of its current state.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `label` - Name of the version, or `None` for an unnamed snapshot
+    /// * `author` - Who records the version, if known
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DocumentVersion)` - The recorded version
+    /// * `Err(DomainError)` - If the document does not exist, the label is invalid or the version
+    ///   could not be stored
+    pub async fn create_version(
+        &self,
+        doc_id: &str,
+        label: Option<&str>,
+        author: Option<&str>,
+    ) -> DomainResult<DocumentVersion> {
+        self.versions()?;
+        let label = label.map(DocumentVersion::normalize_label).transpose()?;
+        let author = author
+            .map(str::trim)
+            .filter(|author| !author.is_empty())
+            .map(str::to_string);
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        let snapshot = self
+            .with_actor(doc_id, |actor| async move { actor.snapshot().await })
+            .await?;
+        self.record_version(
+            doc_id,
+            label,
+            author,
+            snapshot.update.as_deref().unwrap_or_default(),
+        )
+    }
+
+    /// Records a periodic snapshot of every document updated since its last one.
+    ///
+    /// Documents whose snapshot could not be stored are retried by the next call.
+    /// Without a version repository, nothing is recorded.
+    ///
+    /// # Returns
+    ///
+    /// The number of snapshots recorded
+    pub async fn record_periodic_versions(&self) -> usize {
+        if self.versions.is_none() {
+            return 0;
+        }
+        let doc_ids =
+            std::mem::take(&mut *self.unversioned.lock().unwrap_or_else(|e| e.into_inner()));
+
+        let mut recorded = 0;
+        for doc_id in doc_ids {
+            // Deleted since it was updated
+            let Some(document) = self.document_repository.get_document(&doc_id) else {
+                continue;
+            };
+            let snapshot = document.read().await.get_full_update().await;
+            let version =
+                snapshot.and_then(|snapshot| self.record_version(&doc_id, None, None, &snapshot));
+
+            match version {
+                Ok(_) => recorded += 1,
+                Err(e) => {
+                    warn!("Failed to record a version of document '{}': {}", doc_id, e);
+                    self.mark_unversioned(&doc_id);
+                }
+            }
+        }
+        recorded
+    }
+
+    /// Stores a version of a document, numbered after its latest one, and prunes
+    /// the oldest versions beyond the policy's maximum.
+    fn record_version(
+        &self,
+        doc_id: &str,
+        label: Option<String>,
+        author: Option<String>,
+        snapshot: &[u8],
+    ) -> DomainResult<DocumentVersion> {
+        let repository = self.versions()?;
+        let _guard = self
+            .versions_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let existing = repository.list(doc_id)?;
+        let version = DocumentVersion {
+            version: existing.last().map_or(1, |latest| latest.version + 1),
+            label,
+            author,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64,
+            size: snapshot.len(),
+        };
+        repository.put(doc_id, &version, snapshot)?;
+
+        let max_versions = self.version_policy.max_versions;
+        if max_versions > 0 && existing.len() >= max_versions {
+            for pruned in &existing[..existing.len() + 1 - max_versions] {
+                repository.remove(doc_id, pruned.version)?;
+            }
+        }
+        Ok(version)
+    }
+
+    /// Reindexes the documents changed since the last pass for full-text search.
+    ///
+    /// Changed documents are indexed with their current text content, deleted
+    /// ones removed from the index, and the changes committed at once. Documents
+    /// that could not be indexed are retried by the next call. Without a search
+    /// index, nothing is indexed.
+    ///
+    /// # Returns
+    ///
+    /// The number of documents indexed or removed
+    pub async fn index_documents(&self) -> usize {
+        let Some(search) = &self.search else {
+            return 0;
+        };
+        let doc_ids =
+            std::mem::take(&mut *self.unindexed.lock().unwrap_or_else(|e| e.into_inner()));
+        if doc_ids.is_empty() {
+            return 0;
+        }
+
+        let mut indexed = 0;
+        for doc_id in doc_ids {
+            let result = match self.export_content(&doc_id, ExportFormat::Text).await {
+                Ok(content) => search.index(&doc_id, &content),
+                Err(DomainError::NotFound(_)) => search.remove(&doc_id),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => indexed += 1,
+                Err(e) => {
+                    warn!("Failed to index document '{}': {}", doc_id, e);
+                    self.mark_unindexed(&doc_id);
+                }
+            }
+        }
+
+        // Uncommitted changes stay buffered in the index until the next commit
+        if let Err(e) = search.commit() {
+            warn!("Failed to commit the search index: {}", e);
+        }
+        indexed
+    }
+
+    /// Schedules every document for indexing by the next `index_documents` pass,
+    /// e.g. to fill an index kept in memory after a restart.
+    ///
+    /// # Returns
+    ///
+    /// The number of documents scheduled
+    pub async fn reindex_all_documents(&self) -> usize {
+        if self.search.is_none() {
+            return 0;
+        }
+        let doc_ids = self.list_documents().await;
+        let scheduled = doc_ids.len();
+        self.unindexed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(doc_ids);
+        scheduled
+    }
+
+    /// Searches the text content of the documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query, in the syntax of the search index
+    /// * `limit` - Maximum number of hits returned, capped at `MAX_SEARCH_LIMIT`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchHit>)` - The matching documents, best match first
+    /// * `Err(DomainError)` - `Unavailable` if search is not enabled, `InvalidArgument` if the
+    ///   query is empty or malformed, or an error if the index could not be read
+    pub fn search_documents(&self, query: &str, limit: usize) -> DomainResult<Vec<SearchHit>> {
+        let search = self.search.as_ref().ok_or_else(|| {
+            DomainError::Unavailable("Full-text search is not enabled".to_string())
+        })?;
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(DomainError::InvalidArgument(
+                "The search query is empty".to_string(),
+            ));
+        }
+        search.search(query, limit.clamp(1, MAX_SEARCH_LIMIT))
+    }
+
+    /// Records that a document must be reindexed for search, if enabled and owned.
+    fn mark_unindexed(&self, doc_id: &str) {
+        if self.search.is_some() && self.forwarding_owner(doc_id).is_none() {
+            self.unindexed
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(doc_id.to_string());
+        }
+    }
+
+    /// Records that a document must be snapshotted periodically, if enabled and owned.
+    fn mark_unversioned(&self, doc_id: &str) {
+        if self.versions.is_some()
+            && self.version_policy.interval.is_some()
+            && self.forwarding_owner(doc_id).is_none()
+        {
+            self.unversioned
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(doc_id.to_string());
+        }
+    }
+
+    /// Lists the versions recorded in a document's timeline.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<DocumentVersion>)` - The versions, oldest first
+    /// * `Err(DomainError)` - If the versions could not be read
+    pub fn list_versions(&self, doc_id: &str) -> DomainResult<Vec<DocumentVersion>> {
+        self.versions()?.list(doc_id)
+    }
+
+    /// Retrieves a version of a document with its snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `version` - Number of the version
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((DocumentVersion, Vec<u8>))` - The version and the state of the document at that
+    ///   version, encoded as a single update
+    /// * `Err(DomainError)` - `NotFound` if the document has no such version
+    pub fn get_version(
+        &self,
+        doc_id: &str,
+        version: u64,
+    ) -> DomainResult<(DocumentVersion, Vec<u8>)> {
+        self.versions()?
+            .get(doc_id, version)?
+            .ok_or_else(|| DomainError::NotFound(format!("{} (version {})", doc_id, version)))
+    }
+
+    /// Reverts a document to the content of one of its versions.
+    ///
+    /// The update restoring the version's content is computed on top of the
+    /// document's current state and applied like any client update: it is
+    /// persisted and broadcast, so connected clients converge to the version
+    /// without reconnecting, and the changes made since stay in the history.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `version` - Number of the version to restore
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the document changed, `false` if it already held the version's
+    ///   content
+    /// * `Err(DomainError)` - `NotFound` if the document or version does not exist, or an error if
+    ///   the update could not be applied
+    pub async fn revert_document(&self, doc_id: &str, version: u64) -> DomainResult<bool> {
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+        let (_, snapshot) = self.get_version(doc_id, version)?;
+        self.revert_to_state(doc_id, &snapshot).await
+    }
+
+    /// Reverts a document to the content of a previous state, e.g. to roll back an update.
+    ///
+    /// Like [`revert_document`](Self::revert_document), the update restoring
+    /// the content is applied on top of the document's current state, so the
+    /// changes other clients made since to the same roots are reverted too.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `snapshot` - The state to restore, encoded as a single update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the document changed, `false` if it already held the content
+    /// * `Err(DomainError)` - `InvalidUpdate` if the state couldn't be decoded, or an error if the
+    ///   update could not be applied
+    pub async fn revert_to_state(&self, doc_id: &str, snapshot: &[u8]) -> DomainResult<bool> {
+        let state = self.open_document(doc_id).await;
+        let Some(update) = state.revert_update(snapshot).await? else {
+            return Ok(false);
+        };
+        let origin = UpdateOrigin::server(SERVER_UPDATE_SOURCE);
+        self.apply_locked_update(doc_id, &state, &update, origin, None)
+            .await?;
+        Ok(true)
+    }
+
+    /// Writes a change into a map or array root of a document, as the server.
+    ///
+    /// Backend services use it to write into collaborative state without
+    /// running a Yjs client: the update making the change is computed on top of
+    /// the document's current state and applied like any client update, so it
+    /// is persisted, audited and broadcast to the connected clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `edit` - The change to write
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the document changed, `false` e.g. when removing a missing key
+    /// * `Err(DomainError)` - `NotFound` if the document does not exist, `Conflict` if the root
+    ///   holds another kind of shared type, or an error if the update could not be applied
+    pub async fn edit_document(&self, doc_id: &str, edit: SharedEdit) -> DomainResult<bool> {
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        let state = self.open_document(doc_id).await;
+        let Some(update) = state.edit_update(edit).await? else {
+            return Ok(false);
+        };
+        let origin = UpdateOrigin::server(SERVER_UPDATE_SOURCE);
+        self.apply_locked_update(doc_id, &state, &update, origin, None)
+            .await?;
+        Ok(true)
+    }
+
+    /// Undoes or redoes the last change a client made to a document.
+    ///
+    /// With undo enabled by the document's policy, the server keeps an undo
+    /// stack per client, tracking the updates the client applied. Undoing a
+    /// change applies the inverse update, which is persisted and broadcast like
+    /// any update, including to the client itself, so thin clients need no undo
+    /// manager of their own.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `origin` - The client whose change is undone or redone
+    /// * `action` - Whether to undo or redo the change
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the document changed, `false` if the client's stack had nothing to
+    ///   undo or redo
+    /// * `Err(DomainError)` - `Unavailable` if the document's policy disables undo, or an error if
+    ///   the update could not be persisted
+    pub async fn undo_document(
+        &self,
+        doc_id: &str,
+        origin: UpdateOrigin<'_>,
+        action: UndoAction,
+    ) -> DomainResult<bool> {
+        if !self.policies.resolve(doc_id).undo_enabled {
+            return Err(DomainError::Unavailable(
+                "Undo is disabled for this document".to_string(),
+            ));
+        }
+        self.check_writable(doc_id)?;
+
+        let state = self.open_document(doc_id).await;
+        let previous_size = state.size();
+        if !state.undo(origin.client_id, action).await? {
+            return Ok(false);
+        }
+        self.mark_unsaved(doc_id);
+        self.mark_unversioned(doc_id);
+        self.mark_unindexed(doc_id);
+        self.activity.record(doc_id, origin.client_id);
+        self.record_modified(doc_id);
+        self.record_audit(
+            doc_id,
+            origin,
+            state.size().saturating_sub(previous_size),
+            state.sequence_number(),
+        );
+        self.publish_event(DocumentEvent::Updated {
+            doc_id: doc_id.to_string(),
+            source: origin.client_id.to_string(),
+        });
+        Ok(true)
+    }
+
+    /// Drops the undo stack a client has for a document, e.g. once it disconnects.
+    ///
+    /// Documents that are not loaded have no undo stack and are left unloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `client_id` - Identifier of the client
+    pub async fn release_undo(&self, doc_id: &str, client_id: &str) {
+        if let Some(document) = self.document_repository.get_document(doc_id) {
+            document.read().await.release_undo(client_id).await;
+        }
+    }
+
+    /// Exports the content of a document at one of its versions.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `version` - Number of the version
+    /// * `format` - The format to export the content to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The content of the version in the given format
+    /// * `Err(DomainError)` - `NotFound` if the document has no such version, or an error if its
+    ///   snapshot could not be decoded
+    pub async fn export_version(
+        &self,
+        doc_id: &str,
+        version: u64,
+        format: ExportFormat,
+    ) -> DomainResult<String> {
+        let (_, snapshot) = self.get_version(doc_id, version)?;
+        tokio::task::spawn_blocking(move || {
+            let mut document = CollaborativeDocument::new();
+            document.apply_update(&snapshot)?;
+            DocumentExporter::export(&document, format)
+        })
+        .await
+        .map_err(|e| DomainError::Internal(format!("Failed to export the version: {}", e)))?
+    }
+
+    /// Checks whether two names, such as tags, are equivalent under the collation.
+    fn names_match(&self, a: &str, b: &str) -> bool {
+        match &self.collation {
+            Some(collation) => collation.matches(a, b),
+            None => a == b,
+        }
+    }
+
+    /// Sorts names already sorted by their bytes in the order of the collation.
+    ///
+    /// The sort is stable, so names the collation does not tell apart keep
+    /// their byte order and listings stay deterministic.
+    fn collate(&self, names: &mut [String]) {
+        if let Some(collation) = &self.collation {
+            names.sort_by(|a, b| collation.compare(a, b));
+        }
+    }
+
+    /// Publishes a notice to the connected clients.
+    ///
+    /// A notice concerning a document reaches the connections collaborating on
+    /// it; a notice without a document reaches every connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - The notice to deliver
+    ///
+    /// # Returns
+    ///
+    /// The number of connections the notice was offered to
+    pub fn publish_notice(&self, notice: Notice) -> usize {
+        self.notices.send(notice).unwrap_or(0)
+    }
+
+    /// Subscribes a connection to the notices published from now on.
+    pub fn subscribe_notices(&self) -> broadcast::Receiver<Notice> {
+        self.notices.subscribe()
+    }
+
+    /// Subscribes an integration to the lifecycle events of every document from now on.
+    ///
+    /// Documents created implicitly, by the first client opening them, are only
+    /// detected while an integration is subscribed.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<DocumentEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publishes a lifecycle event; it is dropped when no integration is subscribed.
+    fn publish_event(&self, event: DocumentEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Applies a client update through the document's actor and warns the document's
+    /// clients once it nears its size limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `update_data` - The binary update data
+    /// * `origin` - Who sent the update, and through which transport
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was successfully applied
+    /// * `Err(DomainError)` - An error message if the update couldn't be applied
+    async fn apply_client_update(
+        &self,
+        doc_id: &str,
+        update_data: &[u8],
+        origin: UpdateOrigin<'_>,
+    ) -> DomainResult<()> {
+        self.check_writable(doc_id)?;
+        let outcome = self
+            .with_actor(doc_id, |actor| async move {
+                actor
+                    .apply_update(update_data.to_vec(), origin.into())
+                    .await
+            })
+            .await;
+        self.settle_client_update(doc_id, update_data, origin, outcome)
+    }
+
+    /// Applies a client update to a document locked by the caller, like
+    /// [`apply_client_update`](Self::apply_client_update) does through its actor.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `state` - The locked document
+    /// * `update_data` - The binary update data
+    /// * `origin` - Who sent the update, and through which transport
+    /// * `revertible` - Key to record the update under so it can be reverted, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was successfully applied
+    /// * `Err(DomainError)` - An error message if the update couldn't be applied
+    async fn apply_locked_update(
+        &self,
+        doc_id: &str,
+        state: &SingleDocumentServiceImpl,
+        update_data: &[u8],
+        origin: UpdateOrigin<'_>,
+        revertible: Option<&str>,
+    ) -> DomainResult<()> {
+        self.check_writable(doc_id)?;
+        let outcome = DocumentActor::apply_to(
+            state,
+            self.update_limits,
+            update_data,
+            &origin.into(),
+            revertible,
+        )
+        .await;
+        self.settle_client_update(doc_id, update_data, origin, outcome)
+    }
+
+    /// Applies a client update to a document, recording it under a key so that it alone can
+    /// be reverted later with [`revert_update`](Self::revert_update), keeping the changes
+    /// others made since.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `update_data` - The binary update data
+    /// * `origin` - Who sent the update, and through which transport
+    /// * `key` - Key to record the update under, unique to the caller
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was successfully applied
+    /// * `Err(DomainError)` - An error message if the update couldn't be applied
+    pub async fn apply_revertible_update(
+        &self,
+        doc_id: &str,
+        update_data: &[u8],
+        origin: UpdateOrigin<'_>,
+        key: &str,
+    ) -> DomainResult<()> {
+        let state = self.open_document(doc_id).await;
+        self.apply_locked_update(doc_id, &state, update_data, origin, Some(key))
+            .await
+    }
+
+    /// Reverts an update recorded under a key: the content it inserted is deleted and the
+    /// content it deleted is restored, while the changes others made since are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `origin` - Who reverts the update, and through which transport
+    /// * `key` - Key the update was recorded under
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether an update was recorded under the key and got reverted
+    /// * `Err(DomainError)` - An error message if the update couldn't be reverted
+    pub async fn revert_update(
+        &self,
+        doc_id: &str,
+        origin: UpdateOrigin<'_>,
+        key: &str,
+    ) -> DomainResult<bool> {
+        let Some(document) = self.document_repository.get_document(doc_id) else {
+            return Ok(false);
+        };
+        let state = document.write().await;
+        let previous_size = state.size();
+        if !state.revert(key).await? {
+            return Ok(false);
+        }
+        self.mark_unsaved(doc_id);
+        self.mark_unversioned(doc_id);
+        self.mark_unindexed(doc_id);
+        self.record_modified(doc_id);
+        self.record_audit(
+            doc_id,
+            origin,
+            state.size().saturating_sub(previous_size),
+            state.sequence_number(),
+        );
+        self.publish_event(DocumentEvent::Updated {
+            doc_id: doc_id.to_string(),
+            source: origin.client_id.to_string(),
+        });
+        Ok(true)
+    }
+
+    /// Drops an update recorded under a key, once it no longer needs reverting.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `key` - Key the update was recorded under
+    pub async fn release_revertible(&self, doc_id: &str, key: &str) {
+        if let Some(document) = self.document_repository.get_document(doc_id) {
+            document.read().await.release_revertible(key).await;
+        }
+    }
+
+    /// Records the outcome of a client update: an applied update marks the
+    /// document as changed and may warn its clients that it nears its size
+    /// limit, while an update rejected for its size is counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `update_data` - The binary update data
+    /// * `origin` - Who sent the update, and through which transport
+    /// * `outcome` - The outcome of the update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was applied
+    /// * `Err(DomainError)` - Why the update couldn't be applied
+    fn settle_client_update(
+        &self,
+        doc_id: &str,
+        update_data: &[u8],
+        origin: UpdateOrigin<'_>,
+        outcome: UpdateOutcome,
+    ) -> DomainResult<()> {
+        let applied = outcome.map_err(|rejected| match rejected.limit {
+            Some(limit) => self.reject_oversized(doc_id, limit, rejected.error),
+            None => rejected.error,
+        })?;
+
+        self.mark_unsaved(doc_id);
+        self.mark_unversioned(doc_id);
+        self.mark_unindexed(doc_id);
+        self.activity.record(doc_id, origin.client_id);
+        self.record_modified(doc_id);
+        self.record_audit(doc_id, origin, update_data.len(), applied.sequence_number);
+        if let Some(dictionaries) = &self.payload_dictionaries {
+            dictionaries.record(doc_id, update_data);
+        }
+        self.publish_event(DocumentEvent::Updated {
+            doc_id: doc_id.to_string(),
+            source: origin.client_id.to_string(),
+        });
+
+        let policy = self.document_policy(doc_id);
+        if policy.crosses_quota_warning(applied.previous_size, applied.size) {
+            self.publish_notice(
+                Notice::new(
+                    NoticeKind::QuotaWarning,
+                    NoticeSeverity::Warning,
+                    format!(
+                        "The document is nearly at its maximum size of {} bytes",
+                        policy.max_document_size
+                    ),
+                )
+                .with_document(doc_id),
+            );
+        }
+
+        if policy.crosses_character_warning(applied.previous_characters, applied.characters) {
+            self.publish_notice(
+                Notice::new(
+                    NoticeKind::QuotaWarning,
+                    NoticeSeverity::Warning,
+                    format!(
+                        "The document is nearly at its maximum of {} characters",
+                        policy.max_document_characters
+                    ),
+                )
+                .with_document(doc_id),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Records an applied update in the document's audit trail, if enabled.
+    ///
+    /// The update is already applied, so a failure to record it is only logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `origin` - Who applied the update, and through which transport
+    /// * `update_size` - Size in bytes of the binary update
+    /// * `sequence_number` - Sequence number of the broadcast carrying the update
+    fn record_audit(
+        &self,
+        doc_id: &str,
+        origin: UpdateOrigin<'_>,
+        update_size: usize,
+        sequence_number: u64,
+    ) {
+        let Some(audit) = &self.audit else {
+            return;
+        };
+        let applied_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let entry = AuditEntry::new(origin, update_size, sequence_number, applied_at);
+        if let Err(e) = audit.record(doc_id, entry) {
+            warn!(
+                "Failed to record an update of document '{}' in its audit trail: {}",
+                doc_id, e
+            );
+        }
+    }
+
+    /// Lists a page of the audit trail of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `after` - Cursor returned with the previous page, `0` to start from the oldest entry
+    /// * `limit` - Maximum number of entries returned, capped at `MAX_AUDIT_PAGE_SIZE`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AuditPage)` - The entries recorded after the cursor, oldest first
+    /// * `Err(DomainError)` - `Unavailable` if no audit sink is configured, or `StorageFailure` if
+    ///   the trail could not be read
+    pub fn list_audit_entries(
+        &self,
+        doc_id: &str,
+        after: u64,
+        limit: usize,
+    ) -> DomainResult<AuditPage> {
+        let audit = self.audit.as_ref().ok_or_else(|| {
+            DomainError::Unavailable("The audit trail is not enabled".to_string())
+        })?;
+        let limit = limit.clamp(1, MAX_AUDIT_PAGE_SIZE);
+        let entries = audit.list(doc_id, after, limit)?;
+        let next = entries
+            .last()
+            .filter(|_| entries.len() == limit)
+            .map(|last| last.id);
+        Ok(AuditPage { entries, next })
+    }
+
+    /// Rejects an update exceeding the server-wide size limits, counting the rejection.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `state` - The locked document
+    /// * `update_data` - The binary update about to be applied
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update may be applied
+    /// * `Err(DomainError)` - `PayloadTooLarge` if the update or the updated document is too large
+    fn check_update_size(
+        &self,
+        doc_id: &str,
+        state: &SingleDocumentServiceImpl,
+        update_data: &[u8],
+    ) -> DomainResult<()> {
+        self.update_limits
+            .check(state.size(), update_data.len())
+            .map_err(|(limit, error)| self.reject_oversized(doc_id, limit, error))
+    }
+
+    /// Counts an update rejected for exceeding a server-wide size limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `limit` - The exceeded limit
+    /// * `error` - The `PayloadTooLarge` error raised
+    ///
+    /// # Returns
+    ///
+    /// The error, to return to the client
+    fn reject_oversized(&self, doc_id: &str, limit: SizeLimit, error: DomainError) -> DomainError {
+        let rejections = match limit {
+            SizeLimit::Update => &self.oversized_updates,
+            SizeLimit::Document => &self.oversized_documents,
+        };
+        rejections.fetch_add(1, Ordering::Relaxed);
+        warn!("Rejected update to document '{}': {}", doc_id, error);
+        error
+    }
+
+    /// Returns the number of updates rejected for exceeding each server-wide size limit.
+    pub fn oversized_update_counts(&self) -> [(SizeLimit, u64); 2] {
+        [
+            (
+                SizeLimit::Update,
+                self.oversized_updates.load(Ordering::Relaxed),
+            ),
+            (
+                SizeLimit::Document,
+                self.oversized_documents.load(Ordering::Relaxed),
+            ),
+        ]
+    }
+
+    /// Records that a document must be saved to the store, if any and owned.
+    fn mark_unsaved(&self, doc_id: &str) {
+        if self.store.is_some() && self.forwarding_owner(doc_id).is_none() {
+            self.unsaved
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(doc_id.to_string());
+        }
+    }
+
+    /// Saves the documents updated since they were last saved to the store.
+    ///
+    /// Documents that could not be saved are retried by the next call. Without a
+    /// store, nothing is saved.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(usize)` - The number of documents saved
+    /// * `Err(DomainError)` - The first error raised encoding or storing a document, once every
+    ///   document was tried
+    pub async fn save_documents(&self) -> DomainResult<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let doc_ids = std::mem::take(&mut *self.unsaved.lock().unwrap_or_else(|e| e.into_inner()));
+
+        let mut saved = 0;
+        let mut failure = None;
+        for doc_id in doc_ids {
+            // Deleted since it was updated
+            let Some(document) = self.document_repository.get_document(&doc_id) else {
+                continue;
+            };
+            let state = document.read().await.snapshot().await;
+            let outcome = match state {
+                Ok(state) => store.save(&doc_id, &state).await,
+                Err(e) => Err(e),
+            };
+
+            match outcome {
+                Ok(()) => saved += 1,
+                Err(e) => {
+                    warn!("Failed to save document '{}' to the store: {}", doc_id, e);
+                    self.mark_unsaved(&doc_id);
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(saved),
+        }
+    }
+
+    /// Evicts the documents selected by the repository's eviction policy.
+    ///
+    /// Each document is saved to the store before it is deleted from the
+    /// repository, and is restored from the store when it is opened again.
+    /// Documents with subscribers, such as connected clients, stay resident, as
+    /// do documents that could not be saved. Without a store, nothing is evicted.
+    ///
+    /// # Returns
+    ///
+    /// The number of documents evicted
+    pub async fn evict_documents(&self) -> usize {
+        let Some(store) = &self.store else {
+            return 0;
+        };
+
+        let mut evicted = 0;
+        for doc_id in self.document_repository.eviction_candidates() {
+            let Some(document) = self.document_repository.get_document(&doc_id) else {
+                continue;
+            };
+            let mut state = document.write_owned().await;
+            if state.subscriber_count() > 0 || state.is_retired() {
+                continue;
+            }
+
+            match self.unload(store.as_ref(), &doc_id, &mut state).await {
+                Ok(()) => evicted += 1,
+                Err(e) => warn!("Failed to evict document '{}': {}", doc_id, e),
+            }
+        }
+        evicted
+    }
+
+    /// Unloads a document from the repository at an operator's request.
+    ///
+    /// The document is saved to the store and deleted from the repository, as
+    /// eviction does, even if clients are subscribed to it; it is restored from
+    /// the store when it is opened again. Subscribers stop receiving its updates,
+    /// so its clients should be disconnected first.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the document was resident and is now unloaded
+    /// * `Err(DomainError)` - `Unavailable` without a store, or the error raised saving the
+    ///   document
+    pub async fn unload_document(&self, doc_id: &str) -> DomainResult<bool> {
+        let Some(store) = &self.store else {
+            return Err(DomainError::Unavailable(format!(
+                "Document '{}' cannot be unloaded without a document store",
+                doc_id
+            )));
+        };
+        let Some(document) = self.document_repository.get_document(doc_id) else {
+            return Ok(false);
+        };
+        let mut state = document.write_owned().await;
+        if state.is_retired() {
+            return Ok(false);
+        }
+
+        self.unload(store.as_ref(), doc_id, &mut state).await?;
+        Ok(true)
+    }
+
+    /// Saves a resident document to the store, then deletes it from the repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The document store
+    /// * `doc_id` - Identifier of the document
+    /// * `state` - The locked document, retired once deleted
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document was unloaded
+    /// * `Err(DomainError)` - If it could not be saved or deleted, leaving it resident
+    async fn unload(
+        &self,
+        store: &dyn DocumentStore,
+        doc_id: &str,
+        state: &mut SingleDocumentServiceImpl,
+    ) -> DomainResult<()> {
+        store.save(doc_id, &state.snapshot().await?).await?;
+        self.unsaved
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(doc_id);
+        self.document_repository.delete_document(doc_id)?;
+        state.retire();
+        self.forget_actor(doc_id);
+        Ok(())
+    }
+
+    /// Returns the activity of a document, aggregated into time buckets.
+    ///
+    /// Every update applied by a client counts toward the bucket it was applied
+    /// in, and every client that applied one as an editor of that bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `granularity` - Width of the returned buckets
+    ///
+    /// # Returns
+    ///
+    /// The retained buckets during which the document was edited, oldest first
+    pub fn document_activity(
+        &self,
+        doc_id: &str,
+        granularity: ActivityGranularity,
+    ) -> Vec<ActivityBucket> {
+        self.activity.activity(doc_id, granularity)
+    }
+
+    /// Returns the feature policy of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// The policy of the document's namespace, or the default policy
+    pub fn document_policy(&self, doc_id: &str) -> Arc<FeaturePolicy> {
+        self.policies.resolve(doc_id)
+    }
+
+    /// Checks whether a client may access a document.
+    ///
+    /// Transport adapters call this before serving a document to a client, as
+    /// only they know whether the client has a user identity.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `user_id` - Identity of the client, or `None` for a guest
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the client may access the document
+    /// * `Err(DomainError)` - If the document's policy denies access
+    pub fn authorize(&self, doc_id: &str, user_id: Option<&str>) -> DomainResult<()> {
+        self.document_policy(doc_id).authorize(user_id)
+    }
+
+    /// Checks that this instance serves the clients of a document.
+    ///
+    /// Documents owned by other instances of the cluster are redirected, unless
+    /// they are forwarded. Ownership moves as members join or leave the cluster,
+    /// so transports check it again for every message of a connected client.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If this instance serves the document's clients
+    /// * `Err(DomainError::Moved)` - Naming the owner of the document otherwise
+    pub fn check_owner(&self, doc_id: &str) -> DomainResult<()> {
+        // Subdocuments are owned by the owner of their top-level document
+        if let (Some(ownership), None) = (&self.ownership, &self.forwarder) {
+            ownership.check(root_document_id(doc_id))?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the role of a client joining a document.
+    ///
+    /// A clustered instance first checks that it owns the document. The
+    /// document's feature policy is checked next, then the access control
+    /// decides whether the client may edit the document, within the restrictions
+    /// granted to the user at runtime. Transport adapters reject the updates of
+    /// read-only clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `user_id` - Identity of the client, or `None` for a guest
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AccessRole)` - The permission the client holds on the document
+    /// * `Err(DomainError)` - If the client may not access the document
+    pub fn access_role(&self, doc_id: &str, user_id: Option<&str>) -> DomainResult<AccessRole> {
+        self.check_owner(doc_id)?;
+        // Subdocuments share the owner and the permissions of their top-level document
+        let doc_id = root_document_id(doc_id);
+        self.authorize(doc_id, user_id)?;
+        self.check_tenant_quota(doc_id)?;
+
+        let role = match &self.access_control {
+            Some(access_control) => access_control.role(doc_id, user_id)?,
+            None => AccessRole::ReadWrite,
+        };
+
+        let Some(user_id) = user_id else {
+            return Ok(role);
+        };
+        let grant = {
+            let grants = self
+                .access_grants
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            grants
+                .get(&(user_id.to_string(), Some(doc_id.to_string())))
+                .or_else(|| grants.get(&(user_id.to_string(), None)))
+                .copied()
+        };
+
+        match grant {
+            Some(grant) => grant.restrict(role).ok_or_else(|| {
+                DomainError::Unauthorized(format!(
+                    "Access to document '{}' has been revoked",
+                    doc_id
+                ))
+            }),
+            None => Ok(role),
+        }
+    }
+
+    /// Checks whether a document may be served to its tenant.
+    ///
+    /// Existing documents are always served; a new document is refused once its
+    /// tenant has as many documents as allowed, counting those of the repository
+    /// and of the archive tier. Subdocuments count as part of their top-level
+    /// document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document exists, belongs to no tenant or its tenant has room left
+    /// * `Err(DomainError)` - `LimitExceeded` if the tenant has as many documents as allowed
+    pub fn check_tenant_quota(&self, doc_id: &str) -> DomainResult<()> {
+        let doc_id = root_document_id(doc_id);
+        let Some((tenant, limits)) = self.tenant_quotas.limits_of(doc_id) else {
+            return Ok(());
+        };
+        if limits.max_documents == 0
+            || self.document_repository.exists(doc_id)
+            || self.is_archived(doc_id)
+        {
+            return Ok(());
+        }
+
+        let mut doc_ids = self.document_repository.list_documents();
+        if let Some(archive) = &self.archive {
+            doc_ids.extend(archive.archived());
+        }
+        doc_ids.retain(|id| {
+            root_document_id(id) == id.as_str() && FeaturePolicies::namespace_of(id) == Some(tenant)
+        });
+        doc_ids.sort();
+        doc_ids.dedup();
+
+        if doc_ids.len() >= limits.max_documents {
+            return Err(DomainError::LimitExceeded(format!(
+                "Tenant '{}' already has {} documents, the maximum allowed",
+                tenant, limits.max_documents
+            )));
+        }
+        Ok(())
+    }
+
+    /// Changes the permission of a user at runtime, without waiting for it to reconnect.
+    ///
+    /// The grant applies to every later role resolution, so transport adapters
+    /// reject the user's next updates; it is kept in memory only and lost when
+    /// the server restarts. A grant on a single document takes precedence over
+    /// a grant on every document.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Identity of the user
+    /// * `doc_id` - Identifier of the document, or `None` for every document
+    /// * `grant` - The permission granted; `ReadWrite` lifts a previous restriction
+    pub fn grant_access(&self, user_id: &str, doc_id: Option<&str>, grant: AccessGrant) {
+        let key = (user_id.to_string(), doc_id.map(str::to_string));
+        let mut grants = self
+            .access_grants
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match grant {
+            AccessGrant::ReadWrite => grants.remove(&key),
+            grant => grants.insert(key, grant),
+        };
+    }
+
+    /// Opens a document, creating it if needed, and locks it for writing.
+    ///
+    /// The first time a document is opened, its feature policy is resolved and,
+    /// with a broker, it subscribes to the updates of other server instances; a
+    /// document forwarded to its owner follows the owner's updates instead.
+    /// An archived document is moved back to the repository first.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// A guard holding the document's write lock
+    async fn open_document(
+        &self,
+        doc_id: &str,
+    ) -> OwnedRwLockWriteGuard<SingleDocumentServiceImpl> {
+        let (document, mut state, created) = loop {
+            if let Some(archive) = &self.archive {
+                archive.touch(doc_id);
+                if archive.is_archived(doc_id) {
+                    self.rehydrate_document(archive, doc_id).await;
+                }
+            }
+
+            // Looking the document up first is only worth it when someone listens
+            let created =
+                self.events.receiver_count() > 0 && !self.document_repository.exists(doc_id);
+            let document = self.document_repository.get_or_create(doc_id);
+            let state = document.clone().write_owned().await;
+
+            // Archived while waiting for its lock
+            if !state.is_retired() {
+                break (document, state, created);
+            }
+        };
+        let mut restored = false;
+
+        // Documents are opened for the first time until their policy is resolved
+        if state.policy().is_none() {
+            // Yjs reads the option when a document is created, so set it while it is empty
+            let gc = self.document_gc_options(doc_id);
+            if let Err(e) = state.set_gc_options(gc).await {
+                warn!(
+                    "Failed to set the garbage collection of '{}': {}",
+                    doc_id, e
+                );
+            }
+            if let Some(store) = &self.store {
+                restored = Self::restore_document(store.as_ref(), doc_id, &state).await;
+            }
+            state.set_policy(self.policies.resolve(doc_id));
+            state.set_broadcast_coalescing(self.broadcast_coalescing);
+            // Replicas of forwarded documents validate too, so rejected updates never reach
+            // their owner
+            if let Some(validator) = &self.validator {
+                state.set_validator(doc_id, validator.clone());
+            }
+            if let Some(pipeline) = &self.pipeline {
+                state.set_pipeline(doc_id, pipeline.clone());
+            }
+
+            let forwarded = match (&self.forwarder, self.forwarding_owner(doc_id)) {
+                (Some(forwarder), Some(owner)) => match forwarder.forward(doc_id, &owner) {
+                    Ok(relay) => {
+                        debug!(
+                            "Forwarding document '{}' to cluster node '{}'",
+                            doc_id, owner.node_id
+                        );
+                        state.set_owner(relay.to_owner);
+                        tokio::spawn(SingleDocumentServiceImpl::follow_owner(
+                            doc_id.to_string(),
+                            document.clone(),
+                            relay.from_owner,
+                        ));
+                        true
+                    }
+                    Err(e) => {
+                        warn!("Document '{}' is not forwarded to its owner: {}", doc_id, e);
+                        false
+                    }
+                },
+                _ => false,
+            };
+
+            // The owner of a forwarded document logs, exports and shares its updates
+            if let (Some(write_ahead_log), false) = (&self.write_ahead_log, forwarded) {
+                state.set_write_ahead_log(doc_id, write_ahead_log.clone());
+            }
+            if let (Some(exporter), false) = (&self.exporter, forwarded) {
+                state.set_exporter(doc_id, exporter.clone());
+            }
+
+            if let (Some(broker), false) = (&self.broker, forwarded) {
+                match broker.subscribe(doc_id) {
+                    Ok(remote_updates) => {
+                        state.set_broker(doc_id, broker.clone());
+                        tokio::spawn(SingleDocumentServiceImpl::apply_remote_updates(
+                            doc_id.to_string(),
+                            document,
+                            remote_updates,
+                        ));
+                    }
+                    Err(e) => warn!(
+                        "Document '{}' is not shared with other instances: {}",
+                        doc_id, e
+                    ),
+                }
+            }
+        }
+
+        if created && !restored {
+            self.publish_event(DocumentEvent::Created {
+                doc_id: doc_id.to_string(),
+            });
+        }
+        state
+    }
+
+    /// Opens a document, creating it if needed, and locks it for reading.
+    ///
+    /// Readers of a document opened before share its lock, so they neither
+    /// wait for one another nor for the document's actor when it only reads.
+    /// A document opened for the first time, or archived, is opened for
+    /// writing first.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// A guard holding one of the document's read locks
+    async fn read_document(&self, doc_id: &str) -> OwnedRwLockReadGuard<SingleDocumentServiceImpl> {
+        if let Some(archive) = &self.archive {
+            archive.touch(doc_id);
+        }
+        if let Some(document) = self.document_repository.get_document(doc_id) {
+            let state = document.read_owned().await;
+            if state.policy().is_some() && !state.is_retired() {
+                return state;
+            }
+        }
+        self.open_document(doc_id).await.downgrade()
+    }
+
+    /// Returns the actor of a document, opening the document and spawning its actor if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// A handle of the document's actor
+    async fn document_actor(&self, doc_id: &str) -> DocumentActor {
+        if let Some(archive) = &self.archive {
+            archive.touch(doc_id);
+        }
+        let running = |actors: &HashMap<String, DocumentActor>| {
+            actors
+                .get(doc_id)
+                .filter(|actor| !actor.is_closed())
+                .cloned()
+        };
+        if let Some(actor) = running(&self.actors.lock().unwrap_or_else(|e| e.into_inner())) {
+            return actor;
+        }
+
+        loop {
+            // Resolves the document's policy and broker subscription on its first opening
+            drop(self.open_document(doc_id).await);
+            // Unloaded again since it was opened
+            let Some(document) = self.document_repository.get_document(doc_id) else {
+                continue;
+            };
+
+            let mut actors = self.actors.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(actor) = running(&actors) {
+                return actor;
+            }
+            let actor = DocumentActor::spawn(&document, self.update_limits);
+            actors.insert(doc_id.to_string(), actor.clone());
+            return actor;
+        }
+    }
+
+    /// Sends a command to a document's actor, until an actor handles it.
+    ///
+    /// An actor stops without handling its pending commands once its document
+    /// is unloaded or deleted; the command is then sent again to the actor of
+    /// the reopened document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `command` - Sends the command to an actor, returning `None` if the actor stopped
+    ///
+    /// # Returns
+    ///
+    /// The reply of the actor that handled the command
+    async fn with_actor<T, F, Fut>(&self, doc_id: &str, command: F) -> T
+    where
+        F: Fn(DocumentActor) -> Fut,
+        Fut: Future<Output = Option<T>>,
+    {
+        loop {
+            if let Some(reply) = command(self.document_actor(doc_id).await).await {
+                return reply;
+            }
+            self.forget_actor(doc_id);
+        }
+    }
+
+    /// Drops the service's handle of a document's actor, which stops once every
+    /// pending command is handled.
+    fn forget_actor(&self, doc_id: &str) {
+        self.actors
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(doc_id);
+    }
+
+    /// Restores a document opened for the first time from the state saved to the store.
+    ///
+    /// A state that cannot be loaded or applied is logged and the document is
+    /// served as it is, so a store outage does not make documents unavailable.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The document store
+    /// * `doc_id` - Identifier of the document
+    /// * `state` - The locked document
+    ///
+    /// # Returns
+    ///
+    /// `true` if a saved state was applied
+    async fn restore_document(
+        store: &dyn DocumentStore,
+        doc_id: &str,
+        state: &SingleDocumentServiceImpl,
+    ) -> bool {
+        let saved = match store.load(doc_id).await {
+            Ok(Some(saved)) => saved,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!("Failed to load document '{}' from the store: {}", doc_id, e);
+                return false;
+            }
+        };
+
+        match state.restore(&saved).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(
+                    "Failed to restore document '{}' from the store: {}",
+                    doc_id, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Moves an archived document back to the repository.
+    ///
+    /// The archived state is applied as an update, so durable repositories
+    /// persist it, before it is removed from the archive. A state that cannot be
+    /// loaded is logged and the document stays archived, to be retried when it is
+    /// opened again; it is served empty in the meantime.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive tier
+    /// * `doc_id` - Identifier of the document
+    async fn rehydrate_document(&self, archive: &ArchiveTier, doc_id: &str) {
+        let _moving = archive.lock_moves().await;
+        // Rehydrated while waiting for the other moves
+        if !archive.is_archived(doc_id) {
+            return;
+        }
+
+        let saved = match archive.store().load(doc_id).await {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!(
+                    "Failed to load document '{}' from the archive: {}",
+                    doc_id, e
+                );
+                return;
+            }
+        };
+        if let Some(saved) = saved {
+            let document = self.document_repository.get_or_create(doc_id);
+            let state = document.write().await;
+            if let Err(e) = state
+                .apply_update_from(&saved, TransactionOrigin::server(REHYDRATE_UPDATE_SOURCE))
+                .await
+            {
+                warn!("Failed to rehydrate document '{}': {}", doc_id, e);
+                return;
+            }
+        }
+
+        if self.metadata.is_some() {
+            if let Err(e) = self.update_metadata(doc_id, |metadata| metadata.archived_at = None) {
+                warn!("Failed to record that '{}' is not archived: {}", doc_id, e);
+            }
+        }
+        archive.set_archived(doc_id, false);
+        if let Err(e) = archive.store().delete(doc_id).await {
+            warn!(
+                "Failed to remove document '{}' from the archive: {}",
+                doc_id, e
+            );
+        }
+    }
+
+    /// Writes the updates the repository buffers to durable storage.
+    ///
+    /// Failures are logged; the updates that could not be written stay buffered
+    /// for the next flush.
+    ///
+    /// # Returns
+    ///
+    /// The number of documents whose updates were written
+    pub fn flush_documents(&self) -> usize {
+        self.document_repository.flush().unwrap_or_else(|e| {
+            warn!("Failed to flush buffered updates: {}", e);
+            0
+        })
+    }
+
+    /// Applies the updates a previous run left in the write-ahead log.
+    ///
+    /// Called at startup, before clients connect. Replayed updates are applied
+    /// like any other update, so the storage backend records them, and a
+    /// checkpoint then truncates the replayed segments. Updates that cannot be
+    /// applied are logged and skipped. Without a write-ahead log, nothing is
+    /// replayed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(usize)` - The number of updates applied
+    /// * `Err(DomainError)` - `StorageFailure` if the log could not be read or checkpointed
+    pub async fn recover_from_write_ahead_log(&self) -> DomainResult<usize> {
+        let Some(write_ahead_log) = &self.write_ahead_log else {
+            return Ok(0);
+        };
+
+        let mut recovered = 0;
+        for (doc_id, update) in write_ahead_log.replay()? {
+            let state = self.open_document(&doc_id).await;
+            match state.apply_update(&update).await {
+                Ok(()) => {
+                    recovered += 1;
+                    self.mark_unsaved(&doc_id);
+                }
+                Err(e) => warn!("Failed to replay an update of '{}': {}", doc_id, e),
+            }
+        }
+
+        self.checkpoint_write_ahead_log().await?;
+        Ok(recovered)
+    }
+
+    /// Truncates the write-ahead log once the storage backend holds its updates.
+    ///
+    /// The current segment is sealed first, then the repository is flushed and
+    /// the documents updated since they were last saved are saved to the store.
+    /// When neither keeps documents across restarts, the state of every resident
+    /// document is logged to the new segment instead. Without a write-ahead log,
+    /// nothing is done.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the sealed segments were truncated
+    /// * `Err(DomainError)` - The error raised flushing, saving or logging, which leaves the sealed
+    ///   segments in place for the next checkpoint
+    pub async fn checkpoint_write_ahead_log(&self) -> DomainResult<()> {
+        let Some(write_ahead_log) = &self.write_ahead_log else {
+            return Ok(());
+        };
+
+        let checkpoint = write_ahead_log.rotate()?;
+        self.document_repository.flush()?;
+        self.save_documents().await?;
+
+        if !self.document_repository.is_durable() && self.store.is_none() {
+            for doc_id in self.document_repository.resident_documents() {
+                let Some(document) = self.document_repository.get_document(&doc_id) else {
+                    continue;
+                };
+                let state = document.read().await;
+                if state.is_retired() {
+                    continue;
+                }
+                write_ahead_log.append(&doc_id, &state.snapshot().await?)?;
+            }
+        }
+
+        write_ahead_log.truncate(checkpoint)
+    }
+
+    /// Moves the documents left untouched long enough to the archive tier.
+    ///
+    /// Documents with subscribers, such as connected clients, are never archived.
+    /// Without an archive tier, nothing is archived.
+    ///
+    /// # Returns
+    ///
+    /// The number of documents archived
+    pub async fn archive_idle_documents(&self) -> usize {
+        let Some(archive) = &self.archive else {
+            return 0;
+        };
+
+        let mut archived = 0;
+        for doc_id in self.document_repository.list_documents() {
+            if archive.is_archived(&doc_id) || !archive.is_idle(&doc_id) {
+                continue;
+            }
+            match self.archive_document(archive, &doc_id).await {
+                Ok(true) => archived += 1,
+                Ok(false) => {}
+                Err(e) => warn!("Failed to archive document '{}': {}", doc_id, e),
+            }
+        }
+        archived
+    }
+
+    /// Moves a document from the repository to the archive tier.
+    ///
+    /// The state is written to the archive before the document is removed from
+    /// the repository, so a failure leaves it in the repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The archive tier
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(bool)` - Whether the document was archived; documents opened since they were found
+    ///   idle, or with subscribers, are not
+    /// * `Err(DomainError)` - If the document could not be moved
+    async fn archive_document(&self, archive: &ArchiveTier, doc_id: &str) -> DomainResult<bool> {
+        let _moving = archive.lock_moves().await;
+        let Some(document) = self.document_repository.get_document(doc_id) else {
+            return Ok(false);
+        };
+        let mut state = document.write_owned().await;
+        if !archive.is_idle(doc_id) || state.subscriber_count() > 0 || state.is_retired() {
+            return Ok(false);
+        }
+
+        archive
+            .store()
+            .save(doc_id, &state.snapshot().await?)
+            .await?;
+        // Durable repositories delete the metadata along with the document
+        let saved = match &self.metadata {
+            Some(metadata) => metadata.get(doc_id)?,
+            None => DocumentMetadata::default(),
+        };
+        self.document_repository.delete_document(doc_id)?;
+        state.retire();
+        drop(state);
+        self.forget_actor(doc_id);
+
+        archive.set_archived(doc_id, true);
+        if self.metadata.is_some() {
+            let archived_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64;
+            self.update_metadata(doc_id, |metadata| {
+                *metadata = saved;
+                metadata.archived_at = Some(archived_at);
+            })?;
+        }
+        Ok(true)
+    }
+
+    /// Subscribes to a document's updates without synchronizing with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// A broadcast receiver for future document updates
+    pub async fn subscribe(&self, doc_id: &str) -> broadcast::Receiver<UpdateNotification> {
+        self.subscription(doc_id).await.receiver
+    }
+
+    /// Returns the sequence number of the last update broadcast for a document.
+    ///
+    /// Every update broadcast to a document's subscribers is numbered, so clients
+    /// can detect missed updates and ask to resynchronize. Numbering starts over
+    /// when the document is opened again after being unloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// The sequence number, `0` before the first update
+    pub async fn sequence_number(&self, doc_id: &str) -> u64 {
+        self.read_document(doc_id).await.sequence_number()
+    }
+
+    /// Subscribes to a document's updates, along with the sequence number they follow.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing:
+    /// * The sequence number of the last update broadcast before the subscription
+    /// * A broadcast receiver for future document updates
+    pub async fn subscribe_with_sequence(
+        &self,
+        doc_id: &str,
+    ) -> (u64, broadcast::Receiver<UpdateNotification>) {
+        let subscription = self.subscription(doc_id).await;
+        (subscription.sequence_number, subscription.receiver)
+    }
+
+    /// Subscribes to a document's updates through its actor.
+    async fn subscription(&self, doc_id: &str) -> Subscription {
+        self.with_actor(doc_id, |actor| async move { actor.subscribe().await })
+            .await
+    }
+
+    /// Handles a sync request from a client.
+    ///
+    /// This method processes client synchronization requests and returns the missing
+    /// updates based on the client's state vector, along with a receiver for future updates.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to synchronize with
+    /// * `client_state_vector` - The client's current state vector (optional)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((SyncResponse, receiver))` - The updates the client needs with the current state
+    ///   vector, and a broadcast receiver for future document updates
+    /// * `Err(DomainError)` - An error message if the document couldn't be encoded
+    pub async fn handle_sync_request(
+        &self,
+        doc_id: &str,
+        client_state_vector: Option<&[u8]>,
+    ) -> DomainResult<(SyncResponse, broadcast::Receiver<UpdateNotification>)> {
+        // Get the missing updates based on client's state vector
+        let (update_data, state_vector, sequence_number, receiver) =
+            self.compute_sync(doc_id, client_state_vector).await?;
+
+        let response = SyncResponse {
+            update: if update_data.is_empty() {
+                None
+            } else {
+                Some(update_data)
+            },
+            state_vector: Some(state_vector),
+            sequence_number,
+        };
+
+        Ok((response, receiver))
+    }
+
+    /// Handles a sync request from a client, splitting an oversized diff into chunks.
+    ///
+    /// Diffs above the chunk threshold of the service's `DiffThrottle` are
+    /// split into several self-contained updates. The first is returned in the
+    /// sync response and the others must be delivered after it, with flow
+    /// control, as regular updates. Without a client state vector, or when it
+    /// cannot be decoded, the full document state is chunked.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to synchronize with
+    /// * `client_state_vector` - The client's current state vector (optional)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((SyncResponse, chunks, receiver))` - The first chunk with the current state vector,
+    ///   the remaining chunks, empty unless the diff was oversized, and a broadcast receiver for
+    ///   future document updates
+    /// * `Err(DomainError)` - An error message if the document couldn't be encoded
+    pub async fn handle_chunked_sync_request(
+        &self,
+        doc_id: &str,
+        client_state_vector: Option<&[u8]>,
+    ) -> DomainResult<(
+        SyncResponse,
+        Vec<Vec<u8>>,
+        broadcast::Receiver<UpdateNotification>,
+    )> {
+        let diff = self
+            .with_actor(doc_id, |actor| async move {
+                let state_vector = client_state_vector.map(<[u8]>::to_vec);
+                actor
+                    .diff(state_vector, Some(self.diff_throttle.clone()))
+                    .await
+            })
+            .await?;
+        let mut chunks = diff.updates;
+        if chunks.len() > 1 {
+            warn!(
+                "Oversized diff requested for document '{}', delivering {} chunks",
+                doc_id,
+                chunks.len()
+            );
+        }
+
+        let first = if chunks.is_empty() {
+            Vec::new()
+        } else {
+            chunks.remove(0)
+        };
+        let response = SyncResponse {
+            update: if first.is_empty() { None } else { Some(first) },
+            state_vector: Some(diff.state_vector),
+            sequence_number: diff.sequence_number,
+        };
+
+        Ok((response, chunks, diff.receiver))
+    }
+
+    /// Handles an update request from a client.
+    ///
+    /// This method processes document updates sent by clients in Base64 format.
+    /// The update is broadcast to subscribers tagged with the client's identifier,
+    /// so the sender can skip its own update.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to update
+    /// * `origin` - Who sent the update, and through which transport
+    /// * `update_base64` - The Base64-encoded update data
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was successfully applied
+    /// * `Err(DomainError)` - An error message if the update couldn't be applied
+    pub async fn handle_update_request(
+        &self,
+        doc_id: &str,
+        origin: UpdateOrigin<'_>,
+        update_base64: &str,
+    ) -> DomainResult<()> {
+        // Decode Base64 update data
+        let update_data = base64::engine::general_purpose::STANDARD
+            .decode(update_base64)
+            .map_err(|e| {
+                DomainError::InvalidArgument(format!("Failed to decode Base64 update: {}", e))
+            })?;
+
+        self.apply_client_update(doc_id, &update_data, origin).await
+    }
+
+    /// Handles a synchronization step with a state vector from a client.
+    ///
+    /// This method processes client state vectors and returns the necessary updates
+    /// to bring the client up to date.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to synchronize
+    /// * `state_vector_base64` - The Base64-encoded client state vector
+    ///
+    /// # Returns
+    ///
+    /// A result containing:
+    /// * `Ok((SyncResponse, receiver))` - Response with updates and receiver for future updates
+    /// * `Err(DomainError)` - An error message if synchronization couldn't be processed
+    pub async fn handle_sync_step(
+        &self,
+        doc_id: &str,
+        state_vector_base64: &str,
+    ) -> DomainResult<(SyncResponse, broadcast::Receiver<UpdateNotification>)> {
+        // Decode Base64 state vector
+        let state_vector = base64::engine::general_purpose::STANDARD
+            .decode(state_vector_base64)
+            .map_err(|e| {
+                DomainError::InvalidArgument(format!("Failed to decode Base64 state vector: {}", e))
+            })?;
+
+        // Sync with the provided state vector
+        let (update, server_state_vector, sequence_number, receiver) =
+            self.compute_sync(doc_id, Some(&state_vector)).await?;
+
+        let response = SyncResponse {
+            update: if update.is_empty() {
+                None
+            } else {
+                Some(update)
+            },
+            state_vector: Some(server_state_vector),
+            sequence_number,
+        };
+
+        Ok((response, receiver))
+    }
+
+    /// Handles a binary update from a client.
+    ///
+    /// This method processes binary document updates directly without Base64 encoding.
+    /// The update is broadcast to subscribers tagged with the client's identifier,
+    /// so the sender can skip its own update.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to update
+    /// * `origin` - Who sent the update, and through which transport
+    /// * `update_data` - The binary update data
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was successfully applied
+    /// * `Err(DomainError)` - An error message if the update couldn't be applied
+    pub async fn handle_binary_update(
+        &self,
+        doc_id: &str,
+        origin: UpdateOrigin<'_>,
+        update_data: &[u8],
+    ) -> DomainResult<()> {
+        self.apply_client_update(doc_id, update_data, origin).await
+    }
+
+    /// Applies a batch of updates from a client as a single update.
+    ///
+    /// Clients catching up after a long offline session send many small updates;
+    /// a batch is merged into one update, applied in a single transaction under
+    /// one lock of the document, then logged and broadcast once, tagged with the
+    /// client's identifier. The merged update counts as one update against the
+    /// size limits, and the batch is applied entirely or not at all.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to update
+    /// * `origin` - Who sent the updates, and through which transport
+    /// * `updates` - The binary updates, in the order the client made them
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the updates were applied, or the batch is empty
+    /// * `Err(DomainError)` - `InvalidUpdate` if an update cannot be decoded, or the error raised
+    ///   applying the merged update
+    pub async fn apply_updates_batch(
+        &self,
+        doc_id: &str,
+        origin: UpdateOrigin<'_>,
+        updates: Vec<Vec<u8>>,
+    ) -> DomainResult<()> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+        let merged = CollaborativeDocument::merge_updates(&updates)?;
+
+        self.apply_client_update(doc_id, &merged, origin).await
+    }
+
+    /// Handles a message of the binary Yjs sync protocol from a client.
+    ///
+    /// A `SyncStep1` is answered with a `SyncStep2` containing the updates the
+    /// client is missing; an oversized diff is split into chunks, the first sent
+    /// as the `SyncStep2` and the others as `Update` messages. `SyncStep2` and
+    /// `Update` messages are applied to the
+    /// document and broadcast to subscribers tagged with the client's identifier,
+    /// so the sender can skip its own update.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document the connection is bound to
+    /// * `origin` - Who sent the message, and through which transport
+    /// * `message` - The decoded sync protocol message
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SyncProtocolMessage>)` - Replies to send back to the client in order, empty if no
+    ///   reply is needed
+    /// * `Err(DomainError)` - An error message if the update couldn't be applied, or the diff
+    ///   couldn't be encoded
+    pub async fn handle_sync_protocol_message(
+        &self,
+        doc_id: &str,
+        origin: UpdateOrigin<'_>,
+        message: SyncProtocolMessage,
+    ) -> DomainResult<Vec<SyncProtocolMessage>> {
+        match message {
+            SyncProtocolMessage::SyncStep1(state_vector) => {
+                let (response, chunks, _) = self
+                    .handle_chunked_sync_request(doc_id, Some(&state_vector))
+                    .await?;
+
+                let step2 = SyncProtocolMessage::SyncStep2(response.update.unwrap_or_default());
+                Ok(std::iter::once(step2)
+                    .chain(chunks.into_iter().map(SyncProtocolMessage::Update))
+                    .collect())
+            }
+            SyncProtocolMessage::SyncStep2(update) | SyncProtocolMessage::Update(update) => {
+                self.apply_client_update(doc_id, &update, origin).await?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Establishes a synchronization session for a document.
+    ///
+    /// This is the core business logic for initiating collaboration on a document.
+    /// It ensures the document exists and sets up the necessary channels for
+    /// real-time collaboration.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to synchronize with
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing:
+    /// * The document's current state vector as binary data
+    /// * A broadcast receiver for future document updates
+    pub async fn establish_sync_session(
+        &self,
+        doc_id: &str,
+    ) -> (Vec<u8>, broadcast::Receiver<UpdateNotification>) {
+        // Get document state and subscribe to updates
+        let subscription = self.subscription(doc_id).await;
+        (subscription.state_vector, subscription.receiver)
+    }
+
+    /// Applies a document update using the collaborative editing protocol.
+    ///
+    /// This method encapsulates the business rules for applying updates to
+    /// collaborative documents, ensuring data consistency and proper
+    /// synchronization across all clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to update
+    /// * `update_data` - The binary update data to apply
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update was successfully applied
+    /// * `Err(DomainError)` - `DocumentFrozen` if the document is frozen, `PayloadTooLarge` if the
+    ///   update or the updated document exceeds the server's size limits, or an error message if
+    ///   the update couldn't be applied
+    pub async fn apply_document_update(
+        &self,
+        doc_id: &str,
+        update_data: &[u8],
+    ) -> DomainResult<()> {
+        self.check_writable(doc_id)?;
+        // Use repository abstraction for document access
+        let state = self.open_document(doc_id).await;
+        self.check_update_size(doc_id, &state, update_data)?;
+        state.apply_update(update_data).await
+    }
+
+    /// Computes missing updates for client synchronization.
+    ///
+    /// This implements the core synchronization algorithm that determines
+    /// what updates a client needs based on their current state vector.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to synchronize
+    /// * `client_state_vector` - The client's current state vector
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((Vec<u8>, receiver))` - The binary update data the client needs, and a broadcast
+    ///   receiver for future document updates
+    /// * `Err(DomainError)` - An error message if the document couldn't be encoded
+    pub async fn sync_document(
+        &self,
+        doc_id: &str,
+        client_state_vector: Option<&[u8]>,
+    ) -> DomainResult<(Vec<u8>, broadcast::Receiver<UpdateNotification>)> {
+        let (update, _, _, receiver) = self.compute_sync(doc_id, client_state_vector).await?;
+        Ok((update, receiver))
+    }
+
+    /// Computes the sync payload for a client together with the server's state vector.
+    ///
+    /// Without a client state vector, or when it cannot be decoded, the full document
+    /// state is returned. Applying the full state is always safe for a CRDT client.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to synchronize
+    /// * `client_state_vector` - The client's current state vector
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((update, state_vector, sequence_number, receiver))` - The binary update data the
+    ///   client needs, the document's current state vector, the sequence number of the last update
+    ///   included in the payload, and a broadcast receiver for future document updates
+    /// * `Err(DomainError)` - An error message if the document couldn't be encoded
+    async fn compute_sync(
+        &self,
+        doc_id: &str,
+        client_state_vector: Option<&[u8]>,
+    ) -> DomainResult<(
+        Vec<u8>,
+        Vec<u8>,
+        u64,
+        broadcast::Receiver<UpdateNotification>,
+    )> {
+        // Generate update based on client's state vector
+        let diff = self
+            .with_actor(doc_id, |actor| async move {
+                let state_vector = client_state_vector.map(<[u8]>::to_vec);
+                actor.diff(state_vector, None).await
+            })
+            .await?;
+
+        let update = diff.updates.into_iter().next().unwrap_or_default();
+        Ok((
+            update,
+            diff.state_vector,
+            diff.sequence_number,
+            diff.receiver,
+        ))
+    }
+
+    /// Lists the documents of the repository, loaded or persisted, of the store and
+    /// of the archive tier.
+    ///
+    /// # Returns
+    ///
+    /// The identifiers of every document, sorted by the collation if any
+    pub async fn list_documents(&self) -> Vec<String> {
+        let mut doc_ids = self.document_repository.list_documents();
+        if let Some(archive) = &self.archive {
+            doc_ids.extend(archive.archived());
+        }
+        if let Some(store) = &self.store {
+            match store.list().await {
+                Ok(saved) => doc_ids.extend(saved),
+                Err(e) => warn!("Failed to list the documents of the store: {}", e),
+            }
+        }
+        doc_ids.sort();
+        doc_ids.dedup();
+        self.collate(&mut doc_ids);
+        doc_ids
+    }
+
+    /// Creates an empty document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document to create
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document was created
+    /// * `Err(DomainError)` - If the document already exists, its tenant has as many documents as
+    ///   allowed, or it could not be stored
+    pub async fn create_document(&self, doc_id: &str) -> DomainResult<()> {
+        if self.is_archived(doc_id) || self.is_stored(doc_id).await? {
+            return Err(DomainError::Conflict(doc_id.to_string()));
+        }
+        self.check_tenant_quota(doc_id)?;
+        self.document_repository.create_document(doc_id)?;
+        self.mark_unsaved(doc_id);
+        self.record_modified(doc_id);
+
+        // Resolves the document's policy and broker subscription right away
+        self.open_document(doc_id).await;
+        self.publish_event(DocumentEvent::Created {
+            doc_id: doc_id.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Deletes a document along with its stored updates and metadata.
+    ///
+    /// Connections open on the document are not closed, and may recreate it
+    /// with their next update; clients should leave a document before it is deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document to delete
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document was deleted
+    /// * `Err(DomainError)` - If the document does not exist or could not be removed
+    pub async fn delete_document(&self, doc_id: &str) -> DomainResult<()> {
+        let stored = self.is_stored(doc_id).await? || self.is_archived(doc_id);
+        match self.document_repository.delete_document(doc_id) {
+            Err(DomainError::NotFound(_)) if stored => {}
+            result => result?,
+        }
+        self.forget_actor(doc_id);
+        if let Some(archive) = &self.archive {
+            let _moving = archive.lock_moves().await;
+            archive.store().delete(doc_id).await?;
+            archive.set_archived(doc_id, false);
+        }
+        if let Some(store) = &self.store {
+            store.delete(doc_id).await?;
+            self.unsaved
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(doc_id);
+        }
+        self.activity.forget(doc_id);
+        self.mark_unindexed(doc_id);
+        self.modified
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(doc_id);
+        self.frozen
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(doc_id);
+        self.gc_overrides
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(doc_id);
+        if let Some(dictionaries) = &self.payload_dictionaries {
+            dictionaries.forget(doc_id);
+        }
+        if let Some(versions) = &self.versions {
+            versions.clear(doc_id)?;
+            self.unversioned
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(doc_id);
+        }
+        if let Some(audit) = &self.audit {
+            audit.clear(doc_id)?;
+        }
+        self.publish_event(DocumentEvent::Deleted {
+            doc_id: doc_id.to_string(),
+        });
+
+        if let Some(metadata) = &self.metadata {
+            metadata.put(doc_id, &DocumentMetadata::default())?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the state of an existing document as a single update.
+    ///
+    /// Without a state vector the whole document is encoded; with one, only the
+    /// changes missing from that state vector are.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `state_vector_base64` - Optional Base64-encoded state vector of the caller
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SyncResponse)` - The update and the document's current state vector
+    /// * `Err(DomainError)` - If the document does not exist or the state vector is invalid
+    pub async fn get_document_state(
+        &self,
+        doc_id: &str,
+        state_vector_base64: Option<&str>,
+    ) -> DomainResult<SyncResponse> {
+        let state_vector = match state_vector_base64 {
+            Some(encoded) => base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| {
+                    DomainError::InvalidArgument(format!(
+                        "Failed to decode Base64 state vector: {}",
+                        e
+                    ))
+                })?,
+            None => EMPTY_STATE_VECTOR.to_vec(),
+        };
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        let state = self.read_document(doc_id).await;
+        state.flush_broadcast();
+        let update = state.diff_update(&state_vector).await?;
+
+        Ok(SyncResponse {
+            update: Some(update),
+            state_vector: Some(state.get_state_vector().await),
+            sequence_number: state.sequence_number(),
+        })
+    }
+
+    /// Checks whether a document exists, loaded, persisted, saved to the store or archived.
+    ///
+    /// A store that cannot be read is logged and taken as not holding the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    pub async fn document_exists(&self, doc_id: &str) -> bool {
+        if self.document_exists_locally(doc_id) {
+            return true;
+        }
+        self.is_stored(doc_id).await.unwrap_or_else(|e| {
+            warn!(
+                "Failed to look document '{}' up in the store: {}",
+                doc_id, e
+            );
+            false
+        })
+    }
+
+    /// Checks whether a document is in the repository or archived.
+    fn document_exists_locally(&self, doc_id: &str) -> bool {
+        self.document_repository.exists(doc_id) || self.is_archived(doc_id)
+    }
+
+    /// Checks whether a document is archived, if archiving is enabled.
+    fn is_archived(&self, doc_id: &str) -> bool {
+        self.archive
+            .as_ref()
+            .is_some_and(|archive| archive.is_archived(doc_id))
+    }
+
+    /// Checks whether the state of a document is saved to the store, if any.
+    async fn is_stored(&self, doc_id: &str) -> DomainResult<bool> {
+        match &self.store {
+            Some(store) => store.contains(doc_id).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Exports a document as a single update.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `mode` - Whether to export the full CRDT state or a clean copy of the content
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The binary-encoded update
+    /// * `Err(DomainError)` - If the document does not exist or could not be encoded
+    pub async fn export_document(&self, doc_id: &str, mode: ExportMode) -> DomainResult<Vec<u8>> {
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        let state = self.read_document(doc_id).await;
+        state.export(mode).await
+    }
+
+    /// Exports the content of a document in a readable format.
+    ///
+    /// Unlike `export_document`, which exports the CRDT state, the content is
+    /// serialized by the `DocumentExporter` for people and other tools to read,
+    /// without the edit history; a JSON export can only seed a new document
+    /// through `import_content`.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `format` - The format to export the content to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The exported content
+    /// * `Err(DomainError)` - If the document does not exist or could not be serialized
+    pub async fn export_content(&self, doc_id: &str, format: ExportFormat) -> DomainResult<String> {
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        let state = self.read_document(doc_id).await;
+        state.export_content(format).await
+    }
+
+    /// Creates a document from an exported update.
+    ///
+    /// The update is decoded before the document is created, so an invalid
+    /// update leaves no empty document behind. In clean mode the update's
+    /// content is re-materialized first, which strips the history of a full
+    /// export.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document to create
+    /// * `update` - The binary-encoded update
+    /// * `mode` - Whether to import the update as is or as a clean copy of its content
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document was created
+    /// * `Err(DomainError)` - If the document already exists or the update is invalid
+    pub async fn import_document(
+        &self,
+        doc_id: &str,
+        update: &[u8],
+        mode: ExportMode,
+    ) -> DomainResult<()> {
+        if self.document_exists_locally(doc_id) || self.is_stored(doc_id).await? {
+            return Err(DomainError::Conflict(doc_id.to_string()));
+        }
+
+        let update = update.to_vec();
+        let update = tokio::task::spawn_blocking(move || {
+            let mut document = CollaborativeDocument::new();
+            document.apply_update(&update)?;
+            Ok::<_, DomainError>(match mode {
+                ExportMode::Full => update,
+                ExportMode::Clean => document.encode_clean_state(),
+            })
+        })
+        .await
+        .map_err(|e| DomainError::Internal(format!("Failed to decode imported update: {}", e)))??;
+
+        self.seed_document(doc_id, &update).await?;
+        Ok(())
+    }
+
+    /// Creates a document from plain text or JSON content.
+    ///
+    /// The content is turned into the matching shared types by the
+    /// `DocumentImporter` before the document is created, so malformed content
+    /// leaves no empty document behind. The document's feature policy applies
+    /// to the imported content like to any update.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document to create
+    /// * `content` - The content to import
+    /// * `format` - The format of the content
+    /// * `text_root` - Name of the root seeded from plain text
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The binary-encoded state vector of the created document
+    /// * `Err(DomainError)` - If the document already exists, the content is malformed or exceeds
+    ///   the document's limits
+    pub async fn import_content(
+        &self,
+        doc_id: &str,
+        content: String,
+        format: ImportFormat,
+        text_root: String,
+    ) -> DomainResult<Vec<u8>> {
+        if self.document_exists_locally(doc_id) || self.is_stored(doc_id).await? {
+            return Err(DomainError::Conflict(doc_id.to_string()));
+        }
+
+        let update = tokio::task::spawn_blocking(move || {
+            DocumentImporter::import(&content, format, &text_root)
+        })
+        .await
+        .map_err(|e| DomainError::Internal(format!("Failed to build imported content: {}", e)))??;
+
+        let state = self.seed_document(doc_id, &update).await?;
+        Ok(state.get_state_vector().await)
+    }
+
+    /// Applies the update seeding an imported document.
+    async fn seed_document(
+        &self,
+        doc_id: &str,
+        update: &[u8],
+    ) -> DomainResult<OwnedRwLockWriteGuard<SingleDocumentServiceImpl>> {
+        let state = self.open_document(doc_id).await;
+        state
+            .apply_update_from(update, TransactionOrigin::server(IMPORT_UPDATE_SOURCE))
+            .await?;
+        self.mark_unsaved(doc_id);
+        self.mark_unindexed(doc_id);
+        self.record_modified(doc_id);
+        self.record_audit(
+            doc_id,
+            UpdateOrigin::server(IMPORT_UPDATE_SOURCE),
+            update.len(),
+            state.sequence_number(),
+        );
+        Ok(state)
+    }
+
+    /// Checks the dependencies the service needs to serve documents.
+    ///
+    /// The storage backend is always checked; the document store, the archive
+    /// the broker and the cluster membership are checked when configured.
+    /// Readiness probes report the server as ready only while every dependency
+    /// is healthy.
+    ///
+    /// # Returns
+    ///
+    /// The health of each dependency, named `storage`, `store`, `archive`, `broker` and
+    /// `cluster`
+    pub async fn check_dependencies(&self) -> Vec<DependencyHealth> {
+        let mut dependencies = vec![DependencyHealth::of(
+            "storage",
+            self.document_repository.check_health(),
+        )];
+        if let Some(store) = &self.store {
+            dependencies.push(DependencyHealth::of("store", store.check_health().await));
+        }
+        if let Some(archive) = &self.archive {
+            dependencies.push(DependencyHealth::of(
+                "archive",
+                archive.store().check_health().await,
+            ));
+        }
+        if let Some(broker) = &self.broker {
+            dependencies.push(DependencyHealth::of("broker", broker.check_health()));
+        }
+        if let Some(ownership) = &self.ownership {
+            dependencies.push(DependencyHealth::of("cluster", ownership.check_health()));
+        }
+        dependencies
+    }
+
+    /// Gets the number of documents currently loaded in the repository.
+    ///
+    /// This is used as a load signal by transport adapters when deciding whether
+    /// to admit new connections.
+    ///
+    /// # Returns
+    ///
+    /// The number of loaded documents.
+    pub fn loaded_document_count(&self) -> usize {
+        self.document_repository.count()
+    }
+
+    /// Reports the size and subscribers of every document resident in the repository.
+    ///
+    /// # Returns
+    ///
+    /// The statistics of each resident document, sorted by identifier and the collation if any
+    pub async fn resident_documents(&self) -> Vec<ResidentDocumentStats> {
+        let mut doc_ids = self.document_repository.resident_documents();
+        doc_ids.sort();
+        self.collate(&mut doc_ids);
+
+        let mut documents = Vec::with_capacity(doc_ids.len());
+        for doc_id in doc_ids {
+            let Some(document) = self.document_repository.get_document(&doc_id) else {
+                continue;
+            };
+            let state = document.read().await;
+            if state.is_retired() {
+                continue;
+            }
+            documents.push(ResidentDocumentStats {
+                size_bytes: state.size(),
+                subscribers: state.subscriber_count(),
+                sequence_number: state.sequence_number(),
+                doc_id,
+            });
+        }
+        documents
+    }
+
+    /// Gets the complete content of a document.
+    ///
+    /// This method provides access to the document's full content,
+    /// typically for initial loading or full state recovery.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document to retrieve
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The document content
+    /// * `Err(DomainError)` - `NotFound` if the document doesn't exist, or an error if its content
+    ///   couldn't be read
+    pub async fn get_document_content(&self, doc_id: &str) -> DomainResult<String> {
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        let state = self.read_document(doc_id).await;
+        state.get_content().await
+    }
+
+    /// Lists the subdocuments a document references.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the parent document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<String>)` - The GUIDs of the referenced subdocuments, sorted
+    /// * `Err(DomainError)` - `NotFound` if the document doesn't exist, or an error if its content
+    ///   couldn't be read
+    pub async fn list_subdocuments(&self, doc_id: &str) -> DomainResult<Vec<String>> {
+        if !self.document_exists(doc_id).await {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        let state = self.read_document(doc_id).await;
+        state.subdocument_guids().await
+    }
+
+    /// Checks that a subdocument may be synchronized.
+    ///
+    /// A subdocument is loaded lazily, when a client first synchronizes it, and
+    /// only once its parent references it, so clients cannot create subdocuments
+    /// their parent does not know about. Other documents are always accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document about to be synchronized
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document is not a subdocument or its parent references it
+    /// * `Err(DomainError)` - `NotFound` if the parent does not reference the subdocument, or an
+    ///   error if the parent's content couldn't be read
+    pub async fn check_subdocument(&self, doc_id: &str) -> DomainResult<()> {
+        let Some((parent_id, guid)) = split_subdocument_id(doc_id) else {
+            return Ok(());
+        };
+
+        let referenced = match self.list_subdocuments(parent_id).await {
+            Ok(guids) => guids.iter().any(|referenced| referenced == guid),
+            Err(DomainError::NotFound(_)) => false,
+            Err(e) => return Err(e),
+        };
+        if referenced {
+            Ok(())
+        } else {
+            Err(DomainError::NotFound(format!(
+                "Document '{}' does not reference subdocument '{}'",
+                parent_id, guid
+            )))
+        }
+    }
+
+    /// Gets the statistics of a document.
+    ///
+    /// Content counts are maintained as updates are applied, so reading them
+    /// does not go through the document's content; the document's structure is
+    /// measured on the compute pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Some(DocumentStats)` - The document's statistics if the document exists
+    /// * `None` - If the document doesn't exist
+    pub async fn get_document_stats(&self, doc_id: &str) -> Option<DocumentStats> {
+        if !self.document_exists(doc_id).await {
+            return None;
+        }
+
+        let state = self.read_document(doc_id).await;
+        Some(DocumentStats {
+            content: state.content_stats(),
+            size_bytes: state.size(),
+            max_characters: state
+                .policy()
+                .map(|policy| policy.max_document_characters)
+                .filter(|&limit| limit > 0),
+            structure: state.structure_stats().await,
+            gc: state.gc_options(),
+        })
+    }
+}
+
+/// Response to a sync request
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SyncResponse {
+    /// The binary update to apply
+    pub update: Option<Vec<u8>>,
+    /// The current state vector of the document
+    pub state_vector: Option<Vec<u8>>,
+    /// Sequence number of the last update included in the response; the next
+    /// broadcast update carries the following one
+    #[serde(default)]
+    pub sequence_number: u64,
+}
+
+/// Notification of a document update
+#[derive(Clone, Debug)]
+pub struct UpdateNotification {
+    /// The binary update data
+    pub update: Vec<u8>,
+    /// Source of the update: the sending client's identifier, or the source
+    /// tagging an update no client sent, e.g. `SERVER_UPDATE_SOURCE`
+    pub source: String,
+    /// Identity of the user behind the sending client, or `None` for a guest or the server
+    pub user_id: Option<String>,
+    /// Where the update comes from
+    pub origin: OriginKind,
+    /// Position of the update among the updates broadcast for the document,
+    /// starting at 1 once the document is opened
+    pub sequence_number: u64,
+}
+
+/// How the transaction applying an update is tracked
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum UpdateTracking {
+    /// Not tracked
+    None,
+    /// In the undo stack of the origin's client
+    Undo,
+    /// As a revertible update, recorded under the given key
+    Revertible(String),
+}
+
+/// Concrete implementation of a single document service using Yjs CRDT
+pub struct SingleDocumentServiceImpl {
+    /// The collaborative document instance, shared by the operations reading it
+    document: PooledDocument,
+    /// Broadcasts updates to subscribers, coalescing those sent in quick succession
+    broadcaster: Arc<UpdateCoalescer>,
+    /// How the updates broadcast to subscribers are coalesced
+    coalescing: BroadcastCoalescing,
+    /// Compute pool running CPU-heavy CRDT operations off the async workers
+    compute: Arc<ComputePool>,
+    /// Durable log recording applied updates, keyed by the document's identifier
+    update_log: Option<(String, Arc<dyn UpdateLog>)>,
+    /// Feature policy of the document's namespace, resolved when it is first opened
+    policy: Option<Arc<FeaturePolicy>>,
+    /// Approximate encoded size of the document: its restored state plus every
+    /// update applied since, an upper bound as updates may overlap
+    size: AtomicUsize,
+    /// Updates appended to the update log since it was last compacted, for documents without
+    /// history
+    uncompacted: AtomicUsize,
+    /// Characters across the document's text roots, recounted as updates are applied
+    characters: AtomicUsize,
+    /// Words across the document's text roots, recounted as updates are applied
+    words: AtomicUsize,
+    /// Broker publishing applied updates to other instances, keyed by the document's identifier
+    broker: Option<(String, Arc<dyn UpdateBroker>)>,
+    /// Local log recording applied updates until a checkpoint, keyed by the document's identifier
+    write_ahead_log: Option<(String, Arc<dyn WriteAheadLog>)>,
+    /// Relay of the updates applied locally to the document's owner, if it is forwarded
+    owner: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    /// Export of the updates applied on this instance, keyed by the document's identifier
+    exporter: Option<(String, Arc<dyn UpdateExporter>)>,
+    /// Validation of the updates clients apply, keyed by the document's identifier
+    validator: Option<(String, Arc<dyn UpdateValidator>)>,
+    /// Processing of the text clients insert, keyed by the document's identifier
+    pipeline: Option<(String, Arc<UpdatePipeline>)>,
+    /// Whether the document was moved out of the repository, e.g. to the archive tier
+    retired: bool,
+    /// How the content deleted from the document is garbage collected
+    gc: GcOptions,
+}
+
+impl SingleDocumentServiceImpl {
+    /// Creates a new document service instance with its own compute pool
+    pub fn new() -> Self {
+        Self::with_compute_pool(Arc::new(ComputePool::default()))
+    }
+
+    /// Creates a new document service instance running CRDT operations on a shared compute pool
+    pub fn with_compute_pool(compute: Arc<ComputePool>) -> Self {
+        Self::from_document(compute, CollaborativeDocument::new())
+    }
+
+    /// Creates a document service restored from a previously persisted state
+    ///
+    /// # Arguments
+    ///
+    /// * `compute` - The compute pool running CRDT operations
+    /// * `state` - Binary-encoded updates to apply before the document is served
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SingleDocumentServiceImpl)` - The restored document service
+    /// * `Err(DomainError)` - An error message if the state couldn't be applied
+    pub fn from_state(compute: Arc<ComputePool>, state: &[u8]) -> DomainResult<Self> {
+        let mut document = CollaborativeDocument::new();
+        document.apply_update(state)?;
+        let content = document.content_stats();
+        let service = Self::from_document(compute, document);
+        service.size.store(state.len(), Ordering::Relaxed);
+        service.store_content_stats(content);
+        Ok(service)
+    }
+
+    fn from_document(compute: Arc<ComputePool>, document: CollaborativeDocument) -> Self {
+        Self {
+            document: PooledDocument::new(document),
+            broadcaster: Arc::new(UpdateCoalescer::default()),
+            coalescing: BroadcastCoalescing::default(),
+            compute,
+            update_log: None,
+            policy: None,
+            size: AtomicUsize::new(0),
+            uncompacted: AtomicUsize::new(0),
+            characters: AtomicUsize::new(0),
+            words: AtomicUsize::new(0),
+            broker: None,
+            write_ahead_log: None,
+            owner: None,
+            exporter: None,
+            validator: None,
+            pipeline: None,
+            retired: false,
+            gc: GcOptions::default(),
+        }
+    }
+
+    /// Records every update applied from now on in the given update log
+    pub fn with_update_log(mut self, doc_id: &str, update_log: Arc<dyn UpdateLog>) -> Self {
+        self.update_log = Some((doc_id.to_string(), update_log));
+        self
+    }
+
+    /// Get the feature policy enforced on the document, if it has been resolved
+    pub fn policy(&self) -> Option<&Arc<FeaturePolicy>> {
+        self.policy.as_ref()
+    }
+
+    /// Set the feature policy enforced on the document
+    pub fn set_policy(&mut self, policy: Arc<FeaturePolicy>) {
+        self.policy = Some(policy);
+    }
+
+    /// Get how the content deleted from the document is garbage collected
+    pub fn gc_options(&self) -> GcOptions {
+        self.gc
+    }
+
+    /// Set how the content deleted from the document is garbage collected,
+    /// rebuilding the document if collection is enabled or disabled
+    pub async fn set_gc_options(&mut self, gc: GcOptions) -> DomainResult<()> {
+        self.compute
+            .run(
+                CrdtOperation::ApplyUpdate,
+                self.document.clone(),
+                move |doc| doc.set_gc_enabled(gc.enabled),
+            )
+            .await??;
+        self.gc = gc;
+        Ok(())
+    }
+
+    /// Garbage collect the content deleted from the document, whatever its options
+    pub async fn collect_garbage(&self) -> DomainResult<()> {
+        let size = self
+            .compute
+            .run(CrdtOperation::EncodeState, self.document.clone(), |doc| {
+                doc.collect_garbage();
+                doc.encode_full_state().len()
+            })
+            .await?;
+        self.size.store(size, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Get the size of the document's CRDT structure, tombstones included
+    pub async fn structure_stats(&self) -> StructureStats {
+        self.compute
+            .read(CrdtOperation::ReadContent, self.document.clone(), |doc| {
+                doc.structure_stats()
+            })
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Get the approximate encoded size of the document in bytes
+    pub fn size(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Get the characters and words across the document's text roots
+    pub fn content_stats(&self) -> ContentStats {
+        ContentStats {
+            characters: self.characters.load(Ordering::Relaxed),
+            words: self.words.load(Ordering::Relaxed),
+        }
+    }
+
+    fn store_content_stats(&self, content: ContentStats) {
+        self.characters.store(content.characters, Ordering::Relaxed);
+        self.words.store(content.words, Ordering::Relaxed);
+    }
+
+    /// Get the number of receivers subscribed to the document's updates
+    pub fn subscriber_count(&self) -> usize {
+        self.broadcaster.receiver_count()
+    }
+
+    /// Check whether the document was moved out of the repository
+    pub fn is_retired(&self) -> bool {
+        self.retired
+    }
+
+    /// Mark the document as moved out of the repository, so whoever was waiting
+    /// for its lock opens it again
+    pub fn retire(&mut self) {
+        self.retired = true;
+    }
+
+    /// Relay every update applied locally from now on to the document's owner, which
+    /// persists and shares it in place of this instance
+    pub fn set_owner(&mut self, to_owner: mpsc::UnboundedSender<Vec<u8>>) {
+        self.owner = Some(to_owner);
+        self.update_log = None;
+    }
+
+    /// Relay an update to the document's owner, if the document is forwarded
+    fn relay_to_owner(&self, update_data: &[u8]) {
+        if let Some(owner) = &self.owner {
+            if owner.send(update_data.to_vec()).is_err() {
+                warn!("Update not forwarded, the relay to the document's owner is closed");
+            }
+        }
+    }
+
+    /// Publish every update applied locally from now on through the given broker
+    pub fn set_broker(&mut self, doc_id: &str, broker: Arc<dyn UpdateBroker>) {
+        self.broker = Some((doc_id.to_string(), broker));
+    }
+
+    /// Validate every update clients apply from now on with the given validator
+    pub fn set_validator(&mut self, doc_id: &str, validator: Arc<dyn UpdateValidator>) {
+        self.validator = Some((doc_id.to_string(), validator));
+    }
+
+    /// Run every text clients insert from now on through the given pipeline
+    pub fn set_pipeline(&mut self, doc_id: &str, pipeline: Arc<UpdatePipeline>) {
+        self.pipeline = Some((doc_id.to_string(), pipeline));
+    }
+
+    /// Export every update applied on this instance from now on through the given exporter
+    pub fn set_exporter(&mut self, doc_id: &str, exporter: Arc<dyn UpdateExporter>) {
+        self.exporter = Some((doc_id.to_string(), exporter));
+    }
+
+    /// Record every update applied from now on in the given write-ahead log
+    pub fn set_write_ahead_log(&mut self, doc_id: &str, write_ahead_log: Arc<dyn WriteAheadLog>) {
+        self.write_ahead_log = Some((doc_id.to_string(), write_ahead_log));
+    }
+
+    /// Set how the updates broadcast to subscribers are coalesced
+    pub fn set_broadcast_coalescing(&mut self, coalescing: BroadcastCoalescing) {
+        self.coalescing = coalescing;
+    }
+
+    /// Applies the updates other server instances publish for a document.
+    ///
+    /// Remote updates are broadcast to local subscribers like any other update,
+    /// but are not published again. Updates stop being applied once the
+    /// document is retired, as the reopened document subscribes again.
+    async fn apply_remote_updates(
+        doc_id: String,
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+        mut remote_updates: mpsc::UnboundedReceiver<Vec<u8>>,
+    ) {
+        while let Some(update) = remote_updates.recv().await {
+            let state = document.write().await;
+            if state.is_retired() {
+                break;
+            }
+            let origin = TransactionOrigin::remote(REMOTE_UPDATE_SOURCE);
+            if let Err(e) = state.apply_update_from(&update, origin).await {
+                warn!("Failed to apply remote update to '{}': {}", doc_id, e);
+            }
+        }
+    }
+
+    /// Applies the updates of a document's owner, and sends back the changes it is missing
+    /// when the relay connects.
+    ///
+    /// The owner's updates are broadcast to local subscribers like any other update, but are
+    /// not relayed back. Dropping the receiver once the document is retired stops the relay,
+    /// as the reopened document is forwarded again.
+    async fn follow_owner(
+        doc_id: String,
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+        mut from_owner: mpsc::UnboundedReceiver<OwnerMessage>,
+    ) {
+        while let Some(message) = from_owner.recv().await {
+            let state = document.write().await;
+            if state.is_retired() {
+                break;
+            }
+            match message {
+                OwnerMessage::Update(update) => {
+                    let origin = TransactionOrigin::remote(REMOTE_UPDATE_SOURCE);
+                    if let Err(e) = state.apply_update_from(&update, origin).await {
+                        warn!("Failed to apply the owner's update to '{}': {}", doc_id, e);
+                    }
+                }
+                OwnerMessage::StateVector(state_vector) => {
+                    match state.diff_update(&state_vector).await {
+                        Ok(missing) => state.relay_to_owner(&missing),
+                        Err(e) => warn!(
+                            "Failed to send the owner the changes to '{}' it is missing: {}",
+                            doc_id, e
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the current state of the document
+    pub async fn get_state(&self) -> DomainResult<SyncResponse> {
+        self.flush_broadcast();
+        let (update, state_vector) = self
+            .compute
+            .read(CrdtOperation::EncodeState, self.document.clone(), |doc| {
+                (doc.encode_full_state(), doc.get_state_vector())
+            })
+            .await?;
+
+        Ok(SyncResponse {
+            update: Some(update),
+            state_vector: Some(state_vector),
+            sequence_number: self.sequence_number(),
+        })
+    }
+
+    /// Restore a state saved to a document store, without recording, publishing or
+    /// broadcasting it
+    pub async fn restore(&self, state: &[u8]) -> DomainResult<()> {
+        let saved = state.to_vec();
+        let content = self
+            .compute
+            .run(
+                CrdtOperation::ApplyUpdate,
+                self.document.clone(),
+                move |doc| doc.apply_update(&saved).map(|_| doc.content_stats()),
+            )
+            .await??;
+        self.size.fetch_add(state.len(), Ordering::Relaxed);
+        self.store_content_stats(content);
+        Ok(())
+    }
+
+    /// Apply an update made by the server itself to the document
+    pub async fn apply_update(&self, update_data: &[u8]) -> DomainResult<()> {
+        self.apply_update_from(update_data, TransactionOrigin::server(SERVER_UPDATE_SOURCE))
+            .await
+    }
+
+    /// Apply an update to the document, tagging its transaction and its broadcast with its
+    /// origin
+    pub async fn apply_update_from(
+        &self,
+        update_data: &[u8],
+        origin: TransactionOrigin,
+    ) -> DomainResult<()> {
+        self.apply(update_data, origin, UpdateTracking::None).await
+    }
+
+    /// Apply an update from a client, tracking it in the client's undo stack if the
+    /// document's policy enables undo
+    pub async fn apply_tracked_update(
+        &self,
+        update_data: &[u8],
+        origin: &TransactionOrigin,
+    ) -> DomainResult<()> {
+        let tracking = if self.policy.as_ref().is_some_and(|p| p.undo_enabled)
+            && origin.kind == OriginKind::Local
+        {
+            UpdateTracking::Undo
+        } else {
+            UpdateTracking::None
+        };
+        self.apply(update_data, origin.clone(), tracking).await
+    }
+
+    /// Apply an update from a client, recorded under a key so it can be reverted on its own
+    /// until it is released
+    pub async fn apply_revertible_update(
+        &self,
+        update_data: &[u8],
+        origin: &TransactionOrigin,
+        key: &str,
+    ) -> DomainResult<()> {
+        let tracking = UpdateTracking::Revertible(key.to_string());
+        self.apply(update_data, origin.clone(), tracking).await
+    }
+
+    /// Apply an update to the document in a transaction tagged with its origin, tracked as
+    /// requested
+    async fn apply(
+        &self,
+        update_data: &[u8],
+        origin: TransactionOrigin,
+        tracking: UpdateTracking,
+    ) -> DomainResult<()> {
+        if let Some(policy) = &self.policy {
+            policy.check_size(self.size.load(Ordering::Relaxed), update_data.len())?;
+        }
+
+        // Updates that may exceed the character limit are counted on a copy of
+        // the document first, as applied changes cannot be rolled back
+        let update = update_data.to_vec();
+        let characters = self.characters.load(Ordering::Relaxed);
+        let limit = self
+            .policy
+            .clone()
+            .filter(|policy| policy.may_exceed_characters(characters, update_data.len()));
+        let source = origin.source.clone();
+        // Only client updates are validated; the server's and other instances' are trusted
+        let validation = self
+            .validator
+            .clone()
+            .filter(|_| origin.kind == OriginKind::Local)
+            .map(|(doc_id, validator)| (doc_id, validator, origin.clone()));
+        let processing = self
+            .pipeline
+            .clone()
+            .filter(|_| origin.kind == OriginKind::Local)
+            .map(|(doc_id, pipeline)| (doc_id, pipeline, origin.clone()));
+        let (content, corrections, labels, rejection) = self
+            .compute
+            .run(
+                CrdtOperation::ApplyUpdate,
+                self.document.clone(),
+                move |doc| {
+                    if let Some((doc_id, validator, origin)) = validation {
+                        let changes = doc.changes_with(&update)?;
+                        validator.validate(&doc_id, &changes, &origin)?;
+                    }
+                    if let Some(policy) = limit {
+                        let updated = doc.content_stats_with(&update)?;
+                        policy.check_characters(characters, updated.characters)?;
+                    }
+                    let apply = |doc: &mut CollaborativeDocument| match &tracking {
+                        UpdateTracking::None => doc.apply_update_from(&update, &source),
+                        UpdateTracking::Undo => doc.apply_tracked_update(&update, &source),
+                        UpdateTracking::Revertible(key) => {
+                            doc.apply_revertible_update(&update, key)
+                        }
+                    };
+                    let processed = match processing {
+                        Some((doc_id, pipeline, origin)) => {
+                            // The processors inspect the text the applied update inserted, so
+                            // the update is recorded to be reverted if they reject it
+                            let key = match &tracking {
+                                UpdateTracking::Revertible(key) => key.clone(),
+                                _ => {
+                                    doc.begin_revertible(PROCESSING_REVERT_KEY, &source);
+                                    PROCESSING_REVERT_KEY.to_string()
+                                }
+                            };
+                            let insertions = match doc.text_insertions_of(apply) {
+                                Ok((_, insertions)) => insertions,
+                                Err(e) => {
+                                    doc.release_revertible(PROCESSING_REVERT_KEY);
+                                    return Err(e);
+                                }
+                            };
+                            match pipeline.process(&doc_id, &insertions, &origin) {
+                                Ok(processed) => {
+                                    doc.release_revertible(PROCESSING_REVERT_KEY);
+                                    processed
+                                }
+                                Err(e) => {
+                                    let reverted: Vec<Vec<u8>> =
+                                        doc.revert(&key)?.into_iter().collect();
+                                    // The client's undo stack cannot undo a reverted update
+                                    if tracking == UpdateTracking::Undo {
+                                        doc.release_undo(&source);
+                                    }
+                                    return Ok((
+                                        doc.content_stats(),
+                                        reverted,
+                                        Vec::new(),
+                                        Some(e),
+                                    ));
+                                }
+                            }
+                        }
+                        None => {
+                            apply(doc)?;
+                            ProcessedUpdate::default()
+                        }
+                    };
+                    // The replaced text is written over the inserted one before anything is
+                    // broadcast
+                    let mut corrections = Vec::new();
+                    for edit in &processed.edits {
+                        if let Some(correction) = doc.edit_update(edit)? {
+                            doc.apply_update_from(&correction, PROCESSING_UPDATE_SOURCE)?;
+                            corrections.push(correction);
+                        }
+                    }
+                    if let UpdateTracking::Revertible(key) = &tracking {
+                        doc.seal_revertible(key);
+                    }
+                    Ok::<_, DomainError>((doc.content_stats(), corrections, processed.labels, None))
+                },
+            )
+            .await??;
+        let corrected = corrections.iter().map(Vec::len).sum::<usize>();
+        self.size
+            .fetch_add(update_data.len() + corrected, Ordering::Relaxed);
+        self.store_content_stats(content);
+
+        if let Some(error) = rejection {
+            // Everyone, the sender included, receives the rejected update along with its
+            // revert, so every replica drops the update's changes
+            let mut updates = vec![update_data.to_vec()];
+            updates.extend(corrections);
+            let merged = CollaborativeDocument::merge_updates(&updates)?;
+            self.publish(
+                &merged,
+                &TransactionOrigin::server(PROCESSING_UPDATE_SOURCE),
+            )
+            .await?;
+            return Err(error);
+        }
+        if corrections.is_empty() {
+            self.publish(update_data, &origin).await?;
+        } else {
+            // The other clients, instances and pipelines only receive the processed text
+            let mut updates = vec![update_data.to_vec()];
+            updates.extend(corrections.iter().cloned());
+            let merged = CollaborativeDocument::merge_updates(&updates)?;
+            self.publish(&merged, &origin).await?;
+            // The sender, which skips its own updates, is broadcast the correction of the
+            // text it inserted; it is persisted and shared with the merged update already
+            let correction = CollaborativeDocument::merge_updates(&corrections)?;
+            self.broadcaster.publish(
+                &correction,
+                &TransactionOrigin::server(PROCESSING_UPDATE_SOURCE),
+                self.coalescing,
+                true,
+            );
+        }
+        if let Some((doc_id, pipeline)) = &self.pipeline {
+            pipeline.annotate(doc_id, &origin.source, labels);
+        }
+        Ok(())
+    }
+
+    /// Undo or redo the last change tracked in a client's undo stack, broadcasting the
+    /// resulting update to every subscriber; returns whether the document changed
+    pub async fn undo(&self, client_id: &str, action: UndoAction) -> DomainResult<bool> {
+        let client_id = client_id.to_string();
+        let changed = self
+            .compute
+            .run(
+                CrdtOperation::ApplyUpdate,
+                self.document.clone(),
+                move |doc| {
+                    doc.undo(&client_id, action)
+                        .map(|update| (update, doc.content_stats()))
+                },
+            )
+            .await?;
+        let Some((update, content)) = changed else {
+            return Ok(false);
+        };
+
+        self.size.fetch_add(update.len(), Ordering::Relaxed);
+        self.store_content_stats(content);
+        self.publish(&update, &TransactionOrigin::server(UNDO_UPDATE_SOURCE))
+            .await?;
+        Ok(true)
+    }
+
+    /// Drop a client's undo stack
+    pub async fn release_undo(&self, client_id: &str) {
+        self.document.write().await.release_undo(client_id);
+    }
+
+    /// Revert an update recorded under a key, keeping the changes made since by others, and
+    /// broadcast the resulting update to every subscriber; returns whether an update was
+    /// recorded under the key
+    pub async fn revert(&self, key: &str) -> DomainResult<bool> {
+        let key = key.to_string();
+        let reverted = self
+            .compute
+            .run(
+                CrdtOperation::ApplyUpdate,
+                self.document.clone(),
+                move |doc| {
+                    Ok::<_, DomainError>(
+                        doc.revert(&key)?
+                            .map(|update| (update, doc.content_stats())),
+                    )
+                },
+            )
+            .await??;
+        let Some((update, content)) = reverted else {
+            return Ok(false);
+        };
+
+        self.size.fetch_add(update.len(), Ordering::Relaxed);
+        self.store_content_stats(content);
+        self.publish(&update, &TransactionOrigin::server(SERVER_UPDATE_SOURCE))
+            .await?;
+        Ok(true)
+    }
+
+    /// Drop an update recorded under a key, once it no longer needs reverting
+    pub async fn release_revertible(&self, key: &str) {
+        self.document.write().await.release_revertible(key);
+    }
+
+    /// Persist, share and broadcast an update applied to the document
+    async fn publish(&self, update_data: &[u8], origin: &TransactionOrigin) -> DomainResult<()> {
+        // Persist the update before other clients can observe it
+        if let Some((doc_id, update_log)) = &self.update_log {
+            update_log.append(doc_id, update_data).map_err(|e| {
+                DomainError::StorageFailure(format!("Update applied but not persisted: {}", e))
+            })?;
+
+            // Without history, only the merged state is retained: the log is compacted in
+            // the background every few updates rather than on the path of each one
+            if self.policy.as_ref().is_some_and(|p| !p.history_enabled)
+                && self.uncompacted.fetch_add(1, Ordering::Relaxed) + 1
+                    >= HISTORYLESS_COMPACTION_INTERVAL
+            {
+                self.uncompacted.store(0, Ordering::Relaxed);
+                let (doc_id, update_log) = (doc_id.clone(), update_log.clone());
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = update_log.compact(&doc_id) {
+                        warn!("Failed to compact the log of '{}': {}", doc_id, e);
+                    }
+                });
+            }
+        }
+
+        // Logged after the update log, so a checkpoint never truncates an update
+        // the storage backend has not received yet
+        if let Some((doc_id, write_ahead_log)) = &self.write_ahead_log {
+            // Appends wait for the disk, possibly for a sync, so they leave the async workers
+            let (doc_id, write_ahead_log) = (doc_id.clone(), write_ahead_log.clone());
+            let update = update_data.to_vec();
+            tokio::task::spawn_blocking(move || write_ahead_log.append(&doc_id, &update))
+                .await
+                .map_err(|e| DomainError::Internal(format!("Failed to log the update: {}", e)))?
+                .map_err(|e| {
+                    DomainError::StorageFailure(format!("Update applied but not logged: {}", e))
+                })?;
+        }
+
+        // Share local updates with other instances, or with the owner of a forwarded
+        // document; remote ones already were
+        if origin.kind != OriginKind::Remote {
+            self.relay_to_owner(update_data);
+        }
+        if let Some((doc_id, broker)) = &self.broker {
+            if origin.kind != OriginKind::Remote {
+                if let Err(e) = broker.publish(doc_id, update_data) {
+                    warn!(
+                        "Update to '{}' not shared with other instances: {}",
+                        doc_id, e
+                    );
+                }
+            }
+        }
+
+        // Broadcast the update to subscribers; updates made by the server itself,
+        // such as reverts and imports, are not held back with clients' keystrokes
+        let urgent = origin.kind == OriginKind::Server;
+        self.broadcaster
+            .publish(update_data, origin, self.coalescing, urgent);
+
+        if let Some((doc_id, exporter)) = &self.exporter {
+            if origin.kind != OriginKind::Remote {
+                let applied_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as i64;
+                let update = ExportedUpdate::new(
+                    doc_id,
+                    self.pending_sequence_number(),
+                    origin,
+                    update_data,
+                    applied_at,
+                );
+                if let Err(e) = exporter.export(update) {
+                    warn!("Update to '{}' not exported: {}", doc_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Subscribe to updates to the document
+    pub fn subscribe(&self) -> broadcast::Receiver<UpdateNotification> {
+        self.broadcaster.subscribe()
+    }
+
+    /// Get the sequence number of the last update broadcast for the document
+    pub fn sequence_number(&self) -> u64 {
+        self.broadcaster.sequence_number()
+    }
+
+    /// Get the sequence number of the broadcast carrying the last update applied to
+    /// the document, which may still be held back to be coalesced
+    pub fn pending_sequence_number(&self) -> u64 {
+        self.broadcaster.pending_sequence_number()
+    }
+
+    /// Broadcast the updates held back to be coalesced, so the sequence number
+    /// covers every update applied to the document
+    pub fn flush_broadcast(&self) {
+        self.broadcaster.flush();
+    }
+
+    /// Get the current content of the document
+    pub async fn get_content(&self) -> DomainResult<String> {
+        self.compute
+            .read(CrdtOperation::ReadContent, self.document.clone(), |doc| {
+                doc.get_content_as_string()
+            })
+            .await
+    }
+
+    /// Get the GUIDs of the subdocuments the document references
+    pub async fn subdocument_guids(&self) -> DomainResult<Vec<String>> {
+        self.compute
+            .read(CrdtOperation::ReadContent, self.document.clone(), |doc| {
+                doc.subdocument_guids()
+            })
+            .await
+    }
+
+    /// Get the current state vector of the document
+    pub async fn get_state_vector(&self) -> Vec<u8> {
+        self.document.read().await.get_state_vector()
+    }
+
+    /// Get the complete document state to save, garbage collecting the deleted
+    /// content first when the document collects it on snapshots only
+    pub async fn snapshot(&self) -> DomainResult<Vec<u8>> {
+        if !self.gc.on_snapshot || self.gc.enabled {
+            return self.get_full_update().await;
+        }
+
+        let state = self
+            .compute
+            .run(CrdtOperation::EncodeState, self.document.clone(), |doc| {
+                doc.collect_garbage();
+                doc.encode_full_state()
+            })
+            .await?;
+        self.size.store(state.len(), Ordering::Relaxed);
+        Ok(state)
+    }
+
+    /// Get the complete document state encoded as a single update
+    pub async fn get_full_update(&self) -> DomainResult<Vec<u8>> {
+        self.compute
+            .read(CrdtOperation::EncodeState, self.document.clone(), |doc| {
+                doc.encode_full_state()
+            })
+            .await
+    }
+
+    /// Encode the document for export, either in full or as a clean copy
+    pub async fn export(&self, mode: ExportMode) -> DomainResult<Vec<u8>> {
+        self.compute
+            .read(
+                CrdtOperation::EncodeState,
+                self.document.clone(),
+                move |doc| match mode {
+                    ExportMode::Full => doc.encode_full_state(),
+                    ExportMode::Clean => doc.encode_clean_state(),
+                },
+            )
+            .await
+    }
+
+    /// Serialize the document's content in a readable format
+    pub async fn export_content(&self, format: ExportFormat) -> DomainResult<String> {
+        self.compute
+            .read(
+                CrdtOperation::ReadContent,
+                self.document.clone(),
+                move |doc| DocumentExporter::export(doc, format),
+            )
+            .await?
+    }
+
+    /// Compute the update restoring the content of a previous state of the document
+    pub async fn revert_update(&self, state: &[u8]) -> DomainResult<Option<Vec<u8>>> {
+        let state = state.to_vec();
+        self.compute
+            .read(
+                CrdtOperation::ComputeDiff,
+                self.document.clone(),
+                move |doc| doc.revert_update(&state),
+            )
+            .await?
+    }
+
+    /// Compute the update writing a change into a map or array root of the document
+    pub async fn edit_update(&self, edit: SharedEdit) -> DomainResult<Option<Vec<u8>>> {
+        self.compute
+            .read(
+                CrdtOperation::ApplyUpdate,
+                self.document.clone(),
+                move |doc| doc.edit_update(&edit),
+            )
+            .await?
+    }
+
+    /// Get a diff update based on the provided state vector
+    ///
+    /// This method computes the missing updates that a client needs based on
+    /// their current state vector compared to the server's document state.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_state_vector` - The client's current state vector
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - Binary update data containing all changes the client is missing
+    /// * `Err(DomainError)` - An error message if the state vector couldn't be decoded
+    pub async fn diff_update(&self, client_state_vector: &[u8]) -> DomainResult<Vec<u8>> {
+        let client_state_vector = client_state_vector.to_vec();
+        self.compute
+            .read(
+                CrdtOperation::ComputeDiff,
+                self.document.clone(),
+                move |doc| doc.get_missing_updates(&client_state_vector),
+            )
+            .await?
+    }
+
+    /// Computes the updates a client is missing, split into chunks if oversized.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_state_vector` - The client's binary state vector
+    /// * `throttle` - Chunking threshold and chunk size
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Vec<u8>>)` - The diff, as one update or several chunks
+    /// * `Err(DomainError)` - An error message if the state vector couldn't be decoded
+    pub async fn diff_chunks(
+        &self,
+        client_state_vector: &[u8],
+        throttle: &DiffThrottle,
+    ) -> DomainResult<Vec<Vec<u8>>> {
+        let client_state_vector = client_state_vector.to_vec();
+        let threshold = match throttle.chunk_threshold {
+            0 => usize::MAX,
+            threshold => threshold,
+        };
+        let chunk_size = throttle.chunk_size;
+
+        self.compute
+            .read(
+                CrdtOperation::ComputeDiff,
+                self.document.clone(),
+                move |doc| {
+                    doc.get_missing_update_chunks(&client_state_vector, threshold, chunk_size)
+                },
+            )
+            .await?
     }
 }
 
@@ -1,2 +1,11 @@
+pub mod activity_tracker;
+pub mod archive_tier;
+pub mod compute_pool;
+pub mod document_actor;
+pub mod document_exporter;
+pub mod document_importer;
+pub mod document_ownership;
 pub mod document_service;
-
+pub mod payload_dictionaries;
+pub mod update_coalescer;
+pub mod update_pipeline;
@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use tracing::{debug, warn};
+
+use crate::{
+    repositories::dictionary_compressor::DictionaryCompressor,
+    value_objects::payload_dictionary::{CompressedPayload, DictionarySettings, PayloadDictionary},
+};
+
+/// Training samples and current dictionary of a single document.
+#[derive(Default)]
+struct DocumentDictionary {
+    /// Updates sampled since the dictionary was last trained
+    samples: Vec<Vec<u8>>,
+    /// Whether a dictionary is being trained on the previous samples
+    training: bool,
+    /// The latest trained dictionary
+    current: Option<Arc<PayloadDictionary>>,
+}
+
+/// Per-document compression dictionaries of the updates relayed to clients.
+///
+/// The updates clients apply to a document are sampled; once enough are
+/// collected, a dictionary is trained on them off the async workers, then
+/// retrained on the next samples, so it follows how the document is edited.
+/// Connections that negotiated dictionary compression receive each dictionary
+/// once, then the updates of the document compressed with it.
+pub struct PayloadDictionaries {
+    compressor: Arc<dyn DictionaryCompressor>,
+    settings: DictionarySettings,
+    documents: Mutex<HashMap<String, DocumentDictionary>>,
+    /// Identifier of the next trained dictionary
+    next_id: AtomicU32,
+}
+
+impl PayloadDictionaries {
+    /// Creates dictionaries trained and applied by a compressor.
+    ///
+    /// # Arguments
+    ///
+    /// * `compressor` - The compressor training dictionaries and compressing payloads
+    /// * `settings` - When dictionaries are trained and which payloads they compress
+    ///
+    /// # Returns
+    ///
+    /// A new `PayloadDictionaries` without any dictionary
+    pub fn new(compressor: Arc<dyn DictionaryCompressor>, settings: DictionarySettings) -> Self {
+        Self {
+            compressor,
+            settings,
+            documents: Mutex::new(HashMap::new()),
+            next_id: AtomicU32::new(1),
+        }
+    }
+
+    fn documents(&self) -> MutexGuard<'_, HashMap<String, DocumentDictionary>> {
+        self.documents
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Samples an update applied to a document, training its dictionary once
+    /// enough updates are sampled.
+    ///
+    /// Must be called from within a Tokio runtime, which trains the dictionary
+    /// on its blocking threads.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `update` - The binary update applied to the document
+    pub fn record(self: &Arc<Self>, doc_id: &str, update: &[u8]) {
+        if update.is_empty() || update.len() > self.settings.max_sample_size {
+            return;
+        }
+
+        let samples = {
+            let mut documents = self.documents();
+            let document = documents.entry(doc_id.to_string()).or_default();
+            document.samples.push(update.to_vec());
+            if document.training || document.samples.len() < self.settings.train_after_updates {
+                return;
+            }
+            document.training = true;
+            mem::take(&mut document.samples)
+        };
+
+        let dictionaries = Arc::clone(self);
+        let doc_id = doc_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            let trained = dictionaries
+                .compressor
+                .train(&samples, dictionaries.settings.max_dictionary_size);
+
+            let mut documents = dictionaries.documents();
+            // Forgotten while training, e.g. deleted
+            let Some(document) = documents.get_mut(&doc_id) else {
+                return;
+            };
+            document.training = false;
+            match trained {
+                Ok(data) => {
+                    let id = dictionaries.next_id.fetch_add(1, Ordering::Relaxed);
+                    debug!(
+                        "Trained dictionary #{} of {} bytes for document '{}' on {} updates",
+                        id,
+                        data.len(),
+                        doc_id,
+                        samples.len()
+                    );
+                    document.current = Some(Arc::new(PayloadDictionary {
+                        id,
+                        data: data.into(),
+                    }));
+                }
+                Err(e) => warn!(
+                    "Failed to train a dictionary for document '{}': {}",
+                    doc_id, e
+                ),
+            }
+        });
+    }
+
+    /// Returns the latest dictionary of a document, if one was trained.
+    pub fn dictionary(&self, doc_id: &str) -> Option<Arc<PayloadDictionary>> {
+        self.documents()
+            .get(doc_id)
+            .and_then(|document| document.current.clone())
+    }
+
+    /// Compresses a payload relayed to the clients of a document with its latest dictionary.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `payload` - The payload to compress
+    ///
+    /// # Returns
+    ///
+    /// The compressed payload, or `None` if it is better sent as is: no
+    /// dictionary is trained yet, the payload is too small, or compressing it
+    /// failed or did not make it smaller
+    pub fn compress(&self, doc_id: &str, payload: &[u8]) -> Option<CompressedPayload> {
+        if payload.len() < self.settings.min_payload_size {
+            return None;
+        }
+
+        let dictionary = self.dictionary(doc_id)?;
+        match self.compressor.compress(&dictionary.data, payload) {
+            Ok(data) if data.len() < payload.len() => Some(CompressedPayload { dictionary, data }),
+            Ok(_) => None,
+            Err(e) => {
+                warn!(
+                    "Failed to compress a payload of document '{}': {}",
+                    doc_id, e
+                );
+                None
+            }
+        }
+    }
+
+    /// Drops the samples and dictionary of a document, e.g. once it is deleted.
+    pub fn forget(&self, doc_id: &str) {
+        self.documents().remove(doc_id);
+    }
+}
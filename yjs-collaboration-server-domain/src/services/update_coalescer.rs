@@ -0,0 +1,195 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, MutexGuard,
+};
+
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::{
+    entities::document::CollaborativeDocument,
+    services::document_service::UpdateNotification,
+    value_objects::{broadcast_coalescing::BroadcastCoalescing, update_origin::TransactionOrigin},
+};
+
+/// Capacity of a document's broadcast channel; slow subscribers lag beyond it.
+const UPDATE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Updates of a single origin held back until their window ends.
+struct HeldUpdates {
+    /// Origin the updates are tagged with
+    origin: TransactionOrigin,
+    /// The binary updates, in the order they were applied
+    updates: Vec<Vec<u8>>,
+    /// Total size in bytes of the updates
+    size: usize,
+    /// Number of the window, so a timer only flushes the updates it was started for
+    window: u64,
+}
+
+/// Broadcasts the updates applied to a document, coalescing those sent in quick
+/// succession by the same source.
+///
+/// Updates held back are merged into a single update once their window ends,
+/// once they grow too large, or as soon as an update from another source is
+/// broadcast, so subscribers always observe the updates in the order they were
+/// applied. Sequence numbers are assigned as updates are broadcast: a merged
+/// update takes a single one.
+pub struct UpdateCoalescer {
+    /// Broadcast channel for sending updates to subscribers
+    sender: broadcast::Sender<UpdateNotification>,
+    /// Sequence number of the last broadcast update
+    sequence: AtomicU64,
+    /// Updates held back, if any
+    held: Mutex<Option<HeldUpdates>>,
+    /// Number of the last window opened
+    windows: AtomicU64,
+}
+
+impl Default for UpdateCoalescer {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(UPDATE_CHANNEL_CAPACITY).0,
+            sequence: AtomicU64::new(0),
+            held: Mutex::new(None),
+            windows: AtomicU64::new(0),
+        }
+    }
+}
+
+impl UpdateCoalescer {
+    /// Broadcasts an update, or holds it back to be merged with the following ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The binary update
+    /// * `origin` - Origin the update is tagged with
+    /// * `coalescing` - How updates are coalesced; updates are broadcast at once if disabled
+    /// * `urgent` - Whether the update must be broadcast at once, after the held ones
+    pub fn publish(
+        self: &Arc<Self>,
+        update: &[u8],
+        origin: &TransactionOrigin,
+        coalescing: BroadcastCoalescing,
+        urgent: bool,
+    ) {
+        let mut held = self.lock();
+        if urgent || !coalescing.is_enabled() {
+            self.flush_held(&mut held);
+            self.send(update.to_vec(), origin);
+            return;
+        }
+
+        match held.as_mut() {
+            Some(updates) if updates.origin == *origin => {
+                updates.updates.push(update.to_vec());
+                updates.size += update.len();
+                if coalescing.is_full(updates.size) {
+                    self.flush_held(&mut held);
+                }
+            }
+            _ => {
+                self.flush_held(&mut held);
+                let window = self.windows.fetch_add(1, Ordering::Relaxed) + 1;
+                *held = Some(HeldUpdates {
+                    origin: origin.clone(),
+                    updates: vec![update.to_vec()],
+                    size: update.len(),
+                    window,
+                });
+                if coalescing.is_full(update.len()) {
+                    self.flush_held(&mut held);
+                    return;
+                }
+
+                let coalescer = Arc::clone(self);
+                tokio::spawn(async move {
+                    tokio::time::sleep(coalescing.window).await;
+                    coalescer.flush_window(window);
+                });
+            }
+        }
+    }
+
+    /// Broadcasts the updates held back, if any.
+    pub fn flush(&self) {
+        self.flush_held(&mut self.lock());
+    }
+
+    /// Subscribe to the updates broadcast
+    pub fn subscribe(&self) -> broadcast::Receiver<UpdateNotification> {
+        self.sender.subscribe()
+    }
+
+    /// Get the number of receivers subscribed to the updates
+    pub fn receiver_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Get the sequence number of the last update broadcast
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence.load(Ordering::Relaxed)
+    }
+
+    /// Get the sequence number the updates held back will be broadcast with, or
+    /// that of the last update broadcast if none is held
+    pub fn pending_sequence_number(&self) -> u64 {
+        let held = self.lock();
+        self.sequence_number() + u64::from(held.is_some())
+    }
+
+    /// Broadcasts the updates held back for a window, unless they already were.
+    fn flush_window(&self, window: u64) {
+        let mut held = self.lock();
+        if held
+            .as_ref()
+            .is_some_and(|updates| updates.window == window)
+        {
+            self.flush_held(&mut held);
+        }
+    }
+
+    /// Broadcasts the updates held back as a single update, one by one if they
+    /// cannot be merged.
+    fn flush_held(&self, held: &mut Option<HeldUpdates>) {
+        let Some(HeldUpdates {
+            origin,
+            mut updates,
+            ..
+        }) = held.take()
+        else {
+            return;
+        };
+        if updates.len() == 1 {
+            return self.send(updates.swap_remove(0), &origin);
+        }
+
+        match CollaborativeDocument::merge_updates(&updates) {
+            Ok(merged) => self.send(merged, &origin),
+            Err(e) => {
+                debug!("Broadcasting {} updates unmerged: {}", updates.len(), e);
+                for update in updates {
+                    self.send(update, &origin);
+                }
+            }
+        }
+    }
+
+    /// Broadcasts an update with the next sequence number; the held updates are
+    /// locked meanwhile, so sequence numbers follow the broadcast order
+    fn send(&self, update: Vec<u8>, origin: &TransactionOrigin) {
+        let notification = UpdateNotification {
+            update,
+            source: origin.source.clone(),
+            user_id: origin.user_id.clone(),
+            origin: origin.kind,
+            sequence_number: self.sequence.fetch_add(1, Ordering::Relaxed) + 1,
+        };
+
+        let _ = self.sender.send(notification);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<HeldUpdates>> {
+        self.held.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,178 @@
+use std::{cmp::Reverse, sync::Arc};
+
+use tokio::sync::broadcast;
+use tracing::debug;
+
+use crate::{
+    errors::{DomainError, DomainResult},
+    repositories::update_processor::UpdateProcessor,
+    value_objects::{
+        document_event::DocumentEvent,
+        shared_edit::SharedEdit,
+        text_insertion::{ProcessorVerdict, TextInsertion, TextReplacement},
+        update_origin::TransactionOrigin,
+    },
+};
+
+/// Outcome of the pipeline on the texts an update inserts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessedUpdate {
+    /// Labels the processors attached to the update, prefixed with their name
+    pub labels: Vec<String>,
+    /// Edits writing the replaced texts over the inserted ones
+    pub edits: Vec<SharedEdit>,
+}
+
+/// Ordered pipeline of the processors inspecting the text clients insert.
+///
+/// Every text an update inserts goes through each processor in turn, which
+/// may accept it, replace ranges of it, label the update or reject it. The
+/// replacements of an insertion are combined into a single edit, written over
+/// the inserted text once the update is applied, and the labels published as
+/// a `document.annotated` event once the update is applied.
+#[derive(Clone)]
+pub struct UpdatePipeline {
+    processors: Vec<Arc<dyn UpdateProcessor>>,
+    /// Channel the annotations are published to
+    events: broadcast::Sender<DocumentEvent>,
+}
+
+impl UpdatePipeline {
+    /// Creates a pipeline without any processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - The channel of the document events the annotations are published to
+    ///
+    /// # Returns
+    ///
+    /// A new `UpdatePipeline` accepting every text
+    pub fn new(events: broadcast::Sender<DocumentEvent>) -> Self {
+        Self {
+            processors: Vec::new(),
+            events,
+        }
+    }
+
+    /// Appends a processor, run after the ones already registered.
+    ///
+    /// # Arguments
+    ///
+    /// * `processor` - The processor to append
+    ///
+    /// # Returns
+    ///
+    /// The `UpdatePipeline` running the processor last
+    pub fn with_processor(mut self, processor: Arc<dyn UpdateProcessor>) -> Self {
+        self.processors.push(processor);
+        self
+    }
+
+    /// Runs the processors on the texts an update inserts.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `insertions` - The texts the update inserts
+    /// * `origin` - The client and user applying the update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ProcessedUpdate)` - The labels of the update and the edits replacing its texts
+    /// * `Err(DomainError)` - `InvalidUpdate` if a processor rejected the update, or the
+    ///   processor's error if it failed
+    pub fn process(
+        &self,
+        doc_id: &str,
+        insertions: &[TextInsertion],
+        origin: &TransactionOrigin,
+    ) -> DomainResult<ProcessedUpdate> {
+        let mut processed = ProcessedUpdate::default();
+        for insertion in insertions {
+            let mut current = insertion.clone();
+            for processor in &self.processors {
+                match processor.process(doc_id, &current, origin)? {
+                    ProcessorVerdict::Accept => {}
+                    ProcessorVerdict::Reject(reason) => {
+                        debug!(
+                            "Processor '{}' rejected an update of document '{}' from '{}'",
+                            processor.name(),
+                            doc_id,
+                            origin.source
+                        );
+                        return Err(DomainError::InvalidUpdate(format!(
+                            "Rejected by {}: {}",
+                            processor.name(),
+                            reason
+                        )));
+                    }
+                    ProcessorVerdict::Annotate(labels) => {
+                        processed.labels.extend(
+                            labels
+                                .into_iter()
+                                .map(|label| format!("{}:{}", processor.name(), label)),
+                        );
+                    }
+                    ProcessorVerdict::Replace(replacements) => {
+                        current.text =
+                            replace_ranges(&current.text, replacements).ok_or_else(|| {
+                                DomainError::Internal(format!(
+                                    "Processor '{}' replaced an invalid range",
+                                    processor.name()
+                                ))
+                            })?;
+                    }
+                }
+            }
+
+            if current.text != insertion.text {
+                processed.edits.push(SharedEdit::ReplaceText {
+                    root: insertion.root.clone(),
+                    index: insertion.index,
+                    len: insertion.text.len() as u32,
+                    text: current.text,
+                });
+            }
+        }
+        Ok(processed)
+    }
+
+    /// Publishes the labels the processors attached to an applied update, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    /// * `source` - Identifier of the client that sent the update
+    /// * `labels` - Labels of the update
+    pub fn annotate(&self, doc_id: &str, source: &str, labels: Vec<String>) {
+        if labels.is_empty() {
+            return;
+        }
+        // No subscriber is not an error
+        let _ = self.events.send(DocumentEvent::Annotated {
+            doc_id: doc_id.to_string(),
+            source: source.to_string(),
+            labels,
+        });
+    }
+}
+
+/// Replaces ranges of a text, or returns `None` if they overlap or split a character.
+fn replace_ranges(text: &str, mut replacements: Vec<TextReplacement>) -> Option<String> {
+    // From the end, so the offsets of the remaining ranges stay valid
+    replacements.sort_by_key(|replacement| Reverse(replacement.start));
+    let mut replaced = text.to_string();
+    let mut limit = text.len();
+    for replacement in replacements {
+        if replacement.start > replacement.end
+            || replacement.end > limit
+            || !text.is_char_boundary(replacement.start)
+            || !text.is_char_boundary(replacement.end)
+        {
+            return None;
+        }
+        replaced.replace_range(replacement.start..replacement.end, &replacement.text);
+        limit = replacement.start;
+    }
+    Some(replaced)
+}
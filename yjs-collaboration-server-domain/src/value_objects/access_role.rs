@@ -0,0 +1,84 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Permission a client holds on a document.
+///
+/// Every client allowed to join a document may receive its sync responses and
+/// the updates of other clients; only read-write clients may change it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessRole {
+    /// The client may read the document but its updates are rejected
+    ReadOnly,
+    /// The client may read and edit the document
+    #[default]
+    ReadWrite,
+}
+
+impl AccessRole {
+    /// Returns whether a client holding this role may apply updates.
+    pub fn can_write(self) -> bool {
+        self == Self::ReadWrite
+    }
+}
+
+impl FromStr for AccessRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read_only" => Ok(Self::ReadOnly),
+            "read_write" => Ok(Self::ReadWrite),
+            _ => Err(format!("Unknown access role: {}", s)),
+        }
+    }
+}
+
+/// Permission an operator grants a user while the server runs, overriding the
+/// role resolved by the access control.
+///
+/// A grant can only restrict the resolved role: granting `ReadWrite` lifts a
+/// previous restriction rather than letting a read-only user edit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessGrant {
+    /// The user may keep reading the document but its updates are rejected
+    ReadOnly,
+    /// The user holds the role resolved by the access control
+    ReadWrite,
+    /// The user may no longer access the document
+    Revoked,
+}
+
+impl AccessGrant {
+    /// Applies the grant to the role resolved by the access control.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The resolved role
+    ///
+    /// # Returns
+    ///
+    /// The restricted role, or `None` if access is revoked
+    pub fn restrict(self, role: AccessRole) -> Option<AccessRole> {
+        match self {
+            Self::ReadOnly => Some(AccessRole::ReadOnly),
+            Self::ReadWrite => Some(role),
+            Self::Revoked => None,
+        }
+    }
+}
+
+impl FromStr for AccessGrant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read_only" => Ok(Self::ReadOnly),
+            "read_write" => Ok(Self::ReadWrite),
+            "revoked" => Ok(Self::Revoked),
+            _ => Err(format!("Unknown access grant: {}", s)),
+        }
+    }
+}
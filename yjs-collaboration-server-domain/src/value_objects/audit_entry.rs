@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::value_objects::update_origin::{UpdateOrigin, UpdateTransport};
+
+/// Default number of audit entries returned per page.
+pub const DEFAULT_AUDIT_PAGE_SIZE: usize = 100;
+
+/// Maximum number of audit entries returned per page.
+pub const MAX_AUDIT_PAGE_SIZE: usize = 1000;
+
+/// An update recorded in a document's audit trail.
+///
+/// Entries are numbered from 1 by the audit sink in the order they are
+/// recorded; the numbers of entries dropped by retention are never reused, so
+/// they serve as a stable cursor to page through the trail.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Number of the entry within the document's trail
+    pub id: u64,
+    /// Identifier of the client that sent the update
+    pub client_id: String,
+    /// Identity of the user behind the client, or `None` for a guest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Transport the update reached the server through
+    pub transport: UpdateTransport,
+    /// Size in bytes of the binary update
+    pub update_size: usize,
+    /// Sequence number of the broadcast carrying the update
+    pub sequence_number: u64,
+    /// Time the update was applied, as Unix milliseconds
+    pub applied_at: i64,
+}
+
+impl AuditEntry {
+    /// Creates an entry for an update, left unnumbered until it is recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Who applied the update, and through which transport
+    /// * `update_size` - Size in bytes of the binary update
+    /// * `sequence_number` - Sequence number of the broadcast carrying the update
+    /// * `applied_at` - Time the update was applied, as Unix milliseconds
+    ///
+    /// # Returns
+    ///
+    /// A new `AuditEntry` numbered `0`
+    pub fn new(
+        origin: UpdateOrigin<'_>,
+        update_size: usize,
+        sequence_number: u64,
+        applied_at: i64,
+    ) -> Self {
+        Self {
+            id: 0,
+            client_id: origin.client_id.to_string(),
+            user_id: origin.user_id.map(str::to_string),
+            transport: origin.transport,
+            update_size,
+            sequence_number,
+            applied_at,
+        }
+    }
+}
+
+/// A page of a document's audit trail.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditPage {
+    /// The entries, oldest first
+    pub entries: Vec<AuditEntry>,
+    /// Cursor to pass to read the next page, or `None` if this page is the last
+    pub next: Option<u64>,
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+/// How the updates broadcast for a document are coalesced.
+///
+/// Clients typing send an update per keystroke, each relayed to every other
+/// subscriber of the document. With a window set, the updates a source sends
+/// within the window are merged into a single update before being broadcast,
+/// so subscribers receive one message per window instead of one per
+/// keystroke. Updates are still persisted and shared with other instances as
+/// soon as they are applied; only their broadcast is delayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BroadcastCoalescing {
+    /// How long updates are held back to be merged with the following ones
+    /// (`Duration::ZERO` = each update is broadcast at once)
+    pub window: Duration,
+    /// Held updates are broadcast without waiting for the window to end once
+    /// they total this many bytes (`0` = only when the window ends)
+    pub max_bytes: usize,
+}
+
+impl Default for BroadcastCoalescing {
+    /// Creates settings broadcasting each update at once.
+    fn default() -> Self {
+        Self {
+            window: Duration::ZERO,
+            max_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl BroadcastCoalescing {
+    /// Checks whether updates are held back at all.
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero()
+    }
+
+    /// Checks whether held updates totalling the given size are broadcast at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `held_bytes` - Total size in bytes of the held updates
+    ///
+    /// # Returns
+    ///
+    /// `true` if the updates should not wait for the window to end
+    pub fn is_full(&self, held_bytes: usize) -> bool {
+        self.max_bytes > 0 && held_bytes >= self.max_bytes
+    }
+}
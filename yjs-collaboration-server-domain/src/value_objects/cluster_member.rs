@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// A server instance of a cluster, as advertised to the other instances.
+///
+/// Clients connecting to an instance that does not own their document are
+/// sent to the owner's addresses, so they must be reachable by the clients,
+/// not only by the other instances.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClusterMember {
+    /// Identifier of the instance, unique within the cluster
+    pub node_id: String,
+    /// Base URL of the instance's HTTP and WebSocket listener, e.g. `ws://10.0.0.2:8080`
+    pub http_url: String,
+    /// Address of the instance's gRPC listener, e.g. `10.0.0.2:8081`
+    pub grpc_addr: String,
+}
+
+impl ClusterMember {
+    /// Creates a member.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - Identifier of the instance, unique within the cluster
+    /// * `http_url` - Base URL of the instance's HTTP and WebSocket listener
+    /// * `grpc_addr` - Address of the instance's gRPC listener
+    ///
+    /// # Returns
+    ///
+    /// A new `ClusterMember` instance
+    pub fn new(node_id: &str, http_url: &str, grpc_addr: &str) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            http_url: http_url.to_string(),
+            grpc_addr: grpc_addr.to_string(),
+        }
+    }
+}
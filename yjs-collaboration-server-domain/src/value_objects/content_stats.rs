@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+use crate::value_objects::gc_options::GcOptions;
+
+/// Size of a document's content, counted across its text roots.
+///
+/// Characters are Unicode scalar values, so a character outside the Basic
+/// Multilingual Plane counts once, whatever its encoded length; words are runs
+/// of non-whitespace characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentStats {
+    /// Number of characters
+    pub characters: usize,
+    /// Number of words
+    pub words: usize,
+}
+
+impl ContentStats {
+    /// Counts the characters and words of a text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to count
+    ///
+    /// # Returns
+    ///
+    /// The `ContentStats` of the text
+    pub fn of(text: &str) -> Self {
+        Self {
+            characters: text.chars().count(),
+            words: text.split_whitespace().count(),
+        }
+    }
+
+    /// Adds the counts of another text, such as another root of the same document.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The counts to add
+    ///
+    /// # Returns
+    ///
+    /// The combined `ContentStats`
+    pub fn combine(self, other: Self) -> Self {
+        Self {
+            characters: self.characters + other.characters,
+            words: self.words + other.words,
+        }
+    }
+}
+
+/// Size of a document's CRDT structure, which grows with every change made to
+/// the document, deleted or not.
+///
+/// Yjs counts changes in clock units, one per inserted character or element.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructureStats {
+    /// Number of clients that changed the document
+    pub clients: usize,
+    /// Number of clock units inserted into the document, deleted or not
+    pub items: u64,
+    /// Number of clock units deleted from the document, kept as tombstones
+    pub deleted_items: u64,
+}
+
+/// Statistics of a document, as served to integrations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentStats {
+    /// Size of the document's content
+    #[serde(flatten)]
+    pub content: ContentStats,
+    /// Approximate encoded size of the document in bytes
+    pub size_bytes: usize,
+    /// Maximum number of characters allowed by the document's policy, if limited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_characters: Option<usize>,
+    /// Size of the document's CRDT structure
+    #[serde(default)]
+    pub structure: StructureStats,
+    /// How the content deleted from the document is garbage collected
+    #[serde(default)]
+    pub gc: GcOptions,
+}
+
+/// Statistics of a document resident in the repository, as reported to operators.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResidentDocumentStats {
+    /// Identifier of the document
+    pub doc_id: String,
+    /// Approximate encoded size of the document in bytes
+    pub size_bytes: usize,
+    /// Number of subscribers to the document's updates, such as connected clients
+    pub subscribers: usize,
+    /// Sequence number of the last update broadcast for the document
+    pub sequence_number: u64,
+}
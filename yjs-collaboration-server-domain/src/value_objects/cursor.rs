@@ -0,0 +1,61 @@
+use yrs::{updates::decoder::Decode, StickyIndex};
+
+use crate::errors::{DomainError, DomainResult};
+
+/// Maximum size in bytes of an encoded cursor position.
+///
+/// A relative position holds an item ID or the name of a root type, so a
+/// larger one is not a cursor but a payload smuggled through the cursor channel.
+pub const MAX_CURSOR_POSITION_BYTES: usize = 256;
+
+/// A client's cursor or selection in a document.
+///
+/// Both ends are Yjs relative positions (`Y.encodeRelativePosition`), which
+/// stay attached to the same characters while other clients edit the
+/// document. A caret is a selection whose anchor and head are equal. Positions
+/// are relayed as received; decoding them only checks they are well formed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CursorPosition {
+    /// Encoded relative position where the selection started
+    pub anchor: Vec<u8>,
+    /// Encoded relative position where the selection ends, i.e. where the caret is
+    pub head: Vec<u8>,
+}
+
+impl CursorPosition {
+    /// Creates a cursor position from its encoded ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `anchor` - Encoded relative position where the selection started
+    /// * `head` - Encoded relative position of the caret
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CursorPosition)` - The position, if both ends are valid relative positions
+    /// * `Err(DomainError)` - `InvalidArgument` if an end is empty, too large, or not a relative
+    ///   position
+    pub fn new(anchor: Vec<u8>, head: Vec<u8>) -> DomainResult<Self> {
+        check_position("anchor", &anchor)?;
+        check_position("head", &head)?;
+        Ok(Self { anchor, head })
+    }
+}
+
+/// Checks that an end of a selection is a well-formed, reasonably sized relative position.
+fn check_position(name: &str, position: &[u8]) -> DomainResult<()> {
+    if position.is_empty() || position.len() > MAX_CURSOR_POSITION_BYTES {
+        return Err(DomainError::InvalidArgument(format!(
+            "The cursor {} must hold between 1 and {} bytes",
+            name, MAX_CURSOR_POSITION_BYTES
+        )));
+    }
+
+    StickyIndex::decode_v1(position).map_err(|e| {
+        DomainError::InvalidArgument(format!(
+            "The cursor {} is not a relative position: {}",
+            name, e
+        ))
+    })?;
+    Ok(())
+}
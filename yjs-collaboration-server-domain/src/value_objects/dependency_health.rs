@@ -0,0 +1,35 @@
+use serde::Serialize;
+
+use crate::errors::DomainResult;
+
+/// Health of a dependency the server needs to serve documents, as reported by readiness checks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DependencyHealth {
+    /// Name of the dependency, e.g. `storage` or `broker`
+    pub name: &'static str,
+    /// Whether the dependency answered its check
+    pub healthy: bool,
+    /// Why the dependency is unhealthy, if it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DependencyHealth {
+    /// Records the outcome of a dependency's check.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the dependency
+    /// * `result` - The outcome of its check
+    ///
+    /// # Returns
+    ///
+    /// The health of the dependency, carrying the error message if the check failed
+    pub fn of(name: &'static str, result: DomainResult<()>) -> Self {
+        Self {
+            name,
+            healthy: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+}
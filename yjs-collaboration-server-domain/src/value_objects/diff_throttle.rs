@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::errors::{DomainError, DomainResult};
+
+/// Limits applied when a client requests the updates it is missing.
+///
+/// A client with an ancient (or empty) state vector makes the server compute
+/// and transmit a diff as large as the whole document. Diffs above the chunk
+/// threshold are split into several self-contained updates delivered one after
+/// the other, so a single huge frame never monopolizes a connection, and each
+/// session may only have a bounded number of diffs in flight.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffThrottle {
+    /// Diffs larger than this many bytes are delivered in chunks (`0` = never)
+    pub chunk_threshold: usize,
+    /// Target size in bytes of each chunk
+    pub chunk_size: usize,
+    /// Maximum diffs computed or delivered concurrently per session (`0` = unlimited)
+    pub max_concurrent_diffs: usize,
+}
+
+impl Default for DiffThrottle {
+    /// Creates limits chunking diffs above 1 MiB into 256 KiB updates, with at
+    /// most two diffs in flight per session.
+    fn default() -> Self {
+        Self {
+            chunk_threshold: 1024 * 1024,
+            chunk_size: 256 * 1024,
+            max_concurrent_diffs: 2,
+        }
+    }
+}
+
+impl DiffThrottle {
+    /// Creates the diff limiter of a new session.
+    pub fn session_limiter(&self) -> DiffLimiter {
+        DiffLimiter::new(self.max_concurrent_diffs)
+    }
+}
+
+/// Per-session cap on concurrent diff computations.
+///
+/// A permit is held from the moment a diff is requested until its last chunk
+/// has been handed to the connection; requests beyond the cap are rejected
+/// instead of queued, so a client repeating a huge sync request cannot pile up
+/// work on the server.
+#[derive(Clone, Debug)]
+pub struct DiffLimiter {
+    permits: Arc<Semaphore>,
+}
+
+impl DiffLimiter {
+    /// Creates a limiter allowing the given number of concurrent diffs.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_concurrent_diffs` - Maximum diffs in flight (`0` = unlimited)
+    ///
+    /// # Returns
+    ///
+    /// A new `DiffLimiter` instance
+    pub fn new(max_concurrent_diffs: usize) -> Self {
+        let permits = match max_concurrent_diffs {
+            0 => Semaphore::MAX_PERMITS,
+            max => max,
+        };
+
+        Self {
+            permits: Arc::new(Semaphore::new(permits)),
+        }
+    }
+
+    /// Reserves a slot for a diff.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(OwnedSemaphorePermit)` - A permit to hold until the diff is delivered
+    /// * `Err(DomainError)` - `LimitExceeded` if the session already has the maximum diffs in
+    ///   flight
+    pub fn try_acquire(&self) -> DomainResult<OwnedSemaphorePermit> {
+        self.permits.clone().try_acquire_owned().map_err(|_| {
+            DomainError::LimitExceeded(
+                "Too many concurrent sync requests for this session".to_string(),
+            )
+        })
+    }
+}
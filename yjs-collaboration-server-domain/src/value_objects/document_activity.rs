@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Width of the time buckets document activity is aggregated into.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityGranularity {
+    /// One bucket per minute
+    Minute,
+    /// One bucket per hour
+    #[default]
+    Hour,
+}
+
+impl ActivityGranularity {
+    /// Returns the width of a bucket in seconds.
+    pub fn seconds(self) -> i64 {
+        match self {
+            Self::Minute => 60,
+            Self::Hour => 3600,
+        }
+    }
+}
+
+impl FromStr for ActivityGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minute" => Ok(Self::Minute),
+            "hour" => Ok(Self::Hour),
+            _ => Err(format!("Unknown activity granularity: {}", s)),
+        }
+    }
+}
+
+/// Number of buckets of each granularity retained per document.
+///
+/// Older buckets are discarded as new ones are opened, so the memory used by a
+/// document's activity stays bounded however long it is edited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActivityRetention {
+    /// Minute buckets retained (`0` = none)
+    pub minutes: usize,
+    /// Hour buckets retained (`0` = none)
+    pub hours: usize,
+}
+
+impl Default for ActivityRetention {
+    /// Retains the last hour by minute and the last week by hour.
+    fn default() -> Self {
+        Self {
+            minutes: 60,
+            hours: 7 * 24,
+        }
+    }
+}
+
+impl ActivityRetention {
+    /// Returns the number of buckets retained for a granularity.
+    pub fn buckets(&self, granularity: ActivityGranularity) -> usize {
+        match granularity {
+            ActivityGranularity::Minute => self.minutes,
+            ActivityGranularity::Hour => self.hours,
+        }
+    }
+}
+
+/// Activity of a document during a time bucket.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityBucket {
+    /// Start of the bucket, as Unix seconds
+    pub start: i64,
+    /// Updates applied by clients during the bucket
+    pub updates: u64,
+    /// Distinct clients that applied updates during the bucket
+    pub unique_editors: usize,
+}
@@ -0,0 +1,52 @@
+/// A change in the lifecycle of a document, published to integrations such as webhooks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocumentEvent {
+    /// A document was created, explicitly or by the first client opening it
+    Created {
+        /// Identifier of the document
+        doc_id: String,
+    },
+    /// A client update was applied to a document
+    Updated {
+        /// Identifier of the document
+        doc_id: String,
+        /// Identifier of the client that sent the update
+        source: String,
+    },
+    /// The update processors labelled a client update applied to a document
+    Annotated {
+        /// Identifier of the document
+        doc_id: String,
+        /// Identifier of the client that sent the update
+        source: String,
+        /// Labels of the update, prefixed with the name of the processor
+        labels: Vec<String>,
+    },
+    /// A document was deleted
+    Deleted {
+        /// Identifier of the document
+        doc_id: String,
+    },
+}
+
+impl DocumentEvent {
+    /// Returns the identifier of the document the event relates to.
+    pub fn doc_id(&self) -> &str {
+        match self {
+            Self::Created { doc_id }
+            | Self::Updated { doc_id, .. }
+            | Self::Annotated { doc_id, .. }
+            | Self::Deleted { doc_id } => doc_id,
+        }
+    }
+
+    /// Returns the name of the event, as delivered to integrations.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Created { .. } => "document.created",
+            Self::Updated { .. } => "document.updated",
+            Self::Annotated { .. } => "document.annotated",
+            Self::Deleted { .. } => "document.deleted",
+        }
+    }
+}
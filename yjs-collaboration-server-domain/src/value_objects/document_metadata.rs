@@ -0,0 +1,184 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DomainError, DomainResult};
+
+/// Maximum length of a tag in characters.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Maximum length of a document title or owner in characters.
+pub const MAX_TITLE_LENGTH: usize = 256;
+
+/// Operator-managed metadata attached to a document.
+///
+/// Metadata lives beside the document content and is never synchronized to
+/// clients; it lets operators name documents and group them (by project,
+/// team, ...) for reporting and retention rules. The timestamps are kept by
+/// the server as the document is created and modified.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentMetadata {
+    /// Human-readable title of the document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Identity of the user owning the document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Free-form tags, kept sorted and without duplicates
+    pub tags: BTreeSet<String>,
+    /// Time the document was created, as Unix seconds, or `None` if it was created before
+    /// timestamps were recorded and has not been modified since
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    /// Time the content of the document was last modified, as Unix seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    /// Time the document was moved to the archive tier, as Unix seconds, or `None` while it is
+    /// in the primary storage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<i64>,
+    /// Time the document was frozen read-only, e.g. once published, as Unix seconds, or `None`
+    /// while it may be edited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen_at: Option<i64>,
+}
+
+impl DocumentMetadata {
+    /// Returns whether the metadata holds nothing worth storing.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.owner.is_none()
+            && self.tags.is_empty()
+            && self.created_at.is_none()
+            && self.updated_at.is_none()
+            && self.archived_at.is_none()
+            && self.frozen_at.is_none()
+    }
+
+    /// Applies a change to the fields of the metadata edited by operators.
+    ///
+    /// Titles and owners are trimmed, and cleared when left empty; tags are
+    /// normalized with `normalize_tag`.
+    ///
+    /// # Arguments
+    ///
+    /// * `patch` - The change to apply
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the change was applied
+    /// * `Err(DomainError)` - `InvalidArgument` if a field is invalid, in which case the metadata
+    ///   is left unchanged
+    pub fn apply(&mut self, patch: MetadataPatch) -> DomainResult<()> {
+        let title = patch
+            .title
+            .map(|title| normalize_text("Title", title))
+            .transpose()?;
+        let owner = patch
+            .owner
+            .map(|owner| normalize_text("Owner", owner))
+            .transpose()?;
+        let tags = patch
+            .tags
+            .map(|tags| {
+                tags.iter()
+                    .map(|tag| Self::normalize_tag(tag))
+                    .collect::<DomainResult<BTreeSet<_>>>()
+            })
+            .transpose()?;
+
+        if let Some(title) = title {
+            self.title = title;
+        }
+        if let Some(owner) = owner {
+            self.owner = owner;
+        }
+        if let Some(tags) = tags {
+            self.tags = tags;
+        }
+        Ok(())
+    }
+
+    /// Records that the document was modified.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, as Unix seconds
+    pub fn touch(&mut self, now: i64) {
+        self.created_at.get_or_insert(now);
+        self.updated_at = Some(now);
+    }
+
+    /// Validates and normalizes a tag.
+    ///
+    /// Surrounding whitespace is trimmed; the tag must then be non-empty, at
+    /// most `MAX_TAG_LENGTH` characters long and free of whitespace and commas.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag as given by the operator
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The normalized tag
+    /// * `Err(DomainError)` - `InvalidArgument` if the tag is invalid
+    pub fn normalize_tag(tag: &str) -> DomainResult<String> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(DomainError::InvalidArgument(
+                "Tags must not be empty".to_string(),
+            ));
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(DomainError::InvalidArgument(format!(
+                "Tag '{}' is longer than {} characters",
+                tag, MAX_TAG_LENGTH
+            )));
+        }
+        if tag.chars().any(|c| c.is_whitespace() || c == ',') {
+            return Err(DomainError::InvalidArgument(format!(
+                "Tag '{}' must not contain whitespace or commas",
+                tag
+            )));
+        }
+        Ok(tag.to_string())
+    }
+}
+
+/// Change to the fields of a document's metadata edited by operators.
+///
+/// Fields left `None` are kept; `Some(None)` clears the title or owner.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetadataPatch {
+    /// The new title, if changed
+    pub title: Option<Option<String>>,
+    /// The new owner, if changed
+    pub owner: Option<Option<String>>,
+    /// The tags replacing the current ones, if changed
+    pub tags: Option<Vec<String>>,
+}
+
+/// Trims a title or owner, clearing it when left empty.
+///
+/// # Arguments
+///
+/// * `field` - Name of the field, for the error message
+/// * `value` - The value as given by the operator, `None` to clear it
+///
+/// # Returns
+///
+/// * `Ok(Option<String>)` - The normalized value
+/// * `Err(DomainError)` - `InvalidArgument` if the value is longer than `MAX_TITLE_LENGTH`
+fn normalize_text(field: &str, value: Option<String>) -> DomainResult<Option<String>> {
+    let Some(value) = value.map(|value| value.trim().to_string()) else {
+        return Ok(None);
+    };
+    if value.chars().count() > MAX_TITLE_LENGTH {
+        return Err(DomainError::InvalidArgument(format!(
+            "{} is longer than {} characters",
+            field, MAX_TITLE_LENGTH
+        )));
+    }
+    Ok(Some(value).filter(|value| !value.is_empty()))
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DomainError, DomainResult};
+
+/// Maximum length of a version label in characters.
+pub const MAX_LABEL_LENGTH: usize = 128;
+
+/// A version recorded in a document's timeline.
+///
+/// Each version is a snapshot of the document's complete state, encoded as a
+/// single Yjs update, so any Yjs client can load it as is. Versions are numbered
+/// from 1 in the order they are recorded; the numbers of pruned versions are
+/// never reused.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentVersion {
+    /// Number of the version within the document's timeline
+    pub version: u64,
+    /// Name given to the version, or `None` for a periodic snapshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Who recorded the version, or `None` for a periodic snapshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Time the version was recorded, as Unix seconds
+    pub created_at: i64,
+    /// Size in bytes of the encoded snapshot
+    pub size: usize,
+}
+
+impl DocumentVersion {
+    /// Returns whether the version was recorded periodically rather than named by a user.
+    pub fn is_periodic(&self) -> bool {
+        self.label.is_none()
+    }
+
+    /// Validates and normalizes a version label.
+    ///
+    /// Surrounding whitespace is trimmed; the label must then be non-empty and
+    /// at most `MAX_LABEL_LENGTH` characters long.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label as given by the user
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - The normalized label
+    /// * `Err(DomainError)` - `InvalidArgument` if the label is invalid
+    pub fn normalize_label(label: &str) -> DomainResult<String> {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(DomainError::InvalidArgument(
+                "Version labels must not be empty".to_string(),
+            ));
+        }
+        if label.chars().count() > MAX_LABEL_LENGTH {
+            return Err(DomainError::InvalidArgument(format!(
+                "Version labels must not be longer than {} characters",
+                MAX_LABEL_LENGTH
+            )));
+        }
+        Ok(label.to_string())
+    }
+}
+
+/// When versions are recorded and how many are kept per document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionPolicy {
+    /// Interval between two periodic snapshots of an updated document, or `None`
+    /// to record only named versions
+    pub interval: Option<Duration>,
+    /// Maximum versions kept per document, the oldest being pruned first (`0` = unlimited)
+    pub max_versions: usize,
+}
+
+impl Default for VersionPolicy {
+    /// Creates a policy recording only named versions and keeping the last 100 of each document.
+    fn default() -> Self {
+        Self {
+            interval: None,
+            max_versions: 100,
+        }
+    }
+}
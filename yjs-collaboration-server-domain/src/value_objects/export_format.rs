@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Format a document's content is exported to by the `DocumentExporter`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Every root as JSON, XML roots as ProseMirror-style node trees
+    #[default]
+    Json,
+    /// The text and XML roots as Markdown, other roots as JSON code blocks
+    Markdown,
+    /// The text of the text and XML roots, without formatting
+    Text,
+}
+
+impl ExportFormat {
+    /// Returns the media type of documents exported in this format.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Text => "text/plain; charset=utf-8",
+        }
+    }
+
+    /// Returns the file extension of documents exported in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Markdown => "md",
+            Self::Text => "txt",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "text" | "txt" => Ok(Self::Text),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Form in which a document is exported or imported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportMode {
+    /// The complete CRDT state, including the client IDs of every contributor
+    /// and the tombstones of deleted content
+    #[default]
+    Full,
+    /// A "clean room" copy: the current content re-materialized into a fresh
+    /// document under a single server client ID, without edit history
+    Clean,
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::value_objects::update_origin::{OriginKind, TransactionOrigin};
+
+/// An update applied to a document, exported to downstream pipelines.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedUpdate {
+    /// Identifier of the document
+    pub doc_id: String,
+    /// Sequence number of the broadcast carrying the update
+    pub sequence_number: u64,
+    /// Where the update comes from
+    pub origin: OriginKind,
+    /// Identifier of the sending client, or the source tagging an update no client sent
+    pub source: String,
+    /// Identity of the user behind the client, or `None` for a guest or the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// The binary-encoded update, in Yjs v1 encoding
+    pub payload: Vec<u8>,
+    /// Time the update was applied, as Unix milliseconds
+    pub applied_at: i64,
+}
+
+impl ExportedUpdate {
+    /// Creates the export of an update.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - Identifier of the document
+    /// * `sequence_number` - Sequence number of the broadcast carrying the update
+    /// * `origin` - Origin of the transaction that applied the update
+    /// * `payload` - The binary-encoded update
+    /// * `applied_at` - Time the update was applied, as Unix milliseconds
+    ///
+    /// # Returns
+    ///
+    /// A new `ExportedUpdate` instance
+    pub fn new(
+        doc_id: &str,
+        sequence_number: u64,
+        origin: &TransactionOrigin,
+        payload: &[u8],
+        applied_at: i64,
+    ) -> Self {
+        Self {
+            doc_id: doc_id.to_string(),
+            sequence_number,
+            origin: origin.kind,
+            source: origin.source.clone(),
+            user_id: origin.user_id.clone(),
+            payload: payload.to_vec(),
+            applied_at,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    errors::{DomainError, DomainResult},
+    value_objects::gc_options::GcOptions,
+};
+
+/// Separator between a namespace and the rest of a document identifier.
+pub const NAMESPACE_SEPARATOR: char = '/';
+
+/// Feature policy applied to the documents of a namespace.
+///
+/// A document's policy is resolved when it is first opened and then enforced by
+/// every service handling the document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeaturePolicy {
+    /// Whether the individual updates applied to a document are retained in its
+    /// update log; when disabled, persisted documents keep a compacted snapshot and
+    /// the few updates applied since it was last compacted
+    pub history_enabled: bool,
+    /// Whether clients without a user identity may read and edit documents
+    pub guest_access: bool,
+    /// Maximum encoded size of a document in bytes (`0` = unlimited)
+    pub max_document_size: usize,
+    /// Maximum number of characters across a document's text roots (`0` = unlimited)
+    pub max_document_characters: usize,
+    /// URLs notified about document lifecycle events
+    pub webhook_targets: Vec<String>,
+    /// Whether the server keeps an undo stack per client, so clients may undo and
+    /// redo their changes by message
+    pub undo_enabled: bool,
+    /// Whether content deleted from a document is garbage collected as soon as it is deleted
+    pub gc_enabled: bool,
+    /// Whether content deleted from a document is garbage collected before it is saved
+    pub gc_on_snapshot: bool,
+}
+
+impl Default for FeaturePolicy {
+    /// Creates a permissive policy: history retained, guests allowed, no size or content limit,
+    /// no server-side undo, deleted content garbage collected as Yjs does.
+    fn default() -> Self {
+        Self {
+            history_enabled: true,
+            guest_access: true,
+            max_document_size: 0,
+            max_document_characters: 0,
+            webhook_targets: Vec::new(),
+            undo_enabled: false,
+            gc_enabled: true,
+            gc_on_snapshot: false,
+        }
+    }
+}
+
+impl FeaturePolicy {
+    /// Returns how deleted content is garbage collected under this policy.
+    pub fn gc_options(&self) -> GcOptions {
+        GcOptions {
+            enabled: self.gc_enabled,
+            on_snapshot: self.gc_on_snapshot,
+        }
+    }
+
+    /// Checks whether a client may access a document under this policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Identity of the client, or `None` for a guest
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the client may access the document
+    /// * `Err(DomainError)` - `Unauthorized` if guests are not allowed and the client has no
+    ///   identity
+    pub fn authorize(&self, user_id: Option<&str>) -> DomainResult<()> {
+        match user_id {
+            Some(user_id) if !user_id.is_empty() => Ok(()),
+            _ if self.guest_access => Ok(()),
+            _ => Err(DomainError::Unauthorized(
+                "Guest access is disabled for this document".to_string(),
+            )),
+        }
+    }
+
+    /// Checks whether a document may grow by the given number of bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_size` - Current encoded size of the document
+    /// * `additional` - Size of the update about to be applied
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document stays within the size limit
+    /// * `Err(DomainError)` - `LimitExceeded` if the update would exceed the limit
+    pub fn check_size(&self, current_size: usize, additional: usize) -> DomainResult<()> {
+        if self.max_document_size > 0
+            && current_size.saturating_add(additional) > self.max_document_size
+        {
+            return Err(DomainError::LimitExceeded(format!(
+                "Document would exceed the maximum size of {} bytes",
+                self.max_document_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks whether a document just grew past the quota warning level, 90% of
+    /// its maximum size.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous_size` - Size of the document before an update
+    /// * `current_size` - Size of the document after the update
+    ///
+    /// # Returns
+    ///
+    /// `true` only for the update crossing the warning level, so the warning is
+    /// raised once
+    pub fn crosses_quota_warning(&self, previous_size: usize, current_size: usize) -> bool {
+        let warning_level = self.max_document_size / 10 * 9;
+        self.max_document_size > 0 && previous_size < warning_level && current_size >= warning_level
+    }
+
+    /// Checks whether an update may take a document past its character limit.
+    ///
+    /// An update inserts at most one character per byte, so only updates
+    /// larger than the remaining room need their effect on the content counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_characters` - Current number of characters of the document
+    /// * `additional` - Size of the update about to be applied
+    ///
+    /// # Returns
+    ///
+    /// `true` if the content of the updated document must be counted before applying the update
+    pub fn may_exceed_characters(&self, current_characters: usize, additional: usize) -> bool {
+        self.max_document_characters > 0
+            && current_characters.saturating_add(additional) > self.max_document_characters
+    }
+
+    /// Checks whether a document may reach the given number of characters.
+    ///
+    /// An update that shrinks the content is always accepted, so a document over
+    /// its limit, e.g. after the limit was lowered, can still be trimmed.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_characters` - Current number of characters of the document
+    /// * `updated_characters` - Number of characters once the update is applied
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the document stays within the character limit or shrinks
+    /// * `Err(DomainError)` - `LimitExceeded` if the update would exceed the limit
+    pub fn check_characters(
+        &self,
+        current_characters: usize,
+        updated_characters: usize,
+    ) -> DomainResult<()> {
+        if self.max_document_characters > 0
+            && updated_characters > self.max_document_characters
+            && updated_characters > current_characters
+        {
+            return Err(DomainError::LimitExceeded(format!(
+                "Document would exceed the maximum of {} characters",
+                self.max_document_characters
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks whether a document just grew past the content warning level, 90%
+    /// of its maximum number of characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous_characters` - Number of characters before an update
+    /// * `current_characters` - Number of characters after the update
+    ///
+    /// # Returns
+    ///
+    /// `true` only for the update crossing the warning level, so the warning is
+    /// raised once
+    pub fn crosses_character_warning(
+        &self,
+        previous_characters: usize,
+        current_characters: usize,
+    ) -> bool {
+        let warning_level = self.max_document_characters / 10 * 9;
+        self.max_document_characters > 0
+            && previous_characters < warning_level
+            && current_characters >= warning_level
+    }
+}
+
+/// Feature policies of every namespace.
+///
+/// A document's namespace is the part of its identifier before the first `/`
+/// (e.g. `acme` for `acme/roadmap`). Documents outside a configured namespace
+/// use the default policy.
+#[derive(Clone, Debug, Default)]
+pub struct FeaturePolicies {
+    default: Arc<FeaturePolicy>,
+    namespaces: HashMap<String, Arc<FeaturePolicy>>,
+}
+
+impl FeaturePolicies {
+    /// Creates a policy table applying the given policy to every document.
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - The policy of documents outside a configured namespace
+    ///
+    /// # Returns
+    ///
+    /// A new `FeaturePolicies` instance without namespace overrides
+    pub fn new(default: FeaturePolicy) -> Self {
+        Self {
+            default: Arc::new(default),
+            namespaces: HashMap::new(),
+        }
+    }
+
+    /// Sets the policy of a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The namespace, without the trailing separator
+    /// * `policy` - The policy applied to the namespace's documents
+    ///
+    /// # Returns
+    ///
+    /// The `FeaturePolicies` with the namespace policy added
+    pub fn with_namespace(mut self, namespace: &str, policy: FeaturePolicy) -> Self {
+        self.namespaces
+            .insert(namespace.to_string(), Arc::new(policy));
+        self
+    }
+
+    /// Returns the namespace of a document identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A document identifier
+    ///
+    /// # Returns
+    ///
+    /// The namespace, or `None` if the identifier has no namespace prefix
+    pub fn namespace_of(doc_id: &str) -> Option<&str> {
+        doc_id
+            .split_once(NAMESPACE_SEPARATOR)
+            .map(|(namespace, _)| namespace)
+            .filter(|namespace| !namespace.is_empty())
+    }
+
+    /// Resolves the policy of a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A document identifier
+    ///
+    /// # Returns
+    ///
+    /// The policy of the document's namespace, or the default policy
+    pub fn resolve(&self, doc_id: &str) -> Arc<FeaturePolicy> {
+        Self::namespace_of(doc_id)
+            .and_then(|namespace| self.namespaces.get(namespace))
+            .unwrap_or(&self.default)
+            .clone()
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// How the content deleted from a document is garbage collected.
+///
+/// Deleted content leaves tombstones behind in a Yjs document. Collecting them
+/// drops the content they still carry, keeping only their identifiers, so a
+/// document edited for a long time does not keep every character ever typed in
+/// memory. Without collection, deleted content can be restored, e.g. to render
+/// earlier versions of the document, at the cost of memory growing with every
+/// deletion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GcOptions {
+    /// Whether deleted content is collected as soon as each change is applied
+    pub enabled: bool,
+    /// Whether deleted content is collected before the document is saved, even
+    /// when it is otherwise kept in memory
+    pub on_snapshot: bool,
+}
+
+impl Default for GcOptions {
+    /// Creates options collecting deleted content as soon as it is deleted, as Yjs does.
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_snapshot: false,
+        }
+    }
+}
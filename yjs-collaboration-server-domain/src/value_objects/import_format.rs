@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Format of the content a document is seeded from by the `DocumentImporter`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// Plain text, seeding a single text root
+    #[default]
+    Text,
+    /// A JSON object mapping root names to their content, as exported in JSON
+    Json,
+}
+
+impl ImportFormat {
+    /// Infers the format of a request body from its media type.
+    ///
+    /// # Arguments
+    ///
+    /// * `content_type` - Value of the `Content-Type` header
+    ///
+    /// # Returns
+    ///
+    /// `Json` for JSON media types, `Text` otherwise
+    pub fn from_content_type(content_type: &str) -> Self {
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        if media_type.eq_ignore_ascii_case("application/json") || media_type.ends_with("+json") {
+            Self::Json
+        } else {
+            Self::Text
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" | "txt" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown import format: {}", s)),
+        }
+    }
+}
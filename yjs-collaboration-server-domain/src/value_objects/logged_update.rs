@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// Position of an entry in a document's persisted update log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogPosition {
+    /// The snapshot the logged updates were compacted into
+    Snapshot,
+    /// An update appended since the last compaction, with its sequence number
+    Update(u64),
+}
+
+impl fmt::Display for LogPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Snapshot => f.write_str("snapshot"),
+            Self::Update(sequence) => write!(f, "update #{}", sequence),
+        }
+    }
+}
+
+/// Entry of a document's persisted update log, as stored.
+///
+/// Entries are read for inspection, without compacting the log, and a stored
+/// value that cannot be decompressed is returned as an error rather than
+/// failing the whole read, so the corrupted entry can be pinpointed.
+#[derive(Clone, Debug)]
+pub struct LoggedUpdate {
+    /// Position of the entry in the log
+    pub position: LogPosition,
+    /// The binary-encoded update, or why the stored value could not be decompressed
+    pub data: Result<Vec<u8>, String>,
+}
@@ -14,7 +14,9 @@ use sonic_rs::Value;
 /// - Optional Base64-encoded binary update data for document changes
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ClientMessage {
-    /// Identifier of the document this message relates to
+    /// Identifier of the document this message relates to, empty if the message
+    /// relates to the document its connection is bound to
+    #[serde(default)]
     pub doc_id: String,
 
     /// Type of message being sent (e.g., "sync", "update", "sv")
@@ -49,3 +51,135 @@ pub struct ServerMessage {
     /// Base64-encoded binary update or state vector
     pub update: Option<String>,
 }
+
+impl ServerMessage {
+    /// Builds the message delivering a notice to a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - The notice to deliver
+    ///
+    /// # Returns
+    ///
+    /// A `notice` message carrying the notice as its data
+    pub fn notice(notice: &Notice) -> Self {
+        Self {
+            message_type: "notice".to_string(),
+            data: sonic_rs::to_value(notice).ok(),
+            update: None,
+        }
+    }
+}
+
+/// Kind of a server notice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeKind {
+    /// A maintenance window is scheduled
+    Maintenance,
+    /// The document has been locked against edits
+    DocumentLocked,
+    /// The document is close to its maximum size
+    QuotaWarning,
+    /// Clients should reconnect to the server at the notice's `redirect_url`
+    Redirect,
+    /// An operator changed the user's permission on the document
+    PermissionChanged,
+    /// The document is closing, e.g. for a migration; its clients are
+    /// disconnected at the notice's `scheduled_at`
+    DocumentClosing,
+    /// Any other announcement
+    General,
+}
+
+/// Severity of a server notice, which products may map to banner styles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoticeSeverity {
+    /// Informational
+    Info,
+    /// The user should take note
+    Warning,
+    /// The user's work is about to be affected
+    Critical,
+}
+
+/// Structured notice sent by the server to connected clients.
+///
+/// Notices are not part of document synchronization; they let products show
+/// banners (maintenance scheduled, document locked, quota nearly exceeded)
+/// over the existing collaboration connections. A notice concerning a document
+/// is delivered to the connections collaborating on it, and a notice without a
+/// document to every connection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notice {
+    /// What the notice is about
+    pub kind: NoticeKind,
+    /// How prominently the notice should be shown
+    pub severity: NoticeSeverity,
+    /// Human readable message
+    pub message: String,
+    /// Document the notice concerns, or `None` for every connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_id: Option<String>,
+    /// Time the announced event takes place, as Unix seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_at: Option<i64>,
+    /// Server clients should reconnect to, for redirect notices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+}
+
+impl Notice {
+    /// Creates a notice addressed to every connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the notice is about
+    /// * `severity` - How prominently the notice should be shown
+    /// * `message` - Human readable message
+    ///
+    /// # Returns
+    ///
+    /// A new `Notice` instance
+    pub fn new(kind: NoticeKind, severity: NoticeSeverity, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            severity,
+            message: message.into(),
+            doc_id: None,
+            scheduled_at: None,
+            redirect_url: None,
+        }
+    }
+
+    /// Addresses the notice to the connections collaborating on a document.
+    pub fn with_document(mut self, doc_id: &str) -> Self {
+        self.doc_id = Some(doc_id.to_string());
+        self
+    }
+
+    /// Sets the time the announced event takes place, as Unix seconds.
+    pub fn with_scheduled_at(mut self, scheduled_at: i64) -> Self {
+        self.scheduled_at = Some(scheduled_at);
+        self
+    }
+
+    /// Sets the server clients should reconnect to.
+    pub fn with_redirect_url(mut self, redirect_url: &str) -> Self {
+        self.redirect_url = Some(redirect_url.to_string());
+        self
+    }
+
+    /// Returns whether the notice must be delivered to a connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_subscribed` - Whether the connection collaborates on a given document
+    pub fn is_addressed_to(&self, is_subscribed: impl Fn(&str) -> bool) -> bool {
+        match self.doc_id.as_deref() {
+            Some(doc_id) => is_subscribed(doc_id),
+            None => true,
+        }
+    }
+}
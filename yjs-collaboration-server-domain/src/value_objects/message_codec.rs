@@ -0,0 +1,336 @@
+use std::{fmt, str::FromStr};
+
+use base64::Engine;
+
+use crate::{
+    errors::{DomainError, DomainResult},
+    services::document_service::SyncResponse,
+    value_objects::message::{ClientMessage, ServerMessage},
+};
+
+/// A message encoded for the wire, sent as a text or a binary frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncodedMessage {
+    /// UTF-8 text, for text-based encodings
+    Text(String),
+    /// Raw bytes, for binary encodings
+    Binary(Vec<u8>),
+}
+
+/// Wire format of the messages exchanged with clients.
+///
+/// Transport adapters encode the messages they send and decode those they
+/// receive through the codec negotiated for each connection, so supporting a
+/// new wire format only requires implementing this trait.
+pub trait MessageCodec: Send + Sync {
+    /// Encodes a message sent to a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(EncodedMessage)` - The encoded message
+    /// * `Err(DomainError::Internal)` - If the message cannot be encoded
+    fn encode(&self, message: &ServerMessage) -> DomainResult<EncodedMessage>;
+
+    /// Encodes the response to a client's sync request.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The sync response
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(EncodedMessage)` - The encoded response
+    /// * `Err(DomainError::Internal)` - If the response cannot be encoded
+    fn encode_sync_response(&self, response: &SyncResponse) -> DomainResult<EncodedMessage>;
+
+    /// Decodes a message received from a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The payload of the frame, text or binary
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ClientMessage)` - The decoded message
+    /// * `Err(DomainError::InvalidArgument)` - If the frame is malformed
+    fn decode(&self, frame: &[u8]) -> DomainResult<ClientMessage>;
+}
+
+/// Encoding of the messages of a connection, negotiated by the client.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MessageEncoding {
+    /// JSON text frames with Base64-encoded payloads
+    #[default]
+    Json,
+    /// MessagePack binary frames with the fields of the JSON encoding
+    MessagePack,
+    /// Compact binary frames with raw payloads, see [`BinaryCodec`]
+    Binary,
+    /// Binary frames carrying the `ClientMessage` and `ServerMessage` of the
+    /// gRPC schema, so every transport shares a single message definition
+    Protobuf,
+}
+
+impl MessageEncoding {
+    /// Returns the codec encoding and decoding the messages.
+    ///
+    /// # Returns
+    ///
+    /// The codec, or `None` for [`MessageEncoding::Protobuf`], whose codec is
+    /// provided by the transport adapters compiling the gRPC schema
+    pub fn codec(self) -> Option<&'static dyn MessageCodec> {
+        match self {
+            Self::Json => Some(&JsonCodec),
+            Self::MessagePack => Some(&MessagePackCodec),
+            Self::Binary => Some(&BinaryCodec),
+            Self::Protobuf => None,
+        }
+    }
+}
+
+impl fmt::Display for MessageEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "json",
+            Self::MessagePack => "msgpack",
+            Self::Binary => "binary",
+            Self::Protobuf => "protobuf",
+        })
+    }
+}
+
+impl FromStr for MessageEncoding {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::MessagePack),
+            "binary" => Ok(Self::Binary),
+            "protobuf" => Ok(Self::Protobuf),
+            _ => Err(DomainError::InvalidArgument(format!(
+                "Unknown message encoding '{}', expected json, msgpack, binary or protobuf",
+                s
+            ))),
+        }
+    }
+}
+
+/// JSON encoding, the protocol's original wire format.
+pub struct JsonCodec;
+
+impl MessageCodec for JsonCodec {
+    fn encode(&self, message: &ServerMessage) -> DomainResult<EncodedMessage> {
+        sonic_rs::to_string(message)
+            .map(EncodedMessage::Text)
+            .map_err(|e| DomainError::Internal(format!("Failed to encode message: {}", e)))
+    }
+
+    fn encode_sync_response(&self, response: &SyncResponse) -> DomainResult<EncodedMessage> {
+        sonic_rs::to_string(response)
+            .map(EncodedMessage::Text)
+            .map_err(|e| DomainError::Internal(format!("Failed to encode sync response: {}", e)))
+    }
+
+    fn decode(&self, frame: &[u8]) -> DomainResult<ClientMessage> {
+        sonic_rs::from_slice(frame)
+            .map_err(|e| DomainError::InvalidArgument(format!("Invalid JSON message: {}", e)))
+    }
+}
+
+/// MessagePack encoding, mirroring the fields of the JSON encoding as a map.
+pub struct MessagePackCodec;
+
+impl MessageCodec for MessagePackCodec {
+    fn encode(&self, message: &ServerMessage) -> DomainResult<EncodedMessage> {
+        rmp_serde::to_vec_named(message)
+            .map(EncodedMessage::Binary)
+            .map_err(|e| DomainError::Internal(format!("Failed to encode message: {}", e)))
+    }
+
+    fn encode_sync_response(&self, response: &SyncResponse) -> DomainResult<EncodedMessage> {
+        rmp_serde::to_vec_named(response)
+            .map(EncodedMessage::Binary)
+            .map_err(|e| DomainError::Internal(format!("Failed to encode sync response: {}", e)))
+    }
+
+    fn decode(&self, frame: &[u8]) -> DomainResult<ClientMessage> {
+        rmp_serde::from_slice(frame).map_err(|e| {
+            DomainError::InvalidArgument(format!("Invalid MessagePack message: {}", e))
+        })
+    }
+}
+
+/// Leading byte of a binary frame carrying a message.
+const BINARY_MESSAGE: u8 = 0;
+
+/// Leading byte of a binary frame carrying a sync response.
+const BINARY_SYNC_RESPONSE: u8 = 1;
+
+/// Flag set when a binary frame carries an update.
+const HAS_UPDATE: u8 = 1;
+
+/// Flag set when a binary frame carries JSON data.
+const HAS_DATA: u8 = 2;
+
+/// Flag set when a binary sync response carries a state vector.
+const HAS_STATE_VECTOR: u8 = 4;
+
+/// Compact binary encoding, carrying updates as raw bytes instead of Base64.
+///
+/// Integers are big-endian. A message is laid out as
+/// `0x00 | flags: u8 | type length: u8 | type`, followed, for client messages
+/// only, by `doc_id length: u16 | doc_id`, then by `update length: u32 | update`
+/// if the `0x01` flag is set and by the JSON `data` up to the end of the frame
+/// if the `0x02` flag is set. A sync response is laid out as
+/// `0x01 | flags: u8 | sequence_number: u64`, followed by
+/// `update length: u32 | update` if the `0x01` flag is set and by
+/// `state vector length: u32 | state vector` if the `0x04` flag is set.
+pub struct BinaryCodec;
+
+impl MessageCodec for BinaryCodec {
+    fn encode(&self, message: &ServerMessage) -> DomainResult<EncodedMessage> {
+        let message_type = message.message_type.as_bytes();
+        let type_length = u8::try_from(message_type.len()).map_err(|_| {
+            DomainError::Internal(format!(
+                "Message type '{}' is too long",
+                message.message_type
+            ))
+        })?;
+        let update = message
+            .update
+            .as_deref()
+            .map(|update| base64::engine::general_purpose::STANDARD.decode(update))
+            .transpose()
+            .map_err(|e| DomainError::Internal(format!("Invalid Base64 update: {}", e)))?;
+        let data = message
+            .data
+            .as_ref()
+            .map(sonic_rs::to_vec)
+            .transpose()
+            .map_err(|e| DomainError::Internal(format!("Failed to encode message data: {}", e)))?;
+
+        let mut flags = 0;
+        if update.is_some() {
+            flags |= HAS_UPDATE;
+        }
+        if data.is_some() {
+            flags |= HAS_DATA;
+        }
+
+        let mut frame = vec![BINARY_MESSAGE, flags, type_length];
+        frame.extend_from_slice(message_type);
+        if let Some(update) = update {
+            put_bytes(&mut frame, &update)?;
+        }
+        if let Some(data) = data {
+            frame.extend_from_slice(&data);
+        }
+        Ok(EncodedMessage::Binary(frame))
+    }
+
+    fn encode_sync_response(&self, response: &SyncResponse) -> DomainResult<EncodedMessage> {
+        let mut flags = 0;
+        if response.update.is_some() {
+            flags |= HAS_UPDATE;
+        }
+        if response.state_vector.is_some() {
+            flags |= HAS_STATE_VECTOR;
+        }
+
+        let mut frame = vec![BINARY_SYNC_RESPONSE, flags];
+        frame.extend_from_slice(&response.sequence_number.to_be_bytes());
+        if let Some(update) = &response.update {
+            put_bytes(&mut frame, update)?;
+        }
+        if let Some(state_vector) = &response.state_vector {
+            put_bytes(&mut frame, state_vector)?;
+        }
+        Ok(EncodedMessage::Binary(frame))
+    }
+
+    fn decode(&self, frame: &[u8]) -> DomainResult<ClientMessage> {
+        let mut reader = FrameReader(frame);
+        if reader.u8()? != BINARY_MESSAGE {
+            return Err(DomainError::InvalidArgument(
+                "Binary frame does not carry a message".to_string(),
+            ));
+        }
+        let flags = reader.u8()?;
+        let type_length = usize::from(reader.u8()?);
+        let message_type = reader.string(type_length)?;
+        let doc_length = usize::from(reader.u16()?);
+        let doc_id = reader.string(doc_length)?;
+        let update = if flags & HAS_UPDATE != 0 {
+            let length = reader.u32()? as usize;
+            Some(base64::engine::general_purpose::STANDARD.encode(reader.take(length)?))
+        } else {
+            None
+        };
+        let data = if flags & HAS_DATA != 0 {
+            Some(sonic_rs::from_slice(reader.0).map_err(|e| {
+                DomainError::InvalidArgument(format!("Invalid message data: {}", e))
+            })?)
+        } else {
+            None
+        };
+
+        Ok(ClientMessage {
+            doc_id,
+            message_type,
+            data,
+            update,
+        })
+    }
+}
+
+/// Appends a length-prefixed byte string to a binary frame.
+fn put_bytes(frame: &mut Vec<u8>, bytes: &[u8]) -> DomainResult<()> {
+    let length = u32::try_from(bytes.len())
+        .map_err(|_| DomainError::Internal("Payload exceeds 4 GiB".to_string()))?;
+    frame.extend_from_slice(&length.to_be_bytes());
+    frame.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Cursor over the remaining bytes of a binary frame.
+struct FrameReader<'a>(&'a [u8]);
+
+impl<'a> FrameReader<'a> {
+    /// Takes the next `length` bytes.
+    fn take(&mut self, length: usize) -> DomainResult<&'a [u8]> {
+        if self.0.len() < length {
+            return Err(DomainError::InvalidArgument(
+                "Binary frame is truncated".to_string(),
+            ));
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> DomainResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> DomainResult<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> DomainResult<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Takes the next `length` bytes as a UTF-8 string.
+    fn string(&mut self, length: usize) -> DomainResult<String> {
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| {
+            DomainError::InvalidArgument("Binary frame carries invalid UTF-8".to_string())
+        })
+    }
+}
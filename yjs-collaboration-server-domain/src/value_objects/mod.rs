@@ -1,2 +1,34 @@
+pub mod access_role;
+pub mod audit_entry;
+pub mod broadcast_coalescing;
+pub mod cluster_member;
+pub mod content_stats;
+pub mod cursor;
+pub mod dependency_health;
+pub mod diff_throttle;
+pub mod document_event;
+pub mod document_activity;
+pub mod document_metadata;
+pub mod document_version;
+pub mod export_format;
+pub mod exported_update;
+pub mod export_mode;
+pub mod feature_policy;
+pub mod gc_options;
+pub mod import_format;
+pub mod logged_update;
 pub mod message;
-
+pub mod message_codec;
+pub mod payload_dictionary;
+pub mod search_hit;
+pub mod shared_edit;
+pub mod subdocument;
+pub mod sync_protocol;
+pub mod tenant;
+pub mod text_insertion;
+pub mod undo_action;
+pub mod update_changes;
+pub mod update_encoding;
+pub mod update_frame;
+pub mod update_limits;
+pub mod update_origin;
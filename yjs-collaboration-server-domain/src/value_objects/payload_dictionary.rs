@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+/// Number of updates of a document sampled before a dictionary is trained.
+const DEFAULT_TRAIN_AFTER_UPDATES: usize = 1000;
+
+/// Default maximum size of a trained dictionary in bytes.
+const DEFAULT_MAX_DICTIONARY_SIZE: usize = 16 * 1024;
+
+/// Default size below which relayed payloads are sent uncompressed.
+const DEFAULT_MIN_PAYLOAD_SIZE: usize = 32;
+
+/// Default maximum size of the updates kept as training samples.
+const DEFAULT_MAX_SAMPLE_SIZE: usize = 16 * 1024;
+
+/// A compression dictionary trained on the updates of a document.
+///
+/// Dictionaries are numbered from 1 across the server, so a client holding a
+/// dictionary can tell when it has been replaced by a newer one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayloadDictionary {
+    /// Identifier of the dictionary, never `0`
+    pub id: u32,
+    /// Content of the dictionary, shipped to clients before the first payload using it
+    pub data: Arc<[u8]>,
+}
+
+/// A payload compressed with a document's dictionary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressedPayload {
+    /// The dictionary the payload was compressed with
+    pub dictionary: Arc<PayloadDictionary>,
+    /// The compressed payload
+    pub data: Vec<u8>,
+}
+
+/// When dictionaries are trained and which payloads they compress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DictionarySettings {
+    /// Updates of a document sampled before its dictionary is (re)trained
+    pub train_after_updates: usize,
+    /// Maximum size of a dictionary in bytes
+    pub max_dictionary_size: usize,
+    /// Size below which payloads are sent uncompressed
+    pub min_payload_size: usize,
+    /// Size above which updates are not kept as training samples
+    pub max_sample_size: usize,
+}
+
+impl Default for DictionarySettings {
+    /// Creates default settings: a 16 KiB dictionary per document, retrained every 1000 updates.
+    fn default() -> Self {
+        Self {
+            train_after_updates: DEFAULT_TRAIN_AFTER_UPDATES,
+            max_dictionary_size: DEFAULT_MAX_DICTIONARY_SIZE,
+            min_payload_size: DEFAULT_MIN_PAYLOAD_SIZE,
+            max_sample_size: DEFAULT_MAX_SAMPLE_SIZE,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Default number of search hits returned.
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Maximum number of search hits returned.
+pub const MAX_SEARCH_LIMIT: usize = 100;
+
+/// A document matching a full-text search.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// Identifier of the matching document
+    pub doc_id: String,
+    /// Relevance of the document to the query; only meaningful relative to the
+    /// other hits of the same search
+    pub score: f32,
+    /// Excerpt of the document's content around the matches, with the matched
+    /// terms wrapped in `<b>` tags and the rest HTML-escaped
+    pub snippet: String,
+}
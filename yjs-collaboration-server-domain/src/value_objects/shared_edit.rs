@@ -0,0 +1,114 @@
+use yrs::Any;
+
+use crate::errors::{DomainError, DomainResult};
+
+/// Map root written by the REST API when the request does not name one.
+pub const DEFAULT_MAP_ROOT: &str = "map";
+
+/// A change the server writes into a shared type of a document on behalf of a
+/// backend service, without a Yjs client.
+///
+/// Values are plain JSON-like values: objects and arrays are stored as
+/// embedded values, not as nested shared types.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SharedEdit {
+    /// Writes a value under a key of a map root, replacing the previous one
+    SetMapEntry {
+        /// Name of the map root, created if missing
+        root: String,
+        /// Key the value is written under
+        key: String,
+        /// The value
+        value: Any,
+    },
+    /// Removes a key from a map root
+    RemoveMapEntry {
+        /// Name of the map root
+        root: String,
+        /// Key to remove
+        key: String,
+    },
+    /// Inserts values into an array root
+    InsertArrayItems {
+        /// Name of the array root, created if missing
+        root: String,
+        /// Position the values are inserted at, or `None` to append them
+        index: Option<u32>,
+        /// The values, in order
+        values: Vec<Any>,
+    },
+    /// Replaces a range of a text root with other text
+    ReplaceText {
+        /// Name of the text root
+        root: String,
+        /// Offset of the range, in UTF-8 bytes
+        index: u32,
+        /// Length of the range, in UTF-8 bytes
+        len: u32,
+        /// The text written in place of the range
+        text: String,
+    },
+}
+
+impl SharedEdit {
+    /// Creates the edit writing a JSON value under a key of a map root.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - Name of the map root
+    /// * `key` - Key the value is written under
+    /// * `json` - The value, as JSON
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SharedEdit)` - The `SetMapEntry` edit
+    /// * `Err(DomainError)` - `InvalidArgument` if the value is not valid JSON
+    pub fn set_map_entry(root: &str, key: &str, json: &str) -> DomainResult<Self> {
+        Ok(Self::SetMapEntry {
+            root: root.to_string(),
+            key: key.to_string(),
+            value: parse_value(json)?,
+        })
+    }
+
+    /// Creates the edit inserting the items of a JSON array into an array root.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - Name of the array root
+    /// * `index` - Position the items are inserted at, or `None` to append them
+    /// * `json` - The items, as a JSON array
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SharedEdit)` - The `InsertArrayItems` edit
+    /// * `Err(DomainError)` - `InvalidArgument` if the items are not a JSON array
+    pub fn insert_array_items(root: &str, index: Option<u32>, json: &str) -> DomainResult<Self> {
+        let Any::Array(values) = parse_value(json)? else {
+            return Err(DomainError::InvalidArgument(
+                "The inserted items must be a JSON array".to_string(),
+            ));
+        };
+        Ok(Self::InsertArrayItems {
+            root: root.to_string(),
+            index,
+            values: values.to_vec(),
+        })
+    }
+
+    /// Returns the name of the root the edit applies to.
+    pub fn root(&self) -> &str {
+        match self {
+            Self::SetMapEntry { root, .. }
+            | Self::RemoveMapEntry { root, .. }
+            | Self::InsertArrayItems { root, .. }
+            | Self::ReplaceText { root, .. } => root,
+        }
+    }
+}
+
+/// Parses a JSON value written into a shared type.
+fn parse_value(json: &str) -> DomainResult<Any> {
+    sonic_rs::from_str(json)
+        .map_err(|e| DomainError::InvalidArgument(format!("Invalid JSON value: {}", e)))
+}
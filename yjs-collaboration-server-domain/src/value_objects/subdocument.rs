@@ -0,0 +1,56 @@
+/// Separator between a parent document's identifier and the GUID of one of its subdocuments.
+pub const SUBDOCUMENT_SEPARATOR: char = '#';
+
+/// Builds the identifier a subdocument is served under.
+///
+/// Yjs subdocuments are identified by a GUID, unique within their parent, so
+/// they are served as documents of their own, named after their parent and
+/// their GUID (e.g. `acme/roadmap#<guid>`). They live in their parent's
+/// namespace and are synchronized lazily, when a client first asks for them.
+///
+/// # Arguments
+///
+/// * `parent_id` - Identifier of the parent document
+/// * `guid` - GUID of the subdocument within the parent
+///
+/// # Returns
+///
+/// The subdocument's identifier
+pub fn subdocument_id(parent_id: &str, guid: &str) -> String {
+    format!("{}{}{}", parent_id, SUBDOCUMENT_SEPARATOR, guid)
+}
+
+/// Splits a subdocument identifier into its parent's identifier and its GUID.
+///
+/// Subdocuments may be nested, so the parent is the part before the last separator.
+///
+/// # Arguments
+///
+/// * `doc_id` - A document identifier
+///
+/// # Returns
+///
+/// The parent's identifier and the GUID, or `None` if the identifier is not a subdocument's
+pub fn split_subdocument_id(doc_id: &str) -> Option<(&str, &str)> {
+    doc_id
+        .rsplit_once(SUBDOCUMENT_SEPARATOR)
+        .filter(|(parent_id, guid)| !parent_id.is_empty() && !guid.is_empty())
+}
+
+/// Returns the identifier of the top-level document a subdocument belongs to.
+///
+/// Subdocuments are served with the permissions of their top-level document.
+///
+/// # Arguments
+///
+/// * `doc_id` - A document identifier
+///
+/// # Returns
+///
+/// The top-level document's identifier, or `doc_id` itself if it is not a subdocument's
+pub fn root_document_id(doc_id: &str) -> &str {
+    match doc_id.split_once(SUBDOCUMENT_SEPARATOR) {
+        Some((root_id, _)) if !root_id.is_empty() => root_id,
+        _ => doc_id,
+    }
+}
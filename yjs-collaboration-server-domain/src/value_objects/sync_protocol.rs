@@ -0,0 +1,172 @@
+use yrs::{
+    encoding::write::Write,
+    sync::{
+        protocol::{MSG_SYNC, MSG_SYNC_STEP_1, MSG_SYNC_STEP_2, MSG_SYNC_UPDATE},
+        Message, SyncMessage,
+    },
+    updates::{
+        decoder::Decode,
+        encoder::{Encode, Encoder, EncoderV1},
+    },
+};
+
+use crate::{
+    errors::{DomainError, DomainResult},
+    value_objects::{
+        message::Notice,
+        payload_dictionary::{CompressedPayload, PayloadDictionary},
+    },
+};
+
+/// Outer message type of the frames shipping a compression dictionary.
+///
+/// Custom message types are only sent to clients that negotiated dictionary
+/// compression; stock `y-websocket` clients never receive them.
+pub const MSG_PAYLOAD_DICTIONARY: u8 = 100;
+
+/// Outer message type of the frames carrying an update compressed with a dictionary.
+pub const MSG_COMPRESSED_UPDATE: u8 = 101;
+
+/// Outer message type of the frames carrying a server notice, only sent to
+/// clients that opted in.
+pub const MSG_NOTICE: u8 = 102;
+
+/// Message of the official Yjs sync protocol (`y-protocols/sync`).
+///
+/// This value object represents the binary frames exchanged with stock
+/// `y-websocket` clients. Each frame starts with the outer message type
+/// (`0` for sync), followed by the sync step and a length-prefixed payload:
+/// - `SyncStep1` carries the sender's encoded state vector
+/// - `SyncStep2` carries the update the receiver is missing
+/// - `Update` carries an incremental document update
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyncProtocolMessage {
+    /// The sender's state vector, asking the peer for missing updates
+    SyncStep1(Vec<u8>),
+    /// The reply to `SyncStep1`, containing the missing updates
+    SyncStep2(Vec<u8>),
+    /// An incremental update broadcast after the initial synchronization
+    Update(Vec<u8>),
+}
+
+impl SyncProtocolMessage {
+    /// Decodes a binary frame received from a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The binary WebSocket frame
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(SyncProtocolMessage))` - A sync protocol message
+    /// * `Ok(None)` - A valid frame outside the sync protocol (awareness, auth or custom)
+    /// * `Err(DomainError)` - `InvalidUpdate` if the frame is malformed
+    pub fn decode(data: &[u8]) -> DomainResult<Option<Self>> {
+        let message = Message::decode_v1(data).map_err(|e| {
+            DomainError::InvalidUpdate(format!("Failed to decode sync protocol message: {}", e))
+        })?;
+
+        Ok(match message {
+            Message::Sync(SyncMessage::SyncStep1(state_vector)) => {
+                Some(Self::SyncStep1(state_vector.encode_v1()))
+            }
+            Message::Sync(SyncMessage::SyncStep2(update)) => Some(Self::SyncStep2(update)),
+            Message::Sync(SyncMessage::Update(update)) => Some(Self::Update(update)),
+            _ => None,
+        })
+    }
+
+    /// Encodes an auth protocol (`y-protocols/auth`) frame telling the client
+    /// that it is not allowed to perform an operation.
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` - Human readable reason of the denial
+    ///
+    /// # Returns
+    ///
+    /// The binary frame to send to a client
+    pub fn encode_permission_denied(reason: &str) -> Vec<u8> {
+        Message::Auth(Some(reason.to_string())).encode_v1()
+    }
+
+    /// Encodes a frame shipping a compression dictionary to a client.
+    ///
+    /// The frame holds the `MSG_PAYLOAD_DICTIONARY` message type, the
+    /// dictionary ID and the length-prefixed dictionary.
+    ///
+    /// # Arguments
+    ///
+    /// * `dictionary` - The dictionary the following compressed updates use
+    ///
+    /// # Returns
+    ///
+    /// The binary frame to send to a client
+    pub fn encode_dictionary(dictionary: &PayloadDictionary) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        encoder.write_var(MSG_PAYLOAD_DICTIONARY);
+        encoder.write_var(dictionary.id);
+        encoder.write_buf(&dictionary.data);
+        encoder.to_vec()
+    }
+
+    /// Encodes a frame carrying an update compressed with a dictionary.
+    ///
+    /// The frame holds the `MSG_COMPRESSED_UPDATE` message type, the ID of the
+    /// dictionary and the length-prefixed compressed update, which decompresses
+    /// into the payload of a sync `Update` message.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The compressed update
+    ///
+    /// # Returns
+    ///
+    /// The binary frame to send to a client
+    pub fn encode_compressed_update(payload: &CompressedPayload) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        encoder.write_var(MSG_COMPRESSED_UPDATE);
+        encoder.write_var(payload.dictionary.id);
+        encoder.write_buf(&payload.data);
+        encoder.to_vec()
+    }
+
+    /// Encodes a frame carrying a server notice.
+    ///
+    /// The frame holds the `MSG_NOTICE` message type and the notice as a
+    /// length-prefixed JSON string, laid out as the `data` of the JSON
+    /// protocol's `notice` messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - The notice to deliver
+    ///
+    /// # Returns
+    ///
+    /// The binary frame to send to a client
+    pub fn encode_notice(notice: &Notice) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        encoder.write_var(MSG_NOTICE);
+        encoder.write_string(&sonic_rs::to_string(notice).unwrap_or_default());
+        encoder.to_vec()
+    }
+
+    /// Encodes the message into a binary frame.
+    ///
+    /// # Returns
+    ///
+    /// The binary frame to send to a client
+    pub fn encode(&self) -> Vec<u8> {
+        let (step, payload) = match self {
+            Self::SyncStep1(state_vector) => (MSG_SYNC_STEP_1, state_vector),
+            Self::SyncStep2(update) => (MSG_SYNC_STEP_2, update),
+            Self::Update(update) => (MSG_SYNC_UPDATE, update),
+        };
+
+        let mut encoder = EncoderV1::new();
+        encoder.write_var(MSG_SYNC);
+        encoder.write_var(step);
+        encoder.write_buf(payload);
+        encoder.to_vec()
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::{DomainError, DomainResult},
+    value_objects::{
+        feature_policy::{FeaturePolicies, NAMESPACE_SEPARATOR},
+        subdocument::SUBDOCUMENT_SEPARATOR,
+    },
+};
+
+/// Scopes a document identifier to a tenant.
+///
+/// A tenant is the namespace of its documents: the document `roadmap` of the
+/// tenant `acme` is stored, broadcast and governed by the feature policies as
+/// `acme/roadmap`, so two tenants never share a document.
+///
+/// # Arguments
+///
+/// * `tenant` - The tenant, or an empty string for none
+/// * `doc_id` - The document identifier within the tenant
+///
+/// # Returns
+///
+/// * `Ok(String)` - The scoped identifier, or `doc_id` as is without a tenant
+/// * `Err(DomainError::InvalidArgument)` - If the tenant contains a `/` or a `#`, or the document
+///   identifier is empty
+pub fn scoped_document_id(tenant: &str, doc_id: &str) -> DomainResult<String> {
+    if tenant.is_empty() {
+        return Ok(doc_id.to_string());
+    }
+    if tenant.contains([NAMESPACE_SEPARATOR, SUBDOCUMENT_SEPARATOR]) {
+        return Err(DomainError::InvalidArgument(format!(
+            "Invalid tenant '{}': it may not contain '{}' or '{}'",
+            tenant, NAMESPACE_SEPARATOR, SUBDOCUMENT_SEPARATOR
+        )));
+    }
+    if doc_id.is_empty() {
+        return Err(DomainError::InvalidArgument(format!(
+            "A document of tenant '{}' needs an identifier",
+            tenant
+        )));
+    }
+    Ok(format!("{}{}{}", tenant, NAMESPACE_SEPARATOR, doc_id))
+}
+
+/// Finds the identifier a scoped document has within a tenant, the inverse of
+/// [`scoped_document_id`].
+///
+/// # Arguments
+///
+/// * `tenant` - The tenant, or an empty string for none
+/// * `doc_id` - The scoped document identifier
+///
+/// # Returns
+///
+/// The identifier within the tenant, `doc_id` as is without a tenant, or `None` if the document
+/// belongs to another tenant
+pub fn unscoped_document_id<'a>(tenant: &str, doc_id: &'a str) -> Option<&'a str> {
+    if tenant.is_empty() {
+        return Some(doc_id);
+    }
+    doc_id
+        .strip_prefix(tenant)?
+        .strip_prefix(NAMESPACE_SEPARATOR)
+}
+
+/// Limits on the documents and connections of a tenant.
+///
+/// A value of `0` disables the corresponding limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TenantLimits {
+    /// Maximum number of documents of the tenant, subdocuments excluded
+    pub max_documents: usize,
+    /// Maximum number of clients present on the tenant's documents at once
+    pub max_connections: usize,
+}
+
+/// Limits of every tenant.
+///
+/// A document's tenant is its namespace (e.g. `acme` for `acme/roadmap`).
+/// Documents without a namespace belong to no tenant and are never limited;
+/// tenants without limits of their own use the default limits.
+#[derive(Clone, Debug, Default)]
+pub struct TenantQuotas {
+    default: TenantLimits,
+    tenants: HashMap<String, TenantLimits>,
+}
+
+impl TenantQuotas {
+    /// Creates a quota table applying the given limits to every tenant.
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - The limits of tenants without limits of their own
+    ///
+    /// # Returns
+    ///
+    /// A new `TenantQuotas` instance without tenant overrides
+    pub fn new(default: TenantLimits) -> Self {
+        Self {
+            default,
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Sets the limits of a tenant.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant` - The tenant
+    /// * `limits` - The limits applied to the tenant
+    ///
+    /// # Returns
+    ///
+    /// The `TenantQuotas` with the tenant's limits added
+    pub fn with_tenant(mut self, tenant: &str, limits: TenantLimits) -> Self {
+        self.tenants.insert(tenant.to_string(), limits);
+        self
+    }
+
+    /// Resolves the tenant of a document and its limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A document identifier
+    ///
+    /// # Returns
+    ///
+    /// The tenant and its limits, or `None` if the document belongs to no tenant
+    pub fn limits_of<'a>(&self, doc_id: &'a str) -> Option<(&'a str, TenantLimits)> {
+        let tenant = FeaturePolicies::namespace_of(doc_id)?;
+        let limits = self.tenants.get(tenant).copied().unwrap_or(self.default);
+        Some((tenant, limits))
+    }
+}
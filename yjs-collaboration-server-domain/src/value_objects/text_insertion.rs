@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// Text an update inserts into a text root of a document.
+///
+/// Offsets are in UTF-8 bytes, in the text of the root once the update is applied.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextInsertion {
+    /// Name of the text root
+    pub root: String,
+    /// Offset of the inserted text in the root
+    pub index: u32,
+    /// The inserted text
+    pub text: String,
+}
+
+/// Replacement of a range of an inserted text, e.g. masking a word.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextReplacement {
+    /// Start of the range, in UTF-8 bytes from the start of the inserted text
+    pub start: usize,
+    /// End of the range, exclusive, in UTF-8 bytes from the start of the inserted text
+    pub end: usize,
+    /// The text written in place of the range
+    pub text: String,
+}
+
+/// Decision of an update processor on an inserted text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProcessorVerdict {
+    /// The text is kept as is
+    Accept,
+    /// The whole update is rejected, for the given reason
+    Reject(String),
+    /// The text is kept, and the update labelled, e.g. to be reviewed
+    Annotate(Vec<String>),
+    /// Ranges of the text are replaced before the update is broadcast
+    Replace(Vec<TextReplacement>),
+}
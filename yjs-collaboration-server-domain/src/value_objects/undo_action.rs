@@ -0,0 +1,23 @@
+use std::fmt;
+
+/// Change a client asks the server to make through its undo stack.
+///
+/// The server keeps an undo stack per client and document, tracking the changes
+/// the client applied; undoing a change reverts the client's last tracked change,
+/// redoing it applies the last undone change again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UndoAction {
+    /// Revert the client's last change
+    Undo,
+    /// Apply the client's last undone change again
+    Redo,
+}
+
+impl fmt::Display for UndoAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undo => write!(f, "undo"),
+            Self::Redo => write!(f, "redo"),
+        }
+    }
+}
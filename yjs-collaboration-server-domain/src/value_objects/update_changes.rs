@@ -0,0 +1,24 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Changes an update would make to a document, measured before applying it.
+///
+/// Items are counted in Yjs clock units, one per inserted character or
+/// element, like the document's `StructureStats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateChanges {
+    /// Names of the root types whose content the update changes, created ones included
+    pub root_types: BTreeSet<String>,
+    /// Number of clock units the update inserts
+    pub inserted_items: u64,
+    /// Number of clock units the update deletes
+    pub deleted_items: u64,
+}
+
+impl UpdateChanges {
+    /// Returns whether the update changes nothing, e.g. it was already applied.
+    pub fn is_empty(&self) -> bool {
+        self.root_types.is_empty() && self.inserted_items == 0 && self.deleted_items == 0
+    }
+}
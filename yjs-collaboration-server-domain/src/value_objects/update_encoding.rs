@@ -0,0 +1,85 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    Update,
+};
+
+use crate::errors::{DomainError, DomainResult};
+
+/// Version of the Yjs encoding of the updates exchanged with a client.
+///
+/// Documents are stored, diffed and broadcast as v1 updates, the encoding of
+/// the `y-protocols` sync protocol. Clients producing v2 updates (Yjs'
+/// `encodeStateAsUpdateV2`) negotiate it per connection, and their updates are
+/// converted at the edge of the connection, both ways. State vectors are v1
+/// whatever the negotiated version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UpdateEncoding {
+    /// Yjs v1 updates, the default
+    #[default]
+    V1,
+    /// Yjs v2 updates
+    V2,
+}
+
+impl UpdateEncoding {
+    /// Converts an update received from a client into a v1 update.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The update, in this encoding
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cow<[u8]>)` - The v1 update, borrowed when no conversion is needed
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update is not valid in this encoding
+    pub fn decode(self, update: &[u8]) -> DomainResult<Cow<'_, [u8]>> {
+        match self {
+            Self::V2 if !update.is_empty() => Update::decode_v2(update)
+                .map(|update| Cow::Owned(update.encode_v1()))
+                .map_err(|e| DomainError::InvalidUpdate(format!("Invalid v2 update: {}", e))),
+            _ => Ok(Cow::Borrowed(update)),
+        }
+    }
+
+    /// Converts a v1 update sent to a client into this encoding.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The v1 update
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Cow<[u8]>)` - The update in this encoding, borrowed when no conversion is needed
+    /// * `Err(DomainError)` - `InvalidUpdate` if the update is not a valid v1 update
+    pub fn encode(self, update: &[u8]) -> DomainResult<Cow<'_, [u8]>> {
+        match self {
+            Self::V2 if !update.is_empty() => Update::decode_v1(update)
+                .map(|update| Cow::Owned(update.encode_v2()))
+                .map_err(|e| DomainError::InvalidUpdate(format!("Invalid v1 update: {}", e))),
+            _ => Ok(Cow::Borrowed(update)),
+        }
+    }
+}
+
+impl fmt::Display for UpdateEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::V1 => "v1",
+            Self::V2 => "v2",
+        })
+    }
+}
+
+impl FromStr for UpdateEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v1" | "1" => Ok(Self::V1),
+            "v2" | "2" => Ok(Self::V2),
+            _ => Err(format!("Unknown update encoding: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    errors::{DomainError, DomainResult},
+    services::document_service::SyncResponse,
+};
+
+/// Type of a binary update frame, carried by its leading byte.
+///
+/// JSON protocol clients bound to a document may exchange their updates as
+/// binary frames instead of Base64 `update` messages, while every other
+/// message stays JSON. Integers are big-endian. Frames are laid out as:
+/// - `Update`: `0x00 | update` from the client, and `0x00 | sequence_number: u64 | update` from the
+///   server
+/// - `Echo`: `0x01 | sequence_number: u64 | update`, the client's own update sent back by the
+///   server
+/// - `Sync`: `0x02 | sequence_number: u64 | state vector length: u32 | state vector | update`, the
+///   server's answer to a sync request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateFrameType {
+    /// A document update
+    Update = 0,
+    /// The connection's own update, echoed back
+    Echo = 1,
+    /// The response to a sync request
+    Sync = 2,
+}
+
+/// Encodes a frame relaying a document update to a client.
+///
+/// # Arguments
+///
+/// * `update` - The binary update
+/// * `sequence_number` - Position of the update among the document's broadcasts
+/// * `echo` - Whether the update is the connection's own, echoed back
+///
+/// # Returns
+///
+/// An `Update` or `Echo` frame
+pub fn encode_update_frame(update: &[u8], sequence_number: u64, echo: bool) -> Vec<u8> {
+    let frame_type = if echo {
+        UpdateFrameType::Echo
+    } else {
+        UpdateFrameType::Update
+    };
+
+    let mut frame = Vec::with_capacity(9 + update.len());
+    frame.push(frame_type as u8);
+    frame.extend_from_slice(&sequence_number.to_be_bytes());
+    frame.extend_from_slice(update);
+    frame
+}
+
+/// Encodes a frame answering a client's sync request.
+///
+/// # Arguments
+///
+/// * `response` - The sync response
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` - A `Sync` frame, with an empty state vector or update if the response carries
+///   none
+/// * `Err(DomainError::Internal)` - If the state vector exceeds 4 GiB
+pub fn encode_sync_frame(response: &SyncResponse) -> DomainResult<Vec<u8>> {
+    let state_vector = response.state_vector.as_deref().unwrap_or_default();
+    let update = response.update.as_deref().unwrap_or_default();
+    let length = u32::try_from(state_vector.len())
+        .map_err(|_| DomainError::Internal("State vector exceeds 4 GiB".to_string()))?;
+
+    let mut frame = Vec::with_capacity(13 + state_vector.len() + update.len());
+    frame.push(UpdateFrameType::Sync as u8);
+    frame.extend_from_slice(&response.sequence_number.to_be_bytes());
+    frame.extend_from_slice(&length.to_be_bytes());
+    frame.extend_from_slice(state_vector);
+    frame.extend_from_slice(update);
+    Ok(frame)
+}
+
+/// Decodes a frame received from a client.
+///
+/// # Arguments
+///
+/// * `frame` - The binary WebSocket frame
+///
+/// # Returns
+///
+/// * `Ok(&[u8])` - The update the frame carries
+/// * `Err(DomainError::InvalidArgument)` - If the frame is empty or not an `Update` frame
+pub fn decode_update_frame(frame: &[u8]) -> DomainResult<&[u8]> {
+    match frame.split_first() {
+        Some((&frame_type, update)) if frame_type == UpdateFrameType::Update as u8 => Ok(update),
+        Some((frame_type, _)) => Err(DomainError::InvalidArgument(format!(
+            "Unexpected update frame type {}",
+            frame_type
+        ))),
+        None => Err(DomainError::InvalidArgument(
+            "Update frame is empty".to_string(),
+        )),
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt;
+
+use crate::errors::DomainError;
+
+/// Server-wide limits on the size of the updates clients apply to documents.
+///
+/// A single client sending a giant update, or growing a document without end,
+/// could exhaust the server's memory; such updates are rejected before they are
+/// decoded. These limits apply to every document, on top of the maximum size set
+/// by a namespace's feature policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpdateLimits {
+    /// Maximum size in bytes of a single update (`0` = unlimited)
+    pub max_update_size: usize,
+    /// Maximum encoded size in bytes of a document (`0` = unlimited)
+    pub max_document_size: usize,
+}
+
+/// Size limit an update was rejected for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SizeLimit {
+    /// The update itself is too large
+    Update,
+    /// The update would make the document too large
+    Document,
+}
+
+impl fmt::Display for SizeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Update => write!(f, "update"),
+            Self::Document => write!(f, "document"),
+        }
+    }
+}
+
+impl UpdateLimits {
+    /// Checks whether an update may be applied to a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_size` - Current encoded size of the document
+    /// * `update_size` - Size of the update about to be applied
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the update and the updated document stay within the limits
+    /// * `Err((SizeLimit, DomainError))` - The exceeded limit, with a `PayloadTooLarge` error
+    pub fn check(
+        &self,
+        document_size: usize,
+        update_size: usize,
+    ) -> Result<(), (SizeLimit, DomainError)> {
+        if self.max_update_size > 0 && update_size > self.max_update_size {
+            return Err((
+                SizeLimit::Update,
+                DomainError::PayloadTooLarge(format!(
+                    "Update of {} bytes exceeds the maximum update size of {} bytes",
+                    update_size, self.max_update_size
+                )),
+            ));
+        }
+        if self.max_document_size > 0
+            && document_size.saturating_add(update_size) > self.max_document_size
+        {
+            return Err((
+                SizeLimit::Document,
+                DomainError::PayloadTooLarge(format!(
+                    "Document would exceed the maximum document size of {} bytes",
+                    self.max_document_size
+                )),
+            ));
+        }
+        Ok(())
+    }
+}
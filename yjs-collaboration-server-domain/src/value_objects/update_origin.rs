@@ -0,0 +1,179 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Transport an update reached the server through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateTransport {
+    /// A JSON or binary WebSocket connection
+    #[serde(rename = "websocket")]
+    WebSocket,
+    /// A gRPC `Collaborate` stream or call
+    Grpc,
+    /// The server itself, e.g. reverts, imports and replication from a primary
+    Server,
+}
+
+impl fmt::Display for UpdateTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::WebSocket => "websocket",
+            Self::Grpc => "grpc",
+            Self::Server => "server",
+        })
+    }
+}
+
+/// Who applied an update to a document, and through which transport.
+///
+/// The client identifier tags the broadcast of the update, so the sender can
+/// skip its own update, and keys the client's undo stack; the user identity
+/// and transport are only recorded in the document's audit trail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UpdateOrigin<'a> {
+    /// Identifier of the sending client
+    pub client_id: &'a str,
+    /// Identity of the user behind the client, or `None` for a guest
+    pub user_id: Option<&'a str>,
+    /// Transport the update reached the server through
+    pub transport: UpdateTransport,
+}
+
+impl<'a> UpdateOrigin<'a> {
+    /// Creates the origin of an update sent by a guest client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id` - Identifier of the sending client
+    /// * `transport` - Transport the update reached the server through
+    ///
+    /// # Returns
+    ///
+    /// A new `UpdateOrigin` without a user identity
+    pub fn new(client_id: &'a str, transport: UpdateTransport) -> Self {
+        Self {
+            client_id,
+            user_id: None,
+            transport,
+        }
+    }
+
+    /// Creates the origin of an update made by the server itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source tagging the update, e.g. `SERVER_UPDATE_SOURCE`
+    ///
+    /// # Returns
+    ///
+    /// A new `UpdateOrigin` over the `Server` transport
+    pub fn server(source: &'a str) -> Self {
+        Self::new(source, UpdateTransport::Server)
+    }
+
+    /// Attributes the update to a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Identity of the user, where an empty identity stands for a guest
+    ///
+    /// # Returns
+    ///
+    /// The `UpdateOrigin` with the user identity set
+    pub fn with_user(mut self, user_id: Option<&'a str>) -> Self {
+        self.user_id = user_id.filter(|user_id| !user_id.is_empty());
+        self
+    }
+}
+
+/// Where an update comes from, relative to the server instance broadcasting it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OriginKind {
+    /// A client connected to this server instance
+    #[default]
+    Local,
+    /// A client of another server instance, shared through the update broker
+    Remote,
+    /// The server itself, e.g. reverts, imports, undos and REST writes
+    Server,
+}
+
+impl fmt::Display for OriginKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Local => "local",
+            Self::Remote => "remote",
+            Self::Server => "server",
+        })
+    }
+}
+
+/// Origin of the transaction applying an update to a document.
+///
+/// The source tags the Yjs transaction, so the undo stack of a client only
+/// tracks its own changes, and the whole origin is carried along with the
+/// broadcast of the update, so its receivers can tell who made it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionOrigin {
+    /// Identifier of the sending client, or the source tagging an update the
+    /// client did not send, e.g. `SERVER_UPDATE_SOURCE`
+    pub source: String,
+    /// Identity of the user behind the client, or `None` for a guest or the server
+    pub user_id: Option<String>,
+    /// Where the update comes from
+    pub kind: OriginKind,
+}
+
+impl TransactionOrigin {
+    /// Creates the origin of an update made by the server itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source tagging the update, e.g. `SERVER_UPDATE_SOURCE`
+    ///
+    /// # Returns
+    ///
+    /// A new `TransactionOrigin` of kind `Server`
+    pub fn server(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            user_id: None,
+            kind: OriginKind::Server,
+        }
+    }
+
+    /// Creates the origin of an update received from another server instance.
+    ///
+    /// The broker only shares the updates themselves, so who sent them is unknown.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source tagging the update, e.g. `REMOTE_UPDATE_SOURCE`
+    ///
+    /// # Returns
+    ///
+    /// A new `TransactionOrigin` of kind `Remote`
+    pub fn remote(source: &str) -> Self {
+        Self {
+            kind: OriginKind::Remote,
+            ..Self::server(source)
+        }
+    }
+}
+
+impl From<UpdateOrigin<'_>> for TransactionOrigin {
+    /// Tags the transaction with the sending client, or with the server's source
+    /// for updates the server made itself.
+    fn from(origin: UpdateOrigin<'_>) -> Self {
+        Self {
+            source: origin.client_id.to_string(),
+            user_id: origin.user_id.map(str::to_string),
+            kind: match origin.transport {
+                UpdateTransport::Server => OriginKind::Server,
+                UpdateTransport::WebSocket | UpdateTransport::Grpc => OriginKind::Local,
+            },
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Property-based convergence of collaborative documents
+//
+// Simulated peers make random interleavings of inserts and deletes on a shared
+// text and exchange their updates through `CollaborativeDocument`, the way the
+// server relays them. Whatever the interleaving, the causal delivery order or
+// the way diffs are split, every replica must end up with the same content. The
+// updates and state vectors the peers exchange use the v1 encoding, like the
+// clients of the server, so any change of encoding on the server breaks them.
+
+mod support;
+
+use proptest::prelude::*;
+use support::Peer;
+use yjs_collaboration_server_domain::CollaborativeDocument;
+use yrs::{updates::decoder::Decode, ReadTxn, StateVector, Text, Transact};
+
+/// Number of simulated peers
+const PEERS: usize = 3;
+
+/// A step of a scenario.
+#[derive(Clone, Debug)]
+enum Step {
+    /// A peer inserts text at a position, taken modulo the text's length
+    Insert { peer: usize, at: u32, text: String },
+    /// A peer deletes characters from a position, taken modulo the text's length
+    Delete { peer: usize, at: u32, len: u32 },
+    /// A peer synchronizes with the server, both ways
+    Sync { peer: usize },
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        3 => (0..PEERS, any::<u32>(), "[a-z ]{1,6}")
+            .prop_map(|(peer, at, text)| Step::Insert { peer, at, text }),
+        1 => (0..PEERS, any::<u32>(), 1u32..4).prop_map(|(peer, at, len)| Step::Delete {
+            peer,
+            at,
+            len
+        }),
+        1 => (0..PEERS).prop_map(|peer| Step::Sync { peer }),
+    ]
+}
+
+/// Makes the change of an insert or delete step on a peer.
+fn edit(peer: &mut Peer, step: &Step) {
+    peer.edit(|text, txn| {
+        let len = text.len(txn);
+        match step {
+            Step::Insert {
+                at, text: inserted, ..
+            } => text.insert(txn, at % (len + 1), inserted),
+            Step::Delete { at, len: count, .. } if len > 0 => {
+                let index = at % len;
+                text.remove_range(txn, index, (*count).min(len - index));
+            }
+            _ => {}
+        }
+    });
+}
+
+/// Runs a scenario, then syncs every peer until all replicas have seen every
+/// change.
+fn run(steps: &[Step]) -> (Vec<Peer>, CollaborativeDocument) {
+    let mut peers: Vec<Peer> = (0..PEERS).map(|i| Peer::new(i as u64 + 1)).collect();
+    let mut server = CollaborativeDocument::new();
+
+    for step in steps {
+        match step {
+            Step::Insert { peer, .. } | Step::Delete { peer, .. } => edit(&mut peers[*peer], step),
+            Step::Sync { peer } => peers[*peer].sync(&mut server),
+        }
+    }
+
+    // The first pass uploads every change, the second downloads them
+    for _ in 0..2 {
+        for peer in &peers {
+            peer.sync(&mut server);
+        }
+    }
+
+    (peers, server)
+}
+
+/// Decodes a state vector, whose encoding does not order the clients.
+fn decode_sv(state_vector: &[u8]) -> StateVector {
+    StateVector::decode_v1(state_vector).expect("corrupt state vector")
+}
+
+/// Orders updates for delivery, holding each back until the changes it was
+/// made on have been delivered, as relaying through the server does: yrs
+/// drops the deletions of changes from clients a document has not seen yet.
+fn causal_order(mut queue: Vec<(StateVector, Vec<u8>)>) -> Vec<Vec<u8>> {
+    let mut replica = CollaborativeDocument::new();
+    let mut ordered = Vec::new();
+    while !queue.is_empty() {
+        let seen = decode_sv(&replica.get_state_vector());
+        let next = queue
+            .iter()
+            .position(|(made_on, _)| {
+                made_on
+                    .iter()
+                    .all(|(client, clock)| seen.get(client) >= *clock)
+            })
+            .expect("an update was made on changes no peer made");
+        let (_, update) = queue.remove(next);
+        replica
+            .apply_update(&update)
+            .expect("the update cannot be applied");
+        ordered.push(update);
+    }
+    ordered
+}
+
+/// Builds a document from updates applied one at a time.
+fn replay(updates: &[Vec<u8>]) -> CollaborativeDocument {
+    let mut document = CollaborativeDocument::new();
+    for update in updates {
+        document
+            .apply_update(update)
+            .expect("the update cannot be applied");
+    }
+    document
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    /// Peers syncing at random points converge with the server.
+    #[test]
+    fn replicas_converge_after_syncing(steps in prop::collection::vec(step(), 0..60)) {
+        let (peers, server) = run(&steps);
+
+        prop_assert!(!server.has_pending_changes());
+        for peer in &peers {
+            prop_assert_eq!(peer.content(), server.get_text_content());
+            prop_assert_eq!(
+                decode_sv(&peer.state_vector()),
+                decode_sv(&server.get_state_vector())
+            );
+        }
+    }
+
+    /// Updates relayed in any causal order produce the same document, once
+    /// all have arrived.
+    #[test]
+    fn delivery_order_does_not_matter(
+        steps in prop::collection::vec(step(), 0..60),
+        keys in prop::collection::vec(any::<u32>(), 200),
+    ) {
+        let (peers, server) = run(&steps);
+
+        let mut updates: Vec<(u32, (StateVector, Vec<u8>))> = peers
+            .iter()
+            .flat_map(|peer| peer.updates.iter().cloned())
+            .zip(keys.iter().cycle().copied())
+            .map(|(update, key)| (key, update))
+            .collect();
+        updates.sort_by_key(|(key, _)| *key);
+        let updates = causal_order(updates.into_iter().map(|(_, update)| update).collect());
+
+        let shuffled = replay(&updates);
+        prop_assert!(!shuffled.has_pending_changes());
+        prop_assert_eq!(shuffled.get_text_content(), server.get_text_content());
+
+        let merged = replay(&[CollaborativeDocument::merge_updates(&updates).unwrap()]);
+        prop_assert!(!merged.has_pending_changes());
+        prop_assert_eq!(merged.get_text_content(), server.get_text_content());
+    }
+
+    /// A diff split into chunks, applied in order, brings a stale replica
+    /// to the same state as the whole diff.
+    #[test]
+    fn chunked_diffs_match_whole_diffs(
+        steps in prop::collection::vec(step(), 0..60),
+        split in 0..60usize,
+        chunk_size in 1..256usize,
+    ) {
+        let (_, server) = run(&steps);
+        let (stale_peers, _) = run(&steps[..split.min(steps.len())]);
+        let stale_sv = stale_peers[0].state_vector();
+
+        let whole = server.get_missing_updates(&stale_sv).unwrap();
+        let chunks = server.get_missing_update_chunks(&stale_sv, 0, chunk_size).unwrap();
+
+        let expected = stale_peers[0].doc.transact().encode_state_as_update_v1(
+            &StateVector::default(),
+        );
+        let mut from_whole = replay(std::slice::from_ref(&expected));
+        from_whole.apply_update(&whole).unwrap();
+        let mut from_chunks = replay(&[expected]);
+        for chunk in &chunks {
+            from_chunks.apply_update(chunk).unwrap();
+        }
+
+        prop_assert!(!from_chunks.has_pending_changes());
+        prop_assert_eq!(from_chunks.get_text_content(), server.get_text_content());
+        prop_assert_eq!(
+            decode_sv(&from_chunks.get_state_vector()),
+            decode_sv(&from_whole.get_state_vector())
+        );
+    }
+
+    /// The full state of a document restores an identical document.
+    #[test]
+    fn full_state_round_trips(steps in prop::collection::vec(step(), 0..60)) {
+        let (_, server) = run(&steps);
+
+        let restored = replay(&[server.encode_full_state()]);
+        prop_assert_eq!(restored.get_text_content(), server.get_text_content());
+        prop_assert_eq!(
+            decode_sv(&restored.get_state_vector()),
+            decode_sv(&server.get_state_vector())
+        );
+    }
+}
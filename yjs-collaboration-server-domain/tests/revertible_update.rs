@@ -0,0 +1,58 @@
+// Rollback of a revertible update next to concurrent edits
+//
+// A cross-document transaction applies each of its updates as a revertible
+// update, and reverts them when a later one fails. Other clients keep editing
+// the documents meanwhile: reverting the transaction's update must only undo
+// what it changed, and leave their edits in place, on the server as on every
+// replica that applies the update the revert broadcasts.
+
+mod support;
+
+use support::Peer;
+use yjs_collaboration_server_domain::CollaborativeDocument;
+use yrs::{updates::decoder::Decode, ReadTxn, StateVector, Text, Transact};
+
+/// Key the transaction's update is recorded under
+const KEY: &str = "transaction";
+
+#[test]
+fn concurrent_edit_survives_a_reverted_update() {
+    let mut server = CollaborativeDocument::new();
+    let mut author = Peer::new(1);
+    let mut transaction = Peer::new(2);
+    let mut editor = Peer::new(3);
+
+    let written = author.edit(|text, txn| text.insert(txn, 0, "hello world"));
+    server.apply_update(&written).unwrap();
+    transaction.catch_up(&server);
+    editor.catch_up(&server);
+
+    // The transaction replaces a word while another client edits elsewhere
+    let replaced = transaction.edit(|text, txn| {
+        text.remove_range(txn, 6, 5);
+        text.insert(txn, 6, "there");
+    });
+    let concurrent = editor.edit(|text, txn| text.insert(txn, 0, "Oh, "));
+    server.apply_revertible_update(&replaced, KEY).unwrap();
+    server.apply_update(&concurrent).unwrap();
+    assert_eq!(server.get_text_content(), "Oh, hello there");
+
+    let reverted = server
+        .revert(KEY)
+        .unwrap()
+        .expect("the update was not recorded");
+    assert_eq!(server.get_text_content(), "Oh, hello world");
+
+    // Replicas that saw both edits converge once they apply the revert
+    editor.apply(&replaced);
+    editor.apply(&reverted);
+    transaction.apply(&concurrent);
+    transaction.apply(&reverted);
+    assert_eq!(editor.content(), "Oh, hello world");
+    assert_eq!(transaction.content(), "Oh, hello world");
+
+    // Reverted updates are forgotten
+    assert!(server.revert(KEY).unwrap().is_none());
+    let server_state = StateVector::decode_v1(&server.get_state_vector()).unwrap();
+    assert_eq!(transaction.doc.transact().state_vector(), server_state);
+}
@@ -0,0 +1,91 @@
+// Test support for the integration tests
+//
+// Simulated client replicas edit a shared text and exchange v1-encoded
+// updates with a `CollaborativeDocument` standing for the server, like the
+// clients of the server do.
+
+#![allow(dead_code)]
+
+use yjs_collaboration_server_domain::CollaborativeDocument;
+use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    Doc, GetString, ReadTxn, StateVector, TextRef, Transact, TransactionMut, Update,
+};
+
+/// Name of the shared text the peers edit
+pub const TEXT_ROOT: &str = "content";
+
+/// A client replica editing the shared text.
+pub struct Peer {
+    pub doc: Doc,
+    pub text: TextRef,
+    /// The updates made locally, in order, with the state each was made on
+    pub updates: Vec<(StateVector, Vec<u8>)>,
+}
+
+impl Peer {
+    pub fn new(client_id: u64) -> Self {
+        let doc = Doc::with_client_id(client_id);
+        let text = doc.get_or_insert_text(TEXT_ROOT);
+        Self {
+            doc,
+            text,
+            updates: Vec::new(),
+        }
+    }
+
+    pub fn content(&self) -> String {
+        self.text.get_string(&self.doc.transact())
+    }
+
+    pub fn state_vector(&self) -> Vec<u8> {
+        self.doc.transact().state_vector().encode_v1()
+    }
+
+    pub fn apply(&self, update: &[u8]) {
+        let update = Update::decode_v1(update).expect("the server sent a corrupt update");
+        self.doc
+            .transact_mut()
+            .apply_update(update)
+            .expect("the server sent an update that cannot be applied");
+    }
+
+    /// Makes a local change, recording and returning the update it produced.
+    pub fn edit(&mut self, change: impl FnOnce(&TextRef, &mut TransactionMut)) -> Vec<u8> {
+        let before = self.doc.transact().snapshot();
+        change(&self.text, &mut self.doc.transact_mut());
+
+        // Deletions leave the state vector as it was, so compare the delete set too
+        let txn = self.doc.transact();
+        let update = txn.encode_state_as_update_v1(&before.state_map);
+        if txn.snapshot() != before {
+            self.updates.push((before.state_map, update.clone()));
+        }
+        update
+    }
+
+    /// Sends the server the changes it is missing.
+    pub fn upload(&self, server: &mut CollaborativeDocument) {
+        let server_sv = StateVector::decode_v1(&server.get_state_vector())
+            .expect("the server sent a corrupt state vector");
+        let diff = self.doc.transact().encode_state_as_update_v1(&server_sv);
+        server
+            .apply_update(&diff)
+            .expect("the server rejected the peer's diff");
+    }
+
+    /// Catches up with the server.
+    pub fn catch_up(&self, server: &CollaborativeDocument) {
+        let missing = server
+            .get_missing_updates(&self.state_vector())
+            .expect("the server rejected the peer's state vector");
+        self.apply(&missing);
+    }
+
+    /// Sends the server the changes it is missing, then applies those the
+    /// peer is missing, as in the two-step sync handshake.
+    pub fn sync(&self, server: &mut CollaborativeDocument) {
+        self.upload(server);
+        self.catch_up(server);
+    }
+}
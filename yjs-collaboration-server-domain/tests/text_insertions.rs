@@ -0,0 +1,51 @@
+// Text an update inserts, as reported to the update processors
+//
+// Clients send updates inserting text at several places of a root at once.
+// Each place must be reported on its own, at its offset once the update is
+// applied, without the unchanged text between them.
+
+use yjs_collaboration_server_domain::{
+    value_objects::text_insertion::TextInsertion, CollaborativeDocument,
+};
+use yrs::{Doc, ReadTxn, StateVector, Text, Transact};
+
+/// Name of the shared text the client edits
+const TEXT_ROOT: &str = "content";
+
+fn insertion(index: u32, text: &str) -> TextInsertion {
+    TextInsertion {
+        root: TEXT_ROOT.to_string(),
+        index,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn reports_each_place_of_an_update_on_its_own() {
+    let mut server = CollaborativeDocument::new();
+    let client = Doc::with_client_id(1);
+    let text = client.get_or_insert_text(TEXT_ROOT);
+
+    text.insert(&mut client.transact_mut(), 0, "hello world");
+    let written = client
+        .transact()
+        .encode_state_as_update_v1(&StateVector::default());
+    let (_, insertions) = server
+        .text_insertions_of(|doc| doc.apply_update(&written))
+        .unwrap();
+    assert_eq!(insertions, vec![insertion(0, "hello world")]);
+
+    // Both ends of the text change in a single update
+    let before = client.transact().state_vector();
+    {
+        let mut txn = client.transact_mut();
+        text.insert(&mut txn, 11, "!");
+        text.insert(&mut txn, 0, "Oh, ");
+    }
+    let edited = client.transact().encode_state_as_update_v1(&before);
+    let (_, insertions) = server
+        .text_insertions_of(|doc| doc.apply_update(&edited))
+        .unwrap();
+    assert_eq!(insertions, vec![insertion(15, "!"), insertion(0, "Oh, ")]);
+    assert_eq!(server.get_text_content(), "Oh, hello world!");
+}
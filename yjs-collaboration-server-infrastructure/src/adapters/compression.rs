@@ -0,0 +1,116 @@
+/// Identifier stored with uncompressed data.
+const CODEC_NONE: u8 = 0;
+/// Identifier stored with LZ4-compressed data.
+const CODEC_LZ4: u8 = 1;
+/// Identifier stored with Zstandard-compressed data.
+const CODEC_ZSTD: u8 = 2;
+
+/// Compression applied to the snapshots and updates written by durable repositories.
+///
+/// Every stored value records the identifier of the codec it was written with,
+/// so values written with a previous setting (or uncompressed, before
+/// compression was enabled) stay readable after the codec is changed. Values
+/// that do not shrink when compressed, such as most single keystroke updates,
+/// are stored uncompressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionCodec {
+    /// Values are stored as is
+    #[default]
+    None,
+    /// Fast LZ4 block compression
+    Lz4,
+    /// Zstandard compression at the given level (1-22)
+    Zstd { level: i32 },
+}
+
+impl CompressionCodec {
+    /// Compresses a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The value to store
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((u8, Vec<u8>))` - Identifier of the codec used and the stored bytes; the value is kept
+    ///   uncompressed when compression does not make it smaller
+    /// * `Err(String)` - If the value could not be compressed
+    pub fn compress(&self, data: &[u8]) -> Result<(u8, Vec<u8>), String> {
+        let (codec, compressed) = match self {
+            Self::None => return Ok((CODEC_NONE, data.to_vec())),
+            Self::Lz4 => (CODEC_LZ4, lz4_flex::compress_prepend_size(data)),
+            Self::Zstd { level } => (
+                CODEC_ZSTD,
+                zstd::bulk::compress(data, *level)
+                    .map_err(|e| format!("Failed to compress with zstd: {}", e))?,
+            ),
+        };
+
+        if compressed.len() < data.len() {
+            Ok((codec, compressed))
+        } else {
+            Ok((CODEC_NONE, data.to_vec()))
+        }
+    }
+
+    /// Decompresses a stored value, whatever codec it was written with.
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - Identifier of the codec stored with the value
+    /// * `data` - The stored bytes
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The original value
+    /// * `Err(String)` - If the codec is unknown or the data is corrupt
+    pub fn decompress(codec: u8, data: &[u8]) -> Result<Vec<u8>, String> {
+        match codec {
+            CODEC_NONE => Ok(data.to_vec()),
+            CODEC_LZ4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| format!("Corrupt LZ4 data: {}", e)),
+            CODEC_ZSTD => zstd::decode_all(data).map_err(|e| format!("Corrupt zstd data: {}", e)),
+            unknown => Err(format!(
+                "Unknown compression codec {}, the data was written by a newer version",
+                unknown
+            )),
+        }
+    }
+
+    /// Compresses a value into a self-describing frame: the codec identifier
+    /// followed by the stored bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The value to store
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The frame to store
+    /// * `Err(String)` - If the value could not be compressed
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let (codec, payload) = self.compress(data)?;
+
+        let mut frame = Vec::with_capacity(1 + payload.len());
+        frame.push(codec);
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+
+    /// Decodes a frame produced by [`CompressionCodec::encode`].
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The stored frame
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - The original value
+    /// * `Err(String)` - If the frame is empty, its codec unknown or its data corrupt
+    pub fn decode(frame: &[u8]) -> Result<Vec<u8>, String> {
+        let (&codec, payload) = frame
+            .split_first()
+            .ok_or_else(|| "Empty stored value".to_string())?;
+        Self::decompress(codec, payload)
+    }
+}
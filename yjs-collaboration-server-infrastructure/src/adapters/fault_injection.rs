@@ -0,0 +1,266 @@
+use std::{sync::Arc, time::Duration};
+
+use rand::Rng;
+use tokio::sync::{mpsc, RwLock};
+use tracing::debug;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::{document_repository::DocumentRepository, update_broker::UpdateBroker},
+    services::document_service::SingleDocumentServiceImpl,
+    value_objects::logged_update::LoggedUpdate,
+};
+
+/// Faults to inject into repository and broker calls.
+///
+/// Every probability is checked independently on each call and clamped to
+/// `[0, 1]`; the default plan injects nothing.
+#[derive(Clone, Debug, Default)]
+pub struct FaultPlan {
+    /// Probability that a call is delayed
+    pub delay_probability: f64,
+    /// Upper bound of an injected delay; the actual delay is uniformly distributed below it
+    pub max_delay: Duration,
+    /// Probability that a broadcast to the other instances is silently dropped
+    pub drop_probability: f64,
+    /// Probability that a fallible call fails
+    pub failure_probability: f64,
+}
+
+impl FaultPlan {
+    /// Returns whether the plan injects any fault.
+    pub fn is_active(&self) -> bool {
+        (self.delay_probability > 0.0 && !self.max_delay.is_zero())
+            || self.drop_probability > 0.0
+            || self.failure_probability > 0.0
+    }
+}
+
+/// Draws the faults of a `FaultPlan`.
+///
+/// Shared by the fault-injecting decorators so a single plan drives the whole
+/// server. Meant for staging environments, to exercise how clients and
+/// operators cope with slow storage, failed writes and lost broadcasts.
+pub struct FaultInjector {
+    plan: FaultPlan,
+}
+
+impl FaultInjector {
+    /// Creates an injector following a plan.
+    ///
+    /// # Arguments
+    ///
+    /// * `plan` - The faults to inject
+    ///
+    /// # Returns
+    ///
+    /// A new `FaultInjector` instance.
+    pub fn new(plan: FaultPlan) -> Self {
+        Self { plan }
+    }
+
+    /// Returns the plan the injector follows.
+    pub fn plan(&self) -> &FaultPlan {
+        &self.plan
+    }
+
+    fn happens(probability: f64) -> bool {
+        probability > 0.0 && rand::thread_rng().gen_bool(probability.min(1.0))
+    }
+
+    /// Blocks the calling thread for a random delay, with the plan's delay probability.
+    ///
+    /// Repository and broker calls are synchronous, so the delay holds the
+    /// calling worker like slow storage would.
+    pub fn delay(&self, operation: &str) {
+        if self.plan.max_delay.is_zero() || !Self::happens(self.plan.delay_probability) {
+            return;
+        }
+
+        let max_delay = self.plan.max_delay.as_millis() as u64;
+        let delay = Duration::from_millis(rand::thread_rng().gen_range(0..=max_delay));
+        debug!("Injecting {:?} delay into {}", delay, operation);
+        std::thread::sleep(delay);
+    }
+
+    /// Fails with the plan's failure probability.
+    ///
+    /// # Arguments
+    ///
+    /// * `operation` - Name of the operation, included in the error
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the operation may proceed
+    /// * `Err(DomainError)` - An injected failure, reported as the backend being unavailable
+    pub fn fail(&self, operation: &str) -> DomainResult<()> {
+        if Self::happens(self.plan.failure_probability) {
+            debug!("Injecting failure into {}", operation);
+            return Err(DomainError::Unavailable(format!(
+                "Injected fault: {} failed",
+                operation
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns whether a broadcast should be dropped, with the plan's drop probability.
+    pub fn drop_broadcast(&self) -> bool {
+        Self::happens(self.plan.drop_probability)
+    }
+
+    /// Delays then possibly fails an operation.
+    fn disturb(&self, operation: &str) -> DomainResult<()> {
+        self.delay(operation);
+        self.fail(operation)
+    }
+}
+
+/// Document repository decorator injecting delays and failures.
+///
+/// Every call may be delayed; calls that can report an error may also fail
+/// before reaching the decorated repository, which is then left untouched.
+pub struct FaultInjectingRepository<R: DocumentRepository> {
+    inner: R,
+    faults: Arc<FaultInjector>,
+}
+
+impl<R: DocumentRepository> FaultInjectingRepository<R> {
+    /// Wraps a repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The decorated repository
+    /// * `faults` - The injector drawing the faults
+    ///
+    /// # Returns
+    ///
+    /// A new `FaultInjectingRepository` instance.
+    pub fn new(inner: R, faults: Arc<FaultInjector>) -> Self {
+        Self { inner, faults }
+    }
+}
+
+impl<R: DocumentRepository> DocumentRepository for FaultInjectingRepository<R> {
+    fn create_document(
+        &self,
+        doc_id: &str,
+    ) -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        self.faults.disturb("create_document")?;
+        self.inner.create_document(doc_id)
+    }
+
+    fn get_document(&self, doc_id: &str) -> Option<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        self.faults.delay("get_document");
+        self.inner.get_document(doc_id)
+    }
+
+    fn get_or_create(&self, doc_id: &str) -> Arc<RwLock<SingleDocumentServiceImpl>> {
+        self.faults.delay("get_or_create");
+        self.inner.get_or_create(doc_id)
+    }
+
+    fn update_document(
+        &self,
+        doc_id: &str,
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+    ) -> DomainResult<()> {
+        self.faults.disturb("update_document")?;
+        self.inner.update_document(doc_id, document)
+    }
+
+    fn delete_document(&self, doc_id: &str) -> DomainResult<()> {
+        self.faults.disturb("delete_document")?;
+        self.inner.delete_document(doc_id)
+    }
+
+    fn list_documents(&self) -> Vec<String> {
+        self.faults.delay("list_documents");
+        self.inner.list_documents()
+    }
+
+    fn exists(&self, doc_id: &str) -> bool {
+        self.faults.delay("exists");
+        self.inner.exists(doc_id)
+    }
+
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    fn clear(&self) -> DomainResult<()> {
+        self.faults.disturb("clear")?;
+        self.inner.clear()
+    }
+
+    fn flush(&self) -> DomainResult<usize> {
+        self.faults.disturb("flush")?;
+        self.inner.flush()
+    }
+
+    fn is_durable(&self) -> bool {
+        self.inner.is_durable()
+    }
+
+    fn resident_documents(&self) -> Vec<String> {
+        self.inner.resident_documents()
+    }
+
+    fn eviction_candidates(&self) -> Vec<String> {
+        self.inner.eviction_candidates()
+    }
+
+    fn logged_updates(&self, doc_id: &str) -> DomainResult<Vec<LoggedUpdate>> {
+        self.inner.logged_updates(doc_id)
+    }
+
+    fn check_health(&self) -> DomainResult<()> {
+        self.faults.disturb("check_health")?;
+        self.inner.check_health()
+    }
+}
+
+/// Update broker decorator injecting delays, failures and dropped broadcasts.
+///
+/// A dropped broadcast is reported as published, so the other instances miss
+/// the update without this instance noticing, as with a lossy network.
+pub struct FaultInjectingBroker {
+    inner: Arc<dyn UpdateBroker>,
+    faults: Arc<FaultInjector>,
+}
+
+impl FaultInjectingBroker {
+    /// Wraps a broker.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The decorated broker
+    /// * `faults` - The injector drawing the faults
+    ///
+    /// # Returns
+    ///
+    /// A new `FaultInjectingBroker` instance.
+    pub fn new(inner: Arc<dyn UpdateBroker>, faults: Arc<FaultInjector>) -> Self {
+        Self { inner, faults }
+    }
+}
+
+impl UpdateBroker for FaultInjectingBroker {
+    fn publish(&self, doc_id: &str, update: &[u8]) -> DomainResult<()> {
+        self.faults.disturb("publish")?;
+        if self.faults.drop_broadcast() {
+            debug!("Dropping broadcast of an update to document '{}'", doc_id);
+            return Ok(());
+        }
+        self.inner.publish(doc_id, update)
+    }
+
+    fn subscribe(&self, doc_id: &str) -> DomainResult<mpsc::UnboundedReceiver<Vec<u8>>> {
+        self.faults.disturb("subscribe")?;
+        self.inner.subscribe(doc_id)
+    }
+
+    fn check_health(&self) -> DomainResult<()> {
+        self.faults.disturb("check_health")?;
+        self.inner.check_health()
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use tracing::warn;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::audit_sink::AuditSink,
+    value_objects::audit_entry::AuditEntry,
+};
+
+/// Extension of the files holding audit trails.
+const TRAIL_FILE_EXTENSION: &str = "jsonl";
+
+/// An audit sink keeping each document's trail in a file of a directory.
+///
+/// Trails are written as JSON lines, one entry per line, and are never
+/// truncated, so they can be shipped to a log pipeline or kept for compliance.
+/// Each file is named after its document, with every byte other than ASCII
+/// letters, digits, `-` and `_` percent-encoded so document IDs containing
+/// slashes or dots cannot escape the directory.
+pub struct FileAuditSink {
+    /// Directory holding the trail files
+    directory: PathBuf,
+    /// Number of the last entry of each trail written since startup; appends are
+    /// serialized through it
+    last_ids: Mutex<HashMap<String, u64>>,
+}
+
+impl FileAuditSink {
+    /// Creates a sink in a directory, creating the directory if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory holding the trail files
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(FileAuditSink)` - The sink
+    /// * `Err(DomainError)` - `StorageFailure` if the directory could not be created
+    pub fn new(directory: impl Into<PathBuf>) -> DomainResult<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory).map_err(|e| {
+            DomainError::storage(format!(
+                "Failed to create the audit trail directory '{}': {}",
+                directory.display(),
+                e
+            ))
+        })?;
+
+        Ok(Self {
+            directory,
+            last_ids: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the path of the file holding a document's trail.
+    fn path(&self, doc_id: &str) -> PathBuf {
+        let mut name = String::with_capacity(doc_id.len() + TRAIL_FILE_EXTENSION.len() + 1);
+        for byte in doc_id.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+                name.push(byte as char);
+            } else {
+                name.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        name.push('.');
+        name.push_str(TRAIL_FILE_EXTENSION);
+        self.directory.join(name)
+    }
+
+    /// Reads the entries of a document's trail, skipping torn or corrupt lines.
+    fn read(&self, doc_id: &str) -> DomainResult<Vec<AuditEntry>> {
+        let path = self.path(doc_id);
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(DomainError::storage(e)),
+        };
+
+        let mut entries = Vec::new();
+        for line in data.lines().filter(|line| !line.is_empty()) {
+            match sonic_rs::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("Skipped an audit entry of '{}': {}", path.display(), e),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Locks the numbers of the last entries.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, u64>> {
+        self.last_ids.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, doc_id: &str, mut entry: AuditEntry) -> DomainResult<u64> {
+        let mut last_ids = self.lock();
+        let last_id = match last_ids.get(doc_id) {
+            Some(&last_id) => last_id,
+            None => self.read(doc_id)?.last().map_or(0, |last| last.id),
+        };
+        entry.id = last_id + 1;
+
+        let mut line = sonic_rs::to_string(&entry).map_err(DomainError::storage)?;
+        line.push('\n');
+        let path = self.path(doc_id);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| {
+                DomainError::storage(format!(
+                    "Failed to append to the audit trail '{}': {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+        last_ids.insert(doc_id.to_string(), entry.id);
+        Ok(entry.id)
+    }
+
+    fn list(&self, doc_id: &str, after: u64, limit: usize) -> DomainResult<Vec<AuditEntry>> {
+        Ok(self
+            .read(doc_id)?
+            .into_iter()
+            .filter(|entry| entry.id > after)
+            .take(limit)
+            .collect())
+    }
+
+    fn clear(&self, doc_id: &str) -> DomainResult<()> {
+        let mut last_ids = self.lock();
+        match std::fs::remove_file(self.path(doc_id)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(DomainError::storage(e)),
+        }
+        last_ids.remove(doc_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use async_trait::async_trait;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::document_store::DocumentStore,
+};
+
+use crate::adapters::compression::CompressionCodec;
+
+/// Extension of the files holding document states.
+const STATE_FILE_EXTENSION: &str = "ydoc";
+
+/// A document store keeping each document's state in a file of a directory.
+///
+/// States are written compressed, as self-describing frames, which suits a
+/// cold archive tier on cheap disks or a mounted object storage bucket. Each
+/// file is named after its document, with every byte other than ASCII letters,
+/// digits, `-` and `_` percent-encoded so document IDs containing slashes or
+/// dots cannot escape the directory.
+pub struct FileDocumentStore {
+    /// Directory holding the state files
+    directory: PathBuf,
+    /// Compression applied to the written states
+    compression: CompressionCodec,
+}
+
+impl FileDocumentStore {
+    /// Creates a store in a directory, creating the directory if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory holding the state files
+    /// * `compression` - Compression applied to the written states
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(FileDocumentStore)` - The store
+    /// * `Err(DomainError)` - `StorageFailure` if the directory could not be created
+    pub fn new(directory: impl Into<PathBuf>, compression: CompressionCodec) -> DomainResult<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory).map_err(|e| {
+            DomainError::storage(format!(
+                "Failed to create the document store directory '{}': {}",
+                directory.display(),
+                e
+            ))
+        })?;
+
+        Ok(Self {
+            directory,
+            compression,
+        })
+    }
+
+    /// Returns the path of the file holding a document's state.
+    fn path(&self, doc_id: &str) -> PathBuf {
+        let mut name = String::with_capacity(doc_id.len() + STATE_FILE_EXTENSION.len() + 1);
+        for byte in doc_id.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+                name.push(byte as char);
+            } else {
+                name.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        name.push('.');
+        name.push_str(STATE_FILE_EXTENSION);
+        self.directory.join(name)
+    }
+}
+
+/// Decodes the document ID a state file is named after.
+///
+/// # Returns
+///
+/// `None` if the file name was not written by the store
+fn doc_id_from_file_name(file_name: &str) -> Option<String> {
+    let encoded = file_name
+        .strip_suffix(STATE_FILE_EXTENSION)?
+        .strip_suffix('.')?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut chars = encoded.bytes();
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let hex = [chars.next()?, chars.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[async_trait]
+impl DocumentStore for FileDocumentStore {
+    async fn load(&self, doc_id: &str) -> DomainResult<Option<Vec<u8>>> {
+        let frame = match tokio::fs::read(self.path(doc_id)).await {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(DomainError::storage(e)),
+        };
+        CompressionCodec::decode(&frame)
+            .map(Some)
+            .map_err(DomainError::storage)
+    }
+
+    async fn save(&self, doc_id: &str, state: &[u8]) -> DomainResult<()> {
+        let frame = self
+            .compression
+            .encode(state)
+            .map_err(DomainError::storage)?;
+
+        // Writes a temporary file first so a crash never leaves a truncated state
+        let path = self.path(doc_id);
+        let temporary = path.with_extension("tmp");
+        tokio::fs::write(&temporary, frame)
+            .await
+            .map_err(DomainError::storage)?;
+        tokio::fs::rename(&temporary, &path)
+            .await
+            .map_err(DomainError::storage)
+    }
+
+    async fn delete(&self, doc_id: &str) -> DomainResult<()> {
+        match tokio::fs::remove_file(self.path(doc_id)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(DomainError::storage(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> DomainResult<Vec<String>> {
+        let mut entries = tokio::fs::read_dir(&self.directory)
+            .await
+            .map_err(DomainError::storage)?;
+
+        let mut doc_ids = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(DomainError::storage)? {
+            if let Some(doc_id) = entry.file_name().to_str().and_then(doc_id_from_file_name) {
+                doc_ids.push(doc_id);
+            }
+        }
+        Ok(doc_ids)
+    }
+
+    async fn contains(&self, doc_id: &str) -> DomainResult<bool> {
+        tokio::fs::try_exists(self.path(doc_id))
+            .await
+            .map_err(DomainError::storage)
+    }
+
+    async fn check_health(&self) -> DomainResult<()> {
+        match tokio::fs::metadata(&self.directory).await {
+            Ok(metadata) if metadata.is_dir() => Ok(()),
+            Ok(_) => Err(DomainError::unavailable(format!(
+                "{} is not a directory",
+                self.directory.display()
+            ))),
+            Err(e) => Err(DomainError::unavailable(format!(
+                "Cannot access {}: {}",
+                self.directory.display(),
+                e
+            ))),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use tracing::warn;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::write_ahead_log::WriteAheadLog,
+};
+
+/// Extension of the segment files.
+const SEGMENT_FILE_EXTENSION: &str = "wal";
+
+/// Size of a record header: document identifier length, update length and checksum.
+const RECORD_HEADER_BYTES: usize = 12;
+
+/// The segment updates are appended to.
+struct Segment {
+    number: u64,
+    file: File,
+    size: u64,
+}
+
+/// A write-ahead log keeping its segments as files of a local directory.
+///
+/// Segments are named after their zero-padded number, e.g.
+/// `00000000000000000001.wal`, and hold a sequence of records laid out as
+/// `doc_id length: u32 | update length: u32 | CRC-32: u32 | doc_id | update`,
+/// integers big-endian and the checksum covering the document identifier and
+/// update. A segment is started at every checkpoint, at startup, and once the
+/// current one reaches the configured size.
+///
+/// Without syncing, appends reach the operating system before they are
+/// acknowledged, which survives a crash of the server but not of the host.
+pub struct FileWriteAheadLog {
+    /// Directory holding the segment files
+    directory: PathBuf,
+    /// Whether every append is synced to disk before it returns
+    sync: bool,
+    /// Size in bytes after which a new segment is started
+    segment_size: u64,
+    /// Number of the first segment of this run; older ones are left by previous runs
+    first: u64,
+    current: Mutex<Segment>,
+}
+
+impl FileWriteAheadLog {
+    /// Opens a log in a directory, creating the directory if needed.
+    ///
+    /// Segments left by previous runs are kept for [`replay`], and appends go
+    /// to a new segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory holding the segment files
+    /// * `sync` - Whether every append is synced to disk before it returns
+    /// * `segment_size` - Size in bytes after which a new segment is started
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(FileWriteAheadLog)` - The log
+    /// * `Err(DomainError)` - `StorageFailure` if the directory could not be read or the first
+    ///   segment created
+    ///
+    /// [`replay`]: WriteAheadLog::replay
+    pub fn new(directory: impl Into<PathBuf>, sync: bool, segment_size: u64) -> DomainResult<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory).map_err(|e| {
+            DomainError::storage(format!(
+                "Failed to create the write-ahead log directory '{}': {}",
+                directory.display(),
+                e
+            ))
+        })?;
+
+        let first = Self::segments_in(&directory)?
+            .last()
+            .map_or(1, |(number, _)| number + 1);
+        let current = Self::create_segment(&directory, first)?;
+        Ok(Self {
+            directory,
+            sync,
+            segment_size,
+            first,
+            current: Mutex::new(current),
+        })
+    }
+
+    /// Returns the path of a segment file.
+    fn segment_path(directory: &Path, number: u64) -> PathBuf {
+        directory.join(format!("{:020}.{}", number, SEGMENT_FILE_EXTENSION))
+    }
+
+    /// Lists the segment files of a directory with their numbers, oldest first.
+    fn segments_in(directory: &Path) -> DomainResult<Vec<(u64, PathBuf)>> {
+        let entries = std::fs::read_dir(directory).map_err(DomainError::storage)?;
+
+        let mut segments = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(DomainError::storage)?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_FILE_EXTENSION) {
+                continue;
+            }
+            if let Some(number) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            {
+                segments.push((number, path));
+            }
+        }
+        segments.sort();
+        Ok(segments)
+    }
+
+    /// Creates an empty segment file.
+    fn create_segment(directory: &Path, number: u64) -> DomainResult<Segment> {
+        let path = Self::segment_path(directory, number);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                DomainError::storage(format!(
+                    "Failed to create the write-ahead log segment '{}': {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        Ok(Segment {
+            number,
+            file,
+            size: 0,
+        })
+    }
+
+    /// Returns the checksum of a record.
+    fn checksum(doc_id: &[u8], update: &[u8]) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(doc_id);
+        hasher.update(update);
+        hasher.finalize()
+    }
+
+    /// Encodes a record.
+    fn encode_record(doc_id: &str, update: &[u8]) -> DomainResult<Vec<u8>> {
+        let too_large = |_| DomainError::PayloadTooLarge("Update exceeds 4 GiB".to_string());
+        let doc_id_length = u32::try_from(doc_id.len()).map_err(too_large)?;
+        let update_length = u32::try_from(update.len()).map_err(too_large)?;
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_BYTES + doc_id.len() + update.len());
+        record.extend_from_slice(&doc_id_length.to_be_bytes());
+        record.extend_from_slice(&update_length.to_be_bytes());
+        record.extend_from_slice(&Self::checksum(doc_id.as_bytes(), update).to_be_bytes());
+        record.extend_from_slice(doc_id.as_bytes());
+        record.extend_from_slice(update);
+        Ok(record)
+    }
+
+    /// Decodes the records of a segment, stopping at the first torn or corrupt one.
+    ///
+    /// Returns the records read and, if one stopped the decoding, why.
+    fn decode_records(data: &[u8]) -> (Vec<(String, Vec<u8>)>, Option<&'static str>) {
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let Some(header) = data.get(offset..offset + RECORD_HEADER_BYTES) else {
+                return (records, Some("truncated header"));
+            };
+            let word = |at: usize| {
+                u32::from_be_bytes(<[u8; 4]>::try_from(&header[at..at + 4]).unwrap_or_default())
+            };
+            let (doc_id_length, update_length) = (word(0) as usize, word(4) as usize);
+
+            let start = offset + RECORD_HEADER_BYTES;
+            let Some(body) = data.get(start..start + doc_id_length + update_length) else {
+                return (records, Some("truncated record"));
+            };
+            let (doc_id, update) = body.split_at(doc_id_length);
+            if Self::checksum(doc_id, update) != word(8) {
+                return (records, Some("checksum mismatch"));
+            }
+            let Ok(doc_id) = String::from_utf8(doc_id.to_vec()) else {
+                return (records, Some("invalid document identifier"));
+            };
+
+            records.push((doc_id, update.to_vec()));
+            offset = start + body.len();
+        }
+        (records, None)
+    }
+
+    /// Locks the current segment.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Segment> {
+        self.current.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl WriteAheadLog for FileWriteAheadLog {
+    fn append(&self, doc_id: &str, update: &[u8]) -> DomainResult<()> {
+        let record = Self::encode_record(doc_id, update)?;
+        let mut segment = self.lock();
+        if segment.size > 0 && segment.size + record.len() as u64 > self.segment_size {
+            *segment = Self::create_segment(&self.directory, segment.number + 1)?;
+        }
+
+        let written = segment.file.write_all(&record).and_then(|()| {
+            if self.sync {
+                segment.file.sync_data()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = written {
+            // A torn record ends the replay of its segment, so later updates go to a new one
+            if let Ok(next) = Self::create_segment(&self.directory, segment.number + 1) {
+                *segment = next;
+            }
+            return Err(DomainError::storage(format!(
+                "Failed to append to the write-ahead log: {}",
+                e
+            )));
+        }
+        segment.size += record.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&self) -> DomainResult<u64> {
+        let mut segment = self.lock();
+        let sealed = segment.number;
+        *segment = Self::create_segment(&self.directory, sealed + 1)?;
+        Ok(sealed)
+    }
+
+    fn truncate(&self, checkpoint: u64) -> DomainResult<()> {
+        let current = self.lock().number;
+        for (number, path) in Self::segments_in(&self.directory)? {
+            if number > checkpoint || number >= current {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(DomainError::storage(e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn replay(&self) -> DomainResult<Vec<(String, Vec<u8>)>> {
+        let mut updates = Vec::new();
+        for (number, path) in Self::segments_in(&self.directory)? {
+            if number >= self.first {
+                break;
+            }
+            let data = std::fs::read(&path).map_err(DomainError::storage)?;
+            let (records, stopped) = Self::decode_records(&data);
+            if let Some(reason) = stopped {
+                warn!(
+                    "Write-ahead log segment '{}' ends after {} records: {}",
+                    path.display(),
+                    records.len(),
+                    reason
+                );
+            }
+            updates.extend(records);
+        }
+        Ok(updates)
+    }
+}
@@ -0,0 +1,270 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{channel::mpsc as stream_mpsc, StreamExt};
+use tokio::{runtime::Handle, sync::mpsc};
+use tracing::{debug, info, warn};
+use volo_grpc::RecvStream;
+use yjs_collaboration_server_common::volo_gen::collaboration::{
+    client_message, server_message, ClientMessage, CollaborationServiceClientBuilder, JoinDocument,
+    LeaveDocument, ServerMessage, SyncStep1, UpdateMessage,
+};
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::document_forwarder::{DocumentForwarder, ForwardedDocument, OwnerMessage},
+    value_objects::cluster_member::ClusterMember,
+};
+use yrs::{updates::encoder::Encode, StateVector};
+
+/// Delay before reconnecting to the owner after the stream failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// An open `Collaborate` stream: the sender of its requests and its responses.
+type Session = (
+    stream_mpsc::UnboundedSender<ClientMessage>,
+    RecvStream<ServerMessage>,
+);
+
+/// A gRPC implementation of the document forwarding interface.
+///
+/// Every forwarded document opens a `Collaborate` stream to the owner's gRPC
+/// listener, joining the document as the user `cluster:{node_id}` of this
+/// instance, so the owner's access control must let that user write. Each time
+/// the stream opens, the relay requests the owner's whole state and answers the
+/// owner's state vector with the changes it is missing, so the updates applied
+/// while the owner was unreachable are not lost.
+#[derive(Clone, Debug)]
+pub struct GrpcDocumentForwarder {
+    /// This instance, identifying the relays to the owners
+    local: ClusterMember,
+}
+
+impl GrpcDocumentForwarder {
+    /// Creates a forwarder.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - This instance, as advertised to the other members
+    ///
+    /// # Returns
+    ///
+    /// A new `GrpcDocumentForwarder` instance
+    pub fn new(local: ClusterMember) -> Self {
+        Self { local }
+    }
+}
+
+impl DocumentForwarder for GrpcDocumentForwarder {
+    fn forward(&self, doc_id: &str, owner: &ClusterMember) -> DomainResult<ForwardedDocument> {
+        let runtime = Handle::try_current().map_err(|_| {
+            DomainError::unavailable("Documents must be forwarded within a Tokio runtime")
+        })?;
+
+        let (to_owner, local_updates) = mpsc::unbounded_channel();
+        let (owner_messages, from_owner) = mpsc::unbounded_channel();
+        let relay = Relay {
+            doc_id: doc_id.to_string(),
+            client_id: format!("cluster:{}", self.local.node_id),
+            owner: owner.clone(),
+            local_updates,
+            owner_messages,
+        };
+        runtime.spawn(relay.run());
+
+        Ok(ForwardedDocument {
+            to_owner,
+            from_owner,
+        })
+    }
+}
+
+/// Relay of a document between this instance and its owner.
+struct Relay {
+    doc_id: String,
+    /// Client and user identifier of this instance on the owner
+    client_id: String,
+    owner: ClusterMember,
+    /// Updates applied on this instance, to send to the owner
+    local_updates: mpsc::UnboundedReceiver<Vec<u8>>,
+    /// Messages of the owner, to apply on this instance
+    owner_messages: mpsc::UnboundedSender<OwnerMessage>,
+}
+
+impl Relay {
+    /// Relays the document, reconnecting after failures, until either side is dropped.
+    async fn run(mut self) {
+        loop {
+            match self.connect().await {
+                Ok((sender, stream)) => {
+                    info!(
+                        "Forwarding document '{}' to cluster node '{}' at {}",
+                        self.doc_id, self.owner.node_id, self.owner.grpc_addr
+                    );
+                    if !self.relay(&sender, stream).await {
+                        let _ = self.send(
+                            &sender,
+                            client_message::MessageType::LeaveDocument(LeaveDocument {
+                                user_id: self.client_id.clone().into(),
+                            }),
+                        );
+                        return;
+                    }
+                }
+                Err(e) => warn!(
+                    "Failed to reach cluster node '{}' owning document '{}': {}",
+                    self.owner.node_id, self.doc_id, e
+                ),
+            }
+
+            if self.owner_messages.is_closed() {
+                return;
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Opens a stream to the owner, joins the document and requests its whole state.
+    async fn connect(&self) -> Result<Session, String> {
+        let addr = tokio::net::lookup_host(&self.owner.grpc_addr)
+            .await
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("{} resolves to no address", self.owner.grpc_addr))?;
+        let client = CollaborationServiceClientBuilder::new("yjs-collaboration-server")
+            .address(addr)
+            .build();
+
+        let (sender, requests) = stream_mpsc::unbounded();
+        let stream = client
+            .collaborate(requests)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_inner();
+
+        self.send(
+            &sender,
+            client_message::MessageType::JoinDocument(JoinDocument {
+                user_id: self.client_id.clone().into(),
+                user_name: self.client_id.clone().into(),
+                user_color: Default::default(),
+                user_metadata: Default::default(),
+                echo_own_updates: false,
+                accept_compressed_updates: false,
+                accept_encoding: Default::default(),
+                update_encoding: Default::default(),
+            }),
+        )?;
+        self.send(
+            &sender,
+            client_message::MessageType::SyncStep1(SyncStep1 {
+                state_vector: StateVector::default().encode_v1().into(),
+            }),
+        )?;
+        Ok((sender, stream))
+    }
+
+    /// Relays the document on an open stream.
+    ///
+    /// Returns whether the document is still forwarded, so the stream must be reopened.
+    async fn relay(
+        &mut self,
+        sender: &stream_mpsc::UnboundedSender<ClientMessage>,
+        mut stream: RecvStream<ServerMessage>,
+    ) -> bool {
+        loop {
+            tokio::select! {
+                update = self.local_updates.recv() => {
+                    let Some(update) = update else {
+                        return false;
+                    };
+                    let message = client_message::MessageType::Update(UpdateMessage {
+                        update_data: update.into(),
+                        origin_client_id: self.client_id.clone().into(),
+                        sequence_number: 0,
+                        dictionary_id: 0,
+                        encoding: Default::default(),
+                        origin_user_id: Default::default(),
+                        origin_kind: Default::default(),
+                    });
+                    // The owner's state vector is answered with the update when the
+                    // stream is reopened
+                    if self.send(sender, message).is_err() {
+                        return true;
+                    }
+                }
+                message = stream.next() => {
+                    let message = match message {
+                        Some(Ok(message)) => message,
+                        Some(Err(status)) => {
+                            warn!(
+                                "Forwarding stream of document '{}' failed: {}",
+                                self.doc_id, status
+                            );
+                            return true;
+                        }
+                        None => {
+                            debug!(
+                                "Forwarding stream of document '{}' closed by its owner",
+                                self.doc_id
+                            );
+                            return true;
+                        }
+                    };
+                    let Some(message) = self.owner_message(message) else {
+                        continue;
+                    };
+                    if self.owner_messages.send(message).is_err() {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Converts a message of the owner into the message to apply on this instance, if any.
+    fn owner_message(&self, message: ServerMessage) -> Option<OwnerMessage> {
+        match message.message_type? {
+            server_message::MessageType::SyncStep2(step2) => {
+                Some(OwnerMessage::Update(step2.update_data.to_vec()))
+            }
+            server_message::MessageType::SyncResponse(response) => {
+                Some(OwnerMessage::Update(response.update_data.to_vec()))
+            }
+            server_message::MessageType::Update(update) => {
+                Some(OwnerMessage::Update(update.update_data.to_vec()))
+            }
+            server_message::MessageType::SyncStep1(step1) => {
+                Some(OwnerMessage::StateVector(step1.state_vector.to_vec()))
+            }
+            server_message::MessageType::Error(error) => {
+                warn!(
+                    "Cluster node '{}' rejected the forwarding of document '{}': {}",
+                    self.owner.node_id, self.doc_id, error.error_message
+                );
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Sends a message on the stream, stamped with this instance's clock.
+    fn send(
+        &self,
+        sender: &stream_mpsc::UnboundedSender<ClientMessage>,
+        message_type: client_message::MessageType,
+    ) -> Result<(), String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        sender
+            .unbounded_send(ClientMessage {
+                client_id: self.client_id.clone().into(),
+                document_id: self.doc_id.clone().into(),
+                timestamp,
+                message_type: Some(message_type),
+                tenant: Default::default(),
+            })
+            .map_err(|_| "the forwarding stream is closed".to_string())
+    }
+}
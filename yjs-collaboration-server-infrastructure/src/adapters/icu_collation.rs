@@ -0,0 +1,65 @@
+use std::cmp::Ordering;
+
+use icu_collator::{
+    options::{CollatorOptions, Strength},
+    Collator, CollatorBorrowed,
+};
+use icu_locale_core::Locale;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::collation::Collation,
+};
+
+/// A collation following the Unicode Collation Algorithm, tailored to a locale
+/// with the ICU4X data compiled into the server.
+///
+/// Names are ordered as speakers of the locale expect, e.g. accented letters
+/// next to their base letter. When case-insensitive, names differing only by
+/// case, such as `Draft` and `draft`, are equivalent; accents still tell names
+/// apart.
+pub struct IcuCollation {
+    collator: CollatorBorrowed<'static>,
+}
+
+impl IcuCollation {
+    /// Creates a collation for a locale.
+    ///
+    /// # Arguments
+    ///
+    /// * `locale` - BCP 47 language tag such as `de`, `zh-u-co-pinyin` or `sv`; an empty tag
+    ///   selects the language-neutral root collation
+    /// * `case_sensitive` - Whether names differing only by case are told apart
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(IcuCollation)` - The collation
+    /// * `Err(DomainError)` - `InvalidArgument` if the locale is malformed or has no collation data
+    pub fn new(locale: &str, case_sensitive: bool) -> DomainResult<Self> {
+        let locale = if locale.is_empty() {
+            Locale::default()
+        } else {
+            locale.parse::<Locale>().map_err(|e| {
+                DomainError::InvalidArgument(format!(
+                    "Invalid collation locale '{}': {}",
+                    locale, e
+                ))
+            })?
+        };
+
+        let mut options = CollatorOptions::default();
+        if !case_sensitive {
+            options.strength = Some(Strength::Secondary);
+        }
+        let collator = Collator::try_new((&locale).into(), options).map_err(|e| {
+            DomainError::InvalidArgument(format!("No collation for locale '{}': {}", locale, e))
+        })?;
+
+        Ok(Self { collator })
+    }
+}
+
+impl Collation for IcuCollation {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.collator.compare(a, b)
+    }
+}
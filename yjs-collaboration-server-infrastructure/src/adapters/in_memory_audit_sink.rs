@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+use dashmap::DashMap;
+use yjs_collaboration_server_domain::{
+    errors::DomainResult, repositories::audit_sink::AuditSink,
+    value_objects::audit_entry::AuditEntry,
+};
+
+/// The audit trail of a document.
+#[derive(Default)]
+struct Trail {
+    /// Number of the last entry recorded
+    last_id: u64,
+    /// The entries kept, oldest first
+    entries: VecDeque<AuditEntry>,
+}
+
+/// An in-memory implementation of the audit sink interface.
+///
+/// Trails are kept in a concurrent map of per-document queues, bounded by
+/// dropping the oldest entries, and are lost when the server restarts.
+pub struct InMemoryAuditSink {
+    trails: DashMap<String, Trail>,
+    /// Maximum entries kept per document (`0` = unlimited)
+    max_entries: usize,
+}
+
+impl InMemoryAuditSink {
+    /// Creates a new, empty in-memory audit sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_entries` - Maximum entries kept per document, the oldest being dropped first (`0` =
+    ///   unlimited)
+    ///
+    /// # Returns
+    ///
+    /// A new `InMemoryAuditSink` instance.
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            trails: DashMap::new(),
+            max_entries,
+        }
+    }
+}
+
+impl AuditSink for InMemoryAuditSink {
+    fn record(&self, doc_id: &str, mut entry: AuditEntry) -> DomainResult<u64> {
+        let mut trail = self.trails.entry(doc_id.to_string()).or_default();
+        trail.last_id += 1;
+        entry.id = trail.last_id;
+        trail.entries.push_back(entry);
+        if self.max_entries > 0 && trail.entries.len() > self.max_entries {
+            trail.entries.pop_front();
+        }
+        Ok(trail.last_id)
+    }
+
+    fn list(&self, doc_id: &str, after: u64, limit: usize) -> DomainResult<Vec<AuditEntry>> {
+        Ok(self
+            .trails
+            .get(doc_id)
+            .map(|trail| {
+                let start = trail.entries.partition_point(|entry| entry.id <= after);
+                trail.entries.range(start..).take(limit).cloned().collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn clear(&self, doc_id: &str) -> DomainResult<()> {
+        self.trails.remove(doc_id);
+        Ok(())
+    }
+}
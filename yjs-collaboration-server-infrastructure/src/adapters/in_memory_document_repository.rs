@@ -1,11 +1,15 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
     repositories::document_repository::DocumentRepository,
-    services::document_service::SingleDocumentServiceImpl,
+    services::{compute_pool::ComputePool, document_service::SingleDocumentServiceImpl},
 };
 
 /// Global in-memory storage for collaborative documents.
@@ -14,9 +18,31 @@ use yjs_collaboration_server_domain::{
 /// It uses a thread-safe concurrent map with document IDs as keys and document services as values.
 /// DashMap provides high-performance concurrent access without global locking.
 /// The `Lazy` initialization ensures the storage is created only when first accessed.
-static DOCUMENTS: Lazy<DashMap<String, Arc<Mutex<SingleDocumentServiceImpl>>>> =
+static DOCUMENTS: Lazy<DashMap<String, Arc<RwLock<SingleDocumentServiceImpl>>>> =
     Lazy::new(|| DashMap::new());
 
+/// Last time each document of `DOCUMENTS` was accessed, which drives eviction.
+static LAST_ACCESS: Lazy<DashMap<String, Instant>> = Lazy::new(DashMap::new);
+
+/// Selects the documents evicted from memory.
+///
+/// Both limits apply when set: documents idle for longer than the TTL are
+/// evicted, as are the least recently used documents beyond the resident limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionPolicy {
+    /// Time after which a document that was not accessed is evicted
+    pub idle_ttl: Option<Duration>,
+    /// Number of documents kept in memory at most
+    pub max_resident: Option<usize>,
+}
+
+impl EvictionPolicy {
+    /// Returns whether the policy ever evicts a document.
+    pub fn is_enabled(&self) -> bool {
+        self.idle_ttl.is_some() || self.max_resident.is_some()
+    }
+}
+
 /// An in-memory implementation of the document repository interface.
 ///
 /// This repository stores all documents in memory using a static `DashMap`.
@@ -28,9 +54,18 @@ static DOCUMENTS: Lazy<DashMap<String, Arc<Mutex<SingleDocumentServiceImpl>>>> =
 ///
 /// Note: All documents are lost when the server restarts.
 ///
+/// With an eviction policy, documents idle for too long or beyond the resident
+/// limit are offered for eviction; the document service saves them to its
+/// document store first and reloads them from it on their next access.
+///
 /// This implementation contains all the concrete CRUD logic that the domain
 /// layer abstracts through the DocumentRepository trait.
-pub struct InMemoryDocumentRepository;
+pub struct InMemoryDocumentRepository {
+    /// Compute pool shared by the documents created through this repository
+    compute: Arc<ComputePool>,
+    /// Selects the documents evicted from memory
+    eviction: EvictionPolicy,
+}
 
 impl InMemoryDocumentRepository {
     /// Creates a new in-memory document repository instance.
@@ -39,7 +74,49 @@ impl InMemoryDocumentRepository {
     ///
     /// A new `InMemoryDocumentRepository` instance.
     pub fn new() -> Self {
-        Self {}
+        Self::with_compute_pool(Arc::new(ComputePool::default()))
+    }
+
+    /// Creates a new in-memory document repository whose documents run CRDT
+    /// operations on the given compute pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `compute` - The compute pool shared by all created documents
+    ///
+    /// # Returns
+    ///
+    /// A new `InMemoryDocumentRepository` instance.
+    pub fn with_compute_pool(compute: Arc<ComputePool>) -> Self {
+        Self {
+            compute,
+            eviction: EvictionPolicy::default(),
+        }
+    }
+
+    /// Offers documents for eviction according to a policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `eviction` - Selects the documents evicted from memory
+    ///
+    /// # Returns
+    ///
+    /// The `InMemoryDocumentRepository` offering documents for eviction
+    pub fn with_eviction(mut self, eviction: EvictionPolicy) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
+    /// Records that a document was accessed.
+    fn touch(doc_id: &str) {
+        LAST_ACCESS.insert(doc_id.to_string(), Instant::now());
+    }
+
+    fn new_document(&self) -> Arc<RwLock<SingleDocumentServiceImpl>> {
+        Arc::new(RwLock::new(SingleDocumentServiceImpl::with_compute_pool(
+            self.compute.clone(),
+        )))
     }
 }
 
@@ -50,14 +127,15 @@ impl DocumentRepository for InMemoryDocumentRepository {
     fn create_document(
         &self,
         doc_id: &str,
-    ) -> Result<Arc<Mutex<SingleDocumentServiceImpl>>, String> {
+    ) -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>> {
         // With DashMap, we can check for existence and insert atomically
         if DOCUMENTS.contains_key(doc_id) {
-            return Err(format!("Document with ID '{}' already exists", doc_id));
+            return Err(DomainError::Conflict(doc_id.to_string()));
         }
 
-        let doc_service = Arc::new(Mutex::new(SingleDocumentServiceImpl::new()));
+        let doc_service = self.new_document();
         DOCUMENTS.insert(doc_id.to_string(), doc_service.clone());
+        Self::touch(doc_id);
 
         Ok(doc_service)
     }
@@ -65,21 +143,25 @@ impl DocumentRepository for InMemoryDocumentRepository {
     /// Retrieves an existing document by ID.
     ///
     /// This is the concrete implementation of document retrieval logic.
-    fn get_document(&self, doc_id: &str) -> Option<Arc<Mutex<SingleDocumentServiceImpl>>> {
+    fn get_document(&self, doc_id: &str) -> Option<Arc<RwLock<SingleDocumentServiceImpl>>> {
         // With DashMap, we can directly get values without locking the entire map
-        DOCUMENTS.get(doc_id).map(|entry| entry.value().clone())
+        let document = DOCUMENTS.get(doc_id).map(|entry| entry.value().clone())?;
+        Self::touch(doc_id);
+        Some(document)
     }
 
     /// Retrieves an existing document by ID or creates a new one if it doesn't exist.
     ///
     /// This is the concrete implementation that combines get and create operations.
-    fn get_or_create(&self, doc_id: &str) -> Arc<Mutex<SingleDocumentServiceImpl>> {
+    fn get_or_create(&self, doc_id: &str) -> Arc<RwLock<SingleDocumentServiceImpl>> {
         // Use entry API for atomic get-or-insert operations
-        DOCUMENTS
+        let document = DOCUMENTS
             .entry(doc_id.to_string())
-            .or_insert_with(|| Arc::new(Mutex::new(SingleDocumentServiceImpl::new())))
+            .or_insert_with(|| self.new_document())
             .value()
-            .clone()
+            .clone();
+        Self::touch(doc_id);
+        document
     }
 
     /// Updates an existing document.
@@ -88,24 +170,26 @@ impl DocumentRepository for InMemoryDocumentRepository {
     fn update_document(
         &self,
         doc_id: &str,
-        document: Arc<Mutex<SingleDocumentServiceImpl>>,
-    ) -> Result<(), String> {
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+    ) -> DomainResult<()> {
         if !DOCUMENTS.contains_key(doc_id) {
-            return Err(format!("Document with ID '{}' does not exist", doc_id));
+            return Err(DomainError::NotFound(doc_id.to_string()));
         }
 
         DOCUMENTS.insert(doc_id.to_string(), document);
+        Self::touch(doc_id);
         Ok(())
     }
 
     /// Deletes a document by ID.
     ///
     /// This is the concrete implementation of document deletion logic.
-    fn delete_document(&self, doc_id: &str) -> Result<(), String> {
+    fn delete_document(&self, doc_id: &str) -> DomainResult<()> {
         if DOCUMENTS.remove(doc_id).is_some() {
+            LAST_ACCESS.remove(doc_id);
             Ok(())
         } else {
-            Err(format!("Document with ID '{}' does not exist", doc_id))
+            Err(DomainError::NotFound(doc_id.to_string()))
         }
     }
 
@@ -134,10 +218,45 @@ impl DocumentRepository for InMemoryDocumentRepository {
     /// Clears all documents from the repository.
     ///
     /// This is the concrete implementation of repository clearing logic.
-    fn clear(&self) -> Result<(), String> {
+    fn clear(&self) -> DomainResult<()> {
         DOCUMENTS.clear();
+        LAST_ACCESS.clear();
         Ok(())
     }
+
+    /// Lists the documents idle for longer than the TTL, then the least recently
+    /// used documents beyond the resident limit.
+    ///
+    /// This is the concrete implementation of the eviction policy.
+    fn eviction_candidates(&self) -> Vec<String> {
+        if !self.eviction.is_enabled() {
+            return Vec::new();
+        }
+
+        // Forgets the documents deleted while they were being accessed
+        LAST_ACCESS.retain(|doc_id, _| DOCUMENTS.contains_key(doc_id));
+        let mut documents: Vec<(String, Instant)> = LAST_ACCESS
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+        documents.sort_by_key(|(_, accessed)| *accessed);
+
+        let over_limit = self.eviction.max_resident.map_or(0, |max_resident| {
+            documents.len().saturating_sub(max_resident)
+        });
+        documents
+            .into_iter()
+            .enumerate()
+            .filter(|(rank, (_, accessed))| {
+                *rank < over_limit
+                    || self
+                        .eviction
+                        .idle_ttl
+                        .is_some_and(|idle_ttl| accessed.elapsed() >= idle_ttl)
+            })
+            .map(|(_, (doc_id, _))| doc_id)
+            .collect()
+    }
 }
 
 impl Default for InMemoryDocumentRepository {
@@ -0,0 +1,55 @@
+use async_trait::async_trait;
+use dashmap::DashMap;
+use yjs_collaboration_server_domain::{
+    errors::DomainResult, repositories::document_store::DocumentStore,
+};
+
+/// An in-memory implementation of the document store interface.
+///
+/// Saved states are kept in a concurrent map and are lost when the server
+/// restarts; the store suits tests and deployments that only need documents
+/// to outlive their eviction from the repository.
+#[derive(Default)]
+pub struct InMemoryDocumentStore {
+    states: DashMap<String, Vec<u8>>,
+}
+
+impl InMemoryDocumentStore {
+    /// Creates a new, empty in-memory document store.
+    ///
+    /// # Returns
+    ///
+    /// A new `InMemoryDocumentStore` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DocumentStore for InMemoryDocumentStore {
+    async fn load(&self, doc_id: &str) -> DomainResult<Option<Vec<u8>>> {
+        Ok(self.states.get(doc_id).map(|state| state.value().clone()))
+    }
+
+    async fn save(&self, doc_id: &str, state: &[u8]) -> DomainResult<()> {
+        self.states.insert(doc_id.to_string(), state.to_vec());
+        Ok(())
+    }
+
+    async fn delete(&self, doc_id: &str) -> DomainResult<()> {
+        self.states.remove(doc_id);
+        Ok(())
+    }
+
+    async fn list(&self) -> DomainResult<Vec<String>> {
+        Ok(self
+            .states
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect())
+    }
+
+    async fn contains(&self, doc_id: &str) -> DomainResult<bool> {
+        Ok(self.states.contains_key(doc_id))
+    }
+}
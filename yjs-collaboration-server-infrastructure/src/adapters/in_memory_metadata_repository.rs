@@ -0,0 +1,52 @@
+use dashmap::DashMap;
+use yjs_collaboration_server_domain::{
+    errors::DomainResult, repositories::document_metadata_repository::DocumentMetadataRepository,
+    value_objects::document_metadata::DocumentMetadata,
+};
+
+/// An in-memory implementation of the document metadata repository interface.
+///
+/// Metadata is kept in a concurrent map alongside the in-memory documents and
+/// is lost when the server restarts.
+#[derive(Default)]
+pub struct InMemoryMetadataRepository {
+    metadata: DashMap<String, DocumentMetadata>,
+}
+
+impl InMemoryMetadataRepository {
+    /// Creates a new, empty in-memory metadata repository.
+    ///
+    /// # Returns
+    ///
+    /// A new `InMemoryMetadataRepository` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DocumentMetadataRepository for InMemoryMetadataRepository {
+    fn get(&self, doc_id: &str) -> DomainResult<DocumentMetadata> {
+        Ok(self
+            .metadata
+            .get(doc_id)
+            .map(|metadata| metadata.clone())
+            .unwrap_or_default())
+    }
+
+    fn put(&self, doc_id: &str, metadata: &DocumentMetadata) -> DomainResult<()> {
+        if metadata.is_empty() {
+            self.metadata.remove(doc_id);
+        } else {
+            self.metadata.insert(doc_id.to_string(), metadata.clone());
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> DomainResult<Vec<(String, DocumentMetadata)>> {
+        Ok(self
+            .metadata
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect())
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use dashmap::DashMap;
+use yjs_collaboration_server_domain::{
+    errors::DomainResult, repositories::version_repository::VersionRepository,
+    value_objects::document_version::DocumentVersion,
+};
+
+/// An in-memory implementation of the version repository interface.
+///
+/// Versions are kept in a concurrent map of per-document timelines, ordered by
+/// version number, and are lost when the server restarts.
+#[derive(Default)]
+pub struct InMemoryVersionRepository {
+    versions: DashMap<String, BTreeMap<u64, (DocumentVersion, Vec<u8>)>>,
+}
+
+impl InMemoryVersionRepository {
+    /// Creates a new, empty in-memory version repository.
+    ///
+    /// # Returns
+    ///
+    /// A new `InMemoryVersionRepository` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl VersionRepository for InMemoryVersionRepository {
+    fn list(&self, doc_id: &str) -> DomainResult<Vec<DocumentVersion>> {
+        Ok(self
+            .versions
+            .get(doc_id)
+            .map(|timeline| {
+                timeline
+                    .values()
+                    .map(|(version, _)| version.clone())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn get(&self, doc_id: &str, version: u64) -> DomainResult<Option<(DocumentVersion, Vec<u8>)>> {
+        Ok(self
+            .versions
+            .get(doc_id)
+            .and_then(|timeline| timeline.get(&version).cloned()))
+    }
+
+    fn put(&self, doc_id: &str, version: &DocumentVersion, snapshot: &[u8]) -> DomainResult<()> {
+        self.versions
+            .entry(doc_id.to_string())
+            .or_default()
+            .insert(version.version, (version.clone(), snapshot.to_vec()));
+        Ok(())
+    }
+
+    fn remove(&self, doc_id: &str, version: u64) -> DomainResult<()> {
+        self.versions.remove_if_mut(doc_id, |_, timeline| {
+            timeline.remove(&version);
+            timeline.is_empty()
+        });
+        Ok(())
+    }
+
+    fn clear(&self, doc_id: &str) -> DomainResult<()> {
+        self.versions.remove(doc_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    time::Duration,
+};
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rskafka::{
+    client::{
+        partition::{Compression, PartitionClient, UnknownTopicHandling},
+        Client, ClientBuilder,
+    },
+    record::Record,
+};
+use sonic_rs::json;
+use tokio::{runtime::Handle, sync::mpsc, time::Instant};
+use tracing::{error, info, warn};
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::update_exporter::UpdateExporter,
+    value_objects::exported_update::ExportedUpdate,
+};
+
+/// Settings of a Kafka update export.
+#[derive(Clone, Debug)]
+pub struct KafkaExportSettings {
+    /// Bootstrap brokers, e.g. `127.0.0.1:9092`
+    pub brokers: Vec<String>,
+    /// Topic the updates are produced to
+    pub topic: String,
+    /// Maximum updates produced in one batch
+    pub batch_size: usize,
+    /// Maximum time an update waits for its batch to fill up
+    pub linger: Duration,
+    /// Updates waiting to be produced before new ones are dropped
+    pub queue_capacity: usize,
+    /// Attempts made to produce a batch before it is dead-lettered
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after every failed attempt
+    pub retry_backoff: Duration,
+    /// File the batches that could not be produced are appended to, or `None` to drop them
+    pub dead_letter_path: Option<PathBuf>,
+}
+
+/// A Kafka implementation of the update export interface.
+///
+/// Updates are queued and produced in batches, keyed by their document's
+/// identifier, so a document's updates land on the same partition, in order.
+/// The record's value is the binary Yjs update, and its headers carry the
+/// sequence number, the origin, the source and the user of the update.
+///
+/// A batch that cannot be produced is retried with an exponential backoff,
+/// reconnecting to the brokers between attempts. Once its attempts are
+/// exhausted, it is appended to the dead-letter file as JSON lines, with the
+/// update encoded in base64, to be replayed by an operator.
+pub struct KafkaUpdateExporter {
+    /// Queue of the updates drained by the producer task
+    queue: mpsc::Sender<ExportedUpdate>,
+}
+
+impl KafkaUpdateExporter {
+    /// Creates an exporter and starts its producer task.
+    ///
+    /// Must be called within a Tokio runtime, which then drives the producer
+    /// task. The connection is established in the background, so unreachable
+    /// brokers are reported in the logs rather than here.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Brokers, topic, batching, retries and dead-letter file of the export
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(KafkaUpdateExporter)` - The exporter
+    /// * `Err(String)` - If no broker or topic is configured, or no runtime is running
+    pub fn connect(settings: KafkaExportSettings) -> Result<Self, String> {
+        if settings.brokers.is_empty() {
+            return Err("The Kafka export requires at least one broker".to_string());
+        }
+        if settings.topic.is_empty() {
+            return Err("The Kafka export requires a topic".to_string());
+        }
+        let runtime = Handle::try_current()
+            .map_err(|_| "Kafka export must be started within a Tokio runtime".to_string())?;
+
+        let (queue, updates) = mpsc::channel(settings.queue_capacity.max(1));
+        info!(
+            "Exporting document updates to Kafka topic '{}'",
+            settings.topic
+        );
+        runtime.spawn(Producer::new(settings).run(updates));
+
+        Ok(Self { queue })
+    }
+}
+
+impl UpdateExporter for KafkaUpdateExporter {
+    fn export(&self, update: ExportedUpdate) -> DomainResult<()> {
+        self.queue.try_send(update).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => {
+                DomainError::unavailable("Kafka export queue is full")
+            }
+            mpsc::error::TrySendError::Closed(_) => {
+                DomainError::unavailable("Kafka producer has stopped")
+            }
+        })
+    }
+}
+
+/// Producer task of an exporter, connected lazily.
+struct Producer {
+    settings: KafkaExportSettings,
+    client: Option<Client>,
+    /// Partitions of the topic, as listed when connecting
+    partitions: Vec<i32>,
+    partition_clients: HashMap<i32, PartitionClient>,
+}
+
+impl Producer {
+    fn new(settings: KafkaExportSettings) -> Self {
+        Self {
+            settings,
+            client: None,
+            partitions: Vec::new(),
+            partition_clients: HashMap::new(),
+        }
+    }
+
+    /// Produces the queued updates in batches until the exporter is dropped.
+    async fn run(mut self, mut updates: mpsc::Receiver<ExportedUpdate>) {
+        while let Some(update) = updates.recv().await {
+            let mut batch = vec![update];
+            let deadline = Instant::now() + self.settings.linger;
+            while batch.len() < self.settings.batch_size {
+                match tokio::time::timeout_at(deadline, updates.recv()).await {
+                    Ok(Some(update)) => batch.push(update),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            self.deliver(batch).await;
+        }
+    }
+
+    /// Produces a batch, retrying each partition's share until it succeeds or its attempts
+    /// are exhausted, in which case it is dead-lettered.
+    async fn deliver(&mut self, batch: Vec<ExportedUpdate>) {
+        let mut attempt = 1;
+        let mut backoff = self.settings.retry_backoff;
+        let mut pending = batch;
+
+        loop {
+            pending = match self.produce(pending).await {
+                Ok(()) => return,
+                Err((failed, e)) => {
+                    warn!(
+                        "Failed to export {} updates to Kafka (attempt {}/{}): {}",
+                        failed.len(),
+                        attempt,
+                        self.settings.max_attempts,
+                        e
+                    );
+                    failed
+                }
+            };
+            // Reconnect on the next attempt, in case the brokers or partitions changed
+            self.client = None;
+            self.partition_clients.clear();
+
+            if attempt >= self.settings.max_attempts {
+                self.dead_letter(&pending);
+                return;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2);
+            attempt += 1;
+        }
+    }
+
+    /// Produces a batch, grouped by partition.
+    ///
+    /// Returns the updates of the partitions that failed, with the last error.
+    async fn produce(
+        &mut self,
+        batch: Vec<ExportedUpdate>,
+    ) -> Result<(), (Vec<ExportedUpdate>, String)> {
+        if let Err(e) = self.connect().await {
+            return Err((batch, e));
+        }
+
+        let mut groups: BTreeMap<i32, Vec<ExportedUpdate>> = BTreeMap::new();
+        for update in batch {
+            let partition = self.partition(&update.doc_id);
+            groups.entry(partition).or_default().push(update);
+        }
+
+        let mut failed = Vec::new();
+        let mut last_error = String::new();
+        for (partition, updates) in groups {
+            let records = updates.iter().map(record).collect();
+            let produced = match self.partition_client(partition).await {
+                Ok(client) => client
+                    .produce(records, Compression::NoCompression)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = produced {
+                last_error = format!("partition {}: {}", partition, e);
+                failed.extend(updates);
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err((failed, last_error))
+        }
+    }
+
+    /// Connects to the brokers and lists the partitions of the topic, if not connected.
+    async fn connect(&mut self) -> Result<(), String> {
+        if self.client.is_some() {
+            return Ok(());
+        }
+
+        let client = ClientBuilder::new(self.settings.brokers.clone())
+            .build()
+            .await
+            .map_err(|e| format!("Failed to connect to Kafka: {}", e))?;
+        let topics = client
+            .list_topics()
+            .await
+            .map_err(|e| format!("Failed to list the Kafka topics: {}", e))?;
+        let topic = topics
+            .into_iter()
+            .find(|topic| topic.name == self.settings.topic)
+            .ok_or_else(|| format!("Kafka topic '{}' does not exist", self.settings.topic))?;
+
+        self.partitions = topic.partitions.into_iter().collect();
+        if self.partitions.is_empty() {
+            return Err(format!(
+                "Kafka topic '{}' has no partition",
+                self.settings.topic
+            ));
+        }
+        self.client = Some(client);
+        Ok(())
+    }
+
+    /// Returns the client of a partition of the topic, creating it if needed.
+    async fn partition_client(&mut self, partition: i32) -> Result<&PartitionClient, String> {
+        let Some(client) = &self.client else {
+            return Err("Not connected to Kafka".to_string());
+        };
+        if !self.partition_clients.contains_key(&partition) {
+            let partition_client = client
+                .partition_client(
+                    self.settings.topic.clone(),
+                    partition,
+                    UnknownTopicHandling::Error,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+            self.partition_clients.insert(partition, partition_client);
+        }
+        self.partition_clients
+            .get(&partition)
+            .ok_or_else(|| "Partition client dropped".to_string())
+    }
+
+    /// Returns the partition of a document's updates.
+    fn partition(&self, doc_id: &str) -> i32 {
+        // FNV-1a, stable across instances and restarts, unlike the standard library's hasher
+        let hash = doc_id
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        let index = (hash % self.partitions.len().max(1) as u64) as usize;
+        self.partitions.get(index).copied().unwrap_or_default()
+    }
+
+    /// Appends updates that could not be produced to the dead-letter file, if any.
+    fn dead_letter(&self, updates: &[ExportedUpdate]) {
+        let Some(path) = &self.settings.dead_letter_path else {
+            error!(
+                "Dropped {} updates that could not be exported to Kafka",
+                updates.len()
+            );
+            return;
+        };
+
+        let mut lines = String::new();
+        for update in updates {
+            let line = json!({
+                "doc_id": update.doc_id,
+                "sequence_number": update.sequence_number,
+                "origin": update.origin.to_string(),
+                "source": update.source,
+                "user_id": update.user_id,
+                "applied_at": update.applied_at,
+                "payload": base64::engine::general_purpose::STANDARD.encode(&update.payload),
+            });
+            lines.push_str(&line.to_string());
+            lines.push('\n');
+        }
+
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+        match written {
+            Ok(()) => warn!(
+                "Dead-lettered {} updates that could not be exported to Kafka to '{}'",
+                updates.len(),
+                path.display()
+            ),
+            Err(e) => error!(
+                "Dropped {} updates that could not be exported to Kafka nor dead-lettered to \
+                 '{}': {}",
+                updates.len(),
+                path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Converts an update into a record keyed by its document.
+fn record(update: &ExportedUpdate) -> Record {
+    let mut headers = BTreeMap::new();
+    headers.insert(
+        "sequence_number".to_string(),
+        update.sequence_number.to_string().into_bytes(),
+    );
+    headers.insert("origin".to_string(), update.origin.to_string().into_bytes());
+    headers.insert("source".to_string(), update.source.clone().into_bytes());
+    if let Some(user_id) = &update.user_id {
+        headers.insert("user_id".to_string(), user_id.clone().into_bytes());
+    }
+
+    Record {
+        key: Some(update.doc_id.clone().into_bytes()),
+        value: Some(update.payload.clone()),
+        headers,
+        timestamp: DateTime::<Utc>::from_timestamp_millis(update.applied_at)
+            .unwrap_or_else(Utc::now),
+    }
+}
@@ -1,2 +1,26 @@
+pub mod compression;
+#[cfg(feature = "fault-injection")]
+pub mod fault_injection;
+pub mod file_audit_sink;
+pub mod file_document_store;
+pub mod file_write_ahead_log;
+pub mod grpc_document_forwarder;
+pub mod icu_collation;
+pub mod in_memory_audit_sink;
 pub mod in_memory_document_repository;
-
+pub mod in_memory_document_store;
+pub mod in_memory_metadata_repository;
+pub mod in_memory_version_repository;
+pub mod kafka_update_exporter;
+pub mod nats_update_broker;
+pub mod persistent_document_repository;
+pub mod postgres_document_repository;
+pub mod redis_cluster_membership;
+pub mod redis_update_broker;
+pub mod regex_update_processor;
+pub mod s3_document_repository;
+pub mod schema_update_validator;
+pub mod static_access_control;
+pub mod static_cluster_membership;
+pub mod tantivy_search_index;
+pub mod zstd_dictionary_compressor;
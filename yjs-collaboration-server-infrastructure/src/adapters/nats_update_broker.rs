@@ -0,0 +1,345 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_nats::{
+    jetstream::{
+        self,
+        consumer::{pull, AckPolicy, DeliverPolicy},
+        stream, Message,
+    },
+    HeaderMap, ServerAddr,
+};
+use dashmap::DashMap;
+use futures::StreamExt;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use tokio::{runtime::Handle, sync::mpsc};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::update_broker::UpdateBroker,
+};
+
+/// Delay before recreating the consumer after its stream of messages failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Header carrying the identifier of the publishing instance.
+const ORIGIN_HEADER: &str = "Yjs-Origin";
+
+/// Senders delivering remote updates to subscribed documents, keyed by subject
+type Subscriptions = Arc<DashMap<String, mpsc::UnboundedSender<Vec<u8>>>>;
+
+/// Settings of the JetStream stream and consumer of a NATS broker.
+#[derive(Clone, Debug)]
+pub struct NatsStreamSettings {
+    /// Name of the stream retaining the updates
+    pub stream: String,
+    /// Prefix of every subject
+    pub subject_prefix: String,
+    /// Durable consumer of this instance, unique within the cluster
+    pub consumer: String,
+    /// Time updates are retained, bounding how long an instance may be partitioned
+    /// and still replay the updates it missed
+    pub retention: Duration,
+}
+
+/// A NATS JetStream implementation of the update broker interface.
+///
+/// Every document has its own subject, `{prefix}.doc.{doc_id}` with the
+/// identifier percent-encoded, and the stream retains the updates published
+/// to any of them for the retention period. Every instance reads the stream
+/// through its own durable consumer, acknowledging each update once it is
+/// delivered to its document, or dropped if the document is not open here.
+///
+/// An instance that loses its connection, or restarts with the same consumer
+/// name, resumes from its last acknowledged update, so the updates published
+/// while it was partitioned are replayed rather than lost. Each message
+/// carries the identifier of the publishing instance in a header, so an
+/// instance ignores its own updates. The broker reports itself unhealthy
+/// while its consumer is not reading the stream.
+pub struct NatsUpdateBroker {
+    /// Identifier of this instance, used to skip its own messages
+    node_id: String,
+    /// Prefix of every subject
+    subject_prefix: String,
+    /// Queue of (subject, update) pairs drained by the publisher task
+    publisher: mpsc::UnboundedSender<(String, Vec<u8>)>,
+    subscriptions: Subscriptions,
+    /// Whether the consumer task is currently reading the stream
+    connected: Arc<AtomicBool>,
+}
+
+impl NatsUpdateBroker {
+    /// Creates a broker and starts its background tasks.
+    ///
+    /// Must be called within a Tokio runtime, which then drives the background
+    /// tasks. The connection is established, and the stream and the consumer
+    /// created if missing, in the background, so an unreachable server is
+    /// reported in the logs rather than here.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Connection URL, e.g. `nats://127.0.0.1:4222`
+    /// * `settings` - Stream and consumer of the updates
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(NatsUpdateBroker)` - The broker
+    /// * `Err(String)` - If the URL is invalid or no runtime is running
+    pub fn connect(url: &str, settings: NatsStreamSettings) -> Result<Self, String> {
+        let addr: ServerAddr = url
+            .parse()
+            .map_err(|e| format!("Invalid NATS URL: {}", e))?;
+        let runtime = Handle::try_current()
+            .map_err(|_| "NATS broker must be started within a Tokio runtime".to_string())?;
+
+        let node_id = Uuid::new_v4().to_string();
+        let subscriptions: Subscriptions = Arc::new(DashMap::new());
+        let (publisher, published) = mpsc::unbounded_channel();
+        let connected = Arc::new(AtomicBool::new(false));
+        let subject_prefix = settings.subject_prefix.clone();
+
+        runtime.spawn(Self::run(
+            addr,
+            settings,
+            node_id.clone(),
+            published,
+            subscriptions.clone(),
+            connected.clone(),
+        ));
+
+        info!("Sharing document updates through NATS as node {}", node_id);
+
+        Ok(Self {
+            node_id,
+            subject_prefix,
+            publisher,
+            subscriptions,
+            connected,
+        })
+    }
+
+    /// Verifies that a NATS server with JetStream enabled is reachable.
+    ///
+    /// Runs on its own thread and runtime, so it may be called from synchronous
+    /// code whether or not a runtime is running.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Connection URL, e.g. `nats://127.0.0.1:4222`
+    /// * `timeout` - Maximum time to wait for the connection and for JetStream
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If JetStream answered an account information request
+    /// * `Err(String)` - If the URL is invalid, the server is unreachable or JetStream disabled
+    pub fn check_connection(url: &str, timeout: Duration) -> Result<(), String> {
+        let addr: ServerAddr = url
+            .parse()
+            .map_err(|e| format!("Invalid NATS URL: {}", e))?;
+
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| format!("Failed to start the NATS check: {}", e))?;
+            runtime.block_on(async {
+                let client = tokio::time::timeout(timeout, async_nats::connect(addr))
+                    .await
+                    .map_err(|_| "Timed out connecting to NATS".to_string())?
+                    .map_err(|e| format!("Failed to connect to NATS: {}", e))?;
+                tokio::time::timeout(timeout, jetstream::new(client).query_account())
+                    .await
+                    .map_err(|_| "Timed out waiting for JetStream".to_string())?
+                    .map(|_| ())
+                    .map_err(|e| format!("JetStream is not available: {}", e))
+            })
+        })
+        .join()
+        .unwrap_or_else(|_| Err("The NATS check panicked".to_string()))
+    }
+
+    fn subject(&self, doc_id: &str) -> String {
+        format!(
+            "{}.doc.{}",
+            self.subject_prefix,
+            utf8_percent_encode(doc_id, NON_ALPHANUMERIC)
+        )
+    }
+
+    /// Connects to NATS, then publishes queued updates and consumes the stream until the
+    /// broker is dropped.
+    ///
+    /// The client reconnects on its own once connected; the consumer is recreated
+    /// whenever its stream of messages fails.
+    async fn run(
+        addr: ServerAddr,
+        settings: NatsStreamSettings,
+        node_id: String,
+        published: mpsc::UnboundedReceiver<(String, Vec<u8>)>,
+        subscriptions: Subscriptions,
+        connected: Arc<AtomicBool>,
+    ) {
+        let client = loop {
+            match async_nats::connect(addr.clone()).await {
+                Ok(client) => break client,
+                Err(e) => {
+                    warn!("Failed to connect to NATS: {}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        };
+        let context = jetstream::new(client);
+
+        let consumer = tokio::spawn(Self::run_consumer(
+            context.clone(),
+            settings,
+            node_id.clone(),
+            subscriptions,
+            connected.clone(),
+        ));
+        Self::run_publisher(context, &node_id, published).await;
+
+        // The broker was dropped
+        consumer.abort();
+        connected.store(false, Ordering::Release);
+    }
+
+    /// Publishes queued updates to the stream, waiting for each to be stored.
+    async fn run_publisher(
+        context: jetstream::Context,
+        node_id: &str,
+        mut published: mpsc::UnboundedReceiver<(String, Vec<u8>)>,
+    ) {
+        while let Some((subject, update)) = published.recv().await {
+            let mut headers = HeaderMap::new();
+            headers.insert(ORIGIN_HEADER, node_id);
+
+            let stored = match context
+                .publish_with_headers(subject.clone(), headers, update.into())
+                .await
+            {
+                Ok(ack) => ack.await.map(|_| ()).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = stored {
+                error!("Failed to publish update to {}: {}", subject, e);
+            }
+        }
+    }
+
+    /// Reads the stream through this instance's durable consumer, recreating it after
+    /// failures.
+    ///
+    /// `connected` is raised once the consumer reads the stream and lowered
+    /// whenever its stream of messages fails.
+    async fn run_consumer(
+        context: jetstream::Context,
+        settings: NatsStreamSettings,
+        node_id: String,
+        subscriptions: Subscriptions,
+        connected: Arc<AtomicBool>,
+    ) {
+        loop {
+            if let Err(e) =
+                Self::consume(&context, &settings, &node_id, &subscriptions, &connected).await
+            {
+                warn!("NATS consumer '{}' failed: {}", settings.consumer, e);
+            }
+            connected.store(false, Ordering::Release);
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Creates the stream and the consumer if missing, then delivers and acknowledges
+    /// every message until the stream of messages fails.
+    async fn consume(
+        context: &jetstream::Context,
+        settings: &NatsStreamSettings,
+        node_id: &str,
+        subscriptions: &Subscriptions,
+        connected: &AtomicBool,
+    ) -> Result<(), async_nats::Error> {
+        let subjects = format!("{}.doc.*", settings.subject_prefix);
+        let stream = context
+            .get_or_create_stream(stream::Config {
+                name: settings.stream.clone(),
+                subjects: vec![subjects.clone()],
+                max_age: settings.retention,
+                ..Default::default()
+            })
+            .await?;
+
+        // A new consumer starts with the updates published from now on; an existing
+        // one resumes after the last update it acknowledged
+        let consumer = stream
+            .get_or_create_consumer(
+                &settings.consumer,
+                pull::Config {
+                    durable_name: Some(settings.consumer.clone()),
+                    filter_subject: subjects,
+                    deliver_policy: DeliverPolicy::New,
+                    ack_policy: AckPolicy::Explicit,
+                    inactive_threshold: settings.retention,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let mut messages = consumer.messages().await?;
+        connected.store(true, Ordering::Release);
+
+        while let Some(message) = messages.next().await {
+            let message = message?;
+            Self::dispatch(node_id, subscriptions, &message);
+            message.ack().await?;
+        }
+        Ok(())
+    }
+
+    /// Delivers a message to its document, unless this instance published it.
+    fn dispatch(node_id: &str, subscriptions: &Subscriptions, message: &Message) {
+        let origin = message
+            .headers
+            .as_ref()
+            .and_then(|headers| headers.get(ORIGIN_HEADER));
+        if origin.is_some_and(|origin| origin.as_str() == node_id) {
+            return;
+        }
+
+        let subject = message.subject.as_str();
+        let delivered = subscriptions
+            .get(subject)
+            .is_some_and(|sender| sender.send(message.payload.to_vec()).is_ok());
+        if !delivered {
+            subscriptions.remove(subject);
+        }
+    }
+}
+
+impl UpdateBroker for NatsUpdateBroker {
+    fn publish(&self, doc_id: &str, update: &[u8]) -> DomainResult<()> {
+        self.publisher
+            .send((self.subject(doc_id), update.to_vec()))
+            .map_err(|_| DomainError::unavailable("NATS publisher has stopped"))
+    }
+
+    fn subscribe(&self, doc_id: &str) -> DomainResult<mpsc::UnboundedReceiver<Vec<u8>>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        // The consumer reads every document's subject; only subscribed ones are delivered
+        self.subscriptions.insert(self.subject(doc_id), sender);
+        Ok(receiver)
+    }
+
+    fn check_health(&self) -> DomainResult<()> {
+        if self.connected.load(Ordering::Acquire) {
+            Ok(())
+        } else {
+            Err(DomainError::unavailable("Not consuming the NATS stream"))
+        }
+    }
+}
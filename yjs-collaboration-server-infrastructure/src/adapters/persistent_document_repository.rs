@@ -0,0 +1,804 @@
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    sync::{Arc, Weak},
+};
+
+use dashmap::{DashMap, DashSet};
+use sled::{transaction::ConflictableTransactionError, Transactional};
+use tokio::{runtime::Handle, sync::RwLock};
+use tracing::{error, warn};
+use yjs_collaboration_server_domain::{
+    entities::document::CollaborativeDocument,
+    errors::{DomainError, DomainResult},
+    repositories::{
+        document_metadata_repository::DocumentMetadataRepository,
+        document_repository::DocumentRepository, update_log::UpdateLog,
+        version_repository::VersionRepository,
+    },
+    services::{compute_pool::ComputePool, document_service::SingleDocumentServiceImpl},
+    value_objects::{
+        document_metadata::DocumentMetadata,
+        document_version::DocumentVersion,
+        logged_update::{LogPosition, LoggedUpdate},
+    },
+};
+
+use super::compression::CompressionCodec;
+
+/// Separator between the document ID and the sequence number in update keys.
+const KEY_SEPARATOR: u8 = 0;
+/// Length of the suffix following the document ID in update keys: the
+/// separator and the big-endian sequence number, which may contain separators.
+const KEY_SUFFIX_LEN: usize = 1 + std::mem::size_of::<u64>();
+
+/// Key of the storage format marker in the `format` tree.
+const FORMAT_KEY: &str = "value_format";
+/// Format in which every stored snapshot and update is a compression frame.
+const FRAMED_FORMAT: &[u8] = b"codec-frame-v1";
+
+/// Update log and snapshot storage backed by an embedded sled database.
+///
+/// Each document is stored as a snapshot (the merged state at the last
+/// compaction) plus the updates applied since, keyed by document ID and a
+/// monotonically increasing sequence number. Once a document accumulates
+/// `compact_threshold` updates, they are merged into a new snapshot. Document
+/// metadata is kept in its own tree, as JSON keyed by document ID, and the
+/// versions of documents in two trees keyed by document ID and version number:
+/// one for their metadata as JSON, one for their snapshots.
+///
+/// Snapshots, updates and version snapshots are stored as compression frames (see
+/// [`CompressionCodec::encode`]), so changing the codec leaves previously
+/// written values readable.
+///
+/// Compactions run on Tokio's blocking pool rather than on the update path,
+/// one at a time per document, so an older snapshot never overwrites a newer one.
+struct SledUpdateLog {
+    db: sled::Db,
+    snapshots: sled::Tree,
+    updates: sled::Tree,
+    metadata: sled::Tree,
+    versions: sled::Tree,
+    version_snapshots: sled::Tree,
+    compact_threshold: usize,
+    /// Codec compressing newly written snapshots and updates
+    codec: CompressionCodec,
+    /// Number of updates appended per document since its last compaction
+    pending: DashMap<String, usize>,
+    /// Documents whose log is being compacted
+    compacting: DashSet<String>,
+    /// The log itself, moved into the compactions run in the background
+    this: Weak<SledUpdateLog>,
+}
+
+impl SledUpdateLog {
+    fn open(
+        path: &Path,
+        compact_threshold: usize,
+        codec: CompressionCodec,
+    ) -> DomainResult<Arc<Self>> {
+        let db = sled::open(path).map_err(|e| {
+            DomainError::StorageFailure(format!(
+                "Failed to open storage at '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let snapshots = db.open_tree("snapshots").map_err(DomainError::storage)?;
+        let updates = db.open_tree("updates").map_err(DomainError::storage)?;
+        let metadata = db.open_tree("metadata").map_err(DomainError::storage)?;
+        let versions = db.open_tree("versions").map_err(DomainError::storage)?;
+        let version_snapshots = db
+            .open_tree("version_snapshots")
+            .map_err(DomainError::storage)?;
+        let format = db.open_tree("format").map_err(DomainError::storage)?;
+
+        Self::migrate_to_frames(&snapshots, &updates, &format)?;
+
+        Ok(Arc::new_cyclic(|this| Self {
+            db,
+            snapshots,
+            updates,
+            metadata,
+            versions,
+            version_snapshots,
+            compact_threshold,
+            codec,
+            pending: DashMap::new(),
+            compacting: DashSet::new(),
+            this: this.clone(),
+        }))
+    }
+
+    /// Wraps the raw values written before compression support into
+    /// uncompressed frames.
+    ///
+    /// The values and the format marker are rewritten in a single transaction,
+    /// so an interrupted migration is simply run again on the next start.
+    fn migrate_to_frames(
+        snapshots: &sled::Tree,
+        updates: &sled::Tree,
+        format: &sled::Tree,
+    ) -> DomainResult<()> {
+        if format
+            .contains_key(FORMAT_KEY)
+            .map_err(DomainError::storage)?
+        {
+            return Ok(());
+        }
+
+        let frame = |entry: sled::Result<(sled::IVec, sled::IVec)>| -> DomainResult<_> {
+            let (key, value) = entry.map_err(DomainError::storage)?;
+            Ok((
+                key,
+                CompressionCodec::None
+                    .encode(&value)
+                    .map_err(DomainError::StorageFailure)?,
+            ))
+        };
+        let legacy_snapshots = snapshots
+            .iter()
+            .map(frame)
+            .collect::<DomainResult<Vec<_>>>()?;
+        let legacy_updates = updates
+            .iter()
+            .map(frame)
+            .collect::<DomainResult<Vec<_>>>()?;
+
+        (snapshots, updates, format)
+            .transaction(|(snapshots, updates, format)| {
+                for (key, value) in &legacy_snapshots {
+                    snapshots.insert(key.clone(), value.as_slice())?;
+                }
+                for (key, value) in &legacy_updates {
+                    updates.insert(key.clone(), value.as_slice())?;
+                }
+                format.insert(FORMAT_KEY, FRAMED_FORMAT)?;
+                Ok::<_, ConflictableTransactionError<()>>(())
+            })
+            .map_err(|e| {
+                DomainError::StorageFailure(format!("Failed to migrate stored documents: {:?}", e))
+            })
+    }
+
+    /// Compresses a value into a frame with the configured codec.
+    fn encode(&self, data: &[u8]) -> DomainResult<Vec<u8>> {
+        self.codec.encode(data).map_err(DomainError::StorageFailure)
+    }
+
+    fn update_prefix(doc_id: &str) -> Vec<u8> {
+        let mut prefix = doc_id.as_bytes().to_vec();
+        prefix.push(KEY_SEPARATOR);
+        prefix
+    }
+
+    fn version_key(doc_id: &str, version: u64) -> Vec<u8> {
+        let mut key = Self::update_prefix(doc_id);
+        key.extend_from_slice(&version.to_be_bytes());
+        key
+    }
+
+    /// Returns whether any state is stored for the document.
+    fn contains(&self, doc_id: &str) -> DomainResult<bool> {
+        Ok(self
+            .snapshots
+            .contains_key(doc_id)
+            .map_err(DomainError::storage)?
+            || self
+                .updates
+                .scan_prefix(Self::update_prefix(doc_id))
+                .next()
+                .is_some())
+    }
+
+    /// Loads the document's snapshot merged with its pending updates.
+    ///
+    /// Pending updates are then compacted into the snapshot in the background, so
+    /// a document is replayed from a single update on its next load.
+    fn load(&self, doc_id: &str) -> DomainResult<Option<Vec<u8>>> {
+        let (state, merged) = self.merge(doc_id)?;
+        if !merged.is_empty() {
+            self.compact_later(doc_id);
+        }
+        Ok(state)
+    }
+
+    /// Merges the document's snapshot with its pending updates.
+    ///
+    /// # Arguments
+    ///
+    /// * `doc_id` - A string identifier for the document
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((Option<Vec<u8>>, Vec<sled::IVec>))` - The merged state, `None` if nothing is stored
+    ///   for the document, and the keys of the updates merged into it
+    /// * `Err(DomainError)` - `StorageFailure` if the stored values could not be read or merged
+    fn merge(&self, doc_id: &str) -> DomainResult<(Option<Vec<u8>>, Vec<sled::IVec>)> {
+        let snapshot = self.snapshots.get(doc_id).map_err(DomainError::storage)?;
+
+        let mut keys = Vec::new();
+        let mut parts: Vec<Vec<u8>> = snapshot
+            .map(|s| CompressionCodec::decode(&s).map_err(DomainError::StorageFailure))
+            .transpose()?
+            .into_iter()
+            .collect();
+        for entry in self.updates.scan_prefix(Self::update_prefix(doc_id)) {
+            let (key, update) = entry.map_err(DomainError::storage)?;
+            keys.push(key);
+            parts.push(CompressionCodec::decode(&update).map_err(DomainError::StorageFailure)?);
+        }
+
+        match parts.len() {
+            0 => Ok((None, keys)),
+            1 if keys.is_empty() => Ok((parts.pop(), keys)),
+            _ => {
+                let state = yrs::merge_updates_v1(&parts).map_err(|e| {
+                    DomainError::StorageFailure(format!(
+                        "Failed to merge updates of '{}': {}",
+                        doc_id, e
+                    ))
+                })?;
+                Ok((Some(state), keys))
+            }
+        }
+    }
+
+    /// Merges the document's pending updates into its snapshot right away.
+    fn compact_now(&self, doc_id: &str) -> DomainResult<()> {
+        match self.merge(doc_id)? {
+            (Some(state), merged) if !merged.is_empty() => {
+                self.replace_snapshot(doc_id, &state, &merged)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Compacts the document's log on the blocking pool, or on the calling thread
+    /// outside a Tokio runtime.
+    ///
+    /// Skipped while a compaction of the document is running, as it would merge
+    /// the same updates; the updates appended since are merged by the next one.
+    fn compact_later(&self, doc_id: &str) {
+        if !self.compacting.insert(doc_id.to_string()) {
+            return;
+        }
+
+        let doc_id = doc_id.to_string();
+        let compact = move |log: &Self| {
+            if let Err(e) = log.compact_now(&doc_id) {
+                warn!("Failed to compact the log of '{}': {}", doc_id, e);
+            }
+            log.compacting.remove(&doc_id);
+        };
+        match (self.this.upgrade(), Handle::try_current()) {
+            (Some(log), Ok(runtime)) => {
+                runtime.spawn_blocking(move || compact(&log));
+            }
+            _ => compact(self),
+        }
+    }
+
+    /// Stores a new snapshot, then removes the updates merged into it.
+    ///
+    /// A crash in between leaves updates that are already part of the snapshot;
+    /// applying them again on the next load is idempotent.
+    fn replace_snapshot(
+        &self,
+        doc_id: &str,
+        state: &[u8],
+        merged: &[sled::IVec],
+    ) -> DomainResult<()> {
+        let mut batch = sled::Batch::default();
+        for key in merged {
+            batch.remove(key.clone());
+        }
+
+        self.snapshots
+            .insert(doc_id, self.encode(state)?)
+            .map_err(DomainError::storage)?;
+        self.updates
+            .apply_batch(batch)
+            .map_err(DomainError::storage)?;
+        self.pending.remove(doc_id);
+        Ok(())
+    }
+
+    fn remove(&self, doc_id: &str) -> DomainResult<()> {
+        self.snapshots
+            .remove(doc_id)
+            .map_err(DomainError::storage)?;
+        self.metadata.remove(doc_id).map_err(DomainError::storage)?;
+
+        let mut batch = sled::Batch::default();
+        for entry in self.updates.scan_prefix(Self::update_prefix(doc_id)) {
+            let (key, _) = entry.map_err(DomainError::storage)?;
+            batch.remove(key);
+        }
+        self.updates
+            .apply_batch(batch)
+            .map_err(DomainError::storage)?;
+        self.pending.remove(doc_id);
+        Ok(())
+    }
+
+    /// Checks that the database files can still be read.
+    fn check_health(&self) -> DomainResult<()> {
+        self.db.size_on_disk().map(|_| ()).map_err(|e| {
+            DomainError::Unavailable(format!("The sled database is not accessible: {}", e))
+        })
+    }
+
+    /// Reads the document's snapshot and pending updates as stored, without compacting them.
+    fn entries(&self, doc_id: &str) -> DomainResult<Vec<LoggedUpdate>> {
+        let mut entries = Vec::new();
+        if let Some(snapshot) = self.snapshots.get(doc_id).map_err(DomainError::storage)? {
+            entries.push(LoggedUpdate {
+                position: LogPosition::Snapshot,
+                data: CompressionCodec::decode(&snapshot),
+            });
+        }
+
+        let prefix = Self::update_prefix(doc_id);
+        for entry in self.updates.scan_prefix(&prefix) {
+            let (key, update) = entry.map_err(DomainError::storage)?;
+            let sequence = key[prefix.len()..]
+                .try_into()
+                .map(u64::from_be_bytes)
+                .unwrap_or_default();
+            entries.push(LoggedUpdate {
+                position: LogPosition::Update(sequence),
+                data: CompressionCodec::decode(&update),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Lists the documents with a snapshot or logged updates, sorted by ID.
+    fn list(&self) -> BTreeSet<String> {
+        let mut doc_ids: BTreeSet<String> = self
+            .snapshots
+            .iter()
+            .keys()
+            .filter_map(|key| key.ok())
+            .map(|key| String::from_utf8_lossy(&key).into_owned())
+            .collect();
+
+        // The document ID is whatever precedes the fixed-length suffix
+        doc_ids.extend(
+            self.updates
+                .iter()
+                .keys()
+                .filter_map(|key| key.ok())
+                .filter(|key| key.len() >= KEY_SUFFIX_LEN)
+                .map(|key| {
+                    String::from_utf8_lossy(&key[..key.len() - KEY_SUFFIX_LEN]).into_owned()
+                }),
+        );
+
+        doc_ids
+    }
+
+    fn clear(&self) -> DomainResult<()> {
+        self.snapshots.clear().map_err(DomainError::storage)?;
+        self.updates.clear().map_err(DomainError::storage)?;
+        self.metadata.clear().map_err(DomainError::storage)?;
+        self.versions.clear().map_err(DomainError::storage)?;
+        self.version_snapshots
+            .clear()
+            .map_err(DomainError::storage)?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl DocumentMetadataRepository for SledUpdateLog {
+    fn get(&self, doc_id: &str) -> DomainResult<DocumentMetadata> {
+        match self.metadata.get(doc_id).map_err(DomainError::storage)? {
+            Some(value) => sonic_rs::from_slice(&value).map_err(|e| {
+                DomainError::StorageFailure(format!(
+                    "Invalid metadata stored for '{}': {}",
+                    doc_id, e
+                ))
+            }),
+            None => Ok(DocumentMetadata::default()),
+        }
+    }
+
+    fn put(&self, doc_id: &str, metadata: &DocumentMetadata) -> DomainResult<()> {
+        if metadata.is_empty() {
+            self.metadata.remove(doc_id).map_err(DomainError::storage)?;
+        } else {
+            let value = sonic_rs::to_vec(metadata).map_err(DomainError::storage)?;
+            self.metadata
+                .insert(doc_id, value)
+                .map_err(DomainError::storage)?;
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> DomainResult<Vec<(String, DocumentMetadata)>> {
+        self.metadata
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry.map_err(DomainError::storage)?;
+                let doc_id = String::from_utf8_lossy(&key).into_owned();
+                let metadata = sonic_rs::from_slice(&value).map_err(|e| {
+                    DomainError::StorageFailure(format!(
+                        "Invalid metadata stored for '{}': {}",
+                        doc_id, e
+                    ))
+                })?;
+                Ok((doc_id, metadata))
+            })
+            .collect()
+    }
+}
+
+impl VersionRepository for SledUpdateLog {
+    fn list(&self, doc_id: &str) -> DomainResult<Vec<DocumentVersion>> {
+        self.versions
+            .scan_prefix(Self::update_prefix(doc_id))
+            .values()
+            .map(|value| {
+                let value = value.map_err(DomainError::storage)?;
+                sonic_rs::from_slice(&value).map_err(|e| {
+                    DomainError::StorageFailure(format!(
+                        "Invalid version stored for '{}': {}",
+                        doc_id, e
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    fn get(&self, doc_id: &str, version: u64) -> DomainResult<Option<(DocumentVersion, Vec<u8>)>> {
+        let key = Self::version_key(doc_id, version);
+        let Some(value) = self.versions.get(&key).map_err(DomainError::storage)? else {
+            return Ok(None);
+        };
+        let Some(snapshot) = self
+            .version_snapshots
+            .get(&key)
+            .map_err(DomainError::storage)?
+        else {
+            return Ok(None);
+        };
+
+        let version = sonic_rs::from_slice(&value).map_err(|e| {
+            DomainError::StorageFailure(format!("Invalid version stored for '{}': {}", doc_id, e))
+        })?;
+        let snapshot = CompressionCodec::decode(&snapshot).map_err(DomainError::StorageFailure)?;
+        Ok(Some((version, snapshot)))
+    }
+
+    fn put(&self, doc_id: &str, version: &DocumentVersion, snapshot: &[u8]) -> DomainResult<()> {
+        let key = Self::version_key(doc_id, version.version);
+        let value = sonic_rs::to_vec(version).map_err(DomainError::storage)?;
+        let snapshot = self.encode(snapshot)?;
+
+        // The snapshot is written first, so a listed version can always be read
+        self.version_snapshots
+            .insert(&key, snapshot)
+            .map_err(DomainError::storage)?;
+        self.versions
+            .insert(key, value)
+            .map_err(DomainError::storage)?;
+        Ok(())
+    }
+
+    fn remove(&self, doc_id: &str, version: u64) -> DomainResult<()> {
+        let key = Self::version_key(doc_id, version);
+        self.versions.remove(&key).map_err(DomainError::storage)?;
+        self.version_snapshots
+            .remove(key)
+            .map_err(DomainError::storage)?;
+        Ok(())
+    }
+
+    fn clear(&self, doc_id: &str) -> DomainResult<()> {
+        let prefix = Self::update_prefix(doc_id);
+        for tree in [&self.versions, &self.version_snapshots] {
+            let mut batch = sled::Batch::default();
+            for key in tree.scan_prefix(&prefix).keys() {
+                batch.remove(key.map_err(DomainError::storage)?);
+            }
+            tree.apply_batch(batch).map_err(DomainError::storage)?;
+        }
+        Ok(())
+    }
+}
+
+impl UpdateLog for SledUpdateLog {
+    fn append(&self, doc_id: &str, update: &[u8]) -> DomainResult<()> {
+        let seq = self.db.generate_id().map_err(DomainError::storage)?;
+        let mut key = Self::update_prefix(doc_id);
+        key.extend_from_slice(&seq.to_be_bytes());
+
+        self.updates
+            .insert(key, self.encode(update)?)
+            .map_err(DomainError::storage)?;
+
+        let pending = {
+            let mut pending = self.pending.entry(doc_id.to_string()).or_insert(0);
+            *pending += 1;
+            *pending
+        };
+
+        if self.compact_threshold > 0 && pending >= self.compact_threshold {
+            self.compact_later(doc_id);
+        }
+
+        Ok(())
+    }
+
+    /// Schedules the compaction of the document's log, reporting its failures in the logs.
+    fn compact(&self, doc_id: &str) -> DomainResult<()> {
+        self.compact_later(doc_id);
+        Ok(())
+    }
+}
+
+/// A persistent implementation of the document repository interface.
+///
+/// Documents are stored in an embedded sled database as a snapshot plus an
+/// append-only log of the updates applied since, so they survive restarts.
+/// Documents are loaded lazily on first access and then kept in memory; every
+/// update applied to a loaded document is appended to the log before it is
+/// broadcast.
+///
+/// This implementation contains all the concrete CRUD logic that the domain
+/// layer abstracts through the DocumentRepository trait.
+pub struct PersistentDocumentRepository {
+    /// Documents loaded in memory
+    documents: DashMap<String, Arc<RwLock<SingleDocumentServiceImpl>>>,
+    /// Durable storage of document snapshots and updates
+    store: Arc<SledUpdateLog>,
+    /// Compute pool shared by the documents loaded through this repository
+    compute: Arc<ComputePool>,
+}
+
+impl PersistentDocumentRepository {
+    /// Opens (or creates) a persistent document repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Directory of the sled database
+    /// * `compact_threshold` - Number of updates after which a document's log is compacted into a
+    ///   snapshot (`0` compacts only when a document is loaded)
+    /// * `codec` - Compression applied to the stored snapshots and updates
+    /// * `compute` - The compute pool shared by all loaded documents
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(PersistentDocumentRepository)` - The opened repository
+    /// * `Err(String)` - If the database could not be opened
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        compact_threshold: usize,
+        codec: CompressionCodec,
+        compute: Arc<ComputePool>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            documents: DashMap::new(),
+            store: SledUpdateLog::open(path.as_ref(), compact_threshold, codec)
+                .map_err(|e| e.to_string())?,
+            compute,
+        })
+    }
+
+    /// Returns the repository of document metadata stored in the same database.
+    pub fn metadata_repository(&self) -> Arc<dyn DocumentMetadataRepository> {
+        self.store.clone()
+    }
+
+    /// Returns the repository of document versions stored in the same database.
+    pub fn version_repository(&self) -> Arc<dyn VersionRepository> {
+        self.store.clone()
+    }
+
+    /// Builds an in-memory document recording its updates in the store.
+    fn attach(
+        &self,
+        doc_id: &str,
+        document: SingleDocumentServiceImpl,
+    ) -> Arc<RwLock<SingleDocumentServiceImpl>> {
+        let update_log: Arc<dyn UpdateLog> = self.store.clone();
+        Arc::new(RwLock::new(document.with_update_log(doc_id, update_log)))
+    }
+
+    /// Loads a persisted document into memory.
+    fn load(&self, doc_id: &str) -> DomainResult<Option<Arc<RwLock<SingleDocumentServiceImpl>>>> {
+        let Some(state) = self.store.load(doc_id)? else {
+            return Ok(None);
+        };
+
+        let document = SingleDocumentServiceImpl::from_state(self.compute.clone(), &state)
+            .map_err(|e| {
+                DomainError::StorageFailure(format!(
+                    "Failed to restore document '{}': {}",
+                    doc_id, e
+                ))
+            })?;
+        Ok(Some(self.attach(doc_id, document)))
+    }
+
+    /// Creates a new empty document and persists its initial state.
+    fn new_document(&self, doc_id: &str) -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        self.store
+            .append(doc_id, &CollaborativeDocument::new().encode_full_state())?;
+
+        let document = SingleDocumentServiceImpl::with_compute_pool(self.compute.clone());
+        Ok(self.attach(doc_id, document))
+    }
+}
+
+impl DocumentRepository for PersistentDocumentRepository {
+    /// Creates a new document with the given ID.
+    ///
+    /// This is the concrete implementation of document creation logic.
+    fn create_document(
+        &self,
+        doc_id: &str,
+    ) -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        if self.exists(doc_id) {
+            return Err(DomainError::Conflict(doc_id.to_string()));
+        }
+
+        let doc_service = self.new_document(doc_id)?;
+        self.documents
+            .insert(doc_id.to_string(), doc_service.clone());
+
+        Ok(doc_service)
+    }
+
+    /// Retrieves an existing document by ID, loading it from storage if needed.
+    ///
+    /// This is the concrete implementation of document retrieval logic.
+    fn get_document(&self, doc_id: &str) -> Option<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        if let Some(entry) = self.documents.get(doc_id) {
+            return Some(entry.value().clone());
+        }
+
+        // Loaded without holding the shard lock; of concurrent loads, the first one inserted wins
+        let doc_service = match self.load(doc_id) {
+            Ok(doc_service) => doc_service?,
+            Err(e) => {
+                error!("{}", e);
+                return None;
+            }
+        };
+        Some(
+            self.documents
+                .entry(doc_id.to_string())
+                .or_insert(doc_service)
+                .value()
+                .clone(),
+        )
+    }
+
+    /// Retrieves an existing document by ID or creates a new one if it doesn't exist.
+    ///
+    /// Documents whose persisted state cannot be read are served from memory only
+    /// rather than overwritten, so their log is left intact for inspection.
+    fn get_or_create(&self, doc_id: &str) -> Arc<RwLock<SingleDocumentServiceImpl>> {
+        if let Some(entry) = self.documents.get(doc_id) {
+            return entry.value().clone();
+        }
+
+        // Loaded without holding the shard lock; of concurrent loads, the first one inserted
+        // wins, and persisting the empty state of a new document twice is harmless
+        let doc_service = match self.load(doc_id) {
+            Ok(Some(doc_service)) => doc_service,
+            Ok(None) => self.new_document(doc_id).unwrap_or_else(|e| {
+                warn!("Failed to persist new document '{}': {}", doc_id, e);
+                self.attach(
+                    doc_id,
+                    SingleDocumentServiceImpl::with_compute_pool(self.compute.clone()),
+                )
+            }),
+            Err(e) => {
+                error!("{}; serving '{}' without persistence", e, doc_id);
+                Arc::new(RwLock::new(SingleDocumentServiceImpl::with_compute_pool(
+                    self.compute.clone(),
+                )))
+            }
+        };
+        self.documents
+            .entry(doc_id.to_string())
+            .or_insert(doc_service)
+            .value()
+            .clone()
+    }
+
+    /// Updates an existing document.
+    ///
+    /// This is the concrete implementation of document update logic.
+    fn update_document(
+        &self,
+        doc_id: &str,
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+    ) -> DomainResult<()> {
+        if !self.exists(doc_id) {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        self.documents.insert(doc_id.to_string(), document);
+        Ok(())
+    }
+
+    /// Deletes a document by ID, both from memory and from storage.
+    ///
+    /// This is the concrete implementation of document deletion logic.
+    fn delete_document(&self, doc_id: &str) -> DomainResult<()> {
+        if !self.exists(doc_id) {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        self.documents.remove(doc_id);
+        self.store.remove(doc_id)
+    }
+
+    /// Lists all document IDs, including persisted documents that are not loaded.
+    ///
+    /// This is the concrete implementation of document listing logic.
+    fn list_documents(&self) -> Vec<String> {
+        let mut doc_ids = self.store.list();
+        doc_ids.extend(self.documents.iter().map(|entry| entry.key().clone()));
+        doc_ids.into_iter().collect()
+    }
+
+    /// Checks if a document exists in memory or in storage.
+    ///
+    /// This is the concrete implementation that checks document existence.
+    fn exists(&self, doc_id: &str) -> bool {
+        self.documents.contains_key(doc_id) || self.store.contains(doc_id).unwrap_or(false)
+    }
+
+    /// Gets the number of documents currently loaded in memory.
+    ///
+    /// Persisted documents that have not been accessed since startup are not
+    /// counted, so the value reflects memory pressure.
+    fn count(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Reports that documents are kept in the embedded database across restarts.
+    ///
+    /// This is the concrete implementation of the durability check.
+    fn is_durable(&self) -> bool {
+        true
+    }
+
+    /// Lists the documents loaded in memory, leaving persisted documents unloaded.
+    ///
+    /// This is the concrete implementation of resident document listing.
+    fn resident_documents(&self) -> Vec<String> {
+        self.documents
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Clears all documents from memory and storage.
+    ///
+    /// This is the concrete implementation of repository clearing logic.
+    fn clear(&self) -> DomainResult<()> {
+        self.documents.clear();
+        self.store.clear()
+    }
+
+    /// Reads the document's snapshot and pending updates from the database.
+    ///
+    /// This is the concrete implementation of update log inspection.
+    fn logged_updates(&self, doc_id: &str) -> DomainResult<Vec<LoggedUpdate>> {
+        self.store.entries(doc_id)
+    }
+
+    /// Checks that the sled database is still accessible.
+    ///
+    /// This is the concrete implementation of the storage health check.
+    fn check_health(&self) -> DomainResult<()> {
+        self.store.check_health()
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use redis::{aio::MultiplexedConnection, AsyncCommands, Client, RedisResult};
+use tokio::{runtime::Handle, sync::watch};
+use tracing::{info, warn};
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::cluster_membership::ClusterMembership,
+    value_objects::cluster_member::ClusterMember,
+};
+
+/// A Redis implementation of the cluster membership interface.
+///
+/// Every instance sends a heartbeat at a fixed interval, recording its member
+/// in the `{prefix}:cluster:members` hash and the time of the heartbeat,
+/// taken from the Redis clock, in the `{prefix}:cluster:heartbeats` sorted set.
+/// Instances without a heartbeat within the member TTL are pruned from both,
+/// and every heartbeat reads back the remaining members.
+///
+/// While Redis is unreachable, an instance keeps the last list it read, and
+/// reports itself unhealthy.
+#[derive(Clone)]
+pub struct RedisClusterMembership {
+    client: Client,
+    /// Prefix of the membership keys
+    key_prefix: String,
+    /// Interval between two heartbeats of this instance
+    heartbeat_interval: Duration,
+    /// Time without a heartbeat after which a member is considered down
+    member_ttl: Duration,
+    /// Whether the last heartbeat succeeded
+    connected: Arc<AtomicBool>,
+}
+
+impl RedisClusterMembership {
+    /// Creates a membership list stored in Redis.
+    ///
+    /// The connection is established once the instance joins, in the
+    /// background, so an unreachable server is reported in the logs rather
+    /// than here.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Connection URL, e.g. `redis://127.0.0.1:6379`
+    /// * `key_prefix` - Prefix of the membership keys, to share a Redis server between clusters
+    /// * `heartbeat_interval` - Interval between two heartbeats of this instance
+    /// * `member_ttl` - Time without a heartbeat after which a member is considered down
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RedisClusterMembership)` - The membership list
+    /// * `Err(String)` - If the URL is invalid
+    pub fn connect(
+        url: &str,
+        key_prefix: &str,
+        heartbeat_interval: Duration,
+        member_ttl: Duration,
+    ) -> Result<Self, String> {
+        let client = Client::open(url).map_err(|e| format!("Invalid Redis URL: {}", e))?;
+        Ok(Self {
+            client,
+            key_prefix: key_prefix.to_string(),
+            heartbeat_interval,
+            member_ttl,
+            connected: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Sends heartbeats and publishes the live members until every receiver is dropped.
+    async fn run(self, member: ClusterMember, members: watch::Sender<Vec<ClusterMember>>) {
+        let mut connection: Option<MultiplexedConnection> = None;
+        let mut heartbeats = tokio::time::interval(self.heartbeat_interval);
+
+        while !members.is_closed() {
+            heartbeats.tick().await;
+
+            if connection.is_none() {
+                match self.client.get_multiplexed_async_connection().await {
+                    Ok(established) => connection = Some(established),
+                    Err(e) => {
+                        warn!(
+                            "Failed to connect to Redis for the cluster membership: {}",
+                            e
+                        );
+                        self.connected.store(false, Ordering::Release);
+                        continue;
+                    }
+                }
+            }
+            let Some(established) = connection.as_mut() else {
+                continue;
+            };
+
+            match self.heartbeat(established, &member).await {
+                Ok(live) => {
+                    self.connected.store(true, Ordering::Release);
+                    let changed = members.send_if_modified(|current| {
+                        let changed = *current != live;
+                        *current = live;
+                        changed
+                    });
+                    if changed {
+                        let node_ids: Vec<String> = members
+                            .borrow()
+                            .iter()
+                            .map(|member| member.node_id.clone())
+                            .collect();
+                        info!("Cluster members: {}", node_ids.join(", "));
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to send the cluster heartbeat: {}", e);
+                    self.connected.store(false, Ordering::Release);
+                    connection = None;
+                }
+            }
+        }
+    }
+
+    /// Records a heartbeat of this instance, prunes the members that are down and reads
+    /// the others.
+    async fn heartbeat(
+        &self,
+        connection: &mut MultiplexedConnection,
+        member: &ClusterMember,
+    ) -> RedisResult<Vec<ClusterMember>> {
+        let members_key = format!("{}:cluster:members", self.key_prefix);
+        let heartbeats_key = format!("{}:cluster:heartbeats", self.key_prefix);
+        let payload = sonic_rs::to_string(member).unwrap_or_default();
+
+        // Every instance compares heartbeats with the same clock, whatever the skew of its own
+        let (seconds, micros): (u64, u64) = redis::cmd("TIME").query_async(connection).await?;
+        let now = seconds * 1000 + micros / 1000;
+        let expired = now.saturating_sub(self.member_ttl.as_millis() as u64);
+
+        let (live, entries): (Vec<String>, HashMap<String, String>) = redis::pipe()
+            .atomic()
+            .hset(&members_key, &member.node_id, payload)
+            .ignore()
+            .zadd(&heartbeats_key, &member.node_id, now)
+            .ignore()
+            .zrembyscore(&heartbeats_key, "-inf", expired)
+            .ignore()
+            .zrange(&heartbeats_key, 0, -1)
+            .hgetall(&members_key)
+            .query_async(connection)
+            .await?;
+
+        let live: HashSet<String> = live.into_iter().collect();
+        let down: Vec<&String> = entries.keys().filter(|id| !live.contains(*id)).collect();
+        if !down.is_empty() {
+            connection.hdel::<_, _, ()>(&members_key, down).await?;
+        }
+
+        let mut members: Vec<ClusterMember> = entries
+            .iter()
+            .filter(|(node_id, _)| live.contains(*node_id))
+            .filter_map(|(node_id, payload)| match sonic_rs::from_str(payload) {
+                Ok(member) => Some(member),
+                Err(e) => {
+                    warn!("Ignoring malformed cluster member '{}': {}", node_id, e);
+                    None
+                }
+            })
+            .collect();
+        members.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        Ok(members)
+    }
+}
+
+impl ClusterMembership for RedisClusterMembership {
+    fn join(&self, member: ClusterMember) -> DomainResult<watch::Receiver<Vec<ClusterMember>>> {
+        let runtime = Handle::try_current().map_err(|_| {
+            DomainError::unavailable("Cluster membership must be joined within a Tokio runtime")
+        })?;
+
+        info!(
+            "Joining the cluster through Redis as node '{}'",
+            member.node_id
+        );
+        let (members, receiver) = watch::channel(vec![member.clone()]);
+        runtime.spawn(self.clone().run(member, members));
+        Ok(receiver)
+    }
+
+    fn check_health(&self) -> DomainResult<()> {
+        if self.connected.load(Ordering::Acquire) {
+            Ok(())
+        } else {
+            Err(DomainError::unavailable(
+                "Cluster membership not reachable in Redis",
+            ))
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use futures::StreamExt;
+use redis::{aio::MultiplexedConnection, AsyncCommands, Client, Msg};
+use tokio::{runtime::Handle, sync::mpsc};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::update_broker::UpdateBroker,
+};
+
+/// Delay before reconnecting after the Redis connection was lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Senders delivering remote updates to subscribed documents, keyed by channel name
+type Subscriptions = Arc<DashMap<String, mpsc::UnboundedSender<Vec<u8>>>>;
+
+/// A Redis pub/sub implementation of the update broker interface.
+///
+/// Every document has its own channel, `{prefix}:doc:{doc_id}`. Applied updates
+/// are published to the document's channel, and a document's channel is
+/// subscribed when the document is first opened on this instance.
+///
+/// Each message is prefixed with the identifier of the publishing instance, so
+/// an instance ignores its own updates. Publishing and subscribing run on
+/// background tasks that reconnect (and resubscribe) when the connection to
+/// Redis is lost; updates published while disconnected are not retried, and
+/// clients catch up on their next sync. The broker reports itself unhealthy
+/// while its subscription connection is down.
+pub struct RedisUpdateBroker {
+    /// Identifier of this instance, used to skip its own messages
+    node_id: String,
+    /// Prefix of every channel name
+    channel_prefix: String,
+    /// Queue of (channel, message) pairs drained by the publisher task
+    publisher: mpsc::UnboundedSender<(String, Vec<u8>)>,
+    /// Channels to subscribe, drained by the subscriber task
+    subscriber: mpsc::UnboundedSender<String>,
+    subscriptions: Subscriptions,
+    /// Whether the subscriber task is currently connected and subscribed
+    connected: Arc<AtomicBool>,
+}
+
+impl RedisUpdateBroker {
+    /// Creates a broker and starts its background tasks.
+    ///
+    /// Must be called within a Tokio runtime, which then drives the background
+    /// tasks. The connection is established in the background, so an unreachable
+    /// server is reported in the logs rather than here.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Connection URL, e.g. `redis://127.0.0.1:6379`
+    /// * `channel_prefix` - Prefix of every channel name, to share a Redis server between clusters
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RedisUpdateBroker)` - The broker
+    /// * `Err(String)` - If the URL is invalid or no runtime is running
+    pub fn connect(url: &str, channel_prefix: &str) -> Result<Self, String> {
+        let client = Client::open(url).map_err(|e| format!("Invalid Redis URL: {}", e))?;
+        let runtime = Handle::try_current()
+            .map_err(|_| "Redis broker must be started within a Tokio runtime".to_string())?;
+
+        let node_id = Uuid::new_v4().to_string();
+        let subscriptions: Subscriptions = Arc::new(DashMap::new());
+        let (publisher, published) = mpsc::unbounded_channel();
+        let (subscriber, subscribe_requests) = mpsc::unbounded_channel();
+        let connected = Arc::new(AtomicBool::new(false));
+
+        runtime.spawn(Self::run_publisher(client.clone(), published));
+        runtime.spawn(Self::run_subscriber(
+            client,
+            node_id.clone(),
+            subscriptions.clone(),
+            subscribe_requests,
+            connected.clone(),
+        ));
+
+        info!("Sharing document updates through Redis as node {}", node_id);
+
+        Ok(Self {
+            node_id,
+            channel_prefix: channel_prefix.to_string(),
+            publisher,
+            subscriber,
+            subscriptions,
+            connected,
+        })
+    }
+
+    /// Verifies that a Redis server is reachable.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Connection URL, e.g. `redis://127.0.0.1:6379`
+    /// * `timeout` - Maximum time to wait for the connection
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the server answered a `PING`
+    /// * `Err(String)` - If the URL is invalid or the server is unreachable
+    pub fn check_connection(url: &str, timeout: Duration) -> Result<(), String> {
+        let client = Client::open(url).map_err(|e| format!("Invalid Redis URL: {}", e))?;
+        let mut connection = client
+            .get_connection_with_timeout(timeout)
+            .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+        redis::cmd("PING")
+            .query::<String>(&mut connection)
+            .map(|_| ())
+            .map_err(|e| format!("Redis did not answer PING: {}", e))
+    }
+
+    fn channel(&self, doc_id: &str) -> String {
+        format!("{}:doc:{}", self.channel_prefix, doc_id)
+    }
+
+    /// Publishes queued messages, reconnecting lazily after failures.
+    async fn run_publisher(
+        client: Client,
+        mut published: mpsc::UnboundedReceiver<(String, Vec<u8>)>,
+    ) {
+        let mut connection: Option<MultiplexedConnection> = None;
+
+        while let Some((channel, message)) = published.recv().await {
+            if connection.is_none() {
+                match client.get_multiplexed_async_connection().await {
+                    Ok(established) => connection = Some(established),
+                    Err(e) => {
+                        error!("Failed to connect to Redis, update dropped: {}", e);
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(established) = connection.as_mut() {
+                if let Err(e) = established.publish::<_, _, ()>(&channel, message).await {
+                    error!("Failed to publish update to {}: {}", channel, e);
+                    connection = None;
+                }
+            }
+        }
+    }
+
+    /// Receives messages of the subscribed channels, resubscribing after reconnects.
+    ///
+    /// `connected` is raised once the channels are (re)subscribed and lowered
+    /// whenever the connection is lost.
+    async fn run_subscriber(
+        client: Client,
+        node_id: String,
+        subscriptions: Subscriptions,
+        mut subscribe_requests: mpsc::UnboundedReceiver<String>,
+        connected: Arc<AtomicBool>,
+    ) {
+        loop {
+            let (mut sink, mut stream) = match client.get_async_pubsub().await {
+                Ok(pubsub) => pubsub.split(),
+                Err(e) => {
+                    warn!("Failed to connect to Redis for subscriptions: {}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+
+            let channels: Vec<String> = subscriptions.iter().map(|e| e.key().clone()).collect();
+            if !channels.is_empty() {
+                if let Err(e) = sink.subscribe(channels).await {
+                    warn!("Failed to resubscribe to document channels: {}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            }
+            connected.store(true, Ordering::Release);
+
+            loop {
+                tokio::select! {
+                    request = subscribe_requests.recv() => match request {
+                        Some(channel) => {
+                            if let Err(e) = sink.subscribe(&channel).await {
+                                warn!("Failed to subscribe to {}: {}", channel, e);
+                                break;
+                            }
+                        }
+                        // The broker was dropped
+                        None => return,
+                    },
+                    message = stream.next() => match message {
+                        Some(message) => Self::dispatch(&node_id, &subscriptions, message),
+                        None => {
+                            warn!("Redis subscription connection lost, reconnecting");
+                            break;
+                        }
+                    },
+                }
+            }
+
+            connected.store(false, Ordering::Release);
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Delivers a message to its document, unless this instance published it.
+    fn dispatch(node_id: &str, subscriptions: &Subscriptions, message: Msg) {
+        let channel = message.get_channel_name();
+        let payload = message.get_payload_bytes();
+
+        let Some((&origin_len, rest)) = payload.split_first() else {
+            return;
+        };
+        if rest.len() < origin_len as usize {
+            warn!("Ignoring malformed message on {}", channel);
+            return;
+        }
+
+        let (origin, update) = rest.split_at(origin_len as usize);
+        if origin == node_id.as_bytes() {
+            return;
+        }
+
+        let delivered = subscriptions
+            .get(channel)
+            .is_some_and(|sender| sender.send(update.to_vec()).is_ok());
+        if !delivered {
+            subscriptions.remove(channel);
+        }
+    }
+}
+
+impl UpdateBroker for RedisUpdateBroker {
+    fn publish(&self, doc_id: &str, update: &[u8]) -> DomainResult<()> {
+        let mut message = Vec::with_capacity(1 + self.node_id.len() + update.len());
+        message.push(self.node_id.len() as u8);
+        message.extend_from_slice(self.node_id.as_bytes());
+        message.extend_from_slice(update);
+
+        self.publisher
+            .send((self.channel(doc_id), message))
+            .map_err(|_| DomainError::unavailable("Redis publisher has stopped"))
+    }
+
+    fn subscribe(&self, doc_id: &str) -> DomainResult<mpsc::UnboundedReceiver<Vec<u8>>> {
+        let channel = self.channel(doc_id);
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        self.subscriptions.insert(channel.clone(), sender);
+        self.subscriber
+            .send(channel)
+            .map_err(|_| DomainError::unavailable("Redis subscriber has stopped"))?;
+
+        Ok(receiver)
+    }
+
+    fn check_health(&self) -> DomainResult<()> {
+        if self.connected.load(Ordering::Acquire) {
+            Ok(())
+        } else {
+            Err(DomainError::unavailable("Not connected to Redis"))
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use regex::Regex;
+use yjs_collaboration_server_domain::{
+    errors::DomainResult,
+    repositories::update_processor::UpdateProcessor,
+    value_objects::{
+        text_insertion::{ProcessorVerdict, TextInsertion, TextReplacement},
+        update_origin::TransactionOrigin,
+    },
+};
+
+/// What a regex processor does with the text a rule matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegexAction {
+    /// Rejects the whole update
+    Reject,
+    /// Replaces every character of the match with the given one, e.g. `*`
+    Mask(char),
+    /// Replaces the whole match with the given text, e.g. `[redacted]`
+    Redact(String),
+    /// Keeps the text and labels the update with the given label
+    Annotate(String),
+}
+
+/// A pattern and what to do with the text it matches.
+#[derive(Clone, Debug)]
+pub struct RegexRule {
+    pattern: Regex,
+    action: RegexAction,
+}
+
+impl RegexRule {
+    /// Creates a rule from a regular expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The regular expression, e.g. `(?i)\bdarn\b`
+    /// * `action` - What to do with the text it matches
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RegexRule)` - The rule
+    /// * `Err(String)` - If the pattern is not a valid regular expression
+    pub fn new(pattern: &str, action: RegexAction) -> Result<Self, String> {
+        let pattern =
+            Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+        Ok(Self { pattern, action })
+    }
+}
+
+/// An update processor matching the inserted text against regular expressions.
+///
+/// Rules run in order on each inserted text. A match of any reject rule
+/// rejects the update; otherwise the matches of the mask and redact rules are
+/// replaced, skipping those overlapping a match of an earlier rule. Labels of
+/// the annotate rules are only reported when nothing is replaced, so rules
+/// that both replace and label the same text belong in separate processors.
+///
+/// Patterns only see the text of a single update, so a word typed one
+/// keystroke at a time is only matched if the client batches its keystrokes.
+#[derive(Clone, Debug)]
+pub struct RegexUpdateProcessor {
+    name: String,
+    rules: Vec<RegexRule>,
+}
+
+impl RegexUpdateProcessor {
+    /// Creates a processor applying the given rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the processor, reported in logs and labels
+    /// * `rules` - The rules, applied in order
+    ///
+    /// # Returns
+    ///
+    /// A new `RegexUpdateProcessor` instance
+    pub fn new(name: &str, rules: Vec<RegexRule>) -> Self {
+        Self {
+            name: name.to_string(),
+            rules,
+        }
+    }
+}
+
+impl UpdateProcessor for RegexUpdateProcessor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn process(
+        &self,
+        _doc_id: &str,
+        insertion: &TextInsertion,
+        _origin: &TransactionOrigin,
+    ) -> DomainResult<ProcessorVerdict> {
+        let text = insertion.text.as_str();
+        let mut replacements: Vec<TextReplacement> = Vec::new();
+        let mut labels = Vec::new();
+
+        for rule in &self.rules {
+            match &rule.action {
+                RegexAction::Reject => {
+                    if rule.pattern.is_match(text) {
+                        return Ok(ProcessorVerdict::Reject(format!(
+                            "text matches '{}'",
+                            rule.pattern.as_str()
+                        )));
+                    }
+                }
+                RegexAction::Annotate(label) => {
+                    if rule.pattern.is_match(text) && !labels.contains(label) {
+                        labels.push(label.clone());
+                    }
+                }
+                RegexAction::Mask(mask) => {
+                    replace_matches(&rule.pattern, text, &mut replacements, |found| {
+                        mask.to_string().repeat(found.chars().count())
+                    });
+                }
+                RegexAction::Redact(redaction) => {
+                    replace_matches(&rule.pattern, text, &mut replacements, |_| {
+                        redaction.clone()
+                    });
+                }
+            }
+        }
+
+        Ok(if !replacements.is_empty() {
+            ProcessorVerdict::Replace(replacements)
+        } else if !labels.is_empty() {
+            ProcessorVerdict::Annotate(labels)
+        } else {
+            ProcessorVerdict::Accept
+        })
+    }
+}
+
+/// Replaces the matches of a pattern that don't overlap the existing replacements.
+fn replace_matches(
+    pattern: &Regex,
+    text: &str,
+    replacements: &mut Vec<TextReplacement>,
+    replacement: impl Fn(&str) -> String,
+) {
+    for found in pattern.find_iter(text) {
+        let overlaps = replacements
+            .iter()
+            .any(|existing| found.start() < existing.end && existing.start < found.end());
+        if found.is_empty() || overlaps {
+            continue;
+        }
+        replacements.push(TextReplacement {
+            start: found.start(),
+            end: found.end(),
+            text: replacement(found.as_str()),
+        });
+    }
+}
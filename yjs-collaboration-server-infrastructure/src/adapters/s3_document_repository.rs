@@ -0,0 +1,759 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use futures::TryStreamExt;
+use object_store::{aws::AmazonS3Builder, path::Path, ObjectStore, PutPayload};
+use tokio::{
+    runtime::Handle,
+    sync::{Mutex, RwLock},
+};
+use tracing::{error, warn};
+use yjs_collaboration_server_domain::{
+    entities::document::CollaborativeDocument,
+    errors::{DomainError, DomainResult},
+    repositories::{document_repository::DocumentRepository, update_log::UpdateLog},
+    services::{compute_pool::ComputePool, document_service::SingleDocumentServiceImpl},
+    value_objects::logged_update::{LogPosition, LoggedUpdate},
+};
+
+use super::compression::CompressionCodec;
+
+/// Maximum time the bucket may take to answer a health check.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Name of the object holding a document's snapshot.
+const SNAPSHOT_OBJECT: &str = "snapshot";
+
+/// Prefix of the objects holding batches of updates, followed by their number.
+const BATCH_OBJECT_PREFIX: &str = "batch-";
+
+/// Location and credentials of an S3-compatible bucket.
+///
+/// Empty fields fall back to the standard `AWS_*` environment variables, so
+/// deployments relying on instance roles or web identity tokens need only
+/// name the bucket.
+#[derive(Clone, Debug, Default)]
+pub struct S3Settings {
+    /// Name of the bucket
+    pub bucket: String,
+    /// Key prefix under which documents are stored, e.g. `yjs/documents`
+    pub prefix: String,
+    /// Region of the bucket
+    pub region: String,
+    /// Endpoint of an S3-compatible service such as MinIO, empty for AWS
+    pub endpoint: String,
+    /// Access key identifier
+    pub access_key_id: String,
+    /// Secret access key
+    pub secret_access_key: String,
+}
+
+/// Objects stored for a document.
+#[derive(Default)]
+struct StoredObjects {
+    snapshot: Option<Path>,
+    /// Batches with their numbers, oldest first
+    batches: Vec<(u64, Path)>,
+}
+
+/// Batches of updates written for a document since its last snapshot.
+#[derive(Clone, Copy, Default)]
+struct WrittenBatches {
+    /// Number of the last batch written, `0` if none was
+    last: u64,
+    /// Number of updates the batches written since the snapshot hold
+    updates: usize,
+}
+
+/// Update log and snapshot storage backed by an S3-compatible bucket.
+///
+/// Object storage charges per request and cannot append to an object, so
+/// applied updates are buffered in memory, then merged and written by
+/// [`flush`] as one object per document: `{prefix}/{doc_id}/batch-{n}`.
+/// Once a document's batches hold `compact_threshold` updates, they are
+/// merged into its `{prefix}/{doc_id}/snapshot` object and deleted. Every
+/// object is a self-describing frame of the codec it was compressed with.
+///
+/// The repository interface is synchronous, so requests are driven on the
+/// Tokio runtime the store was opened on, leaving the calling worker with
+/// `block_in_place` while they run.
+///
+/// [`flush`]: S3UpdateLog::flush
+struct S3UpdateLog {
+    bucket: Box<dyn ObjectStore>,
+    prefix: Path,
+    runtime: Handle,
+    compact_threshold: usize,
+    /// Codec compressing newly written snapshots and batches
+    codec: CompressionCodec,
+    /// Updates applied per document since the last flush
+    pending: DashMap<String, Vec<Vec<u8>>>,
+    /// Batches written per document since its last snapshot
+    written: DashMap<String, WrittenBatches>,
+    /// Serializes the requests rewriting a document's objects
+    writes: Mutex<()>,
+}
+
+impl S3UpdateLog {
+    fn open(
+        settings: &S3Settings,
+        compact_threshold: usize,
+        codec: CompressionCodec,
+    ) -> Result<Self, String> {
+        let runtime = Handle::try_current()
+            .map_err(|_| "S3 storage must be opened within a Tokio runtime".to_string())?;
+
+        let mut builder = AmazonS3Builder::from_env().with_bucket_name(&settings.bucket);
+        if !settings.region.is_empty() {
+            builder = builder.with_region(&settings.region);
+        }
+        if !settings.endpoint.is_empty() {
+            builder = builder
+                .with_endpoint(&settings.endpoint)
+                .with_allow_http(settings.endpoint.starts_with("http://"));
+        }
+        if !settings.access_key_id.is_empty() {
+            builder = builder
+                .with_access_key_id(&settings.access_key_id)
+                .with_secret_access_key(&settings.secret_access_key);
+        }
+        let bucket = builder
+            .build()
+            .map_err(|e| format!("Invalid S3 settings: {}", e))?;
+
+        let log = Self {
+            bucket: Box::new(bucket),
+            prefix: Path::from(settings.prefix.as_str()),
+            runtime,
+            compact_threshold,
+            codec,
+            pending: DashMap::new(),
+            written: DashMap::new(),
+            writes: Mutex::new(()),
+        };
+        log.check_health()
+            .map_err(|e| format!("Failed to reach bucket '{}': {}", settings.bucket, e))?;
+        Ok(log)
+    }
+
+    /// Runs a request future to completion from synchronous repository code.
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        tokio::task::block_in_place(|| self.runtime.block_on(future))
+    }
+
+    /// Returns the key prefix of the document's objects.
+    fn document_path(&self, doc_id: &str) -> Path {
+        self.prefix.child(doc_id)
+    }
+
+    /// Recovers a document identifier from its percent-encoded key segment.
+    fn doc_id_of(segment: &str) -> Option<String> {
+        percent_encoding::percent_decode_str(segment)
+            .decode_utf8()
+            .ok()
+            .map(|doc_id| doc_id.into_owned())
+    }
+
+    /// Lists the objects stored for the document.
+    async fn objects(&self, doc_id: &str) -> DomainResult<StoredObjects> {
+        let location = self.document_path(doc_id);
+        let listed: Vec<_> = self
+            .bucket
+            .list(Some(&location))
+            .try_collect()
+            .await
+            .map_err(DomainError::storage)?;
+
+        let mut stored = StoredObjects::default();
+        for object in listed {
+            let name = object.location.filename().unwrap_or_default();
+            if name == SNAPSHOT_OBJECT {
+                stored.snapshot = Some(object.location);
+            } else if let Some(number) = name
+                .strip_prefix(BATCH_OBJECT_PREFIX)
+                .and_then(|number| number.parse().ok())
+            {
+                stored.batches.push((number, object.location));
+            }
+        }
+        stored.batches.sort_by_key(|(number, _)| *number);
+        Ok(stored)
+    }
+
+    /// Reads and decompresses an object.
+    async fn read(&self, location: &Path) -> DomainResult<Vec<u8>> {
+        let frame = self
+            .bucket
+            .get(location)
+            .await
+            .map_err(DomainError::storage)?
+            .bytes()
+            .await
+            .map_err(DomainError::storage)?;
+        CompressionCodec::decode(&frame).map_err(DomainError::StorageFailure)
+    }
+
+    /// Compresses and writes an object, replacing any previous version.
+    async fn write(&self, location: &Path, data: &[u8]) -> DomainResult<()> {
+        let frame = self
+            .codec
+            .encode(data)
+            .map_err(DomainError::StorageFailure)?;
+        self.bucket
+            .put(location, PutPayload::from(frame))
+            .await
+            .map_err(DomainError::storage)?;
+        Ok(())
+    }
+
+    /// Merges updates into a single one.
+    fn merge(doc_id: &str, updates: &[Vec<u8>]) -> DomainResult<Vec<u8>> {
+        if let [update] = updates {
+            return Ok(update.clone());
+        }
+        yrs::merge_updates_v1(updates).map_err(|e| {
+            DomainError::StorageFailure(format!("Failed to merge updates of '{}': {}", doc_id, e))
+        })
+    }
+
+    /// Reads the document's snapshot merged with its batches.
+    ///
+    /// Batches are compacted into the snapshot on the way, so a document is
+    /// restored from a single object on its next load. Callers hold `writes`.
+    async fn restore(&self, doc_id: &str) -> DomainResult<Option<Vec<u8>>> {
+        let stored = self.objects(doc_id).await?;
+        let mut parts = Vec::with_capacity(stored.batches.len() + 1);
+        if let Some(snapshot) = &stored.snapshot {
+            parts.push(self.read(snapshot).await?);
+        }
+        for (_, batch) in &stored.batches {
+            parts.push(self.read(batch).await?);
+        }
+
+        let last = stored.batches.last().map_or(0, |(number, _)| *number);
+        if parts.is_empty() {
+            return Ok(None);
+        }
+        let state = Self::merge(doc_id, &parts)?;
+        if !stored.batches.is_empty() {
+            self.replace_snapshot(doc_id, &state, &stored).await?;
+        }
+        self.written
+            .insert(doc_id.to_string(), WrittenBatches { last, updates: 0 });
+        Ok(Some(state))
+    }
+
+    /// Stores a new snapshot, then deletes the batches merged into it.
+    ///
+    /// A crash in between leaves batches that are already part of the snapshot;
+    /// applying them again on the next load is idempotent.
+    async fn replace_snapshot(
+        &self,
+        doc_id: &str,
+        state: &[u8],
+        stored: &StoredObjects,
+    ) -> DomainResult<()> {
+        self.write(&self.document_path(doc_id).child(SNAPSHOT_OBJECT), state)
+            .await?;
+        for (_, batch) in &stored.batches {
+            self.bucket
+                .delete(batch)
+                .await
+                .map_err(DomainError::storage)?;
+        }
+        if let Some(mut written) = self.written.get_mut(doc_id) {
+            written.updates = 0;
+        }
+        Ok(())
+    }
+
+    /// Writes the document's buffered updates as a new batch.
+    ///
+    /// Callers hold `writes`.
+    async fn write_batch(&self, doc_id: &str, updates: &[Vec<u8>]) -> DomainResult<()> {
+        let written = match self.written.get(doc_id).map(|written| *written) {
+            Some(written) => written,
+            None => WrittenBatches {
+                last: self
+                    .objects(doc_id)
+                    .await?
+                    .batches
+                    .last()
+                    .map_or(0, |(number, _)| *number),
+                updates: 0,
+            },
+        };
+
+        let count = updates.len();
+        let batch = Self::merge(doc_id, updates)?;
+        let name = format!("{}{:020}", BATCH_OBJECT_PREFIX, written.last + 1);
+        self.write(&self.document_path(doc_id).child(name), &batch)
+            .await?;
+
+        let written = WrittenBatches {
+            last: written.last + 1,
+            updates: written.updates + count,
+        };
+        self.written.insert(doc_id.to_string(), written);
+
+        if self.compact_threshold > 0 && written.updates >= self.compact_threshold {
+            self.restore(doc_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the updates buffered since the last flush, one batch per document.
+    ///
+    /// Updates that could not be written are buffered again, ahead of those
+    /// applied in the meantime, and retried on the next flush.
+    fn flush(&self) -> DomainResult<usize> {
+        self.block_on(async {
+            let _writes = self.writes.lock().await;
+            let doc_ids: Vec<String> = self
+                .pending
+                .iter()
+                .map(|entry| entry.key().clone())
+                .collect();
+
+            let mut flushed = 0;
+            let mut failure = None;
+            for doc_id in doc_ids {
+                let Some((_, updates)) = self.pending.remove(&doc_id) else {
+                    continue;
+                };
+                match self.write_batch(&doc_id, &updates).await {
+                    Ok(()) => flushed += 1,
+                    Err(e) => {
+                        warn!("Failed to flush the updates of '{}': {}", doc_id, e);
+                        let mut pending = self.pending.entry(doc_id).or_default();
+                        let applied = std::mem::replace(&mut *pending, updates);
+                        pending.extend(applied);
+                        failure.get_or_insert(e);
+                    }
+                }
+            }
+
+            match failure {
+                Some(e) => Err(e),
+                None => Ok(flushed),
+            }
+        })
+    }
+
+    /// Returns whether any state is stored or buffered for the document.
+    fn contains(&self, doc_id: &str) -> DomainResult<bool> {
+        if self.pending.contains_key(doc_id) {
+            return Ok(true);
+        }
+        let location = self.document_path(doc_id);
+        self.block_on(self.bucket.list(Some(&location)).try_next())
+            .map(|object| object.is_some())
+            .map_err(DomainError::storage)
+    }
+
+    /// Loads the document's state, merging any updates not flushed yet.
+    fn load(&self, doc_id: &str) -> DomainResult<Option<Vec<u8>>> {
+        let stored = self.block_on(async {
+            let _writes = self.writes.lock().await;
+            self.restore(doc_id).await
+        })?;
+        let pending = self
+            .pending
+            .get(doc_id)
+            .map(|pending| pending.clone())
+            .unwrap_or_default();
+
+        let parts: Vec<Vec<u8>> = stored.into_iter().chain(pending).collect();
+        if parts.is_empty() {
+            return Ok(None);
+        }
+        Self::merge(doc_id, &parts).map(Some)
+    }
+
+    /// Checks that the bucket answers a listing request in time.
+    fn check_health(&self) -> DomainResult<()> {
+        match self.block_on(tokio::time::timeout(
+            HEALTH_CHECK_TIMEOUT,
+            self.bucket.list_with_delimiter(Some(&self.prefix)),
+        )) {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(DomainError::Unavailable(format!(
+                "The S3 bucket did not answer: {}",
+                e
+            ))),
+            Err(_) => Err(DomainError::Unavailable(format!(
+                "The S3 bucket did not answer within {} ms",
+                HEALTH_CHECK_TIMEOUT.as_millis()
+            ))),
+        }
+    }
+
+    /// Reads the document's snapshot and batches as stored, without compacting them.
+    fn entries(&self, doc_id: &str) -> DomainResult<Vec<LoggedUpdate>> {
+        self.block_on(async {
+            let stored = self.objects(doc_id).await?;
+            let mut entries = Vec::with_capacity(stored.batches.len() + 1);
+            if let Some(snapshot) = &stored.snapshot {
+                entries.push(LoggedUpdate {
+                    position: LogPosition::Snapshot,
+                    data: self.read(snapshot).await.map_err(|e| e.to_string()),
+                });
+            }
+            for (number, batch) in &stored.batches {
+                entries.push(LoggedUpdate {
+                    position: LogPosition::Update(*number),
+                    data: self.read(batch).await.map_err(|e| e.to_string()),
+                });
+            }
+            Ok(entries)
+        })
+    }
+
+    /// Deletes every object under a key prefix.
+    async fn delete_all(&self, location: &Path) -> DomainResult<()> {
+        let listed: Vec<_> = self
+            .bucket
+            .list(Some(location))
+            .try_collect()
+            .await
+            .map_err(DomainError::storage)?;
+        for object in listed {
+            self.bucket
+                .delete(&object.location)
+                .await
+                .map_err(DomainError::storage)?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, doc_id: &str) -> DomainResult<()> {
+        self.block_on(async {
+            let _writes = self.writes.lock().await;
+            self.pending.remove(doc_id);
+            self.written.remove(doc_id);
+            self.delete_all(&self.document_path(doc_id)).await
+        })
+    }
+
+    fn list(&self) -> Vec<String> {
+        let listed = match self.block_on(self.bucket.list_with_delimiter(Some(&self.prefix))) {
+            Ok(listed) => listed.common_prefixes,
+            Err(e) => {
+                error!("Failed to list stored documents: {}", e);
+                Vec::new()
+            }
+        };
+
+        let mut doc_ids: Vec<String> = listed
+            .iter()
+            .filter_map(|location| location.filename().and_then(Self::doc_id_of))
+            .collect();
+        for entry in self.pending.iter() {
+            if !doc_ids.contains(entry.key()) {
+                doc_ids.push(entry.key().clone());
+            }
+        }
+        doc_ids
+    }
+
+    fn clear(&self) -> DomainResult<()> {
+        self.block_on(async {
+            let _writes = self.writes.lock().await;
+            self.pending.clear();
+            self.written.clear();
+            self.delete_all(&self.prefix).await
+        })
+    }
+}
+
+impl UpdateLog for S3UpdateLog {
+    fn append(&self, doc_id: &str, update: &[u8]) -> DomainResult<()> {
+        self.pending
+            .entry(doc_id.to_string())
+            .or_default()
+            .push(update.to_vec());
+        Ok(())
+    }
+
+    /// Merges the updates buffered for the document, leaving stored objects as is.
+    fn compact(&self, doc_id: &str) -> DomainResult<()> {
+        if let Some(mut pending) = self.pending.get_mut(doc_id) {
+            if pending.len() > 1 {
+                *pending = vec![Self::merge(doc_id, &pending)?];
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An S3-compatible object storage implementation of the document repository interface.
+///
+/// Documents are stored as a snapshot plus batches of the updates applied
+/// since, so they survive restarts of servers without a durable local disk,
+/// such as serverless or autoscaled deployments. Documents are restored from
+/// the bucket lazily on first access and then kept in memory; updates applied
+/// to a loaded document are buffered and written by [`flush`], which the
+/// application calls periodically. Updates applied since the last flush are
+/// lost if the server crashes.
+///
+/// This implementation contains all the concrete CRUD logic that the domain
+/// layer abstracts through the DocumentRepository trait.
+///
+/// [`flush`]: DocumentRepository::flush
+pub struct S3DocumentRepository {
+    /// Documents loaded in memory
+    documents: DashMap<String, Arc<RwLock<SingleDocumentServiceImpl>>>,
+    /// Durable storage of document snapshots and updates
+    store: Arc<S3UpdateLog>,
+    /// Compute pool shared by the documents loaded through this repository
+    compute: Arc<ComputePool>,
+}
+
+impl S3DocumentRepository {
+    /// Opens a bucket and checks that it can be listed.
+    ///
+    /// Must be called within a multi-threaded Tokio runtime, which then drives
+    /// the requests to the bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Location and credentials of the bucket
+    /// * `compact_threshold` - Number of flushed updates after which a document's batches are
+    ///   compacted into a snapshot (`0` compacts only when a document is loaded)
+    /// * `codec` - Compression applied to the stored snapshots and batches
+    /// * `compute` - The compute pool shared by all loaded documents
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(S3DocumentRepository)` - The opened repository
+    /// * `Err(String)` - If the settings are invalid or the bucket could not be reached
+    pub fn connect(
+        settings: &S3Settings,
+        compact_threshold: usize,
+        codec: CompressionCodec,
+        compute: Arc<ComputePool>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            documents: DashMap::new(),
+            store: Arc::new(S3UpdateLog::open(settings, compact_threshold, codec)?),
+            compute,
+        })
+    }
+
+    /// Builds an in-memory document recording its updates in the store.
+    fn attach(
+        &self,
+        doc_id: &str,
+        document: SingleDocumentServiceImpl,
+    ) -> Arc<RwLock<SingleDocumentServiceImpl>> {
+        let update_log: Arc<dyn UpdateLog> = self.store.clone();
+        Arc::new(RwLock::new(document.with_update_log(doc_id, update_log)))
+    }
+
+    /// Restores a persisted document into memory.
+    fn load(&self, doc_id: &str) -> DomainResult<Option<Arc<RwLock<SingleDocumentServiceImpl>>>> {
+        let Some(state) = self.store.load(doc_id)? else {
+            return Ok(None);
+        };
+
+        let document = SingleDocumentServiceImpl::from_state(self.compute.clone(), &state)
+            .map_err(|e| {
+                DomainError::StorageFailure(format!(
+                    "Failed to restore document '{}': {}",
+                    doc_id, e
+                ))
+            })?;
+        Ok(Some(self.attach(doc_id, document)))
+    }
+
+    /// Creates a new empty document and buffers its initial state.
+    fn new_document(&self, doc_id: &str) -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        self.store
+            .append(doc_id, &CollaborativeDocument::new().encode_full_state())?;
+
+        let document = SingleDocumentServiceImpl::with_compute_pool(self.compute.clone());
+        Ok(self.attach(doc_id, document))
+    }
+}
+
+impl DocumentRepository for S3DocumentRepository {
+    /// Creates a new document with the given ID.
+    ///
+    /// This is the concrete implementation of document creation logic.
+    fn create_document(
+        &self,
+        doc_id: &str,
+    ) -> DomainResult<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        if self.exists(doc_id) {
+            return Err(DomainError::Conflict(doc_id.to_string()));
+        }
+
+        let doc_service = self.new_document(doc_id)?;
+        self.documents
+            .insert(doc_id.to_string(), doc_service.clone());
+
+        Ok(doc_service)
+    }
+
+    /// Retrieves an existing document by ID, restoring it from the bucket if needed.
+    ///
+    /// This is the concrete implementation of document retrieval logic.
+    fn get_document(&self, doc_id: &str) -> Option<Arc<RwLock<SingleDocumentServiceImpl>>> {
+        if let Some(entry) = self.documents.get(doc_id) {
+            return Some(entry.value().clone());
+        }
+
+        // Restored without holding the shard lock, which would block every worker touching the
+        // shard until the bucket answers; of concurrent loads, the first one inserted wins
+        let doc_service = match self.load(doc_id) {
+            Ok(doc_service) => doc_service?,
+            Err(e) => {
+                error!("{}", e);
+                return None;
+            }
+        };
+        Some(
+            self.documents
+                .entry(doc_id.to_string())
+                .or_insert(doc_service)
+                .value()
+                .clone(),
+        )
+    }
+
+    /// Retrieves an existing document by ID or creates a new one if it doesn't exist.
+    ///
+    /// Documents whose persisted state cannot be read are served from memory only
+    /// rather than overwritten, so their objects are left intact for inspection.
+    fn get_or_create(&self, doc_id: &str) -> Arc<RwLock<SingleDocumentServiceImpl>> {
+        if let Some(entry) = self.documents.get(doc_id) {
+            return entry.value().clone();
+        }
+
+        // Restored without holding the shard lock; of concurrent loads, the first one inserted
+        // wins, and buffering the empty state of a new document twice is harmless
+        let doc_service = match self.load(doc_id) {
+            Ok(Some(doc_service)) => doc_service,
+            Ok(None) => self.new_document(doc_id).unwrap_or_else(|e| {
+                warn!("Failed to persist new document '{}': {}", doc_id, e);
+                self.attach(
+                    doc_id,
+                    SingleDocumentServiceImpl::with_compute_pool(self.compute.clone()),
+                )
+            }),
+            Err(e) => {
+                error!("{}; serving '{}' without persistence", e, doc_id);
+                Arc::new(RwLock::new(SingleDocumentServiceImpl::with_compute_pool(
+                    self.compute.clone(),
+                )))
+            }
+        };
+        self.documents
+            .entry(doc_id.to_string())
+            .or_insert(doc_service)
+            .value()
+            .clone()
+    }
+
+    /// Updates an existing document.
+    ///
+    /// This is the concrete implementation of document update logic.
+    fn update_document(
+        &self,
+        doc_id: &str,
+        document: Arc<RwLock<SingleDocumentServiceImpl>>,
+    ) -> DomainResult<()> {
+        if !self.exists(doc_id) {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        self.documents.insert(doc_id.to_string(), document);
+        Ok(())
+    }
+
+    /// Deletes a document by ID, both from memory and from the bucket.
+    ///
+    /// This is the concrete implementation of document deletion logic.
+    fn delete_document(&self, doc_id: &str) -> DomainResult<()> {
+        if !self.exists(doc_id) {
+            return Err(DomainError::NotFound(doc_id.to_string()));
+        }
+
+        self.documents.remove(doc_id);
+        self.store.remove(doc_id)
+    }
+
+    /// Lists all document IDs, including persisted documents that are not loaded.
+    ///
+    /// This is the concrete implementation of document listing logic.
+    fn list_documents(&self) -> Vec<String> {
+        let mut doc_ids = self.store.list();
+        for entry in self.documents.iter() {
+            if !doc_ids.contains(entry.key()) {
+                doc_ids.push(entry.key().clone());
+            }
+        }
+        doc_ids
+    }
+
+    /// Checks if a document exists in memory or in the bucket.
+    ///
+    /// This is the concrete implementation that checks document existence.
+    fn exists(&self, doc_id: &str) -> bool {
+        self.documents.contains_key(doc_id) || self.store.contains(doc_id).unwrap_or(false)
+    }
+
+    /// Gets the number of documents currently loaded in memory.
+    ///
+    /// Persisted documents that have not been accessed since startup are not
+    /// counted, so the value reflects memory pressure.
+    fn count(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Clears all documents from memory and from the bucket.
+    ///
+    /// This is the concrete implementation of repository clearing logic.
+    fn clear(&self) -> DomainResult<()> {
+        self.documents.clear();
+        self.store.clear()
+    }
+
+    /// Writes the updates buffered since the last flush to the bucket.
+    ///
+    /// This is the concrete implementation of buffered update flushing.
+    fn flush(&self) -> DomainResult<usize> {
+        self.store.flush()
+    }
+
+    /// Reports that documents are kept in the bucket across restarts.
+    ///
+    /// This is the concrete implementation of the durability check.
+    fn is_durable(&self) -> bool {
+        true
+    }
+
+    /// Lists the documents loaded in memory, leaving persisted documents unloaded.
+    ///
+    /// This is the concrete implementation of resident document listing.
+    fn resident_documents(&self) -> Vec<String> {
+        self.documents
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    /// Reads the document's snapshot and batches from the bucket.
+    ///
+    /// This is the concrete implementation of update log inspection.
+    fn logged_updates(&self, doc_id: &str) -> DomainResult<Vec<LoggedUpdate>> {
+        self.store.entries(doc_id)
+    }
+
+    /// Checks that the bucket answers a listing request in time.
+    ///
+    /// This is the concrete implementation of the storage health check.
+    fn check_health(&self) -> DomainResult<()> {
+        self.store.check_health()
+    }
+}
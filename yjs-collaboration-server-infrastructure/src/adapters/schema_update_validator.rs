@@ -0,0 +1,87 @@
+use std::collections::BTreeSet;
+
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::update_validator::UpdateValidator,
+    value_objects::{update_changes::UpdateChanges, update_origin::TransactionOrigin},
+};
+
+/// Constraints the updates of every document must satisfy.
+///
+/// An empty allowlist and limits of `0` accept any update, so the default
+/// rules validate nothing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchemaRules {
+    /// Root types updates may change (empty = any)
+    pub allowed_root_types: BTreeSet<String>,
+    /// Maximum clock units a single update may insert (0 = unlimited)
+    pub max_inserted_items: u64,
+    /// Maximum clock units a single update may delete (0 = unlimited)
+    pub max_deleted_items: u64,
+}
+
+/// An update validator enforcing static schema rules from the configuration.
+///
+/// Updates changing a root type outside the allowlist, creating one included,
+/// or inserting or deleting more items at once than allowed, are rejected as
+/// invalid.
+#[derive(Clone, Debug, Default)]
+pub struct SchemaUpdateValidator {
+    rules: SchemaRules,
+}
+
+impl SchemaUpdateValidator {
+    /// Creates a validator applying the given rules to every document.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - The constraints updates must satisfy
+    ///
+    /// # Returns
+    ///
+    /// A new `SchemaUpdateValidator` instance
+    pub fn new(rules: SchemaRules) -> Self {
+        Self { rules }
+    }
+}
+
+impl UpdateValidator for SchemaUpdateValidator {
+    fn validate(
+        &self,
+        doc_id: &str,
+        changes: &UpdateChanges,
+        _origin: &TransactionOrigin,
+    ) -> DomainResult<()> {
+        if !self.rules.allowed_root_types.is_empty() {
+            if let Some(root) = changes
+                .root_types
+                .iter()
+                .find(|root| !self.rules.allowed_root_types.contains(*root))
+            {
+                return Err(DomainError::InvalidUpdate(format!(
+                    "Root type '{}' of document '{}' may not be changed",
+                    root, doc_id
+                )));
+            }
+        }
+
+        if self.rules.max_inserted_items > 0
+            && changes.inserted_items > self.rules.max_inserted_items
+        {
+            return Err(DomainError::InvalidUpdate(format!(
+                "Update inserts {} items, over the maximum of {}",
+                changes.inserted_items, self.rules.max_inserted_items
+            )));
+        }
+
+        if self.rules.max_deleted_items > 0 && changes.deleted_items > self.rules.max_deleted_items
+        {
+            return Err(DomainError::InvalidUpdate(format!(
+                "Update deletes {} items, over the maximum of {}",
+                changes.deleted_items, self.rules.max_deleted_items
+            )));
+        }
+
+        Ok(())
+    }
+}
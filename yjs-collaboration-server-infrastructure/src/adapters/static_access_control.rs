@@ -0,0 +1,97 @@
+use std::collections::{HashMap, HashSet};
+
+use yjs_collaboration_server_domain::{
+    errors::DomainResult,
+    repositories::access_control::AccessControl,
+    value_objects::{access_role::AccessRole, feature_policy::FeaturePolicies},
+};
+
+/// Roles granted on the documents of a namespace.
+///
+/// Users listed explicitly get the listed role; other identified users get the
+/// user role, and clients without an identity the guest role.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessRules {
+    /// Role of clients without a user identity
+    pub guest_role: AccessRole,
+    /// Role of identified users not listed below
+    pub user_role: AccessRole,
+    /// Users that may only read documents
+    pub read_only_users: HashSet<String>,
+    /// Users that may edit documents
+    pub read_write_users: HashSet<String>,
+}
+
+impl AccessRules {
+    /// Resolves the role of a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Identity of the client, or `None` for a guest
+    ///
+    /// # Returns
+    ///
+    /// The role granted to the client
+    pub fn role_of(&self, user_id: Option<&str>) -> AccessRole {
+        match user_id.filter(|user_id| !user_id.is_empty()) {
+            None => self.guest_role,
+            Some(user_id) if self.read_only_users.contains(user_id) => AccessRole::ReadOnly,
+            Some(user_id) if self.read_write_users.contains(user_id) => AccessRole::ReadWrite,
+            Some(_) => self.user_role,
+        }
+    }
+}
+
+/// An access control backed by static rules from the configuration.
+///
+/// Like feature policies, rules are set per namespace (the part of a document
+/// ID before the first `/`); documents outside a configured namespace use the
+/// default rules.
+#[derive(Clone, Debug, Default)]
+pub struct StaticAccessControl {
+    default: AccessRules,
+    namespaces: HashMap<String, AccessRules>,
+}
+
+impl StaticAccessControl {
+    /// Creates an access control applying the given rules to every document.
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - The rules of documents outside a configured namespace
+    ///
+    /// # Returns
+    ///
+    /// A new `StaticAccessControl` instance without namespace overrides
+    pub fn new(default: AccessRules) -> Self {
+        Self {
+            default,
+            namespaces: HashMap::new(),
+        }
+    }
+
+    /// Sets the rules of a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The namespace, without the trailing separator
+    /// * `rules` - The rules applied to the namespace's documents
+    ///
+    /// # Returns
+    ///
+    /// The `StaticAccessControl` with the namespace rules added
+    pub fn with_namespace(mut self, namespace: &str, rules: AccessRules) -> Self {
+        self.namespaces.insert(namespace.to_string(), rules);
+        self
+    }
+}
+
+impl AccessControl for StaticAccessControl {
+    fn role(&self, doc_id: &str, user_id: Option<&str>) -> DomainResult<AccessRole> {
+        let rules = FeaturePolicies::namespace_of(doc_id)
+            .and_then(|namespace| self.namespaces.get(namespace))
+            .unwrap_or(&self.default);
+
+        Ok(rules.role_of(user_id))
+    }
+}
@@ -0,0 +1,43 @@
+use tokio::sync::watch;
+use yjs_collaboration_server_domain::{
+    errors::DomainResult, repositories::cluster_membership::ClusterMembership,
+    value_objects::cluster_member::ClusterMember,
+};
+
+/// A membership list fixed by the configuration.
+///
+/// Every instance must be configured with the same members; the joining
+/// instance is added to the list if it is not part of it. Members are never
+/// considered down, so the documents of a stopped instance stay unavailable
+/// until it is restarted or the list is changed on every instance.
+#[derive(Clone, Debug, Default)]
+pub struct StaticClusterMembership {
+    members: Vec<ClusterMember>,
+}
+
+impl StaticClusterMembership {
+    /// Creates a membership list.
+    ///
+    /// # Arguments
+    ///
+    /// * `members` - The members of the cluster
+    ///
+    /// # Returns
+    ///
+    /// A new `StaticClusterMembership` instance
+    pub fn new(members: Vec<ClusterMember>) -> Self {
+        Self { members }
+    }
+}
+
+impl ClusterMembership for StaticClusterMembership {
+    fn join(&self, member: ClusterMember) -> DomainResult<watch::Receiver<Vec<ClusterMember>>> {
+        let mut members = self.members.clone();
+        if !members.iter().any(|known| known.node_id == member.node_id) {
+            members.push(member);
+        }
+        members.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+
+        Ok(watch::channel(members).1)
+    }
+}
@@ -0,0 +1,162 @@
+use std::{path::Path, sync::Mutex};
+
+use tantivy::{
+    collector::TopDocs,
+    directory::MmapDirectory,
+    doc,
+    query::QueryParser,
+    schema::{Field, Schema, Value, STORED, STRING, TEXT},
+    snippet::SnippetGenerator,
+    Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term,
+};
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::search_index::SearchIndex,
+    value_objects::search_hit::SearchHit,
+};
+
+/// Memory the index writer may buffer changes in before flushing them to a segment.
+const WRITER_MEMORY_BYTES: usize = 50_000_000;
+
+/// Maximum length in characters of the snippets returned with the hits.
+const SNIPPET_MAX_CHARS: usize = 200;
+
+/// A search index backed by a Tantivy index, in memory or in a directory.
+///
+/// Each document is indexed as a single Tantivy document holding its ID and
+/// its tokenized content; the content is also stored, to build the snippets of
+/// the hits. Queries use Tantivy's query syntax over the content: terms are
+/// combined with `OR` unless prefixed with `+`, and phrases are quoted.
+pub struct TantivySearchIndex {
+    index: Index,
+    reader: IndexReader,
+    /// The single writer of the index; changes are buffered in it until committed
+    writer: Mutex<IndexWriter>,
+    doc_id: Field,
+    content: Field,
+}
+
+impl TantivySearchIndex {
+    /// Creates an empty index kept in memory, lost when the server restarts.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TantivySearchIndex)` - The index
+    /// * `Err(DomainError)` - `StorageFailure` if the index could not be created
+    pub fn in_memory() -> DomainResult<Self> {
+        let (schema, doc_id, content) = Self::schema();
+        Self::open(Index::create_in_ram(schema), doc_id, content)
+    }
+
+    /// Opens the index of a directory, creating the directory and the index if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory holding the index files
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TantivySearchIndex)` - The index
+    /// * `Err(DomainError)` - `StorageFailure` if the directory or the index could not be opened
+    pub fn open_in_dir(directory: impl AsRef<Path>) -> DomainResult<Self> {
+        let directory = directory.as_ref();
+        std::fs::create_dir_all(directory).map_err(|e| {
+            DomainError::storage(format!(
+                "Failed to create the search index directory '{}': {}",
+                directory.display(),
+                e
+            ))
+        })?;
+        let (schema, doc_id, content) = Self::schema();
+        let directory = MmapDirectory::open(directory).map_err(DomainError::storage)?;
+        let index = Index::open_or_create(directory, schema).map_err(DomainError::storage)?;
+        Self::open(index, doc_id, content)
+    }
+
+    /// Builds the schema of the index.
+    fn schema() -> (Schema, Field, Field) {
+        let mut builder = Schema::builder();
+        let doc_id = builder.add_text_field("doc_id", STRING | STORED);
+        let content = builder.add_text_field("content", TEXT | STORED);
+        (builder.build(), doc_id, content)
+    }
+
+    /// Opens the reader and the writer of an index.
+    fn open(index: Index, doc_id: Field, content: Field) -> DomainResult<Self> {
+        let writer = index
+            .writer_with_num_threads(1, WRITER_MEMORY_BYTES)
+            .map_err(DomainError::storage)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .map_err(DomainError::storage)?;
+
+        Ok(Self {
+            index,
+            reader,
+            writer: Mutex::new(writer),
+            doc_id,
+            content,
+        })
+    }
+
+    /// Locks the writer of the index.
+    fn writer(&self) -> std::sync::MutexGuard<'_, IndexWriter> {
+        self.writer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SearchIndex for TantivySearchIndex {
+    fn index(&self, doc_id: &str, content: &str) -> DomainResult<()> {
+        let writer = self.writer();
+        writer.delete_term(Term::from_field_text(self.doc_id, doc_id));
+        writer
+            .add_document(doc!(self.doc_id => doc_id, self.content => content))
+            .map(|_| ())
+            .map_err(DomainError::storage)
+    }
+
+    fn remove(&self, doc_id: &str) -> DomainResult<()> {
+        self.writer()
+            .delete_term(Term::from_field_text(self.doc_id, doc_id));
+        Ok(())
+    }
+
+    fn commit(&self) -> DomainResult<()> {
+        self.writer().commit().map_err(DomainError::storage)?;
+        self.reader.reload().map_err(DomainError::storage)
+    }
+
+    fn search(&self, query: &str, limit: usize) -> DomainResult<Vec<SearchHit>> {
+        let query = QueryParser::for_index(&self.index, vec![self.content])
+            .parse_query(query)
+            .map_err(|e| DomainError::InvalidArgument(format!("Invalid search query: {}", e)))?;
+
+        let searcher = self.reader.searcher();
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(limit))
+            .map_err(DomainError::storage)?;
+        let mut snippets = SnippetGenerator::create(&searcher, &*query, self.content)
+            .map_err(DomainError::storage)?;
+        snippets.set_max_num_chars(SNIPPET_MAX_CHARS);
+
+        top_docs
+            .into_iter()
+            .map(|(score, address)| {
+                let document: TantivyDocument =
+                    searcher.doc(address).map_err(DomainError::storage)?;
+                let doc_id = document
+                    .get_first(self.doc_id)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                Ok(SearchHit {
+                    doc_id,
+                    score,
+                    snippet: snippets.snippet_from_doc(&document).to_html(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+use yjs_collaboration_server_domain::{
+    errors::{DomainError, DomainResult},
+    repositories::dictionary_compressor::DictionaryCompressor,
+};
+
+/// A dictionary compressor producing Zstandard frames.
+///
+/// Dictionaries are trained with Zstandard's COVER algorithm, and every
+/// payload is compressed as a standalone frame, so clients decompress it with
+/// any Zstandard implementation given the same dictionary, such as a
+/// WebAssembly build of zstd in browsers.
+#[derive(Clone, Copy, Debug)]
+pub struct ZstdDictionaryCompressor {
+    /// Compression level (1-22)
+    level: i32,
+}
+
+impl ZstdDictionaryCompressor {
+    /// Creates a compressor.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - Zstandard compression level (1-22)
+    ///
+    /// # Returns
+    ///
+    /// A new `ZstdDictionaryCompressor`
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+}
+
+impl DictionaryCompressor for ZstdDictionaryCompressor {
+    fn train(&self, samples: &[Vec<u8>], max_size: usize) -> DomainResult<Vec<u8>> {
+        zstd::dict::from_samples(samples, max_size)
+            .map_err(|e| DomainError::Internal(format!("Failed to train a zstd dictionary: {}", e)))
+    }
+
+    fn compress(&self, dictionary: &[u8], payload: &[u8]) -> DomainResult<Vec<u8>> {
+        zstd::bulk::Compressor::with_dictionary(self.level, dictionary)
+            .and_then(|mut compressor| compressor.compress(payload))
+            .map_err(|e| DomainError::Internal(format!("Failed to compress with zstd: {}", e)))
+    }
+}
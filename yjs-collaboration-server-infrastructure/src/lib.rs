@@ -7,3 +7,9 @@ pub mod adapters;
 
 // Re-export commonly used infrastructure implementations
 pub use adapters::in_memory_document_repository::InMemoryDocumentRepository;
+pub use adapters::in_memory_metadata_repository::InMemoryMetadataRepository;
+pub use adapters::in_memory_version_repository::InMemoryVersionRepository;
+pub use adapters::persistent_document_repository::PersistentDocumentRepository;
+pub use adapters::postgres_document_repository::PostgresDocumentRepository;
+pub use adapters::redis_update_broker::RedisUpdateBroker;
+pub use adapters::static_access_control::StaticAccessControl;
@@ -0,0 +1,77 @@
+// Listing of the documents persisted in the sled database
+//
+// Update keys end with a big-endian sequence number, whose bytes include the
+// separator following the document ID as soon as sequence numbers are small.
+// Documents must still be listed once each, under their own ID.
+
+use std::sync::Arc;
+
+use yjs_collaboration_server_domain::{
+    repositories::document_repository::DocumentRepository, services::compute_pool::ComputePool,
+};
+use yjs_collaboration_server_infrastructure::{
+    adapters::compression::CompressionCodec, PersistentDocumentRepository,
+};
+use yrs::{Doc, ReadTxn, Text, Transact};
+
+/// Number of updates applied to each document
+const UPDATES: usize = 5;
+
+/// Opens a repository that never compacts, so every update stays in the log.
+fn open(path: &std::path::Path) -> PersistentDocumentRepository {
+    PersistentDocumentRepository::open(
+        path,
+        0,
+        CompressionCodec::None,
+        Arc::new(ComputePool::default()),
+    )
+    .expect("failed to open the repository")
+}
+
+/// The updates typing a word one character at a time.
+fn keystrokes(word: &str) -> Vec<Vec<u8>> {
+    let doc = Doc::new();
+    let text = doc.get_or_insert_text("content");
+    word.chars()
+        .take(UPDATES)
+        .enumerate()
+        .map(|(index, character)| {
+            let before = doc.transact().state_vector();
+            text.insert(
+                &mut doc.transact_mut(),
+                index as u32,
+                &character.to_string(),
+            );
+            doc.transact().encode_state_as_update_v1(&before)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn lists_each_document_once_after_several_updates() {
+    let path = std::env::temp_dir().join(format!("yjs-list-{}", uuid::Uuid::new_v4()));
+
+    let repository = open(&path);
+    for doc_id in ["notes", "drafts"] {
+        let document = repository
+            .create_document(doc_id)
+            .expect("failed to create the document");
+        for update in keystrokes("hello") {
+            document
+                .read()
+                .await
+                .apply_update(&update)
+                .await
+                .expect("failed to apply the update");
+        }
+    }
+    assert!(
+        repository.logged_updates("notes").unwrap().len() > UPDATES,
+        "the updates were compacted"
+    );
+
+    assert_eq!(repository.list_documents(), vec!["drafts", "notes"]);
+
+    drop(repository);
+    let _ = std::fs::remove_dir_all(&path);
+}